@@ -0,0 +1,95 @@
+//! The CDR encoding of the status and the sensor data, and the decoding of the commands
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use zenoh_carla_bridge::testing::{
+    cdr_utils::{self, Endianness},
+    messages::AutowareVersion,
+};
+use zenoh_ros_type::{
+    autoware_auto_vehicle_msgs::VelocityReport,
+    builtin_interfaces::Time,
+    sensor_msgs::{PointCloud2, PointField},
+    std_msgs::Header,
+};
+
+/// A 64-channel lidar at 1500 points per channel is about this many points per frame
+const POINTS: usize = 100_000;
+/// x, y, z and intensity, all FLOAT32
+const POINT_STEP: u32 = 16;
+const FLOAT32: u8 = 7;
+
+const CONTROL_CMD: &[u8] =
+    include_bytes!("../tests/fixtures/ego_control_command_control_cmd_000000.cdr");
+
+fn header(frame_id: &str) -> Header {
+    Header {
+        stamp: Time {
+            sec: 1,
+            nanosec: 500_000_000,
+        },
+        frame_id: frame_id.to_string(),
+    }
+}
+
+fn point_cloud() -> PointCloud2 {
+    let fields = ["x", "y", "z", "intensity"]
+        .iter()
+        .enumerate()
+        .map(|(index, name)| PointField {
+            name: name.to_string(),
+            offset: index as u32 * 4,
+            datatype: FLOAT32,
+            count: 1,
+        })
+        .collect();
+    let data: Vec<u8> = (0..POINTS)
+        .flat_map(|index| {
+            let angle = index as f32 * 0.001;
+            [10.0 * angle.cos(), 10.0 * angle.sin(), 0.5, 1.0]
+        })
+        .flat_map(f32::to_le_bytes)
+        .collect();
+    PointCloud2 {
+        header: header("lidar"),
+        height: 1,
+        width: POINTS as u32,
+        fields,
+        is_bigendian: false,
+        point_step: POINT_STEP,
+        row_step: data.len() as u32,
+        data,
+        is_dense: true,
+    }
+}
+
+fn serialize(c: &mut Criterion) {
+    let report = VelocityReport {
+        header: header("base_link"),
+        longitudinal_velocity: 10.0,
+        lateral_velocity: 0.1,
+        heading_rate: 0.05,
+    };
+    c.bench_function("serialize velocity_status", |b| {
+        b.iter(|| cdr_utils::serialize(black_box(&report), Endianness::Little).unwrap())
+    });
+
+    let cloud = point_cloud();
+    let mut group = c.benchmark_group("serialize pointcloud");
+    group.throughput(Throughput::Bytes(cloud.data.len() as u64));
+    group.bench_function("100k points", |b| {
+        b.iter(|| cdr_utils::serialize(black_box(&cloud), Endianness::Little).unwrap())
+    });
+    group.finish();
+}
+
+fn deserialize(c: &mut Criterion) {
+    c.bench_function("deserialize control_cmd", |b| {
+        b.iter(|| {
+            AutowareVersion::Universe2023
+                .decode_control(black_box(CONTROL_CMD))
+                .unwrap()
+        })
+    });
+}
+
+criterion_group!(benches, serialize, deserialize);
+criterion_main!(benches);
@@ -0,0 +1,118 @@
+//! A tick of a vehicle bridge over the mock vehicle, and the keys of its topics
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use nalgebra::Vector3;
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use zenoh::prelude::sync::*;
+use zenoh_carla_bridge::{
+    testing::{
+        cdr_utils::{self, Endianness},
+        topics, ActorBridge, MockVehicle, NamespaceRegistry, TickSnapshot, TimeStamp,
+        VehicleBridge,
+    },
+    BridgeConfig,
+};
+use zenoh_ros_type::autoware_auto_control_msgs::{
+    AckermannControlCommand, AckermannLateralCommand, LongitudinalCommand,
+};
+
+const DT: f64 = 0.05;
+
+fn session() -> Arc<Session> {
+    let mut config = Config::default();
+    config
+        .insert_json5("scouting/multicast/enabled", "false")
+        .unwrap();
+    Arc::new(zenoh::open(config).res().unwrap())
+}
+
+fn control_command() -> AckermannControlCommand {
+    let stamp = TimeStamp::ZERO;
+    AckermannControlCommand {
+        stamp: stamp.to_msg(),
+        lateral: AckermannLateralCommand {
+            stamp: stamp.to_msg(),
+            steering_tire_angle: 0.1,
+            steering_tire_rotation_rate: 0.0,
+        },
+        longitudinal: LongitudinalCommand {
+            stamp: stamp.to_msg(),
+            speed: 10.0,
+            acceleration: 1.0,
+            jerk: 0.0,
+        },
+    }
+}
+
+/// Step, publish and control, like the main loop does every tick
+fn tick(bridge: &mut VehicleBridge, frame: u64) {
+    let stamp = TimeStamp::from_secs_f64(frame as f64 * DT);
+    let tick = TickSnapshot::without_world(stamp, frame);
+    bridge.step(DT, &tick).unwrap();
+    bridge.publish_status(stamp).unwrap();
+    bridge.apply_control(stamp, DT);
+}
+
+fn step(c: &mut Criterion) {
+    let session = session();
+    let config = BridgeConfig::default();
+    let vehicle = MockVehicle::new(1, "autoware_ego");
+    vehicle.set_velocity(Vector3::new(5.0, 0.0, 0.0));
+    let namespaces = Arc::new(Mutex::new(NamespaceRegistry::default()));
+    let mut bridge = VehicleBridge::new(
+        session.clone(),
+        Box::new(vehicle.clone()),
+        &config,
+        namespaces,
+    )
+    .unwrap();
+
+    // Follow a command rather than the fallback
+    let payload = cdr_utils::serialize(&control_command(), Endianness::Little).unwrap();
+    session
+        .put(config.topics.key("ego", topics::CONTROL_CMD), payload)
+        .res()
+        .unwrap();
+    let start = Instant::now();
+    let mut frame = 0;
+    while vehicle.applied_ackermann_controls().is_empty() {
+        assert!(start.elapsed() < Duration::from_secs(5), "no control_cmd");
+        frame += 1;
+        tick(&mut bridge, frame);
+        thread::sleep(Duration::from_millis(5));
+    }
+
+    c.bench_function("vehicle tick", |b| {
+        b.iter(|| {
+            frame += 1;
+            tick(&mut bridge, frame);
+            vehicle.clear_applied();
+        })
+    });
+}
+
+fn keys(c: &mut Criterion) {
+    let config = BridgeConfig::default();
+    c.bench_function("topic key", |b| {
+        b.iter(|| {
+            config
+                .topics
+                .key(black_box("ego"), black_box(topics::VELOCITY_STATUS))
+        })
+    });
+    c.bench_function("sensor key", |b| {
+        b.iter(|| {
+            config.topics.sensor_key(
+                black_box("ego"),
+                black_box(topics::RADAR_POINTCLOUD),
+                black_box("front"),
+            )
+        })
+    });
+}
+
+criterion_group!(benches, step, keys);
+criterion_main!(benches);
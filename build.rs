@@ -0,0 +1,43 @@
+//! Embed the build information replied on `*/bridge/info`
+use std::{fs, process::Command};
+
+fn main() {
+    let git_hash = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|hash| hash.trim().to_string())
+        .unwrap_or_else(|| String::from("unknown"));
+    println!("cargo:rustc-env=BRIDGE_GIT_HASH={git_hash}");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+
+    // Cargo.lock is resolved before the build scripts run
+    let lock = fs::read_to_string("Cargo.lock").unwrap_or_default();
+    for (name, env) in [
+        ("zenoh", "BRIDGE_ZENOH_VERSION"),
+        ("carla", "BRIDGE_CARLA_VERSION"),
+    ] {
+        println!("cargo:rustc-env={env}={}", locked_version(&lock, name));
+    }
+    println!("cargo:rerun-if-changed=Cargo.lock");
+}
+
+/// The version of a package in Cargo.lock
+fn locked_version(lock: &str, name: &str) -> String {
+    let package = format!("name = \"{name}\"");
+    let mut lines = lock.lines();
+    while let Some(line) = lines.next() {
+        if line.trim() == package {
+            if let Some(version) = lines
+                .next()
+                .and_then(|line| line.trim().strip_prefix("version = "))
+            {
+                return version.trim_matches('"').to_string();
+            }
+        }
+    }
+    String::from("unknown")
+}
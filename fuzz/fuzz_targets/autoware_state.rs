@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use zenoh_carla_bridge::testing::fuzz;
+
+fuzz_target!(|payload: &[u8]| {
+    fuzz::run("autoware_state", payload);
+});
@@ -0,0 +1,260 @@
+use crate::{
+    bridge::sensor_bridge::SensorType,
+    config::{BridgeConfig, NamespaceSource},
+    error::Result,
+    namespace::NamespaceRegistry,
+    topics::{self, TopicMap},
+};
+use arc_swap::ArcSwap;
+use carla::{
+    client::{Actor, World},
+    prelude::*,
+};
+use log::warn;
+use serde_derive::Serialize;
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use zenoh::{prelude::sync::*, queryable::Queryable};
+
+/// How often the snapshot of the actors is refreshed (in wall-clock seconds)
+const REFRESH_INTERVAL_SEC: f64 = 1.0;
+
+/// An actor in the reply of `*/bridge/actors`
+#[derive(Serialize)]
+pub struct ActorInfo {
+    pub id: u32,
+    pub type_id: String,
+    pub role_name: Option<String>,
+    /// In the CARLA coordinates (m)
+    pub location: [f32; 3],
+    /// Roll, pitch and yaw in the CARLA coordinates (degrees)
+    pub rotation: [f32; 3],
+    pub parent_id: Option<u32>,
+    pub attributes: BTreeMap<String, String>,
+}
+
+impl ActorInfo {
+    fn new(actor: &Actor) -> ActorInfo {
+        let attributes: BTreeMap<_, _> = actor
+            .attributes()
+            .iter()
+            .map(|attr| (attr.id().to_string(), attr.value_string()))
+            .collect();
+        let transform = actor.transform();
+        let (roll, pitch, yaw) = transform.rotation.euler_angles();
+        ActorInfo {
+            id: actor.id(),
+            type_id: actor.type_id(),
+            role_name: attributes.get("role_name").cloned(),
+            location: transform.translation.vector.into(),
+            rotation: [roll.to_degrees(), pitch.to_degrees(), yaw.to_degrees()],
+            parent_id: actor.parent().map(|parent| parent.id()),
+            attributes,
+        }
+    }
+}
+
+/// Answer the queries on `*/bridge/actors` with a JSON array of the actors in the world.
+///
+/// The replies come from a snapshot refreshed by the main loop once per interval, so a
+/// burst of queries never turns into a burst of CARLA RPCs or blocks the loop.
+/// The selector parameters `type` and `role_name` filter the actors, with `*` as wildcard,
+/// e.g. `*/bridge/actors?type=vehicle.*`.
+pub struct ActorQueryable<'a> {
+    snapshot: Arc<ArcSwap<Vec<ActorInfo>>>,
+    last_refresh: Option<Instant>,
+    _queryable: Queryable<'a, ()>,
+}
+
+impl<'a> ActorQueryable<'a> {
+    pub fn new(z_session: Arc<Session>, topic_map: &TopicMap) -> Result<ActorQueryable<'a>> {
+        let snapshot = Arc::new(ArcSwap::from_pointee(Vec::new()));
+        let cloned_snapshot = snapshot.clone();
+        let queryable = z_session
+            .declare_queryable(topic_map.key("*", topics::ACTORS))
+            .callback(move |query| {
+                let filters = parse_filters(query.parameters());
+                let snapshot = cloned_snapshot.load();
+                let actors: Vec<_> = snapshot
+                    .iter()
+                    .filter(|actor| filters.iter().all(|filter| filter.matches(actor)))
+                    .collect();
+                let payload = match serde_json::to_vec(&actors) {
+                    Ok(payload) => payload,
+                    Err(e) => {
+                        warn!("Failed to encode the actors: {e}");
+                        return;
+                    }
+                };
+                let sample = Sample::new(query.key_expr().clone(), payload);
+                if let Err(e) = query.reply(Ok(sample)).res() {
+                    warn!("Failed to reply to the actor query: {e}");
+                }
+            })
+            .res()?;
+        Ok(ActorQueryable {
+            snapshot,
+            last_refresh: None,
+            _queryable: queryable,
+        })
+    }
+
+    /// The latest snapshot, shared with the other queryables
+    pub fn snapshot(&self) -> Arc<ArcSwap<Vec<ActorInfo>>> {
+        self.snapshot.clone()
+    }
+
+    /// Take a new snapshot of the actors, if the last one is older than the interval
+    pub fn refresh<'b>(&mut self, actors: impl Iterator<Item = &'b Actor>) {
+        let now = Instant::now();
+        let interval = Duration::from_secs_f64(REFRESH_INTERVAL_SEC);
+        if matches!(self.last_refresh, Some(last) if now - last < interval) {
+            return;
+        }
+        self.last_refresh = Some(now);
+        let mut snapshot: Vec<_> = actors.map(ActorInfo::new).collect();
+        snapshot.sort_by_key(|actor| actor.id);
+        self.snapshot.store(Arc::new(snapshot));
+    }
+}
+
+/// An actor of `zenoh_carla_bridge actors`
+#[derive(Serialize)]
+struct ListedActor {
+    #[serde(flatten)]
+    actor: ActorInfo,
+    /// The namespace of the vehicle bridge, or of the vehicle of a sensor, None if not bridged
+    bridged_namespace: Option<String>,
+}
+
+/// `zenoh_carla_bridge actors`: print the actors of the world whose type_id starts with
+/// the prefix, and which of them the bridge would bridge under which namespace
+pub fn print_actors(
+    world: &World,
+    config: &BridgeConfig,
+    namespace_source: NamespaceSource,
+    type_prefix: Option<&str>,
+    json: bool,
+) -> Result<()> {
+    let mut actors: Vec<_> = world
+        .actors()
+        .iter()
+        .map(|actor| ActorInfo::new(&actor))
+        .collect();
+    actors.sort_by_key(|actor| actor.id);
+    // The same namespaces as the bridges, the vehicles first like the sensors need them
+    let mut registry = NamespaceRegistry::new(namespace_source);
+    let role_names: BTreeMap<_, _> = actors
+        .iter()
+        .map(|actor| (actor.id, actor.role_name.clone().unwrap_or_default()))
+        .collect();
+    let mut namespaces = BTreeMap::new();
+    for actor in actors
+        .iter()
+        .filter(|actor| actor.type_id.starts_with("vehicle."))
+    {
+        if let Some(name) = config.bridged_name(&role_names[&actor.id]) {
+            namespaces.insert(actor.id, registry.namespace(actor.id, name));
+        }
+    }
+    let listed: Vec<_> = actors
+        .into_iter()
+        .filter(|actor| type_prefix.map_or(true, |prefix| actor.type_id.starts_with(prefix)))
+        .map(|actor| {
+            let bridged_namespace = if actor.type_id.starts_with("sensor.") {
+                let supported = actor.type_id.parse() != Ok(SensorType::NotSupport);
+                actor
+                    .parent_id
+                    .filter(|_| supported)
+                    .and_then(|parent_id| namespaces.get(&parent_id).cloned())
+            } else {
+                namespaces.get(&actor.id).cloned()
+            };
+            ListedActor {
+                actor,
+                bridged_namespace,
+            }
+        })
+        .collect();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&listed)?);
+        return Ok(());
+    }
+    println!(
+        "{:>6}  {:<36}  {:<20}  {:>6}  {:<26}  bridged as",
+        "id", "type_id", "role_name", "parent", "location"
+    );
+    for ListedActor {
+        actor,
+        bridged_namespace,
+    } in &listed
+    {
+        let [x, y, z] = actor.location;
+        println!(
+            "{:>6}  {:<36}  {:<20}  {:>6}  {:<26}  {}",
+            actor.id,
+            actor.type_id,
+            actor.role_name.as_deref().unwrap_or("-"),
+            actor
+                .parent_id
+                .map_or_else(|| String::from("-"), |id| id.to_string()),
+            format!("({x:.1}, {y:.1}, {z:.1})"),
+            bridged_namespace.as_deref().unwrap_or("-")
+        );
+    }
+    println!("{} actors", listed.len());
+    Ok(())
+}
+
+enum Filter {
+    TypeId(String),
+    RoleName(String),
+}
+
+impl Filter {
+    fn matches(&self, actor: &ActorInfo) -> bool {
+        match self {
+            Filter::TypeId(pattern) => glob_match(pattern, &actor.type_id),
+            Filter::RoleName(pattern) => {
+                glob_match(pattern, actor.role_name.as_deref().unwrap_or(""))
+            }
+        }
+    }
+}
+
+/// The filters of `type=...&role_name=...`, ignoring the unknown parameters
+fn parse_filters(parameters: &str) -> Vec<Filter> {
+    parameters
+        .split('&')
+        .filter_map(|parameter| parameter.split_once('='))
+        .filter_map(|(name, value)| match name {
+            "type" => Some(Filter::TypeId(value.to_string())),
+            "role_name" => Some(Filter::RoleName(value.to_string())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Match a text against a pattern where `*` stands for any sequence of characters
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<_> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No wildcard at all
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
@@ -0,0 +1,535 @@
+//! Autoware messages which are not provided by zenoh-ros-type
+//!
+//! Generated by `tools/msg_codegen` from the interfaces in `msg/`, don't edit it but
+//! regenerate it with `cargo run -p msg_codegen`.
+#![allow(dead_code)]
+use serde_derive::{Deserialize, Serialize};
+use zenoh_ros_type::{builtin_interfaces::Time, std_msgs::Header};
+
+/// autoware_control_msgs/msg/Lateral: Lateral control message for Ackermann-style platforms
+///
+/// Note regarding tires: If the platform has multiple steering tires, the commands
+/// given here are for a virtual tire at the average lateral position of the steering tires.
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct Lateral {
+    pub stamp: Time,
+    /// Time this configuration state is expected to be achieved in (optional)
+    pub control_time: Time,
+    /// Desired angle of the steering tire in radians left (positive)
+    /// or right (negative) of center (0.0)
+    pub steering_tire_angle: f32,
+    /// Desired rate of change of the steering tire angle in radians per second
+    pub steering_tire_rotation_rate: f32,
+    /// Whether the steering_tire_rotation_rate field is used
+    pub is_defined_steering_tire_rotation_rate: bool,
+}
+
+/// autoware_control_msgs/msg/Longitudinal: Longitudinal control message for a vehicle
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct Longitudinal {
+    pub stamp: Time,
+    /// Time this configuration state is expected to be achieved in (optional)
+    pub control_time: Time,
+    /// Desired platform speed in meters per second
+    pub velocity: f32,
+    /// Desired platform acceleration in meters per second squared
+    pub acceleration: f32,
+    /// Desired platform jerk in meters per second cubed
+    pub jerk: f32,
+    /// Whether the acceleration field is used
+    pub is_defined_acceleration: bool,
+    /// Whether the jerk field is used
+    pub is_defined_jerk: bool,
+}
+
+/// autoware_control_msgs/msg/Control: The successor of AckermannControlCommand
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct Control {
+    pub stamp: Time,
+    /// Time this configuration state is expected to be achieved in (optional)
+    pub control_time: Time,
+    pub lateral: Lateral,
+    pub longitudinal: Longitudinal,
+}
+
+/// tier4_vehicle_msgs/msg/BatteryStatus
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct BatteryStatus {
+    pub stamp: Time,
+    /// Remaining charge in percent
+    pub energy_level: f32,
+}
+
+/// The constants of `DoorCommand`
+pub mod door_command {
+    /// Positions, the bridge only matches ALL by name
+    pub const FRONT_LEFT: u8 = 0;
+    pub const FRONT_RIGHT: u8 = 1;
+    pub const REAR_LEFT: u8 = 2;
+    pub const REAR_RIGHT: u8 = 3;
+    pub const ALL: u8 = 4;
+    /// Commands
+    pub const OPEN: u8 = 1;
+    pub const CLOSE: u8 = 2;
+}
+
+/// zenoh_carla_bridge/msg/DoorCommand: Open or close the doors of a vehicle
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct DoorCommand {
+    pub stamp: Time,
+    pub position: u8,
+    pub command: u8,
+}
+
+/// The constants of `DoorStatus`
+pub mod door_status {
+    pub const NOT_AVAILABLE: u8 = 0;
+    pub const CLOSED: u8 = 1;
+    pub const OPENED: u8 = 2;
+}
+
+/// zenoh_carla_bridge/msg/DoorStatus: State of the doors, indexed by position
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct DoorStatus {
+    pub stamp: Time,
+    pub doors: [u8; 4],
+}
+
+/// The constants of `AdapiDoorCommand`
+pub mod adapi_door_command {
+    pub const OPEN: u8 = 1;
+    pub const CLOSE: u8 = 2;
+}
+
+/// autoware_adapi_v1_msgs/msg/DoorCommand: One door, by its index in the door layout
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Default)]
+pub struct AdapiDoorCommand {
+    pub index: u32,
+    pub command: u8,
+}
+
+/// The request of autoware_adapi_v1_msgs/srv/SetDoorCommand
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct SetDoorCommand {
+    pub stamp: Time,
+    pub doors: Vec<AdapiDoorCommand>,
+}
+
+/// The response of autoware_adapi_v1_msgs/srv/SetDoorCommand
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct SetDoorCommandResponse {
+    pub status: ResponseStatus,
+}
+
+/// The constants of `ResponseStatus`
+pub mod response_status {
+    /// constants for code
+    pub const UNKNOWN: u16 = 50000;
+    pub const SERVICE_UNREADY: u16 = 50001;
+    pub const SERVICE_TIMEOUT: u16 = 50002;
+    pub const TRANSFORM_ERROR: u16 = 50003;
+    pub const PARAMETER_ERROR: u16 = 50004;
+    /// constants for warning
+    pub const DEPRECATED: u16 = 60000;
+    pub const NO_EFFECT: u16 = 60001;
+}
+
+/// autoware_adapi_v1_msgs/msg/ResponseStatus
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct ResponseStatus {
+    /// variables
+    pub success: bool,
+    pub code: u16,
+    pub message: String,
+}
+
+/// The constants of `AdapiDoorStatus`
+pub mod adapi_door_status {
+    pub const UNKNOWN: u8 = 0;
+    pub const NOT_AVAILABLE: u8 = 1;
+    pub const OPENED: u8 = 2;
+    pub const CLOSED: u8 = 3;
+    pub const OPENING: u8 = 4;
+    pub const CLOSING: u8 = 5;
+}
+
+/// autoware_adapi_v1_msgs/msg/DoorStatus
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Default)]
+pub struct AdapiDoorStatus {
+    pub status: u8,
+}
+
+/// autoware_adapi_v1_msgs/msg/DoorStatusArray: Every door, in the order of the door layout
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct DoorStatusArray {
+    pub stamp: Time,
+    pub doors: Vec<AdapiDoorStatus>,
+}
+
+/// zenoh_carla_bridge/msg/AxleSlip
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Default)]
+pub struct AxleSlip {
+    /// The mean of both wheels, NaN when the wheel speeds are unknown
+    pub longitudinal_slip: f32,
+    /// Positive to the left (rad)
+    pub slip_angle: f32,
+}
+
+/// zenoh_carla_bridge/msg/SlipEstimate: The tire slips of a vehicle
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct SlipEstimate {
+    pub stamp: Time,
+    pub front: AxleSlip,
+    pub rear: AxleSlip,
+}
+
+/// zenoh_carla_bridge/msg/WheelSpeeds: Rotational speed of each wheel
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct WheelSpeeds {
+    pub stamp: Time,
+    /// Front left, front right, rear left, rear right (rad/s)
+    pub wheel_speeds: [f32; 4],
+    /// Speed of the vehicle (m/s)
+    pub vehicle_speed: f32,
+}
+
+/// tier4_vehicle_msgs/msg/ActuationCommand
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Default)]
+pub struct ActuationCommand {
+    pub accel_cmd: f64,
+    pub brake_cmd: f64,
+    pub steer_cmd: f64,
+}
+
+/// tier4_vehicle_msgs/msg/ActuationCommandStamped: The output of raw_vehicle_cmd_converter
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct ActuationCommandStamped {
+    pub header: Header,
+    pub actuation: ActuationCommand,
+}
+
+/// The constants of `HeadlightsCommand`
+pub mod headlights_command {
+    pub const NO_COMMAND: u8 = 0;
+    pub const DISABLE: u8 = 1;
+    pub const ENABLE_LOW: u8 = 2;
+    pub const ENABLE_HIGH: u8 = 3;
+}
+
+/// autoware_auto_vehicle_msgs/msg/HeadlightsCommand
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct HeadlightsCommand {
+    pub stamp: Time,
+    pub command: u8,
+}
+
+/// The constants of `HeadlightsReport`
+pub mod headlights_report {
+    pub const DISABLE: u8 = 1;
+    pub const ENABLE_LOW: u8 = 2;
+    pub const ENABLE_HIGH: u8 = 3;
+}
+
+/// autoware_auto_vehicle_msgs/msg/HeadlightsReport
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct HeadlightsReport {
+    pub stamp: Time,
+    pub report: u8,
+}
+
+/// The constants of `MrmState`
+pub mod mrm_state {
+    /// For common use
+    pub const UNKNOWN: u16 = 0;
+    /// For state
+    pub const NORMAL: u16 = 1;
+    pub const MRM_OPERATING: u16 = 2;
+    pub const MRM_SUCCEEDED: u16 = 3;
+    pub const MRM_FAILED: u16 = 4;
+    /// For behavior
+    pub const NONE: u16 = 1;
+    pub const EMERGENCY_STOP: u16 = 2;
+    pub const COMFORTABLE_STOP: u16 = 3;
+}
+
+/// autoware_adapi_v1_msgs/msg/MrmState: The minimum risk maneuver requested by the fail-safe
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct MrmState {
+    pub stamp: Time,
+    pub state: u16,
+    pub behavior: u16,
+}
+
+/// The constants of `MrmBehaviorStatus`
+pub mod mrm_behavior_status {
+    pub const NOT_AVAILABLE: u8 = 0;
+    pub const AVAILABLE: u8 = 1;
+    pub const OPERATING: u8 = 2;
+}
+
+/// tier4_system_msgs/msg/MrmBehaviorStatus: What the operator of a stop behavior reports
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct MrmBehaviorStatus {
+    pub stamp: Time,
+    pub state: u8,
+}
+
+/// diagnostic_msgs/msg/KeyValue
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct KeyValue {
+    /// What to label this value when viewing.
+    pub key: String,
+    /// A value to track over time.
+    pub value: String,
+}
+
+/// The constants of `DiagnosticStatus`
+pub mod diagnostic_status {
+    /// Possible levels of operations.
+    pub const OK: u8 = 0;
+    pub const WARN: u8 = 1;
+    pub const ERROR: u8 = 2;
+    pub const STALE: u8 = 3;
+}
+
+/// diagnostic_msgs/msg/DiagnosticStatus: This message holds the status of an individual component
+/// of the robot.
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct DiagnosticStatus {
+    /// Level of operation enumerated above.
+    pub level: u8,
+    /// A description of the test/component reporting.
+    pub name: String,
+    /// A description of the status.
+    pub message: String,
+    /// A hardware unique string.
+    pub hardware_id: String,
+    /// An array of values associated with the status.
+    pub values: Vec<KeyValue>,
+}
+
+/// diagnostic_msgs/msg/DiagnosticArray: This message is used to send diagnostic information about
+/// the state of the robot.
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct DiagnosticArray {
+    pub header: Header,
+    /// An array of components being reported on.
+    pub status: Vec<DiagnosticStatus>,
+}
+
+/// geometry_msgs/msg/Twist: This expresses velocity in free space broken into its linear and
+/// angular parts.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Default)]
+pub struct Twist {
+    pub linear: [f64; 3],
+    pub angular: [f64; 3],
+}
+
+/// geometry_msgs/msg/TwistStamped: A twist with reference coordinate frame and timestamp
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct TwistStamped {
+    pub header: Header,
+    pub twist: Twist,
+}
+
+/// geometry_msgs/msg/TwistWithCovariance: This expresses velocity in free space with uncertainty.
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct TwistWithCovariance {
+    pub twist: Twist,
+    /// Row-major representation of the 6x6 covariance matrix
+    /// The orientation parameters use a fixed-axis representation.
+    /// In order, the parameters are:
+    /// (x, y, z, rotation about X axis, rotation about Y axis, rotation about Z axis)
+    /// In rows, serde has no arrays of 36 f64
+    pub covariance: [[f64; 6]; 6],
+}
+
+/// geometry_msgs/msg/TwistWithCovarianceStamped: This represents an estimated twist with reference
+/// coordinate frame and timestamp.
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct TwistWithCovarianceStamped {
+    pub header: Header,
+    pub twist: TwistWithCovariance,
+}
+
+/// geometry_msgs/msg/Point32: This contains the position of a point in free space(with 32 bits of
+/// precision).
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Default)]
+pub struct Point32 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+/// geometry_msgs/msg/Polygon: A specification of a polygon where the first and last points are
+/// assumed to be connected
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct Polygon {
+    pub points: Vec<Point32>,
+}
+
+/// geometry_msgs/msg/PolygonStamped: This represents a Polygon with reference coordinate frame and
+/// timestamp
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct PolygonStamped {
+    pub header: Header,
+    pub polygon: Polygon,
+}
+
+/// The constants of `AutowareState`
+pub mod autoware_state {
+    pub const INITIALIZING: u8 = 1;
+    pub const WAITING_FOR_ROUTE: u8 = 2;
+    pub const PLANNING: u8 = 3;
+    pub const WAITING_FOR_ENGAGE: u8 = 4;
+    pub const DRIVING: u8 = 5;
+    pub const ARRIVED_GOAL: u8 = 6;
+    pub const FINALIZING: u8 = 7;
+}
+
+/// autoware_auto_system_msgs/msg/AutowareState: Where Autoware is in its driving cycle
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct AutowareState {
+    pub stamp: Time,
+    pub state: u8,
+}
+
+/// autoware_auto_vehicle_msgs/msg/Engage: Whether Autoware is allowed to drive
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct Engage {
+    pub stamp: Time,
+    pub engage: bool,
+}
+
+/// geometry_msgs/msg/Pose: A representation of pose in free space, composed of position and
+/// orientation.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Default)]
+pub struct Pose {
+    pub position: [f64; 3],
+    pub orientation: [f64; 4],
+}
+
+/// geometry_msgs/msg/PoseWithCovariance: This represents a pose in free space with uncertainty.
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct PoseWithCovariance {
+    pub pose: Pose,
+    /// Row-major representation of the 6x6 covariance matrix
+    /// The orientation parameters use a fixed-axis representation.
+    /// In order, the parameters are:
+    /// (x, y, z, rotation about X axis, rotation about Y axis, rotation about Z axis)
+    /// In rows, serde has no arrays of 36 f64
+    pub covariance: [[f64; 6]; 6],
+}
+
+/// geometry_msgs/msg/PoseWithCovarianceStamped: This expresses an estimated pose with a reference
+/// coordinate frame and timestamp
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct PoseWithCovarianceStamped {
+    pub header: Header,
+    pub pose: PoseWithCovariance,
+}
+
+/// geometry_msgs/msg/PoseStamped: A Pose with reference coordinate frame and timestamp
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct PoseStamped {
+    pub header: Header,
+    pub pose: Pose,
+}
+
+/// autoware_auto_planning_msgs/msg/TrajectoryPoint: Representation of a trajectory point for the
+/// controller
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct TrajectoryPoint {
+    /// A builtin_interfaces/msg/Duration, which has the layout of `Time`
+    pub time_from_start: Time,
+    pub pose: Pose,
+    /// Defaults to 0.0
+    pub longitudinal_velocity_mps: f32,
+    /// Defaults to 0.0
+    pub lateral_velocity_mps: f32,
+    /// Defaults to 0.0
+    pub acceleration_mps2: f32,
+    /// Defaults to 0.0
+    pub heading_rate_rps: f32,
+    /// Defaults to 0.0
+    pub front_wheel_angle_rad: f32,
+    /// Defaults to 0.0
+    pub rear_wheel_angle_rad: f32,
+}
+
+/// The constants of `Trajectory`
+pub mod trajectory {
+    pub const CAPACITY: u32 = 100;
+}
+
+/// autoware_auto_planning_msgs/msg/Trajectory: A set of trajectory points for the controller
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct Trajectory {
+    pub header: Header,
+    /// At most 100
+    pub points: Vec<TrajectoryPoint>,
+}
+
+/// The constants of `GateMode`
+pub mod gate_mode {
+    pub const AUTO: u8 = 0;
+    pub const EXTERNAL: u8 = 1;
+}
+
+/// tier4_control_msgs/msg/GateMode
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Default)]
+pub struct GateMode {
+    pub data: u8,
+}
+
+/// Call the macro with the name of every struct, to test each of them
+#[cfg(test)]
+macro_rules! for_each_message {
+    ($macro:ident) => {
+        $macro!(
+            Lateral,
+            Longitudinal,
+            Control,
+            BatteryStatus,
+            DoorCommand,
+            DoorStatus,
+            AdapiDoorCommand,
+            SetDoorCommand,
+            SetDoorCommandResponse,
+            ResponseStatus,
+            AdapiDoorStatus,
+            DoorStatusArray,
+            AxleSlip,
+            SlipEstimate,
+            WheelSpeeds,
+            ActuationCommand,
+            ActuationCommandStamped,
+            HeadlightsCommand,
+            HeadlightsReport,
+            MrmState,
+            MrmBehaviorStatus,
+            KeyValue,
+            DiagnosticStatus,
+            DiagnosticArray,
+            Twist,
+            TwistStamped,
+            TwistWithCovariance,
+            TwistWithCovarianceStamped,
+            Point32,
+            Polygon,
+            PolygonStamped,
+            AutowareState,
+            Engage,
+            Pose,
+            PoseWithCovariance,
+            PoseWithCovarianceStamped,
+            PoseStamped,
+            TrajectoryPoint,
+            Trajectory,
+            GateMode,
+        );
+    };
+}
+
+#[cfg(test)]
+mod tests;
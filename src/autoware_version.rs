@@ -0,0 +1,170 @@
+//! The Autoware releases, whose control commands have different layouts
+use crate::{
+    autoware_type::Control,
+    cdr_utils,
+    error::{Error, Result},
+};
+use serde_derive::Deserialize;
+use std::{fmt, str::FromStr};
+use zenoh_ros_type::autoware_auto_control_msgs::{
+    AckermannControlCommand, AckermannLateralCommand, LongitudinalCommand,
+};
+
+/// The Autoware release, which decides the layout of the received control command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(try_from = "String")]
+#[non_exhaustive]
+pub enum AutowareVersion {
+    /// autoware_auto_control_msgs/msg/AckermannControlCommand, up to the 2023 releases.
+    #[default]
+    Universe2023,
+    /// autoware_control_msgs/msg/Control, from the 2024 releases.
+    Universe2024,
+}
+
+impl AutowareVersion {
+    fn other(self) -> AutowareVersion {
+        match self {
+            AutowareVersion::Universe2023 => AutowareVersion::Universe2024,
+            AutowareVersion::Universe2024 => AutowareVersion::Universe2023,
+        }
+    }
+
+    fn deserialize_control(self, payload: &[u8]) -> Result<AckermannControlCommand> {
+        match self {
+            AutowareVersion::Universe2023 => cdr_utils::deserialize(payload),
+            AutowareVersion::Universe2024 => {
+                cdr_utils::deserialize::<Control>(payload).map(AckermannControlCommand::from)
+            }
+        }
+    }
+
+    /// Deserialize a control command in the layout of this version.
+    ///
+    /// A payload which only fits the layout of the other version is reported as a mismatch.
+    pub fn decode_control(self, payload: &[u8]) -> Result<AckermannControlCommand> {
+        self.deserialize_control(payload).map_err(|e| {
+            if self.other().deserialize_control(payload).is_ok() {
+                Error::MessageVersionMismatch {
+                    configured: self,
+                    detected: self.other(),
+                }
+            } else {
+                e
+            }
+        })
+    }
+}
+
+impl fmt::Display for AutowareVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AutowareVersion::Universe2023 => write!(f, "universe-2023"),
+            AutowareVersion::Universe2024 => write!(f, "universe-2024"),
+        }
+    }
+}
+
+impl FromStr for AutowareVersion {
+    type Err = String;
+
+    fn from_str(version: &str) -> Result<Self, Self::Err> {
+        match version.trim() {
+            "universe-2023" => Ok(AutowareVersion::Universe2023),
+            "universe-2024" => Ok(AutowareVersion::Universe2024),
+            version => Err(format!(
+                "Unknown Autoware version '{version}', expect universe-2023 or universe-2024"
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for AutowareVersion {
+    type Error = String;
+
+    fn try_from(version: String) -> Result<Self, Self::Error> {
+        version.parse()
+    }
+}
+
+impl From<Control> for AckermannControlCommand {
+    fn from(control: Control) -> Self {
+        AckermannControlCommand {
+            stamp: control.stamp,
+            lateral: AckermannLateralCommand {
+                stamp: control.lateral.stamp,
+                steering_tire_angle: control.lateral.steering_tire_angle,
+                steering_tire_rotation_rate: control.lateral.steering_tire_rotation_rate,
+            },
+            longitudinal: LongitudinalCommand {
+                stamp: control.longitudinal.stamp,
+                speed: control.longitudinal.velocity,
+                acceleration: control.longitudinal.acceleration,
+                jerk: control.longitudinal.jerk,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zenoh_ros_type::builtin_interfaces::Time;
+
+    /// Encoded by `tools/cdr_golden.py` after the interfaces of each release, not captured
+    const UNIVERSE_2023: &[u8] = include_bytes!("../tests/fixtures/control_cmd_universe_2023.cdr");
+    const UNIVERSE_2024: &[u8] = include_bytes!("../tests/fixtures/control_cmd_universe_2024.cdr");
+
+    fn assert_fixture_command(cmd: &AckermannControlCommand) {
+        assert_eq!(
+            cmd.stamp,
+            Time {
+                sec: 1,
+                nanosec: 500_000_000,
+            }
+        );
+        assert_eq!(cmd.lateral.steering_tire_angle, 0.25);
+        assert_eq!(cmd.lateral.steering_tire_rotation_rate, 0.5);
+        assert_eq!(cmd.longitudinal.speed, 2.0);
+        assert_eq!(cmd.longitudinal.acceleration, 1.0);
+        assert_eq!(cmd.longitudinal.jerk, -1.0);
+    }
+
+    #[test]
+    fn decode_the_universe_2023_fixture() {
+        let cmd = AutowareVersion::Universe2023
+            .decode_control(UNIVERSE_2023)
+            .unwrap();
+        assert_fixture_command(&cmd);
+        assert!(matches!(
+            AutowareVersion::Universe2024.decode_control(UNIVERSE_2023),
+            Err(Error::MessageVersionMismatch {
+                configured: AutowareVersion::Universe2024,
+                detected: AutowareVersion::Universe2023,
+            })
+        ));
+    }
+
+    #[test]
+    fn decode_the_universe_2024_fixture() {
+        let cmd = AutowareVersion::Universe2024
+            .decode_control(UNIVERSE_2024)
+            .unwrap();
+        assert_fixture_command(&cmd);
+        assert!(matches!(
+            AutowareVersion::Universe2023.decode_control(UNIVERSE_2024),
+            Err(Error::MessageVersionMismatch {
+                configured: AutowareVersion::Universe2023,
+                detected: AutowareVersion::Universe2024,
+            })
+        ));
+    }
+
+    #[test]
+    fn parse_the_autoware_version() {
+        for version in [AutowareVersion::Universe2023, AutowareVersion::Universe2024] {
+            assert_eq!(version.to_string().parse(), Ok(version));
+        }
+        assert!("universe-2022".parse::<AutowareVersion>().is_err());
+    }
+}
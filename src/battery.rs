@@ -0,0 +1,48 @@
+/// A simple consumption model, since CARLA doesn't simulate batteries
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct BatteryConfig {
+    /// Drain while the vehicle exists (percent per second).
+    pub base_drain: f32,
+    /// Additional drain at full throttle (percent per second).
+    pub throttle_drain: f32,
+    /// Charge at the start (percent).
+    pub initial_charge: f32,
+}
+
+impl Default for BatteryConfig {
+    fn default() -> Self {
+        BatteryConfig {
+            base_drain: 0.001,
+            throttle_drain: 0.02,
+            initial_charge: 100.0,
+        }
+    }
+}
+
+pub struct BatteryModel {
+    config: BatteryConfig,
+    charge: f32,
+}
+
+impl BatteryModel {
+    pub fn new(config: BatteryConfig) -> BatteryModel {
+        BatteryModel {
+            charge: config.initial_charge.clamp(0.0, 100.0),
+            config,
+        }
+    }
+
+    /// Integrate the consumption over `elapsed_sec` with the applied throttle
+    pub fn update(&mut self, elapsed_sec: f64, throttle: f32) {
+        if elapsed_sec <= 0.0 {
+            return;
+        }
+        let drain = self.config.base_drain + self.config.throttle_drain * throttle.clamp(0.0, 1.0);
+        self.charge = (self.charge - drain * elapsed_sec as f32).max(0.0);
+    }
+
+    pub fn charge(&self) -> f32 {
+        self.charge
+    }
+}
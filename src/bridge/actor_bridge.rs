@@ -1,28 +1,191 @@
 use super::{
-    other_bridge::OtherActorBridge, sensor_bridge::SensorBridge,
-    trafficlight_bridge::TrafficLightBridge, trafficsign_bridge::TrafficSignBridge,
+    other_bridge::OtherActorBridge,
+    sensor_bridge::SensorBridge,
+    trafficlight_bridge::TrafficLightBridge,
+    trafficsign_bridge::TrafficSignBridge,
     vehicle_bridge::VehicleBridge,
+    walker_bridge::{self, WalkerBridge},
+};
+use crate::{
+    config::BridgeConfig, error::Result, namespace::NamespaceRegistry, reset::ResetReport,
+    scheduler::TickDue, sensor_pool::SensorPool, time::TimeStamp,
+};
+use carla::{
+    client::{Actor, ActorKind},
+    rpc::ActorId,
+};
+use nalgebra::{Isometry3, Vector3};
+use serde_derive::Serialize;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
 };
-use crate::error::Result;
-use carla::client::{Actor, ActorKind};
-use std::sync::Arc;
 use zenoh::prelude::sync::*;
 
+/// What a bridge is for, counted in the heartbeat
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BridgeKind {
+    Vehicle,
+    Walker,
+    Sensor,
+    Other,
+}
+
+/// The key expressions a bridge declared, recorded at the declaration
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DeclaredKeys {
+    pub publishers: Vec<String>,
+    pub subscribers: Vec<String>,
+    pub queryables: Vec<String>,
+}
+
+/// A bridge in the reply of `*/bridge/list`
+#[derive(Debug, Clone, Serialize)]
+pub struct BridgeDescription {
+    pub actor_id: u32,
+    pub namespace: String,
+    /// `vehicle`, `walker`, or the type_id of a sensor
+    pub kind: String,
+    /// The role_name as spawned, before the sanitization into the key expressions
+    pub raw_name: Option<String>,
+    #[serde(flatten)]
+    pub keys: DeclaredKeys,
+}
+
+/// The motion of an actor in the world snapshot of a tick
+#[derive(Debug, Clone, Copy)]
+pub struct ActorMotion {
+    pub transform: Isometry3<f32>,
+    pub velocity: Vector3<f32>,
+    /// In degrees per second, like CARLA
+    pub angular_velocity: Vector3<f32>,
+}
+
+/// What the main loop reads from the world once per tick, shared by every bridge.
+///
+/// The motions come from a single frame, so the bridges of different vehicles describe
+/// the same instant, and don't query them one RPC at a time.
+#[derive(Debug, Clone, Default)]
+pub struct TickSnapshot {
+    /// The simulation time of the tick, for the headers and the clock
+    pub stamp: TimeStamp,
+    pub frame: u64,
+    /// The bridged actors found in the frame, by actor id
+    pub actors: HashMap<ActorId, ActorMotion>,
+    /// The zenoh session lost its connectivity, see `Heartbeat::zenoh_lost`
+    pub zenoh_lost: bool,
+}
+
+impl TickSnapshot {
+    /// A tick without a world, e.g. of the kinematic backend, the bridges read their actors
+    pub fn without_world(stamp: TimeStamp, frame: u64) -> TickSnapshot {
+        TickSnapshot {
+            stamp,
+            frame,
+            actors: HashMap::new(),
+            zenoh_lost: false,
+        }
+    }
+
+    pub fn motion(&self, actor_id: ActorId) -> Option<&ActorMotion> {
+        self.actors.get(&actor_id)
+    }
+}
+
 pub trait ActorBridge {
-    fn step(&mut self, elapsed_sec: f64, timestamp: f64) -> Result<()>;
+    /// Called every tick, before the status and the control
+    fn step(&mut self, elapsed_sec: f64, tick: &TickSnapshot) -> Result<()>;
+
+    /// Publish the state read in the last step, at `status_rate` in `[loop]`
+    fn publish_status(&mut self, _stamp: TimeStamp) -> Result<()> {
+        Ok(())
+    }
+
+    /// Actuate the actor at `control_rate` in `[loop]`, `elapsed_sec` since the last control
+    fn apply_control(&mut self, _stamp: TimeStamp, _elapsed_sec: f64) {}
+
+    fn kind(&self) -> BridgeKind {
+        BridgeKind::Other
+    }
+
+    /// The namespace and the keys of the bridge, None if it declares nothing
+    fn describe(&self) -> Option<BridgeDescription> {
+        None
+    }
+
+    /// The name of a sensor and how many of its frames the queue to the publisher dropped
+    fn dropped_frames(&self) -> Option<(String, u64)> {
+        None
+    }
+
+    /// The name of a sensor and how many frames its events are behind the world
+    fn frame_lag(&self) -> Option<(String, u64)> {
+        None
+    }
+
+    /// Go back to the state at the spawn, adding what was reset to the report
+    fn reset(&mut self, _report: &mut ResetReport) {}
+}
+
+/// Step the bridge, then publish and control it if it's their turn in the tick.
+///
+/// The control doesn't depend on the status, so it's applied even if publishing failed.
+pub fn run_tick(
+    bridge: &mut dyn ActorBridge,
+    elapsed_sec: f64,
+    tick: &TickSnapshot,
+    due: TickDue,
+) -> Result<()> {
+    bridge.step(elapsed_sec, tick)?;
+    let status = if due.status {
+        bridge.publish_status(tick.stamp)
+    } else {
+        Ok(())
+    };
+    if let Some(control_elapsed_sec) = due.control_elapsed_sec {
+        bridge.apply_control(tick.stamp, control_elapsed_sec);
+    }
+    status
 }
 
 // z_session should outlive Box<>
-pub fn create_bridge(z_session: Arc<Session>, actor: Actor) -> Result<Box<dyn ActorBridge>> {
+pub fn create_bridge(
+    z_session: Arc<Session>,
+    actor: Actor,
+    config: &BridgeConfig,
+    namespaces: &Arc<Mutex<NamespaceRegistry>>,
+    sensor_pool: &SensorPool,
+) -> Result<Box<dyn ActorBridge>> {
     Ok(match actor.into_kinds() {
-        ActorKind::Vehicle(vehicle) => Box::new(VehicleBridge::new(z_session, vehicle)?),
-        ActorKind::Sensor(sensor) => Box::new(SensorBridge::new(z_session, sensor)?),
+        ActorKind::Vehicle(vehicle) => Box::new(VehicleBridge::new(
+            z_session,
+            Box::new(vehicle),
+            config,
+            namespaces.clone(),
+        )?),
+        ActorKind::Sensor(sensor) => Box::new(SensorBridge::new(
+            z_session,
+            sensor,
+            config,
+            namespaces.clone(),
+            sensor_pool,
+        )?),
         ActorKind::TrafficLight(traffic_light) => {
             Box::new(TrafficLightBridge::new(z_session, traffic_light)?)
         }
         ActorKind::TrafficSign(traffic_sign) => {
             Box::new(TrafficSignBridge::new(z_session, traffic_sign)?)
         }
-        ActorKind::Other(other) => Box::new(OtherActorBridge::new(z_session, other)?),
+        // The walkers without a bridged role_name are left to CARLA, e.g. to their AI controller
+        ActorKind::Other(other) => match walker_bridge::ego_walker(other, config) {
+            Ok(walker) => Box::new(WalkerBridge::new(
+                z_session,
+                walker,
+                config,
+                namespaces.clone(),
+            )?),
+            Err(other) => Box::new(OtherActorBridge::new(z_session, other)?),
+        },
     })
 }
@@ -0,0 +1,476 @@
+//! A synthetic vehicle driven by a kinematic bicycle model, for `--backend kinematic`
+use super::{vehicle_actor::VehicleActor, vehicle_bridge::MAX_STEER_ANGLE};
+use crate::{
+    error::{Error, Result},
+    longitudinal::AckermannGains,
+};
+use carla::{
+    geom::BoundingBox,
+    rpc::{
+        VehicleAckermannControl, VehicleControl, VehicleDoor, VehicleLightState,
+        VehiclePhysicsControl, VehicleWheelLocation,
+    },
+};
+use nalgebra::{Isometry3, Translation3, UnitQuaternion, Vector3};
+use serde_derive::Deserialize;
+use std::sync::{Arc, Mutex};
+
+/// A speed below this is standing still (m/s)
+const STANDSTILL_SPEED: f64 = 0.01;
+
+/// Parameters of the model, `[kinematic]` in the configuration file
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct KinematicConfig {
+    /// The role_name is the prefix followed by this name, which becomes the namespace.
+    pub vehicle_name: String,
+    /// Distance between the front and rear axles (m).
+    pub wheelbase: f64,
+    /// Largest acceleration (m/s^2).
+    pub max_acceleration: f64,
+    /// Largest deceleration, as a positive value (m/s^2).
+    pub max_deceleration: f64,
+    /// Largest tire angle (rad).
+    pub max_steer_angle: f64,
+}
+
+impl Default for KinematicConfig {
+    fn default() -> Self {
+        // Like the sample vehicle of Autoware
+        KinematicConfig {
+            vehicle_name: String::from("ego"),
+            wheelbase: 2.79,
+            max_acceleration: 3.0,
+            max_deceleration: 6.0,
+            max_steer_angle: 0.7,
+        }
+    }
+}
+
+impl KinematicConfig {
+    pub fn validate(&self) -> Result<()> {
+        let limits = [
+            ("wheelbase", self.wheelbase),
+            ("max_acceleration", self.max_acceleration),
+            ("max_deceleration", self.max_deceleration),
+            ("max_steer_angle", self.max_steer_angle),
+        ];
+        match limits
+            .iter()
+            .find(|(_, value)| !(value.is_finite() && *value > 0.0))
+        {
+            Some((name, _)) => Err(Error::Config {
+                reason: format!("kinematic {name} must be positive"),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// What the vehicle was last told to do
+#[derive(Debug, Clone, Copy)]
+enum Command {
+    /// Reach the speed (m/s) with at most the acceleration (m/s^2), zero for the limits
+    Ackermann { speed: f64, acceleration: f64 },
+    /// Throttle and brake from 0 to 1
+    Raw {
+        throttle: f64,
+        brake: f64,
+        reverse: bool,
+        hand_brake: bool,
+    },
+}
+
+/// The state of the model in the map frame with ROS axes, at the rear axle
+struct KinematicState {
+    x: f64,
+    y: f64,
+    yaw: f64,
+    /// Signed, negative when reversing (m/s)
+    speed: f64,
+    acceleration: f64,
+    /// Tire angle, positive to the left (rad)
+    steer_angle: f64,
+    yaw_rate: f64,
+    command: Command,
+    light_state: VehicleLightState,
+}
+
+/// A vehicle integrated by the bridge instead of a simulator.
+///
+/// The bridge owns one handle as its actor, and the loop keeps another one to
+/// advance the model, so both share the state.
+#[derive(Clone)]
+pub struct KinematicVehicle {
+    id: u32,
+    role_name: String,
+    config: Arc<KinematicConfig>,
+    state: Arc<Mutex<KinematicState>>,
+}
+
+impl KinematicVehicle {
+    pub fn new(id: u32, role_name: String, config: KinematicConfig) -> KinematicVehicle {
+        KinematicVehicle {
+            id,
+            role_name,
+            config: Arc::new(config),
+            state: Arc::new(Mutex::new(KinematicState {
+                x: 0.0,
+                y: 0.0,
+                yaw: 0.0,
+                speed: 0.0,
+                acceleration: 0.0,
+                steer_angle: 0.0,
+                yaw_rate: 0.0,
+                command: Command::Ackermann {
+                    speed: 0.0,
+                    acceleration: 0.0,
+                },
+                light_state: VehicleLightState::empty(),
+            })),
+        }
+    }
+
+    /// Integrate the model over `elapsed_sec` with the latest command
+    pub fn advance(&self, elapsed_sec: f64) {
+        if elapsed_sec <= 0.0 {
+            return;
+        }
+        let config = &self.config;
+        let mut state = self.state.lock().unwrap();
+        let acceleration = match state.command {
+            Command::Ackermann {
+                speed,
+                acceleration,
+            } => {
+                let accel_limit = capped(acceleration, config.max_acceleration);
+                let decel_limit = capped(acceleration, config.max_deceleration);
+                ((speed - state.speed) / elapsed_sec).clamp(-decel_limit, accel_limit)
+            }
+            Command::Raw {
+                throttle,
+                brake,
+                reverse,
+                hand_brake,
+            } => {
+                let brake = if hand_brake { 1.0 } else { brake };
+                let direction = if reverse { -1.0 } else { 1.0 };
+                let drive = direction * throttle * config.max_acceleration;
+                // The brake slows down to a standstill, it never drives backwards
+                let braked_speed = state.speed.signum()
+                    * (state.speed.abs() - brake * config.max_deceleration * elapsed_sec).max(0.0);
+                (braked_speed - state.speed) / elapsed_sec + drive
+            }
+        };
+        let speed = state.speed + acceleration * elapsed_sec;
+        state.speed = if speed.abs() < STANDSTILL_SPEED {
+            0.0
+        } else {
+            speed
+        };
+        state.acceleration = acceleration;
+        state.yaw_rate = state.speed * state.steer_angle.tan() / config.wheelbase;
+        state.yaw += state.yaw_rate * elapsed_sec;
+        state.x += state.speed * state.yaw.cos() * elapsed_sec;
+        state.y += state.speed * state.yaw.sin() * elapsed_sec;
+    }
+
+    /// Set the tire angle of a CARLA steer (-1 to 1, positive to the right)
+    fn steer(&self, state: &mut KinematicState, steer: f32) {
+        let angle = -(steer * MAX_STEER_ANGLE).to_radians() as f64;
+        state.steer_angle = angle.clamp(-self.config.max_steer_angle, self.config.max_steer_angle);
+    }
+}
+
+/// The commanded limit if there is one, within the limit of the model
+fn capped(commanded: f64, limit: f64) -> f64 {
+    if commanded.abs() > 0.0 {
+        commanded.abs().min(limit)
+    } else {
+        limit
+    }
+}
+
+/* The CARLA getters use CARLA's axes: x forward, y to the right, and the yaw turns right */
+impl VehicleActor for KinematicVehicle {
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn type_id(&self) -> String {
+        String::from("vehicle.bridge.kinematic")
+    }
+
+    fn role_name(&self) -> Option<String> {
+        Some(self.role_name.clone())
+    }
+
+    fn velocity(&self) -> Vector3<f32> {
+        let state = self.state.lock().unwrap();
+        Vector3::new(
+            (state.speed * state.yaw.cos()) as f32,
+            -(state.speed * state.yaw.sin()) as f32,
+            0.0,
+        )
+    }
+
+    fn angular_velocity(&self) -> Vector3<f32> {
+        let state = self.state.lock().unwrap();
+        Vector3::new(0.0, 0.0, -state.yaw_rate.to_degrees() as f32)
+    }
+
+    fn transform(&self) -> Isometry3<f32> {
+        let state = self.state.lock().unwrap();
+        Isometry3::from_parts(
+            Translation3::new(state.x as f32, -state.y as f32, 0.0),
+            UnitQuaternion::from_euler_angles(0.0, 0.0, -state.yaw as f32),
+        )
+    }
+
+    /// The model has a wheelbase but no body
+    fn bounding_box(&self) -> Option<BoundingBox<f32>> {
+        None
+    }
+
+    fn control(&self) -> VehicleControl {
+        let state = self.state.lock().unwrap();
+        let (reverse, hand_brake) = match state.command {
+            Command::Ackermann { speed, .. } => (speed < 0.0, false),
+            Command::Raw {
+                reverse,
+                hand_brake,
+                ..
+            } => (reverse, hand_brake),
+        };
+        // The throttle and brake which would give the acceleration of the last step
+        let forward_acceleration = state.acceleration * if reverse { -1.0 } else { 1.0 };
+        VehicleControl {
+            throttle: (forward_acceleration / self.config.max_acceleration).clamp(0.0, 1.0) as f32,
+            steer: (-state.steer_angle.to_degrees() as f32 / MAX_STEER_ANGLE).clamp(-1.0, 1.0),
+            brake: (-forward_acceleration / self.config.max_deceleration).clamp(0.0, 1.0) as f32,
+            hand_brake,
+            reverse,
+            manual_gear_shift: false,
+            gear: if reverse { -1 } else { 1 },
+        }
+    }
+
+    fn wheel_steer_angle(&self, _wheel_location: VehicleWheelLocation) -> f32 {
+        -self.state.lock().unwrap().steer_angle.to_degrees() as f32
+    }
+
+    fn wheel_angular_velocities(&self) -> Option<[f32; 4]> {
+        None
+    }
+
+    fn apply_control(&mut self, control: &VehicleControl) {
+        let mut state = self.state.lock().unwrap();
+        self.steer(&mut state, control.steer);
+        state.command = Command::Raw {
+            throttle: control.throttle.clamp(0.0, 1.0) as f64,
+            brake: control.brake.clamp(0.0, 1.0) as f64,
+            reverse: control.reverse,
+            hand_brake: control.hand_brake,
+        };
+    }
+
+    fn apply_ackermann_control(&mut self, control: &VehicleAckermannControl) {
+        let mut state = self.state.lock().unwrap();
+        self.steer(&mut state, control.steer);
+        state.command = Command::Ackermann {
+            speed: control.speed as f64,
+            acceleration: control.acceleration as f64,
+        };
+    }
+
+    /// The model reaches the commanded speed on its own
+    fn apply_ackermann_gains(&mut self, _gains: &AckermannGains) {}
+
+    fn open_door(&mut self, _door: VehicleDoor) {}
+
+    fn close_door(&mut self, _door: VehicleDoor) {}
+
+    fn light_state(&self) -> VehicleLightState {
+        self.state.lock().unwrap().light_state
+    }
+
+    fn set_light_state(&mut self, light_state: VehicleLightState) {
+        self.state.lock().unwrap().light_state = light_state;
+    }
+
+    fn physics_control(&self) -> Option<VehiclePhysicsControl> {
+        None
+    }
+
+    fn apply_physics_control(&mut self, _physics: &VehiclePhysicsControl) {}
+
+    fn set_transform(&mut self, transform: &Isometry3<f32>) {
+        let mut state = self.state.lock().unwrap();
+        let (_, _, yaw) = transform.rotation.euler_angles();
+        state.x = transform.translation.x as f64;
+        state.y = -transform.translation.y as f64;
+        state.yaw = -yaw as f64;
+    }
+
+    fn set_target_velocity(&mut self, velocity: &Vector3<f32>) {
+        let mut state = self.state.lock().unwrap();
+        // Only the part along the heading, the model doesn't slip
+        state.speed = velocity.x as f64 * state.yaw.cos() - velocity.y as f64 * state.yaw.sin();
+        state.acceleration = 0.0;
+    }
+
+    fn set_target_angular_velocity(&mut self, _angular_velocity: &Vector3<f32>) {
+        // The yaw rate follows from the speed and the steer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f64 = 0.05;
+
+    fn vehicle() -> KinematicVehicle {
+        KinematicVehicle::new(1, String::from("autoware_ego"), KinematicConfig::default())
+    }
+
+    fn ackermann(speed: f32, acceleration: f32, steer: f32) -> VehicleAckermannControl {
+        VehicleAckermannControl {
+            steer,
+            steer_speed: 0.0,
+            speed,
+            acceleration,
+            jerk: 0.0,
+        }
+    }
+
+    fn raw(throttle: f32, brake: f32, reverse: bool) -> VehicleControl {
+        VehicleControl {
+            throttle,
+            steer: 0.0,
+            brake,
+            hand_brake: false,
+            reverse,
+            manual_gear_shift: false,
+            gear: if reverse { -1 } else { 1 },
+        }
+    }
+
+    fn speed(vehicle: &KinematicVehicle) -> f64 {
+        vehicle.state.lock().unwrap().speed
+    }
+
+    #[test]
+    fn validate_the_parameters() {
+        assert!(KinematicConfig::default().validate().is_ok());
+        for config in [
+            KinematicConfig {
+                wheelbase: 0.0,
+                ..KinematicConfig::default()
+            },
+            KinematicConfig {
+                max_deceleration: -1.0,
+                ..KinematicConfig::default()
+            },
+            KinematicConfig {
+                max_steer_angle: f64::NAN,
+                ..KinematicConfig::default()
+            },
+        ] {
+            assert!(config.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn reach_the_speed_within_the_limits() {
+        let mut vehicle = vehicle();
+        // Without a commanded acceleration, the model accelerates at its limit
+        vehicle.apply_ackermann_control(&ackermann(10.0, 0.0, 0.0));
+        vehicle.advance(1.0);
+        assert!((speed(&vehicle) - 3.0).abs() < 1e-9);
+        // A commanded acceleration below the limit is kept
+        vehicle.apply_ackermann_control(&ackermann(10.0, 1.0, 0.0));
+        vehicle.advance(1.0);
+        assert!((speed(&vehicle) - 4.0).abs() < 1e-9);
+        // The target isn't overshot
+        vehicle.apply_ackermann_control(&ackermann(4.5, 0.0, 0.0));
+        vehicle.advance(1.0);
+        assert!((speed(&vehicle) - 4.5).abs() < 1e-9);
+        vehicle.apply_ackermann_control(&ackermann(0.0, 0.0, 0.0));
+        vehicle.advance(0.5);
+        assert!((speed(&vehicle) - 1.5).abs() < 1e-9);
+        // No time, no change
+        vehicle.advance(0.0);
+        assert!((speed(&vehicle) - 1.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn the_brake_never_drives_backwards() {
+        let mut vehicle = vehicle();
+        vehicle.apply_control(&raw(1.0, 0.0, false));
+        vehicle.advance(1.0);
+        assert!((speed(&vehicle) - 3.0).abs() < 1e-9);
+        vehicle.apply_control(&raw(0.0, 1.0, false));
+        for _ in 0..100 {
+            vehicle.advance(DT);
+        }
+        assert_eq!(speed(&vehicle), 0.0);
+        // The reverse gear drives backwards
+        vehicle.apply_control(&raw(0.5, 0.0, true));
+        vehicle.advance(1.0);
+        assert!((speed(&vehicle) + 1.5).abs() < 1e-9);
+        assert!(vehicle.control().reverse);
+    }
+
+    #[test]
+    fn a_left_turn_in_ros_axes() {
+        let mut vehicle = vehicle();
+        // A negative CARLA steer is to the left
+        let steer = -10.0 / MAX_STEER_ANGLE;
+        vehicle.apply_ackermann_control(&ackermann(2.0, 0.0, steer));
+        vehicle.set_target_velocity(&Vector3::new(2.0, 0.0, 0.0));
+        vehicle.advance(DT);
+        let state = vehicle.state.lock().unwrap();
+        let expected = 2.0 * 10f64.to_radians().tan() / KinematicConfig::default().wheelbase;
+        assert!((state.steer_angle - 10f64.to_radians()).abs() < 1e-6);
+        assert!((state.yaw_rate - expected).abs() < 1e-6);
+        assert!(state.yaw > 0.0 && state.y > 0.0);
+        drop(state);
+        // CARLA sees a turn to the left with y to the right
+        assert!(vehicle.angular_velocity().z < 0.0);
+        assert!(vehicle.transform().translation.y < 0.0);
+        assert!(vehicle.wheel_steer_angle(VehicleWheelLocation::FL_Wheel) < 0.0);
+    }
+
+    #[test]
+    fn the_steer_is_limited() {
+        let mut vehicle = vehicle();
+        vehicle.apply_ackermann_control(&ackermann(0.0, 0.0, 1.0));
+        let limit = KinematicConfig::default().max_steer_angle;
+        assert!((vehicle.state.lock().unwrap().steer_angle + limit).abs() < 1e-9);
+        assert_eq!(
+            vehicle.control().steer,
+            limit.to_degrees() as f32 / MAX_STEER_ANGLE
+        );
+    }
+
+    #[test]
+    fn a_transform_round_trips_through_the_carla_axes() {
+        let mut vehicle = vehicle();
+        let transform = Isometry3::from_parts(
+            Translation3::new(10.0, 5.0, 0.0),
+            UnitQuaternion::from_euler_angles(0.0, 0.0, 0.5),
+        );
+        vehicle.set_transform(&transform);
+        let read_back = vehicle.transform();
+        assert!((read_back.translation.vector - transform.translation.vector).norm() < 1e-5);
+        assert!(read_back.rotation.angle_to(&transform.rotation) < 1e-5);
+        // The velocity along the heading gives the speed
+        vehicle.set_target_velocity(&(transform.rotation * Vector3::new(3.0, 0.0, 0.0)));
+        assert!((speed(&vehicle) - 3.0).abs() < 1e-5);
+        assert!(
+            (vehicle.velocity() - transform.rotation * Vector3::new(3.0, 0.0, 0.0)).norm() < 1e-5
+        );
+    }
+}
@@ -0,0 +1,242 @@
+//! A scripted vehicle recording what a bridge applies to it, for the tests without a simulator
+use super::vehicle_actor::VehicleActor;
+use crate::longitudinal::AckermannGains;
+use carla::{
+    geom::BoundingBox,
+    rpc::{
+        VehicleAckermannControl, VehicleControl, VehicleDoor, VehicleLightState,
+        VehiclePhysicsControl, VehicleWheelLocation,
+    },
+};
+use nalgebra::{Isometry3, Vector3};
+use std::sync::{Arc, Mutex};
+
+struct MockState {
+    velocity: Vector3<f32>,
+    angular_velocity: Vector3<f32>,
+    transform: Isometry3<f32>,
+    bounding_box: Option<BoundingBox<f32>>,
+    /// The steer angles of the FL and FR wheels (degrees)
+    front_steer_angles: [f32; 2],
+    wheel_angular_velocities: Option<[f32; 4]>,
+    /// The latest applied control, which the getter reads back like CARLA
+    control: VehicleControl,
+    light_state: VehicleLightState,
+    applied_controls: Vec<VehicleControl>,
+    applied_ackermann_controls: Vec<VehicleAckermannControl>,
+    physics_reads: usize,
+}
+
+/// A vehicle which stays where the test puts it, and records the RPCs of the bridge.
+///
+/// The bridge owns one handle as its actor, and the test keeps a clone to move the vehicle
+/// and to read what was applied, since both share the state.
+#[derive(Clone)]
+pub struct MockVehicle {
+    id: u32,
+    role_name: String,
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockVehicle {
+    pub fn new(id: u32, role_name: &str) -> MockVehicle {
+        MockVehicle {
+            id,
+            role_name: role_name.to_string(),
+            state: Arc::new(Mutex::new(MockState {
+                velocity: Vector3::zeros(),
+                angular_velocity: Vector3::zeros(),
+                transform: Isometry3::identity(),
+                bounding_box: None,
+                front_steer_angles: [0.0, 0.0],
+                wheel_angular_velocities: None,
+                control: VehicleControl {
+                    throttle: 0.0,
+                    steer: 0.0,
+                    brake: 0.0,
+                    hand_brake: false,
+                    reverse: false,
+                    manual_gear_shift: false,
+                    gear: 0,
+                },
+                light_state: VehicleLightState::empty(),
+                applied_controls: Vec::new(),
+                applied_ackermann_controls: Vec::new(),
+                physics_reads: 0,
+            })),
+        }
+    }
+
+    /// In CARLA's axes (m/s)
+    pub fn set_velocity(&self, velocity: Vector3<f32>) {
+        self.state.lock().unwrap().velocity = velocity;
+    }
+
+    /// In CARLA's axes (degrees/s)
+    pub fn set_angular_velocity(&self, angular_velocity: Vector3<f32>) {
+        self.state.lock().unwrap().angular_velocity = angular_velocity;
+    }
+
+    pub fn set_pose(&self, transform: Isometry3<f32>) {
+        self.state.lock().unwrap().transform = transform;
+    }
+
+    pub fn set_bounding_box(&self, bounding_box: BoundingBox<f32>) {
+        self.state.lock().unwrap().bounding_box = Some(bounding_box);
+    }
+
+    /// The FL and FR wheels, positive to the right like CARLA (degrees)
+    pub fn set_front_steer_angles(&self, front_steer_angles: [f32; 2]) {
+        self.state.lock().unwrap().front_steer_angles = front_steer_angles;
+    }
+
+    pub fn set_wheel_angular_velocities(&self, velocities: [f32; 4]) {
+        self.state.lock().unwrap().wheel_angular_velocities = Some(velocities);
+    }
+
+    /// Every control the bridge applied, in order
+    pub fn applied_controls(&self) -> Vec<VehicleControl> {
+        self.state.lock().unwrap().applied_controls.clone()
+    }
+
+    /// Every Ackermann control the bridge applied, in order
+    pub fn applied_ackermann_controls(&self) -> Vec<VehicleAckermannControl> {
+        self.state
+            .lock()
+            .unwrap()
+            .applied_ackermann_controls
+            .iter()
+            .map(copy_ackermann)
+            .collect()
+    }
+
+    /// How many RPCs actuated the vehicle, of both kinds
+    pub fn actuations(&self) -> usize {
+        let state = self.state.lock().unwrap();
+        state.applied_controls.len() + state.applied_ackermann_controls.len()
+    }
+
+    /// Forget the recorded RPCs, the state stays
+    pub fn clear_applied(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.applied_controls.clear();
+        state.applied_ackermann_controls.clear();
+    }
+
+    /// How many times the bridge read the physics control
+    pub fn physics_reads(&self) -> usize {
+        self.state.lock().unwrap().physics_reads
+    }
+}
+
+fn copy_ackermann(control: &VehicleAckermannControl) -> VehicleAckermannControl {
+    VehicleAckermannControl {
+        steer: control.steer,
+        steer_speed: control.steer_speed,
+        speed: control.speed,
+        acceleration: control.acceleration,
+        jerk: control.jerk,
+    }
+}
+
+impl VehicleActor for MockVehicle {
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn type_id(&self) -> String {
+        String::from("vehicle.bridge.mock")
+    }
+
+    fn role_name(&self) -> Option<String> {
+        Some(self.role_name.clone())
+    }
+
+    fn velocity(&self) -> Vector3<f32> {
+        self.state.lock().unwrap().velocity
+    }
+
+    fn angular_velocity(&self) -> Vector3<f32> {
+        self.state.lock().unwrap().angular_velocity
+    }
+
+    fn transform(&self) -> Isometry3<f32> {
+        self.state.lock().unwrap().transform
+    }
+
+    fn bounding_box(&self) -> Option<BoundingBox<f32>> {
+        self.state
+            .lock()
+            .unwrap()
+            .bounding_box
+            .as_ref()
+            .map(|bounding_box| BoundingBox {
+                transform: bounding_box.transform,
+                extent: bounding_box.extent,
+            })
+    }
+
+    fn control(&self) -> VehicleControl {
+        self.state.lock().unwrap().control
+    }
+
+    fn wheel_steer_angle(&self, wheel_location: VehicleWheelLocation) -> f32 {
+        let [front_left, front_right] = self.state.lock().unwrap().front_steer_angles;
+        match wheel_location {
+            VehicleWheelLocation::FL_Wheel => front_left,
+            VehicleWheelLocation::FR_Wheel => front_right,
+            _ => 0.0,
+        }
+    }
+
+    fn wheel_angular_velocities(&self) -> Option<[f32; 4]> {
+        self.state.lock().unwrap().wheel_angular_velocities
+    }
+
+    fn apply_control(&mut self, control: &VehicleControl) {
+        let mut state = self.state.lock().unwrap();
+        state.control = *control;
+        state.applied_controls.push(*control);
+    }
+
+    fn apply_ackermann_control(&mut self, control: &VehicleAckermannControl) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .applied_ackermann_controls
+            .push(copy_ackermann(control));
+    }
+
+    fn apply_ackermann_gains(&mut self, _gains: &AckermannGains) {}
+
+    fn open_door(&mut self, _door: VehicleDoor) {}
+
+    fn close_door(&mut self, _door: VehicleDoor) {}
+
+    fn light_state(&self) -> VehicleLightState {
+        self.state.lock().unwrap().light_state
+    }
+
+    fn set_light_state(&mut self, light_state: VehicleLightState) {
+        self.state.lock().unwrap().light_state = light_state;
+    }
+
+    /// Without a physics control, the bridge assumes four wheels and the default steer
+    fn physics_control(&self) -> Option<VehiclePhysicsControl> {
+        self.state.lock().unwrap().physics_reads += 1;
+        None
+    }
+
+    fn apply_physics_control(&mut self, _physics: &VehiclePhysicsControl) {}
+
+    fn set_transform(&mut self, transform: &Isometry3<f32>) {
+        self.state.lock().unwrap().transform = *transform;
+    }
+
+    fn set_target_velocity(&mut self, velocity: &Vector3<f32>) {
+        self.state.lock().unwrap().velocity = *velocity;
+    }
+
+    fn set_target_angular_velocity(&mut self, angular_velocity: &Vector3<f32>) {
+        self.state.lock().unwrap().angular_velocity = *angular_velocity;
+    }
+}
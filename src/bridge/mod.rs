@@ -1,6 +1,11 @@
 pub mod actor_bridge;
+pub mod kinematic_vehicle;
+#[cfg(any(test, feature = "test-support"))]
+pub mod mock_vehicle;
 pub mod other_bridge;
 pub mod sensor_bridge;
 pub mod trafficlight_bridge;
 pub mod trafficsign_bridge;
+pub mod vehicle_actor;
 pub mod vehicle_bridge;
+pub mod walker_bridge;
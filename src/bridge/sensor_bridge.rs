@@ -1,6 +1,30 @@
-use super::actor_bridge::ActorBridge;
+use super::{
+    actor_bridge::{ActorBridge, BridgeDescription, BridgeKind, DeclaredKeys, TickSnapshot},
+    vehicle_bridge::TELEPORT_DISTANCE,
+};
 use crate::{
+    autoware_type::{
+        diagnostic_status, DiagnosticArray, DiagnosticStatus, KeyValue, Pose, PoseWithCovariance,
+        PoseWithCovarianceStamped,
+    },
+    cdr_utils::{self, Endianness},
+    collision::{self, CollisionHistory},
+    compression::{self, Compression},
+    config::BridgeConfig,
+    control, depth,
     error::{Error, Result},
+    frame_queue::FrameSender,
+    geo_reference::GeoReference,
+    namespace::{self, NamespaceRegistry},
+    radar::{self, RadarFormat, RadarPoint},
+    reset::ResetReport,
+    sensor_frames::SensorFrames,
+    sensor_noise::{GnssConfig, GnssNoise, ImuNoise, NoiseRng},
+    sensor_pool::SensorPool,
+    stats::STATS_INTERVAL_SEC,
+    status_publisher::StatusPublisher,
+    time::TimeStamp,
+    topics,
     types::{GnssService, GnssStatus, PointFieldType},
     utils,
 };
@@ -10,43 +34,54 @@ use carla::{
     prelude::*,
     sensor::{
         data::{
-            Color, GnssMeasurement, Image as CarlaImage, ImuMeasurement, LidarDetection,
-            LidarMeasurement, SemanticLidarDetection, SemanticLidarMeasurement,
+            CollisionEvent as CarlaCollisionEvent, Color, GnssMeasurement, Image as CarlaImage,
+            ImuMeasurement, LidarDetection, LidarMeasurement, RadarMeasurement,
+            SemanticLidarDetection, SemanticLidarMeasurement,
         },
-        SensorDataBase,
+        SensorData, SensorDataBase,
     },
 };
-use cdr::{CdrLe, Infinite};
 use log::{error, info, warn};
-use nalgebra::{coordinates::XYZ, UnitQuaternion};
+use nalgebra::{coordinates::XYZ, UnitQuaternion, Vector3};
 use std::{
     convert::Infallible,
     mem,
     str::FromStr,
-    sync::{
-        mpsc::{self, Receiver, Sender},
-        Arc,
-    },
-    thread,
+    sync::{Arc, Mutex},
 };
-use zenoh::prelude::sync::*;
+use zenoh::{prelude::sync::*, publication::Publisher, queryable::Queryable};
 use zenoh_ros_type::{sensor_msgs, std_msgs};
 
+/// Added to the altitude of CARLA in the published fixes (m)
+const GNSS_ALTITUDE_OFFSET: f64 = 17.0;
+/// The variance of the roll, pitch and yaw of the GNSS pose, which has no orientation (rad^2)
+const GNSS_ORIENTATION_VARIANCE: f64 = 1e4;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum SensorType {
     CameraRgb,
+    CameraDepth,
     LidarRayCast,
     LidarRayCastSemantic,
     Imu,
     Gnss,
+    Radar,
     Collision,
     NotSupport,
 }
 
-enum MessageType {
-    StopThread,
-    SensorData,
-    InfoData,
+/// What a callback hands to the publishing thread, which converts and serializes it
+enum Frame {
+    Image(std_msgs::Header, CarlaImage),
+    Depth(std_msgs::Header, CarlaImage),
+    Lidar(std_msgs::Header, LidarMeasurement),
+    SemanticLidar(std_msgs::Header, SemanticLidarMeasurement),
+    Imu(std_msgs::Header, ImuMeasurement),
+    /// With the yaw of the sensor in ROS (rad)
+    Gnss(std_msgs::Header, GnssMeasurement, f64),
+    Radar(std_msgs::Header, RadarMeasurement),
+    /// Already serialized, e.g. the collision history
+    Encoded(Vec<u8>),
 }
 
 impl FromStr for SensorType {
@@ -55,10 +90,12 @@ impl FromStr for SensorType {
     fn from_str(type_id: &str) -> Result<Self, Self::Err> {
         Ok(match type_id {
             "sensor.camera.rgb" => SensorType::CameraRgb,
+            "sensor.camera.depth" => SensorType::CameraDepth,
             "sensor.lidar.ray_cast" => SensorType::LidarRayCast,
             "sensor.lidar.ray_cast_semantic" => SensorType::LidarRayCastSemantic,
             "sensor.other.imu" => SensorType::Imu,
             "sensor.other.gnss" => SensorType::Gnss,
+            "sensor.other.radar" => SensorType::Radar,
             "sensor.other.collision" => SensorType::Collision,
             _ => SensorType::NotSupport,
         })
@@ -67,116 +104,399 @@ impl FromStr for SensorType {
 
 pub struct SensorBridge {
     sensor_type: SensorType,
-    _actor: Sensor,
+    actor: Sensor,
     sensor_name: String,
-    tx: Sender<(MessageType, Vec<u8>)>,
+    /// None for an unsupported sensor
+    tx: Option<FrameSender<Frame>>,
+    collision: Option<CollisionMonitor>,
+    /// None for the collision and the unsupported sensors, which have no regular events
+    frame_monitor: Option<FrameMonitor>,
+    vehicle_name: String,
+    sensor_type_id: String,
+    /// The role_name before the sanitization
+    raw_sensor_name: Option<String>,
+    declared: DeclaredKeys,
+    namespaces: Arc<Mutex<NamespaceRegistry>>,
+}
+
+/// The collision history of the parent vehicle, published once per second and after every collision
+struct CollisionMonitor {
+    history: Arc<Mutex<CollisionHistory>>,
+    last_publish: f64,
+    last_location: Option<Vector3<f32>>,
+    tx: FrameSender<Frame>,
+    _queryable_reset: Queryable<'static, ()>,
+}
+
+/// Warn in the diagnostics of the vehicle when the events of the sensor fall behind the world
+struct FrameMonitor {
+    frames: SensorFrames,
+    max_lag: u64,
+    /// At the last tick, None before the first event
+    lag: Option<u64>,
+    last_publish: f64,
+    /// None without the diagnostics of the vehicle
+    publisher: Option<StatusPublisher>,
+    endianness: Endianness,
+}
+
+impl FrameMonitor {
+    fn check(&mut self, tick: &TickSnapshot, vehicle_name: &str, sensor_name: &str) -> Result<()> {
+        let was_lagging = self.is_lagging();
+        self.lag = self.frames.lag(tick.frame);
+        match self.lag {
+            Some(lag) if self.is_lagging() && !was_lagging => {
+                warn!("'{sensor_name}' of '{vehicle_name}' is {lag} frames behind the world")
+            }
+            _ if was_lagging && !self.is_lagging() => {
+                info!("'{sensor_name}' of '{vehicle_name}' caught up with the world")
+            }
+            _ => {}
+        }
+
+        let timestamp = tick.stamp.to_secs_f64();
+        if timestamp - self.last_publish < STATS_INTERVAL_SEC {
+            return Ok(());
+        }
+        self.last_publish = timestamp;
+        let Some(publisher) = &self.publisher else {
+            return Ok(());
+        };
+        let (level, message) = match self.lag {
+            None => (diagnostic_status::OK, String::from("no event yet")),
+            Some(lag) if self.is_lagging() => (
+                diagnostic_status::WARN,
+                format!("{lag} frames behind the world"),
+            ),
+            Some(_) => (diagnostic_status::OK, String::from("OK")),
+        };
+        let mut values = vec![
+            (
+                "lag",
+                self.lag.map(|lag| lag.to_string()).unwrap_or_default(),
+            ),
+            ("max_lag", self.max_lag.to_string()),
+            ("interval", self.frames.interval().to_string()),
+        ];
+        if let Some((frame, stamp)) = self.frames.latest() {
+            values.push(("latest_frame", frame.to_string()));
+            values.push(("latest_stamp", format!("{stamp:.3}")));
+        }
+        // The stamps of the sensor and of the world snapshot, which the vehicle topics use
+        if let Some(stamp) = self.frames.stamp(tick.frame) {
+            values.push((
+                "tick_offset_ms",
+                format!("{:.3}", (stamp - timestamp) * 1e3),
+            ));
+        }
+        let diagnostics_msg = DiagnosticArray {
+            header: utils::ros_header(tick.stamp, ""),
+            status: vec![DiagnosticStatus {
+                level,
+                name: format!("carla_bridge: {vehicle_name} {sensor_name} frames"),
+                message,
+                hardware_id: vehicle_name.to_string(),
+                values: values
+                    .into_iter()
+                    .map(|(key, value)| KeyValue {
+                        key: key.to_string(),
+                        value,
+                    })
+                    .collect(),
+            }],
+        };
+        cdr_utils::put(publisher, &diagnostics_msg, self.endianness)?;
+        Ok(())
+    }
+
+    fn is_lagging(&self) -> bool {
+        matches!(self.lag, Some(lag) if lag > self.max_lag)
+    }
 }
 
 impl SensorBridge {
-    pub fn new(z_session: Arc<Session>, actor: Sensor) -> Result<SensorBridge> {
+    pub fn new(
+        z_session: Arc<Session>,
+        actor: Sensor,
+        config: &BridgeConfig,
+        namespaces: Arc<Mutex<NamespaceRegistry>>,
+        pool: &SensorPool,
+    ) -> Result<SensorBridge> {
         let sensor_id = actor.id();
         let sensor_type_id = actor.type_id();
 
-        let mut vehicle_name = actor
-            .parent()
-            .ok_or(Error::OwnerlessSensor { sensor_id })?
+        let parent = actor.parent().ok_or(Error::OwnerlessSensor { sensor_id })?;
+        let role_name = parent
             .attributes()
             .iter()
             .find(|attr| attr.id() == "role_name")
-            .expect("'role_name' attribute is missing")
-            .value_string();
-        let sensor_name = actor
+            .map(|attr| attr.value_string())
+            .unwrap_or_default();
+        let raw_sensor_name = actor
             .attributes()
             .iter()
             .find(|attr| attr.id() == "role_name")
-            .map(|attr| attr.value_string())
+            .map(|attr| attr.value_string());
+        let sensor_name = raw_sensor_name
+            .as_deref()
+            .map(namespace::sanitize)
+            .filter(|sensor_name| !sensor_name.is_empty())
             .unwrap_or_else(|| generate_sensor_name(&actor));
 
-        // Remove "autoware_" in role name
-        if !vehicle_name.starts_with("autoware_") {
+        // Only the vehicles with the prefix ("autoware_" by default) in role name are bridged
+        let Some(name) = config.bridged_name(&role_name) else {
             return Err(Error::Npc {
-                npc_role_name: vehicle_name,
+                npc_role_name: role_name,
             });
-        } else {
-            vehicle_name = vehicle_name.replace("autoware_", "");
-        }
+        };
+        // The same namespace as the vehicle bridge
+        let (vehicle_name, sensor_name) = {
+            let mut registry = namespaces.lock().unwrap();
+            (
+                registry.namespace(parent.id(), name),
+                registry.sensor_name(parent.id(), sensor_id, &sensor_name),
+            )
+        };
 
         info!("Detected a sensor '{sensor_name}' on '{vehicle_name}'");
         let sensor_type: SensorType = sensor_type_id.parse().unwrap();
-        let (tx, rx) = mpsc::channel();
+        let mut tx = None;
+        let mut collision = None;
+        let mut declared = DeclaredKeys::default();
+        let frames = SensorFrames::default();
 
         match sensor_type {
             SensorType::CameraRgb => {
-                register_camera_rgb(
-                    z_session,
+                tx = Some(register_camera_rgb(
+                    z_session.clone(),
                     &actor,
                     &vehicle_name,
                     &sensor_name,
-                    tx.clone(),
-                    rx,
-                )?;
+                    pool,
+                    config,
+                    &mut declared,
+                    &frames,
+                )?);
+            }
+            SensorType::CameraDepth => {
+                tx = Some(register_camera_depth(
+                    z_session.clone(),
+                    &actor,
+                    &vehicle_name,
+                    &sensor_name,
+                    pool,
+                    config,
+                    &mut declared,
+                    &frames,
+                )?);
             }
             SensorType::LidarRayCast => {
-                register_lidar_raycast(
-                    z_session,
+                tx = Some(register_lidar_raycast(
+                    z_session.clone(),
                     &actor,
                     &vehicle_name,
                     &sensor_name,
-                    tx.clone(),
-                    rx,
-                )?;
+                    pool,
+                    config,
+                    &mut declared,
+                    &frames,
+                )?);
             }
             SensorType::LidarRayCastSemantic => {
-                register_lidar_raycast_semantic(
-                    z_session,
+                tx = Some(register_lidar_raycast_semantic(
+                    z_session.clone(),
                     &actor,
                     &vehicle_name,
                     &sensor_name,
-                    tx.clone(),
-                    rx,
-                )?;
+                    pool,
+                    config,
+                    &mut declared,
+                    &frames,
+                )?);
             }
             SensorType::Imu => {
-                register_imu(
-                    z_session,
+                tx = Some(register_imu(
+                    z_session.clone(),
                     &actor,
                     &vehicle_name,
                     &sensor_name,
-                    tx.clone(),
-                    rx,
-                )?;
+                    pool,
+                    config,
+                    &mut declared,
+                    &frames,
+                )?);
             }
             SensorType::Gnss => {
-                register_gnss(
-                    z_session,
+                tx = Some(register_gnss(
+                    z_session.clone(),
                     &actor,
                     &vehicle_name,
                     &sensor_name,
-                    tx.clone(),
-                    rx,
-                )?;
+                    pool,
+                    config,
+                    &mut declared,
+                    &frames,
+                )?);
+            }
+            SensorType::Radar => {
+                tx = Some(register_radar(
+                    z_session.clone(),
+                    &actor,
+                    &vehicle_name,
+                    &sensor_name,
+                    pool,
+                    config,
+                    &mut declared,
+                    &frames,
+                )?);
             }
             SensorType::Collision => {
-                warn!("Collision sensor is not supported yet");
+                let monitor = register_collision(
+                    z_session.clone(),
+                    &actor,
+                    &vehicle_name,
+                    pool,
+                    config,
+                    &mut declared,
+                )?;
+                tx = Some(monitor.tx.clone());
+                collision = Some(monitor);
             }
             SensorType::NotSupport => {
                 warn!("Unsupported sensor type '{sensor_type_id}'");
             }
         }
 
+        let frame_monitor = match (&tx, &collision) {
+            (Some(_), None) => {
+                let diagnostics = config
+                    .vehicle_config(&vehicle_name, &role_name)?
+                    .topic_groups
+                    .diagnostics;
+                let publisher = diagnostics
+                    .then(|| {
+                        StatusPublisher::declare(
+                            z_session.clone(),
+                            config.topics.key(&vehicle_name, topics::DIAGNOSTICS),
+                            config.redeclare_after,
+                            Arc::default(),
+                        )
+                    })
+                    .transpose()?;
+                Some(FrameMonitor {
+                    frames,
+                    max_lag: config.max_sensor_frame_lag,
+                    lag: None,
+                    last_publish: f64::MIN,
+                    publisher,
+                    endianness: config.cdr_endianness,
+                })
+            }
+            _ => None,
+        };
+
         Ok(SensorBridge {
             sensor_type,
-            _actor: actor,
+            actor,
             sensor_name,
             tx,
+            collision,
+            frame_monitor,
+            vehicle_name,
+            sensor_type_id,
+            raw_sensor_name,
+            declared,
+            namespaces,
         })
     }
 }
 
 impl ActorBridge for SensorBridge {
-    fn step(&mut self, _elapsed_sec: f64, _timestamp: f64) -> Result<()> {
+    fn step(&mut self, _elapsed_sec: f64, tick: &TickSnapshot) -> Result<()> {
+        if let Some(monitor) = &mut self.frame_monitor {
+            monitor.check(tick, &self.vehicle_name, &self.sensor_name)?;
+        }
+        let Some(collision) = &mut self.collision else {
+            return Ok(());
+        };
+        let timestamp = tick.stamp.to_secs_f64();
+        // The sensor moves with its vehicle, so a jump means the vehicle was teleported
+        let transform = match tick.motion(self.actor.id()) {
+            Some(motion) => motion.transform,
+            None => self.actor.transform(),
+        };
+        let location = transform.translation.vector;
+        let teleported = collision
+            .last_location
+            .map_or(false, |last| (location - last).norm() > TELEPORT_DISTANCE);
+        collision.last_location = Some(location);
+        let mut history = collision.history.lock().unwrap();
+        if teleported {
+            info!(
+                "Reset the collision history of '{}', since its vehicle was teleported",
+                self.sensor_name
+            );
+            history.reset();
+        }
+        if teleported || timestamp - collision.last_publish >= STATS_INTERVAL_SEC {
+            collision.last_publish = timestamp;
+            send_collision_history(&history, &collision.tx)?;
+        }
         Ok(())
     }
+
+    fn kind(&self) -> BridgeKind {
+        BridgeKind::Sensor
+    }
+
+    fn describe(&self) -> Option<BridgeDescription> {
+        (self.sensor_type != SensorType::NotSupport).then(|| BridgeDescription {
+            actor_id: self.actor.id(),
+            namespace: self.vehicle_name.clone(),
+            kind: self.sensor_type_id.clone(),
+            raw_name: self.raw_sensor_name.clone(),
+            keys: self.declared.clone(),
+        })
+    }
+
+    fn dropped_frames(&self) -> Option<(String, u64)> {
+        self.tx.as_ref().map(|tx| {
+            (
+                format!("{}/{}", self.vehicle_name, self.sensor_name),
+                tx.dropped(),
+            )
+        })
+    }
+
+    fn frame_lag(&self) -> Option<(String, u64)> {
+        let monitor = self.frame_monitor.as_ref()?;
+        Some((
+            format!("{}/{}", self.vehicle_name, self.sensor_name),
+            monitor.lag?,
+        ))
+    }
+
+    fn reset(&mut self, report: &mut ResetReport) {
+        let Some(collision) = &mut self.collision else {
+            return;
+        };
+        // The vehicle is teleported by the same reset, which isn't a new collision history
+        collision.last_location = None;
+        let mut history = collision.history.lock().unwrap();
+        history.reset();
+        report.collision_histories += 1;
+        if let Err(e) = send_collision_history(&history, &collision.tx) {
+            warn!("Failed to publish the collision history after the reset: {e}");
+        }
+    }
+}
+
+/// The header of a sensor event, always with the stamp of the event rather than of the tick.
+/// Records the frame of the event on the way.
+fn event_header(frames: &SensorFrames, data: &SensorData, frame_id: &str) -> std_msgs::Header {
+    let stamp = data.timestamp();
+    frames.record(data.frame() as u64, stamp);
+    utils::ros_header(TimeStamp::from_secs_f64(stamp), frame_id)
 }
 
 fn register_camera_rgb(
@@ -184,135 +504,185 @@ fn register_camera_rgb(
     actor: &Sensor,
     vehicle_name: &str,
     sensor_name: &str,
-    tx: Sender<(MessageType, Vec<u8>)>,
-    rx: Receiver<(MessageType, Vec<u8>)>,
-) -> Result<()> {
-    let raw_key = format!("{vehicle_name}/rt/sensing/camera/{sensor_name}/image_raw");
-    let info_key = format!("{vehicle_name}/rt/sensing/camera/{sensor_name}/camera_info");
-
-    let image_publisher = z_session.declare_publisher(raw_key.clone()).res()?;
-    let info_publisher = z_session.declare_publisher(info_key.clone()).res()?;
-    thread::spawn(move || loop {
-        match rx.recv() {
-            Ok((MessageType::SensorData, sensor_data)) => {
-                if let Err(_) = image_publisher.put(sensor_data).res() {
-                    error!("Failed to publish to {}", raw_key);
-                }
+    pool: &SensorPool,
+    config: &BridgeConfig,
+    declared: &mut DeclaredKeys,
+    frames: &SensorFrames,
+) -> Result<FrameSender<Frame>> {
+    let endianness = config.cdr_endianness;
+    let topic_map = &config.topics;
+    let raw_key = topic_map.sensor_key(vehicle_name, topics::CAMERA_IMAGE, sensor_name);
+    let info_key = topic_map.sensor_key(vehicle_name, topics::CAMERA_INFO, sensor_name);
+
+    let image_publisher = topics::declare_publisher(&z_session, &raw_key)?;
+    let info_publisher = topics::declare_publisher(&z_session, &info_key)?;
+    declared
+        .publishers
+        .extend([raw_key.clone(), info_key.clone()]);
+    let (width, height, fov) = camera_attributes(actor);
+    let tx = pool.add(raw_key.clone(), config.sensor_queue_depth, move |frame| {
+        if let Frame::Image(header, image) = frame {
+            let info =
+                camera_info_payload(&info_key, header.clone(), width, height, fov, endianness);
+            publish(
+                &image_publisher,
+                &raw_key,
+                camera_payload(&raw_key, header, image, endianness),
+            );
+            publish(&info_publisher, &info_key, info.map(Some));
+        }
+    });
+
+    let callback_tx = tx.clone();
+    let frames = frames.clone();
+    actor.listen(move |data| {
+        let header = event_header(&frames, &data, "camera4/camera_link");
+        callback_tx.push(Frame::Image(header, data.try_into().unwrap()));
+    });
+
+    Ok(tx)
+}
+
+fn register_camera_depth(
+    z_session: Arc<Session>,
+    actor: &Sensor,
+    vehicle_name: &str,
+    sensor_name: &str,
+    pool: &SensorPool,
+    config: &BridgeConfig,
+    declared: &mut DeclaredKeys,
+    frames: &SensorFrames,
+) -> Result<FrameSender<Frame>> {
+    let endianness = config.cdr_endianness;
+    let format = config.depth_format;
+    let topic_map = &config.topics;
+    let raw_key = topic_map.sensor_key(vehicle_name, topics::DEPTH_IMAGE, sensor_name);
+    let compressed_key = topic_map.sensor_key(vehicle_name, topics::DEPTH_COMPRESSED, sensor_name);
+    let info_key = topic_map.sensor_key(vehicle_name, topics::DEPTH_INFO, sensor_name);
+
+    let mut declare = |key: &str| -> Result<_> {
+        let publisher = topics::declare_publisher(&z_session, key)?;
+        declared.publishers.push(key.to_string());
+        Ok(publisher)
+    };
+    let raw_publisher = format.raw().then(|| declare(&raw_key)).transpose()?;
+    let compressed_publisher = format
+        .compressed()
+        .then(|| declare(&compressed_key))
+        .transpose()?;
+    let info_publisher = declare(&info_key)?;
+    let (width, height, fov) = camera_attributes(actor);
+    let tx = pool.add(info_key.clone(), config.sensor_queue_depth, move |frame| {
+        if let Frame::Depth(header, image) = frame {
+            let depth = depth_meters(&image);
+            if depth.is_empty() {
+                return;
             }
-            Ok((MessageType::InfoData, info_data)) => {
-                if let Err(_) = info_publisher.put(info_data).res() {
-                    error!("Failed to publish to {}", info_key);
-                }
+            let (width, height) = (image.width() as u32, image.height() as u32);
+            if let Some(publisher) = &compressed_publisher {
+                let payload = depth::compressed_depth(header.clone(), width, height, &depth)
+                    .and_then(|image| {
+                        cdr_utils::serialize_for(&compressed_key, &image, endianness)
+                    });
+                publish(publisher, &compressed_key, payload.map(Some));
             }
-            _ => {
-                // If tx is released, then the thread will stop
-                info!("Sensor actor thread for {} stop.", raw_key);
-                break;
+            if let Some(publisher) = &raw_publisher {
+                let payload =
+                    depth_payload(&raw_key, header.clone(), width, height, &depth, endianness);
+                publish(publisher, &raw_key, payload.map(Some));
             }
+            let info = camera_info_payload(&info_key, header, width, height, fov, endianness);
+            publish(&info_publisher, &info_key, info.map(Some));
         }
     });
-    let width = actor
-        .attributes()
-        .iter()
-        .find(|attr| attr.id() == "image_size_x")
-        .unwrap()
-        .value()
-        .unwrap()
-        .try_into_int()
-        .unwrap() as u32;
-    let height = actor
-        .attributes()
-        .iter()
-        .find(|attr| attr.id() == "image_size_y")
-        .unwrap()
-        .value()
-        .unwrap()
-        .try_into_int()
-        .unwrap() as u32;
-    let fov = actor
-        .attributes()
-        .iter()
-        .find(|attr| attr.id() == "fov")
-        .unwrap()
-        .value()
-        .unwrap()
-        .try_into_f32()
-        .unwrap() as f64;
-
+    let callback_tx = tx.clone();
+    let frames = frames.clone();
     actor.listen(move |data| {
-        let mut header = utils::create_ros_header(Some(data.timestamp())).unwrap();
-        header.frame_id = String::from("camera4/camera_link");
-        camera_callback(header.clone(), data.try_into().unwrap(), &tx).unwrap();
-        camera_info_callback(header, width, height, fov, &tx).unwrap();
+        let header = event_header(&frames, &data, "camera4/camera_link");
+        callback_tx.push(Frame::Depth(header, data.try_into().unwrap()));
     });
 
-    Ok(())
+    Ok(tx)
 }
 
 fn register_lidar_raycast(
     z_session: Arc<Session>,
     actor: &Sensor,
     vehicle_name: &str,
-    _sensor_name: &str,
-    tx: Sender<(MessageType, Vec<u8>)>,
-    rx: Receiver<(MessageType, Vec<u8>)>,
-) -> Result<()> {
-    let key = format!("{vehicle_name}/rt/carla_pointcloud");
-    let pcd_publisher = z_session.declare_publisher(key.clone()).res()?;
-    thread::spawn(move || loop {
-        match rx.recv() {
-            Ok((MessageType::SensorData, sensor_data)) => {
-                if let Err(_) = pcd_publisher.put(sensor_data).res() {
-                    error!("Failed to publish to {}", key);
-                }
-            }
-            _ => {
-                // If tx is released, then the thread will stop
-                info!("Sensor actor thread for {} stop.", key);
-                break;
+    sensor_name: &str,
+    pool: &SensorPool,
+    config: &BridgeConfig,
+    declared: &mut DeclaredKeys,
+    frames: &SensorFrames,
+) -> Result<FrameSender<Frame>> {
+    let endianness = config.cdr_endianness;
+    let topic_map = &config.topics;
+    let key = topic_map.key(vehicle_name, topics::LIDAR_POINTCLOUD);
+    let pcd_publisher = topics::declare_publisher(&z_session, &key)?;
+    declared.publishers.push(key.clone());
+    let compressed = CompressedPublisher::declare(
+        &z_session,
+        &key,
+        config.lidar_compression(sensor_name),
+        declared,
+    )?;
+    let tx = pool.add(key.clone(), config.sensor_queue_depth, move |frame| {
+        if let Frame::Lidar(header, measure) = frame {
+            let payload = lidar_payload(&key, header, measure, endianness);
+            if let (Some(compressed), Ok(Some(payload))) = (&compressed, &payload) {
+                compressed.publish(payload);
             }
+            publish(&pcd_publisher, &key, payload);
         }
     });
+    let callback_tx = tx.clone();
+    let frames = frames.clone();
     actor.listen(move |data| {
-        let mut header = utils::create_ros_header(Some(data.timestamp())).unwrap();
-        header.frame_id = String::from("velodyne_top");
-        lidar_callback(header, data.try_into().unwrap(), &tx).unwrap();
+        let header = event_header(&frames, &data, "velodyne_top");
+        callback_tx.push(Frame::Lidar(header, data.try_into().unwrap()));
     });
 
-    Ok(())
+    Ok(tx)
 }
 
 fn register_lidar_raycast_semantic(
     z_session: Arc<Session>,
     actor: &Sensor,
     vehicle_name: &str,
-    _sensor_name: &str,
-    tx: Sender<(MessageType, Vec<u8>)>,
-    rx: Receiver<(MessageType, Vec<u8>)>,
-) -> Result<()> {
-    let key = format!("{vehicle_name}/rt/carla_pointcloud");
-    let pcd_publisher = z_session.declare_publisher(key.clone()).res()?;
-    thread::spawn(move || loop {
-        match rx.recv() {
-            Ok((MessageType::SensorData, sensor_data)) => {
-                if let Err(_) = pcd_publisher.put(sensor_data).res() {
-                    error!("Failed to publish to {}", key);
-                }
-            }
-            _ => {
-                // If tx is released, then the thread will stop
-                info!("Sensor actor thread for {} stop.", key);
-                break;
+    sensor_name: &str,
+    pool: &SensorPool,
+    config: &BridgeConfig,
+    declared: &mut DeclaredKeys,
+    frames: &SensorFrames,
+) -> Result<FrameSender<Frame>> {
+    let endianness = config.cdr_endianness;
+    let topic_map = &config.topics;
+    let key = topic_map.key(vehicle_name, topics::LIDAR_POINTCLOUD);
+    let pcd_publisher = topics::declare_publisher(&z_session, &key)?;
+    declared.publishers.push(key.clone());
+    let compressed = CompressedPublisher::declare(
+        &z_session,
+        &key,
+        config.lidar_compression(sensor_name),
+        declared,
+    )?;
+    let tx = pool.add(key.clone(), config.sensor_queue_depth, move |frame| {
+        if let Frame::SemanticLidar(header, measure) = frame {
+            let payload = senmatic_lidar_payload(&key, header, measure, endianness);
+            if let (Some(compressed), Ok(Some(payload))) = (&compressed, &payload) {
+                compressed.publish(payload);
             }
+            publish(&pcd_publisher, &key, payload);
         }
     });
+    let callback_tx = tx.clone();
+    let frames = frames.clone();
     actor.listen(move |data| {
-        let mut header = utils::create_ros_header(Some(data.timestamp())).unwrap();
-        header.frame_id = String::from("velodyne_top");
-        senmatic_lidar_callback(header, data.try_into().unwrap(), &tx).unwrap();
+        let header = event_header(&frames, &data, "velodyne_top");
+        callback_tx.push(Frame::SemanticLidar(header, data.try_into().unwrap()));
     });
 
-    Ok(())
+    Ok(tx)
 }
 
 fn register_imu(
@@ -320,31 +690,34 @@ fn register_imu(
     actor: &Sensor,
     vehicle_name: &str,
     sensor_name: &str,
-    tx: Sender<(MessageType, Vec<u8>)>,
-    rx: Receiver<(MessageType, Vec<u8>)>,
-) -> Result<()> {
-    let key = format!("{vehicle_name}/rt/sensing/imu/{sensor_name}/imu_raw");
-    let imu_publisher = z_session.declare_publisher(key.clone()).res()?;
-    thread::spawn(move || loop {
-        match rx.recv() {
-            Ok((MessageType::SensorData, sensor_data)) => {
-                if let Err(_) = imu_publisher.put(sensor_data).res() {
-                    error!("Failed to publish to {}", key);
-                }
-            }
-            _ => {
-                // If tx is released, then the thread will stop
-                info!("Sensor actor thread for {} stop.", key);
-                break;
-            }
+    pool: &SensorPool,
+    config: &BridgeConfig,
+    declared: &mut DeclaredKeys,
+    frames: &SensorFrames,
+) -> Result<FrameSender<Frame>> {
+    let endianness = config.cdr_endianness;
+    let topic_map = &config.topics;
+    let key = topic_map.sensor_key(vehicle_name, topics::IMU, sensor_name);
+    let imu_publisher = topics::declare_publisher(&z_session, &key)?;
+    declared.publishers.push(key.clone());
+    // Only the worker of the sensor draws the noise, in the order of the measurements
+    let mut noise = ImuNoise::new(config.imu, NoiseRng::new(config.noise_seed, &key));
+    let tx = pool.add(key.clone(), config.sensor_queue_depth, move |frame| {
+        if let Frame::Imu(header, measure) = frame {
+            publish(
+                &imu_publisher,
+                &key,
+                imu_payload(&key, header, measure, &mut noise, endianness),
+            );
         }
     });
+    let callback_tx = tx.clone();
+    let frames = frames.clone();
     actor.listen(move |data| {
-        let mut header = utils::create_ros_header(Some(data.timestamp())).unwrap();
-        header.frame_id = String::from("tamagawa/imu_link");
-        imu_callback(header, data.try_into().unwrap(), &tx).unwrap();
+        let header = event_header(&frames, &data, "tamagawa/imu_link");
+        callback_tx.push(Frame::Imu(header, data.try_into().unwrap()));
     });
-    Ok(())
+    Ok(tx)
 }
 
 fn register_gnss(
@@ -352,41 +725,240 @@ fn register_gnss(
     actor: &Sensor,
     vehicle_name: &str,
     sensor_name: &str,
-    tx: Sender<(MessageType, Vec<u8>)>,
-    rx: Receiver<(MessageType, Vec<u8>)>,
-) -> Result<()> {
-    let key = format!("{vehicle_name}/rt/sensing/gnss/{sensor_name}/nav_sat_fix");
-    let gnss_publisher = z_session.declare_publisher(key.clone()).res()?;
-    thread::spawn(move || loop {
-        match rx.recv() {
-            Ok((MessageType::SensorData, sensor_data)) => {
-                if let Err(_) = gnss_publisher.put(sensor_data).res() {
-                    error!("Failed to publish to {}", key);
-                }
-            }
-            _ => {
-                // If tx is released, then the thread will stop
-                info!("Sensor actor thread for {} stop.", key);
-                break;
+    pool: &SensorPool,
+    config: &BridgeConfig,
+    declared: &mut DeclaredKeys,
+    frames: &SensorFrames,
+) -> Result<FrameSender<Frame>> {
+    let endianness = config.cdr_endianness;
+    let topic_map = &config.topics;
+    let key = topic_map.sensor_key(vehicle_name, topics::GNSS, sensor_name);
+    let gnss_publisher = topics::declare_publisher(&z_session, &key)?;
+    declared.publishers.push(key.clone());
+    let gnss_config = config.gnss;
+    let pose_publisher = match gnss_config.publish_pose.then(|| map_geo_reference(actor)) {
+        Some(Some(geo_reference)) => {
+            let key = topic_map.sensor_key(vehicle_name, topics::GNSS_POSE, sensor_name);
+            let publisher = topics::declare_publisher(&z_session, &key)?;
+            declared.publishers.push(key.clone());
+            Some((publisher, key, geo_reference))
+        }
+        Some(None) => {
+            warn!(
+                "The OpenDRIVE of the map has no geoReference with +lat_0 and +lon_0. \
+                 '{sensor_name}' of '{vehicle_name}' doesn't publish its pose in the map frame"
+            );
+            None
+        }
+        None => None,
+    };
+    let mut noise = GnssNoise::new(gnss_config, NoiseRng::new(config.noise_seed, &key));
+    let tx = pool.add(key.clone(), config.sensor_queue_depth, move |frame| {
+        if let Frame::Gnss(header, measure, yaw) = frame {
+            let fix = gnss_fix(header, measure, &mut noise);
+            if let Some((publisher, pose_key, geo_reference)) = &pose_publisher {
+                publish(
+                    publisher,
+                    pose_key,
+                    gnss_pose_payload(pose_key, &fix, yaw, geo_reference, &gnss_config, endianness),
+                );
             }
+            publish(
+                &gnss_publisher,
+                &key,
+                cdr_utils::serialize_for(&key, &fix, endianness).map(Some),
+            );
         }
     });
+    let callback_tx = tx.clone();
+    let frames = frames.clone();
     actor.listen(move |data| {
-        let mut header = utils::create_ros_header(Some(data.timestamp())).unwrap();
-        header.frame_id = String::from("gnss_link");
-        gnss_callback(header, data.try_into().unwrap(), &tx).unwrap();
+        let header = event_header(&frames, &data, "gnss_link");
+        let (_, _, yaw) = data.transform().rotation.euler_angles();
+        callback_tx.push(Frame::Gnss(header, data.try_into().unwrap(), -yaw as f64));
     });
+    Ok(tx)
+}
+
+fn register_radar(
+    z_session: Arc<Session>,
+    actor: &Sensor,
+    vehicle_name: &str,
+    sensor_name: &str,
+    pool: &SensorPool,
+    config: &BridgeConfig,
+    declared: &mut DeclaredKeys,
+    frames: &SensorFrames,
+) -> Result<FrameSender<Frame>> {
+    let endianness = config.cdr_endianness;
+    let format = config.radar_format;
+    let topic = match format {
+        RadarFormat::RadarScan => topics::RADAR_SCAN,
+        RadarFormat::PointCloud => topics::RADAR_POINTCLOUD,
+    };
+    let key = config.topics.sensor_key(vehicle_name, topic, sensor_name);
+    let radar_publisher = topics::declare_publisher(&z_session, &key)?;
+    declared.publishers.push(key.clone());
+    let tx = pool.add(key.clone(), config.sensor_queue_depth, move |frame| {
+        if let Frame::Radar(header, measure) = frame {
+            let points: Vec<_> = measure
+                .as_slice()
+                .iter()
+                .map(RadarPoint::from_detection)
+                .collect();
+            let payload = match format {
+                RadarFormat::RadarScan => {
+                    cdr_utils::serialize_for(&key, &radar::radar_scan(header, &points), endianness)
+                }
+                RadarFormat::PointCloud => {
+                    cdr_utils::serialize_for(&key, &radar::point_cloud(header, &points), endianness)
+                }
+            };
+            publish(&radar_publisher, &key, payload.map(Some));
+        }
+    });
+    let callback_tx = tx.clone();
+    let frames = frames.clone();
+    actor.listen(move |data| {
+        let header = event_header(&frames, &data, "radar_link");
+        callback_tx.push(Frame::Radar(header, data.try_into().unwrap()));
+    });
+    Ok(tx)
+}
+
+fn register_collision(
+    z_session: Arc<Session>,
+    actor: &Sensor,
+    vehicle_name: &str,
+    pool: &SensorPool,
+    config: &BridgeConfig,
+    declared: &mut DeclaredKeys,
+) -> Result<CollisionMonitor> {
+    let topic_map = &config.topics;
+    let key = topic_map.key(vehicle_name, topics::COLLISION_HISTORY);
+    let collision_publisher = topics::declare_publisher(&z_session, &key)?;
+    declared.publishers.push(key.clone());
+    let tx = pool.add(key.clone(), config.sensor_queue_depth, move |frame| {
+        if let Frame::Encoded(payload) = frame {
+            publish(&collision_publisher, &key, Ok(Some(payload)));
+        }
+    });
+
+    let history = Arc::new(Mutex::new(CollisionHistory::default()));
+    let cloned_history = history.clone();
+    let callback_tx = tx.clone();
+    actor.listen(move |data| {
+        let stamp = data.timestamp();
+        let event: CarlaCollisionEvent = data.try_into().unwrap();
+        let other_actor = event.other_actor();
+        let mut history = cloned_history.lock().unwrap();
+        history.record(collision::CollisionEvent {
+            stamp,
+            other_actor_id: other_actor.as_ref().map(|other| other.id()),
+            other_type_id: other_actor.map(|other| other.type_id()),
+            impulse: event.normal_impulse().norm() as f64,
+        });
+        send_collision_history(&history, &callback_tx).unwrap();
+    });
+
+    // Reply with the history before clearing it, e.g. to grade a finished run
+    let cloned_history = history.clone();
+    let reset_key = topic_map.key(vehicle_name, topics::COLLISION_HISTORY_RESET);
+    declared.queryables.push(reset_key.clone());
+    let queryable_reset = z_session
+        .declare_queryable(reset_key)
+        .callback(move |query| {
+            let mut history = cloned_history.lock().unwrap();
+            match serde_json::to_vec(&*history) {
+                Ok(payload) => {
+                    let sample = Sample::new(query.key_expr().clone(), payload);
+                    if let Err(e) = query.reply(Ok(sample)).res() {
+                        warn!("Failed to reply to the collision history reset: {e}");
+                    }
+                }
+                Err(e) => warn!("Failed to encode the collision history: {e}"),
+            }
+            history.reset();
+        })
+        .res()?;
+
+    Ok(CollisionMonitor {
+        history,
+        last_publish: f64::NEG_INFINITY,
+        last_location: None,
+        tx,
+        _queryable_reset: queryable_reset,
+    })
+}
+
+fn send_collision_history(history: &CollisionHistory, tx: &FrameSender<Frame>) -> Result<()> {
+    tx.push(Frame::Encoded(serde_json::to_vec(history)?));
     Ok(())
 }
 
-fn camera_callback(
+/// The point clouds of a lidar with a compression, on `<key>/compressed`
+struct CompressedPublisher {
+    publisher: Publisher<'static>,
+    key: String,
+    compression: Compression,
+}
+
+impl CompressedPublisher {
+    fn declare(
+        z_session: &Arc<Session>,
+        key: &str,
+        compression: Compression,
+        declared: &mut DeclaredKeys,
+    ) -> Result<Option<CompressedPublisher>> {
+        if compression == Compression::None {
+            return Ok(None);
+        }
+        let key = format!("{key}/compressed");
+        let publisher = topics::declare_publisher(z_session, &key)?;
+        declared.publishers.push(key.clone());
+        info!("Publishing the point clouds compressed with {compression} on {key}");
+        Ok(Some(CompressedPublisher {
+            publisher,
+            key,
+            compression,
+        }))
+    }
+
+    /// Compress the serialized message and publish it, on the worker of the lidar
+    fn publish(&self, payload: &[u8]) {
+        match compression::compress(self.compression, payload) {
+            Ok(frame) => {
+                if let Err(_) = self.publisher.put(frame).res() {
+                    error!("Failed to publish to {}", self.key);
+                }
+            }
+            Err(e) => error!("Failed to compress the point cloud of {}: {e}", self.key),
+        }
+    }
+}
+
+/// Publish the payload of a frame, none if the frame had no data
+fn publish(publisher: &Publisher, key: &str, payload: Result<Option<Vec<u8>>>) {
+    match payload {
+        Ok(Some(payload)) => {
+            if let Err(_) = publisher.put(payload).res() {
+                error!("Failed to publish to {}", key);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => error!("Failed to serialize the message of {key}: {e}"),
+    }
+}
+
+fn camera_payload(
+    key: &str,
     header: std_msgs::Header,
     image: CarlaImage,
-    tx: &Sender<(MessageType, Vec<u8>)>,
-) -> Result<()> {
+    endianness: Endianness,
+) -> Result<Option<Vec<u8>>> {
     let image_data = image.as_slice();
     if image_data.is_empty() {
-        return Ok(());
+        return Ok(None);
     }
     let width = image.width();
     let height = image.height();
@@ -405,20 +977,52 @@ fn camera_callback(
         data,
     };
 
-    let encoded = cdr::serialize::<_, _, CdrLe>(&image_msg, Infinite)?;
-    if let Err(_) = tx.send((MessageType::SensorData, encoded)) {
-        error!("Failed to send message");
-    }
-    Ok(())
+    Ok(Some(cdr_utils::serialize_for(key, &image_msg, endianness)?))
+}
+
+/// The depth of each pixel (m), which CARLA encodes in the B, G and R channels
+fn depth_meters(image: &CarlaImage) -> Vec<f32> {
+    image
+        .as_slice()
+        .iter()
+        .map(|&Color { b, g, r, .. }| {
+            let normalized = (r as f32 + g as f32 * 256.0 + b as f32 * 65536.0) / 16_777_215.0;
+            normalized * 1000.0
+        })
+        .collect()
+}
+
+fn depth_payload(
+    key: &str,
+    header: std_msgs::Header,
+    width: u32,
+    height: u32,
+    depth: &[f32],
+    endianness: Endianness,
+) -> Result<Vec<u8>> {
+    let image_msg = sensor_msgs::Image {
+        header,
+        height,
+        width,
+        encoding: "32FC1".to_string(),
+        is_bigendian: utils::is_bigendian().into(),
+        step: width * 4,
+        data: depth
+            .iter()
+            .flat_map(|meters| meters.to_ne_bytes())
+            .collect(),
+    };
+    cdr_utils::serialize_for(key, &image_msg, endianness)
 }
 
-fn camera_info_callback(
+fn camera_info_payload(
+    key: &str,
     header: std_msgs::Header,
     width: u32,
     height: u32,
     fov: f64,
-    tx: &Sender<(MessageType, Vec<u8>)>,
-) -> Result<()> {
+    endianness: Endianness,
+) -> Result<Vec<u8>> {
     let cx = width as f64 / 2.0;
     let cy = height as f64 / 2.0;
     let fx = width as f64 / (2.0 * (fov * std::f64::consts::PI / 360.0).tan());
@@ -444,21 +1048,18 @@ fn camera_info_callback(
         },
     };
 
-    let encoded = cdr::serialize::<_, _, CdrLe>(&camera_info, Infinite)?;
-    if let Err(_) = tx.send((MessageType::InfoData, encoded)) {
-        error!("Failed to send message");
-    }
-    Ok(())
+    cdr_utils::serialize_for(key, &camera_info, endianness)
 }
 
-fn lidar_callback(
+fn lidar_payload(
+    key: &str,
     header: std_msgs::Header,
     measure: LidarMeasurement,
-    tx: &Sender<(MessageType, Vec<u8>)>,
-) -> Result<()> {
+    endianness: Endianness,
+) -> Result<Option<Vec<u8>>> {
     let lidar_data = measure.as_slice();
     if lidar_data.is_empty() {
-        return Ok(());
+        return Ok(None);
     }
     let point_step = mem::size_of_val(&lidar_data[0]) as u32;
     let data: Vec<_> = lidar_data
@@ -510,21 +1111,18 @@ fn lidar_callback(
         data,
         is_dense: true,
     };
-    let encoded = cdr::serialize::<_, _, CdrLe>(&lidar_msg, Infinite)?;
-    if let Err(_) = tx.send((MessageType::SensorData, encoded)) {
-        error!("Failed to send message");
-    }
-    Ok(())
+    Ok(Some(cdr_utils::serialize_for(key, &lidar_msg, endianness)?))
 }
 
-fn senmatic_lidar_callback(
+fn senmatic_lidar_payload(
+    key: &str,
     header: std_msgs::Header,
     measure: SemanticLidarMeasurement,
-    tx: &Sender<(MessageType, Vec<u8>)>,
-) -> Result<()> {
+    endianness: Endianness,
+) -> Result<Option<Vec<u8>>> {
     let lidar_data = measure.as_slice();
     if lidar_data.is_empty() {
-        return Ok(());
+        return Ok(None);
     }
     let point_step = mem::size_of_val(&lidar_data[0]) as u32;
     let row_step = lidar_data.len() as u32;
@@ -598,17 +1196,13 @@ fn senmatic_lidar_callback(
         data,
         is_dense: true,
     };
-    let encoded = cdr::serialize::<_, _, CdrLe>(&lidar_msg, Infinite)?;
-    if let Err(_) = tx.send((MessageType::SensorData, encoded)) {
-        error!("Failed to send message");
-    }
-    Ok(())
+    Ok(Some(cdr_utils::serialize_for(key, &lidar_msg, endianness)?))
 }
 
 /* TODO: Temporarily solution, since r2r generates wrong IMU message type */
 use serde_derive::{Deserialize, Serialize};
 #[derive(Serialize, Deserialize, PartialEq)]
-struct IMU {
+pub(crate) struct IMU {
     header: std_msgs::Header,
     orientation: [f64; 4],
     orientation_covariance: [f64; 9],
@@ -618,15 +1212,23 @@ struct IMU {
     linear_acceleration_covariance: [f64; 9],
 }
 
-fn imu_callback(
+fn imu_payload(
+    key: &str,
     header: std_msgs::Header,
     measure: ImuMeasurement,
-    tx: &Sender<(MessageType, Vec<u8>)>,
-) -> Result<()> {
+    noise: &mut ImuNoise,
+    endianness: Endianness,
+) -> Result<Option<Vec<u8>>> {
     let accel = measure.accelerometer();
     let gyro = measure.gyroscope();
-    let compass = measure.compass().to_radians();
+    let compass = measure.compass().to_radians() + noise.yaw() as f32;
     let orientation = UnitQuaternion::from_euler_angles(0.0, 0.0, -compass);
+    let (angular_velocity, linear_acceleration) = noise.apply(
+        control::time_to_secs(&header.stamp),
+        [-gyro[0] as f64, gyro[1] as f64, -gyro[2] as f64],
+        [accel[0] as f64, -accel[1] as f64, accel[2] as f64],
+    );
+    let covariance = noise.config();
 
     /*
     TODO: We generates IMU message type by ourselves, since r2r views array as Vec.
@@ -640,19 +1242,11 @@ fn imu_callback(
             /*z:*/ orientation.coords.data.0[0][0] as f64,
             /*w:*/ orientation.coords.data.0[0][2] as f64,
         ],
-        orientation_covariance: [0.0; 9],
-        angular_velocity: [
-            /*x:*/ -gyro[0] as f64,
-            /*y:*/ gyro[1] as f64,
-            /*z:*/ -gyro[2] as f64,
-        ],
-        angular_velocity_covariance: [0.0; 9],
-        linear_acceleration: [
-            /*x:*/ accel[0] as f64,
-            /*y:*/ -accel[1] as f64,
-            /*z:*/ accel[2] as f64,
-        ],
-        linear_acceleration_covariance: [0.0; 9],
+        orientation_covariance: covariance.orientation_covariance,
+        angular_velocity,
+        angular_velocity_covariance: covariance.angular_velocity_covariance,
+        linear_acceleration,
+        linear_acceleration_covariance: covariance.linear_acceleration_covariance,
     };
 
     /* Original IMU message
@@ -680,23 +1274,35 @@ fn imu_callback(
     };
     */
 
-    let encoded = cdr::serialize::<_, _, CdrLe>(&imu_msg, Infinite)?;
-    if let Err(_) = tx.send((MessageType::SensorData, encoded)) {
-        error!("Failed to send message");
-    }
-    Ok(())
+    Ok(Some(cdr_utils::serialize_for(key, &imu_msg, endianness)?))
 }
 
-fn gnss_callback(
+/// The geo reference of the map the sensor is in, None if the OpenDRIVE has no usable one
+fn map_geo_reference(actor: &Sensor) -> Option<GeoReference> {
+    GeoReference::from_opendrive(&actor.world().map().to_open_drive())
+}
+
+fn gnss_fix(
     header: std_msgs::Header,
     measure: GnssMeasurement,
-    tx: &Sender<(MessageType, Vec<u8>)>,
-) -> Result<()> {
-    let gnss_msg = sensor_msgs::NavSatFix {
+    noise: &mut GnssNoise,
+) -> sensor_msgs::NavSatFix {
+    let timestamp = control::time_to_secs(&header.stamp);
+    let (latitude, longitude, altitude) = noise.apply(
+        timestamp,
+        measure.latitude(),
+        measure.longitude(),
+        measure.attitude() + GNSS_ALTITUDE_OFFSET,
+    );
+    let (position_covariance, position_covariance_type) = match noise.covariance() {
+        Some(covariance) => (covariance, 2), // COVARIANCE_TYPE_DIAGONAL_KNOWN
+        None => ([0.0; 9], 0),               // COVARIANCE_TYPE_UNKNOWN
+    };
+    sensor_msgs::NavSatFix {
         header,
-        latitude: measure.latitude(),
-        longitude: measure.longitude(),
-        altitude: measure.attitude() + 17.0,
+        latitude,
+        longitude,
+        altitude,
         status: sensor_msgs::NavSatStatus {
             status: GnssStatus::StatusSbasFix as i8,
             service: GnssService::ServiceGps as u16
@@ -704,30 +1310,195 @@ fn gnss_callback(
                 | GnssService::ServiceCompass as u16
                 | GnssService::ServiceGalileo as u16,
         },
-        position_covariance: [0.0; 9],
-        position_covariance_type: 0, // unknown type
-    };
-    let encoded = cdr::serialize::<_, _, CdrLe>(&gnss_msg, Infinite)?;
-    if let Err(_) = tx.send((MessageType::SensorData, encoded)) {
-        error!("Failed to send message");
+        position_covariance,
+        position_covariance_type,
     }
-    Ok(())
+}
+
+/// The fix in the map frame, moved from the antenna to base_link along the yaw of the sensor.
+/// The orientation is unknown, so its variances are large.
+fn gnss_pose_payload(
+    key: &str,
+    fix: &sensor_msgs::NavSatFix,
+    yaw: f64,
+    geo_reference: &GeoReference,
+    config: &GnssConfig,
+    endianness: Endianness,
+) -> Result<Option<Vec<u8>>> {
+    let [x, y, z] = geo_reference.to_map(
+        fix.latitude,
+        fix.longitude,
+        fix.altitude - GNSS_ALTITUDE_OFFSET,
+    );
+    let [offset_x, offset_y, offset_z] = config.antenna_offset;
+    let (sin, cos) = yaw.sin_cos();
+    let position = [
+        x - (cos * offset_x - sin * offset_y),
+        y - (sin * offset_x + cos * offset_y),
+        z - offset_z,
+    ];
+    let mut covariance = [[0.0; 6]; 6];
+    for axis in 0..3 {
+        covariance[axis][axis] = fix.position_covariance[axis * 4];
+        covariance[axis + 3][axis + 3] = GNSS_ORIENTATION_VARIANCE;
+    }
+    let pose_msg = PoseWithCovarianceStamped {
+        header: std_msgs::Header {
+            stamp: fix.header.stamp.clone(),
+            frame_id: String::from("map"),
+        },
+        pose: PoseWithCovariance {
+            pose: Pose {
+                position,
+                orientation: [0.0, 0.0, 0.0, 1.0],
+            },
+            covariance,
+        },
+    };
+    Ok(Some(cdr_utils::serialize_for(key, &pose_msg, endianness)?))
+}
+
+/// The image width and height and the horizontal field of view (degrees) of a camera
+fn camera_attributes(actor: &Sensor) -> (u32, u32, f64) {
+    let attribute = |id: &str| {
+        actor
+            .attributes()
+            .iter()
+            .find(|attr| attr.id() == id)
+            .unwrap()
+            .value()
+            .unwrap()
+    };
+    let width = attribute("image_size_x").try_into_int().unwrap() as u32;
+    let height = attribute("image_size_y").try_into_int().unwrap() as u32;
+    let fov = attribute("fov").try_into_f32().unwrap() as f64;
+    (width, height, fov)
 }
 
 fn generate_sensor_name(actor: &Sensor) -> String {
     let XYZ { x, y, z } = *actor.location();
-    format!("{x}_{y}_{z}")
+    namespace::sanitize(&format!("{x}_{y}_{z}"))
 }
 
 impl Drop for SensorBridge {
     fn drop(&mut self) {
         info!("Remove sensor name {}", self.sensor_name);
-        if self.sensor_type != SensorType::Collision && self.sensor_type != SensorType::NotSupport {
-            // Not sure why the tx doesn't release in sensor callback, so rx can't use RecvErr to close the thread
-            // I create another message type to notify the thread to close
-            if let Err(_) = self.tx.send((MessageType::StopThread, vec![])) {
-                error!("Unable to stop the thread")
-            }
+        self.namespaces
+            .lock()
+            .unwrap()
+            .release_sensor(self.actor.id());
+        if let Some(tx) = &self.tx {
+            // The sensor callback keeps its sender, so the worker stops on the closed queue
+            tx.close();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const KEY: &str = "ego/rt/diagnostics";
+
+    fn session() -> Arc<Session> {
+        let mut config = Config::default();
+        config
+            .insert_json5("scouting/multicast/enabled", "false")
+            .unwrap();
+        Arc::new(zenoh::open(config).res().unwrap())
+    }
+
+    fn monitor(z_session: &Arc<Session>, frames: &SensorFrames) -> FrameMonitor {
+        let publisher = StatusPublisher::declare(
+            z_session.clone(),
+            KEY.to_string(),
+            Duration::from_secs(2),
+            Arc::default(),
+        )
+        .unwrap();
+        FrameMonitor {
+            frames: frames.clone(),
+            max_lag: 5,
+            lag: None,
+            last_publish: f64::MIN,
+            publisher: Some(publisher),
+            endianness: Endianness::Little,
+        }
+    }
+
+    /// The tick of the world at `frame`, each frame 0.05 s after the previous one
+    fn tick(frame: u64) -> TickSnapshot {
+        TickSnapshot::without_world(TimeStamp::from_secs_f64(frame as f64 * 0.05), frame)
+    }
+
+    #[test]
+    fn a_sensor_dropping_frames_warns_until_it_catches_up() {
+        let z_session = session();
+        let subscriber = z_session.declare_subscriber(KEY).res().unwrap();
+        let frames = SensorFrames::default();
+        let mut monitor = monitor(&z_session, &frames);
+        // The level, the lag and the tick offset of the diagnostics checked at `frame`
+        let mut check = |frame: u64| {
+            monitor.check(&tick(frame), "ego", "front_camera").unwrap();
+            let sample = subscriber.recv_timeout(Duration::from_secs(2)).unwrap();
+            let diagnostics: DiagnosticArray =
+                cdr_utils::deserialize(&sample.payload.contiguous()).unwrap();
+            let status = &diagnostics.status[0];
+            assert_eq!(status.name, "carla_bridge: ego front_camera frames");
+            let value = |key: &str| {
+                status
+                    .values
+                    .iter()
+                    .find(|value| value.key == key)
+                    .map(|value| value.value.clone())
+            };
+            (status.level, value("lag"), value("tick_offset_ms"))
+        };
+
+        assert_eq!(check(1), (diagnostic_status::OK, Some(String::new()), None));
+        // The callbacks of a sensor of every frame, the events carry their own stamps
+        for frame in 1..=30 {
+            frames.record(frame, frame as f64 * 0.05 + 0.001);
+        }
+        assert_eq!(
+            check(30),
+            (
+                diagnostic_status::OK,
+                Some(String::from("0")),
+                Some(String::from("1.000"))
+            )
+        );
+        // Without the events of the frames 31 to 60
+        assert_eq!(
+            check(60),
+            (diagnostic_status::WARN, Some(String::from("29")), None)
+        );
+        frames.record(90, 90.0 * 0.05 + 0.001);
+        assert_eq!(
+            check(90),
+            (
+                diagnostic_status::OK,
+                Some(String::from("0")),
+                Some(String::from("1.000"))
+            )
+        );
+    }
+
+    #[test]
+    fn the_lag_is_checked_every_tick() {
+        let z_session = session();
+        let frames = SensorFrames::default();
+        let mut monitor = monitor(&z_session, &frames);
+        for frame in 1..=10 {
+            frames.record(frame, frame as f64 * 0.05);
+        }
+        for (frame, lagging) in [(11, false), (16, false), (17, true), (18, true)] {
+            monitor.check(&tick(frame), "ego", "lidar").unwrap();
+            assert_eq!(monitor.is_lagging(), lagging, "frame {frame}");
         }
+        frames.record(18, 18.0 * 0.05);
+        monitor.check(&tick(18), "ego", "lidar").unwrap();
+        assert_eq!(monitor.lag, Some(0));
     }
 }
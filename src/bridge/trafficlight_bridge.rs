@@ -1,4 +1,4 @@
-use super::actor_bridge::ActorBridge;
+use super::actor_bridge::{ActorBridge, TickSnapshot};
 use crate::error::Result;
 use carla::client::TrafficLight;
 use std::sync::Arc;
@@ -15,7 +15,7 @@ impl TrafficLightBridge {
 }
 
 impl ActorBridge for TrafficLightBridge {
-    fn step(&mut self, _elapsed_sec: f64, _timestamp: f64) -> Result<()> {
+    fn step(&mut self, _elapsed_sec: f64, _tick: &TickSnapshot) -> Result<()> {
         Ok(())
     }
 }
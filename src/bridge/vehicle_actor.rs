@@ -0,0 +1,273 @@
+use super::actor_bridge::ActorMotion;
+use crate::longitudinal::AckermannGains;
+use carla::{
+    client::{ActorBase, Vehicle},
+    geom::BoundingBox,
+    rpc::{
+        VehicleAckermannControl, VehicleControl, VehicleDoor, VehicleLightState,
+        VehiclePhysicsControl, VehicleWheelLocation,
+    },
+};
+use nalgebra::{Isometry3, Vector3};
+
+/// The subset of a CARLA vehicle the vehicle bridge relies on.
+///
+/// The bridge only talks to the vehicle through this trait, so it can run
+/// against something other than a live simulator.
+pub trait VehicleActor {
+    fn id(&self) -> u32;
+    fn type_id(&self) -> String;
+    fn role_name(&self) -> Option<String>;
+    fn velocity(&self) -> Vector3<f32>;
+    /// In degrees per second, like CARLA
+    fn angular_velocity(&self) -> Vector3<f32>;
+    fn transform(&self) -> Isometry3<f32>;
+    /// Relative to the actor, None if the size is unknown
+    fn bounding_box(&self) -> Option<BoundingBox<f32>>;
+    fn control(&self) -> VehicleControl;
+    fn wheel_steer_angle(&self, wheel_location: VehicleWheelLocation) -> f32;
+    /// Rotational speed of FL, FR, RL and RR wheels (rad/s), if the simulator reports it
+    fn wheel_angular_velocities(&self) -> Option<[f32; 4]>;
+    fn apply_control(&mut self, control: &VehicleControl);
+    fn apply_ackermann_control(&mut self, control: &VehicleAckermannControl);
+    /// Replace the given gains of the Ackermann controller of the simulator
+    fn apply_ackermann_gains(&mut self, gains: &AckermannGains);
+    fn open_door(&mut self, door: VehicleDoor);
+    fn close_door(&mut self, door: VehicleDoor);
+    fn light_state(&self) -> VehicleLightState;
+    fn set_light_state(&mut self, light_state: VehicleLightState);
+    /// None if the physics can't be changed
+    fn physics_control(&self) -> Option<VehiclePhysicsControl>;
+    fn apply_physics_control(&mut self, physics: &VehiclePhysicsControl);
+    fn set_transform(&mut self, transform: &Isometry3<f32>);
+    fn set_target_velocity(&mut self, velocity: &Vector3<f32>);
+    /// In degrees per second, like CARLA
+    fn set_target_angular_velocity(&mut self, angular_velocity: &Vector3<f32>);
+}
+
+/// The state of a vehicle read once per step.
+///
+/// Every read is a blocking RPC, so the publications and the control of a step share
+/// these, which also makes all the topics of a tick describe the same instant.
+#[derive(Debug, Clone, Copy)]
+pub struct ActorSnapshot {
+    pub velocity: Vector3<f32>,
+    /// In degrees per second, like CARLA
+    pub angular_velocity: Vector3<f32>,
+    pub transform: Isometry3<f32>,
+    pub control: VehicleControl,
+    /// The steer angles of the FL and FR wheels (degrees), the FR one isn't read for a
+    /// two-wheeler, which only has the FL one
+    pub front_steer_angles: [f32; 2],
+}
+
+impl ActorSnapshot {
+    /// The motion of the world snapshot is used if there is one, it's read otherwise
+    pub fn read(
+        vehicle: &dyn VehicleActor,
+        two_wheeled: bool,
+        motion: Option<&ActorMotion>,
+    ) -> ActorSnapshot {
+        let front_left = vehicle.wheel_steer_angle(VehicleWheelLocation::FL_Wheel);
+        let front_right = if two_wheeled {
+            front_left
+        } else {
+            vehicle.wheel_steer_angle(VehicleWheelLocation::FR_Wheel)
+        };
+        let motion = motion.copied().unwrap_or_else(|| ActorMotion {
+            transform: vehicle.transform(),
+            velocity: vehicle.velocity(),
+            angular_velocity: vehicle.angular_velocity(),
+        });
+        ActorSnapshot {
+            velocity: motion.velocity,
+            angular_velocity: motion.angular_velocity,
+            transform: motion.transform,
+            control: vehicle.control(),
+            front_steer_angles: [front_left, front_right],
+        }
+    }
+}
+
+impl VehicleActor for Vehicle {
+    fn id(&self) -> u32 {
+        ActorBase::id(self)
+    }
+
+    fn type_id(&self) -> String {
+        ActorBase::type_id(self)
+    }
+
+    fn role_name(&self) -> Option<String> {
+        self.attributes()
+            .iter()
+            .find(|attr| attr.id() == "role_name")
+            .map(|attr| attr.value_string())
+    }
+
+    fn velocity(&self) -> Vector3<f32> {
+        ActorBase::velocity(self)
+    }
+
+    fn angular_velocity(&self) -> Vector3<f32> {
+        ActorBase::angular_velocity(self)
+    }
+
+    fn transform(&self) -> Isometry3<f32> {
+        ActorBase::transform(self)
+    }
+
+    fn bounding_box(&self) -> Option<BoundingBox<f32>> {
+        Some(ActorBase::bounding_box(self))
+    }
+
+    fn control(&self) -> VehicleControl {
+        Vehicle::control(self)
+    }
+
+    fn wheel_steer_angle(&self, wheel_location: VehicleWheelLocation) -> f32 {
+        Vehicle::wheel_steer_angle(self, wheel_location)
+    }
+
+    fn wheel_angular_velocities(&self) -> Option<[f32; 4]> {
+        // The wheel telemetry is only available since CARLA 0.9.15, which the bindings don't cover
+        None
+    }
+
+    fn apply_control(&mut self, control: &VehicleControl) {
+        Vehicle::apply_control(self, control)
+    }
+
+    fn apply_ackermann_control(&mut self, control: &VehicleAckermannControl) {
+        Vehicle::apply_ackermann_control(self, control)
+    }
+
+    #[cfg(feature = "ackermann_settings")]
+    fn apply_ackermann_gains(&mut self, gains: &AckermannGains) {
+        let mut settings = Vehicle::ackermann_controller_settings(self);
+        gains.merge_into(&mut settings);
+        Vehicle::apply_ackermann_controller_settings(self, &settings)
+    }
+
+    /// The configuration refuses the gains without the feature
+    #[cfg(not(feature = "ackermann_settings"))]
+    fn apply_ackermann_gains(&mut self, _gains: &AckermannGains) {}
+
+    fn open_door(&mut self, door: VehicleDoor) {
+        Vehicle::open_door(self, door)
+    }
+
+    fn close_door(&mut self, door: VehicleDoor) {
+        Vehicle::close_door(self, door)
+    }
+
+    fn light_state(&self) -> VehicleLightState {
+        Vehicle::light_state(self)
+    }
+
+    fn set_light_state(&mut self, light_state: VehicleLightState) {
+        Vehicle::set_light_state(self, &light_state)
+    }
+
+    fn physics_control(&self) -> Option<VehiclePhysicsControl> {
+        Some(Vehicle::physics_control(self))
+    }
+
+    fn apply_physics_control(&mut self, physics: &VehiclePhysicsControl) {
+        Vehicle::apply_physics_control(self, physics)
+    }
+
+    fn set_transform(&mut self, transform: &Isometry3<f32>) {
+        ActorBase::set_transform(self, transform)
+    }
+
+    fn set_target_velocity(&mut self, velocity: &Vector3<f32>) {
+        ActorBase::set_target_velocity(self, velocity)
+    }
+
+    fn set_target_angular_velocity(&mut self, angular_velocity: &Vector3<f32>) {
+        ActorBase::set_target_angular_velocity(self, angular_velocity)
+    }
+}
+
+/// A vehicle which is only observed: the reads go to the wrapped vehicle and the
+/// controls, doors, lights, physics and teleports are dropped, for `--dry-run`.
+pub struct DryRunVehicle {
+    vehicle: Box<dyn VehicleActor>,
+}
+
+impl DryRunVehicle {
+    pub fn new(vehicle: Box<dyn VehicleActor>) -> DryRunVehicle {
+        DryRunVehicle { vehicle }
+    }
+}
+
+impl VehicleActor for DryRunVehicle {
+    fn id(&self) -> u32 {
+        self.vehicle.id()
+    }
+
+    fn type_id(&self) -> String {
+        self.vehicle.type_id()
+    }
+
+    fn role_name(&self) -> Option<String> {
+        self.vehicle.role_name()
+    }
+
+    fn velocity(&self) -> Vector3<f32> {
+        self.vehicle.velocity()
+    }
+
+    fn angular_velocity(&self) -> Vector3<f32> {
+        self.vehicle.angular_velocity()
+    }
+
+    fn transform(&self) -> Isometry3<f32> {
+        self.vehicle.transform()
+    }
+
+    fn bounding_box(&self) -> Option<BoundingBox<f32>> {
+        self.vehicle.bounding_box()
+    }
+
+    fn control(&self) -> VehicleControl {
+        self.vehicle.control()
+    }
+
+    fn wheel_steer_angle(&self, wheel_location: VehicleWheelLocation) -> f32 {
+        self.vehicle.wheel_steer_angle(wheel_location)
+    }
+
+    fn wheel_angular_velocities(&self) -> Option<[f32; 4]> {
+        self.vehicle.wheel_angular_velocities()
+    }
+
+    fn apply_control(&mut self, _control: &VehicleControl) {}
+
+    fn apply_ackermann_control(&mut self, _control: &VehicleAckermannControl) {}
+
+    fn apply_ackermann_gains(&mut self, _gains: &AckermannGains) {}
+
+    fn open_door(&mut self, _door: VehicleDoor) {}
+
+    fn close_door(&mut self, _door: VehicleDoor) {}
+
+    fn light_state(&self) -> VehicleLightState {
+        self.vehicle.light_state()
+    }
+
+    fn set_light_state(&mut self, _light_state: VehicleLightState) {}
+
+    fn physics_control(&self) -> Option<VehiclePhysicsControl> {
+        self.vehicle.physics_control()
+    }
+
+    fn apply_physics_control(&mut self, _physics: &VehiclePhysicsControl) {}
+
+    fn set_transform(&mut self, _transform: &Isometry3<f32>) {}
+
+    fn set_target_velocity(&mut self, _velocity: &Vector3<f32>) {}
+
+    fn set_target_angular_velocity(&mut self, _angular_velocity: &Vector3<f32>) {}
+}
@@ -1,331 +1,3863 @@
-use super::actor_bridge::ActorBridge;
+use super::{
+    actor_bridge::{ActorBridge, BridgeDescription, BridgeKind, DeclaredKeys, TickSnapshot},
+    vehicle_actor::{ActorSnapshot, DryRunVehicle, VehicleActor},
+};
 use crate::{
+    autoware_type::{
+        autoware_state, diagnostic_status, headlights_command, mrm_behavior_status,
+        ActuationCommand, ActuationCommandStamped, AutowareState, BatteryStatus, DiagnosticArray,
+        DiagnosticStatus, DoorCommand, DoorStatus, DoorStatusArray, Engage, HeadlightsCommand,
+        HeadlightsReport, KeyValue, MrmBehaviorStatus, MrmState, PolygonStamped, Pose,
+        PoseWithCovariance, PoseWithCovarianceStamped, ResponseStatus, SetDoorCommand,
+        SetDoorCommandResponse, Twist, TwistWithCovariance, TwistWithCovarianceStamped,
+        WheelSpeeds,
+    },
+    autoware_version::AutowareVersion,
+    battery::BatteryModel,
+    cdr_utils::{self, Endianness, SampleDecoder},
+    command_log::{CommandKind, CommandRecorder, CommandReplay},
+    command_subscriber::CommandSubscriber,
+    config::{
+        BridgeConfig, ControlPath, ControllerKind, FallbackPolicy, SteeringConvention,
+        VehicleConfig,
+    },
+    config_file::TopicToggles,
+    control::{
+        self, CommandHistory, CommandValidator, ControlDeduplicator, ControlTarget, GearSelector,
+        JerkLimiter, LowPass, MrmBehavior, SpeedFilter, SteeringCalibration,
+    },
+    doors::{self, DoorState},
     error::{Error, Result},
-    utils,
+    footprint,
+    latency::DelayQueue,
+    lights::{self, LightMonitor},
+    longitudinal::{LongitudinalController, Output, TargetRequest},
+    namespace::{NamespaceRegistry, VehicleIdentity},
+    pedal_map::{self, PedalMaps},
+    physics::{PhysicsRefreshReply, PhysicsUpdate},
+    reset::ResetReport,
+    sensor_noise::{NoiseRng, PoseNoise},
+    service::{Encoding, ServiceRegistry},
+    signal_log::{SignalLog, SignalRow},
+    slip::SlipGeometry,
+    stats::{CommandRate, VehicleStats, STATS_INTERVAL_SEC},
+    status_publisher::StatusPublisher,
+    time::TimeStamp,
+    topics, utils,
 };
 use arc_swap::ArcSwap;
-use atomic_float::AtomicF32;
+use atomic_float::{AtomicF32, AtomicF64};
 use carla::{
-    client::{ActorBase, Vehicle},
-    rpc::{VehicleAckermannControl, VehicleWheelLocation},
+    geom::BoundingBox,
+    rpc::{VehicleAckermannControl, VehicleControl, VehicleLightState, VehiclePhysicsControl},
+};
+use log::{debug, info, warn};
+use nalgebra::{Isometry3, Quaternion, UnitQuaternion, Vector3};
+use std::{
+    sync::{atomic::Ordering, Arc, Mutex},
+    time::{Duration, Instant},
+};
+use zenoh::{
+    prelude::sync::*,
+    queryable::{Query, Queryable},
+    subscriber::Subscriber,
 };
-use cdr::{CdrLe, Infinite};
-use log::{debug, info};
-use std::sync::{atomic::Ordering, Arc};
-use zenoh::{prelude::sync::*, publication::Publisher, subscriber::Subscriber};
 use zenoh_ros_type::{
     autoware_auto_control_msgs::{
         AckermannControlCommand, AckermannLateralCommand, LongitudinalCommand,
     },
     autoware_auto_vehicle_msgs::{
-        control_mode_report, gear_report, hazard_lights_report, turn_indicators_report,
-        ControlModeReport, GearCommand, GearReport, HazardLightsReport, SteeringReport,
-        TurnIndicatorsReport, VelocityReport,
+        control_mode_report, gear_report, hazard_lights_command, turn_indicators_command,
+        ControlModeReport, GearCommand, GearReport, HazardLightsCommand, HazardLightsReport,
+        SteeringReport, TurnIndicatorsCommand, TurnIndicatorsReport, VelocityReport,
     },
-    builtin_interfaces::Time,
 };
 
+/// The steering angle of the tires at full steer (in degrees)
+pub const MAX_STEER_ANGLE: f32 = 69.999;
+/// The brake lights turn on above this brake
+const BRAKE_LIGHT_THRESHOLD: f32 = 0.05;
+/// autoware_auto_vehicle_msgs/msg/GearCommand DRIVE, DRIVE_2 and DRIVE_18
+const GEAR_COMMAND_DRIVE: u8 = 2;
+const GEAR_COMMAND_DRIVE_2: u8 = 3;
+const GEAR_COMMAND_DRIVE_18: u8 = 19;
+/// Above the speed ceiling by more than this, the bridge brakes (m/s)
+const OVERSPEED_MARGIN: f32 = 0.5;
+/// The least brake applied above the ceiling, which grows with the excess speed
+const MIN_OVERSPEED_BRAKE: f32 = 0.1;
+/// Below this speed the brake_hold fallback engages the hand brake (m/s)
+const STANDSTILL_SPEED: f32 = 0.1;
+/// The largest steering offset, a larger one is no longer a calibration (rad)
+const MAX_STEERING_OFFSET: f32 = 0.1;
+/// How long the vehicle cruises for an offset calibration without `duration` (simulation seconds)
+const CALIBRATION_DEFAULT_SEC: f64 = 5.0;
+const CALIBRATION_MAX_SEC: f64 = 60.0;
+/// A calibration gives up when the vehicle doesn't cruise long enough in this time beyond
+/// the duration (simulation seconds)
+const CALIBRATION_TIMEOUT_SEC: f64 = 60.0;
+/// A larger move between two steps means the vehicle was teleported (in meters)
+pub const TELEPORT_DISTANCE: f32 = 10.0;
+
 pub struct VehicleBridge<'a> {
     vehicle_name: String,
-    actor: Vehicle,
-    _subscriber_control_cmd: Subscriber<'a, ()>,
-    _subscriber_gear_cmd: Subscriber<'a, ()>,
-    publisher_velocity: Publisher<'a>,
-    publisher_steer: Publisher<'a>,
-    publisher_gear: Publisher<'a>,
-    publisher_control: Publisher<'a>,
-    publisher_turnindicator: Publisher<'a>,
-    publisher_hazardlight: Publisher<'a>,
+    identity: VehicleIdentity,
+    namespaces: Arc<Mutex<NamespaceRegistry>>,
+    actor: Box<dyn VehicleActor>,
+    _subscriber_control_cmd: Option<Subscriber<'a, ()>>,
+    _subscriber_gear_cmd: Option<CommandSubscriber<'a>>,
+    _subscriber_door_cmd: Option<Subscriber<'a, ()>>,
+    _subscriber_adapi_door_cmd: Option<Subscriber<'a, ()>>,
+    _subscriber_physics_update: Option<Subscriber<'a, ()>>,
+    _subscriber_actuation_cmd: Option<Subscriber<'a, ()>>,
+    _subscriber_control_path: Option<CommandSubscriber<'a>>,
+    _subscriber_control_enable: Option<CommandSubscriber<'a>>,
+    _subscriber_engage: Option<CommandSubscriber<'a>>,
+    _subscriber_headlights_cmd: Option<CommandSubscriber<'a>>,
+    _subscriber_turn_indicators_cmd: Option<Subscriber<'a, ()>>,
+    _subscriber_hazard_lights_cmd: Option<Subscriber<'a, ()>>,
+    _subscriber_mrm_state: Option<Subscriber<'a, ()>>,
+    _subscriber_autoware_state: Option<Subscriber<'a, ()>>,
+    _subscriber_steering_offset: Option<CommandSubscriber<'a>>,
+    _queryable_calibrate: Option<Queryable<'a, ()>>,
+    _queryable_pedal_maps_reload: Option<Queryable<'a, ()>>,
+    /// Taken out while its handlers run on the bridge, see `dispatch_services`
+    services: Option<ServiceRegistry<'a, VehicleBridge<'a>>>,
+    publisher_velocity: Option<StatusPublisher>,
+    publisher_steer: Option<StatusPublisher>,
+    publisher_gear: Option<StatusPublisher>,
+    publisher_control: Option<StatusPublisher>,
+    publisher_turnindicator: Option<StatusPublisher>,
+    publisher_hazardlight: Option<StatusPublisher>,
+    publisher_stats: Option<StatusPublisher>,
+    publisher_battery: Option<StatusPublisher>,
+    publisher_door: Option<StatusPublisher>,
+    publisher_adapi_door: Option<StatusPublisher>,
+    publisher_wheel_speeds: Option<StatusPublisher>,
+    publisher_twist: Option<StatusPublisher>,
+    publisher_pose: Option<StatusPublisher>,
+    /// Of the published pose
+    pose_noise: PoseNoise,
+    publisher_slip: Option<StatusPublisher>,
+    /// None without a physics control of two or four wheels
+    slip_geometry: Option<SlipGeometry>,
+    publisher_footprint: Option<StatusPublisher>,
+    /// Relative to the actor, read once since it doesn't change
+    bounding_box: Option<BoundingBox<f32>>,
+    publisher_headlights: Option<StatusPublisher>,
+    publisher_mrm_comfortable_stop: Option<StatusPublisher>,
+    publisher_mrm_emergency_stop: Option<StatusPublisher>,
+    publisher_diagnostics: Option<StatusPublisher>,
+    /// The filtered longitudinal speed, as reported and as controlled with
     speed: Arc<AtomicF32>,
-    current_ackermann_cmd: Arc<ArcSwap<AckermannControlCommand>>,
+    speed_filter: SpeedFilter,
+    /// Skips the RPCs of the controls repeating the last one
+    control_deduplicator: ControlDeduplicator,
+    current_ackermann_cmd: Arc<ArcSwap<CommandHistory>>,
+    config: VehicleConfig,
+    endianness: Endianness,
+    autoware_version: AutowareVersion,
+    stats: Arc<VehicleStats>,
+    last_stats_time: f64,
+    sim_time: Arc<AtomicF64>,
+    command_replay: Option<CommandReplay>,
+    signal_log: Option<SignalLog>,
+    battery: BatteryModel,
+    last_battery_time: f64,
+    last_footprint_time: f64,
+    last_step_time: Option<f64>,
+    door_state: DoorState,
+    pending_door_cmds: Arc<Mutex<Vec<DoorCommand>>>,
+    commanded_hand_brake: bool,
+    hand_brake_mismatch: bool,
+    pending_physics_updates: Arc<Mutex<Vec<PhysicsUpdate>>>,
+    /// What `two_wheeled`, `max_steer_angle` and `slip_geometry` derive from, None if it
+    /// can't be read
+    physics: Option<VehiclePhysicsControl>,
+    /// Counts the reads of `physics`
+    physics_generation: u64,
+    current_actuation_cmd: Arc<Mutex<ActuationCommand>>,
+    /// Both None without a `command_delay`
+    delayed_control_cmds: Option<Arc<Mutex<DelayQueue<AckermannControlCommand>>>>,
+    delayed_actuation_cmds: Option<Arc<Mutex<DelayQueue<ActuationCommand>>>>,
+    control_path: ControlPath,
+    pending_control_path: Arc<Mutex<Option<ControlPath>>>,
+    jerk_limiter: Option<JerkLimiter>,
+    steer_filter: Option<LowPass>,
+    /// Of the throttle and the brake
+    pedal_filters: Option<(LowPass, LowPass)>,
+    last_target_reverse: bool,
+    last_position: Option<Vector3<f32>>,
+    /// When the vehicle stopped with a zero speed command, None while it's driving
+    standstill_since: Option<f64>,
+    standstill_reset_done: bool,
+    /// control_cmd asks for the other direction while the vehicle is still moving
+    gear_change_pending: bool,
+    longitudinal_controller: LongitudinalController,
+    /// Picks the gears when the bridge drives the gearbox
+    gear_selector: Option<GearSelector>,
+    /// The numbered forward gear of the latest gear command, None for DRIVE
+    commanded_gear: Arc<Mutex<Option<i32>>>,
+    pending_headlights_cmd: Arc<Mutex<Option<u8>>>,
+    pending_turn_indicators_cmd: Arc<Mutex<Option<u8>>>,
+    pending_hazard_lights_cmd: Arc<Mutex<Option<u8>>>,
+    /// The latest turn indicators and hazard lights commands, the hazard lights win
+    turn_indicators_cmd: u8,
+    hazard_lights_cmd: u8,
+    lights: LightMonitor,
+    mrm_behavior: MrmBehavior,
+    pending_mrm_behavior: Arc<Mutex<Option<MrmBehavior>>>,
+    dry_run: bool,
+    /// The state of the vehicle at the start of the step
+    snapshot: ActorSnapshot,
+    /// A bicycle or a motorcycle, with a single front wheel
+    two_wheeled: bool,
+    /// The tire angle at full steer (in degrees)
+    max_steer_angle: f32,
+    /// Where the vehicle was when the bridge was created, the target of a reset
+    spawn_transform: Isometry3<f32>,
+    /// The speed ceiling changed the control of this step
+    speed_limited: bool,
+    /// Simulation time of the latest accepted command of each path, -inf before the first one
+    last_control_cmd_time: Arc<AtomicF64>,
+    last_actuation_cmd_time: Arc<AtomicF64>,
+    in_fallback: bool,
+    /// For the skipped control updates and the control gaps, which may repeat every tick
+    control_dt_log: utils::LogThrottle,
+    /// From the tick, the vehicle falls back while the zenoh session is disconnected
+    zenoh_lost: bool,
+    declared: DeclaredKeys,
+    /// The operator override, the vehicle holds the brake while false
+    control_enabled: bool,
+    pending_control_enable: Arc<Mutex<Option<bool>>>,
+    /// Autoware's engage flag, the commands are only applied with it and `control_enabled`.
+    /// True until the first `/autoware/engage`, like before the bridge followed it
+    engaged: bool,
+    pending_engage: Arc<Mutex<Option<bool>>>,
+    /// The latest `/autoware/state`, only with `require_autoware_state`
+    autoware_state: Arc<Mutex<Option<u8>>>,
+    /// The vehicle is held since Autoware isn't DRIVING
+    waiting_for_driving: bool,
+    /// Added to the commanded tire angle and subtracted from the measured one (rad)
+    steering_offset: f32,
+    pending_steering_offset: Arc<Mutex<Option<f32>>>,
+    /// The calibration queries not answered yet, they share the running calibration
+    pending_calibration_queries: Arc<Mutex<Vec<Query>>>,
+    calibration: Option<(SteeringCalibration, Vec<Query>)>,
 }
 
 impl<'a> VehicleBridge<'a> {
-    pub fn new(z_session: Arc<Session>, actor: Vehicle) -> Result<VehicleBridge<'a>> {
-        let mut vehicle_name = actor
-            .attributes()
-            .iter()
-            .find(|attr| attr.id() == "role_name")
-            .unwrap()
-            .value_string();
+    pub fn new(
+        z_session: Arc<Session>,
+        actor: Box<dyn VehicleActor>,
+        bridge_config: &BridgeConfig,
+        namespaces: Arc<Mutex<NamespaceRegistry>>,
+    ) -> Result<VehicleBridge<'a>> {
+        VehicleBridge::builder(z_session, actor, bridge_config, namespaces).build()
+    }
+
+    /// Enable or disable groups of topics over the `[vehicle.topics]` of the configuration
+    pub fn builder(
+        z_session: Arc<Session>,
+        actor: Box<dyn VehicleActor>,
+        bridge_config: &BridgeConfig,
+        namespaces: Arc<Mutex<NamespaceRegistry>>,
+    ) -> VehicleBridgeBuilder<'_> {
+        VehicleBridgeBuilder {
+            z_session,
+            actor,
+            bridge_config,
+            namespaces,
+            toggles: TopicToggles::default(),
+        }
+    }
+
+    fn create(builder: VehicleBridgeBuilder) -> Result<VehicleBridge<'a>> {
+        let VehicleBridgeBuilder {
+            z_session,
+            actor,
+            bridge_config,
+            namespaces,
+            toggles,
+        } = builder;
+        let role_name = actor.role_name().unwrap_or_default();
 
-        // Remove "autoware_" in role name
-        if !vehicle_name.starts_with("autoware_") {
+        // Only the vehicles with the prefix ("autoware_" by default) in role name are bridged
+        let Some(name) = bridge_config.bridged_name(&role_name) else {
             return Err(Error::Npc {
-                npc_role_name: vehicle_name,
+                npc_role_name: role_name,
             });
+        };
+        let vehicle_name = namespaces.lock().unwrap().namespace(actor.id(), name);
+        let mut config = bridge_config.vehicle_config(&vehicle_name, &role_name)?;
+        config.topic_groups = toggles.apply(&config.topic_groups);
+        let groups = config.topic_groups;
+        let endianness = bridge_config.cdr_endianness;
+        let autoware_version = bridge_config.autoware_version;
+        let topic_map = &bridge_config.topics;
+        let dry_run = bridge_config.dry_run;
+        // Everything still runs, only the requests to change the vehicle are dropped
+        let mut actor: Box<dyn VehicleActor> = if dry_run {
+            Box::new(DryRunVehicle::new(actor))
         } else {
-            vehicle_name = vehicle_name.replace("autoware_", "");
-        }
+            actor
+        };
 
         info!("Detect a vehicle {vehicle_name}");
+        // The bicycles and the motorcycles have a single front wheel, which also bounds the steer
+        let physics = actor.physics_control();
+        let PhysicsConstants {
+            two_wheeled,
+            max_steer_angle,
+            slip_geometry,
+        } = PhysicsConstants::derive(physics.as_ref(), &actor.transform());
+        if two_wheeled {
+            info!("{vehicle_name} is a two-wheeler, steering up to {max_steer_angle} degrees");
+        }
 
-        let publisher_velocity = z_session
-            .declare_publisher(format!("{vehicle_name}/rt/vehicle/status/velocity_status"))
-            .res()?;
-        let publisher_steer = z_session
-            .declare_publisher(format!("{vehicle_name}/rt/vehicle/status/steering_status"))
-            .res()?;
-        let publisher_gear = z_session
-            .declare_publisher(format!("{vehicle_name}/rt/vehicle/status/gear_status"))
-            .res()?;
-        let publisher_control = z_session
-            .declare_publisher(format!("{vehicle_name}/rt/vehicle/status/control_mode"))
-            .res()?;
-        let publisher_turnindicator = z_session
-            .declare_publisher(format!(
-                "{vehicle_name}/rt/vehicle/status/turn_indicators_status"
-            ))
-            .res()?;
-        let publisher_hazardlight = z_session
-            .declare_publisher(format!(
-                "{vehicle_name}/rt/vehicle/status/hazard_lights_status"
-            ))
-            .res()?;
+        let stats = Arc::new(VehicleStats::default());
+        let sim_time = Arc::new(AtomicF64::new(0.0));
+        let status_delayed = |topic: &str| {
+            config.status_delay > 0.0
+                && (config.status_delay_topics.is_empty()
+                    || config
+                        .status_delay_topics
+                        .iter()
+                        .any(|delayed| delayed == topic))
+        };
+        let declare_publisher = |enabled: bool, topic: &str| {
+            enabled
+                .then(|| {
+                    let key = topic_map.key(&vehicle_name, topic);
+                    let failures = stats.publish_failures(&key);
+                    let publisher = StatusPublisher::declare(
+                        z_session.clone(),
+                        key,
+                        bridge_config.redeclare_after,
+                        failures,
+                    )?;
+                    if status_delayed(topic) {
+                        Ok(publisher.with_delay(config.status_delay, sim_time.clone()))
+                    } else {
+                        Ok(publisher)
+                    }
+                })
+                .transpose()
+        };
+        let publisher_velocity =
+            declare_publisher(groups.velocity_report, topics::VELOCITY_STATUS)?;
+        let publisher_steer = declare_publisher(groups.steering_report, topics::STEERING_STATUS)?;
+        let publisher_gear = declare_publisher(groups.gear, topics::GEAR_STATUS)?;
+        let publisher_control = declare_publisher(groups.control, topics::CONTROL_MODE)?;
+        let publisher_turnindicator =
+            declare_publisher(groups.lights, topics::TURN_INDICATORS_STATUS)?;
+        let publisher_hazardlight = declare_publisher(groups.lights, topics::HAZARD_LIGHTS_STATUS)?;
+        let publisher_stats = declare_publisher(groups.diagnostics, topics::STATS)?;
+        let publisher_battery = declare_publisher(groups.battery, topics::BATTERY_CHARGE)?;
+        let publisher_door = declare_publisher(groups.doors, topics::DOOR_STATUS)?;
+        let publisher_adapi_door = declare_publisher(groups.doors, topics::ADAPI_DOOR_STATUS)?;
+        let publisher_wheel_speeds = if two_wheeled {
+            // The four wheels of the report don't exist
+            None
+        } else if actor.wheel_angular_velocities().is_some() {
+            declare_publisher(true, topics::WHEEL_SPEEDS)?
+        } else {
+            info!("The wheel speeds of {vehicle_name} are not available in this CARLA version, skip publishing them");
+            None
+        };
+        let publisher_twist =
+            declare_publisher(config.publish_twist, topics::TWIST_WITH_COVARIANCE)?;
+        let localization = bridge_config.localization;
+        // The latency of the pose comes on top of the status delay
+        let publisher_pose =
+            declare_publisher(localization.publish_pose, topics::POSE_WITH_COVARIANCE)?.map(
+                |publisher| {
+                    let mut delay = localization.latency();
+                    if status_delayed(topics::POSE_WITH_COVARIANCE) {
+                        delay += config.status_delay;
+                    }
+                    if delay > 0.0 {
+                        publisher.with_delay(delay, sim_time.clone())
+                    } else {
+                        publisher
+                    }
+                },
+            );
+        let pose_noise = PoseNoise::new(
+            localization,
+            NoiseRng::new(
+                bridge_config.noise_seed,
+                &topic_map.key(&vehicle_name, topics::POSE_WITH_COVARIANCE),
+            ),
+        );
+        let publisher_slip = match slip_geometry {
+            Some(_) => declare_publisher(config.publish_slip, topics::SLIP)?,
+            None if config.publish_slip => {
+                info!("The wheels of {vehicle_name} are unknown, skip publishing the slips");
+                None
+            }
+            None => None,
+        };
+        let bounding_box = actor.bounding_box();
+        let publisher_footprint = match bounding_box {
+            Some(_) => declare_publisher(config.footprint_rate > 0.0, topics::FOOTPRINT)?,
+            None if config.footprint_rate > 0.0 => {
+                info!("The size of {vehicle_name} is unknown, skip publishing its footprint");
+                None
+            }
+            None => None,
+        };
+        let publisher_headlights = declare_publisher(groups.lights, topics::HEADLIGHTS_STATUS)?;
+        let publisher_mrm_comfortable_stop =
+            declare_publisher(groups.mrm, topics::MRM_COMFORTABLE_STOP_STATUS)?;
+        let publisher_mrm_emergency_stop =
+            declare_publisher(groups.mrm, topics::MRM_EMERGENCY_STOP_STATUS)?;
+        let publisher_diagnostics = declare_publisher(groups.diagnostics, topics::DIAGNOSTICS)?;
         let speed = Arc::new(AtomicF32::new(0.0));
+        let battery = BatteryModel::new(config.battery);
 
-        // TODO: We can use default value here
-        let current_ackermann_cmd = Arc::new(ArcSwap::from_pointee(AckermannControlCommand {
-            stamp: Time { sec: 0, nanosec: 0 },
-            lateral: AckermannLateralCommand {
-                stamp: Time { sec: 0, nanosec: 0 },
-                steering_tire_angle: 0.0,
-                steering_tire_rotation_rate: 0.0,
-            },
-            longitudinal: LongitudinalCommand {
-                stamp: Time { sec: 0, nanosec: 0 },
-                speed: 0.0,
-                acceleration: 0.0,
-                jerk: 0.0,
-            },
-        }));
+        let current_ackermann_cmd =
+            Arc::new(ArcSwap::from_pointee(CommandHistory::new(stop_command())));
+        let command_recorder = match &config.record_commands {
+            Some(path) => Some(Arc::new(Mutex::new(CommandRecorder::open(
+                path,
+                &vehicle_name,
+            )?))),
+            None => None,
+        };
+        let command_replay = match &config.replay_commands {
+            Some(path) => {
+                let replay = CommandReplay::open(path, &vehicle_name)?;
+                info!(
+                    "Replay {} commands from {} for {vehicle_name}",
+                    replay.len(),
+                    path.display()
+                );
+                Some(replay)
+            }
+            None => None,
+        };
+        let signal_log = match &config.signal_log {
+            Some(path) => Some(SignalLog::create(path, &vehicle_name)?),
+            None => None,
+        };
+
+        // The commands wait for command_delay in step(), without a delay they take effect in
+        // the callback
+        let delayed_control_cmds = (config.command_delay > 0.0)
+            .then(|| Arc::new(Mutex::new(DelayQueue::new(config.command_delay))));
+        let delayed_actuation_cmds = (config.command_delay > 0.0)
+            .then(|| Arc::new(Mutex::new(DelayQueue::new(config.command_delay))));
+        let last_control_cmd_time = Arc::new(AtomicF64::new(f64::NEG_INFINITY));
+        let cloned_last_cmd_time = last_control_cmd_time.clone();
         let cloned_cmd = current_ackermann_cmd.clone();
-        let subscriber_control_cmd = z_session
-            .declare_subscriber(format!("{vehicle_name}/rt/control/command/control_cmd"))
-            .callback_mut(move |sample| {
-                let result: Result<AckermannControlCommand, _> =
-                    cdr::deserialize_from(&*sample.payload.contiguous(), cdr::size::Infinite);
-                let Ok(cmd) = result else {
-                    return;
-                };
-                cloned_cmd.store(Arc::new(cmd));
+        let cloned_delayed_cmds = delayed_control_cmds.clone();
+        let cloned_sim_time = sim_time.clone();
+        let cloned_recorder = command_recorder.clone();
+        let control_cmd_key = topic_map.key(&vehicle_name, topics::CONTROL_CMD);
+        let mut control_cmd_decoder = SampleDecoder::new(&control_cmd_key, stats.clone());
+        let mut control_cmd_validator =
+            CommandValidator::new(&control_cmd_key, &config, stats.clone());
+        let cloned_stats = stats.clone();
+        let mut declared = DeclaredKeys::default();
+        let subscriber_control_cmd = (groups.control && command_replay.is_none())
+            .then(|| {
+                declared.subscribers.push(control_cmd_key.clone());
+                z_session
+                    .declare_subscriber(control_cmd_key)
+                    .callback_mut(move |sample| {
+                        cloned_stats.control_cmd_arrivals.record();
+                        let payload = sample.payload.contiguous();
+                        if let Some(recorder) = &cloned_recorder {
+                            let timestamp = cloned_sim_time.load(Ordering::Relaxed);
+                            if let Err(e) = recorder.lock().unwrap().record(
+                                CommandKind::Control,
+                                timestamp,
+                                &payload,
+                            ) {
+                                warn!("Failed to record the control command: {e}");
+                            }
+                        }
+                        let Some(cmd) = control_cmd_decoder.decode_with(&payload, |payload| {
+                            autoware_version.decode_control(payload)
+                        }) else {
+                            return;
+                        };
+                        if !control_cmd_validator.accept_control(&cmd) {
+                            return;
+                        }
+                        if let Some(delayed) = &cloned_delayed_cmds {
+                            let timestamp = cloned_sim_time.load(Ordering::Relaxed);
+                            delayed.lock().unwrap().push(timestamp, cmd);
+                            return;
+                        }
+                        cloned_last_cmd_time
+                            .store(cloned_sim_time.load(Ordering::Relaxed), Ordering::Relaxed);
+                        // The callback is the only writer, so a plain load-then-store is enough
+                        let history = cloned_cmd.load().push(cmd);
+                        cloned_cmd.store(Arc::new(history));
+                    })
+                    .res()
+            })
+            .transpose()?;
+        let query_latest = |topic: &str| bridge_config.query_latest.contains(topic);
+        let cloned_sim_time = sim_time.clone();
+        // Only the numbered forward gears are used, for the manual gearbox
+        let commanded_gear = Arc::new(Mutex::new(None));
+        let cloned_commanded_gear = commanded_gear.clone();
+        let subscriber_gear_cmd = (groups.gear && command_replay.is_none())
+            .then(|| {
+                let gear_cmd_key = topic_map.key(&vehicle_name, topics::GEAR_CMD);
+                let mut gear_cmd_decoder = SampleDecoder::new(&gear_cmd_key, stats.clone());
+                declared.subscribers.push(gear_cmd_key.clone());
+                CommandSubscriber::declare(
+                    &z_session,
+                    gear_cmd_key,
+                    query_latest(topics::GEAR_CMD),
+                    move |sample| {
+                        // The direction is still derived from control_cmd
+                        if let Some(cmd) =
+                            gear_cmd_decoder.decode::<GearCommand>(&sample.payload.contiguous())
+                        {
+                            if let Some(gear) = forward_gear(cmd.command) {
+                                *cloned_commanded_gear.lock().unwrap() = gear;
+                            }
+                        }
+                        if let Some(recorder) = &command_recorder {
+                            let timestamp = cloned_sim_time.load(Ordering::Relaxed);
+                            let payload = sample.payload.contiguous();
+                            if let Err(e) = recorder.lock().unwrap().record(
+                                CommandKind::Gear,
+                                timestamp,
+                                &payload,
+                            ) {
+                                warn!("Failed to record the gear command: {e}");
+                            }
+                        }
+                    },
+                )
+            })
+            .transpose()?;
+
+        // The door RPCs are sent in step(), not from the zenoh thread
+        let pending_door_cmds = Arc::new(Mutex::new(Vec::new()));
+        let cloned_door_cmds = pending_door_cmds.clone();
+        let door_cmd_key = topic_map.key(&vehicle_name, topics::DOOR_CMD);
+        let mut door_cmd_decoder = SampleDecoder::new(&door_cmd_key, stats.clone());
+        let subscriber_door_cmd = groups
+            .doors
+            .then(|| {
+                declared.subscribers.push(door_cmd_key.clone());
+                z_session
+                    .declare_subscriber(door_cmd_key)
+                    .callback_mut(move |sample| {
+                        if let Some(cmd) =
+                            door_cmd_decoder.decode::<DoorCommand>(&sample.payload.contiguous())
+                        {
+                            cloned_door_cmds.lock().unwrap().push(cmd);
+                        }
+                    })
+                    .res()
+            })
+            .transpose()?;
+        // The commands of the ADAPI door service join the others, door by door
+        let cloned_door_cmds = pending_door_cmds.clone();
+        let adapi_door_cmd_key = topic_map.key(&vehicle_name, topics::ADAPI_DOOR_CMD);
+        let mut adapi_door_cmd_decoder = SampleDecoder::new(&adapi_door_cmd_key, stats.clone());
+        let cloned_vehicle_name = vehicle_name.clone();
+        let subscriber_adapi_door_cmd = groups
+            .doors
+            .then(|| {
+                declared.subscribers.push(adapi_door_cmd_key.clone());
+                z_session
+                    .declare_subscriber(adapi_door_cmd_key)
+                    .callback_mut(move |sample| {
+                        let Some(request) = adapi_door_cmd_decoder
+                            .decode::<SetDoorCommand>(&sample.payload.contiguous())
+                        else {
+                            return;
+                        };
+                        let mut pending = cloned_door_cmds.lock().unwrap();
+                        for cmd in &request.doors {
+                            match doors::from_adapi(request.stamp.clone(), cmd) {
+                                Some(cmd) => pending.push(cmd),
+                                None => warn!(
+                                    "Ignore the ADAPI door command {cmd:?} of {cloned_vehicle_name}"
+                                ),
+                            }
+                        }
+                    })
+                    .res()
+            })
+            .transpose()?;
+        let door_state = DoorState::new(doors::has_doors(&actor.type_id()));
+
+        // Like the doors, the physics is applied in step()
+        let pending_physics_updates = Arc::new(Mutex::new(Vec::new()));
+        let cloned_physics_updates = pending_physics_updates.clone();
+        let physics_update_key = topic_map.key(&vehicle_name, topics::PHYSICS_UPDATE);
+        let physics_update_failures = stats.topic_failures(&physics_update_key);
+        let cloned_stats = stats.clone();
+        let subscriber_physics_update = groups
+            .physics
+            .then(|| {
+                declared.subscribers.push(physics_update_key.clone());
+                z_session
+                    .declare_subscriber(physics_update_key)
+                    .callback_mut(move |sample| {
+                        match serde_json::from_slice::<PhysicsUpdate>(&sample.payload.contiguous())
+                        {
+                            Ok(update) => cloned_physics_updates.lock().unwrap().push(update),
+                            Err(e) => {
+                                cloned_stats
+                                    .rejected_samples
+                                    .fetch_add(1, Ordering::Relaxed);
+                                physics_update_failures.fetch_add(1, Ordering::Relaxed);
+                                warn!("Invalid physics update on {}: {e}", sample.key_expr);
+                            }
+                        }
+                    })
+                    .res()
+            })
+            .transpose()?;
+
+        // Both paths keep receiving, so switching between them takes effect immediately
+        let current_actuation_cmd = Arc::new(Mutex::new(ActuationCommand::default()));
+        let cloned_actuation_cmd = current_actuation_cmd.clone();
+        let last_actuation_cmd_time = Arc::new(AtomicF64::new(f64::NEG_INFINITY));
+        let cloned_last_cmd_time = last_actuation_cmd_time.clone();
+        let cloned_sim_time = sim_time.clone();
+        let cloned_delayed_cmds = delayed_actuation_cmds.clone();
+        let actuation_cmd_key = topic_map.key(&vehicle_name, topics::ACTUATION_CMD);
+        let mut actuation_cmd_decoder = SampleDecoder::new(&actuation_cmd_key, stats.clone());
+        let mut actuation_cmd_validator =
+            CommandValidator::new(&actuation_cmd_key, &config, stats.clone());
+        let subscriber_actuation_cmd = groups
+            .control
+            .then(|| {
+                declared.subscribers.push(actuation_cmd_key.clone());
+                z_session
+                    .declare_subscriber(actuation_cmd_key)
+                    .callback_mut(move |sample| {
+                        let Some(cmd) = actuation_cmd_decoder
+                            .decode::<ActuationCommandStamped>(&sample.payload.contiguous())
+                        else {
+                            return;
+                        };
+                        if !actuation_cmd_validator.accept_actuation(&cmd.actuation) {
+                            return;
+                        }
+                        let timestamp = cloned_sim_time.load(Ordering::Relaxed);
+                        match &cloned_delayed_cmds {
+                            Some(delayed) => delayed.lock().unwrap().push(timestamp, cmd.actuation),
+                            None => {
+                                *cloned_actuation_cmd.lock().unwrap() = cmd.actuation;
+                                cloned_last_cmd_time.store(timestamp, Ordering::Relaxed);
+                            }
+                        }
+                    })
+                    .res()
             })
-            .res()?;
-        let subscriber_gear_cmd = z_session
-            .declare_subscriber(format!("{vehicle_name}/rt/control/command/gear_cmd"))
-            .callback_mut(move |_sample| {
-                // TODO: We don't this now, since reverse will be calculated while subscribing control_cmd
+            .transpose()?;
+        let pending_control_path = Arc::new(Mutex::new(None));
+        let cloned_control_path = pending_control_path.clone();
+        let control_path_key = topic_map.key(&vehicle_name, topics::CONTROL_MODE_SELECT);
+        let subscriber_control_path = groups
+            .control
+            .then(|| {
+                declared.subscribers.push(control_path_key.clone());
+                CommandSubscriber::declare(
+                    &z_session,
+                    control_path_key,
+                    query_latest(topics::CONTROL_MODE_SELECT),
+                    move |sample| {
+                        let payload = sample.payload.contiguous();
+                        match std::str::from_utf8(&payload)
+                            .map_err(|e| e.to_string())
+                            .and_then(|path| path.parse::<ControlPath>())
+                        {
+                            Ok(path) => *cloned_control_path.lock().unwrap() = Some(path),
+                            Err(e) => warn!("Invalid control path on {}: {e}", sample.key_expr),
+                        }
+                    },
+                )
+            })
+            .transpose()?;
+
+        let pending_control_enable = Arc::new(Mutex::new(None));
+        let cloned_control_enable = pending_control_enable.clone();
+        let control_enable_key = topic_map.key(&vehicle_name, topics::CONTROL_ENABLE);
+        let subscriber_control_enable = groups
+            .control
+            .then(|| {
+                declared.subscribers.push(control_enable_key.clone());
+                CommandSubscriber::declare(
+                    &z_session,
+                    control_enable_key,
+                    query_latest(topics::CONTROL_ENABLE),
+                    move |sample| {
+                        let payload = sample.payload.contiguous();
+                        match std::str::from_utf8(&payload)
+                            .map_err(|e| e.to_string())
+                            .and_then(|enable| {
+                                enable.trim().parse::<bool>().map_err(|e| e.to_string())
+                            }) {
+                            Ok(enable) => *cloned_control_enable.lock().unwrap() = Some(enable),
+                            Err(e) => warn!("Invalid control enable on {}: {e}", sample.key_expr),
+                        }
+                    },
+                )
+            })
+            .transpose()?;
+        stats.control_enabled.store(true, Ordering::Relaxed);
+
+        let pending_engage = Arc::new(Mutex::new(None));
+        let cloned_engage = pending_engage.clone();
+        let engage_key = topic_map.key(&vehicle_name, topics::ENGAGE);
+        let mut engage_decoder = SampleDecoder::new(&engage_key, stats.clone());
+        let subscriber_engage = groups
+            .control
+            .then(|| {
+                declared.subscribers.push(engage_key.clone());
+                CommandSubscriber::declare(
+                    &z_session,
+                    engage_key,
+                    query_latest(topics::ENGAGE),
+                    move |sample| {
+                        if let Some(engage) =
+                            engage_decoder.decode::<Engage>(&sample.payload.contiguous())
+                        {
+                            *cloned_engage.lock().unwrap() = Some(engage.engage);
+                        }
+                    },
+                )
+            })
+            .transpose()?;
+
+        // The latest headlights command is applied in step()
+        let pending_headlights_cmd = Arc::new(Mutex::new(None));
+        let cloned_headlights_cmd = pending_headlights_cmd.clone();
+        let headlights_cmd_key = topic_map.key(&vehicle_name, topics::HEADLIGHTS_CMD);
+        let mut headlights_cmd_decoder = SampleDecoder::new(&headlights_cmd_key, stats.clone());
+        let subscriber_headlights_cmd = groups
+            .lights
+            .then(|| {
+                declared.subscribers.push(headlights_cmd_key.clone());
+                CommandSubscriber::declare(
+                    &z_session,
+                    headlights_cmd_key,
+                    query_latest(topics::HEADLIGHTS_CMD),
+                    move |sample| {
+                        if let Some(cmd) = headlights_cmd_decoder
+                            .decode::<HeadlightsCommand>(&sample.payload.contiguous())
+                        {
+                            if cmd.command != headlights_command::NO_COMMAND {
+                                *cloned_headlights_cmd.lock().unwrap() = Some(cmd.command);
+                            }
+                        }
+                    },
+                )
+            })
+            .transpose()?;
+
+        // The stop overrides the commands from step(), like the control path
+        let pending_mrm_behavior = Arc::new(Mutex::new(None));
+        let cloned_mrm_behavior = pending_mrm_behavior.clone();
+        let mrm_state_key = topic_map.key(&vehicle_name, topics::MRM_STATE);
+        let mut mrm_state_decoder = SampleDecoder::new(&mrm_state_key, stats.clone());
+        let subscriber_mrm_state = groups
+            .mrm
+            .then(|| {
+                declared.subscribers.push(mrm_state_key.clone());
+                z_session
+                    .declare_subscriber(mrm_state_key)
+                    .callback_mut(move |sample| {
+                        if let Some(state) =
+                            mrm_state_decoder.decode::<MrmState>(&sample.payload.contiguous())
+                        {
+                            *cloned_mrm_behavior.lock().unwrap() =
+                                Some(MrmBehavior::from_state(&state));
+                        }
+                    })
+                    .res()
+            })
+            .transpose()?;
+
+        // Read by update_carla_control(), which holds the vehicle until Autoware drives
+        let autoware_state = Arc::new(Mutex::new(None));
+        let cloned_autoware_state = autoware_state.clone();
+        let autoware_state_key = topic_map.key(&vehicle_name, topics::AUTOWARE_STATE);
+        let mut autoware_state_decoder = SampleDecoder::new(&autoware_state_key, stats.clone());
+        let subscriber_autoware_state = (groups.control && config.require_autoware_state)
+            .then(|| {
+                declared.subscribers.push(autoware_state_key.clone());
+                z_session
+                    .declare_subscriber(autoware_state_key)
+                    .callback_mut(move |sample| {
+                        if let Some(state) = autoware_state_decoder
+                            .decode::<AutowareState>(&sample.payload.contiguous())
+                        {
+                            *cloned_autoware_state.lock().unwrap() = Some(state.state);
+                        }
+                    })
+                    .res()
+            })
+            .transpose()?;
+
+        // The offset is applied in step(), like the control path
+        let pending_steering_offset = Arc::new(Mutex::new(None));
+        let cloned_steering_offset = pending_steering_offset.clone();
+        let steering_offset_key = topic_map.key(&vehicle_name, topics::STEERING_OFFSET);
+        let subscriber_steering_offset = groups
+            .control
+            .then(|| {
+                declared.subscribers.push(steering_offset_key.clone());
+                CommandSubscriber::declare(
+                    &z_session,
+                    steering_offset_key,
+                    query_latest(topics::STEERING_OFFSET),
+                    move |sample| {
+                        let payload = sample.payload.contiguous();
+                        match std::str::from_utf8(&payload)
+                            .map_err(|e| e.to_string())
+                            .and_then(|offset| parse_steering_offset(offset.trim()))
+                        {
+                            Ok(offset) => *cloned_steering_offset.lock().unwrap() = Some(offset),
+                            Err(e) => warn!("Invalid steering offset on {}: {e}", sample.key_expr),
+                        }
+                    },
+                )
+            })
+            .transpose()?;
+
+        // The handlers run in step(), on the actor
+        let mut services: ServiceRegistry<'a, VehicleBridge<'a>> =
+            ServiceRegistry::new(z_session.clone());
+        if groups.physics {
+            let physics_refresh_key = topic_map.key(&vehicle_name, topics::PHYSICS_REFRESH);
+            declared.queryables.push(physics_refresh_key.clone());
+            services.register(physics_refresh_key, Encoding::Json, |bridge, _: ()| {
+                Ok(bridge.refresh_physics())
+            })?;
+        }
+        if groups.doors {
+            let door_service_key = topic_map.key(&vehicle_name, topics::ADAPI_DOOR_SERVICE);
+            declared.queryables.push(door_service_key.clone());
+            services.register(
+                door_service_key,
+                Encoding::Cdr(endianness),
+                |bridge, request: SetDoorCommand| Ok(bridge.set_doors(&request)),
+            )?;
+        }
+
+        // The calibration runs over the next steps, which reply to the queries
+        let pending_calibration_queries = Arc::new(Mutex::new(Vec::new()));
+        let cloned_calibration_queries = pending_calibration_queries.clone();
+        let calibrate_key = topic_map.key(&vehicle_name, topics::STEERING_OFFSET_CALIBRATE);
+        let queryable_calibrate = groups
+            .control
+            .then(|| {
+                declared.queryables.push(calibrate_key.clone());
+                z_session
+                    .declare_queryable(calibrate_key)
+                    .callback(move |query| cloned_calibration_queries.lock().unwrap().push(query))
+                    .res()
             })
-            .res()?;
-
-        let _subscriber_turnindicator = z_session
-            .declare_subscriber(format!(
-                "{vehicle_name}/rt/control/command/turn_indicators_cmd"
-            ))
-            .callback_mut(move |_sample| {
-                // TODO: Not support yet
+            .transpose()?;
+
+        if !config.ackermann_gains.is_empty() {
+            if config.controller == ControllerKind::CarlaAckermann {
+                actor.apply_ackermann_gains(&config.ackermann_gains);
+                info!("Pushed the Ackermann controller gains of {vehicle_name} to CARLA");
+            } else {
+                warn!("Only the carla_ackermann controller uses the ackermann gains of {vehicle_name}");
+            }
+        }
+
+        // The maps are read in the zenoh thread, a reload never touches CARLA
+        let pedal_maps = match (&config.accel_map, &config.brake_map) {
+            (Some(accel_path), Some(brake_path)) => {
+                if config.controller != ControllerKind::SimplePid {
+                    warn!("Only the simple_pid controller uses the pedal maps of {vehicle_name}");
+                }
+                let maps = PedalMaps::load(accel_path, brake_path)?;
+                Some((
+                    Arc::new(ArcSwap::from_pointee(maps)),
+                    accel_path.clone(),
+                    brake_path.clone(),
+                ))
+            }
+            _ => None,
+        };
+        let queryable_pedal_maps_reload = match &pedal_maps {
+            Some((maps, accel_path, brake_path)) if groups.control => {
+                let cloned_maps = maps.clone();
+                let (accel_path, brake_path) = (accel_path.clone(), brake_path.clone());
+                let reload_key = topic_map.key(&vehicle_name, topics::PEDAL_MAPS_RELOAD);
+                declared.queryables.push(reload_key.clone());
+                let queryable = z_session
+                    .declare_queryable(reload_key)
+                    .callback(move |query| {
+                        let result = pedal_map::reload(&cloned_maps, &accel_path, &brake_path)
+                            .and_then(|report| Ok(serde_json::to_vec(&report)?));
+                        let reply = match result {
+                            Ok(payload) => Ok(Sample::new(query.key_expr().clone(), payload)),
+                            Err(e) => Err(e.to_string().into()),
+                        };
+                        if let Err(e) = query.reply(reply).res() {
+                            warn!("Failed to reply to the pedal maps reload: {e}");
+                        }
+                    })
+                    .res()?;
+                Some(queryable)
+            }
+            _ => None,
+        };
+
+        // The blinkers follow the latest commands in step(), like the headlights
+        let pending_turn_indicators_cmd = Arc::new(Mutex::new(None));
+        let cloned_turn_indicators_cmd = pending_turn_indicators_cmd.clone();
+        let turn_indicators_cmd_key = topic_map.key(&vehicle_name, topics::TURN_INDICATORS_CMD);
+        let mut turn_indicators_cmd_decoder =
+            SampleDecoder::new(&turn_indicators_cmd_key, stats.clone());
+        let subscriber_turn_indicators_cmd = groups
+            .lights
+            .then(|| {
+                declared.subscribers.push(turn_indicators_cmd_key.clone());
+                z_session
+                    .declare_subscriber(turn_indicators_cmd_key)
+                    .callback_mut(move |sample| {
+                        if let Some(cmd) = turn_indicators_cmd_decoder
+                            .decode::<TurnIndicatorsCommand>(&sample.payload.contiguous())
+                        {
+                            if cmd.command != turn_indicators_command::NO_COMMAND {
+                                *cloned_turn_indicators_cmd.lock().unwrap() = Some(cmd.command);
+                            }
+                        }
+                    })
+                    .res()
             })
-            .res()?;
-        let _subscriber_hazardlight = z_session
-            .declare_subscriber(format!(
-                "{vehicle_name}/rt/control/command/hazard_lights_cmd"
-            ))
-            .callback_mut(move |_sample| {
-                // TODO: Not support yet
+            .transpose()?;
+        let pending_hazard_lights_cmd = Arc::new(Mutex::new(None));
+        let cloned_hazard_lights_cmd = pending_hazard_lights_cmd.clone();
+        let hazard_lights_cmd_key = topic_map.key(&vehicle_name, topics::HAZARD_LIGHTS_CMD);
+        let mut hazard_lights_cmd_decoder =
+            SampleDecoder::new(&hazard_lights_cmd_key, stats.clone());
+        let subscriber_hazard_lights_cmd = groups
+            .lights
+            .then(|| {
+                declared.subscribers.push(hazard_lights_cmd_key.clone());
+                z_session
+                    .declare_subscriber(hazard_lights_cmd_key)
+                    .callback_mut(move |sample| {
+                        if let Some(cmd) = hazard_lights_cmd_decoder
+                            .decode::<HazardLightsCommand>(&sample.payload.contiguous())
+                        {
+                            if cmd.command != hazard_lights_command::NO_COMMAND {
+                                *cloned_hazard_lights_cmd.lock().unwrap() = Some(cmd.command);
+                            }
+                        }
+                    })
+                    .res()
             })
-            .res()?;
+            .transpose()?;
+
+        declared.publishers = [
+            &publisher_velocity,
+            &publisher_steer,
+            &publisher_gear,
+            &publisher_control,
+            &publisher_turnindicator,
+            &publisher_hazardlight,
+            &publisher_stats,
+            &publisher_battery,
+            &publisher_door,
+            &publisher_adapi_door,
+            &publisher_headlights,
+            &publisher_mrm_comfortable_stop,
+            &publisher_mrm_emergency_stop,
+            &publisher_diagnostics,
+        ]
+        .into_iter()
+        .flatten()
+        .chain(&publisher_wheel_speeds)
+        .chain(&publisher_twist)
+        .chain(&publisher_pose)
+        .chain(&publisher_slip)
+        .chain(&publisher_footprint)
+        .map(|publisher| publisher.key_expr().to_string())
+        .collect();
 
+        let spawn_transform = actor.transform();
+        let snapshot = ActorSnapshot::read(actor.as_ref(), two_wheeled, None);
+        let identity = VehicleIdentity {
+            actor_id: actor.id(),
+            role_name,
+            namespace: vehicle_name.clone(),
+        };
         Ok(VehicleBridge {
             vehicle_name,
+            identity,
+            namespaces,
             actor,
             _subscriber_control_cmd: subscriber_control_cmd,
             _subscriber_gear_cmd: subscriber_gear_cmd,
+            _subscriber_door_cmd: subscriber_door_cmd,
+            _subscriber_adapi_door_cmd: subscriber_adapi_door_cmd,
+            _subscriber_physics_update: subscriber_physics_update,
+            _subscriber_actuation_cmd: subscriber_actuation_cmd,
+            _subscriber_control_path: subscriber_control_path,
+            _subscriber_control_enable: subscriber_control_enable,
+            _subscriber_engage: subscriber_engage,
+            _subscriber_headlights_cmd: subscriber_headlights_cmd,
+            _subscriber_turn_indicators_cmd: subscriber_turn_indicators_cmd,
+            _subscriber_hazard_lights_cmd: subscriber_hazard_lights_cmd,
+            _subscriber_mrm_state: subscriber_mrm_state,
+            _subscriber_autoware_state: subscriber_autoware_state,
+            _subscriber_steering_offset: subscriber_steering_offset,
+            _queryable_calibrate: queryable_calibrate,
+            _queryable_pedal_maps_reload: queryable_pedal_maps_reload,
+            services: Some(services),
             publisher_velocity,
             publisher_steer,
             publisher_gear,
             publisher_control,
             publisher_turnindicator,
             publisher_hazardlight,
+            publisher_stats,
+            publisher_battery,
+            publisher_door,
+            publisher_adapi_door,
+            publisher_wheel_speeds,
+            publisher_twist,
+            publisher_pose,
+            pose_noise,
+            publisher_slip,
+            slip_geometry,
+            publisher_footprint,
+            bounding_box,
+            publisher_headlights,
+            publisher_mrm_comfortable_stop,
+            publisher_mrm_emergency_stop,
+            publisher_diagnostics,
             speed,
             current_ackermann_cmd,
+            endianness,
+            autoware_version,
+            stats,
+            last_stats_time: f64::NEG_INFINITY,
+            sim_time,
+            command_replay,
+            signal_log,
+            battery,
+            last_battery_time: f64::NEG_INFINITY,
+            last_footprint_time: f64::NEG_INFINITY,
+            last_step_time: None,
+            door_state,
+            pending_door_cmds,
+            // The Ackermann control never engages the hand brake
+            commanded_hand_brake: false,
+            hand_brake_mismatch: false,
+            pending_physics_updates,
+            physics,
+            physics_generation: 1,
+            current_actuation_cmd,
+            delayed_control_cmds,
+            delayed_actuation_cmds,
+            control_path: config.control_path,
+            pending_control_path,
+            jerk_limiter: config.jerk_limit.map(JerkLimiter::new),
+            steer_filter: config.steer_time_constant.map(LowPass::new),
+            speed_filter: SpeedFilter::new(
+                config.max_measured_acceleration,
+                config.speed_median_samples,
+            ),
+            control_deduplicator: ControlDeduplicator::new(config.control_refresh_ticks),
+            pedal_filters: config
+                .pedal_time_constant
+                .map(|time_constant| (LowPass::new(time_constant), LowPass::new(time_constant))),
+            last_target_reverse: false,
+            last_position: None,
+            standstill_since: None,
+            standstill_reset_done: false,
+            gear_change_pending: false,
+            longitudinal_controller: LongitudinalController::new(
+                config.controller,
+                config.pid,
+                pedal_maps.map(|(maps, _, _)| maps),
+            ),
+            gear_selector: config.manual_gear_shift.then(|| {
+                GearSelector::new(
+                    config.gear_shift_speeds.clone(),
+                    config.gear_shift_hysteresis,
+                )
+            }),
+            commanded_gear,
+            pending_headlights_cmd,
+            pending_turn_indicators_cmd,
+            pending_hazard_lights_cmd,
+            turn_indicators_cmd: turn_indicators_command::DISABLE,
+            hazard_lights_cmd: hazard_lights_command::DISABLE,
+            lights: LightMonitor::new(config.light_status_rate),
+            mrm_behavior: MrmBehavior::None,
+            pending_mrm_behavior,
+            dry_run,
+            snapshot,
+            two_wheeled,
+            max_steer_angle,
+            spawn_transform,
+            speed_limited: false,
+            last_control_cmd_time,
+            last_actuation_cmd_time,
+            in_fallback: false,
+            control_dt_log: utils::LogThrottle::new(Duration::from_secs(5)),
+            zenoh_lost: false,
+            declared,
+            control_enabled: true,
+            pending_control_enable,
+            engaged: true,
+            pending_engage,
+            autoware_state,
+            waiting_for_driving: false,
+            steering_offset: 0.0,
+            pending_steering_offset,
+            pending_calibration_queries,
+            calibration: None,
+            config,
         })
     }
 
-    fn pub_current_velocity(&mut self, timestamp: f64) -> Result<()> {
-        //let transform = vehicle_actor.transform();
-        let velocity = self.actor.velocity();
-        //let angular_velocity = vehicle_actor.angular_velocity();
-        //let accel = vehicle_actor.acceleration();
-        let mut header = utils::create_ros_header(Some(timestamp)).unwrap();
-        header.frame_id = String::from("base_link");
+    /// The velocity in base_link with ROS axes: longitudinal, lateral (m/s) and yaw rate (rad/s)
+    fn body_twist(&self) -> (f32, f32, f32) {
+        let snapshot = &self.snapshot;
+        control::body_twist(
+            &snapshot.transform,
+            &snapshot.velocity,
+            &snapshot.angular_velocity,
+        )
+    }
+
+    /// The tire angle of the front axle in the steering convention, before the offset (rad)
+    fn wheel_tire_angle(&self) -> f32 {
+        control::wheel_tire_angle(
+            self.snapshot.front_steer_angles,
+            self.two_wheeled,
+            self.config.steering_convention,
+        )
+    }
+
+    /// The tire angle reported to Autoware, which doesn't see the offset (rad)
+    fn measured_tire_angle(&self) -> f32 {
+        self.wheel_tire_angle() - self.steering_offset
+    }
+
+    fn pub_current_velocity(&mut self, stamp: TimeStamp) -> Result<()> {
+        let Some(publisher) = &self.publisher_velocity else {
+            return Ok(());
+        };
+        let longitudinal_velocity = self.speed.load(Ordering::Relaxed);
+        let (_, lateral_velocity, heading_rate) = self.body_twist();
+        let header = utils::ros_header(stamp, "base_link");
         let velocity_msg = VelocityReport {
             header,
-            longitudinal_velocity: velocity.norm(),
-            lateral_velocity: 0.0,
-            heading_rate: self
-                .actor
-                .wheel_steer_angle(VehicleWheelLocation::FL_Wheel)
-                .to_radians()
-                * -1.0,
+            longitudinal_velocity,
+            lateral_velocity,
+            heading_rate,
         };
         debug!(
             "Carla => Autoware: current velocity: {}",
             velocity_msg.longitudinal_velocity
         );
-        let encoded = cdr::serialize::<_, _, CdrLe>(&velocity_msg, Infinite)?;
-        self.publisher_velocity.put(encoded).res()?;
-        self.speed
-            .store(velocity_msg.longitudinal_velocity, Ordering::Relaxed);
-
+        cdr_utils::put(publisher, &velocity_msg, self.endianness)?;
         Ok(())
     }
 
-    fn pub_current_steer(&mut self, timestamp: f64) -> Result<()> {
+    fn pub_current_steer(&mut self, stamp: TimeStamp) -> Result<()> {
+        let Some(publisher) = &self.publisher_steer else {
+            return Ok(());
+        };
         let steer_msg = SteeringReport {
-            stamp: Time {
-                sec: timestamp.floor() as i32,
-                nanosec: (timestamp.fract() * 1_000_000_000_f64) as u32,
-            },
-            steering_tire_angle: self
-                .actor
-                .wheel_steer_angle(VehicleWheelLocation::FL_Wheel)
-                .to_radians()
-                * -1.0,
-        };
-        let encoded = cdr::serialize::<_, _, CdrLe>(&steer_msg, Infinite)?;
-        self.publisher_steer.put(encoded).res()?;
+            stamp: stamp.to_msg(),
+            // Undo the conversion in update_carla_control()
+            steering_tire_angle: self.measured_tire_angle() * self.config.steering_ratio,
+        };
+        cdr_utils::put(publisher, &steer_msg, self.endianness)?;
         Ok(())
     }
 
-    fn pub_current_gear(&mut self, timestamp: f64) -> Result<()> {
+    fn pub_current_gear(&mut self, stamp: TimeStamp) -> Result<()> {
+        let Some(publisher) = &self.publisher_gear else {
+            return Ok(());
+        };
+        let control = self.snapshot.control;
         let gear_msg = GearReport {
-            stamp: Time {
-                sec: timestamp.floor() as i32,
-                nanosec: (timestamp.fract() * 1_000_000_000_f64) as u32,
+            stamp: stamp.to_msg(),
+            report: if control.hand_brake {
+                gear_report::PARK
+            } else if control.reverse {
+                gear_report::REVERSE
+            } else {
+                gear_report::DRIVE
             },
-            report: if self.actor.control().reverse { 20 } else { 2 }, /* TODO: Use enum (20: reverse, 2: drive) */
         };
-        let encoded = cdr::serialize::<_, _, CdrLe>(&gear_msg, Infinite)?;
-        self.publisher_gear.put(encoded).res()?;
+        cdr_utils::put(publisher, &gear_msg, self.endianness)?;
         Ok(())
     }
 
-    fn pub_current_control(&mut self, timestamp: f64) -> Result<()> {
+    /// Track the hand brake read back from CARLA, which may be changed by someone else
+    fn update_hand_brake(&mut self, hand_brake: bool) {
+        self.stats.hand_brake.store(hand_brake, Ordering::Relaxed);
+        let mismatch = hand_brake != self.commanded_hand_brake;
+        if mismatch && !self.hand_brake_mismatch {
+            warn!(
+                "The hand brake of {} is {}, but the bridge commanded it {}",
+                self.vehicle_name,
+                if hand_brake { "engaged" } else { "released" },
+                if self.commanded_hand_brake {
+                    "engaged"
+                } else {
+                    "released"
+                }
+            );
+        }
+        self.hand_brake_mismatch = mismatch;
+    }
+
+    fn pub_current_control(&mut self, stamp: TimeStamp) -> Result<()> {
+        let Some(publisher) = &self.publisher_control else {
+            return Ok(());
+        };
         let control_msg = ControlModeReport {
-            stamp: Time {
-                sec: timestamp.floor() as i32,
-                nanosec: (timestamp.fract() * 1_000_000_000_f64) as u32,
+            stamp: stamp.to_msg(),
+            // TODO: Now we don't have any way to switch to MANUAL
+            mode: if self.dry_run {
+                control_mode_report::NOT_READY
+            } else if self.in_fallback {
+                control_mode_report::DISENGAGED
+            } else {
+                control_mode_report::AUTONOMOUS
             },
-            mode: 1, /* 1: AUTONOMOUS, 4: MANUAL. TODO: Now we don't have any way to switch these two modes. */
         };
-        let encoded = cdr::serialize::<_, _, CdrLe>(&control_msg, Infinite)?;
-        self.publisher_control.put(encoded).res()?;
+        cdr_utils::put(publisher, &control_msg, self.endianness)?;
         Ok(())
     }
 
-    fn pub_current_indicator(&mut self, timestamp: f64) -> Result<()> {
-        // TODO: Not support yet
+    fn pub_current_indicator(&mut self, stamp: TimeStamp) -> Result<()> {
+        let Some(publisher) = &self.publisher_turnindicator else {
+            return Ok(());
+        };
         let turnindicator_msg = TurnIndicatorsReport {
-            stamp: Time {
-                sec: timestamp.floor() as i32,
-                nanosec: (timestamp.fract() * 1000_000_000_f64) as u32,
-            },
-            report: turn_indicators_report::DISABLE,
+            stamp: stamp.to_msg(),
+            report: lights::turn_indicators(self.lights.state()),
         };
-        let encoded = cdr::serialize::<_, _, CdrLe>(&turnindicator_msg, Infinite)?;
-        self.publisher_turnindicator.put(encoded).res()?;
+        cdr_utils::put(publisher, &turnindicator_msg, self.endianness)?;
         Ok(())
     }
 
-    fn pub_hazard_light(&mut self, timestamp: f64) -> Result<()> {
-        // TODO: Not support yet
+    fn pub_hazard_light(&mut self, stamp: TimeStamp) -> Result<()> {
+        let Some(publisher) = &self.publisher_hazardlight else {
+            return Ok(());
+        };
         let hazardlight_msg = HazardLightsReport {
-            stamp: Time {
-                sec: timestamp.floor() as i32,
-                nanosec: (timestamp.fract() * 1000_000_000_f64) as u32,
+            stamp: stamp.to_msg(),
+            report: lights::hazard_lights(self.lights.state()),
+        };
+        cdr_utils::put(publisher, &hazardlight_msg, self.endianness)?;
+        Ok(())
+    }
+
+    fn update_headlights(&mut self) {
+        let Some(command) = self.pending_headlights_cmd.lock().unwrap().take() else {
+            return;
+        };
+        let lights = match command {
+            headlights_command::DISABLE => VehicleLightState::empty(),
+            headlights_command::ENABLE_LOW => VehicleLightState::LOW_BEAM,
+            headlights_command::ENABLE_HIGH => {
+                VehicleLightState::LOW_BEAM | VehicleLightState::HIGH_BEAM
+            }
+            _ => {
+                debug!(
+                    "Ignore the headlights command {command} of {}",
+                    self.vehicle_name
+                );
+                return;
+            }
+        };
+        self.update_light_state(
+            VehicleLightState::LOW_BEAM | VehicleLightState::HIGH_BEAM,
+            lights,
+        );
+    }
+
+    fn update_blinkers(&mut self) {
+        let turn_indicators_cmd = self.pending_turn_indicators_cmd.lock().unwrap().take();
+        let hazard_lights_cmd = self.pending_hazard_lights_cmd.lock().unwrap().take();
+        if turn_indicators_cmd.is_none() && hazard_lights_cmd.is_none() {
+            return;
+        }
+        self.turn_indicators_cmd = turn_indicators_cmd.unwrap_or(self.turn_indicators_cmd);
+        self.hazard_lights_cmd = hazard_lights_cmd.unwrap_or(self.hazard_lights_cmd);
+        let lights = if self.hazard_lights_cmd == hazard_lights_command::ENABLE {
+            VehicleLightState::LEFT_BLINKER | VehicleLightState::RIGHT_BLINKER
+        } else {
+            match self.turn_indicators_cmd {
+                turn_indicators_command::ENABLE_LEFT => VehicleLightState::LEFT_BLINKER,
+                turn_indicators_command::ENABLE_RIGHT => VehicleLightState::RIGHT_BLINKER,
+                _ => VehicleLightState::empty(),
+            }
+        };
+        self.update_light_state(
+            VehicleLightState::LEFT_BLINKER | VehicleLightState::RIGHT_BLINKER,
+            lights,
+        );
+    }
+
+    fn pub_headlights(&mut self, stamp: TimeStamp) -> Result<()> {
+        let Some(publisher) = &self.publisher_headlights else {
+            return Ok(());
+        };
+        let headlights_msg = HeadlightsReport {
+            stamp: stamp.to_msg(),
+            report: lights::headlights(self.lights.state()),
+        };
+        cdr_utils::put(publisher, &headlights_msg, self.endianness)?;
+        Ok(())
+    }
+
+    /// Drain the battery over every tick, whatever the status rate
+    fn update_battery(&mut self, timestamp: f64) {
+        let elapsed_sim_sec = self
+            .last_step_time
+            .map_or(0.0, |last_step_time| timestamp - last_step_time);
+        self.battery
+            .update(elapsed_sim_sec, self.snapshot.control.throttle);
+    }
+
+    fn pub_battery(&mut self, stamp: TimeStamp) -> Result<()> {
+        let timestamp = stamp.to_secs_f64();
+        if timestamp - self.last_battery_time < 1.0 {
+            return Ok(());
+        }
+        self.last_battery_time = timestamp;
+        let Some(publisher) = &self.publisher_battery else {
+            return Ok(());
+        };
+        let battery_msg = BatteryStatus {
+            stamp: stamp.to_msg(),
+            energy_level: self.battery.charge(),
+        };
+        cdr_utils::put(publisher, &battery_msg, self.endianness)?;
+        Ok(())
+    }
+
+    fn update_doors(&mut self) {
+        let cmds: Vec<_> = self.pending_door_cmds.lock().unwrap().drain(..).collect();
+        if let Err(e) = self.apply_door_cmds(&cmds) {
+            warn!("{e}");
+        }
+    }
+
+    /// Apply the door commands, or refuse them all while the vehicle moves. The commands
+    /// the doors can't follow are ignored, and counted.
+    fn apply_door_cmds(&mut self, cmds: &[DoorCommand]) -> std::result::Result<usize, String> {
+        // Like a real vehicle, the doors stay as they are while driving
+        let speed = self.snapshot.velocity.norm();
+        if !cmds.is_empty() && speed > self.config.door_max_speed {
+            return Err(format!(
+                "Refuse {} door commands of {}, which moves at {speed:.2} m/s",
+                cmds.len(),
+                self.vehicle_name
+            ));
+        }
+        let mut ignored = 0;
+        for cmd in cmds {
+            let Some((doors, open)) = self.door_state.apply(cmd) else {
+                debug!(
+                    "Ignore the door command {:?} of {}, doors available: {}",
+                    cmd,
+                    self.vehicle_name,
+                    self.door_state.is_available()
+                );
+                ignored += 1;
+                continue;
+            };
+            for door in doors {
+                if open {
+                    self.actor.open_door(door);
+                } else {
+                    self.actor.close_door(door);
+                }
+            }
+        }
+        Ok(ignored)
+    }
+
+    /// The ADAPI door service: the same as the commands of the topic, but the response tells
+    /// whether the doors follow
+    fn set_doors(&mut self, request: &SetDoorCommand) -> SetDoorCommandResponse {
+        let cmds: Option<Vec<_>> = request
+            .doors
+            .iter()
+            .map(|cmd| doors::from_adapi(request.stamp.clone(), cmd))
+            .collect();
+        let result = match cmds {
+            Some(cmds) => self
+                .apply_door_cmds(&cmds)
+                .and_then(|ignored| match ignored {
+                    0 => Ok(()),
+                    _ => Err(format!(
+                        "{} has no door for {ignored} of the commands",
+                        self.vehicle_name
+                    )),
+                }),
+            None => Err(format!(
+                "Unknown door index or command in {:?}",
+                request.doors
+            )),
+        };
+        let status = match result {
+            Ok(()) => ResponseStatus {
+                success: true,
+                code: 0,
+                message: String::new(),
             },
-            report: hazard_lights_report::DISABLE,
+            Err(message) => {
+                warn!("{message}");
+                ResponseStatus {
+                    success: false,
+                    code: 0,
+                    message,
+                }
+            }
+        };
+        SetDoorCommandResponse { status }
+    }
+
+    fn update_physics(&mut self) {
+        let updates: Vec<_> = self
+            .pending_physics_updates
+            .lock()
+            .unwrap()
+            .drain(..)
+            .collect();
+        if updates.is_empty() {
+            return;
+        }
+        let Some(mut physics) = self.actor.physics_control() else {
+            warn!(
+                "Ignore {} physics updates, the physics of {} can't be changed",
+                updates.len(),
+                self.vehicle_name
+            );
+            return;
+        };
+        let mut changed = false;
+        for update in updates {
+            if let Err(e) = update.validate() {
+                warn!(
+                    "Reject the physics update {update:?} of {}: {e}",
+                    self.vehicle_name
+                );
+                continue;
+            }
+            update.merge_into(&mut physics);
+            changed = true;
+        }
+        if changed {
+            self.actor.apply_physics_control(&physics);
+            info!(
+                "Update the physics of {}: mass:{} max_rpm:{} drag_coefficient:{}",
+                self.vehicle_name, physics.mass, physics.max_rpm, physics.drag_coefficient
+            );
+            self.refresh_physics();
+        }
+    }
+
+    /// Read the physics control again and rebuild what derives from it: the steer range,
+    /// the wheels and the slip geometry. The controller starts over, since the vehicle it
+    /// controlled changed. Called after every physics update of the bridge, and by
+    /// `bridge/physics/refresh` after a script changed the physics.
+    pub fn refresh_physics(&mut self) -> PhysicsRefreshReply {
+        self.physics = self.actor.physics_control();
+        self.physics_generation += 1;
+        let constants = PhysicsConstants::derive(self.physics.as_ref(), &self.snapshot.transform);
+        if constants.two_wheeled != self.two_wheeled {
+            warn!(
+                "The wheels of {} changed, its wheel topics stay as they were declared",
+                self.vehicle_name
+            );
+        }
+        self.two_wheeled = constants.two_wheeled;
+        self.max_steer_angle = constants.max_steer_angle;
+        self.slip_geometry = constants.slip_geometry;
+        self.reset_controller();
+        let reply = PhysicsRefreshReply {
+            generation: self.physics_generation,
+            mass: self.physics.as_ref().map(|physics| physics.mass),
+            wheels: self
+                .physics
+                .as_ref()
+                .map_or(0, |physics| physics.wheels.len()),
+            two_wheeled: self.two_wheeled,
+            max_steer_angle: self.max_steer_angle,
+        };
+        info!("Refreshed the physics of {}: {reply:?}", self.vehicle_name);
+        reply
+    }
+
+    /// Run the handlers of the service requests received since the last step
+    fn dispatch_services(&mut self) {
+        if let Some(mut services) = self.services.take() {
+            services.dispatch(self);
+            self.services = Some(services);
+        }
+    }
+
+    fn pub_door_status(&mut self, stamp: TimeStamp) -> Result<()> {
+        let Some(publisher) = &self.publisher_door else {
+            return Ok(());
+        };
+        let door_msg = DoorStatus {
+            stamp: stamp.to_msg(),
+            doors: self.door_state.doors(),
+        };
+        cdr_utils::put(publisher, &door_msg, self.endianness)?;
+        if let Some(publisher) = &self.publisher_adapi_door {
+            let adapi_door_msg = DoorStatusArray {
+                stamp: stamp.to_msg(),
+                doors: self.door_state.adapi_doors(),
+            };
+            cdr_utils::put(publisher, &adapi_door_msg, self.endianness)?;
+        }
+        Ok(())
+    }
+
+    fn pub_wheel_speeds(&mut self, stamp: TimeStamp) -> Result<()> {
+        let Some(publisher) = &self.publisher_wheel_speeds else {
+            return Ok(());
         };
-        let encoded = cdr::serialize::<_, _, CdrLe>(&hazardlight_msg, Infinite)?;
-        self.publisher_hazardlight.put(encoded).res()?;
+        let Some(wheel_speeds) = self.actor.wheel_angular_velocities() else {
+            return Ok(());
+        };
+        let wheel_msg = WheelSpeeds {
+            stamp: stamp.to_msg(),
+            wheel_speeds,
+            vehicle_speed: self.speed.load(Ordering::Relaxed),
+        };
+        cdr_utils::put(publisher, &wheel_msg, self.endianness)?;
+        Ok(())
+    }
+
+    fn pub_slip(&mut self, stamp: TimeStamp) -> Result<()> {
+        let (Some(publisher), Some(geometry)) = (&self.publisher_slip, &self.slip_geometry) else {
+            return Ok(());
+        };
+        let tire_angle = control::wheel_tire_angle(
+            self.snapshot.front_steer_angles,
+            self.two_wheeled,
+            SteeringConvention::LeftPositive,
+        );
+        let slip_msg = geometry.estimate(
+            stamp.to_msg(),
+            self.body_twist(),
+            tire_angle,
+            self.actor.wheel_angular_velocities(),
+        );
+        cdr_utils::put(publisher, &slip_msg, self.endianness)?;
         Ok(())
     }
 
-    fn update_carla_control(&mut self, elapsed_sec: f64) {
-        let AckermannControlCommand {
-            lateral:
-                AckermannLateralCommand {
-                    steering_tire_angle,
-                    ..
+    fn pub_twist(&mut self, stamp: TimeStamp) -> Result<()> {
+        let Some(publisher) = &self.publisher_twist else {
+            return Ok(());
+        };
+        let (longitudinal, lateral, yaw_rate) = self.body_twist();
+        let header = utils::ros_header(stamp, "base_link");
+        let mut covariance = [[0.0; 6]; 6];
+        for (axis, variance) in self.config.twist_covariance.iter().enumerate() {
+            covariance[axis][axis] = *variance;
+        }
+        let twist_msg = TwistWithCovarianceStamped {
+            header,
+            twist: TwistWithCovariance {
+                twist: Twist {
+                    linear: [longitudinal as f64, lateral as f64, 0.0],
+                    angular: [0.0, 0.0, yaw_rate as f64],
                 },
-            longitudinal:
-                LongitudinalCommand {
-                    speed,
-                    acceleration,
-                    jerk,
-                    ..
+                covariance,
+            },
+        };
+        cdr_utils::put(publisher, &twist_msg, self.endianness)?;
+        Ok(())
+    }
+
+    /// The ground truth of a pose estimator, with the noise of `[localization]`
+    fn pub_pose(&mut self, stamp: TimeStamp) -> Result<()> {
+        let Some(publisher) = &self.publisher_pose else {
+            return Ok(());
+        };
+        let transform = &self.snapshot.transform;
+        let translation = transform.translation.vector;
+        let position = utils::carla_to_ros_position(translation.x, translation.y, translation.z);
+        let [i, j, k, w] = utils::carla_to_ros_orientation(&transform.rotation);
+        // Already normalized, which keeps the ground truth exact
+        let orientation = UnitQuaternion::new_unchecked(Quaternion::new(w, i, j, k));
+        let (position, orientation) =
+            self.pose_noise
+                .apply(stamp.to_secs_f64(), position, orientation);
+        let pose_msg = PoseWithCovarianceStamped {
+            header: utils::ros_header(stamp, "map"),
+            pose: PoseWithCovariance {
+                pose: Pose {
+                    position,
+                    orientation: [orientation.i, orientation.j, orientation.k, orientation.w],
                 },
-            ..
-        } = **self.current_ackermann_cmd.load();
-        debug!(
-            "Autoware => Carla: speed:{} accel:{} steering_tire_angle:{}",
-            speed,
-            acceleration,
-            -steering_tire_angle.to_degrees()
-        );
-        let current_speed = self.actor.velocity().norm();
-        let (_, pitch_radians, _) = self.actor.transform().rotation.euler_angles();
+                covariance: self.pose_noise.covariance(),
+            },
+        };
+        cdr_utils::put(publisher, &pose_msg, self.endianness)?;
+        Ok(())
+    }
 
-        let steer = {
-            let max_steer_angle = 69.999;
-            (-steering_tire_angle.to_degrees() / max_steer_angle).clamp(-1.0, 1.0)
+    /// The footprint only changes with the pose, so it's published at `footprint_rate`
+    fn pub_footprint(&mut self, stamp: TimeStamp) -> Result<()> {
+        let (Some(publisher), Some(bounding_box)) = (&self.publisher_footprint, &self.bounding_box)
+        else {
+            return Ok(());
+        };
+        let timestamp = stamp.to_secs_f64();
+        if timestamp - self.last_footprint_time < 1.0 / self.config.footprint_rate {
+            return Ok(());
+        }
+        self.last_footprint_time = timestamp;
+        let footprint_msg = PolygonStamped {
+            header: utils::ros_header(stamp, "map"),
+            polygon: footprint::footprint(
+                &self.snapshot.transform,
+                bounding_box,
+                self.config.footprint_margin,
+            ),
         };
+        cdr_utils::put(publisher, &footprint_msg, self.endianness)?;
+        Ok(())
+    }
 
-        // Compute the steering speed
-        let steer_speed = if steering_tire_angle.to_degrees().abs() < 3.0 {
-            0.0
-        } else if steering_tire_angle.to_degrees() > 0.0 {
-            0.1
+    /// Publish the stats and the diagnostics, whose rate is computed here rather than per command
+    fn pub_stats(&mut self, stamp: TimeStamp) -> Result<()> {
+        let timestamp = stamp.to_secs_f64();
+        if timestamp - self.last_stats_time < STATS_INTERVAL_SEC {
+            return Ok(());
+        }
+        self.last_stats_time = timestamp;
+        let control_cmd_rate = self.stats.control_cmd_arrivals.rate(Instant::now());
+        let encoded = serde_json::to_vec(&self.stats.report(
+            timestamp,
+            &self.identity,
+            self.control_path,
+            control_cmd_rate,
+        ))?;
+        if let Some(publisher) = &self.publisher_stats {
+            publisher.put(encoded);
+        }
+        self.pub_diagnostics(stamp, control_cmd_rate)
+    }
+
+    fn pub_diagnostics(&mut self, stamp: TimeStamp, control_cmd_rate: CommandRate) -> Result<()> {
+        let Some(publisher) = &self.publisher_diagnostics else {
+            return Ok(());
+        };
+        let diagnostics_msg = DiagnosticArray {
+            header: utils::ros_header(stamp, ""),
+            status: vec![
+                self.command_rate_status(control_cmd_rate),
+                self.light_status(stamp.to_secs_f64()),
+            ],
+        };
+        cdr_utils::put(publisher, &diagnostics_msg, self.endianness)?;
+        Ok(())
+    }
+
+    fn command_rate_status(&self, control_cmd_rate: CommandRate) -> DiagnosticStatus {
+        let min_rate = self.config.min_command_rate;
+        let (level, message) = if self.command_replay.is_some() {
+            (
+                diagnostic_status::OK,
+                String::from("replaying the commands"),
+            )
+        } else if self.control_path != ControlPath::Ackermann {
+            (
+                diagnostic_status::OK,
+                String::from("not the active control path"),
+            )
+        } else if control_cmd_rate.rate_hz < min_rate {
+            (
+                diagnostic_status::WARN,
+                format!("control_cmd below {min_rate} Hz"),
+            )
         } else {
-            -0.1
+            (diagnostic_status::OK, String::from("OK"))
         };
+        let values = [
+            ("rate_hz", control_cmd_rate.rate_hz),
+            ("jitter_ms", control_cmd_rate.jitter_ms),
+            ("min_rate_hz", min_rate),
+        ]
+        .into_iter()
+        .map(|(key, value)| KeyValue {
+            key: key.to_string(),
+            value: format!("{value:.1}"),
+        })
+        .collect();
+        DiagnosticStatus {
+            level,
+            name: format!("carla_bridge: {} control_cmd rate", self.vehicle_name),
+            message,
+            hardware_id: self.vehicle_name.clone(),
+            values,
+        }
+    }
 
-        self.actor
-            .apply_ackermann_control(&VehicleAckermannControl {
-                steer,
-                steer_speed,
-                speed,
-                acceleration,
-                jerk,
-            });
+    /// Whether the lights commanded by the bridge are the ones CARLA reports
+    fn light_status(&self, timestamp: f64) -> DiagnosticStatus {
+        let (level, message) = match self.lights.mismatch(timestamp) {
+            // The commands are dropped on purpose
+            _ if self.dry_run => (diagnostic_status::OK, String::from("read-only")),
+            Some(differing) => (
+                diagnostic_status::WARN,
+                format!("the lights {differing:?} differ from the command"),
+            ),
+            None => (diagnostic_status::OK, String::from("OK")),
+        };
+        DiagnosticStatus {
+            level,
+            name: format!("carla_bridge: {} lights", self.vehicle_name),
+            message,
+            hardware_id: self.vehicle_name.clone(),
+            values: vec![KeyValue {
+                key: String::from("light_state"),
+                value: format!("{:#x}", self.lights.state().bits()),
+            }],
+        }
+    }
 
-        debug!(
-            "Autoware => Carla: elapse_sec:{} current_speed:{} pitch_radians:{}",
-            elapsed_sec, current_speed, pitch_radians
+    fn replay_commands(&mut self, timestamp: f64) {
+        let Some(replay) = &mut self.command_replay else {
+            return;
+        };
+        let was_finished = replay.is_finished();
+        for entry in replay.poll(timestamp) {
+            // Gear commands are recorded, but reverse is still derived from control_cmd
+            if entry.kind != CommandKind::Control {
+                continue;
+            }
+            let decoded = self
+                .autoware_version
+                .decode_control(&entry.payload)
+                .and_then(|cmd| {
+                    self.config
+                        .command_limits
+                        .check_control(&cmd, self.config.steering_ratio)
+                        .map(|()| cmd)
+                        .map_err(|reason| {
+                            self.stats.rejected_commands.fetch_add(1, Ordering::Relaxed);
+                            Error::InvalidCommand { reason }
+                        })
+                });
+            match decoded {
+                Ok(cmd) => {
+                    let history = self.current_ackermann_cmd.load().push(cmd);
+                    self.current_ackermann_cmd.store(Arc::new(history));
+                    self.last_control_cmd_time
+                        .store(timestamp, Ordering::Relaxed);
+                }
+                Err(e) => warn!("Skip a recorded control command: {e}"),
+            }
+        }
+        if !was_finished && replay.is_finished() {
+            info!("Finished replaying the commands of {}", self.vehicle_name);
+        }
+    }
+
+    fn update_control_path(&mut self) {
+        let Some(path) = self.pending_control_path.lock().unwrap().take() else {
+            return;
+        };
+        if path == self.control_path {
+            return;
+        }
+        info!(
+            "Switch the control path of {} from {:?} to {:?}",
+            self.vehicle_name, self.control_path, path
         );
+        self.control_path = path;
+        self.reset_controller();
     }
 
-    pub fn vehicle_name(&self) -> &str {
-        &self.vehicle_name
+    fn update_control_enable(&mut self) {
+        let Some(enable) = self.pending_control_enable.lock().unwrap().take() else {
+            return;
+        };
+        if enable == self.control_enabled {
+            return;
+        }
+        if enable {
+            info!("Resume the control of {}", self.vehicle_name);
+            self.reset_controller();
+        } else {
+            info!(
+                "Freeze {}, the commands are ignored until it's enabled again",
+                self.vehicle_name
+            );
+        }
+        self.control_enabled = enable;
+        self.stats.control_enabled.store(enable, Ordering::Relaxed);
     }
-}
 
-impl<'a> ActorBridge for VehicleBridge<'a> {
-    fn step(&mut self, elapsed_sec: f64, timestamp: f64) -> Result<()> {
-        self.pub_current_velocity(timestamp)?;
-        self.pub_current_steer(timestamp)?;
-        self.pub_current_gear(timestamp)?;
-        self.pub_current_control(timestamp)?;
-        self.pub_current_indicator(timestamp)?;
-        self.pub_hazard_light(timestamp)?;
-        self.update_carla_control(elapsed_sec);
-        Ok(())
+    fn update_engage(&mut self) {
+        let Some(engage) = self.pending_engage.lock().unwrap().take() else {
+            return;
+        };
+        if engage == self.engaged {
+            return;
+        }
+        if engage {
+            info!("Autoware engaged {}, apply its commands", self.vehicle_name);
+            // The commands of before are stale
+            self.reset_controller();
+        } else {
+            info!(
+                "Autoware disengaged {}, hold it until engaged again",
+                self.vehicle_name
+            );
+        }
+        self.engaged = engage;
     }
-}
 
-impl<'a> Drop for VehicleBridge<'a> {
-    fn drop(&mut self) {
-        info!("Remove vehicle name {}", self.vehicle_name());
+    fn update_steering_offset(&mut self) {
+        let Some(offset) = self.pending_steering_offset.lock().unwrap().take() else {
+            return;
+        };
+        if offset != self.steering_offset {
+            info!(
+                "Set the steering offset of {} to {offset} rad",
+                self.vehicle_name
+            );
+            self.steering_offset = offset;
+        }
+    }
+
+    /// Start a calibration for the new queries, or add the step to the running one and
+    /// reply once it has a result or gives up
+    fn update_calibration(&mut self, elapsed_sec: f64, timestamp: f64) {
+        let queries: Vec<_> = self
+            .pending_calibration_queries
+            .lock()
+            .unwrap()
+            .drain(..)
+            .collect();
+        if !queries.is_empty() {
+            match &mut self.calibration {
+                Some((_, running)) => running.extend(queries),
+                None => {
+                    let duration = calibration_duration(queries[0].parameters());
+                    info!(
+                        "Calibrate the steering offset of {} over {duration} s of cruise",
+                        self.vehicle_name
+                    );
+                    let calibration = SteeringCalibration::new(
+                        duration,
+                        duration + CALIBRATION_TIMEOUT_SEC,
+                        timestamp,
+                    );
+                    self.calibration = Some((calibration, queries));
+                }
+            }
+        }
+        let Some((calibration, _)) = &mut self.calibration else {
+            return;
+        };
+        let (speed, _, yaw_rate) = self.body_twist();
+        calibration.add(speed, yaw_rate, self.wheel_tire_angle(), elapsed_sec);
+        let reply = match calibration.result() {
+            Some(offset) if offset.abs() > MAX_STEERING_OFFSET => Err(format!(
+                "the calibrated steering offset {offset} rad exceeds {MAX_STEERING_OFFSET} rad"
+            )),
+            Some(offset) => Ok(offset),
+            None if calibration.timed_out(timestamp) => Err(format!(
+                "the vehicle cruised straight for only {:.1} s",
+                calibration.cruise_sec()
+            )),
+            None => return,
+        };
+        let Some((_, queries)) = self.calibration.take() else {
+            return;
+        };
+        let reply = match reply {
+            Ok(offset) => {
+                info!(
+                    "Calibrated the steering offset of {} to {offset} rad",
+                    self.vehicle_name
+                );
+                let previous = std::mem::replace(&mut self.steering_offset, offset);
+                Ok(format!(
+                    r#"{{"steering_offset":{offset},"previous":{previous}}}"#
+                ))
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to calibrate the steering offset of {}: {e}",
+                    self.vehicle_name
+                );
+                Err(e)
+            }
+        };
+        for query in queries {
+            let result = match &reply {
+                Ok(payload) => Ok(Sample::new(query.key_expr().clone(), payload.clone())),
+                Err(e) => Err(e.clone().into()),
+            };
+            if let Err(e) = query.reply(result).res() {
+                warn!("Failed to reply to the steering calibration: {e}");
+            }
+        }
+    }
+
+    fn update_mrm_behavior(&mut self) {
+        let Some(behavior) = self.pending_mrm_behavior.lock().unwrap().take() else {
+            return;
+        };
+        if behavior == self.mrm_behavior {
+            return;
+        }
+        info!(
+            "The MRM behavior of {} changes from {:?} to {:?}",
+            self.vehicle_name, self.mrm_behavior, behavior
+        );
+        self.mrm_behavior = behavior;
+        // The commands kept arriving during the stop, so don't interpolate from the one before
+        self.reset_controller();
+    }
+
+    /// Report which stop operates, so the fail-safe state machine of Autoware moves on
+    fn pub_mrm_status(&mut self, stamp: TimeStamp) -> Result<()> {
+        let behaviors = [
+            (
+                &self.publisher_mrm_comfortable_stop,
+                MrmBehavior::ComfortableStop,
+            ),
+            (
+                &self.publisher_mrm_emergency_stop,
+                MrmBehavior::EmergencyStop,
+            ),
+        ];
+        for (publisher, behavior) in behaviors {
+            let Some(publisher) = publisher else {
+                continue;
+            };
+            let status_msg = MrmBehaviorStatus {
+                stamp: stamp.to_msg(),
+                state: if self.mrm_behavior == behavior {
+                    mrm_behavior_status::OPERATING
+                } else {
+                    mrm_behavior_status::AVAILABLE
+                },
+            };
+            cdr_utils::put(publisher, &status_msg, self.endianness)?;
+        }
+        Ok(())
     }
-}
 
+    /// Forget the state carried over from the previous commands, e.g. the interpolation
+    fn reset_controller(&mut self) {
+        self.current_ackermann_cmd.rcu(|history| CommandHistory {
+            previous: None,
+            latest: history.latest.clone(),
+        });
+        if let Some(jerk_limiter) = &mut self.jerk_limiter {
+            jerk_limiter.reset();
+        }
+        if let Some(steer_filter) = &mut self.steer_filter {
+            steer_filter.reset();
+        }
+        if let Some((throttle_filter, brake_filter)) = &mut self.pedal_filters {
+            throttle_filter.reset();
+            brake_filter.reset();
+        }
+        self.longitudinal_controller.reset();
+    }
+
+    /// The target speed, zero while the vehicle has to stop before changing the direction.
+    ///
+    /// CARLA flips the reverse gear at any speed, so a reverse command while rolling forward
+    /// (or the other way around) first brakes below `gear_change_speed`.
+    fn gate_gear_change(&mut self, target_speed: f32) -> f32 {
+        let reverse = target_speed < 0.0;
+        let changing = target_speed != 0.0 && reverse != self.snapshot.control.reverse;
+        let moving = self.snapshot.velocity.norm() >= self.config.gear_change_speed;
+        let gear = if reverse { "reverse" } else { "drive" };
+        match (changing && moving, self.gear_change_pending) {
+            (true, false) => info!("Stop {} before shifting to {gear}", self.vehicle_name),
+            (false, true) if changing => {
+                info!("{} stopped, shift to {gear}", self.vehicle_name)
+            }
+            _ => {}
+        }
+        self.gear_change_pending = changing && moving;
+        if self.gear_change_pending {
+            0.0
+        } else {
+            target_speed
+        }
+    }
+
+    /// Reset the controller when a new drive starts: after a gear change, a teleport or a
+    /// long stop
+    fn detect_new_drive(&mut self, target_speed: f32, timestamp: f64) {
+        let reverse = target_speed < 0.0;
+        let gear_changed = reverse != self.last_target_reverse;
+        self.last_target_reverse = reverse;
+
+        let position = self.snapshot.transform.translation.vector;
+        let teleported = self
+            .last_position
+            .map_or(false, |last| (position - last).norm() > TELEPORT_DISTANCE);
+        self.last_position = Some(position);
+        if teleported {
+            self.speed_filter.reset();
+        }
+
+        // Reset once per stop, when it lasted long enough
+        let standing_still = target_speed.abs() < STANDSTILL_SPEED
+            && self.snapshot.velocity.norm() < STANDSTILL_SPEED;
+        let mut long_stop = false;
+        if standing_still {
+            let since = *self.standstill_since.get_or_insert(timestamp);
+            long_stop =
+                !self.standstill_reset_done && timestamp - since >= self.config.standstill_reset;
+            self.standstill_reset_done |= long_stop;
+        } else {
+            self.standstill_since = None;
+            self.standstill_reset_done = false;
+        }
+
+        if gear_changed || teleported || long_stop {
+            debug!(
+                "Reset the controller of {} (gear changed: {gear_changed}, teleported: {teleported}, long stop: {long_stop})",
+                self.vehicle_name
+            );
+            self.reset_controller();
+        }
+    }
+
+    /// Whether the vehicle is held since Autoware isn't DRIVING, with `require_autoware_state`
+    fn update_waiting_for_driving(&mut self) -> bool {
+        if !self.config.require_autoware_state {
+            return false;
+        }
+        let state = *self.autoware_state.lock().unwrap();
+        let waiting = state != Some(autoware_state::DRIVING);
+        if waiting != self.waiting_for_driving {
+            if waiting {
+                info!(
+                    "Hold {} until Autoware is DRIVING (state: {state:?})",
+                    self.vehicle_name
+                );
+            } else {
+                info!("Autoware drives {}, apply its commands", self.vehicle_name);
+                // The commands of before are stale
+                self.reset_controller();
+            }
+            self.waiting_for_driving = waiting;
+        }
+        waiting
+    }
+
+    /// The period to step the control by, None to skip the update and leave the previous
+    /// control applied, e.g. when the loop stepped twice in a frame. After a longer pause,
+    /// the controller starts over and steps by `max_control_dt`, against integrator jumps.
+    fn control_elapsed(&mut self, elapsed_sec: f64) -> Option<f64> {
+        // NaN is skipped as well
+        if !(elapsed_sec >= self.config.min_control_dt) {
+            let count = self
+                .stats
+                .skipped_control_updates
+                .fetch_add(1, Ordering::Relaxed)
+                + 1;
+            if self.control_dt_log.ready() {
+                warn!(
+                    "Skipped the control update of {} after {elapsed_sec} s ({count} so far)",
+                    self.vehicle_name
+                );
+            }
+            return None;
+        }
+        if elapsed_sec > self.config.max_control_dt {
+            let count = self.stats.control_gaps.fetch_add(1, Ordering::Relaxed) + 1;
+            if self.control_dt_log.ready() {
+                warn!(
+                    "Reset the controller of {} after a gap of {elapsed_sec} s ({count} so far)",
+                    self.vehicle_name
+                );
+            }
+            self.reset_controller();
+            return Some(self.config.max_control_dt);
+        }
+        Some(elapsed_sec)
+    }
+
+    fn update_carla_control(&mut self, elapsed_sec: f64, timestamp: f64) {
+        self.release_delayed_commands(timestamp);
+        self.speed_limited = false;
+        let fallback = self.update_fallback(timestamp);
+        let waiting_for_driving = self.update_waiting_for_driving();
+        let (source, control) = match (self.mrm_behavior, self.control_path) {
+            _ if !self.control_enabled => ("frozen", self.apply_frozen()),
+            (MrmBehavior::ComfortableStop, _) => {
+                ("comfortable_stop", self.apply_comfortable_stop())
+            }
+            (MrmBehavior::EmergencyStop, _) => ("emergency_stop", self.apply_emergency_stop()),
+            (MrmBehavior::None, _) if fallback => ("fallback", self.apply_fallback()),
+            (MrmBehavior::None, _) if waiting_for_driving => {
+                ("waiting_for_driving", self.apply_frozen())
+            }
+            (MrmBehavior::None, _) if !self.engaged => ("disengaged", self.apply_frozen()),
+            (MrmBehavior::None, ControlPath::Ackermann) => (
+                "ackermann",
+                self.apply_ackermann_control(elapsed_sec, timestamp),
+            ),
+            (MrmBehavior::None, ControlPath::Raw) => ("raw", self.apply_raw_control()),
+        };
+        if self.signal_log.is_some() {
+            self.log_signals(timestamp, source, &control);
+        }
+        if self.speed_limited {
+            self.stats
+                .speed_limited_sec
+                .fetch_add(elapsed_sec, Ordering::Relaxed);
+        }
+        if self.config.sync_vehicle_lights {
+            self.sync_vehicle_lights(&control);
+        }
+    }
+
+    /// Make the commands current once their `command_delay` elapsed, like on arrival
+    fn release_delayed_commands(&mut self, timestamp: f64) {
+        if let Some(delayed) = &self.delayed_control_cmds {
+            for cmd in delayed.lock().unwrap().pop_due(timestamp) {
+                let history = self.current_ackermann_cmd.load().push(cmd);
+                self.current_ackermann_cmd.store(Arc::new(history));
+                self.last_control_cmd_time
+                    .store(timestamp, Ordering::Relaxed);
+            }
+        }
+        if let Some(delayed) = &self.delayed_actuation_cmds {
+            if let Some(cmd) = delayed.lock().unwrap().pop_due(timestamp).pop() {
+                *self.current_actuation_cmd.lock().unwrap() = cmd;
+                self.last_actuation_cmd_time
+                    .store(timestamp, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Put the status payloads once their `status_delay`, or the latency of the pose, elapsed
+    fn flush_delayed_status(&self) {
+        if self.config.status_delay <= 0.0 && self.pose_noise.config().latency_ms == 0 {
+            return;
+        }
+        self.status_publishers().for_each(StatusPublisher::flush);
+    }
+
+    fn status_publishers(&self) -> impl Iterator<Item = &StatusPublisher> {
+        [
+            &self.publisher_velocity,
+            &self.publisher_steer,
+            &self.publisher_gear,
+            &self.publisher_control,
+            &self.publisher_turnindicator,
+            &self.publisher_hazardlight,
+            &self.publisher_stats,
+            &self.publisher_battery,
+            &self.publisher_door,
+            &self.publisher_adapi_door,
+            &self.publisher_wheel_speeds,
+            &self.publisher_twist,
+            &self.publisher_pose,
+            &self.publisher_slip,
+            &self.publisher_footprint,
+            &self.publisher_headlights,
+            &self.publisher_mrm_comfortable_stop,
+            &self.publisher_mrm_emergency_stop,
+            &self.publisher_diagnostics,
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    /// The latest control_cmd whatever drives the vehicle, so a switch of the source shows
+    fn log_signals(&mut self, timestamp: f64, source: &'static str, control: &VehicleControl) {
+        let target = self
+            .current_ackermann_cmd
+            .load()
+            .target(timestamp, self.config.interpolate_control);
+        let (speed, _, _) = self.body_twist();
+        let (_, pitch, _) = self.snapshot.transform.rotation.euler_angles();
+        let row = SignalRow {
+            sim_time: timestamp,
+            source,
+            cmd_speed: target.speed,
+            cmd_acceleration: target.acceleration,
+            cmd_steering_tire_angle: target.steering_tire_angle / self.config.steering_ratio,
+            speed,
+            throttle: control.throttle,
+            brake: control.brake,
+            steer: control.steer,
+            pitch,
+            gear: self.snapshot.control.gear,
+        };
+        if let Some(signal_log) = &mut self.signal_log {
+            signal_log.push(row);
+        }
+    }
+
+    fn sync_vehicle_lights(&mut self, control: &VehicleControl) {
+        let mut lights = VehicleLightState::empty();
+        lights.set(
+            VehicleLightState::BRAKE,
+            control.brake > BRAKE_LIGHT_THRESHOLD,
+        );
+        lights.set(VehicleLightState::REVERSE, control.reverse);
+        self.update_light_state(
+            VehicleLightState::BRAKE | VehicleLightState::REVERSE,
+            lights,
+        );
+    }
+
+    /// Replace the `mask` bits of the light state with `lights`, keeping the others (e.g. indicators)
+    fn update_light_state(&mut self, mask: VehicleLightState, lights: VehicleLightState) {
+        if let Some(updated) = self.lights.command(mask, lights) {
+            self.actor.set_light_state(updated);
+        }
+    }
+
+    /// Apply the control, unless it repeats the last applied one within
+    /// `control_refresh_ticks`
+    fn send_control(&mut self, control: &VehicleControl) {
+        if self.control_deduplicator.should_apply(control) {
+            self.actor.apply_control(control);
+        } else {
+            self.stats
+                .skipped_control_applies
+                .fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// The CARLA steer (-1 to 1, positive to the right) of a commanded tire angle
+    fn carla_steer(&self, steering_tire_angle: f32) -> f32 {
+        control::carla_steer(
+            steering_tire_angle + self.steering_offset,
+            self.config.steering_convention,
+            self.max_steer_angle,
+        )
+    }
+
+    /// Whether the fallback drives the vehicle: no valid command of the active path yet,
+    /// the zenoh session lost its connectivity, or the latest command is older than the
+    /// timeout
+    fn update_fallback(&mut self, timestamp: f64) -> bool {
+        let last_cmd_time = match self.control_path {
+            ControlPath::Ackermann => &self.last_control_cmd_time,
+            ControlPath::Raw => &self.last_actuation_cmd_time,
+        }
+        .load(Ordering::Relaxed);
+        let reason = if last_cmd_time == f64::NEG_INFINITY {
+            Some("no valid command received yet")
+        } else if self.zenoh_lost {
+            Some("the zenoh session lost its connectivity")
+        } else if self
+            .config
+            .command_timeout
+            .map_or(false, |timeout| timestamp - last_cmd_time > timeout)
+        {
+            Some("the commands are stale")
+        } else {
+            None
+        };
+        match (reason, self.in_fallback) {
+            (Some(reason), false) => {
+                warn!(
+                    "{} falls back to {:?}: {reason}",
+                    self.vehicle_name, self.config.fallback_policy
+                );
+                self.stats.fallback_entries.fetch_add(1, Ordering::Relaxed);
+            }
+            (None, true) => {
+                info!("{} is driven by the commands again", self.vehicle_name);
+                // Release the hand brake of the fallback, the commands start from a standstill
+                self.commanded_hand_brake = false;
+                self.send_control(&VehicleControl {
+                    hand_brake: false,
+                    ..self.snapshot.control
+                });
+                self.reset_controller();
+            }
+            _ => {}
+        }
+        self.in_fallback = reason.is_some();
+        self.stats
+            .in_fallback
+            .store(self.in_fallback, Ordering::Relaxed);
+        self.in_fallback
+    }
+
+    /// Return the control of the fallback policy, keeping the steer
+    fn apply_fallback(&mut self) -> VehicleControl {
+        let (brake, hand_brake) = match self.config.fallback_policy {
+            FallbackPolicy::BrakeHold => (
+                1.0,
+                self.commanded_hand_brake || self.snapshot.velocity.norm() < STANDSTILL_SPEED,
+            ),
+            FallbackPolicy::Coast => (0.0, false),
+            FallbackPolicy::HandbrakeImmediate => (0.0, true),
+        };
+        self.commanded_hand_brake = hand_brake;
+        let control = self.hold_max_speed(VehicleControl {
+            throttle: 0.0,
+            brake,
+            hand_brake,
+            ..self.snapshot.control
+        });
+        self.send_control(&control);
+        control
+    }
+
+    /// How much the vehicle exceeds the speed ceiling beyond the margin, if it does (m/s)
+    fn overspeed(&self) -> Option<f32> {
+        let max_speed = self.config.max_speed?;
+        let overspeed = self.snapshot.velocity.norm() - max_speed - OVERSPEED_MARGIN;
+        (overspeed > 0.0).then_some(overspeed)
+    }
+
+    /// Brake if the vehicle still exceeds the speed ceiling, e.g. downhill, whatever the command.
+    /// The control is only changed, the caller applies it. The stops of the MRM brake on their
+    /// own, so they don't go through here.
+    fn hold_max_speed(&mut self, control: VehicleControl) -> VehicleControl {
+        let Some(overspeed) = self.overspeed() else {
+            return control;
+        };
+        self.speed_limited = true;
+        VehicleControl {
+            throttle: 0.0,
+            brake: (overspeed / OVERSPEED_MARGIN).clamp(MIN_OVERSPEED_BRAKE, 1.0),
+            ..control
+        }
+    }
+
+    /// Decelerate to a standstill with the Ackermann controller, keeping the steer
+    fn apply_comfortable_stop(&mut self) -> VehicleControl {
+        let steer = self.snapshot.control.steer;
+        self.actor
+            .apply_ackermann_control(&VehicleAckermannControl {
+                steer,
+                steer_speed: 0.0,
+                speed: 0.0,
+                acceleration: self.config.mrm_comfortable_deceleration,
+                jerk: 0.0,
+            });
+        self.control_deduplicator.invalidate();
+        self.snapshot.control
+    }
+
+    /// Hold the brake while the operator froze the vehicle or Autoware isn't driving, keeping
+    /// the steer
+    fn apply_frozen(&mut self) -> VehicleControl {
+        let control = VehicleControl {
+            throttle: 0.0,
+            brake: 1.0,
+            hand_brake: self.commanded_hand_brake,
+            ..self.snapshot.control
+        };
+        self.send_control(&control);
+        control
+    }
+
+    /// Full brake, keeping the steer
+    fn apply_emergency_stop(&mut self) -> VehicleControl {
+        let control = VehicleControl {
+            throttle: 0.0,
+            brake: 1.0,
+            hand_brake: self.commanded_hand_brake,
+            ..self.snapshot.control
+        };
+        self.send_control(&control);
+        control
+    }
+
+    /// Return the applied control
+    fn apply_raw_control(&mut self) -> VehicleControl {
+        let ActuationCommand {
+            accel_cmd,
+            brake_cmd,
+            steer_cmd,
+        } = *self.current_actuation_cmd.lock().unwrap();
+        debug!(
+            "Autoware => Carla: accel_cmd:{} brake_cmd:{} steer_cmd:{}",
+            accel_cmd, brake_cmd, steer_cmd
+        );
+        // steer_cmd is a tire angle like the Ackermann command
+        let steering_tire_angle = steer_cmd as f32 / self.config.steering_ratio;
+        let control = VehicleControl {
+            throttle: (accel_cmd as f32).clamp(0.0, 1.0),
+            steer: self.carla_steer(steering_tire_angle),
+            brake: (brake_cmd as f32).clamp(0.0, 1.0),
+            hand_brake: self.commanded_hand_brake,
+            ..self.snapshot.control
+        };
+        let control = self.shift_gear(control);
+        let control = self.hold_max_speed(control);
+        self.send_control(&control);
+        control
+    }
+
+    /// Put the gear in the control, if the bridge drives the gearbox.
+    ///
+    /// Reverse is gear -1, a numbered gear command wins over the speed table.
+    fn shift_gear(&mut self, control: VehicleControl) -> VehicleControl {
+        let speed = self.snapshot.velocity.norm();
+        let commanded_gear = *self.commanded_gear.lock().unwrap();
+        let Some(gear_selector) = &mut self.gear_selector else {
+            return control;
+        };
+        let gear = match commanded_gear {
+            _ if control.reverse => -1,
+            Some(gear) => gear,
+            None => gear_selector.select(speed),
+        };
+        VehicleControl {
+            manual_gear_shift: true,
+            gear,
+            ..control
+        }
+    }
+
+    /// Return the control the longitudinal controller applied
+    fn apply_ackermann_control(&mut self, elapsed_sec: f64, timestamp: f64) -> VehicleControl {
+        let ControlTarget {
+            speed,
+            acceleration,
+            jerk,
+            steering_tire_angle,
+        } = self
+            .current_ackermann_cmd
+            .load()
+            .target(timestamp, self.config.interpolate_control);
+        let steering_tire_angle = steering_tire_angle / self.config.steering_ratio;
+        let speed = match self.config.max_speed {
+            Some(max_speed) if speed.abs() > max_speed => {
+                self.speed_limited = true;
+                speed.clamp(-max_speed, max_speed)
+            }
+            _ => speed,
+        };
+        let speed = self.gate_gear_change(speed);
+        self.detect_new_drive(speed, timestamp);
+        let acceleration = match &mut self.jerk_limiter {
+            Some(jerk_limiter) => jerk_limiter.limit(acceleration, jerk, elapsed_sec),
+            None => acceleration,
+        };
+        debug!(
+            "Autoware => Carla: speed:{} accel:{} steering_tire_angle:{}",
+            speed,
+            acceleration,
+            steering_tire_angle.to_degrees() * self.config.steering_convention.sign()
+        );
+        let current_speed = self.speed.load(Ordering::Relaxed);
+        let (_, raw_pitch_radians, _) = self.snapshot.transform.rotation.euler_angles();
+        let pitch_radians = self.config.pitch_compensation.apply(raw_pitch_radians);
+
+        let steer = self.carla_steer(steering_tire_angle);
+
+        // Compute the steering speed
+        let steer_speed = if steering_tire_angle.to_degrees().abs() < 3.0 {
+            0.0
+        } else if steering_tire_angle.to_degrees() > 0.0 {
+            0.1
+        } else {
+            -0.1
+        };
+
+        let request = TargetRequest {
+            speed,
+            acceleration,
+            jerk,
+            steer,
+            steer_speed,
+            current_speed,
+            pitch: pitch_radians,
+        };
+        let output = self.longitudinal_controller.control(&request, elapsed_sec);
+        let applied = match self.smooth(output, elapsed_sec) {
+            Output::Ackermann(control) => {
+                // The target speed is already capped, CARLA brakes down to it
+                if self.overspeed().is_some() {
+                    self.speed_limited = true;
+                }
+                self.actor.apply_ackermann_control(&control);
+                self.control_deduplicator.invalidate();
+                // The server turns it into a control on the next tick
+                self.snapshot.control
+            }
+            Output::Raw(control) => {
+                let control = self.shift_gear(control);
+                let control = self.hold_max_speed(control);
+                self.send_control(&control);
+                control
+            }
+        };
+
+        debug!(
+            "Autoware => Carla: elapse_sec:{} current_speed:{} pitch_radians:{} (measured:{}, mode:{:?})",
+            elapsed_sec,
+            current_speed,
+            pitch_radians,
+            raw_pitch_radians,
+            self.config.pitch_compensation
+        );
+        applied
+    }
+
+    /// Low-pass the steer and the pedals of the controller, if configured
+    fn smooth(&mut self, output: Output, elapsed_sec: f64) -> Output {
+        let mut steer_filter = |steer| match &mut self.steer_filter {
+            Some(filter) => filter.filter(steer, elapsed_sec),
+            None => steer,
+        };
+        match output {
+            Output::Ackermann(control) => Output::Ackermann(VehicleAckermannControl {
+                steer: steer_filter(control.steer),
+                ..control
+            }),
+            Output::Raw(control) => {
+                let steer = steer_filter(control.steer);
+                let (throttle, brake) = match &mut self.pedal_filters {
+                    Some((throttle_filter, brake_filter)) => (
+                        throttle_filter.filter(control.throttle, elapsed_sec),
+                        brake_filter.filter(control.brake, elapsed_sec),
+                    ),
+                    None => (control.throttle, control.brake),
+                };
+                Output::Raw(VehicleControl {
+                    throttle,
+                    steer,
+                    brake,
+                    ..control
+                })
+            }
+        }
+    }
+
+    pub fn vehicle_name(&self) -> &str {
+        &self.vehicle_name
+    }
+}
+
+/// What the bridge derives from the physics control, at the creation and at every refresh
+struct PhysicsConstants {
+    /// A bicycle or a motorcycle, with a single front wheel
+    two_wheeled: bool,
+    /// The tire angle at full steer (degrees), bounded by the front wheel of a two-wheeler
+    max_steer_angle: f32,
+    slip_geometry: Option<SlipGeometry>,
+}
+
+impl PhysicsConstants {
+    fn derive(physics: Option<&VehiclePhysicsControl>, transform: &Isometry3<f32>) -> Self {
+        let two_wheeled = physics.map_or(false, |physics| physics.wheels.len() == 2);
+        let max_steer_angle = match physics {
+            Some(physics) if two_wheeled && physics.wheels[0].max_steer_angle > 0.0 => {
+                physics.wheels[0].max_steer_angle
+            }
+            _ => MAX_STEER_ANGLE,
+        };
+        PhysicsConstants {
+            two_wheeled,
+            max_steer_angle,
+            slip_geometry: physics
+                .and_then(|physics| SlipGeometry::from_physics(physics, transform)),
+        }
+    }
+}
+
+/// The manual gear of a forward gear command: None for DRIVE, which leaves it to the speed
+/// table, or the number of DRIVE_2 to DRIVE_18. The other commands aren't forward gears.
+fn forward_gear(command: u8) -> Option<Option<i32>> {
+    match command {
+        GEAR_COMMAND_DRIVE => Some(None),
+        // DRIVE_2 is the second gear
+        GEAR_COMMAND_DRIVE_2..=GEAR_COMMAND_DRIVE_18 => {
+            Some(Some((command - GEAR_COMMAND_DRIVE + 1) as i32))
+        }
+        _ => None,
+    }
+}
+
+/// A steering offset of `bridge/steering_offset`, in radians within the limit
+fn parse_steering_offset(offset: &str) -> std::result::Result<f32, String> {
+    let offset = offset.parse::<f32>().map_err(|e| e.to_string())?;
+    if !offset.is_finite() || offset.abs() > MAX_STEERING_OFFSET {
+        return Err(format!("{offset} rad is beyond {MAX_STEERING_OFFSET} rad"));
+    }
+    Ok(offset)
+}
+
+/// The `duration=<sec>` of a calibration query, the default without a valid one
+fn calibration_duration(parameters: &str) -> f64 {
+    parameters
+        .split('&')
+        .find_map(|parameter| parameter.strip_prefix("duration="))
+        .and_then(|duration| duration.parse::<f64>().ok())
+        .filter(|duration| duration.is_finite() && *duration > 0.0)
+        .map_or(CALIBRATION_DEFAULT_SEC, |duration| {
+            duration.min(CALIBRATION_MAX_SEC)
+        })
+}
+
+/// A command to stand still, until the first one is received
+fn stop_command() -> AckermannControlCommand {
+    AckermannControlCommand {
+        stamp: TimeStamp::ZERO.to_msg(),
+        lateral: AckermannLateralCommand {
+            stamp: TimeStamp::ZERO.to_msg(),
+            steering_tire_angle: 0.0,
+            steering_tire_rotation_rate: 0.0,
+        },
+        longitudinal: LongitudinalCommand {
+            stamp: TimeStamp::ZERO.to_msg(),
+            speed: 0.0,
+            acceleration: 0.0,
+            jerk: 0.0,
+        },
+    }
+}
+
+/// A `VehicleBridge` with some groups of topics enabled or disabled, on top of the
+/// `[vehicle.topics]` of the configuration. A disabled group declares nothing on zenoh.
+pub struct VehicleBridgeBuilder<'c> {
+    z_session: Arc<Session>,
+    actor: Box<dyn VehicleActor>,
+    bridge_config: &'c BridgeConfig,
+    namespaces: Arc<Mutex<NamespaceRegistry>>,
+    toggles: TopicToggles,
+}
+
+// The toggles are for the embedders, only the tests reach them inside the crate
+#[cfg_attr(not(feature = "test-support"), allow(dead_code))]
+impl<'c> VehicleBridgeBuilder<'c> {
+    pub fn with_velocity_report(mut self, enabled: bool) -> Self {
+        self.toggles.velocity_report = Some(enabled);
+        self
+    }
+
+    pub fn with_steering_report(mut self, enabled: bool) -> Self {
+        self.toggles.steering_report = Some(enabled);
+        self
+    }
+
+    /// The gear report and gear_cmd
+    pub fn with_gear(mut self, enabled: bool) -> Self {
+        self.toggles.gear = Some(enabled);
+        self
+    }
+
+    /// Without the control, the bridge never actuates the vehicle
+    pub fn with_control(mut self, enabled: bool) -> Self {
+        self.toggles.control = Some(enabled);
+        self
+    }
+
+    /// The turn indicators, the hazard lights and the headlights
+    pub fn with_lights(mut self, enabled: bool) -> Self {
+        self.toggles.lights = Some(enabled);
+        self
+    }
+
+    pub fn with_battery(mut self, enabled: bool) -> Self {
+        self.toggles.battery = Some(enabled);
+        self
+    }
+
+    pub fn with_doors(mut self, enabled: bool) -> Self {
+        self.toggles.doors = Some(enabled);
+        self
+    }
+
+    pub fn with_mrm(mut self, enabled: bool) -> Self {
+        self.toggles.mrm = Some(enabled);
+        self
+    }
+
+    pub fn with_physics(mut self, enabled: bool) -> Self {
+        self.toggles.physics = Some(enabled);
+        self
+    }
+
+    /// The stats and the diagnostics
+    pub fn with_diagnostics(mut self, enabled: bool) -> Self {
+        self.toggles.diagnostics = Some(enabled);
+        self
+    }
+
+    /// Set all the groups of a `[vehicle.topics]` table at once
+    pub fn with_topics(mut self, toggles: TopicToggles) -> Self {
+        self.toggles = toggles;
+        self
+    }
+
+    pub fn build<'a>(self) -> Result<VehicleBridge<'a>> {
+        VehicleBridge::create(self)
+    }
+}
+
+impl<'a> ActorBridge for VehicleBridge<'a> {
+    /// Read the vehicle and take the pending commands of the callbacks, every tick, so the
+    /// status and the control see the same snapshot whenever each of them runs
+    fn step(&mut self, _elapsed_sec: f64, tick: &TickSnapshot) -> Result<()> {
+        let timestamp = tick.stamp.to_secs_f64();
+        self.sim_time.store(timestamp, Ordering::Relaxed);
+        self.zenoh_lost = tick.zenoh_lost;
+        self.flush_delayed_status();
+        let motion = tick.motion(self.actor.id());
+        self.snapshot = ActorSnapshot::read(self.actor.as_ref(), self.two_wheeled, motion);
+        let (longitudinal_velocity, _, _) = self.body_twist();
+        let speed = self.speed_filter.filter(longitudinal_velocity, timestamp);
+        self.speed.store(speed, Ordering::Relaxed);
+        self.update_hand_brake(self.snapshot.control.hand_brake);
+        self.lights.refresh(self.actor.as_ref(), timestamp);
+        self.stats
+            .light_state
+            .store(self.lights.state().bits(), Ordering::Relaxed);
+        self.update_battery(timestamp);
+        self.update_headlights();
+        self.update_blinkers();
+        self.update_doors();
+        self.update_physics();
+        self.dispatch_services();
+        self.update_control_path();
+        self.update_control_enable();
+        self.update_engage();
+        self.update_mrm_behavior();
+        self.update_steering_offset();
+        self.last_step_time = Some(timestamp);
+        Ok(())
+    }
+
+    fn publish_status(&mut self, stamp: TimeStamp) -> Result<()> {
+        self.pub_current_velocity(stamp)?;
+        self.pub_current_steer(stamp)?;
+        self.pub_current_gear(stamp)?;
+        self.pub_current_control(stamp)?;
+        self.pub_current_indicator(stamp)?;
+        self.pub_hazard_light(stamp)?;
+        self.pub_headlights(stamp)?;
+        self.pub_battery(stamp)?;
+        self.pub_mrm_status(stamp)?;
+        self.pub_door_status(stamp)?;
+        self.pub_wheel_speeds(stamp)?;
+        self.pub_twist(stamp)?;
+        self.pub_pose(stamp)?;
+        self.pub_slip(stamp)?;
+        self.pub_footprint(stamp)?;
+        self.pub_stats(stamp)
+    }
+
+    fn apply_control(&mut self, stamp: TimeStamp, elapsed_sec: f64) {
+        // Without the control topics, the vehicle is left to CARLA, e.g. to the autopilot
+        if !self.config.topic_groups.control {
+            return;
+        }
+        let Some(elapsed_sec) = self.control_elapsed(elapsed_sec) else {
+            return;
+        };
+        let timestamp = stamp.to_secs_f64();
+        self.update_calibration(elapsed_sec, timestamp);
+        self.replay_commands(timestamp);
+        self.update_carla_control(elapsed_sec, timestamp);
+    }
+
+    fn kind(&self) -> BridgeKind {
+        BridgeKind::Vehicle
+    }
+
+    fn describe(&self) -> Option<BridgeDescription> {
+        Some(BridgeDescription {
+            actor_id: self.identity.actor_id,
+            namespace: self.vehicle_name.clone(),
+            kind: String::from("vehicle"),
+            raw_name: Some(self.identity.role_name.clone()),
+            keys: self.declared.clone(),
+        })
+    }
+
+    fn reset(&mut self, report: &mut ResetReport) {
+        self.actor.set_transform(&self.spawn_transform);
+        self.actor.set_target_velocity(&Vector3::zeros());
+        self.actor.set_target_angular_velocity(&Vector3::zeros());
+        // Until Autoware sends the next command, the vehicle stands still
+        self.current_ackermann_cmd
+            .store(Arc::new(CommandHistory::new(stop_command())));
+        *self.current_actuation_cmd.lock().unwrap() = ActuationCommand::default();
+        if let Some(delayed) = &self.delayed_control_cmds {
+            delayed.lock().unwrap().clear();
+        }
+        if let Some(delayed) = &self.delayed_actuation_cmds {
+            delayed.lock().unwrap().clear();
+        }
+        self.status_publishers()
+            .for_each(StatusPublisher::clear_delayed);
+        self.reset_controller();
+        self.speed_filter.reset();
+        self.control_deduplicator.invalidate();
+        self.last_position = Some(self.spawn_transform.translation.vector);
+        self.standstill_since = None;
+        self.standstill_reset_done = false;
+        self.gear_change_pending = false;
+        if let Some(gear_selector) = &mut self.gear_selector {
+            gear_selector.reset();
+        }
+        self.mrm_behavior = MrmBehavior::None;
+        *self.pending_mrm_behavior.lock().unwrap() = None;
+        // The fallback holds the vehicle until Autoware sends the commands again
+        self.last_control_cmd_time
+            .store(f64::NEG_INFINITY, Ordering::Relaxed);
+        self.last_actuation_cmd_time
+            .store(f64::NEG_INFINITY, Ordering::Relaxed);
+        self.battery = BatteryModel::new(self.config.battery);
+        self.stats.reset();
+        info!("Reset {} to its spawn pose", self.vehicle_name);
+        report.vehicles.push(self.vehicle_name.clone());
+    }
+}
+
+impl<'a> Drop for VehicleBridge<'a> {
+    fn drop(&mut self) {
+        info!("Remove vehicle name {}", self.vehicle_name());
+        self.namespaces.lock().unwrap().release(self.actor.id());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bridge::{
+            kinematic_vehicle::{KinematicConfig, KinematicVehicle},
+            mock_vehicle::MockVehicle,
+        },
+        config::NamespaceSource,
+        sensor_noise::MIN_POSE_VARIANCE,
+    };
+    use std::time::Duration;
+    use zenoh_ros_type::autoware_auto_vehicle_msgs::{SteeringReport, VelocityReport};
+
+    /// The tick period of the tests (s)
+    const DT: f64 = 0.05;
+    const WAIT: Duration = Duration::from_secs(2);
+
+    /// A peer without scouting, which only talks to itself
+    fn session() -> Arc<Session> {
+        let mut config = Config::default();
+        config
+            .insert_json5("scouting/multicast/enabled", "false")
+            .unwrap();
+        Arc::new(zenoh::open(config).res().unwrap())
+    }
+
+    fn wait_until(mut condition: impl FnMut() -> bool) {
+        let start = Instant::now();
+        while !condition() {
+            assert!(start.elapsed() < WAIT, "timed out");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    fn control_command(
+        timestamp: f64,
+        speed: f32,
+        acceleration: f32,
+        steering_tire_angle: f32,
+    ) -> AckermannControlCommand {
+        let stamp = TimeStamp::from_secs_f64(timestamp);
+        AckermannControlCommand {
+            stamp: stamp.to_msg(),
+            lateral: AckermannLateralCommand {
+                stamp: stamp.to_msg(),
+                steering_tire_angle,
+                steering_tire_rotation_rate: 0.0,
+            },
+            longitudinal: LongitudinalCommand {
+                stamp: stamp.to_msg(),
+                speed,
+                acceleration,
+                jerk: 0.0,
+            },
+        }
+    }
+
+    /// A bridge of `autoware_ego` over a mock vehicle, stepped like the main loop
+    struct Harness {
+        session: Arc<Session>,
+        vehicle: MockVehicle,
+        bridge: VehicleBridge<'static>,
+        config: BridgeConfig,
+        frame: u64,
+    }
+
+    impl Harness {
+        fn new(config: BridgeConfig) -> Harness {
+            let session = session();
+            let vehicle = MockVehicle::new(1, "autoware_ego");
+            let namespaces = Arc::new(Mutex::new(NamespaceRegistry::new(
+                NamespaceSource::default(),
+            )));
+            let bridge = VehicleBridge::new(
+                session.clone(),
+                Box::new(vehicle.clone()),
+                &config,
+                namespaces,
+            )
+            .unwrap();
+            Harness {
+                session,
+                vehicle,
+                bridge,
+                config,
+                frame: 0,
+            }
+        }
+
+        fn key(&self, topic: &str) -> String {
+            self.config.topics.key("ego", topic)
+        }
+
+        fn timestamp(&self) -> f64 {
+            self.frame as f64 * DT
+        }
+
+        /// Put a control command stamped now, and wait for the bridge to take it
+        fn send_control(&self, speed: f32, acceleration: f32, steering_tire_angle: f32) {
+            let cmd = control_command(self.timestamp(), speed, acceleration, steering_tire_angle);
+            put_control(
+                &self.session,
+                &self.bridge,
+                self.key(topics::CONTROL_CMD),
+                &cmd,
+            );
+        }
+
+        /// Step, publish and control the bridge like a tick of the main loop
+        fn tick(&mut self) {
+            self.frame += 1;
+            let stamp = TimeStamp::from_secs_f64(self.timestamp());
+            let tick = TickSnapshot::without_world(stamp, self.frame);
+            run_tick_now(&mut self.bridge, &tick);
+        }
+    }
+
+    fn put_control(
+        session: &Session,
+        bridge: &VehicleBridge,
+        key: String,
+        cmd: &AckermannControlCommand,
+    ) {
+        let payload = cdr_utils::serialize(cmd, Endianness::Little).unwrap();
+        let before = bridge.current_ackermann_cmd.load_full();
+        session.put(key, payload).res().unwrap();
+        wait_until(|| !Arc::ptr_eq(&before, &bridge.current_ackermann_cmd.load_full()));
+    }
+
+    fn run_tick_now(bridge: &mut VehicleBridge, tick: &TickSnapshot) {
+        bridge.step(DT, tick).unwrap();
+        bridge.publish_status(tick.stamp).unwrap();
+        bridge.apply_control(tick.stamp, DT);
+    }
+
+    #[test]
+    fn only_the_prefixed_vehicles_are_bridged() {
+        let session = session();
+        let namespaces = Arc::new(Mutex::new(NamespaceRegistry::new(
+            NamespaceSource::default(),
+        )));
+        let npc = MockVehicle::new(2, "hero");
+        let result =
+            VehicleBridge::new(session, Box::new(npc), &BridgeConfig::default(), namespaces);
+        assert!(matches!(result, Err(Error::Npc { npc_role_name }) if npc_role_name == "hero"));
+    }
+
+    #[test]
+    fn hold_the_brake_before_the_first_command() {
+        let mut harness = Harness::new(BridgeConfig::default());
+        harness.tick();
+        let control = *harness.vehicle.applied_controls().last().unwrap();
+        assert_eq!(control.throttle, 0.0);
+        assert_eq!(control.brake, 1.0);
+        // Standing still, the fallback also engages the hand brake
+        assert!(control.hand_brake);
+        assert!(harness.vehicle.applied_ackermann_controls().is_empty());
+    }
+
+    #[test]
+    fn a_control_command_actuates_the_vehicle() {
+        let mut harness = Harness::new(BridgeConfig::default());
+        harness.send_control(5.0, 1.0, 0.1);
+        harness.tick();
+        let applied = harness.vehicle.applied_ackermann_controls();
+        let control = applied.last().unwrap();
+        assert_eq!(control.speed, 5.0);
+        assert_eq!(control.acceleration, 1.0);
+        assert_eq!(
+            control.steer,
+            control::carla_steer(0.1, SteeringConvention::default(), MAX_STEER_ANGLE)
+        );
+        assert!(harness.vehicle.applied_controls().is_empty());
+    }
+
+    fn put_engage(harness: &Harness, engage: bool) {
+        let msg = Engage {
+            stamp: TimeStamp::from_secs_f64(harness.timestamp()).to_msg(),
+            engage,
+        };
+        let payload = cdr_utils::serialize(&msg, Endianness::Little).unwrap();
+        harness
+            .session
+            .put(harness.key(topics::ENGAGE), payload)
+            .res()
+            .unwrap();
+        wait_until(|| harness.bridge.pending_engage.lock().unwrap().is_some());
+    }
+
+    #[test]
+    fn a_disengaged_vehicle_holds_the_brake() {
+        let mut harness = Harness::new(BridgeConfig::default());
+        harness.send_control(5.0, 1.0, 0.0);
+        harness.tick();
+        assert_eq!(harness.vehicle.applied_ackermann_controls().len(), 1);
+
+        put_engage(&harness, false);
+        harness.vehicle.clear_applied();
+        harness.send_control(5.0, 1.0, 0.0);
+        harness.tick();
+        assert!(harness.bridge.control_enabled);
+        assert!(harness.vehicle.applied_ackermann_controls().is_empty());
+        let control = *harness.vehicle.applied_controls().last().unwrap();
+        assert_eq!(control.throttle, 0.0);
+        assert_eq!(control.brake, 1.0);
+
+        put_engage(&harness, true);
+        harness.send_control(5.0, 1.0, 0.0);
+        harness.tick();
+        assert_eq!(harness.vehicle.applied_ackermann_controls().len(), 1);
+    }
+
+    #[test]
+    fn an_invalid_command_keeps_the_previous_one() {
+        let mut harness = Harness::new(BridgeConfig::default());
+        harness.send_control(5.0, 1.0, 0.0);
+        let cmd = control_command(0.0, f32::NAN, 1.0, 0.0);
+        harness
+            .session
+            .put(
+                harness.key(topics::CONTROL_CMD),
+                cdr_utils::serialize(&cmd, Endianness::Little).unwrap(),
+            )
+            .res()
+            .unwrap();
+        wait_until(|| {
+            harness
+                .bridge
+                .stats
+                .rejected_commands
+                .load(Ordering::Relaxed)
+                == 1
+        });
+        harness.tick();
+        let applied = harness.vehicle.applied_ackermann_controls();
+        assert_eq!(applied.last().unwrap().speed, 5.0);
+    }
+
+    #[test]
+    fn publish_the_status_of_the_vehicle() {
+        let mut harness = Harness::new(BridgeConfig::default());
+        let velocity_subscriber = harness
+            .session
+            .declare_subscriber(harness.key(topics::VELOCITY_STATUS))
+            .res()
+            .unwrap();
+        let steering_subscriber = harness
+            .session
+            .declare_subscriber(harness.key(topics::STEERING_STATUS))
+            .res()
+            .unwrap();
+        harness.vehicle.set_velocity(Vector3::new(3.0, 0.0, 0.0));
+        harness.vehicle.set_front_steer_angles([10.0, 20.0]);
+        harness.tick();
+
+        let sample = velocity_subscriber.recv_timeout(WAIT).unwrap();
+        let velocity: VelocityReport =
+            cdr_utils::deserialize(&sample.payload.contiguous()).unwrap();
+        assert_eq!(velocity.header.frame_id, "base_link");
+        assert_eq!(velocity.header.stamp, TimeStamp::from_secs_f64(DT).to_msg());
+        assert_eq!(velocity.longitudinal_velocity, 3.0);
+
+        let sample = steering_subscriber.recv_timeout(WAIT).unwrap();
+        let steering: SteeringReport =
+            cdr_utils::deserialize(&sample.payload.contiguous()).unwrap();
+        assert_eq!(
+            steering.steering_tire_angle,
+            control::wheel_tire_angle([10.0, 20.0], false, SteeringConvention::default())
+        );
+    }
+
+    #[test]
+    fn the_velocity_and_the_twist_share_the_body_frame() {
+        let mut config = BridgeConfig::default();
+        config.vehicle.publish_twist = true;
+        let mut harness = Harness::new(config);
+        let velocity_subscriber = harness
+            .session
+            .declare_subscriber(harness.key(topics::VELOCITY_STATUS))
+            .res()
+            .unwrap();
+        let twist_subscriber = harness
+            .session
+            .declare_subscriber(harness.key(topics::TWIST_WITH_COVARIANCE))
+            .res()
+            .unwrap();
+        // Heading along CARLA's y, so the forward speed is along it and the drift along -x
+        let heading = Isometry3::rotation(Vector3::z() * std::f32::consts::FRAC_PI_2);
+        harness.vehicle.set_pose(heading);
+        harness.vehicle.set_velocity(Vector3::new(-0.5, 3.0, 0.0));
+        // Turning right in CARLA, clockwise from above in ROS
+        harness
+            .vehicle
+            .set_angular_velocity(Vector3::new(0.0, 0.0, 30.0));
+        // The wheels don't move the heading rate
+        harness.vehicle.set_front_steer_angles([10.0, 10.0]);
+        harness.tick();
+        let (longitudinal, lateral, yaw_rate) = control::body_twist(
+            &heading,
+            &Vector3::new(-0.5, 3.0, 0.0),
+            &Vector3::new(0.0, 0.0, 30.0),
+        );
+        assert!((longitudinal - 3.0).abs() < 1e-5);
+        assert!((yaw_rate + 30f32.to_radians()).abs() < 1e-5);
+
+        let sample = velocity_subscriber.recv_timeout(WAIT).unwrap();
+        let velocity: VelocityReport =
+            cdr_utils::deserialize(&sample.payload.contiguous()).unwrap();
+        assert_eq!(velocity.lateral_velocity, lateral);
+        assert_eq!(velocity.heading_rate, yaw_rate);
+
+        let sample = twist_subscriber.recv_timeout(WAIT).unwrap();
+        let twist: TwistWithCovarianceStamped =
+            cdr_utils::deserialize(&sample.payload.contiguous()).unwrap();
+        assert_eq!(twist.header.frame_id, "base_link");
+        assert_eq!(
+            twist.twist.twist.linear,
+            [longitudinal as f64, lateral as f64, 0.0]
+        );
+        assert_eq!(twist.twist.twist.angular, [0.0, 0.0, yaw_rate as f64]);
+        let variances = BridgeConfig::default().vehicle.twist_covariance;
+        for (axis, variance) in variances.iter().enumerate() {
+            assert_eq!(twist.twist.covariance[axis][axis], *variance);
+        }
+        assert_eq!(twist.twist.covariance[0][5], 0.0);
+    }
+
+    #[test]
+    fn the_ground_truth_pose_comes_after_its_latency() {
+        let mut config = BridgeConfig::default();
+        config.localization.publish_pose = true;
+        config.localization.latency_ms = 100;
+        let mut harness = Harness::new(config);
+        let subscriber = harness
+            .session
+            .declare_subscriber(harness.key(topics::POSE_WITH_COVARIANCE))
+            .res()
+            .unwrap();
+        let pose = Isometry3::new(Vector3::new(10.0, 5.0, 0.5), Vector3::z() * 0.3);
+        harness.vehicle.set_pose(pose);
+        harness.tick();
+        assert!(subscriber.recv_timeout(Duration::from_millis(100)).is_err());
+        for _ in 0..3 {
+            harness.tick();
+        }
+        let sample = subscriber.recv_timeout(WAIT).unwrap();
+        let pose_msg: PoseWithCovarianceStamped =
+            cdr_utils::deserialize(&sample.payload.contiguous()).unwrap();
+        // The pose of the first tick
+        assert!((control::time_to_secs(&pose_msg.header.stamp) - DT).abs() < 1e-9);
+        assert_eq!(pose_msg.header.frame_id, "map");
+        // Without noise, exactly the transform of the actor
+        assert_eq!(
+            pose_msg.pose.pose,
+            Pose {
+                position: utils::carla_to_ros_position(10.0, 5.0, 0.5),
+                orientation: utils::carla_to_ros_orientation(&pose.rotation),
+            }
+        );
+        assert_eq!(pose_msg.pose.covariance[0][0], MIN_POSE_VARIANCE);
+        assert_eq!(pose_msg.pose.covariance[0][1], 0.0);
+    }
+
+    /// The steering report after a tick with the front wheels at `front_steer_angles`
+    fn reported_tire_angle(harness: &mut Harness, front_steer_angles: [f32; 2]) -> f32 {
+        let subscriber = harness
+            .session
+            .declare_subscriber(harness.key(topics::STEERING_STATUS))
+            .res()
+            .unwrap();
+        harness.vehicle.set_front_steer_angles(front_steer_angles);
+        harness.tick();
+        let sample = subscriber.recv_timeout(WAIT).unwrap();
+        let steering: SteeringReport =
+            cdr_utils::deserialize(&sample.payload.contiguous()).unwrap();
+        steering.steering_tire_angle
+    }
+
+    #[test]
+    fn report_the_mean_of_the_asymmetric_front_wheels() {
+        let mut harness = Harness::new(BridgeConfig::default());
+        // Turning left, the inner left wheel turns more
+        let left = reported_tire_angle(&mut harness, [-25.0, -15.0]);
+        assert!((left - 20f32.to_radians()).abs() < 1e-6);
+        // Turning right, the inner right wheel turns more
+        let right = reported_tire_angle(&mut harness, [15.0, 25.0]);
+        assert!((right + 20f32.to_radians()).abs() < 1e-6);
+        assert_eq!(harness.bridge.measured_tire_angle(), right);
+
+        let mut config = BridgeConfig::default();
+        config.vehicle.steering_convention = SteeringConvention::RightPositive;
+        let mut harness = Harness::new(config);
+        let right = reported_tire_angle(&mut harness, [15.0, 25.0]);
+        assert!((right - 20f32.to_radians()).abs() < 1e-6);
+    }
+
+    fn has_previous_command(harness: &Harness) -> bool {
+        harness
+            .bridge
+            .current_ackermann_cmd
+            .load()
+            .previous
+            .is_some()
+    }
+
+    #[test]
+    fn a_long_stop_resets_the_controller() {
+        let mut harness = Harness::new(BridgeConfig::default());
+        harness.send_control(0.0, 0.0, 0.0);
+        harness.tick();
+        harness.send_control(0.0, 0.0, 0.0);
+        // A short stop keeps the state
+        for _ in 0..10 {
+            harness.tick();
+        }
+        assert!(has_previous_command(&harness));
+        // Past `standstill_reset`
+        for _ in 0..15 {
+            harness.tick();
+        }
+        assert!(!has_previous_command(&harness));
+    }
+
+    #[test]
+    fn a_gear_change_or_a_teleport_resets_the_controller() {
+        let mut harness = Harness::new(BridgeConfig::default());
+        harness.send_control(1.0, 0.0, 0.0);
+        harness.tick();
+        harness.send_control(-1.0, 0.0, 0.0);
+        assert!(has_previous_command(&harness));
+        harness.tick();
+        assert!(!has_previous_command(&harness));
+
+        harness.send_control(-1.0, 0.0, 0.0);
+        harness.tick();
+        assert!(has_previous_command(&harness));
+        harness
+            .vehicle
+            .set_pose(Isometry3::translation(TELEPORT_DISTANCE + 1.0, 0.0, 0.0));
+        harness.tick();
+        assert!(!has_previous_command(&harness));
+    }
+
+    /// A tick of the main loop whose control steps by `elapsed_sec` instead of `DT`
+    fn tick_control_after(harness: &mut Harness, elapsed_sec: f64) {
+        harness.frame += 1;
+        let stamp = TimeStamp::from_secs_f64(harness.timestamp());
+        let tick = TickSnapshot::without_world(stamp, harness.frame);
+        harness.bridge.step(DT, &tick).unwrap();
+        harness.bridge.publish_status(stamp).unwrap();
+        harness.bridge.apply_control(stamp, elapsed_sec);
+    }
+
+    fn control_guard_counts(harness: &Harness) -> (u64, u64) {
+        let stats = &harness.bridge.stats;
+        (
+            stats.skipped_control_updates.load(Ordering::Relaxed),
+            stats.control_gaps.load(Ordering::Relaxed),
+        )
+    }
+
+    #[test]
+    fn the_control_steps_by_the_tick_period() {
+        let mut harness = Harness::new(BridgeConfig::default());
+        harness.send_control(5.0, 1.0, 0.0);
+        for _ in 0..20 {
+            harness.tick();
+        }
+        assert_eq!(harness.vehicle.applied_ackermann_controls().len(), 20);
+        assert_eq!(control_guard_counts(&harness), (0, 0));
+    }
+
+    #[test]
+    fn a_double_step_keeps_the_previous_control() {
+        let mut harness = Harness::new(BridgeConfig::default());
+        harness.send_control(5.0, 1.0, 0.1);
+        harness.tick();
+        harness.vehicle.clear_applied();
+        tick_control_after(&mut harness, 0.0);
+        tick_control_after(&mut harness, f64::NAN);
+        assert!(harness.vehicle.applied_ackermann_controls().is_empty());
+        assert!(harness.vehicle.applied_controls().is_empty());
+        assert_eq!(control_guard_counts(&harness), (2, 0));
+        // The next regular tick controls again
+        harness.tick();
+        assert_eq!(harness.vehicle.applied_ackermann_controls().len(), 1);
+    }
+
+    #[test]
+    fn a_pause_of_the_loop_resets_the_controller() {
+        let mut harness = Harness::new(BridgeConfig::default());
+        harness.send_control(5.0, 1.0, 0.0);
+        harness.tick();
+        harness.send_control(5.0, 1.0, 0.0);
+        assert!(has_previous_command(&harness));
+        harness.vehicle.clear_applied();
+        tick_control_after(&mut harness, 10.0);
+        assert!(!has_previous_command(&harness));
+        assert_eq!(control_guard_counts(&harness), (0, 1));
+        // The vehicle still follows the command after the pause
+        let applied = harness.vehicle.applied_ackermann_controls();
+        assert_eq!(applied.len(), 1);
+        assert_eq!(applied[0].speed, 5.0);
+    }
+
+    #[test]
+    fn the_physics_is_read_again_only_on_a_refresh() {
+        let mut harness = Harness::new(BridgeConfig::default());
+        harness.send_control(5.0, 1.0, 0.0);
+        harness.tick();
+        harness.send_control(5.0, 1.0, 0.0);
+        for _ in 0..5 {
+            harness.tick();
+        }
+        // The ticks use what was derived at the creation
+        assert_eq!(harness.vehicle.physics_reads(), 1);
+        assert!(has_previous_command(&harness));
+
+        let reply = harness.bridge.refresh_physics();
+        assert_eq!(harness.vehicle.physics_reads(), 2);
+        assert_eq!(reply.generation, 2);
+        assert_eq!(reply.mass, None);
+        assert_eq!(reply.wheels, 0);
+        assert!(!reply.two_wheeled);
+        assert_eq!(reply.max_steer_angle, MAX_STEER_ANGLE);
+        // The controller of the previous physics starts over
+        assert!(!has_previous_command(&harness));
+    }
+
+    #[test]
+    fn the_refresh_queryable_replies_with_the_new_generation() {
+        let mut harness = Harness::new(BridgeConfig::default());
+        let replies = harness
+            .session
+            .get(harness.key(topics::PHYSICS_REFRESH))
+            .res()
+            .unwrap();
+        // The handler runs in the step of the bridge
+        let start = Instant::now();
+        let reply = loop {
+            assert!(start.elapsed() < WAIT, "no reply");
+            harness.tick();
+            if let Ok(reply) = replies.recv_timeout(Duration::from_millis(10)) {
+                break reply;
+            }
+        };
+        let payload = reply.sample.unwrap().value.payload.contiguous().to_vec();
+        let reply: serde_json::Value = serde_json::from_slice(&payload).unwrap();
+        assert_eq!(reply["generation"], 2);
+        assert_eq!(reply["wheels"], 0);
+        assert_eq!(harness.vehicle.physics_reads(), 2);
+    }
+
+    #[test]
+    fn an_update_of_an_unreadable_physics_is_ignored() {
+        let mut harness = Harness::new(BridgeConfig::default());
+        harness
+            .session
+            .put(
+                harness.key(topics::PHYSICS_UPDATE),
+                r#"{"mass": 1500.0}"#.as_bytes().to_vec(),
+            )
+            .res()
+            .unwrap();
+        wait_until(|| {
+            !harness
+                .bridge
+                .pending_physics_updates
+                .lock()
+                .unwrap()
+                .is_empty()
+        });
+        harness.tick();
+        // Read to apply the update, but nothing changed to refresh
+        assert_eq!(harness.vehicle.physics_reads(), 2);
+        assert_eq!(harness.bridge.physics_generation, 1);
+    }
+
+    #[test]
+    fn a_held_brake_is_applied_again_every_refresh() {
+        let mut harness = Harness::new(BridgeConfig::default());
+        assert_eq!(harness.config.vehicle.control_refresh_ticks, 10);
+        // The brake of the fallback, the same every tick
+        let mut applied_ticks = Vec::new();
+        for tick in 1..=30 {
+            harness.vehicle.clear_applied();
+            harness.tick();
+            if !harness.vehicle.applied_controls().is_empty() {
+                applied_ticks.push(tick);
+            }
+        }
+        assert_eq!(applied_ticks, [1, 11, 21]);
+        assert_eq!(
+            harness
+                .bridge
+                .stats
+                .skipped_control_applies
+                .load(Ordering::Relaxed),
+            27
+        );
+    }
+
+    #[test]
+    fn no_overshoot_after_a_long_commanded_stop() {
+        let session = session();
+        let config = BridgeConfig::default();
+        let vehicle =
+            KinematicVehicle::new(1, String::from("autoware_ego"), KinematicConfig::default());
+        let namespaces = Arc::new(Mutex::new(NamespaceRegistry::new(
+            NamespaceSource::default(),
+        )));
+        let mut bridge = VehicleBridge::new(
+            session.clone(),
+            Box::new(vehicle.clone()),
+            &config,
+            namespaces,
+        )
+        .unwrap();
+        let key = config.topics.key("ego", topics::CONTROL_CMD);
+        let mut max_speed: f32 = 0.0;
+        for frame in 1..=400 {
+            let timestamp = frame as f64 * DT;
+            // Stopped for 10 seconds, then drive at 5 m/s
+            let speed = if frame <= 200 { 0.0 } else { 5.0 };
+            if frame == 1 || frame == 201 {
+                let cmd = control_command(timestamp, speed, 1.0, 0.0);
+                put_control(&session, &bridge, key.clone(), &cmd);
+            }
+            vehicle.advance(DT);
+            let tick = TickSnapshot::without_world(TimeStamp::from_secs_f64(timestamp), frame);
+            run_tick_now(&mut bridge, &tick);
+            max_speed = max_speed.max(vehicle.velocity().norm());
+        }
+        assert!(max_speed <= 5.0 + 1e-3, "{max_speed}");
+        assert!((vehicle.velocity().norm() - 5.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn shift_to_reverse_only_once_stopped() {
+        let mut harness = Harness::new(BridgeConfig::default());
+        let gear_subscriber = harness
+            .session
+            .declare_subscriber(harness.key(topics::GEAR_STATUS))
+            .res()
+            .unwrap();
+        harness.vehicle.set_velocity(Vector3::new(3.0, 0.0, 0.0));
+        harness.send_control(-1.0, 0.0, 0.0);
+        harness.tick();
+        // Still rolling forward, so stop first and keep reporting the current gear
+        let applied = harness.vehicle.applied_ackermann_controls();
+        assert_eq!(applied.last().unwrap().speed, 0.0);
+        assert!(harness.bridge.gear_change_pending);
+        let sample = gear_subscriber.recv_timeout(WAIT).unwrap();
+        let gear: GearReport = cdr_utils::deserialize(&sample.payload.contiguous()).unwrap();
+        assert_eq!(gear.report, 2);
+
+        harness.vehicle.set_velocity(Vector3::new(0.05, 0.0, 0.0));
+        harness.tick();
+        let applied = harness.vehicle.applied_ackermann_controls();
+        assert_eq!(applied.last().unwrap().speed, -1.0);
+        assert!(!harness.bridge.gear_change_pending);
+    }
+
+    #[test]
+    fn shift_to_reverse_at_a_standstill() {
+        let mut harness = Harness::new(BridgeConfig::default());
+        harness.send_control(-1.0, 0.0, 0.0);
+        harness.tick();
+        let applied = harness.vehicle.applied_ackermann_controls();
+        assert_eq!(applied.last().unwrap().speed, -1.0);
+        assert!(!harness.bridge.gear_change_pending);
+        // Back to drive from a standstill as well
+        harness.send_control(1.0, 0.0, 0.0);
+        harness.tick();
+        let applied = harness.vehicle.applied_ackermann_controls();
+        assert_eq!(applied.last().unwrap().speed, 1.0);
+    }
+
+    #[test]
+    fn map_the_numbered_forward_gears() {
+        assert_eq!(forward_gear(GEAR_COMMAND_DRIVE), Some(None));
+        assert_eq!(forward_gear(GEAR_COMMAND_DRIVE_2), Some(Some(2)));
+        assert_eq!(forward_gear(GEAR_COMMAND_DRIVE_18), Some(Some(18)));
+        // Reverse, park and the rest aren't forward gears
+        assert_eq!(forward_gear(20), None);
+        assert_eq!(forward_gear(22), None);
+    }
+
+    fn manual_gear_config() -> BridgeConfig {
+        let mut config = BridgeConfig::default();
+        config.vehicle.controller = ControllerKind::SimplePid;
+        config.vehicle.manual_gear_shift = true;
+        config
+    }
+
+    #[test]
+    fn the_manual_gearbox_follows_the_speed_table() {
+        let mut harness = Harness::new(manual_gear_config());
+        harness.vehicle.set_velocity(Vector3::new(5.0, 0.0, 0.0));
+        harness.send_control(6.0, 0.0, 0.0);
+        harness.tick();
+        let control = *harness.vehicle.applied_controls().last().unwrap();
+        assert!(control.manual_gear_shift);
+        assert_eq!(control.gear, 2);
+        // Reverse is gear -1 whatever the table
+        harness.vehicle.set_velocity(Vector3::zeros());
+        harness.send_control(-1.0, 0.0, 0.0);
+        harness.tick();
+        let control = *harness.vehicle.applied_controls().last().unwrap();
+        assert!(control.reverse);
+        assert_eq!(control.gear, -1);
+    }
+
+    #[test]
+    fn a_numbered_gear_command_wins_over_the_table() {
+        let mut harness = Harness::new(manual_gear_config());
+        let cmd = GearCommand {
+            stamp: TimeStamp::ZERO.to_msg(),
+            command: GEAR_COMMAND_DRIVE_2 + 2,
+        };
+        harness
+            .session
+            .put(
+                harness.key(topics::GEAR_CMD),
+                cdr_utils::serialize(&cmd, Endianness::Little).unwrap(),
+            )
+            .res()
+            .unwrap();
+        wait_until(|| harness.bridge.commanded_gear.lock().unwrap().is_some());
+        harness.send_control(1.0, 0.0, 0.0);
+        harness.tick();
+        let control = *harness.vehicle.applied_controls().last().unwrap();
+        assert_eq!(control.gear, 4);
+    }
+
+    #[test]
+    fn the_automatic_gearbox_is_left_alone() {
+        let mut config = BridgeConfig::default();
+        config.vehicle.controller = ControllerKind::SimplePid;
+        let mut harness = Harness::new(config);
+        harness.vehicle.set_velocity(Vector3::new(5.0, 0.0, 0.0));
+        harness.send_control(6.0, 0.0, 0.0);
+        harness.tick();
+        let control = *harness.vehicle.applied_controls().last().unwrap();
+        assert!(!control.manual_gear_shift);
+    }
+
+    #[test]
+    fn the_steer_filter_resets_on_a_teleport() {
+        let mut config = BridgeConfig::default();
+        config.vehicle.steer_time_constant = Some(0.5);
+        let mut harness = Harness::new(config);
+        let last_steer = |harness: &Harness| {
+            harness
+                .vehicle
+                .applied_ackermann_controls()
+                .last()
+                .unwrap()
+                .steer
+        };
+        harness.send_control(1.0, 0.0, 0.0);
+        harness.tick();
+        assert_eq!(last_steer(&harness), 0.0);
+        harness.send_control(1.0, 0.0, 0.2);
+        harness.tick();
+        let target = control::carla_steer(0.2, SteeringConvention::default(), MAX_STEER_ANGLE);
+        let smoothed = last_steer(&harness);
+        assert!(smoothed.abs() > 0.0 && smoothed.abs() < target.abs());
+        // Snappy across the discontinuity
+        harness
+            .vehicle
+            .set_pose(Isometry3::translation(TELEPORT_DISTANCE + 1.0, 0.0, 0.0));
+        harness.tick();
+        assert_eq!(last_steer(&harness), target);
+    }
+
+    fn overspeed_config() -> BridgeConfig {
+        let mut config = BridgeConfig::default();
+        config.vehicle.max_speed = Some(5.0);
+        config
+    }
+
+    #[test]
+    fn the_ceiling_caps_the_ackermann_target() {
+        let mut harness = Harness::new(overspeed_config());
+        harness.vehicle.set_velocity(Vector3::new(7.0, 0.0, 0.0));
+        harness.send_control(10.0, 1.0, 0.0);
+        harness.vehicle.clear_applied();
+        harness.tick();
+        // A single RPC, CARLA brakes down to the capped target on its own
+        assert_eq!(harness.vehicle.actuations(), 1);
+        let applied = harness.vehicle.applied_ackermann_controls();
+        assert_eq!(applied[0].speed, 5.0);
+        assert!(harness.bridge.speed_limited);
+        assert!(
+            harness
+                .bridge
+                .stats
+                .speed_limited_sec
+                .load(Ordering::Relaxed)
+                > 0.0
+        );
+    }
+
+    #[test]
+    fn the_ceiling_brakes_the_fallback_in_the_same_control() {
+        let mut config = overspeed_config();
+        config.vehicle.fallback_policy = FallbackPolicy::Coast;
+        let mut harness = Harness::new(config);
+        harness.vehicle.set_velocity(Vector3::new(7.0, 0.0, 0.0));
+        harness.tick();
+        assert_eq!(harness.vehicle.actuations(), 1);
+        let control = harness.vehicle.applied_controls()[0];
+        assert_eq!(control.throttle, 0.0);
+        assert_eq!(control.brake, 1.0);
+        // Within the margin, the coasting is left alone
+        harness.vehicle.set_velocity(Vector3::new(5.2, 0.0, 0.0));
+        harness.vehicle.clear_applied();
+        harness.tick();
+        assert!(!harness.bridge.speed_limited);
+        assert!(harness
+            .vehicle
+            .applied_controls()
+            .iter()
+            .all(|control| control.brake == 0.0));
+    }
+
+    fn put_blinkers(harness: &Harness, topic: &str, payload: Vec<u8>) {
+        let pending = if topic == topics::TURN_INDICATORS_CMD {
+            harness.bridge.pending_turn_indicators_cmd.clone()
+        } else {
+            harness.bridge.pending_hazard_lights_cmd.clone()
+        };
+        harness
+            .session
+            .put(harness.key(topic), payload)
+            .res()
+            .unwrap();
+        wait_until(|| pending.lock().unwrap().is_some());
+    }
+
+    #[test]
+    fn the_lights_commands_reach_the_vehicle() {
+        let mut harness = Harness::new(BridgeConfig::default());
+        let described = harness.bridge.describe().unwrap();
+        for topic in [topics::TURN_INDICATORS_CMD, topics::HAZARD_LIGHTS_CMD] {
+            assert!(described.keys.subscribers.contains(&harness.key(topic)));
+        }
+        let blinkers = VehicleLightState::LEFT_BLINKER | VehicleLightState::RIGHT_BLINKER;
+        let turn_indicators = |command| {
+            cdr_utils::serialize(
+                &TurnIndicatorsCommand {
+                    stamp: TimeStamp::from_secs_f64(0.0).to_msg(),
+                    command,
+                },
+                Endianness::Little,
+            )
+            .unwrap()
+        };
+        let hazard_lights = |command| {
+            cdr_utils::serialize(
+                &HazardLightsCommand {
+                    stamp: TimeStamp::from_secs_f64(0.0).to_msg(),
+                    command,
+                },
+                Endianness::Little,
+            )
+            .unwrap()
+        };
+
+        put_blinkers(
+            &harness,
+            topics::TURN_INDICATORS_CMD,
+            turn_indicators(turn_indicators_command::ENABLE_LEFT),
+        );
+        harness.tick();
+        assert_eq!(
+            harness.vehicle.light_state() & blinkers,
+            VehicleLightState::LEFT_BLINKER
+        );
+
+        // The hazard lights win over the turn, which comes back once they are off
+        put_blinkers(
+            &harness,
+            topics::HAZARD_LIGHTS_CMD,
+            hazard_lights(hazard_lights_command::ENABLE),
+        );
+        harness.tick();
+        assert_eq!(harness.vehicle.light_state() & blinkers, blinkers);
+        put_blinkers(
+            &harness,
+            topics::HAZARD_LIGHTS_CMD,
+            hazard_lights(hazard_lights_command::DISABLE),
+        );
+        harness.tick();
+        assert_eq!(
+            harness.vehicle.light_state() & blinkers,
+            VehicleLightState::LEFT_BLINKER
+        );
+
+        put_blinkers(
+            &harness,
+            topics::TURN_INDICATORS_CMD,
+            turn_indicators(turn_indicators_command::DISABLE),
+        );
+        harness.tick();
+        assert!((harness.vehicle.light_state() & blinkers).is_empty());
+    }
+
+    #[test]
+    fn a_disabled_group_declares_nothing() {
+        let session = session();
+        let namespaces = Arc::new(Mutex::new(NamespaceRegistry::new(
+            NamespaceSource::default(),
+        )));
+        let vehicle = MockVehicle::new(1, "autoware_ego");
+        let config = BridgeConfig::default();
+        let mut bridge =
+            VehicleBridge::builder(session, Box::new(vehicle.clone()), &config, namespaces)
+                .with_control(false)
+                .build()
+                .unwrap();
+        let control_cmd = config.topics.key("ego", topics::CONTROL_CMD);
+        let described = bridge.describe().unwrap();
+        assert!(!described.keys.subscribers.contains(&control_cmd));
+        run_tick_now(
+            &mut bridge,
+            &TickSnapshot::without_world(TimeStamp::from_secs_f64(DT), 1),
+        );
+        // Left to CARLA, not even the fallback applies
+        assert_eq!(vehicle.actuations(), 0);
+    }
+
+    fn build_bridge(
+        config: &BridgeConfig,
+        customize: impl FnOnce(VehicleBridgeBuilder) -> VehicleBridgeBuilder,
+    ) -> VehicleBridge<'static> {
+        let namespaces = Arc::new(Mutex::new(NamespaceRegistry::new(
+            NamespaceSource::default(),
+        )));
+        let vehicle = MockVehicle::new(1, "autoware_ego");
+        customize(VehicleBridge::builder(
+            session(),
+            Box::new(vehicle),
+            config,
+            namespaces,
+        ))
+        .build()
+        .unwrap()
+    }
+
+    #[test]
+    fn a_monitoring_bridge_only_declares_the_velocity_report() {
+        let config = BridgeConfig::default();
+        let bridge = build_bridge(&config, |builder| {
+            builder
+                .with_velocity_report(true)
+                .with_steering_report(false)
+                .with_gear(false)
+                .with_control(false)
+                .with_lights(false)
+                .with_battery(false)
+                .with_doors(false)
+                .with_mrm(false)
+                .with_physics(false)
+                .with_diagnostics(false)
+        });
+        let keys = bridge.describe().unwrap().keys;
+        assert_eq!(
+            keys.publishers,
+            [config.topics.key("ego", topics::VELOCITY_STATUS)]
+        );
+        assert!(keys.subscribers.is_empty(), "{:?}", keys.subscribers);
+        assert!(keys.queryables.is_empty(), "{:?}", keys.queryables);
+    }
+
+    #[test]
+    fn the_topics_of_the_configuration_map_onto_the_builder() {
+        let mut config = BridgeConfig::default();
+        config.vehicle.topic_groups.gear = false;
+        let gear_status = config.topics.key("ego", topics::GEAR_STATUS);
+        let gear_cmd = config.topics.key("ego", topics::GEAR_CMD);
+
+        let keys = build_bridge(&config, |builder| builder)
+            .describe()
+            .unwrap()
+            .keys;
+        assert!(!keys.publishers.contains(&gear_status));
+        assert!(!keys.subscribers.contains(&gear_cmd));
+        assert!(keys
+            .publishers
+            .contains(&config.topics.key("ego", topics::VELOCITY_STATUS)));
+
+        // The builder has the last word
+        let keys = build_bridge(&config, |builder| builder.with_gear(true))
+            .describe()
+            .unwrap()
+            .keys;
+        assert!(keys.publishers.contains(&gear_status));
+        assert!(keys.subscribers.contains(&gear_cmd));
+
+        // A `[vehicle.topics]` table sets the same groups at once
+        let toggles = TopicToggles {
+            gear: Some(false),
+            ..Default::default()
+        };
+        let keys = build_bridge(&BridgeConfig::default(), |builder| {
+            builder.with_topics(toggles)
+        })
+        .describe()
+        .unwrap()
+        .keys;
+        assert!(!keys.publishers.contains(&gear_status));
+        assert!(!keys.subscribers.contains(&gear_cmd));
+    }
+}
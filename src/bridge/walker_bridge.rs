@@ -0,0 +1,283 @@
+//! A pedestrian ego, `walker.pedestrian.*` with a bridged role_name, steered on `rt/cmd_vel`
+use super::actor_bridge::{
+    ActorBridge, ActorMotion, BridgeDescription, BridgeKind, DeclaredKeys, TickSnapshot,
+};
+use crate::{
+    autoware_type::{Pose, PoseStamped, Twist, TwistStamped},
+    cdr_utils::{self, Endianness},
+    config::BridgeConfig,
+    control,
+    error::Result,
+    namespace::{NamespaceRegistry, VehicleIdentity},
+    reset::ResetReport,
+    status_publisher::StatusPublisher,
+    time::TimeStamp,
+    topics, utils,
+};
+use atomic_float::AtomicF64;
+use carla::{
+    client::{Actor, ActorBase, Walker},
+    rpc::WalkerControl,
+};
+use log::{info, warn};
+use nalgebra::{Isometry3, Vector3};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use zenoh::{prelude::sync::*, subscriber::Subscriber};
+
+/// The walker stops when the latest `cmd_vel` is older than this (s of simulation time)
+const COMMAND_TIMEOUT_SEC: f64 = 0.5;
+
+/// The walker of a `walker.pedestrian.*` actor whose role_name has the prefix, the actor
+/// itself otherwise
+pub fn ego_walker(actor: Actor, config: &BridgeConfig) -> std::result::Result<Walker, Actor> {
+    let role_name = role_name(&actor).unwrap_or_default();
+    if !actor.type_id().starts_with("walker.pedestrian.")
+        || config.bridged_name(&role_name).is_none()
+    {
+        return Err(actor);
+    }
+    Walker::try_from(actor)
+}
+
+fn role_name(actor: &impl ActorBase) -> Option<String> {
+    actor
+        .attributes()
+        .iter()
+        .find(|attr| attr.id() == "role_name")
+        .map(|attr| attr.value_string())
+}
+
+/// Bridge a pedestrian like a vehicle: the same namespaces, stamps and conventions.
+///
+/// `cmd_vel` is a geometry_msgs/Twist in the frame of the walker: the linear x and y
+/// velocity, the angular z velocity, and a positive linear z to jump. A walker has no
+/// heading of its own to steer, it faces where it goes, so the bridge turns the commanded
+/// heading by the angular velocity and walks in the direction of the linear velocity
+/// rotated by it. Without a command for half a second, the walker stops.
+pub struct WalkerBridge<'a> {
+    walker_name: String,
+    identity: VehicleIdentity,
+    namespaces: Arc<Mutex<NamespaceRegistry>>,
+    actor: Walker,
+    dry_run: bool,
+    endianness: Endianness,
+    publisher_pose: StatusPublisher,
+    publisher_velocity: StatusPublisher,
+    _subscriber_cmd_vel: Subscriber<'a, ()>,
+    /// The latest command and the simulation time it arrived at
+    cmd_vel: Arc<Mutex<Option<(Twist, f64)>>>,
+    sim_time: Arc<AtomicF64>,
+    /// The heading the commands turn, in the map frame with ROS axes (rad)
+    heading: f64,
+    motion: ActorMotion,
+    spawn_transform: Isometry3<f32>,
+    declared: DeclaredKeys,
+}
+
+impl<'a> WalkerBridge<'a> {
+    pub fn new(
+        z_session: Arc<Session>,
+        actor: Walker,
+        bridge_config: &BridgeConfig,
+        namespaces: Arc<Mutex<NamespaceRegistry>>,
+    ) -> Result<WalkerBridge<'a>> {
+        let role_name = role_name(&actor).unwrap_or_default();
+        let name = bridge_config.bridged_name(&role_name).unwrap_or_default();
+        let walker_name = namespaces.lock().unwrap().namespace(actor.id(), name);
+        info!("Detect a walker {walker_name}");
+        let topic_map = &bridge_config.topics;
+        let mut declared = DeclaredKeys::default();
+
+        let failures = Arc::new(AtomicU64::new(0));
+        let mut declare_publisher = |topic: &str| {
+            let key = topic_map.key(&walker_name, topic);
+            declared.publishers.push(key.clone());
+            StatusPublisher::declare(
+                z_session.clone(),
+                key,
+                bridge_config.redeclare_after,
+                failures.clone(),
+            )
+        };
+        let publisher_pose = declare_publisher(topics::WALKER_POSE)?;
+        let publisher_velocity = declare_publisher(topics::WALKER_VELOCITY)?;
+
+        let sim_time = Arc::new(AtomicF64::new(0.0));
+        let cmd_vel = Arc::new(Mutex::new(None));
+        let cloned_sim_time = sim_time.clone();
+        let cloned_cmd_vel = cmd_vel.clone();
+        let cmd_vel_key = topic_map.key(&walker_name, topics::WALKER_CMD_VEL);
+        let cloned_key = cmd_vel_key.clone();
+        let mut throttle = utils::LogThrottle::new(Duration::from_secs(1));
+        declared.subscribers.push(cmd_vel_key.clone());
+        let subscriber_cmd_vel = z_session
+            .declare_subscriber(cmd_vel_key)
+            .callback_mut(move |sample| {
+                match cdr_utils::deserialize::<Twist>(&sample.payload.contiguous()) {
+                    Ok(twist) => {
+                        let timestamp = cloned_sim_time.load(Ordering::Relaxed);
+                        *cloned_cmd_vel.lock().unwrap() = Some((twist, timestamp));
+                    }
+                    Err(e) if throttle.ready() => {
+                        warn!("Dropped an invalid command on {cloned_key}: {e}")
+                    }
+                    Err(_) => {}
+                }
+            })
+            .res()?;
+
+        let spawn_transform = actor.transform();
+        let motion = ActorMotion {
+            transform: spawn_transform,
+            velocity: actor.velocity(),
+            angular_velocity: actor.angular_velocity(),
+        };
+        let identity = VehicleIdentity {
+            actor_id: actor.id(),
+            role_name,
+            namespace: walker_name.clone(),
+        };
+        Ok(WalkerBridge {
+            walker_name,
+            identity,
+            namespaces,
+            actor,
+            dry_run: bridge_config.dry_run,
+            endianness: bridge_config.cdr_endianness,
+            publisher_pose,
+            publisher_velocity,
+            _subscriber_cmd_vel: subscriber_cmd_vel,
+            cmd_vel,
+            sim_time,
+            heading: ros_yaw(&spawn_transform),
+            motion,
+            spawn_transform,
+            declared,
+        })
+    }
+
+    /// The control of the command, turning the heading over the elapsed time
+    fn walker_control(&mut self, command: Option<Twist>, elapsed_sec: f64) -> WalkerControl {
+        let Some(Twist { linear, angular }) = command else {
+            return WalkerControl {
+                direction: Vector3::zeros(),
+                speed: 0.0,
+                jump: false,
+            };
+        };
+        self.heading += angular[2] * elapsed_sec;
+        let (sin, cos) = self.heading.sin_cos();
+        let [x, y, z] = linear;
+        // The linear velocity in the map frame, then in CARLA's with y to the right
+        let direction = Vector3::new((cos * x - sin * y) as f32, -(sin * x + cos * y) as f32, 0.0);
+        let speed = direction.norm();
+        WalkerControl {
+            direction: if speed > 0.0 {
+                direction / speed
+            } else {
+                direction
+            },
+            speed,
+            jump: z > 0.0,
+        }
+    }
+}
+
+/// The yaw of a CARLA transform in the map frame with ROS axes (rad)
+fn ros_yaw(transform: &Isometry3<f32>) -> f64 {
+    let (_, _, yaw) = transform.rotation.euler_angles();
+    -yaw as f64
+}
+
+impl<'a> ActorBridge for WalkerBridge<'a> {
+    fn step(&mut self, _elapsed_sec: f64, tick: &TickSnapshot) -> Result<()> {
+        self.sim_time
+            .store(tick.stamp.to_secs_f64(), Ordering::Relaxed);
+        self.motion = tick
+            .motion(self.actor.id())
+            .copied()
+            .unwrap_or_else(|| ActorMotion {
+                transform: self.actor.transform(),
+                velocity: self.actor.velocity(),
+                angular_velocity: self.actor.angular_velocity(),
+            });
+        Ok(())
+    }
+
+    fn publish_status(&mut self, stamp: TimeStamp) -> Result<()> {
+        let translation = self.motion.transform.translation;
+        let pose_msg = PoseStamped {
+            header: utils::ros_header(stamp, "map"),
+            pose: Pose {
+                position: utils::carla_to_ros_position(translation.x, translation.y, translation.z),
+                orientation: utils::carla_to_ros_orientation(&self.motion.transform.rotation),
+            },
+        };
+        cdr_utils::put(&self.publisher_pose, &pose_msg, self.endianness)?;
+        let (longitudinal, lateral, yaw_rate) = control::body_twist(
+            &self.motion.transform,
+            &self.motion.velocity,
+            &self.motion.angular_velocity,
+        );
+        let velocity_msg = TwistStamped {
+            header: utils::ros_header(stamp, "base_link"),
+            twist: Twist {
+                linear: [longitudinal as f64, lateral as f64, 0.0],
+                angular: [0.0, 0.0, yaw_rate as f64],
+            },
+        };
+        cdr_utils::put(&self.publisher_velocity, &velocity_msg, self.endianness)?;
+        Ok(())
+    }
+
+    fn apply_control(&mut self, stamp: TimeStamp, elapsed_sec: f64) {
+        let timestamp = stamp.to_secs_f64();
+        let command = match *self.cmd_vel.lock().unwrap() {
+            Some((twist, received)) if timestamp - received <= COMMAND_TIMEOUT_SEC => Some(twist),
+            _ => None,
+        };
+        let control = self.walker_control(command, elapsed_sec);
+        if !self.dry_run {
+            self.actor.apply_control(&control);
+        }
+    }
+
+    fn kind(&self) -> BridgeKind {
+        BridgeKind::Walker
+    }
+
+    fn describe(&self) -> Option<BridgeDescription> {
+        Some(BridgeDescription {
+            actor_id: self.identity.actor_id,
+            namespace: self.walker_name.clone(),
+            kind: String::from("walker"),
+            raw_name: Some(self.identity.role_name.clone()),
+            keys: self.declared.clone(),
+        })
+    }
+
+    fn reset(&mut self, report: &mut ResetReport) {
+        if !self.dry_run {
+            self.actor.set_transform(&self.spawn_transform);
+            self.actor.set_target_velocity(&Vector3::zeros());
+        }
+        // Until the planner sends the next command, the walker stands still
+        *self.cmd_vel.lock().unwrap() = None;
+        self.heading = ros_yaw(&self.spawn_transform);
+        info!("Reset {} to its spawn pose", self.walker_name);
+        report.walkers.push(self.walker_name.clone());
+    }
+}
+
+impl<'a> Drop for WalkerBridge<'a> {
+    fn drop(&mut self) {
+        info!("Remove walker name {}", self.walker_name);
+        self.namespaces.lock().unwrap().release(self.actor.id());
+    }
+}
@@ -0,0 +1,177 @@
+use crate::{
+    bridge::actor_bridge::ActorBridge,
+    error::Result,
+    time::TimeStamp,
+    topics::{self, TopicMap},
+};
+use log::warn;
+use serde_derive::Serialize;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+use zenoh::{prelude::sync::*, publication::Publisher, queryable::Queryable};
+
+/// How many events are kept for the late joiners
+const MAX_EVENTS: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Created,
+    Destroyed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventReason {
+    /// The actor appeared in the world
+    Discovered,
+    /// The actor left the world, or the world was reloaded
+    Despawned,
+    /// The bridge failed, while being created or stepped
+    Error,
+    /// The bridge process exits
+    Shutdown,
+}
+
+/// An event in the JSON published on `*/bridge/events`
+#[derive(Debug, Clone, Serialize)]
+pub struct BridgeEvent {
+    pub event: EventKind,
+    pub reason: EventReason,
+    pub actor_id: u32,
+    /// None when the bridge failed before it knew its namespace
+    pub namespace: Option<String>,
+    /// `vehicle`, or the type_id of a sensor
+    pub kind: String,
+    /// Wall-clock seconds since the UNIX epoch
+    pub stamp: f64,
+    pub error: Option<String>,
+}
+
+/// The namespace and kind of a live bridge, for its destroyed event
+struct LiveBridge {
+    namespace: String,
+    kind: String,
+}
+
+/// Publish an event whenever a vehicle or sensor bridge is created or destroyed.
+///
+/// The latest events are kept, and a query on `*/bridge/events` replies with them
+/// as a JSON array, oldest first. The bridges still alive when this is dropped, i.e.
+/// when the bridge exits, get a shutdown event.
+pub struct BridgeEvents<'a> {
+    publisher: Publisher<'a>,
+    history: Arc<Mutex<VecDeque<BridgeEvent>>>,
+    live: BTreeMap<u32, LiveBridge>,
+    _queryable: Queryable<'a, ()>,
+}
+
+impl<'a> BridgeEvents<'a> {
+    pub fn new(z_session: Arc<Session>, topic_map: &TopicMap) -> Result<BridgeEvents<'a>> {
+        let key = topic_map.key("*", topics::BRIDGE_EVENTS);
+        let publisher = topics::declare_publisher(&z_session, &key)?;
+        let history = Arc::new(Mutex::new(VecDeque::new()));
+        let cloned_history = history.clone();
+        let queryable = z_session
+            .declare_queryable(key)
+            .callback(move |query| {
+                let payload = match serde_json::to_vec(&*cloned_history.lock().unwrap()) {
+                    Ok(payload) => payload,
+                    Err(e) => {
+                        warn!("Failed to encode the bridge events: {e}");
+                        return;
+                    }
+                };
+                let sample = Sample::new(query.key_expr().clone(), payload);
+                if let Err(e) = query.reply(Ok(sample)).res() {
+                    warn!("Failed to reply to the bridge event query: {e}");
+                }
+            })
+            .res()?;
+        Ok(BridgeEvents {
+            publisher,
+            history,
+            live: BTreeMap::new(),
+            _queryable: queryable,
+        })
+    }
+
+    /// A bridge was added to the registry, the bridges without a description are ignored
+    pub fn created(&mut self, bridge: &dyn ActorBridge) {
+        let Some(description) = bridge.describe() else {
+            return;
+        };
+        self.live.insert(
+            description.actor_id,
+            LiveBridge {
+                namespace: description.namespace.clone(),
+                kind: description.kind.clone(),
+            },
+        );
+        self.publish(BridgeEvent {
+            event: EventKind::Created,
+            reason: EventReason::Discovered,
+            actor_id: description.actor_id,
+            namespace: Some(description.namespace),
+            kind: description.kind,
+            stamp: TimeStamp::now().to_secs_f64(),
+            error: None,
+        });
+    }
+
+    /// A bridge was removed from the registry
+    pub fn destroyed(&mut self, actor_id: u32, reason: EventReason, error: Option<String>) {
+        let Some(bridge) = self.live.remove(&actor_id) else {
+            return;
+        };
+        self.publish(BridgeEvent {
+            event: EventKind::Destroyed,
+            reason,
+            actor_id,
+            namespace: Some(bridge.namespace),
+            kind: bridge.kind,
+            stamp: TimeStamp::now().to_secs_f64(),
+            error,
+        });
+    }
+
+    /// The bridge of an actor couldn't be created
+    pub fn creation_failed(&mut self, actor_id: u32, type_id: String, error: String) {
+        self.publish(BridgeEvent {
+            event: EventKind::Destroyed,
+            reason: EventReason::Error,
+            actor_id,
+            namespace: None,
+            kind: type_id,
+            stamp: TimeStamp::now().to_secs_f64(),
+            error: Some(error),
+        });
+    }
+
+    fn publish(&mut self, event: BridgeEvent) {
+        match serde_json::to_vec(&event) {
+            Ok(payload) => {
+                if let Err(e) = self.publisher.put(payload).res() {
+                    warn!("Failed to publish the bridge event: {e}");
+                }
+            }
+            Err(e) => warn!("Failed to encode the bridge event: {e}"),
+        }
+        let mut history = self.history.lock().unwrap();
+        if history.len() == MAX_EVENTS {
+            history.pop_front();
+        }
+        history.push_back(event);
+    }
+}
+
+impl<'a> Drop for BridgeEvents<'a> {
+    fn drop(&mut self) {
+        let actor_ids: Vec<_> = self.live.keys().cloned().collect();
+        for actor_id in actor_ids {
+            self.destroyed(actor_id, EventReason::Shutdown, None);
+        }
+    }
+}
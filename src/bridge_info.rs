@@ -0,0 +1,153 @@
+//! The build and configuration of the running bridge, for support requests
+use crate::{
+    bridge::actor_bridge::{ActorBridge, BridgeKind},
+    config::BridgeConfig,
+    error::Result,
+    topics::{self, TopicMap},
+};
+use arc_swap::ArcSwap;
+use log::warn;
+use serde_derive::Serialize;
+use std::{collections::BTreeMap, sync::Arc};
+use zenoh::{prelude::sync::*, queryable::Queryable};
+
+/// The optional cargo features the bridge was built with. The crate has none yet.
+const FEATURES: &[&str] = &[];
+
+/// The active configuration, without anything which may hold credentials
+#[derive(Serialize)]
+pub struct ConfigSummary {
+    pub carla_address: String,
+    pub carla_port: u16,
+    /// Without the endpoint configuration after `?` or `#`, e.g. the TLS files
+    pub zenoh_listen: Vec<String>,
+    pub asynchronous: bool,
+    pub fixed_delta_seconds: f64,
+    pub loop_rate: f64,
+    pub role_name_prefix: String,
+    pub namespace_source: String,
+    pub autoware_version: String,
+    pub cdr_endianness: String,
+    /// The namespaces with a `[vehicles.<name>]` section
+    pub vehicle_overrides: Vec<String>,
+    /// The files of `vehicles.d/`, by role_name pattern
+    pub role_overrides: BTreeMap<String, Vec<String>>,
+}
+
+impl ConfigSummary {
+    pub fn strip_endpoint(endpoint: &str) -> String {
+        endpoint
+            .split(['?', '#'])
+            .next()
+            .unwrap_or_default()
+            .to_string()
+    }
+}
+
+/// The reply of `*/bridge/info`
+#[derive(Serialize)]
+pub struct BridgeInfo {
+    pub version: &'static str,
+    pub git_hash: &'static str,
+    pub features: &'static [&'static str],
+    pub zenoh_version: &'static str,
+    pub carla_crate_version: &'static str,
+    pub carla_server_version: String,
+    pub config: ConfigSummary,
+    /// The effective settings of every bridged vehicle, by namespace
+    pub vehicles: BTreeMap<String, VehicleSummary>,
+}
+
+/// The settings a vehicle bridge was created with
+#[derive(Serialize)]
+pub struct VehicleSummary {
+    pub role_name: String,
+    /// The files of `vehicles.d/` merged into `config`, in their order
+    pub override_files: Vec<String>,
+    /// The `VehicleConfig` as debug text, which reads like the configuration file
+    pub config: String,
+}
+
+impl BridgeInfo {
+    /// `carla_server_version` is asked once at the start, not per query
+    pub fn new(carla_server_version: String, config: ConfigSummary) -> BridgeInfo {
+        BridgeInfo {
+            version: env!("CARGO_PKG_VERSION"),
+            git_hash: env!("BRIDGE_GIT_HASH"),
+            features: FEATURES,
+            zenoh_version: env!("BRIDGE_ZENOH_VERSION"),
+            carla_crate_version: env!("BRIDGE_CARLA_VERSION"),
+            carla_server_version,
+            config,
+            vehicles: BTreeMap::new(),
+        }
+    }
+
+    /// Describe the vehicles among the bridges, with their settings resolved from the
+    /// configuration
+    pub fn set_vehicles<'b>(
+        &mut self,
+        bridges: impl Iterator<Item = &'b dyn ActorBridge>,
+        bridge_config: &BridgeConfig,
+    ) {
+        self.vehicles = bridges
+            .filter(|bridge| bridge.kind() == BridgeKind::Vehicle)
+            .filter_map(|bridge| {
+                let description = bridge.describe()?;
+                let role_name = description.raw_name?;
+                let config = bridge_config
+                    .vehicle_config(&description.namespace, &role_name)
+                    .ok()?;
+                let override_files = bridge_config
+                    .role_overrides
+                    .matching(&role_name)
+                    .map(|role_override| role_override.file.display().to_string())
+                    .collect();
+                let summary = VehicleSummary {
+                    role_name,
+                    override_files,
+                    config: format!("{config:#?}"),
+                };
+                Some((description.namespace, summary))
+            })
+            .collect();
+    }
+}
+
+/// Answer the queries on `*/bridge/info` with the JSON of the bridge info, encoded once per
+/// change of the vehicles
+pub struct InfoQueryable<'a> {
+    payload: Arc<ArcSwap<Vec<u8>>>,
+    _queryable: Queryable<'a, ()>,
+}
+
+impl<'a> InfoQueryable<'a> {
+    pub fn new(
+        z_session: Arc<Session>,
+        info: &BridgeInfo,
+        topic_map: &TopicMap,
+    ) -> Result<InfoQueryable<'a>> {
+        let payload = Arc::new(ArcSwap::from_pointee(serde_json::to_vec_pretty(info)?));
+        let cloned_payload = payload.clone();
+        let queryable = z_session
+            .declare_queryable(topic_map.key("*", topics::INFO))
+            .callback(move |query| {
+                let payload = cloned_payload.load().as_ref().clone();
+                let sample = Sample::new(query.key_expr().clone(), payload);
+                if let Err(e) = query.reply(Ok(sample)).res() {
+                    warn!("Failed to reply to the info query: {e}");
+                }
+            })
+            .res()?;
+        Ok(InfoQueryable {
+            payload,
+            _queryable: queryable,
+        })
+    }
+
+    pub fn update(&self, info: &BridgeInfo) -> Result<()> {
+        self.payload
+            .store(Arc::new(serde_json::to_vec_pretty(info)?));
+        Ok(())
+    }
+}
@@ -0,0 +1,53 @@
+use crate::{
+    bridge::actor_bridge::{ActorBridge, BridgeDescription},
+    error::Result,
+    topics::{self, TopicMap},
+};
+use arc_swap::ArcSwap;
+use log::warn;
+use std::sync::Arc;
+use zenoh::{prelude::sync::*, queryable::Queryable};
+
+/// Answer the queries on `*/bridge/list` with a JSON array of the active bridges.
+///
+/// Each bridge is listed with its namespace and the keys it declared, which are the
+/// resolved keys after the remapping. The main loop only takes a new snapshot when
+/// bridges come or go, so the queries never wait for the loop.
+pub struct BridgeListQueryable<'a> {
+    snapshot: Arc<ArcSwap<Vec<BridgeDescription>>>,
+    _queryable: Queryable<'a, ()>,
+}
+
+impl<'a> BridgeListQueryable<'a> {
+    pub fn new(z_session: Arc<Session>, topic_map: &TopicMap) -> Result<BridgeListQueryable<'a>> {
+        let snapshot = Arc::new(ArcSwap::from_pointee(Vec::new()));
+        let cloned_snapshot = snapshot.clone();
+        let queryable = z_session
+            .declare_queryable(topic_map.key("*", topics::BRIDGE_LIST))
+            .callback(move |query| {
+                let payload = match serde_json::to_vec(&**cloned_snapshot.load()) {
+                    Ok(payload) => payload,
+                    Err(e) => {
+                        warn!("Failed to encode the bridge list: {e}");
+                        return;
+                    }
+                };
+                let sample = Sample::new(query.key_expr().clone(), payload);
+                if let Err(e) = query.reply(Ok(sample)).res() {
+                    warn!("Failed to reply to the bridge list query: {e}");
+                }
+            })
+            .res()?;
+        Ok(BridgeListQueryable {
+            snapshot,
+            _queryable: queryable,
+        })
+    }
+
+    /// Take a new snapshot of the bridges, after some were created or removed
+    pub fn update<'b>(&self, bridges: impl Iterator<Item = &'b dyn ActorBridge>) {
+        let mut snapshot: Vec<_> = bridges.filter_map(|bridge| bridge.describe()).collect();
+        snapshot.sort_by_key(|bridge| bridge.actor_id);
+        self.snapshot.store(Arc::new(snapshot));
+    }
+}
@@ -0,0 +1,215 @@
+use crate::{
+    actor_query::ActorInfo,
+    autoware_type::{diagnostic_status, DiagnosticArray, DiagnosticStatus, KeyValue},
+    cdr_utils::{self, Endianness},
+    config::BridgeConfig,
+    error::Result,
+    status_publisher::StatusPublisher,
+    time::TimeStamp,
+    topics, utils,
+};
+use log::warn;
+use serde_derive::Serialize;
+use std::{
+    collections::BTreeMap,
+    sync::{atomic::AtomicU64, Arc},
+    time::{Duration, Instant},
+};
+use zenoh::prelude::sync::*;
+
+/// How often the server stats are published (in wall-clock seconds)
+const CARLA_STATS_INTERVAL_SEC: f64 = 1.0;
+
+/// The stats message published as JSON on `*/bridge/stats/carla`
+#[derive(Serialize)]
+pub struct CarlaStatsReport {
+    pub stamp: f64,
+    pub frame: u64,
+    /// Wall-clock seconds between consecutive server frames over the last interval, from
+    /// the platform timestamps of the frames. In synchronous mode, it includes the loop of
+    /// the bridge between the ticks.
+    pub frame_time_avg_sec: Option<f64>,
+    pub frame_time_max_sec: Option<f64>,
+    /// Wall-clock seconds the bridge waited for a tick of the server, only in synchronous
+    /// mode, where it's the cost of the server alone
+    pub tick_wait_avg_sec: Option<f64>,
+    /// The actors of the snapshot of `*/bridge/actors` by the first part of their type_id,
+    /// e.g. `vehicle` or `sensor`
+    pub actors: BTreeMap<String, usize>,
+    pub synchronous_mode: bool,
+    pub no_rendering_mode: bool,
+}
+
+/// Tell the cost of the CARLA server from the one of the bridge, when the simulation slows down
+pub struct CarlaStats {
+    publisher: StatusPublisher,
+    diagnostics_publisher: StatusPublisher,
+    endianness: Endianness,
+    /// Above this average frame time, the diagnostics warn (wall-clock seconds)
+    max_frame_time: f64,
+    window_start: Option<Instant>,
+    /// The latest frame and its platform timestamp
+    last_frame: Option<(u64, f64)>,
+    frame_time_sum: f64,
+    frame_time_max: Option<f64>,
+    frames: u64,
+    tick_wait_sum: Duration,
+    ticks: u32,
+}
+
+impl CarlaStats {
+    /// `publish_failures` is shared with the `SimulatorClock`
+    pub fn new(
+        z_session: Arc<Session>,
+        max_frame_time: f64,
+        bridge_config: &BridgeConfig,
+        publish_failures: Arc<AtomicU64>,
+    ) -> Result<CarlaStats> {
+        let topic_map = &bridge_config.topics;
+        let publisher = StatusPublisher::declare(
+            z_session.clone(),
+            topic_map.key("*", topics::CARLA_STATS),
+            bridge_config.redeclare_after,
+            publish_failures.clone(),
+        )?;
+        let diagnostics_publisher = StatusPublisher::declare(
+            z_session,
+            topic_map.key("*", topics::DIAGNOSTICS),
+            bridge_config.redeclare_after,
+            publish_failures,
+        )?;
+        Ok(CarlaStats {
+            publisher,
+            diagnostics_publisher,
+            endianness: bridge_config.cdr_endianness,
+            max_frame_time,
+            window_start: None,
+            last_frame: None,
+            frame_time_sum: 0.0,
+            frame_time_max: None,
+            frames: 0,
+            tick_wait_sum: Duration::ZERO,
+            ticks: 0,
+        })
+    }
+
+    /// Start a new window, e.g. when the frames start over with a new world
+    pub fn restart(&mut self) {
+        self.window_start = None;
+        self.last_frame = None;
+        self.clear_window();
+    }
+
+    /// Count the time the loop waited for a tick of the server in synchronous mode
+    pub fn record_tick_wait(&mut self, wait: Duration) {
+        self.tick_wait_sum += wait;
+        self.ticks += 1;
+    }
+
+    /// Count the frame of a processed tick, and publish the stats once per interval.
+    ///
+    /// `settings` gives the synchronous and the no-rendering modes of the world, read only
+    /// when the stats are published.
+    pub fn update(
+        &mut self,
+        timestamp: f64,
+        frame: u64,
+        platform_timestamp: f64,
+        actors: &[ActorInfo],
+        settings: impl FnOnce() -> (bool, bool),
+    ) -> Result<()> {
+        if let Some((last_frame, last_platform_timestamp)) = self.last_frame {
+            if frame > last_frame {
+                let frame_time =
+                    (platform_timestamp - last_platform_timestamp) / (frame - last_frame) as f64;
+                self.frame_time_sum += platform_timestamp - last_platform_timestamp;
+                self.frames += frame - last_frame;
+                self.frame_time_max = Some(
+                    self.frame_time_max
+                        .map_or(frame_time, |max| max.max(frame_time)),
+                );
+            }
+        }
+        self.last_frame = Some((frame, platform_timestamp));
+        let now = Instant::now();
+        let start = *self.window_start.get_or_insert(now);
+        if (now - start).as_secs_f64() < CARLA_STATS_INTERVAL_SEC {
+            return Ok(());
+        }
+        self.window_start = Some(now);
+
+        let mut actor_counts = BTreeMap::new();
+        for actor in actors {
+            let category = actor.type_id.split('.').next().unwrap_or_default();
+            *actor_counts.entry(category.to_string()).or_insert(0) += 1;
+        }
+        let (synchronous_mode, no_rendering_mode) = settings();
+        let report = CarlaStatsReport {
+            stamp: timestamp,
+            frame,
+            frame_time_avg_sec: (self.frames > 0).then(|| self.frame_time_sum / self.frames as f64),
+            frame_time_max_sec: self.frame_time_max,
+            tick_wait_avg_sec: (self.ticks > 0)
+                .then(|| (self.tick_wait_sum / self.ticks).as_secs_f64()),
+            actors: actor_counts,
+            synchronous_mode,
+            no_rendering_mode,
+        };
+        self.publisher.put(serde_json::to_vec(&report)?);
+        cdr_utils::put(
+            &self.diagnostics_publisher,
+            &self.diagnostics(&report),
+            self.endianness,
+        )?;
+        self.clear_window();
+        Ok(())
+    }
+
+    /// A WARN while the average frame time exceeds `max_frame_time`
+    fn diagnostics(&self, report: &CarlaStatsReport) -> DiagnosticArray {
+        let (level, message) = match report.frame_time_avg_sec {
+            Some(frame_time) if frame_time > self.max_frame_time => {
+                warn!("The CARLA server takes {frame_time:.3} s per frame");
+                (diagnostic_status::WARN, "The server frames are slow")
+            }
+            Some(_) => (diagnostic_status::OK, "The server frames are on time"),
+            None => (diagnostic_status::OK, "No server frame in the last second"),
+        };
+        let mut values = Vec::new();
+        let seconds = [
+            ("frame_time_avg_sec", report.frame_time_avg_sec),
+            ("frame_time_max_sec", report.frame_time_max_sec),
+            ("tick_wait_avg_sec", report.tick_wait_avg_sec),
+        ];
+        for (key, value) in seconds {
+            if let Some(value) = value {
+                values.push(KeyValue {
+                    key: key.to_string(),
+                    value: format!("{value:.4}"),
+                });
+            }
+        }
+        values.push(KeyValue {
+            key: String::from("max_frame_time_sec"),
+            value: format!("{:.4}", self.max_frame_time),
+        });
+        DiagnosticArray {
+            header: utils::ros_header(TimeStamp::from_secs_f64(report.stamp), ""),
+            status: vec![DiagnosticStatus {
+                level,
+                name: String::from("carla_bridge: server"),
+                message: message.to_string(),
+                hardware_id: String::from("carla"),
+                values,
+            }],
+        }
+    }
+
+    fn clear_window(&mut self) {
+        self.frame_time_sum = 0.0;
+        self.frame_time_max = None;
+        self.frames = 0;
+        self.tick_wait_sum = Duration::ZERO;
+        self.ticks = 0;
+    }
+}
@@ -0,0 +1,333 @@
+use crate::{
+    debug_json,
+    error::{Error, Result},
+    stats::VehicleStats,
+    status_publisher::StatusPublisher,
+    utils::LogThrottle,
+};
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use cdr::{Bounded, CdrBe, CdrLe, Infinite};
+use log::warn;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_derive::Deserialize;
+use std::{
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// Size of the RTPS encapsulation header in front of every CDR payload
+pub const ENCAPSULATION_HEADER_SIZE: usize = 4;
+/// Representation identifier of plain CDR, big-endian
+pub const CDR_BE: [u8; 2] = [0x00, 0x00];
+/// Representation identifier of plain CDR, little-endian
+pub const CDR_LE: [u8; 2] = [0x00, 0x01];
+/// CDR pads the end of a message up to this alignment
+const MAX_PADDING: usize = 3;
+/// How many bytes of a rejected payload are logged
+const LOGGED_BYTES: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(try_from = "String")]
+#[non_exhaustive]
+pub enum Endianness {
+    #[default]
+    Little,
+    Big,
+}
+
+impl FromStr for Endianness {
+    type Err = String;
+
+    fn from_str(endianness: &str) -> Result<Self, Self::Err> {
+        match endianness {
+            "little" | "le" => Ok(Endianness::Little),
+            "big" | "be" => Ok(Endianness::Big),
+            _ => Err(format!(
+                "Unknown endianness '{endianness}', expect little or big"
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for Endianness {
+    type Error = String;
+
+    fn try_from(endianness: String) -> Result<Self, Self::Error> {
+        endianness.parse()
+    }
+}
+
+/// Read the encapsulation header and return the endianness of the payload
+pub fn parse_header(payload: &[u8]) -> Result<Endianness> {
+    if payload.len() < ENCAPSULATION_HEADER_SIZE {
+        return Err(Error::InvalidPayloadLength {
+            len: payload.len(),
+            expected: ENCAPSULATION_HEADER_SIZE,
+        });
+    }
+    match [payload[0], payload[1]] {
+        CDR_LE => Ok(Endianness::Little),
+        CDR_BE => Ok(Endianness::Big),
+        header => Err(Error::UnknownEncapsulation { header }),
+    }
+}
+
+/// Deserialize a CDR payload after checking its encapsulation header.
+///
+/// Payloads with trailing data the type can't account for are rejected, since
+/// they usually mean the publisher uses another message layout.
+///
+/// Anyone on the zenoh network can publish, so the reads are bounded by the size of the
+/// payload: a string or a sequence claiming a length beyond it fails before its buffer
+/// is allocated.
+pub fn deserialize<T>(payload: &[u8]) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    let endianness = parse_header(payload)?;
+    let data = &payload[ENCAPSULATION_HEADER_SIZE..];
+    let (value, consumed) = match endianness {
+        Endianness::Little => deserialize_data::<T, LittleEndian>(data)?,
+        Endianness::Big => deserialize_data::<T, BigEndian>(data)?,
+    };
+    if data.len() > consumed + MAX_PADDING {
+        return Err(Error::InvalidPayloadLength {
+            len: payload.len(),
+            expected: consumed + ENCAPSULATION_HEADER_SIZE,
+        });
+    }
+    Ok(value)
+}
+
+/// Deserialize the data after the header, and how many of its bytes the value took
+fn deserialize_data<T, E>(data: &[u8]) -> Result<(T, usize)>
+where
+    T: DeserializeOwned,
+    E: ByteOrder,
+{
+    let mut reader = data;
+    let value = {
+        let mut deserializer =
+            cdr::Deserializer::<_, _, E>::new(&mut reader, Bounded(data.len() as u64));
+        serde::Deserialize::deserialize(&mut deserializer)?
+    };
+    Ok((value, data.len() - reader.len()))
+}
+
+/// Serialize a message into a CDR payload, including the encapsulation header
+pub fn serialize<T>(value: &T, endianness: Endianness) -> Result<Vec<u8>>
+where
+    T: Serialize,
+{
+    Ok(match endianness {
+        Endianness::Little => cdr::serialize::<_, _, CdrLe>(value, Infinite)?,
+        Endianness::Big => cdr::serialize::<_, _, CdrBe>(value, Infinite)?,
+    })
+}
+
+/// `serialize` for a key, with its JSON copy under `--debug-json`
+pub fn serialize_for<T>(key: &str, value: &T, endianness: Endianness) -> Result<Vec<u8>>
+where
+    T: Serialize,
+{
+    debug_json::mirror(key, value);
+    serialize(value, endianness)
+}
+
+/// Serialize the message and put it, with its JSON copy under `--debug-json`.
+///
+/// Only a serialization error is returned, the publisher handles the zenoh ones.
+pub fn put<T>(publisher: &StatusPublisher, value: &T, endianness: Endianness) -> Result<()>
+where
+    T: Serialize,
+{
+    let payload = serialize_for(publisher.key_expr(), value, endianness)?;
+    publisher.put(payload);
+    Ok(())
+}
+
+/// Decode the samples of a subscribed topic.
+///
+/// Failures are counted in the stats and logged at most once per few seconds,
+/// so a message layout mismatch doesn't look like "no samples arriving".
+pub struct SampleDecoder {
+    topic: String,
+    stats: Arc<VehicleStats>,
+    failures: Arc<AtomicU64>,
+    log: LogThrottle,
+}
+
+impl SampleDecoder {
+    pub fn new(topic: &str, stats: Arc<VehicleStats>) -> SampleDecoder {
+        SampleDecoder {
+            topic: topic.to_string(),
+            failures: stats.topic_failures(topic),
+            stats,
+            log: LogThrottle::new(Duration::from_secs(5)),
+        }
+    }
+
+    pub fn decode<T>(&mut self, payload: &[u8]) -> Option<T>
+    where
+        T: DeserializeOwned + Serialize,
+    {
+        self.decode_with(payload, deserialize)
+    }
+
+    /// Like `decode`, with a custom deserializer, e.g. one depending on the message version
+    pub fn decode_with<T, F>(&mut self, payload: &[u8], deserializer: F) -> Option<T>
+    where
+        T: Serialize,
+        F: FnOnce(&[u8]) -> Result<T>,
+    {
+        match deserializer(payload) {
+            Ok(value) => {
+                debug_json::trace_command(&self.topic, &value);
+                Some(value)
+            }
+            Err(e) => {
+                self.stats.rejected_samples.fetch_add(1, Ordering::Relaxed);
+                let failures = self.failures.fetch_add(1, Ordering::Relaxed) + 1;
+                if self.log.ready() {
+                    let head: Vec<_> = payload
+                        .iter()
+                        .take(LOGGED_BYTES)
+                        .map(|byte| format!("{byte:02x}"))
+                        .collect();
+                    warn!(
+                        "Failed to deserialize a sample on {} ({failures} failures so far): {e}. {} bytes: {}",
+                        self.topic,
+                        payload.len(),
+                        head.join(" ")
+                    );
+                }
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_derive::Serialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Sample {
+        flag: u8,
+        count: u32,
+        name: String,
+    }
+
+    fn sample() -> Sample {
+        Sample {
+            flag: 1,
+            count: 0x0102_0304,
+            name: String::from("ego"),
+        }
+    }
+
+    #[test]
+    fn round_trip_in_both_endiannesses() {
+        for endianness in [Endianness::Little, Endianness::Big] {
+            let payload = serialize(&sample(), endianness).unwrap();
+            assert_eq!(parse_header(&payload).unwrap(), endianness);
+            assert_eq!(deserialize::<Sample>(&payload).unwrap(), sample());
+        }
+    }
+
+    #[test]
+    fn read_a_big_endian_payload() {
+        let payload = [
+            0x00, 0x00, 0x00, 0x00, // CDR_BE, options
+            0x01, 0x00, 0x00, 0x00, // flag, padding
+            0x01, 0x02, 0x03, 0x04, // count
+            0x00, 0x00, 0x00, 0x04, b'e', b'g', b'o', 0x00, // name
+        ];
+        assert_eq!(deserialize::<Sample>(&payload).unwrap(), sample());
+        assert_eq!(serialize(&sample(), Endianness::Big).unwrap(), payload);
+    }
+
+    #[test]
+    fn reject_a_short_payload() {
+        assert!(matches!(
+            deserialize::<Sample>(&[0x00, 0x01]),
+            Err(Error::InvalidPayloadLength {
+                len: 2,
+                expected: ENCAPSULATION_HEADER_SIZE
+            })
+        ));
+        // A complete header without the message the type needs
+        assert!(deserialize::<Sample>(&[0x00, 0x01, 0x00, 0x00, 0x01]).is_err());
+    }
+
+    #[test]
+    fn reject_an_unknown_representation() {
+        let mut payload = serialize(&sample(), Endianness::Little).unwrap();
+        // PL_CDR_LE, the parameter list of the discovery data
+        payload[1] = 0x03;
+        assert!(matches!(
+            deserialize::<Sample>(&payload),
+            Err(Error::UnknownEncapsulation {
+                header: [0x00, 0x03]
+            })
+        ));
+    }
+
+    #[test]
+    fn reject_trailing_bytes() {
+        let payload = serialize(&sample(), Endianness::Little).unwrap();
+        // Up to the alignment, the bytes are padding
+        let mut padded = payload.clone();
+        padded.extend_from_slice(&[0; MAX_PADDING]);
+        assert_eq!(deserialize::<Sample>(&padded).unwrap(), sample());
+
+        let mut trailing = payload.clone();
+        trailing.extend_from_slice(&[0; MAX_PADDING + 1]);
+        assert!(matches!(
+            deserialize::<Sample>(&trailing),
+            Err(Error::InvalidPayloadLength { len, expected })
+                if len == trailing.len() && expected == payload.len()
+        ));
+    }
+
+    #[test]
+    fn reject_a_length_beyond_the_payload() {
+        let payload = [
+            0x00, 0x01, 0x00, 0x00, // CDR_LE, options
+            0x01, 0x00, 0x00, 0x00, // flag, padding
+            0x04, 0x03, 0x02, 0x01, // count
+            0xf0, 0xff, 0xff, 0xff, // a 4 GB name
+        ];
+        assert!(matches!(
+            deserialize::<Sample>(&payload),
+            Err(Error::Cdr(_))
+        ));
+    }
+
+    #[test]
+    fn count_the_failures_of_a_topic() {
+        let stats = Arc::new(VehicleStats::default());
+        let mut decoder = SampleDecoder::new("control_cmd", stats.clone());
+        assert_eq!(decoder.decode::<Sample>(&[0x00]), None);
+        assert_eq!(decoder.decode::<Sample>(&[0x00, 0x02, 0x00, 0x00]), None);
+        let payload = serialize(&sample(), Endianness::Little).unwrap();
+        assert_eq!(decoder.decode::<Sample>(&payload), Some(sample()));
+        assert_eq!(stats.rejected_samples.load(Ordering::Relaxed), 2);
+        assert_eq!(
+            stats.topic_failures("control_cmd").load(Ordering::Relaxed),
+            2
+        );
+    }
+
+    #[test]
+    fn parse_the_endianness() {
+        assert_eq!("le".parse(), Ok(Endianness::Little));
+        assert_eq!("big".parse(), Ok(Endianness::Big));
+        assert!("middle".parse::<Endianness>().is_err());
+    }
+}
@@ -1,39 +1,72 @@
-use crate::error::Result;
-use cdr::{CdrLe, Infinite};
-use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
-use zenoh::{prelude::sync::*, publication::Publisher};
-use zenoh_ros_type::{builtin_interfaces, rosgraph_msgs};
+use crate::{
+    cdr_utils::{self, Endianness},
+    config::BridgeConfig,
+    error::Result,
+    status_publisher::StatusPublisher,
+    time::TimeStamp,
+    topics,
+};
+use serde_derive::Serialize;
+use std::sync::{atomic::AtomicU64, Arc};
+use zenoh::prelude::sync::*;
+use zenoh_ros_type::rosgraph_msgs;
 
-pub struct SimulatorClock<'a> {
-    publisher_clock: Publisher<'a>,
+/// The JSON published on `*/bridge/frame` every tick, to find the frame of a sample in the
+/// CARLA recorder files and the server logs
+#[derive(Serialize)]
+pub struct FrameReport {
+    pub frame: u64,
+    pub episode_id: u64,
+    /// The stamp of the clock and of the headers
+    pub stamp: f64,
+    /// The time of the episode as CARLA counts it
+    pub elapsed_seconds: f64,
 }
 
-impl<'a> SimulatorClock<'a> {
-    pub fn new(z_session: Arc<Session>) -> Result<SimulatorClock<'a>> {
-        let publisher_clock = z_session.declare_publisher("*/rt/clock").res()?;
-        Ok(SimulatorClock { publisher_clock })
+pub struct SimulatorClock {
+    publisher_clock: StatusPublisher,
+    publisher_frame: StatusPublisher,
+    endianness: Endianness,
+}
+
+impl SimulatorClock {
+    /// `publish_failures` counts the failed puts, see `SimStats`
+    pub fn new(
+        z_session: Arc<Session>,
+        bridge_config: &BridgeConfig,
+        publish_failures: Arc<AtomicU64>,
+    ) -> Result<SimulatorClock> {
+        let publisher_clock = StatusPublisher::declare(
+            z_session.clone(),
+            bridge_config.topics.key("*", topics::CLOCK),
+            bridge_config.redeclare_after,
+            publish_failures.clone(),
+        )?;
+        let publisher_frame = StatusPublisher::declare(
+            z_session,
+            bridge_config.topics.key("*", topics::FRAME),
+            bridge_config.redeclare_after,
+            publish_failures,
+        )?;
+        Ok(SimulatorClock {
+            publisher_clock,
+            publisher_frame,
+            endianness: bridge_config.cdr_endianness,
+        })
     }
 
-    pub fn publish_clock(&self, timestamp: Option<f64>) -> Result<()> {
-        let time = if let Some(sec) = timestamp {
-            builtin_interfaces::Time {
-                sec: sec.floor() as i32,
-                nanosec: (sec.fract() * 1000_000_000_f64) as u32,
-            }
-        } else {
-            // If there is no timestamp, use system time
-            let now = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .expect("Unable to get current time");
-            builtin_interfaces::Time {
-                sec: now.as_secs() as i32,
-                nanosec: now.subsec_nanos(),
-            }
+    pub fn publish_clock(&self, stamp: Option<TimeStamp>) -> Result<()> {
+        // If there is no timestamp, use system time
+        let time = stamp.unwrap_or_else(TimeStamp::now);
+        let clock_msg = rosgraph_msgs::Clock {
+            clock: time.to_msg(),
         };
-        let clock_msg = rosgraph_msgs::Clock { clock: time };
-        let encoded = cdr::serialize::<_, _, CdrLe>(&clock_msg, Infinite)?;
-        self.publisher_clock.put(encoded).res()?;
+        cdr_utils::put(&self.publisher_clock, &clock_msg, self.endianness)
+    }
+
+    /// Only the CARLA backend has frames and episodes
+    pub fn publish_frame(&self, report: &FrameReport) -> Result<()> {
+        self.publisher_frame.put(serde_json::to_vec(report)?);
         Ok(())
     }
 }
@@ -0,0 +1,43 @@
+use serde_derive::Serialize;
+use std::collections::VecDeque;
+
+/// How many individual events are kept, the older ones only remain in the totals
+const MAX_EVENTS: usize = 100;
+
+/// A collision in the JSON published on `<vehicle>/bridge/collision_history`
+#[derive(Debug, Clone, Serialize)]
+pub struct CollisionEvent {
+    /// Simulation time (s)
+    pub stamp: f64,
+    pub other_actor_id: Option<u32>,
+    pub other_type_id: Option<String>,
+    /// Norm of the normal impulse (N*s)
+    pub impulse: f64,
+}
+
+/// The collisions of a vehicle since its spawn or the last reset
+#[derive(Debug, Default, Serialize)]
+pub struct CollisionHistory {
+    pub count: u64,
+    /// Sum of the impulses of every collision, including the dropped events (N*s)
+    pub cumulative_impulse: f64,
+    pub last: Option<CollisionEvent>,
+    /// The latest events, oldest first
+    pub events: VecDeque<CollisionEvent>,
+}
+
+impl CollisionHistory {
+    pub fn record(&mut self, event: CollisionEvent) {
+        self.count += 1;
+        self.cumulative_impulse += event.impulse;
+        if self.events.len() == MAX_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(event.clone());
+        self.last = Some(event);
+    }
+
+    pub fn reset(&mut self) {
+        *self = CollisionHistory::default();
+    }
+}
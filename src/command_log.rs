@@ -0,0 +1,143 @@
+//! Record the received control commands and replay them later.
+//!
+//! Each entry of the log file is laid out as
+//!
+//! | field        | type          | meaning                                  |
+//! |--------------|---------------|------------------------------------------|
+//! | length       | u32 (LE)      | size of the rest of the entry            |
+//! | kind         | u8            | 0: control_cmd, 1: gear_cmd              |
+//! | timestamp    | f64 (LE)      | simulation time when it was received     |
+//! | name length  | u16 (LE)      | size of the vehicle name                 |
+//! | vehicle name | UTF-8         | vehicle the command was sent to          |
+//! | payload      | bytes         | the CDR payload as received              |
+use crate::error::{Error, Result};
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Write},
+    path::Path,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandKind {
+    Control = 0,
+    Gear = 1,
+}
+
+pub struct CommandEntry {
+    pub kind: CommandKind,
+    pub timestamp: f64,
+    pub payload: Vec<u8>,
+}
+
+pub struct CommandRecorder {
+    file: File,
+    vehicle_name: String,
+}
+
+impl CommandRecorder {
+    pub fn open(path: &Path, vehicle_name: &str) -> Result<CommandRecorder> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(CommandRecorder {
+            file,
+            vehicle_name: vehicle_name.to_string(),
+        })
+    }
+
+    pub fn record(&mut self, kind: CommandKind, timestamp: f64, payload: &[u8]) -> Result<()> {
+        let name = self.vehicle_name.as_bytes();
+        let len = 1 + 8 + 2 + name.len() + payload.len();
+        let mut entry = Vec::with_capacity(4 + len);
+        entry.extend_from_slice(&(len as u32).to_le_bytes());
+        entry.push(kind as u8);
+        entry.extend_from_slice(&timestamp.to_le_bytes());
+        entry.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        entry.extend_from_slice(name);
+        entry.extend_from_slice(payload);
+        // Write the whole entry at once, so several vehicles can append to the same file
+        self.file.write_all(&entry)?;
+        Ok(())
+    }
+}
+
+pub struct CommandReplay {
+    entries: Vec<CommandEntry>,
+    next: usize,
+    start_time: Option<f64>,
+}
+
+impl CommandReplay {
+    /// Load the commands recorded for the vehicle
+    pub fn open(path: &Path, vehicle_name: &str) -> Result<CommandReplay> {
+        let mut buf = vec![];
+        File::open(path)?.read_to_end(&mut buf)?;
+
+        let mut entries = vec![];
+        let mut rest = buf.as_slice();
+        while !rest.is_empty() {
+            let (entry, name, remain) = parse_entry(rest)?;
+            if name == vehicle_name {
+                entries.push(entry);
+            }
+            rest = remain;
+        }
+        Ok(CommandReplay {
+            entries,
+            next: 0,
+            start_time: None,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Return the commands due at the simulation time, relative to the first call
+    pub fn poll(&mut self, timestamp: f64) -> &[CommandEntry] {
+        let Some(first) = self.entries.first() else {
+            return &[];
+        };
+        let start_time = *self.start_time.get_or_insert(timestamp);
+        let elapsed = timestamp - start_time;
+        let begin = self.next;
+        while self.next < self.entries.len()
+            && self.entries[self.next].timestamp - first.timestamp <= elapsed
+        {
+            self.next += 1;
+        }
+        &self.entries[begin..self.next]
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.entries.len()
+    }
+}
+
+fn parse_entry(buf: &[u8]) -> Result<(CommandEntry, &str, &[u8])> {
+    let corrupted = || Error::CorruptedCommandLog;
+    let len = u32::from_le_bytes(buf.get(..4).ok_or_else(corrupted)?.try_into().unwrap()) as usize;
+    let entry = buf.get(4..4 + len).ok_or_else(corrupted)?;
+    let kind = match entry.first() {
+        Some(0) => CommandKind::Control,
+        Some(1) => CommandKind::Gear,
+        _ => return Err(corrupted()),
+    };
+    let timestamp = f64::from_le_bytes(entry.get(1..9).ok_or_else(corrupted)?.try_into().unwrap());
+    let name_len =
+        u16::from_le_bytes(entry.get(9..11).ok_or_else(corrupted)?.try_into().unwrap()) as usize;
+    let name = entry.get(11..11 + name_len).ok_or_else(corrupted)?;
+    let name = std::str::from_utf8(name).map_err(|_| corrupted())?;
+    let payload = entry[11 + name_len..].to_vec();
+    Ok((
+        CommandEntry {
+            kind,
+            timestamp,
+            payload,
+        },
+        name,
+        &buf[4 + len..],
+    ))
+}
@@ -0,0 +1,44 @@
+//! Subscribers of the latched-style command topics, which may fetch the latest value first
+use crate::error::Result;
+use std::sync::Arc;
+use zenoh::{prelude::sync::*, subscriber::Subscriber};
+use zenoh_ext::{QueryingSubscriber, SessionExt};
+
+/// Keeps the subscription alive, whichever kind it is
+pub enum CommandSubscriber<'a> {
+    Plain(Subscriber<'a, ()>),
+    /// Queried the cache of the publisher once at the start, then subscribed.
+    Querying(QueryingSubscriber<'a, ()>),
+}
+
+impl<'a> CommandSubscriber<'a> {
+    /// Subscribe to `key`. With `query_latest`, the samples cached on the publisher side are
+    /// fetched first, so the bridge starts from the last value instead of waiting for the next
+    /// one. Without a cache, it's the same as a plain subscriber.
+    pub fn declare<F>(
+        z_session: &Arc<Session>,
+        key: String,
+        query_latest: bool,
+        callback: F,
+    ) -> Result<CommandSubscriber<'a>>
+    where
+        F: FnMut(Sample) + Send + Sync + 'static,
+    {
+        let subscriber = if query_latest {
+            CommandSubscriber::Querying(
+                z_session
+                    .declare_querying_subscriber(key)
+                    .callback_mut(callback)
+                    .res()?,
+            )
+        } else {
+            CommandSubscriber::Plain(
+                z_session
+                    .declare_subscriber(key)
+                    .callback_mut(callback)
+                    .res()?,
+            )
+        };
+        Ok(subscriber)
+    }
+}
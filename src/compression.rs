@@ -0,0 +1,219 @@
+//! Point clouds compressed for thin links, `compression` of `[sensors]` and `[sensors.lidars.<name>]`
+use crate::{
+    cdr_utils,
+    error::{Error, Result},
+};
+use log::{info, warn};
+use rand::{rngs::StdRng, SeedableRng};
+use rand_distr::{Distribution, Normal};
+use serde_derive::Deserialize;
+use std::{fmt, str::FromStr, sync::Arc, time::Instant};
+use zenoh::prelude::sync::*;
+use zenoh_ros_type::sensor_msgs;
+
+/// The first bytes of a compressed payload
+const MAGIC: [u8; 4] = *b"ZCPC";
+/// The magic, the codec, 3 reserved bytes and the size before the compression (u64, little-endian)
+const HEADER_LEN: usize = 16;
+const CODEC_NONE: u8 = 0;
+const CODEC_LZ4: u8 = 1;
+const CODEC_ZSTD: u8 = 2;
+const DEFAULT_ZSTD_LEVEL: i32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(try_from = "String")]
+#[non_exhaustive]
+pub enum Compression {
+    /// Only the raw point cloud is published.
+    #[default]
+    None,
+    /// Fast enough for every frame, a modest ratio.
+    Lz4,
+    /// The level trades the CPU time of the sensor workers for the ratio.
+    Zstd(i32),
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(compression: &str) -> Result<Self, Self::Err> {
+        let levels = zstd::compression_level_range();
+        match compression.trim() {
+            "none" => Ok(Compression::None),
+            "lz4" => Ok(Compression::Lz4),
+            "zstd" => Ok(Compression::Zstd(DEFAULT_ZSTD_LEVEL)),
+            compression => compression
+                .strip_prefix("zstd(")
+                .and_then(|rest| rest.strip_suffix(')'))
+                .and_then(|level| level.trim().parse::<i32>().ok())
+                .filter(|level| levels.contains(level))
+                .map(Compression::Zstd)
+                .ok_or_else(|| {
+                    format!(
+                        "Unknown compression '{compression}', expect none, lz4 or zstd(<level>) with the level in {}..={}",
+                        levels.start(),
+                        levels.end()
+                    )
+                }),
+        }
+    }
+}
+
+impl TryFrom<String> for Compression {
+    type Error = String;
+
+    fn try_from(compression: String) -> Result<Self, Self::Error> {
+        compression.parse()
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Compression::None => write!(f, "none"),
+            Compression::Lz4 => write!(f, "lz4"),
+            Compression::Zstd(level) => write!(f, "zstd({level})"),
+        }
+    }
+}
+
+/// The serialized message behind the header of the codec
+pub fn compress(compression: Compression, payload: &[u8]) -> Result<Vec<u8>> {
+    let (codec, body) = match compression {
+        Compression::None => (CODEC_NONE, payload.to_vec()),
+        Compression::Lz4 => (CODEC_LZ4, lz4_flex::compress(payload)),
+        Compression::Zstd(level) => (CODEC_ZSTD, zstd::bulk::compress(payload, level)?),
+    };
+    let mut frame = Vec::with_capacity(HEADER_LEN + body.len());
+    frame.extend_from_slice(&MAGIC);
+    frame.extend_from_slice(&[codec, 0, 0, 0]);
+    frame.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    frame.extend_from_slice(&body);
+    Ok(frame)
+}
+
+/// Decode a payload of a `.../compressed` key back into the serialized message
+pub fn decompress(frame: &[u8]) -> Result<(&'static str, Vec<u8>)> {
+    let invalid = |reason: &str| Error::InvalidCompressedPayload {
+        reason: reason.to_string(),
+    };
+    if frame.len() < HEADER_LEN || frame[..4] != MAGIC {
+        return Err(invalid("no header of the bridge"));
+    }
+    let original_len = u64::from_le_bytes(frame[8..HEADER_LEN].try_into().unwrap()) as usize;
+    let body = &frame[HEADER_LEN..];
+    let (codec, payload) = match frame[4] {
+        CODEC_NONE => ("none", body.to_vec()),
+        CODEC_LZ4 => (
+            "lz4",
+            lz4_flex::decompress(body, original_len).map_err(|e| invalid(&e.to_string()))?,
+        ),
+        CODEC_ZSTD => ("zstd", zstd::bulk::decompress(body, original_len)?),
+        codec => return Err(invalid(&format!("unknown codec {codec}"))),
+    };
+    if payload.len() != original_len {
+        return Err(invalid(&format!(
+            "{} bytes after the decompression, the header says {original_len}",
+            payload.len()
+        )));
+    }
+    Ok((codec, payload))
+}
+
+/// `--inspect-compressed`: decode every sample of the key and log what it holds, until killed
+pub fn inspect(z_session: Arc<Session>, key: &str) -> Result<()> {
+    let subscriber = z_session.declare_subscriber(key).res()?;
+    info!("Waiting for compressed point clouds on {key}");
+    while let Ok(sample) = subscriber.recv() {
+        let frame = sample.payload.contiguous();
+        let start = Instant::now();
+        let decoded = decompress(&frame).and_then(|(codec, payload)| {
+            let cloud: sensor_msgs::PointCloud2 = cdr_utils::deserialize(&payload)?;
+            Ok((codec, payload.len(), cloud))
+        });
+        match decoded {
+            Ok((codec, original_len, cloud)) => info!(
+                "{}: {codec}, {} -> {original_len} bytes ({:.2}x), {} points, decoded in {:.2} ms",
+                sample.key_expr,
+                frame.len(),
+                original_len as f64 / frame.len() as f64,
+                cloud.width * cloud.height,
+                start.elapsed().as_secs_f64() * 1000.0
+            ),
+            Err(e) => warn!("{}: {e}", sample.key_expr),
+        }
+    }
+    Ok(())
+}
+
+/// `--bench-compression`: compress a synthetic 64-channel cloud with each setting.
+///
+/// The cloud has the layout of the CARLA lidar (x, y, z and intensity as f32), a ground
+/// plane, walls at varying distances and a 2 cm range noise, a stand-in for an urban scene.
+pub fn benchmark() -> Result<()> {
+    const CHANNELS: usize = 64;
+    const POINTS_PER_CHANNEL: usize = 2048;
+    const RUNS: u32 = 10;
+    let mut rng = StdRng::seed_from_u64(0);
+    let noise = Normal::new(0.0, 0.02).unwrap();
+    let mut data = Vec::with_capacity(CHANNELS * POINTS_PER_CHANNEL * 16);
+    for channel in 0..CHANNELS {
+        let elevation = (-24.8 + 26.8 * channel as f32 / (CHANNELS - 1) as f32).to_radians();
+        for step in 0..POINTS_PER_CHANNEL {
+            let azimuth = std::f32::consts::TAU * step as f32 / POINTS_PER_CHANNEL as f32;
+            let wall =
+                10.0 + 5.0 * (3.0 * azimuth).sin().abs() + 20.0 * (azimuth * 0.5).sin().powi(2);
+            let ground = if elevation < 0.0 {
+                1.73 / (-elevation).sin()
+            } else {
+                f32::INFINITY
+            };
+            let range = wall.min(ground).min(100.0) + noise.sample(&mut rng);
+            let horizontal = range * elevation.cos();
+            let point = [
+                horizontal * azimuth.cos(),
+                horizontal * azimuth.sin(),
+                range * elevation.sin(),
+                (-0.004 * range).exp(),
+            ];
+            data.extend(point.iter().flat_map(|value| value.to_ne_bytes()));
+        }
+    }
+    println!(
+        "{CHANNELS} channels x {POINTS_PER_CHANNEL} points, {} bytes of point data, {RUNS} runs each",
+        data.len()
+    );
+    println!(
+        "{:<10} {:>10} {:>8} {:>14} {:>16}",
+        "setting", "bytes", "ratio", "compress ms", "decompress ms"
+    );
+    let settings = [
+        Compression::Lz4,
+        Compression::Zstd(1),
+        Compression::Zstd(3),
+        Compression::Zstd(9),
+        Compression::Zstd(19),
+    ];
+    for compression in settings {
+        let start = Instant::now();
+        let mut frame = Vec::new();
+        for _ in 0..RUNS {
+            frame = compress(compression, &data)?;
+        }
+        let compress_ms = start.elapsed().as_secs_f64() * 1000.0 / RUNS as f64;
+        let start = Instant::now();
+        for _ in 0..RUNS {
+            decompress(&frame)?;
+        }
+        let decompress_ms = start.elapsed().as_secs_f64() * 1000.0 / RUNS as f64;
+        println!(
+            "{:<10} {:>10} {:>8.2} {:>14.2} {:>16.2}",
+            compression.to_string(),
+            frame.len(),
+            data.len() as f64 / frame.len() as f64,
+            compress_ms,
+            decompress_ms
+        );
+    }
+    Ok(())
+}
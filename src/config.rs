@@ -0,0 +1,600 @@
+use crate::{
+    autoware_version::AutowareVersion,
+    battery::BatteryConfig,
+    cdr_utils::Endianness,
+    compression::Compression,
+    control::CommandLimits,
+    depth::DepthFormat,
+    error::Result,
+    longitudinal::{AckermannGains, PidConfig},
+    radar::RadarFormat,
+    role_overrides::RoleOverrides,
+    sensor_noise::{GnssConfig, ImuConfig, LocalizationConfig},
+    topics::TopicMap,
+};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    str::FromStr,
+    time::Duration,
+};
+
+/// Settings shared by the whole bridge
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct BridgeConfig {
+    pub vehicle: VehicleConfig,
+    /// Overrides of `vehicle`, by vehicle name.
+    pub vehicles: HashMap<String, VehicleConfig>,
+    /// The files of `vehicles.d/`, by role_name pattern, merged over both at the discovery.
+    pub role_overrides: RoleOverrides,
+    /// Only the vehicles whose role_name starts with it are bridged.
+    pub role_name_prefix: String,
+    /// Endianness of the CDR payloads the bridge publishes.
+    pub cdr_endianness: Endianness,
+    /// Decides the layout of the received control commands.
+    pub autoware_version: AutowareVersion,
+    /// The remapped topic names.
+    pub topics: TopicMap,
+    /// The command topics which query the latest value at the start.
+    pub query_latest: HashSet<String>,
+    /// A publisher failing for this long is declared again.
+    pub redeclare_after: Duration,
+    /// Makes the sensor noise reproducible.
+    pub noise_seed: Option<u64>,
+    pub gnss: GnssConfig,
+    pub imu: ImuConfig,
+    /// The ground-truth pose of the vehicles.
+    pub localization: LocalizationConfig,
+    /// Frames kept between a sensor callback and its publisher, the oldest is dropped beyond.
+    pub sensor_queue_depth: usize,
+    /// A sensor whose events are more frames behind the world warns in the diagnostics.
+    pub max_sensor_frame_lag: u64,
+    /// Also publish the point clouds compressed, on `<key>/compressed`.
+    pub lidar_compression: Compression,
+    /// Overrides of `lidar_compression`, by sensor name.
+    pub lidar_compression_overrides: HashMap<String, Compression>,
+    /// The images of the depth cameras.
+    pub depth_format: DepthFormat,
+    /// What the radars publish.
+    pub radar_format: RadarFormat,
+    /// Run the whole pipeline without actuating the vehicles or changing the world.
+    pub dry_run: bool,
+}
+
+impl Default for BridgeConfig {
+    fn default() -> Self {
+        BridgeConfig {
+            vehicle: VehicleConfig::default(),
+            vehicles: HashMap::new(),
+            role_overrides: RoleOverrides::default(),
+            role_name_prefix: String::from("autoware_"),
+            cdr_endianness: Endianness::default(),
+            autoware_version: AutowareVersion::default(),
+            topics: TopicMap::default(),
+            query_latest: HashSet::new(),
+            redeclare_after: Duration::from_secs(2),
+            noise_seed: None,
+            gnss: GnssConfig::default(),
+            imu: ImuConfig::default(),
+            localization: LocalizationConfig::default(),
+            sensor_queue_depth: 2,
+            max_sensor_frame_lag: 5,
+            lidar_compression: Compression::default(),
+            lidar_compression_overrides: HashMap::new(),
+            depth_format: DepthFormat::default(),
+            radar_format: RadarFormat::default(),
+            dry_run: false,
+        }
+    }
+}
+
+impl BridgeConfig {
+    /// The name of a vehicle after the role_name_prefix, None for an NPC left alone
+    pub fn bridged_name<'a>(&self, role_name: &'a str) -> Option<&'a str> {
+        role_name.strip_prefix(self.role_name_prefix.as_str())
+    }
+
+    /// The settings of a vehicle, including its overrides by name and by role_name
+    pub fn vehicle_config(&self, vehicle_name: &str, role_name: &str) -> Result<VehicleConfig> {
+        let config = self.vehicles.get(vehicle_name).unwrap_or(&self.vehicle);
+        self.role_overrides.apply(role_name, config)
+    }
+
+    /// The compression of a lidar, including its override
+    pub fn lidar_compression(&self, sensor_name: &str) -> Compression {
+        self.lidar_compression_overrides
+            .get(sensor_name)
+            .copied()
+            .unwrap_or(self.lidar_compression)
+    }
+}
+
+/// Settings applied to every bridged vehicle
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct VehicleConfig {
+    /// Interpolate the received control commands to the simulation time.
+    pub interpolate_control: bool,
+    /// How the vehicle pitch is handed to the longitudinal control.
+    pub pitch_compensation: PitchCompensation,
+    /// Append the received commands to this file.
+    pub record_commands: Option<PathBuf>,
+    /// Feed the commands from this file instead of subscribing to them.
+    pub replay_commands: Option<PathBuf>,
+    /// Log the control signals of each step into `<stem>_<vehicle>.csv` next to this path.
+    pub signal_log: Option<PathBuf>,
+    /// Parameters of the simulated battery.
+    pub battery: BatteryConfig,
+    /// Steering wheel angle per tire angle, for Autoware commanding the steering wheel.
+    pub steering_ratio: f32,
+    /// The command path active at the start.
+    pub control_path: ControlPath,
+    /// Follow the commanded jerk, capped by this value (in m/s^3).
+    pub jerk_limit: Option<f32>,
+    /// Low-pass the steer of the controller with this time constant (s).
+    pub steer_time_constant: Option<f32>,
+    /// Low-pass the throttle and the brake of the controller with this time constant (s).
+    pub pedal_time_constant: Option<f32>,
+    /// Replace the measured speeds implying a larger acceleration by the previous one (m/s^2).
+    pub max_measured_acceleration: f32,
+    /// The median of the measured speed over this many samples, 1 for none.
+    pub speed_median_samples: usize,
+    /// Turn the brake and reverse lights on and off with the applied control.
+    pub sync_vehicle_lights: bool,
+    /// Hold the vehicle until `/autoware/state` is DRIVING.
+    pub require_autoware_state: bool,
+    /// Bounds of the accepted control and actuation commands.
+    pub command_limits: CommandLimits,
+    /// Which direction a positive steering tire angle turns to.
+    pub steering_convention: SteeringConvention,
+    /// Publish the ground-truth twist for gyro_odometer.
+    pub publish_twist: bool,
+    /// Publish the tire slip estimates on `bridge/debug/slip`.
+    pub publish_slip: bool,
+    /// Variances of the linear x, y, z and angular x, y, z velocity in the twist.
+    pub twist_covariance: [f64; 6],
+    /// Deceleration of the comfortable stop requested by the fail-safe of Autoware (m/s^2).
+    pub mrm_comfortable_deceleration: f32,
+    /// Ceiling of the speed enforced by the bridge, whatever the commands (m/s).
+    pub max_speed: Option<f32>,
+    /// What the vehicle does while no valid command drives it.
+    pub fallback_policy: FallbackPolicy,
+    /// The commands older than this are stale and the fallback takes over (simulation seconds).
+    pub command_timeout: Option<f64>,
+    /// Reset the controller after standing still this long with a zero speed command (simulation seconds).
+    pub standstill_reset: f64,
+    /// Skip the control update when less time passed since the last one (simulation seconds).
+    pub min_control_dt: f64,
+    /// Reset the controller when more time passed, and step it by this much (simulation seconds).
+    pub max_control_dt: f64,
+    /// Re-apply an unchanged control at least every this many control updates, 1 applies all.
+    pub control_refresh_ticks: u32,
+    /// The commands take effect this long after their arrival, 0 without a delay (simulation seconds).
+    pub command_delay: f64,
+    /// The status topics of `status_delay_topics` are published this long after (simulation seconds).
+    pub status_delay: f64,
+    /// The canonical names of the delayed status topics, all of them if empty.
+    pub status_delay_topics: Vec<String>,
+    /// Shift between forward and reverse only below this speed, braking until then (m/s).
+    pub gear_change_speed: f32,
+    /// The doors refuse to open or close above this speed (m/s).
+    pub door_max_speed: f32,
+    /// Select the gear in the bridge instead of the automatic gearbox of CARLA.
+    pub manual_gear_shift: bool,
+    /// Ascending speeds above which the next forward gear is used (m/s).
+    pub gear_shift_speeds: Vec<f32>,
+    /// The downshift happens this much below the shift speed (m/s).
+    pub gear_shift_hysteresis: f32,
+    /// The longitudinal controller of the Ackermann path.
+    pub controller: ControllerKind,
+    /// Gains of the `simple_pid` controller.
+    pub pid: PidConfig,
+    /// Gains pushed to the Ackermann controller of the CARLA server, none keeps its own.
+    pub ackermann_gains: AckermannGains,
+    /// Throttle and brake calibration of `simple_pid`, in the CSV layout of raw_vehicle_cmd_converter.
+    pub accel_map: Option<PathBuf>,
+    pub brake_map: Option<PathBuf>,
+    /// Below this control_cmd rate the diagnostics warn (Hz of wall-clock time).
+    pub min_command_rate: f64,
+    /// How often the light state is read back from CARLA (Hz of simulation time).
+    pub light_status_rate: f64,
+    /// How often the footprint is published, 0 for never (Hz of simulation time).
+    pub footprint_rate: f64,
+    /// Added to every side of the bounding box in the footprint (m).
+    pub footprint_margin: f32,
+    /// The groups of topics the bridge declares.
+    pub topic_groups: TopicGroups,
+}
+
+/// The groups of topics a vehicle bridge declares, `[vehicle.topics]` in the configuration file.
+///
+/// Nothing of a disabled group is declared on zenoh. Without `control`, the bridge only
+/// reports the vehicle and never actuates it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TopicGroups {
+    /// velocity_status.
+    pub velocity_report: bool,
+    /// steering_status.
+    pub steering_report: bool,
+    /// gear_status and gear_cmd.
+    pub gear: bool,
+    /// control_cmd, actuation_cmd, control_mode, the control path, the enable, the steering
+    /// offset with its calibration and the reload of the pedal maps.
+    pub control: bool,
+    /// The turn indicators, the hazard lights and the headlights, reports and commands.
+    pub lights: bool,
+    pub battery: bool,
+    /// door_status and door_cmd.
+    pub doors: bool,
+    /// mrm_state and the status of the stops.
+    pub mrm: bool,
+    /// physics_update.
+    pub physics: bool,
+    /// The diagnostics and the stats of the bridge.
+    pub diagnostics: bool,
+}
+
+impl Default for TopicGroups {
+    fn default() -> Self {
+        TopicGroups {
+            velocity_report: true,
+            steering_report: true,
+            gear: true,
+            control: true,
+            lights: true,
+            battery: true,
+            doors: true,
+            mrm: true,
+            physics: true,
+            diagnostics: true,
+        }
+    }
+}
+
+impl Default for VehicleConfig {
+    fn default() -> Self {
+        VehicleConfig {
+            interpolate_control: false,
+            pitch_compensation: PitchCompensation::default(),
+            record_commands: None,
+            replay_commands: None,
+            signal_log: None,
+            battery: BatteryConfig::default(),
+            steering_ratio: 1.0,
+            control_path: ControlPath::default(),
+            jerk_limit: None,
+            steer_time_constant: None,
+            pedal_time_constant: None,
+            max_measured_acceleration: 30.0,
+            speed_median_samples: 1,
+            sync_vehicle_lights: true,
+            require_autoware_state: false,
+            command_limits: CommandLimits::default(),
+            steering_convention: SteeringConvention::default(),
+            publish_twist: false,
+            publish_slip: false,
+            // Like vehicle_velocity_converter, the unmeasured axes get a large variance
+            twist_covariance: [0.04, 10000.0, 10000.0, 10000.0, 10000.0, 0.01],
+            // Like the comfortable_stop_operator of Autoware
+            mrm_comfortable_deceleration: 1.0,
+            max_speed: None,
+            fallback_policy: FallbackPolicy::default(),
+            command_timeout: None,
+            standstill_reset: 1.0,
+            min_control_dt: 0.001,
+            max_control_dt: 1.0,
+            control_refresh_ticks: 10,
+            command_delay: 0.0,
+            status_delay: 0.0,
+            status_delay_topics: Vec::new(),
+            gear_change_speed: 0.1,
+            door_max_speed: 0.3,
+            manual_gear_shift: false,
+            gear_shift_speeds: vec![4.0, 8.0, 13.0, 18.0],
+            gear_shift_hysteresis: 1.0,
+            controller: ControllerKind::default(),
+            pid: PidConfig::default(),
+            ackermann_gains: AckermannGains::default(),
+            accel_map: None,
+            brake_map: None,
+            min_command_rate: 20.0,
+            light_status_rate: 5.0,
+            footprint_rate: 1.0,
+            footprint_margin: 0.0,
+            topic_groups: TopicGroups::default(),
+        }
+    }
+}
+
+/// Which of the received commands drives the vehicle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", try_from = "String")]
+#[non_exhaustive]
+pub enum ControlPath {
+    /// `control_cmd` through the Ackermann controller of CARLA.
+    #[default]
+    Ackermann,
+    /// `actuation_cmd` as throttle, brake and steer, e.g. from raw_vehicle_cmd_converter.
+    Raw,
+}
+
+/// What the vehicle does before the first command of the active path, or after it went stale
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(try_from = "String")]
+#[non_exhaustive]
+pub enum FallbackPolicy {
+    /// Brake to a standstill, then engage the hand brake.
+    #[default]
+    BrakeHold,
+    /// Release the throttle and the brake.
+    Coast,
+    /// Engage the hand brake right away.
+    HandbrakeImmediate,
+}
+
+/// Which controller turns the speed of `control_cmd` into the vehicle control
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(try_from = "String")]
+#[non_exhaustive]
+pub enum ControllerKind {
+    /// The Ackermann controller of the CARLA server.
+    #[default]
+    CarlaAckermann,
+    /// The PID of the bridge, applying the throttle and the brake.
+    SimplePid,
+}
+
+/// The sign of the steering tire angle in the commands and the reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(try_from = "String")]
+#[non_exhaustive]
+pub enum SteeringConvention {
+    /// A positive angle turns left, like REP 103 and Autoware.
+    #[default]
+    LeftPositive,
+    /// A positive angle turns right, like CARLA.
+    RightPositive,
+}
+
+impl SteeringConvention {
+    /// Multiply an angle with it to convert between the convention and CARLA, both ways
+    pub fn sign(&self) -> f32 {
+        match self {
+            SteeringConvention::LeftPositive => -1.0,
+            SteeringConvention::RightPositive => 1.0,
+        }
+    }
+}
+
+impl FromStr for SteeringConvention {
+    type Err = String;
+
+    fn from_str(convention: &str) -> Result<Self, Self::Err> {
+        match convention.trim() {
+            "left_positive" => Ok(SteeringConvention::LeftPositive),
+            "right_positive" => Ok(SteeringConvention::RightPositive),
+            convention => Err(format!(
+                "Unknown steering convention '{convention}', expect left_positive or right_positive"
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for SteeringConvention {
+    type Error = String;
+
+    fn try_from(convention: String) -> Result<Self, Self::Error> {
+        convention.parse()
+    }
+}
+
+/// Where the namespace of the vehicle topics comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(try_from = "String")]
+#[non_exhaustive]
+pub enum NamespaceSource {
+    /// The role_name without the prefix, `vehicle_<actor_id>` if it's empty.
+    #[default]
+    RoleName,
+    /// `id/<actor_id>`, stable even if the role_names are reused.
+    ActorId,
+    /// The role_name, or `id/<actor_id>` if it's empty or taken by another vehicle.
+    RoleNameOrId,
+}
+
+impl FromStr for NamespaceSource {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        match source.trim() {
+            "role_name" => Ok(NamespaceSource::RoleName),
+            "actor_id" => Ok(NamespaceSource::ActorId),
+            "role_name_or_id" => Ok(NamespaceSource::RoleNameOrId),
+            source => Err(format!(
+                "Unknown namespace source '{source}', expect role_name, actor_id or role_name_or_id"
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for NamespaceSource {
+    type Error = String;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        source.parse()
+    }
+}
+
+/// What the vehicles are simulated with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum Backend {
+    /// The actors of a CARLA server.
+    #[default]
+    Carla,
+    /// A single vehicle integrated by a kinematic bicycle model, without any simulator.
+    Kinematic,
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(backend: &str) -> Result<Self, Self::Err> {
+        match backend.trim() {
+            "carla" => Ok(Backend::Carla),
+            "kinematic" => Ok(Backend::Kinematic),
+            backend => Err(format!(
+                "Unknown backend '{backend}', expect carla or kinematic"
+            )),
+        }
+    }
+}
+
+impl FromStr for FallbackPolicy {
+    type Err = String;
+
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        match policy.trim() {
+            "brake_hold" => Ok(FallbackPolicy::BrakeHold),
+            "coast" => Ok(FallbackPolicy::Coast),
+            "handbrake_immediate" => Ok(FallbackPolicy::HandbrakeImmediate),
+            policy => Err(format!(
+                "Unknown fallback policy '{policy}', expect brake_hold, coast or handbrake_immediate"
+            )),
+        }
+    }
+}
+
+impl FromStr for ControllerKind {
+    type Err = String;
+
+    fn from_str(controller: &str) -> Result<Self, Self::Err> {
+        match controller.trim() {
+            "carla_ackermann" => Ok(ControllerKind::CarlaAckermann),
+            "simple_pid" => Ok(ControllerKind::SimplePid),
+            controller => Err(format!(
+                "Unknown controller '{controller}', expect carla_ackermann or simple_pid"
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for ControllerKind {
+    type Error = String;
+
+    fn try_from(controller: String) -> Result<Self, Self::Error> {
+        controller.parse()
+    }
+}
+
+impl TryFrom<String> for FallbackPolicy {
+    type Error = String;
+
+    fn try_from(policy: String) -> Result<Self, Self::Error> {
+        policy.parse()
+    }
+}
+
+impl TryFrom<String> for PitchCompensation {
+    type Error = String;
+
+    fn try_from(mode: String) -> Result<Self, Self::Error> {
+        mode.parse()
+    }
+}
+
+impl FromStr for ControlPath {
+    type Err = String;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        match path.trim() {
+            "ackermann" => Ok(ControlPath::Ackermann),
+            "raw" => Ok(ControlPath::Raw),
+            path => Err(format!(
+                "Unknown control path '{path}', expect ackermann or raw"
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for ControlPath {
+    type Error = String;
+
+    fn try_from(path: String) -> Result<Self, Self::Error> {
+        path.parse()
+    }
+}
+
+/// Parse a steering ratio, which must be positive to keep the steering loop closed
+pub fn parse_steering_ratio(ratio: &str) -> Result<f32, String> {
+    ratio
+        .trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|ratio| ratio.is_finite() && *ratio > 0.0)
+        .ok_or_else(|| format!("Invalid steering ratio '{ratio}', expect a positive number"))
+}
+
+/// Parse a positive number, e.g. a period or a rate
+pub fn parse_positive(value: &str) -> Result<f64, String> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite() && *value > 0.0)
+        .ok_or_else(|| format!("Invalid value '{value}', expect a positive number"))
+}
+
+/// How much of the measured pitch the longitudinal control compensates for
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(try_from = "String")]
+#[non_exhaustive]
+pub enum PitchCompensation {
+    /// Use the measured pitch as is.
+    #[default]
+    Full,
+    /// Always assume a flat road.
+    Off,
+    /// Ignore pitches smaller than the threshold (in degrees).
+    Deadband(f32),
+}
+
+impl PitchCompensation {
+    pub fn apply(&self, pitch_radians: f32) -> f32 {
+        match *self {
+            PitchCompensation::Full => pitch_radians,
+            PitchCompensation::Off => 0.0,
+            PitchCompensation::Deadband(threshold) => {
+                if pitch_radians.to_degrees().abs() < threshold {
+                    0.0
+                } else {
+                    pitch_radians
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for PitchCompensation {
+    type Err = String;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode.trim() {
+            "full" => Ok(PitchCompensation::Full),
+            "off" => Ok(PitchCompensation::Off),
+            mode => mode
+                .strip_prefix("deadband(")
+                .and_then(|rest| rest.strip_suffix(')'))
+                .map(|threshold| threshold.trim().trim_end_matches('°'))
+                .and_then(|threshold| threshold.parse::<f32>().ok())
+                .filter(|threshold| threshold.is_finite() && *threshold >= 0.0)
+                .map(PitchCompensation::Deadband)
+                .ok_or_else(|| {
+                    format!("Unknown pitch compensation '{mode}', expect full, off or deadband(<degrees>)")
+                }),
+        }
+    }
+}
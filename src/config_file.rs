@@ -0,0 +1,950 @@
+//! The TOML configuration file given by `--config`.
+//!
+//! Every key is optional, and the command line flags override the file.
+use crate::{
+    autoware_version::AutowareVersion,
+    battery::BatteryConfig,
+    bridge::kinematic_vehicle::KinematicConfig,
+    cdr_utils::Endianness,
+    compression::Compression,
+    config::{
+        ControlPath, ControllerKind, FallbackPolicy, NamespaceSource, PitchCompensation,
+        SteeringConvention, TopicGroups, VehicleConfig,
+    },
+    control::CommandLimits,
+    depth::DepthFormat,
+    error::{Error, Result},
+    longitudinal::{AckermannGains, PidConfig},
+    radar::RadarFormat,
+    sensor_noise::{GnssConfig, ImuConfig, LocalizationConfig},
+    topics::{self, KeyStyle},
+};
+use serde_derive::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Printed by `--dump-default-config`
+pub const DEFAULT_CONFIG: &str = r#"# Configuration of zenoh_carla_bridge, every key is optional.
+# The command line flags override the values here.
+
+[carla]
+# Address and port of the CARLA simulator
+address = "127.0.0.1"
+port = 2000
+# Only the vehicles whose role_name starts with the prefix are bridged.
+# The rest of the role_name is the namespace of the vehicle topics.
+role_name_prefix = "autoware_"
+# The namespace of the vehicle topics: "role_name", "actor_id" (id/<actor_id>)
+# or "role_name_or_id" (id/<actor_id> when the role_name is empty or taken)
+namespace_source = "role_name"
+
+[zenoh]
+# Endpoints the zenoh session listens on
+listen = ["tcp/localhost:7447"]
+# Stamp every publication with the HLC time of the session, which the zenoh storages and
+# the replay tools use to order the samples
+timestamping = true
+# Endianness of the published CDR payloads: "little" or "big"
+cdr_endianness = "little"
+# Layout of the received control commands: "universe-2023" (AckermannControlCommand)
+# or "universe-2024" (autoware_control_msgs/Control)
+autoware_version = "universe-2023"
+# The vehicles stop with their fallback_policy once the session has had neither a router nor
+# a peer for longer (wall-clock seconds), and resume when it reconnects
+disconnect_timeout = 3.0
+
+[loop]
+# Let CARLA run on its own instead of ticking it from the bridge
+asynchronous = false
+# Simulated seconds per tick in synchronous mode, which is also the period of the bridge loop
+fixed_delta_seconds = 0.05
+# Rate of the bridge loop in asynchronous mode (Hz)
+loop_rate = 20.0
+# Warn when the simulation runs slower than this factor of real time
+# min_real_time_factor = 0.8
+# The diagnostics warn when the CARLA server takes longer between its frames on average,
+# over a second (wall-clock seconds)
+max_frame_time = 0.1
+# Publish the vehicle status and apply the control at most at these rates (Hz), on the
+# simulation time, instead of at every tick
+# status_rate = 25.0
+# control_rate = 100.0
+# The simulation counts as stalled when no tick finished and the world frame didn't advance
+# for this long, e.g. when the server hangs (wall-clock seconds). The heartbeat then reports
+# stalled and */diagnostics an ERROR
+stall_timeout = 10.0
+# Exit on a stall instead, with the code 3, so a restart policy reconnects to CARLA
+exit_on_stall = false
+
+[record]
+# Record the received control commands into the file
+# commands = "commands.log"
+# Replay the control commands from the file instead of subscribing to them
+# replay_commands = "commands.log"
+# Log the control signals of each step, one CSV per vehicle: signals_<vehicle>.csv
+# signal_log = "signals.csv"
+# Record all the published topics into a MCAP file
+# mcap = "bridge.mcap"
+# Start a new MCAP file once the current one exceeds the size (in MB)
+# mcap_max_size = 1024
+# Also record the received commands into the MCAP file
+mcap_received = false
+
+# Applied to every vehicle
+[vehicle]
+# Interpolate control commands between their stamps instead of latching the latest one
+interpolate_control = false
+# Pitch compensation of the longitudinal control: "full", "off" or "deadband(<degrees>)"
+pitch_compensation = "full"
+# Steering wheel angle per tire angle, if Autoware commands the steering wheel angle
+steering_ratio = 1.0
+# Which way a positive steering tire angle turns: "left_positive" (Autoware) or "right_positive" (CARLA)
+steering_convention = "left_positive"
+# The command driving the vehicle at the start: "ackermann" (control_cmd) or "raw" (actuation_cmd)
+control_path = "ackermann"
+# Ramp the acceleration with the commanded jerk, which is capped by this value (m/s^3)
+# jerk_limit = 10.0
+# Smooth the steer, and the throttle and brake of simple_pid, before they are applied, with
+# first-order low-pass filters of these time constants (s). They start over after a gear
+# change, a teleport and an MRM, where the control should jump
+# steer_time_constant = 0.1
+# pedal_time_constant = 0.1
+# A measured speed implying a larger acceleration is replaced by the previous one, in the
+# velocity report and in the control, against the spikes of collisions (m/s^2). It has to
+# stay above the hardest braking, about 10 m/s^2
+max_measured_acceleration = 30.0
+# Then the median of the measured speed over 1 (off), 2 or 3 samples
+speed_median_samples = 1
+# Turn the brake and reverse lights on and off with the applied control
+sync_vehicle_lights = true
+# Hold the vehicle until /autoware/state is DRIVING, against the leftover commands while
+# Autoware starts up. Off since the minimal setups don't publish the state
+require_autoware_state = false
+# Drop the commands beyond these bounds, or with NaN or infinite values
+# Largest tire angle (degrees)
+max_command_steering_angle = 70.0
+# Largest speed (m/s)
+max_command_speed = 60.0
+# Largest acceleration and deceleration (m/s^2)
+max_command_acceleration = 20.0
+# Publish the ground-truth velocity and yaw rate as TwistWithCovarianceStamped for gyro_odometer
+publish_twist = false
+# Publish the longitudinal slips and the slip angles of both axles on bridge/debug/slip
+publish_slip = false
+# Variances of the linear x, y, z and angular x, y, z velocity in the twist
+twist_covariance = [0.04, 10000.0, 10000.0, 10000.0, 10000.0, 0.01]
+# Deceleration of the comfortable stop requested by the fail-safe of Autoware (m/s^2)
+mrm_comfortable_deceleration = 1.0
+# Hold the speed under this ceiling whatever the commands, braking if it's still exceeded
+# by 0.5 m/s, e.g. downhill. The emergency and comfortable stops aren't affected (m/s)
+# max_speed_mps = 8.0
+# What the vehicle does before the first command of the active control path, or while the
+# commands are stale: "brake_hold" (stop, then hand brake), "coast" or "handbrake_immediate"
+fallback_policy = "brake_hold"
+# The commands older than this are stale (simulation seconds), never if not set
+# command_timeout = 0.5
+# Reset the controller after standing still this long with a zero speed command, so the
+# drive away after a long stop doesn't start from the state of the stop (simulation seconds)
+standstill_reset = 1.0
+# The control update is skipped when less time passed since the last one, e.g. when the loop
+# stepped twice in a frame, and the controller starts over when more time passed, e.g. after
+# a debugger pause, stepping by the maximum (simulation seconds)
+min_control_dt = 0.001
+max_control_dt = 1.0
+# The apply_control RPC is skipped while the control repeats the last applied one, e.g. the
+# brake of a vehicle stopped at a light, yet re-applied every this many control updates in
+# case the server dropped it. 1 applies every control
+control_refresh_ticks = 10
+# Artificial latencies, like the actuation and the sensing of a real vehicle, 0 for none
+# (simulation seconds). control_cmd and actuation_cmd take effect command_delay after their
+# arrival, and the status topics of status_delay_topics are published status_delay after,
+# with their original stamps. An empty status_delay_topics delays every status topic
+command_delay = 0.0
+status_delay = 0.0
+# status_delay_topics = ["rt/vehicle/status/velocity_status", "rt/vehicle/status/steering_status"]
+# Switch between forward and reverse only below this speed, the vehicle brakes to it
+# first when control_cmd asks for the other direction (m/s)
+gear_change_speed = 0.1
+# The door commands are refused above this speed, like on a real vehicle (m/s)
+door_max_speed = 0.3
+# Select the gear in the bridge, e.g. for trucks and buses whose automatic gearbox
+# behaves poorly at low speed. Only the raw control path and the simple_pid controller
+# drive the gearbox, the Ackermann controller of CARLA shifts on its own
+manual_gear_shift = false
+# The next forward gear starts above each speed, ascending (m/s)
+gear_shift_speeds = [4.0, 8.0, 13.0, 18.0]
+# The downshift happens this much below the shift speed, so the gears don't toggle (m/s)
+gear_shift_hysteresis = 1.0
+# The longitudinal controller of the ackermann control path: "carla_ackermann" (the
+# controller of the CARLA server) or "simple_pid" (throttle and brake from the bridge)
+controller = "carla_ackermann"
+# Gains of simple_pid, whose output is an acceleration (m/s^2): per speed error (1/s),
+# per integrated speed error (1/s^2) and per measured acceleration
+pid_kp = 0.5
+pid_ki = 0.1
+pid_kd = 0.0
+# How much of the commanded acceleration is applied directly
+pid_feedforward = 1.0
+# Bound of the integral term, against the windup (m/s^2)
+pid_max_integral = 2.0
+# The acceleration at full throttle and the deceleration at full brake (m/s^2)
+pid_max_acceleration = 3.0
+pid_max_deceleration = 8.0
+# Gains of the Ackermann controller of the CARLA server, pushed when the vehicle is bridged
+# with carla_ackermann: of the speed and of the acceleration loops. The unset ones keep the
+# values of the server. Needs a bridge built with the ackermann_settings feature
+# ackermann_speed_kp = 0.15
+# ackermann_speed_ki = 0.0
+# ackermann_speed_kd = 0.25
+# ackermann_accel_kp = 0.01
+# ackermann_accel_ki = 0.0
+# ackermann_accel_kd = 0.01
+# The accel and brake maps of raw_vehicle_cmd_converter (CSV), which turn the acceleration of
+# simple_pid into the throttle and the brake instead of the two limits above. Both or none
+# accel_map = "accel_map.csv"
+# brake_map = "brake_map.csv"
+# The diagnostics on rt/diagnostics warn when control_cmd arrives at a lower rate, over the
+# last 2 seconds (Hz of wall-clock time)
+min_command_rate = 20.0
+# How often the light state is read back from CARLA for the turn indicators, hazard lights
+# and headlights reports (Hz of simulation time)
+light_status_rate = 5.0
+# How often the footprint polygon of the bounding box is published on rt/vehicle/footprint,
+# 0 for never (Hz of simulation time)
+footprint_rate = 1.0
+# Safety margin added to every side of the bounding box in the footprint (m)
+footprint_margin = 0.0
+# Battery drain while the vehicle exists (percent per second)
+battery_base_drain = 0.001
+# Additional battery drain at full throttle (percent per second)
+battery_throttle_drain = 0.02
+# Charge at the start (percent)
+battery_initial_charge = 100.0
+
+# The groups of topics each vehicle declares on zenoh, none of a disabled group is declared.
+# Without control, the vehicle is only reported and never actuated, e.g. for monitoring
+[vehicle.topics]
+velocity_report = true
+steering_report = true
+# gear_status and gear_cmd
+gear = true
+# control_cmd, actuation_cmd, control_mode and the other control keys
+control = true
+# The turn indicators, hazard lights and headlights
+lights = true
+battery = true
+doors = true
+# mrm_state and the status of the stops
+mrm = true
+physics = true
+# The diagnostics and the stats
+diagnostics = true
+
+# Override the [vehicle] values of a single vehicle, by its namespace. The files of a
+# vehicles.d/ directory next to this file override them again by role_name pattern, each
+# with role_name = "autoware_hero*" and the [vehicle] values at its top level
+# [vehicles.v1]
+# steering_ratio = 15.0
+# [vehicles.v1.topics]
+# control = false
+
+[sensors]
+# Seed the sensor noise and the one of [localization], so two runs of the same simulation
+# publish the same measurements
+# noise_seed = 42
+# Frames of each sensor waiting for the publisher, the oldest one is dropped when a new one
+# doesn't fit. A deeper queue takes bursts at the cost of latency.
+queue_depth = 2
+# Warn in the diagnostics when the events of a sensor are this many frames behind the world,
+# not counting the frames between two events of a sensor with a longer sensor_tick
+max_frame_lag = 5
+# Threads converting and serializing the sensor frames, the physical cores minus 2 if not set
+# workers = 4
+# Also publish the point clouds compressed on <pointcloud key>/compressed: none, lz4 or
+# zstd(<level>). --bench-compression compares them on this machine.
+lidar_compression = "none"
+
+# The images of the depth cameras: raw (32FC1 in meters), compressed_depth (16-bit PNG in
+# millimeters on image_raw/compressedDepth, for image_transport) or both
+depth_format = "raw"
+# What the radars publish: radar_scan (radar_msgs/RadarScan on scan) or pointcloud
+# (PointCloud2 with x, y, z, velocity and intensity on pointcloud)
+radar_format = "radar_scan"
+
+# Override the compression of a single lidar, by its sensor name
+# [sensors.lidars.top]
+# compression = "zstd(3)"
+
+[sensors.gnss]
+# Standard deviation of the east and north position (m)
+horizontal_noise = 0.0
+# Standard deviation of the altitude (m)
+vertical_noise = 0.0
+# Let the position bias drift as a random walk, with this spread after one second (m)
+bias_drift = 0.0
+# Variances of east, north and up in the published fix (m^2), unknown if not set
+# position_covariance = [1.0, 1.0, 4.0]
+# Also publish the fixes in the map frame on sensing/gnss/pose_with_covariance, projected with
+# the geoReference of the OpenDRIVE map
+publish_pose = false
+# Where the antenna is from base_link, x forward, y left and z up (m)
+antenna_offset = [0.0, 0.0, 0.0]
+
+[sensors.imu]
+# Row-major covariances in the published message. The EKF of Autoware rejects all zeros.
+orientation_covariance = [0.0001, 0.0, 0.0, 0.0, 0.0001, 0.0, 0.0, 0.0, 0.0001]
+angular_velocity_covariance = [0.0001, 0.0, 0.0, 0.0, 0.0001, 0.0, 0.0, 0.0, 0.0001]
+linear_acceleration_covariance = [0.01, 0.0, 0.0, 0.0, 0.01, 0.0, 0.0, 0.0, 0.01]
+# Standard deviations added on top of the noise of the sensor blueprint
+# Yaw (rad)
+orientation_noise = 0.0
+# Each axis of the angular velocity (rad/s)
+angular_velocity_noise = 0.0
+# Each axis of the linear acceleration (m/s^2)
+linear_acceleration_noise = 0.0
+# Let the biases drift as random walks, with this spread after one second
+angular_velocity_bias_drift = 0.0
+linear_acceleration_bias_drift = 0.0
+
+# The ground-truth pose of each vehicle, for running Autoware without a pose estimator
+[localization]
+# Publish the pose in the map frame on localization/pose_estimator/pose_with_covariance
+publish_pose = false
+# Standard deviations of x and y, of z (m) and of the yaw (rad)
+horizontal_noise = 0.0
+vertical_noise = 0.0
+yaw_noise = 0.0
+# Let the position and the yaw biases drift as random walks, with this spread after one
+# second (m and rad)
+bias_drift = 0.0
+yaw_bias_drift = 0.0
+# Publish the pose this late behind the simulation (ms)
+latency_ms = 0
+# Variances of x, y, z, roll, pitch and yaw in the published pose. The squares of the noise
+# if not set, at least 1e-6 since the EKF of Autoware rejects zeros
+# covariance = [0.01, 0.01, 0.01, 0.0001, 0.0001, 0.001]
+
+[debug]
+# Publish the lanes of the CARLA map as a MarkerArray on rt/bridge/debug/carla_lanes
+carla_lanes = false
+# Distance between the waypoints of the lanes (m)
+lane_resolution = 2.0
+# Set to false on headless servers to disable every drawing in the CARLA world
+drawing = true
+# Draw the trajectory planned by Autoware for each vehicle in the CARLA world
+draw_trajectory = false
+# How long a drawn trajectory stays (s)
+trajectory_lifetime = 1.0
+# Long trajectories are decimated to this many points
+trajectory_max_points = 100
+# Draw the goal pose of each vehicle as an arrow with its namespace
+draw_goal = false
+# Write the Autoware state, the speed and the engage flag above each vehicle, for the demos
+draw_hud = false
+# Also publish the outgoing messages of these key expressions as JSON on <key>/json, for
+# z_sub. A debug feature, it doubles the serialization work. "**" mirrors every key
+# json = ["*/rt/vehicle/status/**"]
+
+[topics]
+# How the ROS topics become keys: "dds_rt" (<namespace>/rt/vehicle/status/velocity_status,
+# like zenoh-bridge-dds), "plain" (<namespace>/vehicle/status/velocity_status, like
+# zenoh-plugin-ros2dds) or a template with {namespace} and {topic}, the name without rt/,
+# e.g. "ros2/{namespace}/{topic}". The bridge topics, bridge/..., keep their names
+key_style = "dds_rt"
+# Fetch the latest value of these command topics at the start, from the publication cache
+# of the Autoware side. Only gear_cmd, headlights_cmd, bridge/control_mode_select,
+# bridge/control_enable and bridge/steering_offset
+query_latest = []
+# A status publisher failing for this long is declared again, with a backoff between the
+# attempts (s). The control of the vehicles goes on meanwhile
+redeclare_after = 2.0
+
+# Rename the topics, relative to the vehicle namespace. `--print-topics` lists them.
+[topics.remap]
+# "rt/vehicle/status/velocity_status" = "rt/vehicle/status/twist"
+
+# Keep the latest samples of the low-rate topics, so a late querying subscriber gets them
+# right away. The depth is per key expression, 0 disables the cache of a topic.
+[topics.cache]
+"rt/bridge/debug/carla_lanes" = 1
+"bridge/collision_history" = 1
+
+# The synthetic vehicle of `--backend kinematic`, driven by a kinematic bicycle model
+[kinematic]
+# The vehicle gets the role_name_prefix followed by this name, e.g. autoware_ego
+vehicle_name = "ego"
+# Distance between the front and rear axles (m)
+wheelbase = 2.79
+# Largest acceleration and deceleration (m/s^2)
+max_acceleration = 3.0
+max_deceleration = 6.0
+# Largest tire angle (rad)
+max_steer_angle = 0.7
+"#;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct FileConfig {
+    pub carla: CarlaSection,
+    pub zenoh: ZenohSection,
+    #[serde(rename = "loop")]
+    pub loop_timing: LoopSection,
+    pub record: RecordSection,
+    pub vehicle: VehicleSection,
+    pub vehicles: BTreeMap<String, VehicleSection>,
+    pub sensors: SensorsSection,
+    pub debug: DebugSection,
+    pub topics: TopicsSection,
+    pub kinematic: KinematicConfig,
+    pub localization: LocalizationConfig,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct DebugSection {
+    pub carla_lanes: Option<bool>,
+    pub lane_resolution: Option<f64>,
+    pub drawing: Option<bool>,
+    pub draw_trajectory: Option<bool>,
+    pub trajectory_lifetime: Option<f32>,
+    pub trajectory_max_points: Option<usize>,
+    pub draw_goal: Option<bool>,
+    pub draw_hud: Option<bool>,
+    pub json: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct SensorsSection {
+    pub noise_seed: Option<u64>,
+    pub queue_depth: Option<usize>,
+    pub max_frame_lag: Option<u64>,
+    pub workers: Option<usize>,
+    pub lidar_compression: Option<Compression>,
+    pub lidars: BTreeMap<String, LidarSection>,
+    pub depth_format: Option<DepthFormat>,
+    pub radar_format: Option<RadarFormat>,
+    pub gnss: GnssConfig,
+    pub imu: ImuConfig,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct LidarSection {
+    pub compression: Option<Compression>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct TopicsSection {
+    /// Canonical topic name to the configured one, both relative to the namespace
+    pub remap: BTreeMap<String, String>,
+    /// How the ROS topics become keys
+    pub key_style: Option<KeyStyle>,
+    /// Canonical names of the command topics fetched at the start
+    pub query_latest: Vec<String>,
+    /// Seconds of failed puts before a publisher is declared again
+    pub redeclare_after: Option<f64>,
+    /// Canonical topic name to the history depth of its publication cache
+    pub cache: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct CarlaSection {
+    pub address: Option<String>,
+    pub port: Option<u16>,
+    pub role_name_prefix: Option<String>,
+    pub namespace_source: Option<NamespaceSource>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct ZenohSection {
+    pub listen: Option<Vec<String>>,
+    pub timestamping: Option<bool>,
+    pub cdr_endianness: Option<Endianness>,
+    pub autoware_version: Option<AutowareVersion>,
+    pub disconnect_timeout: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct LoopSection {
+    pub asynchronous: Option<bool>,
+    pub fixed_delta_seconds: Option<f64>,
+    pub loop_rate: Option<f64>,
+    pub min_real_time_factor: Option<f64>,
+    pub max_frame_time: Option<f64>,
+    pub status_rate: Option<f64>,
+    pub control_rate: Option<f64>,
+    pub stall_timeout: Option<f64>,
+    pub exit_on_stall: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct RecordSection {
+    pub commands: Option<PathBuf>,
+    pub replay_commands: Option<PathBuf>,
+    pub signal_log: Option<PathBuf>,
+    pub mcap: Option<PathBuf>,
+    pub mcap_max_size: Option<u64>,
+    pub mcap_received: Option<bool>,
+}
+
+/// The per-vehicle settings, also used for the command line flags
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct VehicleSection {
+    pub interpolate_control: Option<bool>,
+    pub pitch_compensation: Option<PitchCompensation>,
+    pub steering_ratio: Option<f32>,
+    pub steering_convention: Option<SteeringConvention>,
+    pub control_path: Option<ControlPath>,
+    pub jerk_limit: Option<f32>,
+    pub steer_time_constant: Option<f32>,
+    pub pedal_time_constant: Option<f32>,
+    pub max_measured_acceleration: Option<f32>,
+    pub speed_median_samples: Option<usize>,
+    pub sync_vehicle_lights: Option<bool>,
+    pub require_autoware_state: Option<bool>,
+    /// In degrees, while the commands are in radians
+    pub max_command_steering_angle: Option<f32>,
+    pub max_command_speed: Option<f32>,
+    pub max_command_acceleration: Option<f32>,
+    pub publish_twist: Option<bool>,
+    pub publish_slip: Option<bool>,
+    pub twist_covariance: Option<[f64; 6]>,
+    pub mrm_comfortable_deceleration: Option<f32>,
+    pub max_speed_mps: Option<f32>,
+    pub fallback_policy: Option<FallbackPolicy>,
+    pub command_timeout: Option<f64>,
+    pub standstill_reset: Option<f64>,
+    pub min_control_dt: Option<f64>,
+    pub max_control_dt: Option<f64>,
+    pub control_refresh_ticks: Option<u32>,
+    pub command_delay: Option<f64>,
+    pub status_delay: Option<f64>,
+    pub status_delay_topics: Option<Vec<String>>,
+    pub gear_change_speed: Option<f32>,
+    pub door_max_speed: Option<f32>,
+    pub manual_gear_shift: Option<bool>,
+    pub gear_shift_speeds: Option<Vec<f32>>,
+    pub gear_shift_hysteresis: Option<f32>,
+    pub controller: Option<ControllerKind>,
+    pub pid_kp: Option<f32>,
+    pub pid_ki: Option<f32>,
+    pub pid_kd: Option<f32>,
+    pub pid_feedforward: Option<f32>,
+    pub pid_max_integral: Option<f32>,
+    pub pid_max_acceleration: Option<f32>,
+    pub pid_max_deceleration: Option<f32>,
+    pub ackermann_speed_kp: Option<f32>,
+    pub ackermann_speed_ki: Option<f32>,
+    pub ackermann_speed_kd: Option<f32>,
+    pub ackermann_accel_kp: Option<f32>,
+    pub ackermann_accel_ki: Option<f32>,
+    pub ackermann_accel_kd: Option<f32>,
+    pub accel_map: Option<PathBuf>,
+    pub brake_map: Option<PathBuf>,
+    pub min_command_rate: Option<f64>,
+    pub light_status_rate: Option<f64>,
+    pub footprint_rate: Option<f64>,
+    pub footprint_margin: Option<f32>,
+    pub battery_base_drain: Option<f32>,
+    pub battery_throttle_drain: Option<f32>,
+    pub battery_initial_charge: Option<f32>,
+    pub topics: TopicToggles,
+}
+
+/// `[vehicle.topics]`, the groups of `TopicGroups` to enable or disable
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct TopicToggles {
+    pub velocity_report: Option<bool>,
+    pub steering_report: Option<bool>,
+    pub gear: Option<bool>,
+    pub control: Option<bool>,
+    pub lights: Option<bool>,
+    pub battery: Option<bool>,
+    pub doors: Option<bool>,
+    pub mrm: Option<bool>,
+    pub physics: Option<bool>,
+    pub diagnostics: Option<bool>,
+}
+
+impl TopicToggles {
+    /// Replace the groups which are set here
+    pub fn apply(&self, groups: &TopicGroups) -> TopicGroups {
+        TopicGroups {
+            velocity_report: self.velocity_report.unwrap_or(groups.velocity_report),
+            steering_report: self.steering_report.unwrap_or(groups.steering_report),
+            gear: self.gear.unwrap_or(groups.gear),
+            control: self.control.unwrap_or(groups.control),
+            lights: self.lights.unwrap_or(groups.lights),
+            battery: self.battery.unwrap_or(groups.battery),
+            doors: self.doors.unwrap_or(groups.doors),
+            mrm: self.mrm.unwrap_or(groups.mrm),
+            physics: self.physics.unwrap_or(groups.physics),
+            diagnostics: self.diagnostics.unwrap_or(groups.diagnostics),
+        }
+    }
+}
+
+impl FileConfig {
+    pub fn load(path: &Path) -> Result<FileConfig> {
+        let text = fs::read_to_string(path)?;
+        toml::from_str(&text).map_err(|e| Error::Config {
+            reason: format!("{}: {e}", path.display()),
+        })
+    }
+}
+
+impl VehicleSection {
+    /// Replace the values of `config` which are set here
+    pub fn apply(&self, config: &VehicleConfig) -> Result<VehicleConfig> {
+        if matches!(self.steering_ratio, Some(ratio) if !(ratio.is_finite() && ratio > 0.0)) {
+            return Err(Error::Config {
+                reason: String::from("steering_ratio must be positive"),
+            });
+        }
+        if matches!(self.jerk_limit, Some(jerk) if !(jerk.is_finite() && jerk >= 0.0)) {
+            return Err(Error::Config {
+                reason: String::from("jerk_limit must not be negative"),
+            });
+        }
+        let limits = [
+            (
+                "max_command_steering_angle",
+                self.max_command_steering_angle,
+            ),
+            ("max_command_speed", self.max_command_speed),
+            ("max_command_acceleration", self.max_command_acceleration),
+            (
+                "mrm_comfortable_deceleration",
+                self.mrm_comfortable_deceleration,
+            ),
+            ("max_speed_mps", self.max_speed_mps),
+            ("gear_change_speed", self.gear_change_speed),
+            ("door_max_speed", self.door_max_speed),
+            ("pid_max_acceleration", self.pid_max_acceleration),
+            ("pid_max_deceleration", self.pid_max_deceleration),
+            ("steer_time_constant", self.steer_time_constant),
+            ("pedal_time_constant", self.pedal_time_constant),
+            ("max_measured_acceleration", self.max_measured_acceleration),
+        ];
+        for (name, limit) in limits {
+            if matches!(limit, Some(limit) if !(limit.is_finite() && limit > 0.0)) {
+                return Err(Error::Config {
+                    reason: format!("{name} must be positive"),
+                });
+            }
+        }
+        if self
+            .speed_median_samples
+            .map_or(false, |samples| !(1..=3).contains(&samples))
+        {
+            return Err(Error::Config {
+                reason: String::from("speed_median_samples must be 1, 2 or 3"),
+            });
+        }
+        if self
+            .command_timeout
+            .map_or(false, |timeout| !(timeout.is_finite() && timeout > 0.0))
+        {
+            return Err(Error::Config {
+                reason: String::from("command_timeout must be positive"),
+            });
+        }
+        if self
+            .min_command_rate
+            .map_or(false, |rate| !(rate.is_finite() && rate >= 0.0))
+        {
+            return Err(Error::Config {
+                reason: String::from("min_command_rate must not be negative"),
+            });
+        }
+        if self
+            .light_status_rate
+            .map_or(false, |rate| !(rate.is_finite() && rate > 0.0))
+        {
+            return Err(Error::Config {
+                reason: String::from("light_status_rate must be positive"),
+            });
+        }
+        if self
+            .footprint_rate
+            .map_or(false, |rate| !(rate.is_finite() && rate >= 0.0))
+        {
+            return Err(Error::Config {
+                reason: String::from("footprint_rate must not be negative"),
+            });
+        }
+        if self
+            .footprint_margin
+            .map_or(false, |margin| !(margin.is_finite() && margin >= 0.0))
+        {
+            return Err(Error::Config {
+                reason: String::from("footprint_margin must not be negative"),
+            });
+        }
+        if self
+            .standstill_reset
+            .map_or(false, |duration| !(duration.is_finite() && duration > 0.0))
+        {
+            return Err(Error::Config {
+                reason: String::from("standstill_reset must be positive"),
+            });
+        }
+        for (name, dt) in [
+            ("min_control_dt", self.min_control_dt),
+            ("max_control_dt", self.max_control_dt),
+        ] {
+            if matches!(dt, Some(dt) if !(dt.is_finite() && dt > 0.0)) {
+                return Err(Error::Config {
+                    reason: format!("{name} must be positive"),
+                });
+            }
+        }
+        if let (Some(min), Some(max)) = (self.min_control_dt, self.max_control_dt) {
+            if min >= max {
+                return Err(Error::Config {
+                    reason: String::from("min_control_dt must be below max_control_dt"),
+                });
+            }
+        }
+        for (name, delay) in [
+            ("command_delay", self.command_delay),
+            ("status_delay", self.status_delay),
+        ] {
+            if matches!(delay, Some(delay) if !(delay.is_finite() && delay >= 0.0)) {
+                return Err(Error::Config {
+                    reason: format!("{name} must not be negative"),
+                });
+            }
+        }
+        if let Some(topic) = self
+            .status_delay_topics
+            .iter()
+            .flatten()
+            .find(|topic| !topics::is_known(topic))
+        {
+            return Err(Error::Config {
+                reason: format!("Unknown topic '{topic}' in status_delay_topics"),
+            });
+        }
+        if self.control_refresh_ticks == Some(0) {
+            return Err(Error::Config {
+                reason: String::from("control_refresh_ticks must be at least 1"),
+            });
+        }
+        if let Some(speeds) = &self.gear_shift_speeds {
+            let valid = speeds.iter().all(|speed| speed.is_finite() && *speed > 0.0)
+                && speeds.windows(2).all(|pair| pair[0] < pair[1]);
+            if !valid {
+                return Err(Error::Config {
+                    reason: String::from("gear_shift_speeds must be positive and ascending"),
+                });
+            }
+        }
+        if self.gear_shift_hysteresis.map_or(false, |hysteresis| {
+            !(hysteresis.is_finite() && hysteresis >= 0.0)
+        }) {
+            return Err(Error::Config {
+                reason: String::from("gear_shift_hysteresis must not be negative"),
+            });
+        }
+        let gains = [
+            ("pid_kp", self.pid_kp),
+            ("pid_ki", self.pid_ki),
+            ("pid_kd", self.pid_kd),
+            ("pid_feedforward", self.pid_feedforward),
+            ("pid_max_integral", self.pid_max_integral),
+            ("ackermann_speed_kp", self.ackermann_speed_kp),
+            ("ackermann_speed_ki", self.ackermann_speed_ki),
+            ("ackermann_speed_kd", self.ackermann_speed_kd),
+            ("ackermann_accel_kp", self.ackermann_accel_kp),
+            ("ackermann_accel_ki", self.ackermann_accel_ki),
+            ("ackermann_accel_kd", self.ackermann_accel_kd),
+        ];
+        for (name, gain) in gains {
+            if matches!(gain, Some(gain) if !(gain.is_finite() && gain >= 0.0)) {
+                return Err(Error::Config {
+                    reason: format!("{name} must not be negative"),
+                });
+            }
+        }
+        let accel_map = self.accel_map.clone().or_else(|| config.accel_map.clone());
+        let brake_map = self.brake_map.clone().or_else(|| config.brake_map.clone());
+        if accel_map.is_some() != brake_map.is_some() {
+            return Err(Error::Config {
+                reason: String::from("accel_map and brake_map must be set together"),
+            });
+        }
+        let twist_covariance = self.twist_covariance.unwrap_or_default();
+        if twist_covariance
+            .iter()
+            .any(|variance| !(variance.is_finite() && *variance >= 0.0))
+        {
+            return Err(Error::Config {
+                reason: String::from("twist_covariance must not be negative"),
+            });
+        }
+        let command_limits = CommandLimits {
+            max_steering_tire_angle: self.max_command_steering_angle.map_or(
+                config.command_limits.max_steering_tire_angle,
+                f32::to_radians,
+            ),
+            max_speed: self
+                .max_command_speed
+                .unwrap_or(config.command_limits.max_speed),
+            max_acceleration: self
+                .max_command_acceleration
+                .unwrap_or(config.command_limits.max_acceleration),
+        };
+        let battery = BatteryConfig {
+            base_drain: self.battery_base_drain.unwrap_or(config.battery.base_drain),
+            throttle_drain: self
+                .battery_throttle_drain
+                .unwrap_or(config.battery.throttle_drain),
+            initial_charge: self
+                .battery_initial_charge
+                .unwrap_or(config.battery.initial_charge),
+        };
+        let gains = &config.ackermann_gains;
+        let ackermann_gains = AckermannGains {
+            speed_kp: self.ackermann_speed_kp.or(gains.speed_kp),
+            speed_ki: self.ackermann_speed_ki.or(gains.speed_ki),
+            speed_kd: self.ackermann_speed_kd.or(gains.speed_kd),
+            accel_kp: self.ackermann_accel_kp.or(gains.accel_kp),
+            accel_ki: self.ackermann_accel_ki.or(gains.accel_ki),
+            accel_kd: self.ackermann_accel_kd.or(gains.accel_kd),
+        };
+        if !(ackermann_gains.is_empty() || cfg!(feature = "ackermann_settings")) {
+            return Err(Error::Config {
+                reason: String::from(
+                    "the ackermann_* gains need a bridge built with the ackermann_settings feature",
+                ),
+            });
+        }
+        let pid = PidConfig {
+            kp: self.pid_kp.unwrap_or(config.pid.kp),
+            ki: self.pid_ki.unwrap_or(config.pid.ki),
+            kd: self.pid_kd.unwrap_or(config.pid.kd),
+            feedforward: self.pid_feedforward.unwrap_or(config.pid.feedforward),
+            max_integral: self.pid_max_integral.unwrap_or(config.pid.max_integral),
+            max_acceleration: self
+                .pid_max_acceleration
+                .unwrap_or(config.pid.max_acceleration),
+            max_deceleration: self
+                .pid_max_deceleration
+                .unwrap_or(config.pid.max_deceleration),
+        };
+        Ok(VehicleConfig {
+            interpolate_control: self
+                .interpolate_control
+                .unwrap_or(config.interpolate_control),
+            pitch_compensation: self.pitch_compensation.unwrap_or(config.pitch_compensation),
+            steering_ratio: self.steering_ratio.unwrap_or(config.steering_ratio),
+            steering_convention: self
+                .steering_convention
+                .unwrap_or(config.steering_convention),
+            control_path: self.control_path.unwrap_or(config.control_path),
+            jerk_limit: self.jerk_limit.or(config.jerk_limit),
+            steer_time_constant: self.steer_time_constant.or(config.steer_time_constant),
+            pedal_time_constant: self.pedal_time_constant.or(config.pedal_time_constant),
+            max_measured_acceleration: self
+                .max_measured_acceleration
+                .unwrap_or(config.max_measured_acceleration),
+            speed_median_samples: self
+                .speed_median_samples
+                .unwrap_or(config.speed_median_samples),
+            sync_vehicle_lights: self
+                .sync_vehicle_lights
+                .unwrap_or(config.sync_vehicle_lights),
+            require_autoware_state: self
+                .require_autoware_state
+                .unwrap_or(config.require_autoware_state),
+            command_limits,
+            publish_twist: self.publish_twist.unwrap_or(config.publish_twist),
+            publish_slip: self.publish_slip.unwrap_or(config.publish_slip),
+            twist_covariance: self.twist_covariance.unwrap_or(config.twist_covariance),
+            mrm_comfortable_deceleration: self
+                .mrm_comfortable_deceleration
+                .unwrap_or(config.mrm_comfortable_deceleration),
+            max_speed: self.max_speed_mps.or(config.max_speed),
+            fallback_policy: self.fallback_policy.unwrap_or(config.fallback_policy),
+            command_timeout: self.command_timeout.or(config.command_timeout),
+            standstill_reset: self.standstill_reset.unwrap_or(config.standstill_reset),
+            min_control_dt: self.min_control_dt.unwrap_or(config.min_control_dt),
+            max_control_dt: self.max_control_dt.unwrap_or(config.max_control_dt),
+            control_refresh_ticks: self
+                .control_refresh_ticks
+                .unwrap_or(config.control_refresh_ticks),
+            command_delay: self.command_delay.unwrap_or(config.command_delay),
+            status_delay: self.status_delay.unwrap_or(config.status_delay),
+            status_delay_topics: self
+                .status_delay_topics
+                .clone()
+                .unwrap_or_else(|| config.status_delay_topics.clone()),
+            gear_change_speed: self.gear_change_speed.unwrap_or(config.gear_change_speed),
+            door_max_speed: self.door_max_speed.unwrap_or(config.door_max_speed),
+            manual_gear_shift: self.manual_gear_shift.unwrap_or(config.manual_gear_shift),
+            gear_shift_speeds: self
+                .gear_shift_speeds
+                .clone()
+                .unwrap_or_else(|| config.gear_shift_speeds.clone()),
+            gear_shift_hysteresis: self
+                .gear_shift_hysteresis
+                .unwrap_or(config.gear_shift_hysteresis),
+            controller: self.controller.unwrap_or(config.controller),
+            pid,
+            ackermann_gains,
+            accel_map,
+            brake_map,
+            min_command_rate: self.min_command_rate.unwrap_or(config.min_command_rate),
+            light_status_rate: self.light_status_rate.unwrap_or(config.light_status_rate),
+            footprint_rate: self.footprint_rate.unwrap_or(config.footprint_rate),
+            footprint_margin: self.footprint_margin.unwrap_or(config.footprint_margin),
+            battery,
+            topic_groups: self.topics.apply(&config.topic_groups),
+            ..config.clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_default_config_parses() {
+        let config: FileConfig = toml::from_str(DEFAULT_CONFIG).unwrap();
+        assert_eq!(config.zenoh.cdr_endianness, Some(Endianness::Little));
+        assert_eq!(config.localization, LocalizationConfig::default());
+    }
+
+    #[test]
+    fn parse_the_cdr_endianness() {
+        let config: FileConfig = toml::from_str("[zenoh]\ncdr_endianness = \"big\"").unwrap();
+        assert_eq!(config.zenoh.cdr_endianness, Some(Endianness::Big));
+        let config: FileConfig = toml::from_str("[zenoh]\ncdr_endianness = \"le\"").unwrap();
+        assert_eq!(config.zenoh.cdr_endianness, Some(Endianness::Little));
+        assert!(toml::from_str::<FileConfig>("[zenoh]\ncdr_endianness = \"middle\"").is_err());
+    }
+}
@@ -0,0 +1,1364 @@
+use crate::{
+    autoware_type::{mrm_state, ActuationCommand, MrmState},
+    config::{SteeringConvention, VehicleConfig},
+    stats::VehicleStats,
+    time::TimeStamp,
+    utils::LogThrottle,
+};
+use carla::rpc::VehicleControl;
+use log::{debug, warn};
+use nalgebra::{Isometry3, Vector3};
+use std::{
+    collections::VecDeque,
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+use zenoh_ros_type::{
+    autoware_auto_control_msgs::{
+        AckermannControlCommand, AckermannLateralCommand, LongitudinalCommand,
+    },
+    builtin_interfaces::Time,
+};
+
+/// The values of a control command the vehicle controller cares about
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ControlTarget {
+    pub speed: f32,
+    pub acceleration: f32,
+    pub jerk: f32,
+    pub steering_tire_angle: f32,
+}
+
+impl From<&AckermannControlCommand> for ControlTarget {
+    fn from(cmd: &AckermannControlCommand) -> Self {
+        let AckermannControlCommand {
+            lateral:
+                AckermannLateralCommand {
+                    steering_tire_angle,
+                    ..
+                },
+            longitudinal:
+                LongitudinalCommand {
+                    speed,
+                    acceleration,
+                    jerk,
+                    ..
+                },
+            ..
+        } = *cmd;
+        ControlTarget {
+            speed,
+            acceleration,
+            jerk,
+            steering_tire_angle,
+        }
+    }
+}
+
+/// The two most recent control commands, ordered by their stamps
+pub struct CommandHistory {
+    pub previous: Option<Arc<AckermannControlCommand>>,
+    pub latest: Arc<AckermannControlCommand>,
+}
+
+impl CommandHistory {
+    pub fn new(cmd: AckermannControlCommand) -> CommandHistory {
+        CommandHistory {
+            previous: None,
+            latest: Arc::new(cmd),
+        }
+    }
+
+    /// Insert a newly received command, keeping the history ordered even if
+    /// commands arrive out of order.
+    pub fn push(&self, cmd: AckermannControlCommand) -> CommandHistory {
+        let stamp = time_to_secs(&cmd.stamp);
+        if stamp >= time_to_secs(&self.latest.stamp) {
+            return CommandHistory {
+                previous: Some(self.latest.clone()),
+                latest: Arc::new(cmd),
+            };
+        }
+        // An older command arrived late: it can only replace the previous one
+        let previous = match &self.previous {
+            Some(previous) if time_to_secs(&previous.stamp) >= stamp => previous.clone(),
+            _ => Arc::new(cmd),
+        };
+        CommandHistory {
+            previous: Some(previous),
+            latest: self.latest.clone(),
+        }
+    }
+
+    /// The target at `timestamp`, interpolated between the two commands if asked to
+    pub fn target(&self, timestamp: f64, interpolate: bool) -> ControlTarget {
+        let latest = ControlTarget::from(&*self.latest);
+        match &self.previous {
+            Some(previous) if interpolate => interpolate_target(
+                time_to_secs(&previous.stamp),
+                &ControlTarget::from(&**previous),
+                time_to_secs(&self.latest.stamp),
+                &latest,
+                timestamp,
+            ),
+            _ => latest,
+        }
+    }
+}
+
+/// Linearly interpolate speed, acceleration and steering angle between two
+/// stamped targets. Past the latest one, the line extrapolates for at most the
+/// period between the two, so the targets keep ramping while the next command
+/// is on its way, and stops there if it never comes. The extrapolated speed
+/// doesn't cross zero, which would shift the direction. Times before the
+/// previous target are clamped to it.
+pub fn interpolate_target(
+    prev_stamp: f64,
+    prev: &ControlTarget,
+    latest_stamp: f64,
+    latest: &ControlTarget,
+    timestamp: f64,
+) -> ControlTarget {
+    let span = latest_stamp - prev_stamp;
+    if span <= 0.0 || !span.is_finite() {
+        return *latest;
+    }
+    let ratio = ((timestamp - prev_stamp) / span).clamp(0.0, 2.0) as f32;
+    let lerp = |a: f32, b: f32| a + (b - a) * ratio;
+    let speed = lerp(prev.speed, latest.speed);
+    ControlTarget {
+        speed: if latest.speed >= 0.0 {
+            speed.max(0.0)
+        } else {
+            speed.min(0.0)
+        },
+        acceleration: lerp(prev.acceleration, latest.acceleration),
+        jerk: latest.jerk,
+        steering_tire_angle: lerp(prev.steering_tire_angle, latest.steering_tire_angle),
+    }
+}
+
+/// Limit how fast the acceleration target changes, following the commanded jerk
+pub struct JerkLimiter {
+    /// Upper bound of the commanded jerk (m/s^3).
+    max_jerk: f32,
+    acceleration: Option<f32>,
+}
+
+impl JerkLimiter {
+    pub fn new(max_jerk: f32) -> JerkLimiter {
+        JerkLimiter {
+            max_jerk,
+            acceleration: None,
+        }
+    }
+
+    /// Start over from the next target, e.g. after a gear change
+    pub fn reset(&mut self) {
+        self.acceleration = None;
+    }
+
+    /// The acceleration moved towards the target by at most `jerk * elapsed_sec`.
+    /// A zero or negative jerk, commanded or as the upper bound, passes the target through.
+    pub fn limit(&mut self, acceleration: f32, jerk: f32, elapsed_sec: f64) -> f32 {
+        let jerk = jerk.min(self.max_jerk);
+        let limited = match self.acceleration {
+            Some(last) if jerk > 0.0 && elapsed_sec.is_finite() && elapsed_sec >= 0.0 => {
+                let max_change = jerk * elapsed_sec as f32;
+                acceleration.clamp(last - max_change, last + max_change)
+            }
+            _ => acceleration,
+        };
+        self.acceleration = Some(limited);
+        limited
+    }
+}
+
+/// A first-order low-pass filter on a control output, against the chatter at the tick rate
+pub struct LowPass {
+    /// The time to reach 63% of a step (s).
+    time_constant: f32,
+    value: Option<f32>,
+}
+
+impl LowPass {
+    pub fn new(time_constant: f32) -> LowPass {
+        LowPass {
+            time_constant,
+            value: None,
+        }
+    }
+
+    /// Start over from the next input, where the output should jump
+    pub fn reset(&mut self) {
+        self.value = None;
+    }
+
+    /// The output moved towards the input by `1 - exp(-elapsed_sec / time_constant)`, which
+    /// holds the time constant whatever the tick period. The first input passes through.
+    pub fn filter(&mut self, input: f32, elapsed_sec: f64) -> f32 {
+        let filtered = match self.value {
+            Some(last) if elapsed_sec.is_finite() && elapsed_sec >= 0.0 => {
+                let ratio = 1.0 - (-(elapsed_sec as f32) / self.time_constant).exp();
+                last + (input - last) * ratio
+            }
+            _ => input,
+        };
+        self.value = Some(filtered);
+        filtered
+    }
+}
+
+/// Rejected samples in a row after which the speed is taken anyway, e.g. after a teleport
+const MAX_REJECTED_SPEEDS: u32 = 3;
+
+/// Drop the spikes of the measured speed, e.g. of a collision or a physics glitch, before
+/// the velocity report and the controller see them.
+///
+/// A sample implying an acceleration beyond `max_acceleration` since the last accepted one
+/// is replaced by that one, until `MAX_REJECTED_SPEEDS` of them came in a row. The accepted
+/// samples then go through a median of `median_samples`, 1 for none.
+pub struct SpeedFilter {
+    /// Far beyond any braking of a tire on the road (m/s^2).
+    max_acceleration: f32,
+    median_samples: usize,
+    /// (sim time, speed) of the last accepted sample
+    accepted: Option<(f64, f32)>,
+    rejected: u32,
+    recent: VecDeque<f32>,
+}
+
+impl SpeedFilter {
+    pub fn new(max_acceleration: f32, median_samples: usize) -> SpeedFilter {
+        SpeedFilter {
+            max_acceleration,
+            median_samples: median_samples.max(1),
+            accepted: None,
+            rejected: 0,
+            recent: VecDeque::new(),
+        }
+    }
+
+    /// Start over from the next sample, where the speed really jumps
+    pub fn reset(&mut self) {
+        self.accepted = None;
+        self.rejected = 0;
+        self.recent.clear();
+    }
+
+    /// The speed to report and to control with, for the measured one at the sim time (s)
+    pub fn filter(&mut self, speed: f32, timestamp: f64) -> f32 {
+        let accepted = match self.accepted {
+            Some((last_time, last_speed)) if speed.is_finite() => {
+                let elapsed = (timestamp - last_time) as f32;
+                let spike = (speed - last_speed).abs() > self.max_acceleration * elapsed.max(0.0);
+                if spike && self.rejected < MAX_REJECTED_SPEEDS {
+                    self.rejected += 1;
+                    debug!(
+                        "Rejected the measured speed {speed} m/s after {last_speed} m/s {elapsed} s earlier"
+                    );
+                    last_speed
+                } else {
+                    self.rejected = 0;
+                    self.accepted = Some((timestamp, speed));
+                    speed
+                }
+            }
+            Some((_, last_speed)) => last_speed,
+            None => {
+                self.accepted = Some((timestamp, speed));
+                speed
+            }
+        };
+        if self.recent.len() == self.median_samples {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(accepted);
+        let mut sorted: Vec<f32> = self.recent.iter().copied().collect();
+        sorted.sort_by(f32::total_cmp);
+        let middle = sorted.len() / 2;
+        if sorted.len() % 2 == 0 {
+            (sorted[middle - 1] + sorted[middle]) / 2.0
+        } else {
+            sorted[middle]
+        }
+    }
+}
+
+/// Pick the forward gear from the speed, for the manual gearbox.
+///
+/// Gear `n + 2` starts above `shift_speeds[n]`, and the downshift waits until the speed
+/// is `hysteresis` lower, so a speed around a shift point doesn't toggle the gears.
+pub struct GearSelector {
+    /// Ascending upshift speeds (m/s).
+    shift_speeds: Vec<f32>,
+    hysteresis: f32,
+    gear: i32,
+}
+
+impl GearSelector {
+    pub fn new(shift_speeds: Vec<f32>, hysteresis: f32) -> GearSelector {
+        GearSelector {
+            shift_speeds,
+            hysteresis,
+            gear: 1,
+        }
+    }
+
+    pub fn select(&mut self, speed: f32) -> i32 {
+        let speed = speed.abs();
+        // The speeds are ascending, so count the shift points below
+        let upshifts = self
+            .shift_speeds
+            .iter()
+            .take_while(|shift| speed > **shift)
+            .count() as i32;
+        let stays = self
+            .shift_speeds
+            .iter()
+            .take_while(|shift| speed > **shift - self.hysteresis)
+            .count() as i32;
+        // Up as soon as a shift point is passed, down only below it by the hysteresis
+        self.gear = self.gear.clamp(upshifts + 1, stays + 1);
+        self.gear
+    }
+
+    pub fn reset(&mut self) {
+        self.gear = 1;
+    }
+}
+
+/// Below this difference, the throttle, the steer and the brake count as unchanged
+const CONTROL_EPSILON: f32 = 1e-4;
+
+/// Skip the apply_control RPCs repeating the last applied control, e.g. the brake of a
+/// vehicle stopped at a light.
+///
+/// An unchanged control is still applied every `refresh` calls, in case the server dropped
+/// it, e.g. after a teleport. A refresh of 1 applies every control.
+pub struct ControlDeduplicator {
+    refresh: u32,
+    last: Option<VehicleControl>,
+    skipped: u32,
+}
+
+impl ControlDeduplicator {
+    pub fn new(refresh: u32) -> ControlDeduplicator {
+        ControlDeduplicator {
+            refresh,
+            last: None,
+            skipped: 0,
+        }
+    }
+
+    /// Whether the control needs the RPC, false when it repeats the last applied one
+    pub fn should_apply(&mut self, control: &VehicleControl) -> bool {
+        let repeated = self
+            .last
+            .as_ref()
+            .map_or(false, |last| same_control(last, control));
+        if repeated && self.skipped + 1 < self.refresh {
+            self.skipped += 1;
+            return false;
+        }
+        self.last = Some(*control);
+        self.skipped = 0;
+        true
+    }
+
+    /// Forget the last control, once something else drove the vehicle, e.g. the Ackermann
+    /// controller of CARLA, so the next one is applied
+    pub fn invalidate(&mut self) {
+        self.last = None;
+        self.skipped = 0;
+    }
+}
+
+fn same_control(a: &VehicleControl, b: &VehicleControl) -> bool {
+    (a.throttle - b.throttle).abs() < CONTROL_EPSILON
+        && (a.steer - b.steer).abs() < CONTROL_EPSILON
+        && (a.brake - b.brake).abs() < CONTROL_EPSILON
+        && a.hand_brake == b.hand_brake
+        && a.reverse == b.reverse
+        && a.manual_gear_shift == b.manual_gear_shift
+        && a.gear == b.gear
+}
+
+/// A cruise slower than this doesn't tell the steering offset (m/s)
+const CALIBRATION_MIN_SPEED: f32 = 3.0;
+/// The heading holds below this yaw rate (rad/s)
+const CALIBRATION_MAX_YAW_RATE: f32 = 0.01;
+/// A cruise changes its speed by less than this (m/s^2)
+const CALIBRATION_MAX_ACCELERATION: f32 = 0.3;
+
+/// Average the tire angle which holds the heading, for `bridge/steering_offset/auto_calibrate`.
+///
+/// Only the steps of a straight cruise count: above a minimum speed, almost constant, and
+/// without a yaw rate. Whatever steers the vehicle then, e.g. the controller of Autoware,
+/// compensates the drift, so the mean measured tire angle is the offset.
+pub struct SteeringCalibration {
+    /// How long the vehicle has to cruise (simulation seconds)
+    duration: f64,
+    /// Without enough cruise until then, the calibration gives up (simulation time)
+    deadline: f64,
+    cruise_sec: f64,
+    /// Sum of the tire angles weighted by the step durations (rad*s)
+    weighted_angle: f64,
+    last_speed: Option<f32>,
+}
+
+impl SteeringCalibration {
+    pub fn new(duration: f64, timeout: f64, timestamp: f64) -> SteeringCalibration {
+        SteeringCalibration {
+            duration,
+            deadline: timestamp + timeout,
+            cruise_sec: 0.0,
+            weighted_angle: 0.0,
+            last_speed: None,
+        }
+    }
+
+    /// Add a step of `elapsed_sec` with the measured speed, yaw rate and tire angle
+    pub fn add(&mut self, speed: f32, yaw_rate: f32, tire_angle: f32, elapsed_sec: f64) {
+        let last_speed = self.last_speed.replace(speed);
+        if !elapsed_sec.is_finite() || elapsed_sec <= 0.0 {
+            return;
+        }
+        let cruising = matches!(last_speed, Some(last)
+            if ((speed - last) / elapsed_sec as f32).abs() < CALIBRATION_MAX_ACCELERATION)
+            && speed.abs() >= CALIBRATION_MIN_SPEED
+            && yaw_rate.abs() < CALIBRATION_MAX_YAW_RATE;
+        if cruising {
+            self.cruise_sec += elapsed_sec;
+            self.weighted_angle += tire_angle as f64 * elapsed_sec;
+        }
+    }
+
+    /// The mean tire angle, once the vehicle cruised long enough
+    pub fn result(&self) -> Option<f32> {
+        (self.cruise_sec >= self.duration).then(|| (self.weighted_angle / self.cruise_sec) as f32)
+    }
+
+    pub fn cruise_sec(&self) -> f64 {
+        self.cruise_sec
+    }
+
+    pub fn timed_out(&self, timestamp: f64) -> bool {
+        timestamp > self.deadline
+    }
+}
+
+/// The minimum risk maneuver overriding the commands while the fail-safe of Autoware asks for it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MrmBehavior {
+    #[default]
+    None,
+    /// Decelerate at the configured rate until the vehicle stops.
+    ComfortableStop,
+    /// Full brake.
+    EmergencyStop,
+}
+
+impl MrmBehavior {
+    /// The behavior of the latest MRM state. It stays until Autoware reports no behavior
+    /// again, also after the vehicle stopped, so a stopped vehicle doesn't drive off.
+    pub fn from_state(state: &MrmState) -> MrmBehavior {
+        match state.behavior {
+            mrm_state::COMFORTABLE_STOP => MrmBehavior::ComfortableStop,
+            mrm_state::EMERGENCY_STOP => MrmBehavior::EmergencyStop,
+            _ => MrmBehavior::None,
+        }
+    }
+}
+
+/// Bounds of the accepted commands. A command outside of them is dropped, and the
+/// previous one stays in effect.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct CommandLimits {
+    /// Largest tire angle in either direction, after the steering ratio (rad).
+    pub max_steering_tire_angle: f32,
+    /// Largest speed in either direction (m/s).
+    pub max_speed: f32,
+    /// Largest acceleration or deceleration (m/s^2).
+    pub max_acceleration: f32,
+}
+
+impl Default for CommandLimits {
+    fn default() -> Self {
+        CommandLimits {
+            max_steering_tire_angle: 70_f32.to_radians(),
+            max_speed: 60.0,
+            max_acceleration: 20.0,
+        }
+    }
+}
+
+impl CommandLimits {
+    /// Check an Ackermann command, whose angle is divided by `steering_ratio` before use
+    pub fn check_control(
+        &self,
+        cmd: &AckermannControlCommand,
+        steering_ratio: f32,
+    ) -> Result<(), String> {
+        let values = [
+            ("steering_tire_angle", cmd.lateral.steering_tire_angle),
+            (
+                "steering_tire_rotation_rate",
+                cmd.lateral.steering_tire_rotation_rate,
+            ),
+            ("speed", cmd.longitudinal.speed),
+            ("acceleration", cmd.longitudinal.acceleration),
+            ("jerk", cmd.longitudinal.jerk),
+        ];
+        if let Some((name, value)) = values.iter().find(|(_, value)| !value.is_finite()) {
+            return Err(format!("{name} is {value}"));
+        }
+        self.check_steering(cmd.lateral.steering_tire_angle / steering_ratio)?;
+        if cmd.longitudinal.speed.abs() > self.max_speed {
+            return Err(format!(
+                "speed {} m/s exceeds {} m/s",
+                cmd.longitudinal.speed, self.max_speed
+            ));
+        }
+        if cmd.longitudinal.acceleration.abs() > self.max_acceleration {
+            return Err(format!(
+                "acceleration {} m/s^2 exceeds {} m/s^2",
+                cmd.longitudinal.acceleration, self.max_acceleration
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check a raw actuation command. The pedals are clamped when applied, so only
+    /// the steering has a bound.
+    pub fn check_actuation(
+        &self,
+        cmd: &ActuationCommand,
+        steering_ratio: f32,
+    ) -> Result<(), String> {
+        let values = [
+            ("accel_cmd", cmd.accel_cmd),
+            ("brake_cmd", cmd.brake_cmd),
+            ("steer_cmd", cmd.steer_cmd),
+        ];
+        if let Some((name, value)) = values.iter().find(|(_, value)| !value.is_finite()) {
+            return Err(format!("{name} is {value}"));
+        }
+        self.check_steering(cmd.steer_cmd as f32 / steering_ratio)
+    }
+
+    fn check_steering(&self, steering_tire_angle: f32) -> Result<(), String> {
+        if steering_tire_angle.abs() > self.max_steering_tire_angle {
+            return Err(format!(
+                "steering tire angle {steering_tire_angle} rad exceeds {} rad",
+                self.max_steering_tire_angle
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Drop the invalid commands of a subscribed topic.
+///
+/// The drops are counted in the stats and logged at most once per few seconds.
+pub struct CommandValidator {
+    topic: String,
+    limits: CommandLimits,
+    steering_ratio: f32,
+    stats: Arc<VehicleStats>,
+    log: LogThrottle,
+}
+
+impl CommandValidator {
+    pub fn new(topic: &str, config: &VehicleConfig, stats: Arc<VehicleStats>) -> CommandValidator {
+        CommandValidator {
+            topic: topic.to_string(),
+            limits: config.command_limits,
+            steering_ratio: config.steering_ratio,
+            stats,
+            log: LogThrottle::new(Duration::from_secs(5)),
+        }
+    }
+
+    pub fn accept_control(&mut self, cmd: &AckermannControlCommand) -> bool {
+        let checked = self.limits.check_control(cmd, self.steering_ratio);
+        self.accept(checked)
+    }
+
+    pub fn accept_actuation(&mut self, cmd: &ActuationCommand) -> bool {
+        let checked = self.limits.check_actuation(cmd, self.steering_ratio);
+        self.accept(checked)
+    }
+
+    fn accept(&mut self, checked: Result<(), String>) -> bool {
+        let Err(reason) = checked else {
+            return true;
+        };
+        let rejected = self.stats.rejected_commands.fetch_add(1, Ordering::Relaxed) + 1;
+        if self.log.ready() {
+            warn!(
+                "Drop an invalid command on {} ({rejected} so far), keep the previous one: {reason}",
+                self.topic
+            );
+        }
+        false
+    }
+}
+
+/// The CARLA steer (-1 to 1, positive to the right) of a tire angle in the convention (rad),
+/// with the tire angle at full steer `max_steer_angle` (degrees)
+pub fn carla_steer(
+    steering_tire_angle: f32,
+    convention: SteeringConvention,
+    max_steer_angle: f32,
+) -> f32 {
+    (steering_tire_angle.to_degrees() * convention.sign() / max_steer_angle).clamp(-1.0, 1.0)
+}
+
+/// The tire angle of the front axle in the convention (rad) of the CARLA wheel angles (degrees).
+///
+/// The inner wheel turns more than the outer one, so a single wheel would be biased
+/// by the turn direction. Both wheels use the same sign in CARLA, so their mean is
+/// the angle of a virtual wheel at the center of the axle. A two-wheeler only has the
+/// front wheel, which CARLA names the front left one.
+pub fn wheel_tire_angle(
+    front_steer_angles: [f32; 2],
+    two_wheeled: bool,
+    convention: SteeringConvention,
+) -> f32 {
+    let [front_left, front_right] = front_steer_angles;
+    let angle = if two_wheeled {
+        front_left
+    } else {
+        (front_left + front_right) / 2.0
+    };
+    angle.to_radians() * convention.sign()
+}
+
+/// The CARLA velocity (m/s) and angular velocity (degrees/s) of the world frame in the
+/// body frame with ROS axes: longitudinal, lateral (m/s) and yaw rate (rad/s)
+pub fn body_twist(
+    transform: &Isometry3<f32>,
+    velocity: &Vector3<f32>,
+    angular_velocity: &Vector3<f32>,
+) -> (f32, f32, f32) {
+    let velocity = transform.rotation.inverse_transform_vector(velocity);
+    // CARLA is left-handed with y to the right, ROS has y to the left
+    (velocity.x, -velocity.y, -angular_velocity.z.to_radians())
+}
+
+/// Whether a target speed (m/s) drives backwards, and the CARLA gear of it
+pub fn direction_gear(speed: f32) -> (bool, i32) {
+    let reverse = speed < 0.0;
+    (reverse, if reverse { -1 } else { 1 })
+}
+
+pub fn time_to_secs(time: &Time) -> f64 {
+    TimeStamp::from_msg(time).to_secs_f64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The degrees of CARLA at full steer in the tests
+    const MAX_STEER: f32 = 70.0;
+
+    fn command(
+        stamp: f64,
+        speed: f32,
+        acceleration: f32,
+        steering_tire_angle: f32,
+    ) -> AckermannControlCommand {
+        let stamp = TimeStamp::from_secs_f64(stamp);
+        AckermannControlCommand {
+            stamp: stamp.to_msg(),
+            lateral: AckermannLateralCommand {
+                stamp: stamp.to_msg(),
+                steering_tire_angle,
+                steering_tire_rotation_rate: 0.0,
+            },
+            longitudinal: LongitudinalCommand {
+                stamp: stamp.to_msg(),
+                speed,
+                acceleration,
+                jerk: 0.0,
+            },
+        }
+    }
+
+    fn target(speed: f32, acceleration: f32, jerk: f32, steering_tire_angle: f32) -> ControlTarget {
+        ControlTarget {
+            speed,
+            acceleration,
+            jerk,
+            steering_tire_angle,
+        }
+    }
+
+    #[test]
+    fn interpolate_between_the_commands() {
+        let prev = target(0.0, 1.0, 0.5, -0.2);
+        let latest = target(10.0, 3.0, 2.0, 0.2);
+        let middle = interpolate_target(1.0, &prev, 2.0, &latest, 1.5);
+        assert_eq!(middle.speed, 5.0);
+        assert_eq!(middle.acceleration, 2.0);
+        assert_eq!(middle.steering_tire_angle, 0.0);
+        // The jerk isn't interpolated, it bounds the change of the acceleration
+        assert_eq!(middle.jerk, 2.0);
+    }
+
+    #[test]
+    fn extrapolate_for_one_command_period() {
+        let prev = target(0.0, 1.0, 0.0, -0.2);
+        let latest = target(10.0, 3.0, 0.0, 0.2);
+        // At the latest stamp, then half and a whole period past it
+        assert_eq!(interpolate_target(1.0, &prev, 2.0, &latest, 2.0), latest);
+        let half = interpolate_target(1.0, &prev, 2.0, &latest, 2.5);
+        assert_eq!(half.speed, 15.0);
+        assert_eq!(half.acceleration, 4.0);
+        assert!((half.steering_tire_angle - 0.4).abs() < 1e-6);
+        let bound = target(20.0, 5.0, 0.0, 0.6);
+        let whole = interpolate_target(1.0, &prev, 2.0, &latest, 3.0);
+        assert_eq!(whole.speed, bound.speed);
+        assert_eq!(whole.acceleration, bound.acceleration);
+        assert!((whole.steering_tire_angle - bound.steering_tire_angle).abs() < 1e-6);
+        // No further, the next command is late
+        assert_eq!(interpolate_target(1.0, &prev, 2.0, &latest, 5.0), whole);
+    }
+
+    #[test]
+    fn the_extrapolated_speed_stops_at_zero() {
+        let prev = target(2.0, 0.0, 0.0, 0.0);
+        let latest = target(0.5, 0.0, 0.0, 0.0);
+        assert_eq!(interpolate_target(1.0, &prev, 2.0, &latest, 3.0).speed, 0.0);
+        let reverse = interpolate_target(
+            1.0,
+            &target(-2.0, 0.0, 0.0, 0.0),
+            2.0,
+            &target(-0.5, 0.0, 0.0, 0.0),
+            3.0,
+        );
+        assert_eq!(reverse.speed, 0.0);
+    }
+
+    #[test]
+    fn clamp_before_the_previous_command() {
+        let prev = target(0.0, 1.0, 0.0, -0.2);
+        let latest = target(10.0, 3.0, 0.0, 0.2);
+        assert_eq!(
+            interpolate_target(1.0, &prev, 2.0, &latest, 0.0),
+            ControlTarget {
+                jerk: latest.jerk,
+                ..prev
+            }
+        );
+    }
+
+    #[test]
+    fn interpolation_needs_a_time_span() {
+        let prev = target(0.0, 0.0, 0.0, 0.0);
+        let latest = target(10.0, 1.0, 0.0, 0.1);
+        assert_eq!(interpolate_target(2.0, &prev, 2.0, &latest, 2.0), latest);
+        assert_eq!(interpolate_target(3.0, &prev, 2.0, &latest, 2.5), latest);
+        assert_eq!(
+            interpolate_target(f64::NEG_INFINITY, &prev, 2.0, &latest, 1.0),
+            latest
+        );
+    }
+
+    #[test]
+    fn history_keeps_the_two_latest_commands() {
+        let history = CommandHistory::new(command(1.0, 1.0, 0.0, 0.0))
+            .push(command(2.0, 2.0, 0.0, 0.0))
+            .push(command(3.0, 3.0, 0.0, 0.0));
+        assert_eq!(history.previous.as_ref().unwrap().longitudinal.speed, 2.0);
+        assert_eq!(history.latest.longitudinal.speed, 3.0);
+    }
+
+    #[test]
+    fn history_orders_the_commands_arriving_out_of_order() {
+        let history =
+            CommandHistory::new(command(2.0, 2.0, 0.0, 0.0)).push(command(1.0, 1.0, 0.0, 0.0));
+        assert_eq!(history.latest.longitudinal.speed, 2.0);
+        assert_eq!(history.previous.as_ref().unwrap().longitudinal.speed, 1.0);
+
+        // A late command newer than the previous one replaces it
+        let history = history.push(command(1.5, 1.5, 0.0, 0.0));
+        assert_eq!(history.latest.longitudinal.speed, 2.0);
+        assert_eq!(history.previous.as_ref().unwrap().longitudinal.speed, 1.5);
+    }
+
+    #[test]
+    fn history_ignores_the_stale_commands() {
+        let history = CommandHistory::new(command(1.0, 1.0, 0.0, 0.0))
+            .push(command(2.0, 2.0, 0.0, 0.0))
+            .push(command(0.5, 0.5, 0.0, 0.0));
+        assert_eq!(history.latest.longitudinal.speed, 2.0);
+        assert_eq!(history.previous.as_ref().unwrap().longitudinal.speed, 1.0);
+    }
+
+    #[test]
+    fn history_interpolates_only_if_asked_to() {
+        let history =
+            CommandHistory::new(command(1.0, 0.0, 0.0, 0.0)).push(command(2.0, 4.0, 2.0, 0.1));
+        assert_eq!(history.target(1.5, false), target(4.0, 2.0, 0.0, 0.1));
+        let interpolated = history.target(1.5, true);
+        assert_eq!(interpolated.speed, 2.0);
+        assert_eq!(interpolated.acceleration, 1.0);
+        // Long after the latest command, its target holds
+        assert_eq!(history.target(10.0, true), target(4.0, 2.0, 0.0, 0.1));
+    }
+
+    #[test]
+    fn a_single_command_is_the_target() {
+        let history = CommandHistory::new(command(1.0, 3.0, 1.0, 0.05));
+        assert_eq!(history.target(0.0, true), target(3.0, 1.0, 0.0, 0.05));
+        assert_eq!(history.target(5.0, true), target(3.0, 1.0, 0.0, 0.05));
+    }
+
+    #[test]
+    fn the_first_acceleration_passes_through() {
+        let mut limiter = JerkLimiter::new(10.0);
+        assert_eq!(limiter.limit(2.0, 1.0, 0.1), 2.0);
+    }
+
+    #[test]
+    fn a_step_is_limited_by_the_jerk() {
+        let mut limiter = JerkLimiter::new(10.0);
+        limiter.limit(0.0, 1.0, 0.1);
+        let max_change = 1.0 * 0.1_f64 as f32;
+        assert_eq!(limiter.limit(2.0, 1.0, 0.1), max_change);
+        assert_eq!(limiter.limit(2.0, 1.0, 0.1), 2.0 * max_change);
+        // Down as well
+        assert_eq!(limiter.limit(-2.0, 1.0, 0.1), max_change);
+    }
+
+    #[test]
+    fn the_commanded_jerk_is_capped() {
+        let mut limiter = JerkLimiter::new(0.5);
+        limiter.limit(0.0, 10.0, 0.1);
+        assert_eq!(limiter.limit(2.0, 10.0, 0.1), 0.5 * 0.1_f64 as f32);
+    }
+
+    #[test]
+    fn a_zero_or_negative_jerk_is_unlimited() {
+        let mut limiter = JerkLimiter::new(10.0);
+        limiter.limit(0.0, 1.0, 0.1);
+        assert_eq!(limiter.limit(2.0, 0.0, 0.1), 2.0);
+        assert_eq!(limiter.limit(-1.0, -1.0, 0.1), -1.0);
+
+        let mut unbounded = JerkLimiter::new(0.0);
+        unbounded.limit(0.0, 1.0, 0.1);
+        assert_eq!(unbounded.limit(2.0, 1.0, 0.1), 2.0);
+    }
+
+    #[test]
+    fn an_invalid_period_is_unlimited() {
+        let mut limiter = JerkLimiter::new(10.0);
+        limiter.limit(0.0, 1.0, 0.1);
+        assert_eq!(limiter.limit(2.0, 1.0, f64::NAN), 2.0);
+        assert_eq!(limiter.limit(0.0, 1.0, -0.1), 0.0);
+    }
+
+    #[test]
+    fn reset_starts_from_the_next_acceleration() {
+        let mut limiter = JerkLimiter::new(10.0);
+        limiter.limit(0.0, 1.0, 0.1);
+        limiter.reset();
+        assert_eq!(limiter.limit(2.0, 1.0, 0.1), 2.0);
+    }
+
+    fn validator(stats: &Arc<VehicleStats>) -> CommandValidator {
+        CommandValidator::new("control_cmd", &VehicleConfig::default(), stats.clone())
+    }
+
+    #[test]
+    fn accept_a_valid_command() {
+        let stats = Arc::new(VehicleStats::default());
+        let mut validator = validator(&stats);
+        assert!(validator.accept_control(&command(1.0, -5.0, -3.0, 0.3)));
+        assert!(validator.accept_actuation(&ActuationCommand {
+            accel_cmd: 0.5,
+            brake_cmd: 0.0,
+            steer_cmd: -0.3,
+        }));
+        assert_eq!(stats.rejected_commands.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn reject_the_non_finite_values() {
+        let stats = Arc::new(VehicleStats::default());
+        let mut validator = validator(&stats);
+        assert!(!validator.accept_control(&command(1.0, f32::NAN, 0.0, 0.0)));
+        assert!(!validator.accept_control(&command(1.0, 0.0, f32::INFINITY, 0.0)));
+        assert!(!validator.accept_control(&command(1.0, 0.0, 0.0, f32::NEG_INFINITY)));
+        let mut cmd = command(1.0, 0.0, 0.0, 0.0);
+        cmd.longitudinal.jerk = f32::NAN;
+        assert!(!validator.accept_control(&cmd));
+        let mut cmd = command(1.0, 0.0, 0.0, 0.0);
+        cmd.lateral.steering_tire_rotation_rate = f32::INFINITY;
+        assert!(!validator.accept_control(&cmd));
+        assert!(!validator.accept_actuation(&ActuationCommand {
+            accel_cmd: f64::NAN,
+            brake_cmd: 0.0,
+            steer_cmd: 0.0,
+        }));
+        assert_eq!(stats.rejected_commands.load(Ordering::Relaxed), 6);
+    }
+
+    #[test]
+    fn reject_the_values_out_of_range() {
+        let limits = CommandLimits::default();
+        assert!(limits
+            .check_control(&command(1.0, limits.max_speed + 1.0, 0.0, 0.0), 1.0)
+            .is_err());
+        assert!(limits
+            .check_control(&command(1.0, -limits.max_speed - 1.0, 0.0, 0.0), 1.0)
+            .is_err());
+        assert!(limits
+            .check_control(&command(1.0, 0.0, -limits.max_acceleration - 1.0, 0.0), 1.0)
+            .is_err());
+        let beyond = limits.max_steering_tire_angle + 0.1;
+        assert!(limits
+            .check_control(&command(1.0, 0.0, 0.0, beyond), 1.0)
+            .is_err());
+        let steering = |steer_cmd| ActuationCommand {
+            accel_cmd: 0.0,
+            brake_cmd: 0.0,
+            steer_cmd,
+        };
+        assert!(limits
+            .check_actuation(&steering(-beyond as f64), 1.0)
+            .is_err());
+        // The pedals are clamped, not rejected
+        assert!(limits
+            .check_actuation(
+                &ActuationCommand {
+                    accel_cmd: 2.0,
+                    brake_cmd: -1.0,
+                    steer_cmd: 0.0,
+                },
+                1.0
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn the_steering_limit_applies_after_the_ratio() {
+        let limits = CommandLimits::default();
+        // A steering wheel angle, 16 times the tire angle
+        let wheel_angle = limits.max_steering_tire_angle * 8.0;
+        let cmd = command(1.0, 0.0, 0.0, wheel_angle);
+        assert!(limits.check_control(&cmd, 1.0).is_err());
+        assert!(limits.check_control(&cmd, 16.0).is_ok());
+    }
+
+    #[test]
+    fn the_convention_signs_a_left_turn() {
+        // CARLA steers right with a positive steer and a positive wheel angle
+        let left = 0.1;
+        assert!(carla_steer(left, SteeringConvention::LeftPositive, MAX_STEER) < 0.0);
+        assert!(carla_steer(left, SteeringConvention::RightPositive, MAX_STEER) > 0.0);
+        assert!(wheel_tire_angle([-10.0, -10.0], false, SteeringConvention::LeftPositive) > 0.0);
+        assert!(wheel_tire_angle([-10.0, -10.0], false, SteeringConvention::RightPositive) < 0.0);
+    }
+
+    #[test]
+    fn the_convention_signs_a_right_turn() {
+        let right = -0.1;
+        assert!(carla_steer(right, SteeringConvention::LeftPositive, MAX_STEER) > 0.0);
+        assert!(wheel_tire_angle([10.0, 10.0], false, SteeringConvention::LeftPositive) < 0.0);
+        assert!(wheel_tire_angle([10.0, 10.0], false, SteeringConvention::RightPositive) > 0.0);
+    }
+
+    #[test]
+    fn the_report_follows_the_command_in_both_conventions() {
+        for convention in [
+            SteeringConvention::LeftPositive,
+            SteeringConvention::RightPositive,
+        ] {
+            let steer = carla_steer(0.2, convention, MAX_STEER);
+            // The wheels turn by the steer of CARLA
+            let wheel_angle = steer * MAX_STEER;
+            let reported = wheel_tire_angle([wheel_angle, wheel_angle], false, convention);
+            assert!((reported - 0.2).abs() < 1e-6, "{convention:?}: {reported}");
+        }
+    }
+
+    #[test]
+    fn calibrate_the_offset_of_a_cruise() {
+        let mut calibration = SteeringCalibration::new(2.0, 10.0, 0.0);
+        for _ in 0..30 {
+            calibration.add(10.0, 0.0, 0.01, 0.1);
+        }
+        // The first step has no speed to compare with
+        assert!((calibration.cruise_sec() - 2.9).abs() < 1e-9);
+        assert!((calibration.result().unwrap() - 0.01).abs() < 1e-6);
+    }
+
+    #[test]
+    fn calibrate_only_while_cruising() {
+        let mut calibration = SteeringCalibration::new(1.0, 10.0, 0.0);
+        calibration.add(10.0, 0.0, 0.01, 0.1);
+        // Too slow
+        calibration.add(1.0, 0.0, 0.01, 0.1);
+        calibration.add(1.0, 0.0, 0.01, 0.1);
+        // Turning
+        calibration.add(1.0, 0.5, 0.01, 0.1);
+        // Accelerating at 10 m/s^2
+        calibration.add(5.0, 0.0, 0.01, 0.1);
+        calibration.add(6.0, 0.0, 0.01, 0.1);
+        // An invalid period
+        calibration.add(6.0, 0.0, 0.01, 0.0);
+        assert_eq!(calibration.cruise_sec(), 0.0);
+        assert_eq!(calibration.result(), None);
+    }
+
+    #[test]
+    fn a_calibration_times_out() {
+        let calibration = SteeringCalibration::new(5.0, 60.0, 10.0);
+        assert!(!calibration.timed_out(70.0));
+        assert!(calibration.timed_out(70.1));
+    }
+
+    #[test]
+    fn shift_the_gears_with_hysteresis() {
+        let mut selector = GearSelector::new(vec![4.0, 8.0], 1.0);
+        assert_eq!(selector.select(0.0), 1);
+        assert_eq!(selector.select(4.5), 2);
+        // Around the shift point, the gear holds
+        assert_eq!(selector.select(3.5), 2);
+        assert_eq!(selector.select(4.2), 2);
+        assert_eq!(selector.select(2.9), 1);
+        assert_eq!(selector.select(3.5), 1);
+        // A jump shifts over several gears, the reverse speeds count as their magnitude
+        assert_eq!(selector.select(-9.0), 3);
+        assert_eq!(selector.select(7.5), 3);
+        assert_eq!(selector.select(1.0), 1);
+        selector.select(9.0);
+        selector.reset();
+        assert_eq!(selector.select(3.5), 1);
+    }
+
+    /// The output of a unit step after `duration` at the tick period
+    fn low_pass_step(time_constant: f32, period: f64, duration: f64) -> f32 {
+        let mut filter = LowPass::new(time_constant);
+        filter.filter(0.0, period);
+        let mut output = 0.0;
+        for _ in 0..(duration / period).round() as usize {
+            output = filter.filter(1.0, period);
+        }
+        output
+    }
+
+    #[test]
+    fn the_low_pass_holds_its_time_constant() {
+        let at_time_constant = 1.0 - (-1f32).exp();
+        for period in [0.01, 0.05, 0.1] {
+            let output = low_pass_step(0.5, period, 0.5);
+            assert!(
+                (output - at_time_constant).abs() < 1e-4,
+                "{period}: {output}"
+            );
+        }
+        // Three time constants reach 95%
+        assert!((low_pass_step(0.5, 0.05, 1.5) - 0.950).abs() < 1e-3);
+    }
+
+    #[test]
+    fn the_low_pass_starts_over_after_a_reset() {
+        let mut filter = LowPass::new(0.5);
+        // The first input passes through
+        assert_eq!(filter.filter(0.3, 0.05), 0.3);
+        assert!(filter.filter(1.0, 0.05) < 1.0);
+        // An invalid period passes the input through
+        assert_eq!(filter.filter(0.7, f64::NAN), 0.7);
+        filter.reset();
+        assert_eq!(filter.filter(-1.0, 0.05), -1.0);
+    }
+
+    #[test]
+    fn the_carla_steer_of_a_tire_angle() {
+        use SteeringConvention::{LeftPositive, RightPositive};
+        let half = 35f32.to_radians();
+        for (angle, convention, max_steer_angle, expected) in [
+            (0.0, LeftPositive, 70.0, 0.0),
+            (half, LeftPositive, 70.0, -0.5),
+            (half, RightPositive, 70.0, 0.5),
+            (-half, LeftPositive, 70.0, 0.5),
+            (-half, RightPositive, 70.0, -0.5),
+            (half, LeftPositive, 35.0, -1.0),
+            // Beyond full steer
+            (90f32.to_radians(), LeftPositive, 70.0, -1.0),
+            (-90f32.to_radians(), RightPositive, 70.0, -1.0),
+        ] {
+            let steer = carla_steer(angle, convention, max_steer_angle);
+            assert!(
+                (steer - expected).abs() < 1e-6,
+                "{angle} {convention:?} {max_steer_angle}: {steer}"
+            );
+        }
+    }
+
+    #[test]
+    fn the_tire_angle_of_the_front_wheels() {
+        use SteeringConvention::{LeftPositive, RightPositive};
+        for (wheels, two_wheeled, convention, expected_deg) in [
+            ([0.0, 0.0], false, LeftPositive, 0.0),
+            ([10.0, 20.0], false, LeftPositive, -15.0),
+            ([10.0, 20.0], false, RightPositive, 15.0),
+            ([-20.0, -10.0], false, LeftPositive, 15.0),
+            // A two-wheeler only has the front left wheel
+            ([10.0, 0.0], true, LeftPositive, -10.0),
+            ([-10.0, 0.0], true, RightPositive, -10.0),
+        ] {
+            let angle = wheel_tire_angle(wheels, two_wheeled, convention);
+            assert!(
+                (angle - f32::to_radians(expected_deg)).abs() < 1e-6,
+                "{wheels:?} {two_wheeled} {convention:?}: {angle}"
+            );
+        }
+    }
+
+    #[test]
+    fn the_body_twist_in_ros_axes() {
+        let quarter_turn = Isometry3::rotation(Vector3::z() * std::f32::consts::FRAC_PI_2);
+        for (transform, velocity, angular_velocity, expected) in [
+            (
+                Isometry3::identity(),
+                [1.0, 0.0, 0.0],
+                [0.0; 3],
+                (1.0, 0.0, 0.0),
+            ),
+            // CARLA's y is to the right, ROS' to the left
+            (
+                Isometry3::identity(),
+                [0.0, 1.0, 0.0],
+                [0.0; 3],
+                (0.0, -1.0, 0.0),
+            ),
+            // Heading along CARLA's y
+            (quarter_turn, [0.0, 2.0, 0.0], [0.0; 3], (2.0, 0.0, 0.0)),
+            (quarter_turn, [-1.0, 0.0, 0.0], [0.0; 3], (0.0, -1.0, 0.0)),
+            // A right turn in CARLA (degrees/s) is clockwise in ROS (rad/s)
+            (
+                Isometry3::identity(),
+                [0.0; 3],
+                [0.0, 0.0, 90.0],
+                (0.0, 0.0, -std::f32::consts::FRAC_PI_2),
+            ),
+        ] {
+            let (longitudinal, lateral, yaw_rate) = body_twist(
+                &transform,
+                &Vector3::from(velocity),
+                &Vector3::from(angular_velocity),
+            );
+            let (expected_longitudinal, expected_lateral, expected_yaw_rate) = expected;
+            assert!(
+                (longitudinal - expected_longitudinal).abs() < 1e-5,
+                "{velocity:?}"
+            );
+            assert!((lateral - expected_lateral).abs() < 1e-5, "{velocity:?}");
+            assert!(
+                (yaw_rate - expected_yaw_rate).abs() < 1e-5,
+                "{angular_velocity:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn the_direction_of_a_target_speed() {
+        for (speed, expected) in [
+            (5.0, (false, 1)),
+            (0.0, (false, 1)),
+            (-0.0, (false, 1)),
+            (-0.5, (true, -1)),
+            (f32::NEG_INFINITY, (true, -1)),
+        ] {
+            assert_eq!(direction_gear(speed), expected, "{speed}");
+        }
+    }
+
+    #[test]
+    fn any_tire_angle_round_trips_through_carla() {
+        for convention in [
+            SteeringConvention::LeftPositive,
+            SteeringConvention::RightPositive,
+        ] {
+            for step in -100..=100 {
+                let angle = MAX_STEER.to_radians() * step as f32 / 100.0;
+                let steer = carla_steer(angle, convention, MAX_STEER);
+                let wheel_angle = steer * MAX_STEER;
+                let reported = wheel_tire_angle([wheel_angle, wheel_angle], false, convention);
+                assert!(
+                    (reported - angle).abs() < 1e-5,
+                    "{convention:?} {angle}: {reported}"
+                );
+                if step != 0 {
+                    assert_eq!(reported.signum(), angle.signum(), "{convention:?} {angle}");
+                }
+            }
+        }
+    }
+
+    /// The filtered speeds of samples every `DT` seconds
+    fn filter_speeds(filter: &mut SpeedFilter, speeds: &[f32]) -> Vec<f32> {
+        const DT: f64 = 0.05;
+        speeds
+            .iter()
+            .enumerate()
+            .map(|(step, speed)| filter.filter(*speed, step as f64 * DT))
+            .collect()
+    }
+
+    fn default_speed_filter() -> SpeedFilter {
+        let config = VehicleConfig::default();
+        SpeedFilter::new(
+            config.max_measured_acceleration,
+            config.speed_median_samples,
+        )
+    }
+
+    #[test]
+    fn a_single_frame_spike_is_replaced() {
+        let mut filter = default_speed_filter();
+        assert_eq!(
+            filter_speeds(&mut filter, &[10.0, 10.1, 25.0, 10.2, -3.0, 10.3]),
+            [10.0, 10.1, 10.1, 10.2, 10.2, 10.3]
+        );
+    }
+
+    #[test]
+    fn hard_braking_is_not_masked() {
+        // An emergency stop at 1.2 g, beyond what a tire gives on a dry road
+        let braking: Vec<f32> = (0..40)
+            .map(|step| (20.0 - 12.0 * 0.05 * step as f32).max(0.0))
+            .collect();
+        let mut filter = default_speed_filter();
+        assert_eq!(filter_speeds(&mut filter, &braking), braking);
+        // And a car stopped dead by a wall from 5 m/s in a frame is a spike
+        let mut filter = default_speed_filter();
+        assert_eq!(filter_speeds(&mut filter, &[5.0, 0.0])[1], 5.0);
+    }
+
+    #[test]
+    fn a_lasting_jump_is_followed() {
+        let mut filter = default_speed_filter();
+        // A teleport, the speed stays at the new value past MAX_REJECTED_SPEEDS samples
+        let filtered = filter_speeds(&mut filter, &[12.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+        assert_eq!(filtered, [12.0, 12.0, 12.0, 12.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn a_reset_accepts_the_next_speed() {
+        let mut filter = default_speed_filter();
+        assert_eq!(filter_speeds(&mut filter, &[12.0, 0.0]), [12.0, 12.0]);
+        filter.reset();
+        assert_eq!(filter.filter(0.0, 0.1), 0.0);
+    }
+
+    #[test]
+    fn a_non_finite_speed_keeps_the_last_one() {
+        let mut filter = default_speed_filter();
+        assert_eq!(
+            filter_speeds(&mut filter, &[3.0, f32::NAN, f32::INFINITY, 3.1]),
+            [3.0, 3.0, 3.0, 3.1]
+        );
+    }
+
+    #[test]
+    fn the_median_of_the_accepted_speeds() {
+        let mut filter = SpeedFilter::new(1000.0, 3);
+        assert_eq!(
+            filter_speeds(&mut filter, &[1.0, 3.0, 20.0, 2.0, 4.0]),
+            [1.0, 2.0, 3.0, 3.0, 4.0]
+        );
+        let mut even = SpeedFilter::new(1000.0, 2);
+        assert_eq!(filter_speeds(&mut even, &[1.0, 3.0, 4.0]), [1.0, 2.0, 3.5]);
+        // No median below one sample
+        let mut none = SpeedFilter::new(1000.0, 0);
+        assert_eq!(filter_speeds(&mut none, &[1.0, 3.0]), [1.0, 3.0]);
+    }
+
+    const BRAKE: VehicleControl = VehicleControl {
+        throttle: 0.0,
+        steer: 0.0,
+        brake: 1.0,
+        hand_brake: true,
+        reverse: false,
+        manual_gear_shift: false,
+        gear: 0,
+    };
+
+    /// Whether the deduplicator applies each of the controls, in turn
+    fn applied(deduplicator: &mut ControlDeduplicator, controls: &[VehicleControl]) -> Vec<bool> {
+        controls
+            .iter()
+            .map(|control| deduplicator.should_apply(control))
+            .collect()
+    }
+
+    #[test]
+    fn a_repeated_control_is_applied_every_refresh() {
+        let mut deduplicator = ControlDeduplicator::new(3);
+        assert_eq!(
+            applied(&mut deduplicator, &[BRAKE; 7]),
+            [true, false, false, true, false, false, true]
+        );
+    }
+
+    #[test]
+    fn a_changed_control_is_applied_at_once() {
+        let mut deduplicator = ControlDeduplicator::new(10);
+        let steered = VehicleControl {
+            steer: 0.1,
+            ..BRAKE
+        };
+        let released = VehicleControl {
+            hand_brake: false,
+            ..steered
+        };
+        assert_eq!(
+            applied(
+                &mut deduplicator,
+                &[BRAKE, BRAKE, steered, released, released]
+            ),
+            [true, false, true, true, false]
+        );
+    }
+
+    #[test]
+    fn a_difference_below_the_epsilon_is_a_repeat() {
+        let mut deduplicator = ControlDeduplicator::new(10);
+        let noisy = VehicleControl {
+            brake: 1.0 - CONTROL_EPSILON / 2.0,
+            steer: CONTROL_EPSILON / 2.0,
+            ..BRAKE
+        };
+        let changed = VehicleControl {
+            brake: 1.0 - CONTROL_EPSILON * 2.0,
+            ..BRAKE
+        };
+        assert_eq!(
+            applied(&mut deduplicator, &[BRAKE, noisy, changed]),
+            [true, false, true]
+        );
+    }
+
+    #[test]
+    fn an_invalidated_control_is_applied_again() {
+        let mut deduplicator = ControlDeduplicator::new(10);
+        assert!(deduplicator.should_apply(&BRAKE));
+        deduplicator.invalidate();
+        assert!(deduplicator.should_apply(&BRAKE));
+        assert!(!deduplicator.should_apply(&BRAKE));
+    }
+
+    #[test]
+    fn a_refresh_of_one_applies_every_control() {
+        let mut deduplicator = ControlDeduplicator::new(1);
+        assert_eq!(applied(&mut deduplicator, &[BRAKE; 3]), [true; 3]);
+    }
+}
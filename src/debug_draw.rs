@@ -0,0 +1,391 @@
+use crate::{
+    autoware_type::{autoware_state, AutowareState, Engage, Pose, PoseStamped, Trajectory},
+    cdr_utils,
+    error::Result,
+    topics::{self, TopicMap},
+    utils::{self, LogThrottle},
+};
+use carla::{client::World, geom::Location, sensor::data::Color};
+use log::warn;
+use nalgebra::{Quaternion, UnitQuaternion};
+use serde::de::DeserializeOwned;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use zenoh::{prelude::sync::*, subscriber::Subscriber};
+
+/// Lift the drawings above the road, so they aren't hidden in the asphalt (m)
+const DRAW_HEIGHT: f32 = 0.5;
+const TRAJECTORY_THICKNESS: f32 = 0.1;
+const TRAJECTORY_COLOR: Color = Color {
+    r: 0,
+    g: 255,
+    b: 128,
+    a: 255,
+};
+/// CARLA can't erase a drawing, so the goal is drawn again with a short lifetime
+/// and the previous goal fades out on its own (simulation seconds)
+const GOAL_REDRAW_INTERVAL_SEC: f64 = 1.0;
+const GOAL_LIFETIME_SEC: f32 = 1.1;
+/// Length of the arrow along the goal heading (m)
+const GOAL_ARROW_LENGTH: f64 = 3.0;
+const GOAL_THICKNESS: f32 = 0.2;
+const GOAL_ARROW_SIZE: f32 = 0.5;
+/// Height of the label above the arrow (m)
+const GOAL_TEXT_HEIGHT: f32 = 2.0;
+const GOAL_COLOR: Color = Color {
+    r: 255,
+    g: 0,
+    b: 255,
+    a: 255,
+};
+/// The HUD follows the vehicle by being drawn again every few ticks, each text fading out
+/// right after the next one appears (simulation seconds)
+const HUD_REDRAW_INTERVAL_SEC: f64 = 0.2;
+const HUD_LIFETIME_SEC: f32 = 0.25;
+/// Height of the HUD above the origin of the vehicle (m)
+const HUD_HEIGHT: f32 = 3.0;
+const HUD_COLOR: Color = Color {
+    r: 255,
+    g: 255,
+    b: 0,
+    a: 255,
+};
+
+/// Settings of the drawings in the CARLA world
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct DebugDrawConfig {
+    /// Master switch, off on the headless servers where nobody looks at the drawings.
+    pub enabled: bool,
+    /// Draw the planned trajectories of the vehicles.
+    pub trajectory: bool,
+    /// How long a drawn trajectory stays (s).
+    pub trajectory_lifetime: f32,
+    /// Long trajectories are decimated to this many points.
+    pub trajectory_max_points: usize,
+    /// Draw the goal poses of the vehicles.
+    pub goal: bool,
+    /// Write the Autoware state, the speed and the engage flag above the vehicles.
+    pub hud: bool,
+}
+
+impl Default for DebugDrawConfig {
+    fn default() -> Self {
+        DebugDrawConfig {
+            enabled: true,
+            trajectory: false,
+            trajectory_lifetime: 1.0,
+            trajectory_max_points: 100,
+            goal: false,
+            hud: false,
+        }
+    }
+}
+
+/// Draw what Autoware plans into the CARLA window.
+///
+/// The subscribers only keep the latest messages, it's drawn from the main loop
+/// since the CARLA client isn't used from the zenoh threads.
+pub struct DebugDraw<'a> {
+    config: DebugDrawConfig,
+    /// The latest trajectory not drawn yet, by key expression
+    pending_trajectories: Arc<Mutex<HashMap<String, Trajectory>>>,
+    /// The goals received since the last update, by namespace
+    pending_goals: Arc<Mutex<HashMap<String, Pose>>>,
+    /// The current goal of each namespace
+    goals: HashMap<String, Pose>,
+    last_goal_draw: Option<f64>,
+    /// What Autoware reported last, by namespace
+    hud_states: Arc<Mutex<HashMap<String, HudState>>>,
+    last_hud_draw: Option<f64>,
+    _subscriber_trajectory: Option<Subscriber<'a, ()>>,
+    _subscriber_goal: Option<Subscriber<'a, ()>>,
+    _subscriber_autoware_state: Option<Subscriber<'a, ()>>,
+    _subscriber_engage: Option<Subscriber<'a, ()>>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct HudState {
+    state: Option<u8>,
+    engage: Option<bool>,
+}
+
+/// A bridged vehicle the HUD is drawn above
+pub struct HudVehicle {
+    pub namespace: String,
+    pub location: Location,
+    /// m/s
+    pub speed: f32,
+}
+
+impl<'a> DebugDraw<'a> {
+    pub fn new(
+        z_session: Arc<Session>,
+        config: DebugDrawConfig,
+        topic_map: &TopicMap,
+    ) -> Result<DebugDraw<'a>> {
+        let pending_trajectories = Arc::new(Mutex::new(HashMap::new()));
+        let cloned_trajectories = pending_trajectories.clone();
+        let subscriber_trajectory = (config.enabled && config.trajectory)
+            .then(|| {
+                subscribe_latest(
+                    &z_session,
+                    topic_map.key("**", topics::TRAJECTORY),
+                    move |key_expr, trajectory: Trajectory| {
+                        cloned_trajectories
+                            .lock()
+                            .unwrap()
+                            .insert(key_expr.to_string(), trajectory);
+                    },
+                )
+            })
+            .transpose()?;
+
+        let pending_goals = Arc::new(Mutex::new(HashMap::new()));
+        let cloned_goals = pending_goals.clone();
+        let goal_suffix = format!("/{}", topic_map.resolve(topics::GOAL));
+        let subscriber_goal = (config.enabled && config.goal)
+            .then(|| {
+                subscribe_latest(
+                    &z_session,
+                    topic_map.key("**", topics::GOAL),
+                    move |key_expr, goal: PoseStamped| {
+                        let namespace = key_expr.strip_suffix(&goal_suffix).unwrap_or(key_expr);
+                        cloned_goals
+                            .lock()
+                            .unwrap()
+                            .insert(namespace.to_string(), goal.pose);
+                    },
+                )
+            })
+            .transpose()?;
+
+        let hud_states = Arc::new(Mutex::new(HashMap::<String, HudState>::new()));
+        let cloned_states = hud_states.clone();
+        let state_suffix = format!("/{}", topic_map.resolve(topics::AUTOWARE_STATE));
+        let subscriber_autoware_state = (config.enabled && config.hud)
+            .then(|| {
+                subscribe_latest(
+                    &z_session,
+                    topic_map.key("**", topics::AUTOWARE_STATE),
+                    move |key_expr, state: AutowareState| {
+                        let namespace = key_expr.strip_suffix(&state_suffix).unwrap_or(key_expr);
+                        cloned_states
+                            .lock()
+                            .unwrap()
+                            .entry(namespace.to_string())
+                            .or_default()
+                            .state = Some(state.state);
+                    },
+                )
+            })
+            .transpose()?;
+        let cloned_states = hud_states.clone();
+        let engage_suffix = format!("/{}", topic_map.resolve(topics::ENGAGE));
+        let subscriber_engage = (config.enabled && config.hud)
+            .then(|| {
+                subscribe_latest(
+                    &z_session,
+                    topic_map.key("**", topics::ENGAGE),
+                    move |key_expr, engage: Engage| {
+                        let namespace = key_expr.strip_suffix(&engage_suffix).unwrap_or(key_expr);
+                        cloned_states
+                            .lock()
+                            .unwrap()
+                            .entry(namespace.to_string())
+                            .or_default()
+                            .engage = Some(engage.engage);
+                    },
+                )
+            })
+            .transpose()?;
+
+        Ok(DebugDraw {
+            config,
+            pending_trajectories,
+            pending_goals,
+            goals: HashMap::new(),
+            last_goal_draw: None,
+            hud_states,
+            last_hud_draw: None,
+            _subscriber_trajectory: subscriber_trajectory,
+            _subscriber_goal: subscriber_goal,
+            _subscriber_autoware_state: subscriber_autoware_state,
+            _subscriber_engage: subscriber_engage,
+        })
+    }
+
+    /// Draw the messages received since the last call, at the simulation time `sec`.
+    /// `vehicles` is only called when the HUD is drawn.
+    pub fn update<F>(&mut self, world: &mut World, sec: f64, vehicles: F)
+    where
+        F: FnOnce() -> Vec<HudVehicle>,
+    {
+        let trajectories: Vec<_> = self.pending_trajectories.lock().unwrap().drain().collect();
+        for (_, trajectory) in trajectories {
+            self.draw_trajectory(world, &trajectory);
+        }
+
+        let new_goals: Vec<_> = self.pending_goals.lock().unwrap().drain().collect();
+        let redraw = !new_goals.is_empty()
+            || matches!(self.last_goal_draw, Some(last) if sec - last >= GOAL_REDRAW_INTERVAL_SEC);
+        self.goals.extend(new_goals);
+        if redraw && !self.goals.is_empty() {
+            self.last_goal_draw = Some(sec);
+            for (namespace, goal) in &self.goals {
+                draw_goal(world, namespace, goal);
+            }
+        }
+
+        let hud_due =
+            !matches!(self.last_hud_draw, Some(last) if sec - last < HUD_REDRAW_INTERVAL_SEC);
+        if self.config.enabled && self.config.hud && hud_due {
+            self.last_hud_draw = Some(sec);
+            let states = self.hud_states.lock().unwrap().clone();
+            for vehicle in vehicles() {
+                let state = states.get(&vehicle.namespace).copied().unwrap_or_default();
+                draw_hud(world, &vehicle, state);
+            }
+        }
+    }
+
+    /// Forget the goals and trajectories, which belong to the previous world
+    pub fn reset(&mut self) {
+        self.pending_trajectories.lock().unwrap().clear();
+        self.pending_goals.lock().unwrap().clear();
+        self.goals.clear();
+        self.last_goal_draw = None;
+        self.hud_states.lock().unwrap().clear();
+        self.last_hud_draw = None;
+    }
+
+    fn draw_trajectory(&self, world: &mut World, trajectory: &Trajectory) {
+        let points = &trajectory.points;
+        let step = points
+            .len()
+            .div_ceil(self.config.trajectory_max_points - 1)
+            .max(1);
+        // Every step-th point, and always the end
+        let mut locations: Vec<_> = points
+            .iter()
+            .step_by(step)
+            .chain(points.last())
+            .map(|point| {
+                let [x, y, z] = point.pose.position;
+                ros_to_carla_location(x, y, z)
+            })
+            .collect();
+        locations.dedup();
+        let mut debug = world.debug();
+        for segment in locations.windows(2) {
+            debug.draw_line(
+                &segment[0],
+                &segment[1],
+                TRAJECTORY_THICKNESS,
+                TRAJECTORY_COLOR,
+                self.config.trajectory_lifetime,
+                false,
+            );
+        }
+    }
+}
+
+/// An arrow along the heading of the goal, with the namespace above it
+fn draw_goal(world: &mut World, namespace: &str, goal: &Pose) {
+    let [x, y, z] = goal.position;
+    let [qx, qy, qz, qw] = goal.orientation;
+    let (_, _, yaw) =
+        UnitQuaternion::from_quaternion(Quaternion::new(qw, qx, qy, qz)).euler_angles();
+    // The heading is applied in the map frame, so both ends go through the same conversion
+    let begin = ros_to_carla_location(x, y, z);
+    let end = ros_to_carla_location(
+        x + GOAL_ARROW_LENGTH * yaw.cos(),
+        y + GOAL_ARROW_LENGTH * yaw.sin(),
+        z,
+    );
+    let mut label = begin;
+    label.z += GOAL_TEXT_HEIGHT;
+    let mut debug = world.debug();
+    debug.draw_arrow(
+        &begin,
+        &end,
+        GOAL_THICKNESS,
+        GOAL_ARROW_SIZE,
+        GOAL_COLOR,
+        GOAL_LIFETIME_SEC,
+        false,
+    );
+    debug.draw_string(
+        &label,
+        &format!("goal {namespace}"),
+        false,
+        GOAL_COLOR,
+        GOAL_LIFETIME_SEC,
+        false,
+    );
+}
+
+/// `ego: DRIVING | 32.4 km/h | engaged` above the vehicle
+fn draw_hud(world: &mut World, vehicle: &HudVehicle, state: HudState) {
+    let state_name = match state.state {
+        Some(autoware_state::INITIALIZING) => "INITIALIZING",
+        Some(autoware_state::WAITING_FOR_ROUTE) => "WAITING_FOR_ROUTE",
+        Some(autoware_state::PLANNING) => "PLANNING",
+        Some(autoware_state::WAITING_FOR_ENGAGE) => "WAITING_FOR_ENGAGE",
+        Some(autoware_state::DRIVING) => "DRIVING",
+        Some(autoware_state::ARRIVED_GOAL) => "ARRIVED_GOAL",
+        Some(autoware_state::FINALIZING) => "FINALIZING",
+        Some(_) => "UNKNOWN",
+        None => "no state",
+    };
+    let engage = match state.engage {
+        Some(true) => "engaged",
+        Some(false) => "disengaged",
+        None => "engage unknown",
+    };
+    let text = format!(
+        "{}: {state_name} | {:.1} km/h | {engage}",
+        vehicle.namespace,
+        vehicle.speed * 3.6
+    );
+    let mut location = vehicle.location;
+    location.z += HUD_HEIGHT;
+    world
+        .debug()
+        .draw_string(&location, &text, false, HUD_COLOR, HUD_LIFETIME_SEC, false);
+}
+
+/// A position of the map frame as a drawing location, lifted above the road
+fn ros_to_carla_location(x: f64, y: f64, z: f64) -> Location {
+    let [x, y, z] = utils::ros_to_carla_position(x, y, z);
+    Location::new(x, y, z + DRAW_HEIGHT)
+}
+
+/// Subscribe to a CDR topic, handing the key expression and the message to `handler`
+fn subscribe_latest<'a, T, F>(
+    z_session: &Arc<Session>,
+    key: String,
+    mut handler: F,
+) -> Result<Subscriber<'a, ()>>
+where
+    T: DeserializeOwned + 'static,
+    F: FnMut(&str, T) + Send + Sync + 'static,
+{
+    let mut log = LogThrottle::new(Duration::from_secs(5));
+    let subscriber = z_session
+        .declare_subscriber(key)
+        .callback_mut(move |sample| {
+            match cdr_utils::deserialize::<T>(&sample.payload.contiguous()) {
+                Ok(message) => handler(sample.key_expr.as_str(), message),
+                Err(e) if log.ready() => {
+                    warn!("Failed to deserialize {}: {e}", sample.key_expr)
+                }
+                Err(_) => {}
+            }
+        })
+        .res()?;
+    Ok(subscriber)
+}
@@ -0,0 +1,83 @@
+//! `--debug-json`: a JSON copy of the outgoing messages on `<key>/json`, for `z_sub` and the
+//! other zenoh tools which can't read CDR.
+//!
+//! A debug feature, off by default: every mirrored message is serialized twice.
+use crate::error::Result;
+use log::{debug, log_enabled, trace, warn, Level};
+use serde::Serialize;
+use std::sync::{Arc, OnceLock};
+use zenoh::prelude::sync::*;
+
+static MIRROR: OnceLock<JsonMirror> = OnceLock::new();
+
+struct JsonMirror {
+    z_session: Arc<Session>,
+    patterns: Vec<OwnedKeyExpr>,
+}
+
+/// Mirror the keys matching any of the key expressions, e.g. `**` for every key
+pub fn enable(z_session: Arc<Session>, patterns: &[String]) -> Result<()> {
+    let patterns = patterns
+        .iter()
+        .map(|pattern| OwnedKeyExpr::autocanonize(pattern.clone()))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    warn!(
+        "Debug: the messages of {} are also published as JSON on <key>/json, which doubles their serialization",
+        patterns
+            .iter()
+            .map(|pattern| pattern.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    if MIRROR
+        .set(JsonMirror {
+            z_session,
+            patterns,
+        })
+        .is_err()
+    {
+        warn!("The JSON mirror was already enabled, keep the first patterns");
+    }
+    Ok(())
+}
+
+/// Publish the message as JSON on `<key>/json`, if `--debug-json` covers the key
+pub fn mirror<T: Serialize>(key: &str, value: &T) {
+    let Some(mirror) = MIRROR.get() else {
+        return;
+    };
+    let Ok(key_expr) = keyexpr::new(key) else {
+        return;
+    };
+    if !mirror
+        .patterns
+        .iter()
+        .any(|pattern| pattern.intersects(key_expr))
+    {
+        return;
+    }
+    let json_key = format!("{key}/json");
+    let result = serde_json::to_vec(value).map(|json| {
+        mirror
+            .z_session
+            .put(&json_key, json)
+            .encoding(KnownEncoding::AppJson)
+            .res()
+    });
+    match result {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => debug!("Failed to publish the JSON copy on {json_key}: {e}"),
+        Err(e) => debug!("Failed to encode the JSON copy of {key}: {e}"),
+    }
+}
+
+/// Log a received command as JSON, with `RUST_LOG=zenoh_carla_bridge::debug_json=trace`
+pub fn trace_command<T: Serialize>(key: &str, value: &T) {
+    if !log_enabled!(Level::Trace) {
+        return;
+    }
+    match serde_json::to_string(value) {
+        Ok(json) => trace!("{key}: {json}"),
+        Err(e) => trace!("{key}: not representable as JSON, {e}"),
+    }
+}
@@ -0,0 +1,187 @@
+//! The depth camera images, `depth_format` in `[sensors]`
+use crate::error::Result;
+use serde_derive::{Deserialize, Serialize};
+use std::str::FromStr;
+use zenoh_ros_type::std_msgs::Header;
+
+/// `format` of the image_transport plugin for a 16-bit depth image
+const COMPRESSED_DEPTH_FORMAT: &str = "16UC1; compressedDepth png";
+/// `compressionFormat::PNG` in the `ConfigHeader` of compressed_depth_image_transport
+const CONFIG_FORMAT_PNG: i32 = 1;
+
+/// Which depth images a depth camera publishes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(try_from = "String")]
+#[non_exhaustive]
+pub enum DepthFormat {
+    /// `image_raw`, 32FC1 in meters.
+    #[default]
+    Raw,
+    /// `image_raw/compressedDepth`, a 16-bit PNG in millimeters.
+    CompressedDepth,
+    Both,
+}
+
+impl DepthFormat {
+    pub fn raw(&self) -> bool {
+        matches!(self, DepthFormat::Raw | DepthFormat::Both)
+    }
+
+    pub fn compressed(&self) -> bool {
+        matches!(self, DepthFormat::CompressedDepth | DepthFormat::Both)
+    }
+}
+
+impl FromStr for DepthFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.trim() {
+            "raw" => Ok(DepthFormat::Raw),
+            "compressed_depth" => Ok(DepthFormat::CompressedDepth),
+            "both" => Ok(DepthFormat::Both),
+            format => Err(format!(
+                "Unknown depth format '{format}', expect raw, compressed_depth or both"
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for DepthFormat {
+    type Error = String;
+
+    fn try_from(format: String) -> Result<Self, Self::Error> {
+        format.parse()
+    }
+}
+
+/// sensor_msgs/CompressedImage
+#[derive(Serialize, Deserialize)]
+pub struct CompressedImage {
+    pub header: Header,
+    pub format: String,
+    pub data: Vec<u8>,
+}
+
+/// The depth of 16UC1, 0 for no valid reading like the ROS depth drivers.
+///
+/// Beyond 65.535 m doesn't fit, so it is as invalid as NaN or a non-positive depth,
+/// rather than clamped to a wall at the maximum range.
+pub fn to_millimeters(meters: f32) -> u16 {
+    let millimeters = (meters * 1000.0).round();
+    if millimeters.is_finite() && (1.0..=u16::MAX as f32).contains(&millimeters) {
+        millimeters as u16
+    } else {
+        0
+    }
+}
+
+/// Encode the depth (m) like `compressed_depth_image_transport` does a 16UC1 image: the
+/// 12 bytes of its `ConfigHeader`, then a 16-bit grayscale PNG of the millimeters
+pub fn compressed_depth(
+    header: Header,
+    width: u32,
+    height: u32,
+    depth: &[f32],
+) -> Result<CompressedImage> {
+    let mut data = Vec::with_capacity(12 + depth.len());
+    data.extend_from_slice(&CONFIG_FORMAT_PNG.to_le_bytes());
+    // The depth quantization parameters only apply to the inverse depth of 32FC1
+    data.extend_from_slice(&0f32.to_le_bytes());
+    data.extend_from_slice(&0f32.to_le_bytes());
+    {
+        let mut encoder = png::Encoder::new(&mut data, width, height);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Sixteen);
+        encoder.set_compression(png::Compression::Fast);
+        let mut writer = encoder.write_header()?;
+        // The samples of a 16-bit PNG are big-endian
+        let samples: Vec<u8> = depth
+            .iter()
+            .flat_map(|meters| to_millimeters(*meters).to_be_bytes())
+            .collect();
+        writer.write_image_data(&samples)?;
+        writer.finish()?;
+    }
+    Ok(CompressedImage {
+        header,
+        format: COMPRESSED_DEPTH_FORMAT.to_string(),
+        data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{time::TimeStamp, utils};
+
+    #[test]
+    fn convert_to_millimeters() {
+        assert_eq!(to_millimeters(1.0), 1000);
+        assert_eq!(to_millimeters(0.0012), 1);
+        // Rounded to the nearest millimeter
+        assert_eq!(to_millimeters(2.0004), 2000);
+        assert_eq!(to_millimeters(2.0006), 2001);
+        assert_eq!(to_millimeters(65.535), u16::MAX);
+    }
+
+    #[test]
+    fn the_invalid_depths_are_zero() {
+        for meters in [
+            0.0,
+            0.0004,
+            -1.0,
+            65.536,
+            1000.0,
+            f32::NAN,
+            f32::INFINITY,
+            f32::NEG_INFINITY,
+        ] {
+            assert_eq!(to_millimeters(meters), 0, "{meters}");
+        }
+    }
+
+    #[test]
+    fn decode_a_compressed_depth_image() {
+        let depth = [0.5, 1.25, f32::NAN, 70.0, 65.535, 0.001];
+        let header = utils::ros_header(TimeStamp::from_secs_f64(1.5), "depth_camera");
+        let image = compressed_depth(header, 3, 2, &depth).unwrap();
+        assert_eq!(image.format, "16UC1; compressedDepth png");
+        assert_eq!(image.header.frame_id, "depth_camera");
+
+        // The ConfigHeader of compressed_depth_image_transport, then the PNG
+        let (config, png_data) = image.data.split_at(12);
+        assert_eq!(config[0..4], CONFIG_FORMAT_PNG.to_le_bytes());
+        assert_eq!(config[4..12], [0; 8]);
+        assert_eq!(
+            png_data[..8],
+            [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n']
+        );
+
+        let mut decoder = png::Decoder::new(png_data);
+        decoder.set_transformations(png::Transformations::IDENTITY);
+        let mut reader = decoder.read_info().unwrap();
+        let mut samples = vec![0; reader.output_buffer_size()];
+        let frame = reader.next_frame(&mut samples).unwrap();
+        assert_eq!((frame.width, frame.height), (3, 2));
+        assert_eq!(frame.color_type, png::ColorType::Grayscale);
+        assert_eq!(frame.bit_depth, png::BitDepth::Sixteen);
+        let millimeters: Vec<u16> = samples[..frame.buffer_size()]
+            .chunks_exact(2)
+            .map(|sample| u16::from_be_bytes([sample[0], sample[1]]))
+            .collect();
+        assert_eq!(millimeters, [500, 1250, 0, 0, u16::MAX, 1]);
+    }
+
+    #[test]
+    fn parse_the_format() {
+        assert_eq!("raw".parse::<DepthFormat>(), Ok(DepthFormat::Raw));
+        assert_eq!(" both ".parse::<DepthFormat>(), Ok(DepthFormat::Both));
+        assert!("compressed_depth"
+            .parse::<DepthFormat>()
+            .unwrap()
+            .compressed());
+        assert!(!DepthFormat::CompressedDepth.raw());
+        assert!("png".parse::<DepthFormat>().is_err());
+    }
+}
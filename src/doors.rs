@@ -0,0 +1,103 @@
+use crate::autoware_type::{
+    adapi_door_command, adapi_door_status, door_command, door_status, AdapiDoorCommand,
+    AdapiDoorStatus, DoorCommand,
+};
+use carla::rpc::VehicleDoor;
+use zenoh_ros_type::builtin_interfaces::Time;
+
+/// Blueprints with articulated doors, the others ignore the door RPCs
+const BLUEPRINTS_WITH_DOORS: &[&str] = &[
+    "vehicle.dodge.charger_2020",
+    "vehicle.dodge.charger_police_2020",
+    "vehicle.ford.crown",
+    "vehicle.lincoln.mkz_2020",
+    "vehicle.mercedes.coupe_2020",
+    "vehicle.mini.cooper_s_2021",
+    "vehicle.nissan.patrol_2021",
+];
+
+const DOORS: [VehicleDoor; 4] = [
+    VehicleDoor::FL,
+    VehicleDoor::FR,
+    VehicleDoor::RL,
+    VehicleDoor::RR,
+];
+
+pub fn has_doors(type_id: &str) -> bool {
+    BLUEPRINTS_WITH_DOORS.contains(&type_id)
+}
+
+/// The door states last commanded, indexed by the positions in `door_command`
+pub struct DoorState {
+    doors: [u8; 4],
+}
+
+impl DoorState {
+    pub fn new(available: bool) -> DoorState {
+        let state = if available {
+            door_status::CLOSED
+        } else {
+            door_status::NOT_AVAILABLE
+        };
+        DoorState { doors: [state; 4] }
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.doors[0] != door_status::NOT_AVAILABLE
+    }
+
+    pub fn doors(&self) -> [u8; 4] {
+        self.doors
+    }
+
+    /// The doors in the layout of the ADAPI status, in the order of the positions in `door_command`
+    pub fn adapi_doors(&self) -> Vec<AdapiDoorStatus> {
+        self.doors
+            .iter()
+            .map(|state| AdapiDoorStatus {
+                status: match *state {
+                    door_status::CLOSED => adapi_door_status::CLOSED,
+                    door_status::OPENED => adapi_door_status::OPENED,
+                    _ => adapi_door_status::NOT_AVAILABLE,
+                },
+            })
+            .collect()
+    }
+
+    /// Record the command and return the CARLA doors to open (true) or close (false)
+    pub fn apply(&mut self, cmd: &DoorCommand) -> Option<(Vec<VehicleDoor>, bool)> {
+        if !self.is_available() {
+            return None;
+        }
+        let (state, open) = match cmd.command {
+            door_command::OPEN => (door_status::OPENED, true),
+            door_command::CLOSE => (door_status::CLOSED, false),
+            _ => return None,
+        };
+        let indices: Vec<usize> = match cmd.position {
+            door_command::ALL => (0..DOORS.len()).collect(),
+            position if (position as usize) < DOORS.len() => vec![position as usize],
+            _ => return None,
+        };
+        indices.iter().for_each(|&idx| self.doors[idx] = state);
+        Some((indices.iter().map(|&idx| DOORS[idx]).collect(), open))
+    }
+}
+
+/// The ADAPI command of a door as a `DoorCommand`, None for an unknown index or command.
+/// The indices of the ADAPI layout are the positions in `door_command`.
+pub fn from_adapi(stamp: Time, cmd: &AdapiDoorCommand) -> Option<DoorCommand> {
+    let position = u8::try_from(cmd.index)
+        .ok()
+        .filter(|position| *position < door_command::ALL)?;
+    let command = match cmd.command {
+        adapi_door_command::OPEN => door_command::OPEN,
+        adapi_door_command::CLOSE => door_command::CLOSE,
+        _ => return None,
+    };
+    Some(DoorCommand {
+        stamp,
+        position,
+        command,
+    })
+}
@@ -0,0 +1,350 @@
+//! `zenoh_carla_bridge echo <key-expr>`: decode the messages of the bridge topics to the
+//! terminal, like `ros2 topic echo`
+use crate::{
+    autoware_type::{
+        self, BatteryStatus, DoorCommand, DoorStatus, DoorStatusArray, GateMode, HeadlightsCommand,
+        HeadlightsReport, MrmBehaviorStatus, PoseWithCovarianceStamped, SetDoorCommand,
+        SlipEstimate, Trajectory, TwistWithCovarianceStamped, WheelSpeeds,
+    },
+    autoware_version::AutowareVersion,
+    bridge::sensor_bridge::IMU,
+    cdr_utils, depth,
+    error::Result,
+    radar, ros_types,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fs,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use zenoh::prelude::sync::*;
+use zenoh_ros_type::{autoware_auto_vehicle_msgs, rosgraph_msgs, sensor_msgs};
+
+/// Arrays longer than this, e.g. the data of an image, are cut to their first elements
+const MAX_ARRAY_LEN: usize = 16;
+/// How many bytes of an undecodable payload are dumped
+const MAX_DUMP_LEN: usize = 256;
+/// The rate counts the arrivals of this last second
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+/// How often Ctrl+C is checked while no sample arrives
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Default)]
+struct KeyStats {
+    count: u64,
+    first: Option<Instant>,
+    last: Option<Instant>,
+    recent: VecDeque<Instant>,
+}
+
+impl KeyStats {
+    fn record(&mut self, now: Instant) {
+        self.count += 1;
+        self.first.get_or_insert(now);
+        self.last = Some(now);
+        self.recent.push_back(now);
+        while matches!(self.recent.front(), Some(time) if now - *time > RATE_WINDOW) {
+            self.recent.pop_front();
+        }
+    }
+
+    fn recent_rate(&self) -> usize {
+        self.recent.len()
+    }
+
+    /// Over the time between the first and the last arrival
+    fn average_rate(&self) -> Option<f64> {
+        let span = (self.last? - self.first?).as_secs_f64();
+        (self.count > 1 && span > 0.0).then(|| (self.count - 1) as f64 / span)
+    }
+}
+
+/// Print the samples of the key expression until Ctrl+C, then a summary per key.
+///
+/// The type of each key comes from the suffix table of `ros_types`, unless `type_name`
+/// forces one. The control commands follow the configured `autoware_version`.
+/// With `save_raw`, every payload is also written as received into the directory,
+/// see `raw_file_name`.
+pub fn run(
+    z_session: Arc<Session>,
+    key_expr: &str,
+    type_name: Option<&str>,
+    autoware_version: AutowareVersion,
+    save_raw: Option<&Path>,
+) -> Result<()> {
+    if let Some(dir) = save_raw {
+        fs::create_dir_all(dir)?;
+    }
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let flag = interrupted.clone();
+    ctrlc::set_handler(move || flag.store(true, Ordering::Relaxed))?;
+    let subscriber = z_session.declare_subscriber(key_expr).res()?;
+    eprintln!("Echoing {key_expr}, Ctrl+C to stop");
+    let start = Instant::now();
+    let mut stats: BTreeMap<String, KeyStats> = BTreeMap::new();
+    while !interrupted.load(Ordering::Relaxed) {
+        let Ok(sample) = subscriber.recv_timeout(POLL_INTERVAL) else {
+            continue;
+        };
+        let now = Instant::now();
+        let key = sample.key_expr.as_str().to_string();
+        let key_stats = stats.entry(key.clone()).or_default();
+        key_stats.record(now);
+        let payload = sample.payload.contiguous();
+        if let Some(dir) = save_raw {
+            fs::write(dir.join(raw_file_name(&key, key_stats.count)), &payload)?;
+        }
+        let type_name = type_name.or_else(|| ros_types::type_name_from_key(&key));
+        let hlc = sample
+            .timestamp
+            .map(|timestamp| format!(" | HLC {timestamp}"))
+            .unwrap_or_default();
+        println!(
+            "--- {:.3} s | {key} | {} | {} bytes | {} Hz{hlc}",
+            (now - start).as_secs_f64(),
+            type_name.unwrap_or("unknown type"),
+            payload.len(),
+            key_stats.recent_rate()
+        );
+        match type_name.map(|type_name| decode(type_name, &payload, autoware_version)) {
+            Some(Some(Ok(mut value))) => {
+                abbreviate(&mut value);
+                println!("{}", serde_json::to_string_pretty(&value)?);
+            }
+            Some(Some(Err(e))) => {
+                println!("Failed to decode: {e}");
+                hex_dump(&payload);
+            }
+            Some(None) | None => hex_dump(&payload),
+        }
+    }
+    print_summary(&stats, start.elapsed());
+    Ok(())
+}
+
+/// `v1/rt/control/command/control_cmd` becomes `v1_rt_control_command_control_cmd_000001.cdr`,
+/// with the CDR encapsulation header as on the wire
+fn raw_file_name(key: &str, index: u64) -> String {
+    format!("{}_{index:06}.cdr", key.replace('/', "_"))
+}
+
+/// The message as JSON, None if the type has no decoder here
+fn decode(
+    type_name: &str,
+    payload: &[u8],
+    autoware_version: AutowareVersion,
+) -> Option<Result<Value>> {
+    use autoware_auto_vehicle_msgs as vehicle_msgs;
+    Some(match type_name {
+        "rosgraph_msgs/msg/Clock" => to_json::<rosgraph_msgs::Clock>(payload),
+        "autoware_auto_vehicle_msgs/msg/VelocityReport" => {
+            to_json::<vehicle_msgs::VelocityReport>(payload)
+        }
+        "autoware_auto_vehicle_msgs/msg/SteeringReport" => {
+            to_json::<vehicle_msgs::SteeringReport>(payload)
+        }
+        "autoware_auto_vehicle_msgs/msg/GearReport" => to_json::<vehicle_msgs::GearReport>(payload),
+        "autoware_auto_vehicle_msgs/msg/ControlModeReport" => {
+            to_json::<vehicle_msgs::ControlModeReport>(payload)
+        }
+        "autoware_auto_vehicle_msgs/msg/TurnIndicatorsReport" => {
+            to_json::<vehicle_msgs::TurnIndicatorsReport>(payload)
+        }
+        "autoware_auto_vehicle_msgs/msg/HazardLightsReport" => {
+            to_json::<vehicle_msgs::HazardLightsReport>(payload)
+        }
+        "autoware_auto_vehicle_msgs/msg/HeadlightsReport" => to_json::<HeadlightsReport>(payload),
+        "autoware_auto_vehicle_msgs/msg/GearCommand" => {
+            to_json::<vehicle_msgs::GearCommand>(payload)
+        }
+        "autoware_auto_vehicle_msgs/msg/HeadlightsCommand" => to_json::<HeadlightsCommand>(payload),
+        "tier4_control_msgs/msg/GateMode" => to_json::<GateMode>(payload),
+        "autoware_auto_planning_msgs/msg/Trajectory" => to_json::<Trajectory>(payload),
+        "autoware_auto_control_msgs/msg/AckermannControlCommand" => autoware_version
+            .decode_control(payload)
+            .and_then(|cmd| Ok(serde_json::to_value(cmd)?)),
+        "tier4_vehicle_msgs/msg/BatteryStatus" => to_json::<BatteryStatus>(payload),
+        "tier4_vehicle_msgs/msg/ActuationCommandStamped" => {
+            to_json::<autoware_type::ActuationCommandStamped>(payload)
+        }
+        "tier4_system_msgs/msg/MrmBehaviorStatus" => to_json::<MrmBehaviorStatus>(payload),
+        "zenoh_carla_bridge/msg/DoorCommand" => to_json::<DoorCommand>(payload),
+        "zenoh_carla_bridge/msg/DoorStatus" => to_json::<DoorStatus>(payload),
+        "autoware_adapi_v1_msgs/srv/SetDoorCommand_Request" => to_json::<SetDoorCommand>(payload),
+        "autoware_adapi_v1_msgs/msg/DoorStatusArray" => to_json::<DoorStatusArray>(payload),
+        "zenoh_carla_bridge/msg/WheelSpeeds" => to_json::<WheelSpeeds>(payload),
+        "zenoh_carla_bridge/msg/SlipEstimate" => to_json::<SlipEstimate>(payload),
+        "geometry_msgs/msg/TwistWithCovarianceStamped" => {
+            to_json::<TwistWithCovarianceStamped>(payload)
+        }
+        "geometry_msgs/msg/PoseWithCovarianceStamped" => {
+            to_json::<PoseWithCovarianceStamped>(payload)
+        }
+        "sensor_msgs/msg/Image" => to_json::<sensor_msgs::Image>(payload),
+        "sensor_msgs/msg/CompressedImage" => to_json::<depth::CompressedImage>(payload),
+        "sensor_msgs/msg/CameraInfo" => to_json::<sensor_msgs::CameraInfo>(payload),
+        "sensor_msgs/msg/PointCloud2" => to_json::<sensor_msgs::PointCloud2>(payload),
+        "sensor_msgs/msg/Imu" => to_json::<IMU>(payload),
+        "sensor_msgs/msg/NavSatFix" => to_json::<sensor_msgs::NavSatFix>(payload),
+        "radar_msgs/msg/RadarScan" => to_json::<radar::RadarScan>(payload),
+        _ => return None,
+    })
+}
+
+fn to_json<T: DeserializeOwned + Serialize>(payload: &[u8]) -> Result<Value> {
+    let message: T = cdr_utils::deserialize(payload)?;
+    Ok(serde_json::to_value(message)?)
+}
+
+/// Cut the long arrays, so an image or a point cloud doesn't flood the terminal
+fn abbreviate(value: &mut Value) {
+    match value {
+        Value::Array(items) => {
+            if items.len() > MAX_ARRAY_LEN {
+                let more = items.len() - MAX_ARRAY_LEN;
+                items.truncate(MAX_ARRAY_LEN);
+                items.push(Value::String(format!("... {more} more")));
+            }
+            items.iter_mut().for_each(abbreviate);
+        }
+        Value::Object(fields) => fields.values_mut().for_each(abbreviate),
+        _ => {}
+    }
+}
+
+fn hex_dump(payload: &[u8]) {
+    for (line, chunk) in payload.chunks(16).take(MAX_DUMP_LEN / 16).enumerate() {
+        let bytes: Vec<_> = chunk.iter().map(|byte| format!("{byte:02x}")).collect();
+        println!("{:04x}  {}", line * 16, bytes.join(" "));
+    }
+    if payload.len() > MAX_DUMP_LEN {
+        println!("... {} more bytes", payload.len() - MAX_DUMP_LEN);
+    }
+}
+
+fn print_summary(stats: &BTreeMap<String, KeyStats>, elapsed: Duration) {
+    let total: u64 = stats.values().map(|key_stats| key_stats.count).sum();
+    eprintln!(
+        "\n{total} messages on {} keys in {:.1} s",
+        stats.len(),
+        elapsed.as_secs_f64()
+    );
+    for (key, key_stats) in stats {
+        match key_stats.average_rate() {
+            Some(rate) => eprintln!("{key}: {} messages, {rate:.2} Hz", key_stats.count),
+            None => eprintln!("{key}: {} messages", key_stats.count),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A recording of `--save-raw`, from `tests/fixtures`
+    fn recorded(key: &str, index: u64) -> Vec<u8> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(raw_file_name(key, index));
+        fs::read(&path).unwrap_or_else(|e| panic!("{}: {e}", path.display()))
+    }
+
+    fn decode_recorded(key: &str) -> Value {
+        let type_name = ros_types::type_name_from_key(key).unwrap();
+        let payload = recorded(key, 0);
+        decode(type_name, &payload, AutowareVersion::default())
+            .unwrap_or_else(|| panic!("no decoder for {type_name}"))
+            .unwrap()
+    }
+
+    #[test]
+    fn name_the_raw_files_after_the_key() {
+        assert_eq!(
+            raw_file_name("ego/vehicle/status/velocity_status", 42),
+            "ego_vehicle_status_velocity_status_000042.cdr"
+        );
+        assert_eq!(
+            raw_file_name("carla/clock", 1_000_000),
+            "carla_clock_1000000.cdr"
+        );
+    }
+
+    #[test]
+    fn decode_the_recorded_payloads() {
+        let stamp = json!({"sec": 1, "nanosec": 500_000_000});
+        assert_eq!(decode_recorded("carla/clock"), json!({ "clock": stamp }));
+
+        let velocity = decode_recorded("ego/vehicle/status/velocity_status");
+        assert_eq!(velocity["header"]["stamp"], stamp);
+        assert_eq!(velocity["header"]["frame_id"], "base_link");
+        assert_eq!(velocity["longitudinal_velocity"], 3.0);
+        assert_eq!(velocity["lateral_velocity"], -0.5);
+        assert_eq!(velocity["heading_rate"], 0.25);
+
+        let steering = decode_recorded("ego/vehicle/status/steering_status");
+        assert_eq!(steering["steering_tire_angle"], 0.125);
+
+        let gear = decode_recorded("ego/vehicle/status/gear_status");
+        assert_eq!(gear["report"], 2);
+
+        let command_stamp = json!({"sec": 42, "nanosec": 100_000_000});
+        let control = decode_recorded("ego/control/command/control_cmd");
+        assert_eq!(control["stamp"], command_stamp);
+        assert_eq!(control["lateral"]["stamp"]["nanosec"], 95_000_000);
+        assert_eq!(control["lateral"]["steering_tire_angle"], -0.0390625);
+        assert_eq!(control["longitudinal"]["speed"], 4.1875);
+        assert_eq!(control["longitudinal"]["jerk"], 0.3125);
+
+        let gear = decode_recorded("ego/control/command/gear_cmd");
+        assert_eq!(gear["stamp"], command_stamp);
+        assert_eq!(gear["command"], 20);
+
+        let gate_mode = decode_recorded("ego/control/current_gate_mode");
+        assert_eq!(gate_mode["data"], autoware_type::gate_mode::AUTO);
+
+        let trajectory = decode_recorded("ego/planning/scenario_planning/trajectory");
+        assert_eq!(
+            trajectory["header"],
+            json!({"stamp": {"sec": 42, "nanosec": 50_000_000}, "frame_id": "map"})
+        );
+        let points = trajectory["points"].as_array().unwrap();
+        assert_eq!(points.len(), 3);
+        assert_eq!(points[2]["time_from_start"]["nanosec"], 200_000_000);
+        assert_eq!(points[2]["pose"]["position"], json!([121.5, -34.25, 0.0]));
+        assert_eq!(points[2]["longitudinal_velocity_mps"], 4.5);
+
+        let cloud = decode_recorded("ego/sensing/radar/front/pointcloud");
+        assert_eq!(cloud["width"], 2);
+        assert_eq!(cloud["fields"][3]["name"], "velocity");
+    }
+
+    #[test]
+    fn a_truncated_payload_fails_to_decode() {
+        let payload = recorded("ego/vehicle/status/velocity_status", 0);
+        let decoded = decode(
+            "autoware_auto_vehicle_msgs/msg/VelocityReport",
+            &payload[..payload.len() - 4],
+            AutowareVersion::default(),
+        );
+        assert!(matches!(decoded, Some(Err(_))));
+        assert!(decode("std_msgs/msg/Empty", &payload, AutowareVersion::default()).is_none());
+    }
+
+    #[test]
+    fn abbreviate_the_long_arrays() {
+        let mut value = json!({ "data": vec![0; MAX_ARRAY_LEN + 5], "short": [1, 2] });
+        abbreviate(&mut value);
+        let data = value["data"].as_array().unwrap();
+        assert_eq!(data.len(), MAX_ARRAY_LEN + 1);
+        assert_eq!(data[MAX_ARRAY_LEN], "... 5 more");
+        assert_eq!(value["short"], json!([1, 2]));
+    }
+}
@@ -1,6 +1,9 @@
+use crate::autoware_version::AutowareVersion;
+
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum Error {
     #[error("CDR error: {0}")]
     Cdr(#[from] cdr::Error),
@@ -13,4 +16,49 @@ pub enum Error {
 
     #[error("The vehicle is NPC")]
     Npc { npc_role_name: String },
+
+    #[error("Unknown CDR encapsulation header {header:02x?}")]
+    UnknownEncapsulation { header: [u8; 2] },
+
+    #[error("Invalid CDR payload length {len}, expected {expected} bytes")]
+    InvalidPayloadLength { len: usize, expected: usize },
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("The command log is corrupted")]
+    CorruptedCommandLog,
+
+    #[error("MCAP error: {0}")]
+    Mcap(#[from] mcap::McapError),
+
+    #[error("Invalid configuration: {reason}")]
+    Config { reason: String },
+
+    #[error("The control command has the layout of {detected}, not {configured}. Check the autoware_version setting")]
+    MessageVersionMismatch {
+        configured: AutowareVersion,
+        detected: AutowareVersion,
+    },
+
+    #[error("Invalid command: {reason}")]
+    InvalidCommand { reason: String },
+
+    #[error("Invalid pedal map {}: {reason}", path.display())]
+    InvalidPedalMap {
+        path: std::path::PathBuf,
+        reason: String,
+    },
+
+    #[error("Invalid compressed payload: {reason}")]
+    InvalidCompressedPayload { reason: String },
+
+    #[error("PNG error: {0}")]
+    Png(#[from] png::EncodingError),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Failed to handle Ctrl+C: {0}")]
+    CtrlC(#[from] ctrlc::Error),
 }
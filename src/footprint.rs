@@ -0,0 +1,139 @@
+//! The 2D footprint of a vehicle, from its bounding box, on `rt/vehicle/footprint`
+use crate::{
+    autoware_type::{Point32, Polygon},
+    utils,
+};
+use carla::geom::BoundingBox;
+use nalgebra::{Isometry3, Point3};
+
+/// The corners of the bounding box grown by the margin (m) on every side, in the map frame.
+///
+/// They run counter-clockwise in ROS, from the front right corner, at the height of the
+/// bottom of the box.
+pub fn footprint(
+    transform: &Isometry3<f32>,
+    bounding_box: &BoundingBox<f32>,
+    margin: f32,
+) -> Polygon {
+    let half_length = bounding_box.extent.x + margin;
+    let half_width = bounding_box.extent.y + margin;
+    let bottom = -bounding_box.extent.z;
+    // CARLA has y to the right, so these are the front right, front left, rear left and
+    // rear right corners after the conversion
+    let corners = [
+        (half_length, half_width),
+        (half_length, -half_width),
+        (-half_length, -half_width),
+        (-half_length, half_width),
+    ];
+    let points = corners
+        .iter()
+        .map(|&(x, y)| {
+            let corner = transform * bounding_box.transform * Point3::new(x, y, bottom);
+            let [x, y, z] = utils::carla_to_ros_position(corner.x, corner.y, corner.z);
+            Point32 {
+                x: x as f32,
+                y: y as f32,
+                z: z as f32,
+            }
+        })
+        .collect();
+    Polygon { points }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{Translation3, UnitQuaternion, Vector3};
+
+    /// A car of 4.5 x 1.8 x 1.5 m, whose box starts 0.2 m ahead of its origin and sits on it
+    fn car_box() -> BoundingBox<f32> {
+        BoundingBox {
+            transform: Isometry3::translation(0.2, 0.0, 0.75),
+            extent: Vector3::new(2.25, 0.9, 0.75),
+        }
+    }
+
+    /// A pose of CARLA: at (x, y, z) with the yaw of CARLA (degrees)
+    fn pose(x: f32, y: f32, z: f32, yaw: f32) -> Isometry3<f32> {
+        Isometry3::from_parts(
+            Translation3::new(x, y, z),
+            UnitQuaternion::from_euler_angles(0.0, 0.0, yaw.to_radians()),
+        )
+    }
+
+    /// Twice the signed area, positive for the counter-clockwise polygons
+    fn signed_area(polygon: &Polygon) -> f32 {
+        let points = &polygon.points;
+        (0..points.len())
+            .map(|index| {
+                let (a, b) = (&points[index], &points[(index + 1) % points.len()]);
+                a.x * b.y - b.x * a.y
+            })
+            .sum()
+    }
+
+    /// Whether the point is inside the counter-clockwise convex polygon
+    fn contains(polygon: &Polygon, x: f32, y: f32) -> bool {
+        let points = &polygon.points;
+        (0..points.len()).all(|index| {
+            let (a, b) = (&points[index], &points[(index + 1) % points.len()]);
+            (b.x - a.x) * (y - a.y) - (b.y - a.y) * (x - a.x) > 0.0
+        })
+    }
+
+    fn assert_near(point: &Point32, expected: [f32; 3]) {
+        let position = [point.x, point.y, point.z];
+        for (axis, expected_axis) in position.iter().zip(expected) {
+            assert!(
+                (axis - expected_axis).abs() < 1e-4,
+                "{position:?} != {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn the_corners_from_the_front_right() {
+        let polygon = footprint(&Isometry3::identity(), &car_box(), 0.0);
+        assert_eq!(polygon.points.len(), 4);
+        assert_near(&polygon.points[0], [2.45, -0.9, 0.0]);
+        assert_near(&polygon.points[1], [2.45, 0.9, 0.0]);
+        assert_near(&polygon.points[2], [-2.05, 0.9, 0.0]);
+        assert_near(&polygon.points[3], [-2.05, -0.9, 0.0]);
+    }
+
+    #[test]
+    fn the_margin_grows_every_side() {
+        let polygon = footprint(&Isometry3::identity(), &car_box(), 0.5);
+        assert_near(&polygon.points[0], [2.95, -1.4, 0.0]);
+        assert_near(&polygon.points[2], [-2.55, 1.4, 0.0]);
+        // (4.5 + 1.0) x (1.8 + 1.0) m
+        assert!((signed_area(&polygon) / 2.0 - 5.5 * 2.8).abs() < 1e-3);
+    }
+
+    #[test]
+    fn counter_clockwise_around_the_vehicle_in_any_pose() {
+        for yaw in (-180..180).step_by(15) {
+            let transform = pose(120.0, -35.0, 2.0, yaw as f32);
+            let polygon = footprint(&transform, &car_box(), 0.1);
+            assert!(signed_area(&polygon) > 0.0, "yaw {yaw}");
+            let [x, y, _] = utils::carla_to_ros_position(120.0, -35.0, 2.0);
+            assert!(contains(&polygon, x as f32, y as f32), "yaw {yaw}");
+            assert!(
+                polygon
+                    .points
+                    .iter()
+                    .all(|point| (point.z - 2.0).abs() < 1e-4),
+                "yaw {yaw}"
+            );
+        }
+    }
+
+    #[test]
+    fn the_front_follows_the_yaw() {
+        // CARLA turns clockwise from above with a positive yaw, 90 degrees faces ROS -y
+        let polygon = footprint(&pose(0.0, 0.0, 0.0, 90.0), &car_box(), 0.0);
+        let front_y = (polygon.points[0].y + polygon.points[1].y) / 2.0;
+        assert!((front_y + 2.45).abs() < 1e-4, "{front_y}");
+    }
+}
@@ -0,0 +1,236 @@
+//! A bounded queue between the callback of a sensor and the worker converting its frames
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    time::Duration,
+};
+
+/// Wakes the consumer of several queues when any of them gets a frame or closes
+#[derive(Default)]
+pub struct Wake {
+    pending: Mutex<bool>,
+    ready: Condvar,
+}
+
+impl Wake {
+    pub fn notify(&self) {
+        *self.pending.lock().unwrap() = true;
+        self.ready.notify_one();
+    }
+
+    /// Wait for a notification since the last wait, at most the timeout
+    pub fn wait(&self, timeout: Duration) {
+        let pending = self.pending.lock().unwrap();
+        let (mut pending, _) = self
+            .ready
+            .wait_timeout_while(pending, timeout, |pending| !*pending)
+            .unwrap();
+        *pending = false;
+    }
+}
+
+struct State<T> {
+    frames: VecDeque<T>,
+    closed: bool,
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    wake: Arc<Wake>,
+    depth: usize,
+    senders: AtomicUsize,
+    dropped: AtomicU64,
+}
+
+/// A queue of at most `depth` frames, which drops the oldest one when it's full.
+///
+/// The CARLA callbacks run on the thread of the client, shared by every sensor, so they
+/// must never wait on a slow consumer, and the camera frames are too large to pile up.
+pub fn frame_queue<T>(depth: usize, wake: Arc<Wake>) -> (FrameSender<T>, FrameReceiver<T>) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            frames: VecDeque::with_capacity(depth),
+            closed: false,
+        }),
+        wake,
+        depth: depth.max(1),
+        senders: AtomicUsize::new(1),
+        dropped: AtomicU64::new(0),
+    });
+    (
+        FrameSender {
+            shared: shared.clone(),
+        },
+        FrameReceiver { shared },
+    )
+}
+
+pub struct FrameSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> FrameSender<T> {
+    /// Queue the frame without blocking, dropping the oldest one of a full queue
+    pub fn push(&self, frame: T) {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.closed {
+            return;
+        }
+        if state.frames.len() >= self.shared.depth {
+            state.frames.pop_front();
+            self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        state.frames.push_back(frame);
+        drop(state);
+        self.shared.wake.notify();
+    }
+
+    /// Let the receiver drain the queue and stop, ignoring the frames pushed afterwards.
+    ///
+    /// The callback of a sensor keeps its sender as long as CARLA keeps the callback,
+    /// so the owner closes the queue rather than waiting for the last sender to go.
+    pub fn close(&self) {
+        self.shared.state.lock().unwrap().closed = true;
+        self.shared.wake.notify();
+    }
+
+    /// How many frames were dropped since the start
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl<T> Clone for FrameSender<T> {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::Relaxed);
+        FrameSender {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for FrameSender<T> {
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.close();
+        }
+    }
+}
+
+/// What the receiver finds in the queue, without waiting
+pub enum Recv<T> {
+    Frame(T),
+    Empty,
+    /// Closed and drained
+    Closed,
+}
+
+pub struct FrameReceiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> FrameReceiver<T> {
+    pub fn try_recv(&self) -> Recv<T> {
+        let mut state = self.shared.state.lock().unwrap();
+        match state.frames.pop_front() {
+            Some(frame) => Recv::Frame(frame),
+            None if state.closed => Recv::Closed,
+            None => Recv::Empty,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{thread, time::Instant};
+
+    fn queued<T>(receiver: &FrameReceiver<T>) -> usize {
+        receiver.shared.state.lock().unwrap().frames.len()
+    }
+
+    #[test]
+    fn drop_the_oldest_frame_when_full() {
+        let (sender, receiver) = frame_queue(2, Arc::new(Wake::default()));
+        for frame in 1..=5 {
+            sender.push(frame);
+        }
+        assert_eq!(sender.dropped(), 3);
+        assert!(matches!(receiver.try_recv(), Recv::Frame(4)));
+        assert!(matches!(receiver.try_recv(), Recv::Frame(5)));
+        assert!(matches!(receiver.try_recv(), Recv::Empty));
+    }
+
+    #[test]
+    fn drain_a_closed_queue() {
+        let (sender, receiver) = frame_queue(2, Arc::new(Wake::default()));
+        sender.push(1);
+        sender.close();
+        // Too late
+        sender.push(2);
+        assert!(matches!(receiver.try_recv(), Recv::Frame(1)));
+        assert!(matches!(receiver.try_recv(), Recv::Closed));
+        assert_eq!(sender.dropped(), 0);
+    }
+
+    #[test]
+    fn the_last_sender_closes_the_queue() {
+        let (sender, receiver) = frame_queue::<u32>(1, Arc::new(Wake::default()));
+        let clone = sender.clone();
+        drop(sender);
+        assert!(matches!(receiver.try_recv(), Recv::Empty));
+        drop(clone);
+        assert!(matches!(receiver.try_recv(), Recv::Closed));
+    }
+
+    #[test]
+    fn a_push_wakes_the_consumer() {
+        let wake = Arc::new(Wake::default());
+        let (sender, receiver) = frame_queue(2, wake.clone());
+        let producer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            sender.push(1);
+        });
+        let start = Instant::now();
+        wake.wait(Duration::from_secs(10));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(matches!(receiver.try_recv(), Recv::Frame(1)));
+        producer.join().unwrap();
+    }
+
+    #[test]
+    fn a_slow_consumer_keeps_the_queue_bounded() {
+        const FRAMES: usize = 500;
+        const DEPTH: usize = 2;
+        let wake = Arc::new(Wake::default());
+        let (sender, receiver) = frame_queue(DEPTH, wake.clone());
+        let producer = thread::spawn(move || {
+            for _ in 0..FRAMES {
+                // Like a camera frame, too large to pile up
+                sender.push(vec![0u8; 64 * 1024]);
+            }
+            sender.dropped()
+        });
+        let mut received = 0;
+        let mut max_queued = 0;
+        loop {
+            max_queued = max_queued.max(queued(&receiver));
+            match receiver.try_recv() {
+                Recv::Frame(frame) => {
+                    assert_eq!(frame.len(), 64 * 1024);
+                    received += 1;
+                    thread::sleep(Duration::from_millis(1));
+                }
+                Recv::Empty => wake.wait(Duration::from_millis(100)),
+                Recv::Closed => break,
+            }
+        }
+        let dropped = producer.join().unwrap();
+        assert!(max_queued <= DEPTH);
+        assert!(dropped > 0);
+        assert_eq!(received + dropped as usize, FRAMES);
+    }
+}
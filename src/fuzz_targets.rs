@@ -0,0 +1,104 @@
+//! The decoding of each subscribed message type, the way the subscriber callbacks run it,
+//! for the cargo-fuzz targets in `fuzz/` and for the run over their seeds in `cargo test`
+use crate::{
+    autoware_type::{
+        ActuationCommandStamped, AutowareState, DoorCommand, Engage, HeadlightsCommand, MrmState,
+        PoseStamped, SetDoorCommand, Trajectory, Twist,
+    },
+    autoware_version::AutowareVersion,
+    cdr_utils::SampleDecoder,
+    stats::VehicleStats,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::sync::Arc;
+use zenoh_ros_type::autoware_auto_vehicle_msgs::{
+    GearCommand, HazardLightsCommand, TurnIndicatorsCommand,
+};
+
+/// The targets by the name of their binary in `fuzz/`, true if the payload decoded
+pub const TARGETS: &[(&str, fn(&[u8]) -> bool)] = &[
+    ("control_cmd", control_cmd),
+    ("gear_cmd", decode::<GearCommand>),
+    ("turn_indicators_cmd", decode::<TurnIndicatorsCommand>),
+    ("hazard_lights_cmd", decode::<HazardLightsCommand>),
+    ("actuation_cmd", decode::<ActuationCommandStamped>),
+    ("door_cmd", decode::<DoorCommand>),
+    ("set_door_cmd", decode::<SetDoorCommand>),
+    ("headlights_cmd", decode::<HeadlightsCommand>),
+    ("mrm_state", decode::<MrmState>),
+    ("autoware_state", decode::<AutowareState>),
+    ("cmd_vel", decode::<Twist>),
+    ("trajectory", decode::<Trajectory>),
+    ("goal_pose", decode::<PoseStamped>),
+    ("engage", decode::<Engage>),
+];
+
+/// Run the target of this name over a payload
+pub fn run(target: &str, payload: &[u8]) -> bool {
+    let (_, decode) = TARGETS
+        .iter()
+        .find(|(name, _)| *name == target)
+        .unwrap_or_else(|| panic!("no fuzz target {target}"));
+    decode(payload)
+}
+
+fn decoder() -> SampleDecoder {
+    SampleDecoder::new("fuzz", Arc::new(VehicleStats::default()))
+}
+
+fn decode<T>(payload: &[u8]) -> bool
+where
+    T: DeserializeOwned + Serialize,
+{
+    decoder().decode::<T>(payload).is_some()
+}
+
+/// Either layout, including the detection of the other one on a failure
+fn control_cmd(payload: &[u8]) -> bool {
+    let mut decoded = false;
+    for version in [AutowareVersion::Universe2023, AutowareVersion::Universe2024] {
+        decoded |= decoder()
+            .decode_with(payload, |payload| version.decode_control(payload))
+            .is_some();
+    }
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, path::Path};
+
+    /// Every seed decodes, and none of its truncations panics
+    #[test]
+    fn run_the_targets_over_their_seeds() {
+        let seeds = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/seeds");
+        for (target, decode) in TARGETS {
+            let mut count = 0;
+            for entry in fs::read_dir(seeds.join(target)).unwrap() {
+                let path = entry.unwrap().path();
+                let payload = fs::read(&path).unwrap();
+                assert!(decode(&payload), "{} doesn't decode", path.display());
+                for len in 0..payload.len() {
+                    decode(&payload[..len]);
+                }
+                count += 1;
+            }
+            assert!(count > 0, "no seeds for {target}");
+        }
+    }
+
+    /// The seeds are the corpus of the targets, so each target has a directory of them
+    #[test]
+    fn every_seed_directory_has_a_target() {
+        let seeds = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/seeds");
+        for entry in fs::read_dir(seeds).unwrap() {
+            let name = entry.unwrap().file_name();
+            let name = name.to_str().unwrap();
+            assert!(
+                TARGETS.iter().any(|(target, _)| *target == name),
+                "no target for the seeds of {name}"
+            );
+        }
+    }
+}
@@ -0,0 +1,141 @@
+//! The geo reference of a CARLA map, to place the GNSS fixes in the map frame.
+//!
+//! CARLA puts the origin of a map at the `+lat_0` and `+lon_0` of the proj string in the
+//! `<geoReference>` of the OpenDRIVE header, and converts a location to a fix with a
+//! Mercator projection scaled at that latitude (`carla/geom/GeoLocation.cpp`).
+//! `GeoReference::to_map` is the inverse of that conversion.
+
+/// The radius of the earth used by CARLA (m)
+const EARTH_RADIUS: f64 = 6378137.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoReference {
+    /// The origin, in degrees
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl GeoReference {
+    /// The geo reference in the header of an OpenDRIVE file.
+    /// None without one, or if its proj string has no `+lat_0` and `+lon_0`.
+    pub fn from_opendrive(opendrive: &str) -> Option<GeoReference> {
+        let start = opendrive.find("<geoReference>")? + "<geoReference>".len();
+        let end = start + opendrive[start..].find("</geoReference>")?;
+        let proj = opendrive[start..end]
+            .trim()
+            .trim_start_matches("<![CDATA[")
+            .trim_end_matches("]]>");
+        let latitude = proj_parameter(proj, "lat_0")?;
+        let longitude = proj_parameter(proj, "lon_0")?;
+        (latitude.abs() < 90.0 && longitude.abs() <= 180.0).then_some(GeoReference {
+            latitude,
+            longitude,
+        })
+    }
+
+    /// The position of a fix in the map frame of ROS (x east, y north, z up), in meters
+    pub fn to_map(&self, latitude: f64, longitude: f64, altitude: f64) -> [f64; 3] {
+        let scale = self.latitude.to_radians().cos();
+        let [origin_x, origin_y] = mercator(self.latitude, self.longitude, scale);
+        let [x, y] = mercator(latitude, longitude, scale);
+        [x - origin_x, y - origin_y, altitude]
+    }
+}
+
+/// The value of `+name=value` in a proj string
+fn proj_parameter(proj: &str, name: &str) -> Option<f64> {
+    proj.split_whitespace()
+        .filter_map(|parameter| parameter.strip_prefix('+')?.split_once('='))
+        .find(|(key, _)| *key == name)
+        .and_then(|(_, value)| value.parse().ok())
+}
+
+fn mercator(latitude: f64, longitude: f64, scale: f64) -> [f64; 2] {
+    [
+        scale * longitude.to_radians() * EARTH_RADIUS,
+        scale
+            * EARTH_RADIUS
+            * ((90.0 + latitude) * std::f64::consts::PI / 360.0)
+                .tan()
+                .ln(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The header of the OpenDRIVE files of the CARLA towns
+    const TOWN_HEADER: &str = r#"<header revMajor="1" revMinor="4" name="" version="1">
+        <geoReference><![CDATA[+proj=tmerc +lat_0=4.9000000000000000e+1 +lon_0=8.0000000000000000e+0 +k=1 +x_0=0 +y_0=0 +datum=WGS84 +units=m +geoidgrids=egm96_15.gtx +vunits=m +no_defs ]]></geoReference>
+    </header>"#;
+
+    fn assert_near(position: [f64; 3], expected: [f64; 3]) {
+        for (axis, expected) in position.iter().zip(expected) {
+            assert!(
+                (axis - expected).abs() < 1e-3,
+                "{position:?} != {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn the_geo_reference_of_the_towns() {
+        assert_eq!(
+            GeoReference::from_opendrive(TOWN_HEADER),
+            Some(GeoReference {
+                latitude: 49.0,
+                longitude: 8.0,
+            })
+        );
+        let without_cdata = "<geoReference>+lat_0=-33.5 +lon_0=151.25</geoReference>";
+        assert_eq!(
+            GeoReference::from_opendrive(without_cdata),
+            Some(GeoReference {
+                latitude: -33.5,
+                longitude: 151.25,
+            })
+        );
+    }
+
+    #[test]
+    fn a_map_without_a_usable_geo_reference() {
+        for opendrive in [
+            "<header revMajor=\"1\" revMinor=\"4\"></header>",
+            "<geoReference><![CDATA[]]></geoReference>",
+            "<geoReference>+proj=tmerc +lon_0=8</geoReference>",
+            "<geoReference>+lat_0=49 +lon_0=east</geoReference>",
+            "<geoReference>+lat_0=90 +lon_0=8</geoReference>",
+            "<geoReference>+lat_0=49 +lon_0=181</geoReference>",
+            "<geoReference>+lat_0=49 +lon_0=8",
+        ] {
+            assert_eq!(GeoReference::from_opendrive(opendrive), None, "{opendrive}");
+        }
+    }
+
+    /// The fixes CARLA reports at locations of the towns, the y axis of CARLA points south
+    #[test]
+    fn the_fixes_of_the_towns_in_the_map_frame() {
+        let town = GeoReference::from_opendrive(TOWN_HEADER).unwrap();
+        assert_near(town.to_map(49.0, 8.0, 0.0), [0.0, 0.0, 0.0]);
+        // CARLA (100, -50, 2)
+        assert_near(
+            town.to_map(49.000449155617, 8.001369259845, 2.0),
+            [100.0, 50.0, 2.0],
+        );
+        // CARLA (-250.5, 120.25, 0)
+        assert_near(
+            town.to_map(48.998919764157, 7.996570004089, 0.0),
+            [-250.5, -120.25, 0.0],
+        );
+        let equator = GeoReference {
+            latitude: 0.0,
+            longitude: 0.0,
+        };
+        // CARLA (1000, 1000, -5)
+        assert_near(
+            equator.to_map(-0.008983152804, 0.008983152841, -5.0),
+            [1000.0, -1000.0, -5.0],
+        );
+    }
+}
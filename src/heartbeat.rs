@@ -0,0 +1,362 @@
+use crate::{
+    autoware_type::{diagnostic_status, DiagnosticArray, DiagnosticStatus, KeyValue},
+    cdr_utils::{self, Endianness},
+    error::Result,
+    time::TimeStamp,
+    topics::{self, TopicMap},
+    utils,
+};
+use log::{error, info, warn};
+use serde_derive::Serialize;
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+use zenoh::prelude::sync::*;
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+/// CARLA counts as disconnected when the main loop made no progress for longer (in seconds)
+const CARLA_TIMEOUT_SEC: f64 = 5.0;
+/// The exit code of `exit_on_stall`, for a restart policy to tell it from an error
+const STALL_EXIT_CODE: i32 = 3;
+
+/// Settings of the stall watchdog, which runs in the heartbeat thread
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct WatchdogConfig {
+    /// The simulation counts as stalled when neither a tick finished nor the world frame
+    /// advanced for longer (wall-clock seconds).
+    pub stall_timeout: f64,
+    /// Exit the process on a stall, so a restart policy reconnects to CARLA.
+    pub exit_on_stall: bool,
+    /// The zenoh session counts as disconnected when it had neither a router nor a peer for
+    /// longer (wall-clock seconds), after it had one. The vehicles then stop safely.
+    pub disconnect_timeout: f64,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        WatchdogConfig {
+            stall_timeout: 10.0,
+            exit_on_stall: false,
+            disconnect_timeout: 3.0,
+        }
+    }
+}
+
+/// The JSON published on `*/bridge/heartbeat`
+#[derive(Serialize)]
+pub struct HeartbeatReport {
+    pub seq: u64,
+    /// Wall-clock seconds since the start
+    pub uptime_sec: f64,
+    pub carla_connected: bool,
+    /// Wall-clock seconds since the main loop last finished a tick, none before the first one
+    pub last_tick_age_sec: Option<f64>,
+    pub vehicle_bridges: usize,
+    pub walker_bridges: usize,
+    pub sensor_bridges: usize,
+    /// Neither a tick finished nor the world frame advanced for `stall_timeout`
+    pub stalled: bool,
+    /// The session reaches a router or a peer, or lost them for less than
+    /// `disconnect_timeout`
+    pub zenoh_connected: bool,
+    /// The CARLA recorder runs, started by `bridge/recorder`
+    pub recording: bool,
+    pub recording_file: Option<String>,
+    pub version: &'static str,
+}
+
+/// What the main loop reports to the heartbeat thread
+#[derive(Default)]
+struct LoopState {
+    vehicle_bridges: AtomicUsize,
+    walker_bridges: AtomicUsize,
+    sensor_bridges: AtomicUsize,
+    last_tick: Mutex<Option<Instant>>,
+    /// The latest world frame and when it changed
+    last_frame: Mutex<Option<(u64, Instant)>>,
+    /// The main loop blocks on purpose, e.g. while CARLA loads a world
+    watchdog_paused: AtomicBool,
+    zenoh_lost: AtomicBool,
+    recording_file: Mutex<Option<String>>,
+}
+
+impl LoopState {
+    /// Wall-clock seconds since the loop last made progress, none before the first tick
+    /// and while the watchdog is paused
+    fn stall_age(&self) -> Option<f64> {
+        if self.watchdog_paused.load(Ordering::Relaxed) {
+            return None;
+        }
+        let tick_age = (*self.last_tick.lock().unwrap())?.elapsed();
+        let (_, frame_changed) = (*self.last_frame.lock().unwrap())?;
+        Some(tick_age.max(frame_changed.elapsed()).as_secs_f64())
+    }
+}
+
+/// Publish a heartbeat once per second from a thread of its own.
+///
+/// It keeps beating while the main loop is stuck in a CARLA RPC, so a silent heartbeat
+/// means the bridge is dead, and a stale `last_tick_age_sec` means CARLA doesn't answer.
+///
+/// The watchdog of the same thread also catches a server which answers with the same frame,
+/// e.g. paused in the editor while the bridge runs asynchronously. During a stall, it
+/// publishes an ERROR on `*/diagnostics` every beat, and an OK once the loop recovers.
+///
+/// It also watches the routers and the peers of the zenoh session, the same way: once the
+/// session lost all of them for `disconnect_timeout`, `zenoh_lost` tells the vehicle bridges
+/// to stop, until the session reconnects by itself.
+pub struct Heartbeat {
+    state: Arc<LoopState>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Heartbeat {
+    pub fn start(
+        z_session: Arc<Session>,
+        topic_map: &TopicMap,
+        watchdog: WatchdogConfig,
+        endianness: Endianness,
+    ) -> Result<Heartbeat> {
+        let publisher =
+            topics::declare_publisher(&z_session, &topic_map.key("*", topics::HEARTBEAT))?;
+        let diagnostics_publisher =
+            topics::declare_publisher(&z_session, &topic_map.key("*", topics::DIAGNOSTICS))?;
+        let state = Arc::new(LoopState::default());
+        let stop = Arc::new(AtomicBool::new(false));
+        let cloned_state = state.clone();
+        let cloned_stop = stop.clone();
+        let start = Instant::now();
+        let thread = thread::spawn(move || {
+            let mut seq = 0;
+            let mut stalled = false;
+            let mut ever_connected = false;
+            let mut disconnected_since: Option<Instant> = None;
+            while !cloned_stop.load(Ordering::Relaxed) {
+                let stall_age = cloned_state.stall_age();
+                let was_stalled = stalled;
+                stalled = stall_age.map_or(false, |age| age > watchdog.stall_timeout);
+                if stalled && !was_stalled {
+                    error!(
+                        "The simulation made no progress for {} s, CARLA may hang",
+                        watchdog.stall_timeout
+                    );
+                } else if was_stalled && !stalled {
+                    info!("The simulation makes progress again");
+                }
+                if stalled || was_stalled {
+                    let diagnostics = stall_diagnostics(stalled, stall_age, &cloned_state);
+                    match cdr_utils::serialize(&diagnostics, endianness) {
+                        Ok(payload) => {
+                            if let Err(e) = diagnostics_publisher.put(payload).res() {
+                                warn!("Failed to publish the stall diagnostics: {e}");
+                            }
+                        }
+                        Err(e) => warn!("Failed to encode the stall diagnostics: {e}"),
+                    }
+                }
+                let connected = is_connected(&z_session);
+                ever_connected |= connected;
+                if connected {
+                    disconnected_since = None;
+                } else if ever_connected && disconnected_since.is_none() {
+                    disconnected_since = Some(Instant::now());
+                }
+                let was_lost = cloned_state.zenoh_lost.load(Ordering::Relaxed);
+                let lost = disconnected_since.map_or(false, |since| {
+                    since.elapsed().as_secs_f64() > watchdog.disconnect_timeout
+                });
+                cloned_state.zenoh_lost.store(lost, Ordering::Relaxed);
+                if lost && !was_lost {
+                    error!(
+                        "The zenoh session has had no router nor peer for {} s, stop the vehicles",
+                        watchdog.disconnect_timeout
+                    );
+                } else if was_lost && !lost {
+                    info!("The zenoh session reconnected, resume the vehicles");
+                }
+                if lost || was_lost {
+                    let diagnostics = connectivity_diagnostics(lost);
+                    match cdr_utils::serialize(&diagnostics, endianness) {
+                        Ok(payload) => {
+                            if let Err(e) = diagnostics_publisher.put(payload).res() {
+                                warn!("Failed to publish the zenoh diagnostics: {e}");
+                            }
+                        }
+                        Err(e) => warn!("Failed to encode the zenoh diagnostics: {e}"),
+                    }
+                }
+                let last_tick_age_sec = cloned_state
+                    .last_tick
+                    .lock()
+                    .unwrap()
+                    .map(|last| last.elapsed().as_secs_f64());
+                let recording_file = cloned_state.recording_file.lock().unwrap().clone();
+                let report = HeartbeatReport {
+                    seq,
+                    uptime_sec: start.elapsed().as_secs_f64(),
+                    carla_connected: last_tick_age_sec.map_or(false, |age| age < CARLA_TIMEOUT_SEC),
+                    last_tick_age_sec,
+                    stalled,
+                    zenoh_connected: !lost,
+                    vehicle_bridges: cloned_state.vehicle_bridges.load(Ordering::Relaxed),
+                    walker_bridges: cloned_state.walker_bridges.load(Ordering::Relaxed),
+                    sensor_bridges: cloned_state.sensor_bridges.load(Ordering::Relaxed),
+                    recording: recording_file.is_some(),
+                    recording_file,
+                    version: env!("CARGO_PKG_VERSION"),
+                };
+                match serde_json::to_vec(&report) {
+                    Ok(payload) => {
+                        if let Err(e) = publisher.put(payload).res() {
+                            warn!("Failed to publish the heartbeat: {e}");
+                        }
+                    }
+                    Err(e) => warn!("Failed to encode the heartbeat: {e}"),
+                }
+                seq += 1;
+                if stalled && watchdog.exit_on_stall {
+                    error!("Exit on the stall, for a restart to reconnect to CARLA");
+                    std::process::exit(STALL_EXIT_CODE);
+                }
+                thread::sleep(HEARTBEAT_INTERVAL);
+            }
+        });
+        Ok(Heartbeat {
+            state,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// Called by the main loop after every tick, with the frame of the world
+    pub fn update(
+        &self,
+        vehicle_bridges: usize,
+        walker_bridges: usize,
+        sensor_bridges: usize,
+        frame: u64,
+    ) {
+        self.state
+            .vehicle_bridges
+            .store(vehicle_bridges, Ordering::Relaxed);
+        self.state
+            .walker_bridges
+            .store(walker_bridges, Ordering::Relaxed);
+        self.state
+            .sensor_bridges
+            .store(sensor_bridges, Ordering::Relaxed);
+        let now = Instant::now();
+        *self.state.last_tick.lock().unwrap() = Some(now);
+        let mut last_frame = self.state.last_frame.lock().unwrap();
+        if last_frame.map_or(true, |(last, _)| last != frame) {
+            *last_frame = Some((frame, now));
+        }
+    }
+
+    /// Pause the watchdog around what blocks the main loop on purpose. Its progress starts
+    /// over on resume, so the time of the pause doesn't count as a stall.
+    pub fn pause_watchdog(&self, paused: bool) {
+        self.state.watchdog_paused.store(paused, Ordering::Relaxed);
+        if !paused {
+            let now = Instant::now();
+            if let Some(last_tick) = self.state.last_tick.lock().unwrap().as_mut() {
+                *last_tick = now;
+            }
+            if let Some((_, changed)) = self.state.last_frame.lock().unwrap().as_mut() {
+                *changed = now;
+            }
+        }
+    }
+
+    /// The zenoh session lost its routers and peers for `disconnect_timeout`
+    pub fn zenoh_lost(&self) -> bool {
+        self.state.zenoh_lost.load(Ordering::Relaxed)
+    }
+
+    /// The file the CARLA recorder writes, none while it's off
+    pub fn set_recording(&self, file: Option<&str>) {
+        let mut recording_file = self.state.recording_file.lock().unwrap();
+        if recording_file.as_deref() != file {
+            *recording_file = file.map(str::to_string);
+        }
+    }
+}
+
+/// The ERROR of a stall, or the OK after it, stamped with the wall clock since the
+/// simulation time stands still
+fn stall_diagnostics(stalled: bool, stall_age: Option<f64>, state: &LoopState) -> DiagnosticArray {
+    let (level, message) = if stalled {
+        (diagnostic_status::ERROR, "The simulation stalled")
+    } else {
+        (diagnostic_status::OK, "The simulation makes progress")
+    };
+    let mut values = Vec::new();
+    if let Some(age) = stall_age {
+        values.push(KeyValue {
+            key: String::from("stall_age_sec"),
+            value: format!("{age:.1}"),
+        });
+    }
+    if let Some((frame, _)) = *state.last_frame.lock().unwrap() {
+        values.push(KeyValue {
+            key: String::from("last_frame"),
+            value: frame.to_string(),
+        });
+    }
+    DiagnosticArray {
+        header: utils::ros_header(TimeStamp::now(), ""),
+        status: vec![DiagnosticStatus {
+            level,
+            name: String::from("carla_bridge: simulation"),
+            message: message.to_string(),
+            hardware_id: String::from("carla"),
+            values,
+        }],
+    }
+}
+
+/// A session reaching a router or a peer. A put on the session itself would succeed even
+/// without them, so it can't tell.
+fn is_connected(z_session: &Session) -> bool {
+    let info = z_session.info();
+    info.routers_zid().res().next().is_some() || info.peers_zid().res().next().is_some()
+}
+
+/// The ERROR of a lost zenoh session, or the OK after it. Published on the session itself,
+/// the ERROR reaches the local subscribers at least.
+fn connectivity_diagnostics(lost: bool) -> DiagnosticArray {
+    let (level, message) = if lost {
+        (
+            diagnostic_status::ERROR,
+            "The zenoh session lost its connectivity",
+        )
+    } else {
+        (diagnostic_status::OK, "The zenoh session is connected")
+    };
+    DiagnosticArray {
+        header: utils::ros_header(TimeStamp::now(), ""),
+        status: vec![DiagnosticStatus {
+            level,
+            name: String::from("carla_bridge: zenoh"),
+            message: message.to_string(),
+            hardware_id: String::from("zenoh"),
+            values: Vec::new(),
+        }],
+    }
+}
+
+impl Drop for Heartbeat {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
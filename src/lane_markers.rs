@@ -0,0 +1,228 @@
+use crate::{
+    cdr_utils::{self, Endianness},
+    error::Result,
+    time::TimeStamp,
+    topics::{self, TopicMap},
+    utils,
+};
+use carla::client::Map;
+use log::info;
+use serde_derive::Serialize;
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use zenoh::{prelude::sync::*, publication::Publisher};
+use zenoh_ros_type::{builtin_interfaces::Time, std_msgs::Header};
+
+/// How often the markers are published again for the late joiners (in wall-clock seconds)
+const REPUBLISH_INTERVAL_SEC: f64 = 5.0;
+/// visualization_msgs/msg/Marker LINE_STRIP
+const LINE_STRIP: i32 = 4;
+/// visualization_msgs/msg/Marker ADD
+const ADD: i32 = 0;
+/// Width of the lines (m)
+const LINE_WIDTH: f64 = 0.2;
+
+/* The visualization_msgs of ROS 2 Humble, which zenoh-ros-type doesn't provide */
+#[derive(Serialize)]
+struct Point {
+    x: f64,
+    y: f64,
+    z: f64,
+}
+
+#[derive(Serialize, Clone, Copy)]
+struct ColorRgba {
+    r: f32,
+    g: f32,
+    b: f32,
+    a: f32,
+}
+
+#[derive(Serialize)]
+struct Pose {
+    position: Point,
+    /// x, y, z, w
+    orientation: [f64; 4],
+}
+
+#[derive(Serialize)]
+struct CompressedImage {
+    header: Header,
+    format: String,
+    data: Vec<u8>,
+}
+
+#[derive(Serialize)]
+struct UvCoordinate {
+    u: f32,
+    v: f32,
+}
+
+#[derive(Serialize)]
+struct MeshFile {
+    filename: String,
+    data: Vec<u8>,
+}
+
+#[derive(Serialize)]
+struct Marker {
+    header: Header,
+    ns: String,
+    id: i32,
+    marker_type: i32,
+    action: i32,
+    pose: Pose,
+    scale: [f64; 3],
+    color: ColorRgba,
+    lifetime: Time,
+    frame_locked: bool,
+    points: Vec<Point>,
+    colors: Vec<ColorRgba>,
+    texture_resource: String,
+    texture: CompressedImage,
+    uv_coordinates: Vec<UvCoordinate>,
+    text: String,
+    mesh_resource: String,
+    mesh_file: MeshFile,
+    mesh_use_embedded_materials: bool,
+}
+
+#[derive(Serialize)]
+struct MarkerArray {
+    markers: Vec<Marker>,
+}
+
+/// Publish the lanes of the CARLA map as line strips, to compare them with the lanelet map in RViz.
+///
+/// Generating the waypoints is expensive on the big towns, so it's done once at the start,
+/// and the serialized markers are published again now and then for the late joiners.
+pub struct LaneMarkers<'a> {
+    publisher: Publisher<'a>,
+    payload: Vec<u8>,
+    last_publish: Option<Instant>,
+}
+
+impl<'a> LaneMarkers<'a> {
+    pub fn new(
+        z_session: Arc<Session>,
+        map: &Map,
+        resolution: f64,
+        endianness: Endianness,
+        topic_map: &TopicMap,
+    ) -> Result<LaneMarkers<'a>> {
+        let publisher =
+            topics::declare_publisher(&z_session, &topic_map.key("*", topics::CARLA_LANES))?;
+
+        // The waypoints aren't ordered, so sort them by their distance along each lane
+        let mut lanes: BTreeMap<(u32, u32, i32), Vec<(f64, Point)>> = BTreeMap::new();
+        for waypoint in map.generate_waypoints(resolution).iter() {
+            let location = waypoint.transform().translation;
+            let [x, y, z] = utils::carla_to_ros_position(location.x, location.y, location.z);
+            let point = Point { x, y, z };
+            lanes
+                .entry((
+                    waypoint.road_id(),
+                    waypoint.section_id(),
+                    waypoint.lane_id(),
+                ))
+                .or_default()
+                .push((waypoint.s(), point));
+        }
+        let mut header = utils::create_ros_header(None)?;
+        header.frame_id = String::from("map");
+        let markers: Vec<_> = lanes
+            .into_iter()
+            .enumerate()
+            .map(|(id, ((road_id, _, _), mut points))| {
+                points.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+                lane_marker(&header, id as i32, road_id, points)
+            })
+            .collect();
+        info!(
+            "Generated {} lane markers from the CARLA map",
+            markers.len()
+        );
+        let payload = cdr_utils::serialize(&MarkerArray { markers }, endianness)?;
+        Ok(LaneMarkers {
+            publisher,
+            payload,
+            last_publish: None,
+        })
+    }
+
+    /// Publish the markers, if the last time is older than the interval
+    pub fn update(&mut self) -> Result<()> {
+        let now = Instant::now();
+        let interval = Duration::from_secs_f64(REPUBLISH_INTERVAL_SEC);
+        if matches!(self.last_publish, Some(last) if now - last < interval) {
+            return Ok(());
+        }
+        self.last_publish = Some(now);
+        self.publisher.put(self.payload.clone()).res()?;
+        Ok(())
+    }
+}
+
+fn lane_marker(header: &Header, id: i32, road_id: u32, points: Vec<(f64, Point)>) -> Marker {
+    let color = road_color(road_id);
+    Marker {
+        header: Header {
+            stamp: TimeStamp::from_msg(&header.stamp).to_msg(),
+            frame_id: header.frame_id.clone(),
+        },
+        ns: String::from("carla_lanes"),
+        id,
+        marker_type: LINE_STRIP,
+        action: ADD,
+        pose: Pose {
+            position: Point {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            },
+            orientation: [0.0, 0.0, 0.0, 1.0],
+        },
+        scale: [LINE_WIDTH, 0.0, 0.0],
+        color,
+        // Zero means forever
+        lifetime: TimeStamp::ZERO.to_msg(),
+        frame_locked: false,
+        points: points.into_iter().map(|(_, point)| point).collect(),
+        colors: Vec::new(),
+        texture_resource: String::new(),
+        texture: CompressedImage {
+            header: Header {
+                stamp: TimeStamp::ZERO.to_msg(),
+                frame_id: String::new(),
+            },
+            format: String::new(),
+            data: Vec::new(),
+        },
+        uv_coordinates: Vec::new(),
+        text: String::new(),
+        mesh_resource: String::new(),
+        mesh_file: MeshFile {
+            filename: String::new(),
+            data: Vec::new(),
+        },
+        mesh_use_embedded_materials: false,
+    }
+}
+
+/// A bright color of its own for each road, spread over the hues by the golden ratio
+fn road_color(road_id: u32) -> ColorRgba {
+    let hue = (road_id as f32 * 0.618_034).fract() * 6.0;
+    let x = 1.0 - (hue % 2.0 - 1.0).abs();
+    let (r, g, b) = match hue as u32 {
+        0 => (1.0, x, 0.0),
+        1 => (x, 1.0, 0.0),
+        2 => (0.0, 1.0, x),
+        3 => (0.0, x, 1.0),
+        4 => (x, 0.0, 1.0),
+        _ => (1.0, 0.0, x),
+    };
+    ColorRgba { r, g, b, a: 1.0 }
+}
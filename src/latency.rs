@@ -0,0 +1,43 @@
+//! The artificial latencies of `command_delay` and `status_delay` in `[vehicle]`, like the
+//! actuation and the sensing of a real vehicle, and of the pose of `[localization]`. They
+//! count in simulation time, so a run faster than real time sees the same delays.
+use std::collections::VecDeque;
+
+/// Hold the values for a fixed delay before they take effect, the oldest first
+#[derive(Debug)]
+pub struct DelayQueue<T> {
+    /// Simulation seconds
+    delay: f64,
+    queue: VecDeque<(f64, T)>,
+}
+
+impl<T> DelayQueue<T> {
+    pub fn new(delay: f64) -> DelayQueue<T> {
+        DelayQueue {
+            delay,
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Queue a value received at the simulation time
+    pub fn push(&mut self, timestamp: f64, value: T) {
+        self.queue.push_back((timestamp, value));
+    }
+
+    /// Take the values whose delay elapsed at the simulation time, the oldest first
+    pub fn pop_due(&mut self, timestamp: f64) -> Vec<T> {
+        let mut due = Vec::new();
+        while matches!(self.queue.front(), Some((received, _)) if received + self.delay <= timestamp)
+        {
+            if let Some((_, value)) = self.queue.pop_front() {
+                due.push(value);
+            }
+        }
+        due
+    }
+
+    /// Drop the queued values, e.g. on a reset
+    pub fn clear(&mut self) {
+        self.queue.clear();
+    }
+}
@@ -0,0 +1,174 @@
+//! Bridge the vehicles and sensors of CARLA to Autoware over zenoh.
+//!
+//! [`BridgeRuntime`] owns the main loop: it follows the actors of the CARLA world, or drives
+//! a kinematic vehicle, and steps a bridge per actor. The `zenoh_carla_bridge` binary only
+//! turns the command line and the configuration file into a [`RuntimeConfig`], so a
+//! scenario runner can embed the bridge the same way:
+//!
+//! ```no_run
+//! use zenoh_carla_bridge::{BridgeRuntime, RuntimeConfig};
+//!
+//! let mut config = RuntimeConfig::default();
+//! config.loop_rate = 10.0;
+//! config.asynchronous = true;
+//! config.bridge.vehicle.steering_ratio = 15.0;
+//! BridgeRuntime::new(config)?.run()?;
+//! # Ok::<(), zenoh_carla_bridge::Error>(())
+//! ```
+//!
+//! The items re-exported here are the whole embedding API, the modules are internal. The
+//! `cli` module only serves the binary and follows its needs, and the `testing` module,
+//! behind the `test-support` feature, serves the integration tests, the benchmarks and the
+//! fuzz targets.
+pub(crate) mod actor_query;
+pub(crate) mod autoware_type;
+pub(crate) mod autoware_version;
+pub(crate) mod battery;
+pub(crate) mod bridge;
+pub(crate) mod bridge_events;
+pub(crate) mod bridge_info;
+pub(crate) mod bridge_query;
+pub(crate) mod carla_stats;
+pub(crate) mod cdr_utils;
+pub(crate) mod clock;
+pub(crate) mod collision;
+pub(crate) mod command_log;
+pub(crate) mod command_subscriber;
+pub(crate) mod compression;
+pub(crate) mod config;
+pub(crate) mod config_file;
+pub(crate) mod control;
+pub(crate) mod debug_draw;
+pub(crate) mod debug_json;
+pub(crate) mod depth;
+pub(crate) mod doors;
+pub(crate) mod echo;
+pub(crate) mod error;
+pub(crate) mod footprint;
+pub(crate) mod frame_queue;
+#[cfg(any(test, feature = "test-support"))]
+pub(crate) mod fuzz_targets;
+pub(crate) mod geo_reference;
+pub(crate) mod heartbeat;
+pub(crate) mod lane_markers;
+pub(crate) mod latency;
+pub(crate) mod lights;
+pub(crate) mod load_world;
+pub(crate) mod longitudinal;
+pub(crate) mod mcap_recorder;
+pub(crate) mod namespace;
+pub(crate) mod pedal_map;
+pub(crate) mod physics;
+pub(crate) mod radar;
+pub(crate) mod recorder;
+pub(crate) mod replay;
+pub(crate) mod reset;
+pub(crate) mod role_overrides;
+pub(crate) mod ros_types;
+pub(crate) mod runtime;
+pub(crate) mod scheduler;
+pub(crate) mod sensor_frames;
+pub(crate) mod sensor_noise;
+pub(crate) mod sensor_pool;
+pub(crate) mod service;
+pub(crate) mod signal_log;
+pub(crate) mod sim_stats;
+pub(crate) mod slip;
+pub(crate) mod spawn_points;
+pub(crate) mod stats;
+pub(crate) mod status_publisher;
+pub(crate) mod time;
+pub(crate) mod topic_cache;
+pub(crate) mod topics;
+pub(crate) mod types;
+pub(crate) mod utils;
+
+pub use config::{BridgeConfig, VehicleConfig};
+pub use config_file::FileConfig;
+pub use error::{Error, Result};
+pub use runtime::{BridgeRuntime, RuntimeConfig};
+
+/// What the `zenoh_carla_bridge` binary needs besides the embedding API
+#[doc(hidden)]
+pub mod cli {
+    pub use crate::{
+        autoware_version::AutowareVersion,
+        cdr_utils::Endianness,
+        config_file::{TopicToggles, VehicleSection, DEFAULT_CONFIG},
+        debug_draw::DebugDrawConfig,
+        mcap_recorder::McapConfig,
+        role_overrides::RoleOverrides,
+        topics::TopicMap,
+    };
+
+    pub mod actor_query {
+        pub use crate::actor_query::print_actors;
+    }
+
+    pub mod compression {
+        pub use crate::compression::{benchmark, inspect};
+    }
+
+    pub mod config {
+        pub use crate::config::{
+            parse_positive, parse_steering_ratio, Backend, ControlPath, PitchCompensation,
+            SteeringConvention,
+        };
+    }
+
+    pub mod echo {
+        pub use crate::echo::run;
+    }
+
+    pub mod role_overrides {
+        pub use crate::role_overrides::OVERRIDES_DIR;
+    }
+
+    pub mod runtime {
+        pub use crate::runtime::connect_carla;
+    }
+
+    pub mod topics {
+        pub use crate::topics::LATCHED_COMMAND_TOPICS;
+    }
+}
+
+/// A vehicle bridge over a scripted vehicle, and the codecs, without a simulator
+#[cfg(any(test, feature = "test-support"))]
+pub mod testing {
+    pub use crate::{
+        bridge::{
+            actor_bridge::{ActorBridge, TickSnapshot},
+            mock_vehicle::MockVehicle,
+            vehicle_bridge::{VehicleBridge, VehicleBridgeBuilder},
+        },
+        namespace::NamespaceRegistry,
+        time::TimeStamp,
+    };
+
+    pub mod cdr_utils {
+        pub use crate::cdr_utils::{deserialize, serialize, Endianness};
+    }
+
+    pub mod fuzz {
+        pub use crate::fuzz_targets::{run, TARGETS};
+    }
+
+    pub mod topics {
+        pub use crate::topics::{
+            KeyStyle, TopicMap, CONTROL_CMD, RADAR_POINTCLOUD, VELOCITY_STATUS,
+        };
+    }
+
+    /// The messages the bridge subscribes to
+    pub mod messages {
+        pub use crate::{
+            autoware_type::{
+                ActuationCommandStamped, AutowareState, DoorCommand, Engage, HeadlightsCommand,
+                MrmState, PoseStamped, SetDoorCommand, Trajectory, Twist,
+            },
+            autoware_version::AutowareVersion,
+        };
+        pub use zenoh_ros_type::autoware_auto_vehicle_msgs::GearCommand;
+    }
+}
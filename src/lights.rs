@@ -0,0 +1,109 @@
+//! The light state of a vehicle read back from CARLA, and how it compares with the commands
+use crate::{autoware_type::headlights_report, bridge::vehicle_actor::VehicleActor};
+use carla::rpc::VehicleLightState;
+use zenoh_ros_type::autoware_auto_vehicle_msgs::{hazard_lights_report, turn_indicators_report};
+
+/// A difference from the commanded lights lasting longer than this is reported (simulation seconds)
+const MISMATCH_SEC: f64 = 1.0;
+
+/// The light state of the last read, so the reports of every tick don't cost an RPC each.
+///
+/// The commands of the bridge update the cached state right away, the next read tells
+/// whether they stuck or something else, e.g. a script or a weather preset, changed them.
+pub struct LightMonitor {
+    interval_sec: f64,
+    state: VehicleLightState,
+    last_read: Option<f64>,
+    /// The bits the bridge commands, and their commanded values
+    commanded_mask: VehicleLightState,
+    commanded: VehicleLightState,
+    mismatch_since: Option<f64>,
+}
+
+impl LightMonitor {
+    pub fn new(rate_hz: f64) -> LightMonitor {
+        LightMonitor {
+            interval_sec: 1.0 / rate_hz,
+            state: VehicleLightState::empty(),
+            last_read: None,
+            commanded_mask: VehicleLightState::empty(),
+            commanded: VehicleLightState::empty(),
+            mismatch_since: None,
+        }
+    }
+
+    pub fn state(&self) -> VehicleLightState {
+        self.state
+    }
+
+    /// Read the state again from CARLA, once per interval of the simulation time
+    pub fn refresh(&mut self, actor: &dyn VehicleActor, timestamp: f64) {
+        if matches!(self.last_read, Some(last) if timestamp - last < self.interval_sec) {
+            return;
+        }
+        self.last_read = Some(timestamp);
+        self.state = actor.light_state();
+        if self.differing().is_empty() {
+            self.mismatch_since = None;
+        } else {
+            self.mismatch_since.get_or_insert(timestamp);
+        }
+    }
+
+    /// Command the `mask` bits to `lights`, keeping the others (e.g. indicators).
+    ///
+    /// Returns the state to apply, none if the cached state already has it.
+    pub fn command(
+        &mut self,
+        mask: VehicleLightState,
+        lights: VehicleLightState,
+    ) -> Option<VehicleLightState> {
+        self.commanded_mask |= mask;
+        self.commanded = (self.commanded - mask) | (lights & mask);
+        let updated = (self.state - mask) | (lights & mask);
+        (updated != self.state).then(|| {
+            self.state = updated;
+            updated
+        })
+    }
+
+    /// The commanded bits which differ from CARLA since longer than a second
+    pub fn mismatch(&self, timestamp: f64) -> Option<VehicleLightState> {
+        self.mismatch_since
+            .filter(|since| timestamp - since > MISMATCH_SEC)
+            .map(|_| self.differing())
+    }
+
+    fn differing(&self) -> VehicleLightState {
+        (self.state ^ self.commanded) & self.commanded_mask
+    }
+}
+
+/// Both blinkers are the hazard lights, not a turn
+pub fn turn_indicators(state: VehicleLightState) -> u8 {
+    let left = state.contains(VehicleLightState::LEFT_BLINKER);
+    let right = state.contains(VehicleLightState::RIGHT_BLINKER);
+    match (left, right) {
+        (true, false) => turn_indicators_report::ENABLE_LEFT,
+        (false, true) => turn_indicators_report::ENABLE_RIGHT,
+        _ => turn_indicators_report::DISABLE,
+    }
+}
+
+pub fn hazard_lights(state: VehicleLightState) -> u8 {
+    if state.contains(VehicleLightState::LEFT_BLINKER | VehicleLightState::RIGHT_BLINKER) {
+        hazard_lights_report::ENABLE
+    } else {
+        hazard_lights_report::DISABLE
+    }
+}
+
+pub fn headlights(state: VehicleLightState) -> u8 {
+    if state.contains(VehicleLightState::HIGH_BEAM) {
+        headlights_report::ENABLE_HIGH
+    } else if state.contains(VehicleLightState::LOW_BEAM) {
+        headlights_report::ENABLE_LOW
+    } else {
+        headlights_report::DISABLE
+    }
+}
@@ -0,0 +1,149 @@
+//! `*/bridge/load_world`: switch the CARLA town between the scenarios of a batch
+use crate::{
+    error::Result,
+    heartbeat::Heartbeat,
+    topics::{self, TopicMap},
+};
+use carla::client::Client;
+use log::{info, warn};
+use serde_derive::Serialize;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use zenoh::{prelude::sync::*, queryable::Query, queryable::Queryable};
+
+/// Loading a town takes much longer than the other RPCs
+const LOAD_WORLD_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// The reply of `*/bridge/load_world`
+#[derive(Debug, Serialize)]
+pub struct LoadWorldReply {
+    pub map_name: String,
+}
+
+/// Keep the query on `*/bridge/load_world` for the main loop, which owns the CARLA client.
+///
+/// The payload is the town as plain text, e.g. `Town05` or `/Game/Carla/Maps/Town05`. The
+/// reply waits until the bridges of the old world are gone and the settings are applied to
+/// the new one, while the heartbeat keeps beating from its thread. A second query is
+/// rejected until the first one is replied.
+pub struct LoadWorldQueryable<'a> {
+    pending: Arc<Mutex<Option<Query>>>,
+    /// From the arrival of a query until its reply
+    busy: Arc<AtomicBool>,
+    /// The query whose world was loaded, replied by `finish`
+    loading: Option<Query>,
+    _queryable: Queryable<'a, ()>,
+}
+
+impl<'a> LoadWorldQueryable<'a> {
+    /// A dry run rejects every query, since it doesn't change the world
+    pub fn new(
+        z_session: Arc<Session>,
+        topic_map: &TopicMap,
+        dry_run: bool,
+    ) -> Result<LoadWorldQueryable<'a>> {
+        let pending = Arc::new(Mutex::new(None));
+        let busy = Arc::new(AtomicBool::new(false));
+        let cloned_pending = pending.clone();
+        let cloned_busy = busy.clone();
+        let queryable = z_session
+            .declare_queryable(topic_map.key("*", topics::LOAD_WORLD))
+            .callback(move |query| {
+                let rejection = if dry_run {
+                    "The bridge runs with --dry-run, which doesn't load a world"
+                } else if cloned_busy.swap(true, Ordering::Relaxed) {
+                    "Another world is being loaded"
+                } else {
+                    *cloned_pending.lock().unwrap() = Some(query);
+                    return;
+                };
+                warn!("Rejected a load_world query: {rejection}");
+                if let Err(e) = query.reply(Err(rejection.into())).res() {
+                    warn!("Failed to reply to the load_world query: {e}");
+                }
+            })
+            .res()?;
+        Ok(LoadWorldQueryable {
+            pending,
+            busy,
+            loading: None,
+            _queryable: queryable,
+        })
+    }
+
+    /// Load the town of the query received since the last call, true if a world was loaded.
+    ///
+    /// It blocks until the server runs the new world, with the stall watchdog paused, the
+    /// main loop then detects the new world and rebuilds the bridges like after a reload by
+    /// another client.
+    pub fn load(&mut self, client: &mut Client, heartbeat: &Heartbeat) -> bool {
+        let Some(query) = self.pending.lock().unwrap().take() else {
+            return false;
+        };
+        let town = query
+            .value()
+            .map(|value| String::from_utf8_lossy(&value.payload.contiguous()).into_owned())
+            .unwrap_or_default();
+        match find_map(client, town.trim()) {
+            Ok(map) => {
+                info!("Loading the CARLA world {map}");
+                let timeout = client.timeout();
+                client.set_timeout(LOAD_WORLD_TIMEOUT);
+                heartbeat.pause_watchdog(true);
+                client.load_world(&map);
+                heartbeat.pause_watchdog(false);
+                client.set_timeout(timeout);
+                self.loading = Some(query);
+                true
+            }
+            Err(e) => {
+                warn!("{e}");
+                self.reply(query, Err(e));
+                false
+            }
+        }
+    }
+
+    /// Reply to the query of the loaded world, once the bridges are rebuilt
+    pub fn finish(&mut self, map_name: &str) {
+        if let Some(query) = self.loading.take() {
+            info!("Loaded the CARLA world {map_name}");
+            let reply = LoadWorldReply {
+                map_name: map_name.to_string(),
+            };
+            self.reply(query, serde_json::to_vec(&reply).map_err(|e| e.to_string()));
+        }
+    }
+
+    fn reply(&self, query: Query, payload: std::result::Result<Vec<u8>, String>) {
+        let result = payload
+            .map(|payload| Sample::new(query.key_expr().clone(), payload))
+            .map_err(Into::into);
+        if let Err(e) = query.reply(result).res() {
+            warn!("Failed to reply to the load_world query: {e}");
+        }
+        self.busy.store(false, Ordering::Relaxed);
+    }
+}
+
+/// The map of the server named by its path or by its last component, e.g. `Town05`
+fn find_map(client: &Client, town: &str) -> std::result::Result<String, String> {
+    if town.is_empty() {
+        return Err(String::from("The load_world query names no town"));
+    }
+    let maps = client.available_maps();
+    maps.iter()
+        .find(|map| *map == town || map.rsplit('/').next() == Some(town))
+        .cloned()
+        .ok_or_else(|| {
+            format!(
+                "The CARLA server has no map '{town}', but {}",
+                maps.join(", ")
+            )
+        })
+}
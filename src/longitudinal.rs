@@ -0,0 +1,351 @@
+//! The longitudinal controllers of the Ackermann control path, `controller` in `[vehicle]`
+use crate::{config::ControllerKind, control, pedal_map::PedalMaps};
+use arc_swap::ArcSwap;
+#[cfg(feature = "ackermann_settings")]
+use carla::rpc::AckermannControllerSettings;
+use carla::rpc::{VehicleAckermannControl, VehicleControl};
+use std::sync::Arc;
+
+const GRAVITY: f32 = 9.81;
+/// Below this speed a stop request holds the brake (m/s)
+const HOLD_SPEED: f32 = 0.1;
+
+/// Gains and limits of the simple PID, the `pid_*` keys of `[vehicle]`
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct PidConfig {
+    /// Acceleration per speed error (1/s).
+    pub kp: f32,
+    /// Acceleration per integrated speed error (1/s^2).
+    pub ki: f32,
+    /// Acceleration per measured acceleration, damping the response (unitless).
+    pub kd: f32,
+    /// How much of the commanded acceleration is applied directly.
+    pub feedforward: f32,
+    /// Bound of the integral term, against the windup (m/s^2).
+    pub max_integral: f32,
+    /// The acceleration at full throttle (m/s^2).
+    pub max_acceleration: f32,
+    /// The deceleration at full brake, as a positive value (m/s^2).
+    pub max_deceleration: f32,
+}
+
+impl Default for PidConfig {
+    fn default() -> Self {
+        PidConfig {
+            kp: 0.5,
+            ki: 0.1,
+            kd: 0.0,
+            feedforward: 1.0,
+            max_integral: 2.0,
+            max_acceleration: 3.0,
+            max_deceleration: 8.0,
+        }
+    }
+}
+
+/// Gains of the Ackermann controller of the CARLA server, the `ackermann_*` keys of `[vehicle]`.
+///
+/// Only the given ones replace the settings of the server, which keeps the others.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[non_exhaustive]
+pub struct AckermannGains {
+    pub speed_kp: Option<f32>,
+    pub speed_ki: Option<f32>,
+    pub speed_kd: Option<f32>,
+    pub accel_kp: Option<f32>,
+    pub accel_ki: Option<f32>,
+    pub accel_kd: Option<f32>,
+}
+
+impl AckermannGains {
+    pub fn is_empty(&self) -> bool {
+        *self == AckermannGains::default()
+    }
+
+    #[cfg(feature = "ackermann_settings")]
+    pub fn merge_into(&self, settings: &mut AckermannControllerSettings) {
+        let gains = [
+            (&mut settings.speed_kp, self.speed_kp),
+            (&mut settings.speed_ki, self.speed_ki),
+            (&mut settings.speed_kd, self.speed_kd),
+            (&mut settings.accel_kp, self.accel_kp),
+            (&mut settings.accel_ki, self.accel_ki),
+            (&mut settings.accel_kd, self.accel_kd),
+        ];
+        for (setting, gain) in gains {
+            if let Some(gain) = gain {
+                *setting = gain;
+            }
+        }
+    }
+}
+
+/// What the controller is asked to reach, after the gating and limiting of the bridge
+#[derive(Debug, Clone, Copy)]
+pub struct TargetRequest {
+    /// Negative when reversing (m/s)
+    pub speed: f32,
+    /// Along the vehicle x axis (m/s^2)
+    pub acceleration: f32,
+    pub jerk: f32,
+    /// The CARLA steer (-1 to 1)
+    pub steer: f32,
+    pub steer_speed: f32,
+    /// The measured speed along the heading, negative when rolling backwards (m/s)
+    pub current_speed: f32,
+    /// After the pitch compensation, positive when the nose points up (rad)
+    pub pitch: f32,
+}
+
+/// What the bridge applies to the vehicle
+pub enum Output {
+    /// Handed to the controller of the CARLA server
+    Ackermann(VehicleAckermannControl),
+    /// Throttle, brake and steer, like the raw control path
+    Raw(VehicleControl),
+}
+
+pub enum LongitudinalController {
+    /// The Ackermann controller of the CARLA server, a black box to the bridge.
+    CarlaAckermann,
+    SimplePid(SpeedPid),
+}
+
+impl LongitudinalController {
+    pub fn new(
+        kind: ControllerKind,
+        pid: PidConfig,
+        pedal_maps: Option<Arc<ArcSwap<PedalMaps>>>,
+    ) -> LongitudinalController {
+        match kind {
+            ControllerKind::CarlaAckermann => LongitudinalController::CarlaAckermann,
+            ControllerKind::SimplePid => {
+                LongitudinalController::SimplePid(SpeedPid::new(pid, pedal_maps))
+            }
+        }
+    }
+
+    pub fn control(&mut self, request: &TargetRequest, elapsed_sec: f64) -> Output {
+        match self {
+            LongitudinalController::CarlaAckermann => Output::Ackermann(VehicleAckermannControl {
+                steer: request.steer,
+                steer_speed: request.steer_speed,
+                speed: request.speed,
+                acceleration: request.acceleration,
+                jerk: request.jerk,
+            }),
+            LongitudinalController::SimplePid(pid) => {
+                Output::Raw(pid.control(request, elapsed_sec))
+            }
+        }
+    }
+
+    /// Forget the integral and the last speed, e.g. after a gear change
+    pub fn reset(&mut self) {
+        if let LongitudinalController::SimplePid(pid) = self {
+            pid.reset();
+        }
+    }
+}
+
+/// A PID on the speed in the direction of travel, with the commanded acceleration as
+/// feedforward and the slope compensated by the pitch.
+///
+/// The resulting acceleration maps to the throttle or the brake through the calibration
+/// maps of the vehicle, so the PID only corrects what they miss, or linearly without them.
+pub struct SpeedPid {
+    config: PidConfig,
+    /// Replaced as a whole when the files are reloaded
+    pedal_maps: Option<Arc<ArcSwap<PedalMaps>>>,
+    /// The integral term itself, so a gain change doesn't make it jump (m/s^2)
+    integral: f32,
+    last_speed: Option<f32>,
+}
+
+impl SpeedPid {
+    pub fn new(config: PidConfig, pedal_maps: Option<Arc<ArcSwap<PedalMaps>>>) -> SpeedPid {
+        SpeedPid {
+            config,
+            pedal_maps,
+            integral: 0.0,
+            last_speed: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.integral = 0.0;
+        self.last_speed = None;
+    }
+
+    /// The acceleration to apply along the direction of travel (m/s^2)
+    fn acceleration(&mut self, request: &TargetRequest, elapsed_sec: f64) -> f32 {
+        let config = &self.config;
+        let direction = if request.speed < 0.0 { -1.0 } else { 1.0 };
+        let speed = request.current_speed * direction;
+        let error = request.speed.abs() - speed;
+        let dt = elapsed_sec as f32;
+        let valid_dt = dt.is_finite() && dt > 0.0;
+        if valid_dt {
+            self.integral = (self.integral + config.ki * error * dt)
+                .clamp(-config.max_integral, config.max_integral);
+        }
+        // On the measurement, so a step of the target doesn't kick
+        let derivative = match self.last_speed {
+            Some(last) if valid_dt => -config.kd * (speed - last) / dt,
+            _ => 0.0,
+        };
+        self.last_speed = Some(speed);
+        // Uphill in the direction of travel takes more throttle
+        let slope = direction * GRAVITY * request.pitch.sin();
+        config.feedforward * direction * request.acceleration
+            + config.kp * error
+            + self.integral
+            + derivative
+            + slope
+    }
+
+    pub fn control(&mut self, request: &TargetRequest, elapsed_sec: f64) -> VehicleControl {
+        let (reverse, gear) = control::direction_gear(request.speed);
+        let hold = request.speed == 0.0 && request.current_speed.abs() < HOLD_SPEED;
+        let (throttle, brake) = if hold {
+            // Hold the stop without winding up against it
+            self.reset();
+            (0.0, 1.0)
+        } else {
+            let acceleration = self.acceleration(request, elapsed_sec);
+            match &self.pedal_maps {
+                Some(maps) => {
+                    let (throttle, brake) = maps
+                        .load()
+                        .pedals(request.current_speed as f64, acceleration as f64);
+                    (throttle as f32, brake as f32)
+                }
+                None => (
+                    (acceleration / self.config.max_acceleration).clamp(0.0, 1.0),
+                    (-acceleration / self.config.max_deceleration).clamp(0.0, 1.0),
+                ),
+            }
+        };
+        VehicleControl {
+            throttle,
+            steer: request.steer,
+            brake,
+            hand_brake: false,
+            reverse,
+            manual_gear_shift: false,
+            gear,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::{
+        kinematic_vehicle::{KinematicConfig, KinematicVehicle},
+        vehicle_actor::VehicleActor,
+    };
+
+    const DT: f64 = 0.05;
+
+    fn request(speed: f32, current_speed: f32) -> TargetRequest {
+        TargetRequest {
+            speed,
+            acceleration: 0.0,
+            jerk: 0.0,
+            steer: 0.0,
+            steer_speed: 0.0,
+            current_speed,
+            pitch: 0.0,
+        }
+    }
+
+    fn pid() -> SpeedPid {
+        SpeedPid::new(PidConfig::default(), None)
+    }
+
+    #[test]
+    fn the_carla_controller_passes_the_target_through() {
+        let mut controller =
+            LongitudinalController::new(ControllerKind::CarlaAckermann, PidConfig::default(), None);
+        let target = TargetRequest {
+            acceleration: 1.0,
+            steer: 0.2,
+            ..request(5.0, 1.0)
+        };
+        let Output::Ackermann(control) = controller.control(&target, DT) else {
+            panic!("not an Ackermann control");
+        };
+        assert_eq!(control.speed, 5.0);
+        assert_eq!(control.acceleration, 1.0);
+        assert_eq!(control.steer, 0.2);
+    }
+
+    #[test]
+    fn hold_a_stop_without_windup() {
+        let mut pid = pid();
+        // Wind the integral up against a stuck vehicle
+        for _ in 0..300 {
+            pid.control(&request(2.0, 0.0), DT);
+        }
+        assert_eq!(pid.integral, PidConfig::default().max_integral);
+        let control = pid.control(&request(0.0, 0.0), DT);
+        assert_eq!((control.throttle, control.brake), (0.0, 1.0));
+        assert_eq!(pid.integral, 0.0);
+    }
+
+    #[test]
+    fn compensate_the_slope_and_the_direction() {
+        let flat = pid().control(&request(2.0, 2.0), DT);
+        let uphill = pid().control(
+            &TargetRequest {
+                pitch: 0.1,
+                ..request(2.0, 2.0)
+            },
+            DT,
+        );
+        assert_eq!(flat.throttle, 0.0);
+        assert!(uphill.throttle > 0.0);
+        // Backwards below the target magnitude takes throttle in reverse
+        let reverse = pid().control(&request(-2.0, -1.0), DT);
+        assert!(reverse.reverse && reverse.gear == -1);
+        assert!(reverse.throttle > 0.0 && reverse.brake == 0.0);
+        // Faster than the target brakes
+        let braking = pid().control(&request(2.0, 4.0), DT);
+        assert!(braking.throttle == 0.0 && braking.brake > 0.0);
+    }
+
+    /// The speeds of a step from a standstill to `target`, the model driven by the controller
+    fn step_response(kind: ControllerKind, target: f32, duration: f64) -> Vec<f32> {
+        let mut vehicle = KinematicVehicle::new(1, String::from("ego"), KinematicConfig::default());
+        let mut controller = LongitudinalController::new(kind, PidConfig::default(), None);
+        let mut speeds = Vec::new();
+        for _ in 0..(duration / DT) as usize {
+            let current_speed = vehicle.velocity().x;
+            match controller.control(&request(target, current_speed), DT) {
+                Output::Ackermann(control) => vehicle.apply_ackermann_control(&control),
+                Output::Raw(control) => vehicle.apply_control(&control),
+            }
+            vehicle.advance(DT);
+            speeds.push(vehicle.velocity().x);
+        }
+        speeds
+    }
+
+    #[test]
+    fn compare_the_step_responses() {
+        let ackermann = step_response(ControllerKind::CarlaAckermann, 5.0, 30.0);
+        let pid = step_response(ControllerKind::SimplePid, 5.0, 30.0);
+        let max = |speeds: &[f32]| speeds.iter().copied().fold(f32::MIN, f32::max);
+        // The server controller of the model reaches the target at the limit, without overshoot
+        assert!(max(&ackermann) <= 5.0 + 1e-4);
+        assert!((ackermann[40] - 5.0).abs() < 1e-4);
+        // The PID overshoots a little with the default gains, and settles
+        assert!(max(&pid) < 6.0, "overshoot {}", max(&pid));
+        assert!((pid.last().unwrap() - 5.0).abs() < 0.1);
+        // It is slower to get there
+        let rise = |speeds: &[f32]| speeds.iter().position(|speed| *speed >= 4.5).unwrap();
+        assert!(rise(&ackermann) < rise(&pid));
+    }
+}
@@ -1,137 +1,480 @@
-mod bridge;
-mod clock;
-mod error;
-mod types;
-mod utils;
-
 use anyhow::Result;
-use bridge::actor_bridge::ActorBridge;
-use carla::{client::Client, prelude::*, rpc::ActorId};
-use clap::Parser;
-use clock::SimulatorClock;
-use error::Error;
-use log::{debug, info};
+use clap::{ArgAction, Parser, Subcommand};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::HashMap,
+    path::{Path, PathBuf},
     sync::Arc,
-    thread,
-    time::{Duration, Instant},
+    time::Duration,
 };
 use zenoh::prelude::sync::*;
+use zenoh_carla_bridge::{
+    cli::{
+        actor_query, compression,
+        config::{self, Backend, ControlPath, PitchCompensation, SteeringConvention},
+        echo, role_overrides, runtime, topics, AutowareVersion, DebugDrawConfig, Endianness,
+        McapConfig, RoleOverrides, TopicMap, VehicleSection, DEFAULT_CONFIG,
+    },
+    BridgeConfig, BridgeRuntime, Error, FileConfig, RuntimeConfig, VehicleConfig,
+};
 
-/// Command line options
+/// Command line options, which override the configuration file
 #[derive(Debug, Clone, Parser)]
 struct Opts {
-    /// Carla simulator address.
-    #[clap(long, default_value = "127.0.0.1")]
-    pub carla_address: String,
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+
+    /// The TOML configuration file.
+    #[clap(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Print the default configuration file and exit.
+    #[clap(long)]
+    pub dump_default_config: bool,
+
+    /// Print the topics after remapping and exit.
+    #[clap(long)]
+    pub print_topics: bool,
+
+    /// Compress a synthetic 64-channel point cloud with each setting of `lidar_compression`
+    /// and exit.
+    #[clap(long)]
+    pub bench_compression: bool,
+
+    /// Decode the compressed point clouds of the key and log their codec and size, instead
+    /// of bridging, e.g. `--inspect-compressed 'v1/rt/carla_pointcloud/compressed'`.
+    #[clap(long)]
+    pub inspect_compressed: Option<String>,
+
+    /// Debug: also publish each outgoing message of the matching keys as JSON on
+    /// `<key>/json`, for `z_sub`. Without a key expression, every key, which doubles the
+    /// serialization work. Repeat for several patterns, e.g. `--debug-json '*/rt/vehicle/**'`.
+    #[clap(long, value_name = "KEY_EXPR", num_args = 0..=1, default_missing_value = "**")]
+    pub debug_json: Vec<String>,
+
+    /// Run the whole pipeline without actuating the vehicles or changing the CARLA world,
+    /// e.g. against a shared server. The bridge follows the ticks of the server.
+    #[clap(long)]
+    pub dry_run: bool,
 
-    /// Carla simulator port.
-    #[clap(long, default_value = "2000")]
-    pub carla_port: u16,
+    /// Replay a file of the CARLA recorder on the server and only observe it: the vehicles
+    /// are bridged but never actuated. The server resolves a relative name like the recorder.
+    #[clap(long, conflicts_with = "dry_run")]
+    pub carla_replay: Option<String>,
 
-    /// Zenoh listen address.
-    #[clap(long, default_value = "tcp/localhost:7447")]
+    /// Time factor of the replay (default: 1).
+    #[clap(long, value_parser = config::parse_positive, requires = "carla_replay")]
+    pub replay_speed: Option<f64>,
+
+    /// Refuse to start unless a run is reproducible: the synchronous mode, the commands
+    /// replayed with --replay-commands and a noise_seed for the sensors. The same
+    /// configuration and command log then give the same trajectory.
+    #[clap(long)]
+    pub deterministic: bool,
+
+    /// What the vehicles are simulated with: carla (default) or kinematic, a single vehicle
+    /// driven by a kinematic bicycle model without any simulator, see `[kinematic]`.
+    #[clap(long)]
+    pub backend: Option<Backend>,
+
+    /// Carla simulator address (default: 127.0.0.1).
+    #[clap(long, global = true)]
+    pub carla_address: Option<String>,
+
+    /// Carla simulator port (default: 2000).
+    #[clap(long, global = true)]
+    pub carla_port: Option<u16>,
+
+    /// Zenoh listen address (default: tcp/localhost:7447).
+    #[clap(long, global = true)]
     pub zenoh_listen: Vec<String>,
+
+    /// Interpolate control commands between their stamps instead of latching the latest one.
+    #[clap(long)]
+    pub interpolate_control: bool,
+
+    /// Publish the ground-truth twist of the vehicles for gyro_odometer.
+    #[clap(long)]
+    pub publish_twist: bool,
+
+    /// Pitch compensation of the longitudinal control: full (default), off or deadband(<degrees>).
+    #[clap(long)]
+    pub pitch_compensation: Option<PitchCompensation>,
+
+    /// Endianness of the published CDR payloads: little (default) or big.
+    #[clap(long)]
+    pub cdr_endianness: Option<Endianness>,
+
+    /// Layout of the received control commands: universe-2023 (default) or universe-2024.
+    #[clap(long)]
+    pub autoware_version: Option<AutowareVersion>,
+
+    /// Record the received control commands into the file.
+    #[clap(long)]
+    pub record_commands: Option<PathBuf>,
+
+    /// Replay the control commands from the file instead of subscribing to them.
+    #[clap(long, conflicts_with = "record_commands")]
+    pub replay_commands: Option<PathBuf>,
+
+    /// Log the control signals of each step into a CSV per vehicle, `<stem>_<vehicle>.csv`.
+    #[clap(long)]
+    pub signal_log: Option<PathBuf>,
+
+    /// Record all the published topics into a MCAP file.
+    #[clap(long)]
+    pub record: Option<PathBuf>,
+
+    /// Start a new MCAP file once the current one exceeds the size (in MB).
+    #[clap(long)]
+    pub record_max_size: Option<u64>,
+
+    /// Also record the received commands into the MCAP file.
+    #[clap(long)]
+    pub record_received: bool,
+
+    /// Battery drain while the vehicle exists (percent per second, default: 0.001).
+    #[clap(long)]
+    pub battery_base_drain: Option<f32>,
+
+    /// Additional battery drain at full throttle (percent per second, default: 0.02).
+    #[clap(long)]
+    pub battery_throttle_drain: Option<f32>,
+
+    /// Steering wheel angle per tire angle, if Autoware commands the steering wheel angle (default: 1.0).
+    #[clap(long, value_parser = config::parse_steering_ratio)]
+    pub steering_ratio: Option<f32>,
+
+    /// Which way a positive steering tire angle turns: left_positive (Autoware, default) or right_positive.
+    #[clap(long)]
+    pub steering_convention: Option<SteeringConvention>,
+
+    /// The command driving the vehicles at the start: ackermann (control_cmd, default) or raw (actuation_cmd).
+    #[clap(long)]
+    pub control_path: Option<ControlPath>,
+
+    /// Ramp the acceleration with the commanded jerk, which is capped by this value (m/s^3).
+    #[clap(long)]
+    pub jerk_limit: Option<f32>,
+
+    /// Turn the brake and reverse lights on and off with the applied control (default: true).
+    #[clap(long, action = ArgAction::Set)]
+    pub sync_vehicle_lights: Option<bool>,
+
+    /// Let CARLA run on its own instead of ticking it from the bridge.
+    #[clap(long)]
+    pub asynchronous: bool,
+
+    /// Simulated seconds per tick in synchronous mode, which is also the period of the bridge loop (default: 0.05).
+    #[clap(long, value_parser = config::parse_positive)]
+    pub fixed_delta_seconds: Option<f64>,
+
+    /// Rate of the bridge loop in asynchronous mode (Hz, default: 20).
+    #[clap(long, value_parser = config::parse_positive)]
+    pub loop_rate: Option<f64>,
+
+    /// Warn when the simulation runs slower than this factor of real time.
+    #[clap(long)]
+    pub min_real_time_factor: Option<f64>,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+enum Command {
+    /// Print the actors of the CARLA world and the namespace each one would be bridged
+    /// under, then exit.
+    Actors {
+        /// Only the actors whose type_id starts with it, e.g. `vehicle.`
+        #[clap(long = "type")]
+        type_prefix: Option<String>,
+
+        /// Print a JSON array instead of a table
+        #[clap(long)]
+        json: bool,
+    },
+
+    /// Decode the messages of a key expression to the terminal until Ctrl+C, instead of
+    /// bridging, e.g. `echo 'v1/rt/vehicle/status/**'`. Connects to the zenoh endpoints the
+    /// bridge listens on.
+    Echo {
+        key_expr: String,
+
+        /// The ROS 2 type of the messages, e.g. `sensor_msgs/msg/Imu`, instead of the one
+        /// guessed from the key. Unknown types are dumped in hex.
+        #[clap(long = "type")]
+        type_name: Option<String>,
+
+        /// Also save each raw payload into the directory, e.g. to capture test fixtures.
+        #[clap(long)]
+        save_raw: Option<PathBuf>,
+    },
 }
 
 fn main() -> Result<(), Error> {
     pretty_env_logger::init();
 
-    let Opts {
-        carla_address,
-        carla_port,
-        zenoh_listen,
-    } = Opts::parse();
-
-    info!("Running Carla Autoware Zenoh bridge...");
-    let mut config = Config::default();
-    config
-        .listen
-        .endpoints
-        .extend(zenoh_listen.iter().map(|p| p.parse().unwrap()));
-    let z_session = Arc::new(zenoh::open(config).res()?);
-
-    // Carla
-    let client = Client::connect(&carla_address, carla_port, None);
-    let mut world = client.world();
-    // Carla settings (synchronous)
-    let mut carla_settings = world.settings();
-    carla_settings.synchronous_mode = true;
-    carla_settings.fixed_delta_seconds = Some(0.05);
-    world.apply_settings(&carla_settings, Duration::from_millis(1000));
-
-    // Create bridge list
-    let mut bridge_list: HashMap<ActorId, Box<dyn ActorBridge>> = HashMap::new();
-
-    // Create clock publisher
-    let mut last_time = Instant::now();
-    let simulator_clock = SimulatorClock::new(z_session.clone()).unwrap();
-
-    // Create thread for ticking
-    let client_for_tick = Client::connect(&carla_address, carla_port, None);
-    thread::spawn(move || loop {
-        let mut world = client_for_tick.world();
-        world.tick();
-        // 20 ticks for 1 simulated seconds, and tick every 0.1 real seconds
-        // 1 simulated second = 2 real seconds
-        thread::sleep(Duration::from_millis(100));
+    let opts = Opts::parse();
+    if opts.dump_default_config {
+        print!("{DEFAULT_CONFIG}");
+        return Ok(());
+    }
+    if opts.bench_compression {
+        return compression::benchmark();
+    }
+    let file = match &opts.config {
+        Some(path) => FileConfig::load(path)?,
+        None => FileConfig::default(),
+    };
+
+    let carla_address = opts
+        .carla_address
+        .or(file.carla.address)
+        .unwrap_or_else(|| String::from("127.0.0.1"));
+    let carla_port = opts.carla_port.or(file.carla.port).unwrap_or(2000);
+    let zenoh_listen = if opts.zenoh_listen.is_empty() {
+        file.zenoh
+            .listen
+            .unwrap_or_else(|| vec![String::from("tcp/localhost:7447")])
+    } else {
+        opts.zenoh_listen
+    };
+    let cdr_endianness = opts
+        .cdr_endianness
+        .or(file.zenoh.cdr_endianness)
+        .unwrap_or_default();
+    let autoware_version = opts
+        .autoware_version
+        .or(file.zenoh.autoware_version)
+        .unwrap_or_default();
+    if let Some(Command::Echo {
+        key_expr,
+        type_name,
+        save_raw,
+    }) = &opts.command
+    {
+        let mut config = Config::default();
+        // The bridge listens on them, so they can't be listened on twice
+        config
+            .connect
+            .endpoints
+            .extend(zenoh_listen.iter().map(|p| p.parse().unwrap()));
+        let z_session = Arc::new(zenoh::open(config).res()?);
+        return echo::run(
+            z_session,
+            key_expr,
+            type_name.as_deref(),
+            autoware_version,
+            save_raw.as_deref(),
+        );
+    }
+    let asynchronous = opts.asynchronous || file.loop_timing.asynchronous.unwrap_or(false);
+    let fixed_delta_seconds = opts
+        .fixed_delta_seconds
+        .or(file.loop_timing.fixed_delta_seconds)
+        .unwrap_or(0.05);
+    let loop_rate = opts
+        .loop_rate
+        .or(file.loop_timing.loop_rate)
+        .unwrap_or(20.0);
+    let mut debug_draw_config = DebugDrawConfig::default();
+    // The drawings would show up on the screen of whoever owns the server
+    debug_draw_config.enabled =
+        file.debug.drawing.unwrap_or(debug_draw_config.enabled) && !opts.dry_run;
+    if let Some(trajectory) = file.debug.draw_trajectory {
+        debug_draw_config.trajectory = trajectory;
+    }
+    if let Some(trajectory_lifetime) = file.debug.trajectory_lifetime {
+        debug_draw_config.trajectory_lifetime = trajectory_lifetime;
+    }
+    if let Some(trajectory_max_points) = file.debug.trajectory_max_points {
+        debug_draw_config.trajectory_max_points = trajectory_max_points;
+    }
+    if let Some(goal) = file.debug.draw_goal {
+        debug_draw_config.goal = goal;
+    }
+    if let Some(hud) = file.debug.draw_hud {
+        debug_draw_config.hud = hud;
+    }
+    let min_real_time_factor = opts
+        .min_real_time_factor
+        .or(file.loop_timing.min_real_time_factor);
+    let record = opts.record.or(file.record.mcap);
+    let record_max_size = opts.record_max_size.or(file.record.mcap_max_size);
+    let record_received = opts.record_received || file.record.mcap_received.unwrap_or(false);
+    let record_commands = opts.record_commands.or(file.record.commands);
+    let replay_commands = opts.replay_commands.or(file.record.replay_commands);
+    let signal_log = opts.signal_log.or(file.record.signal_log);
+    if record_commands.is_some() && replay_commands.is_some() {
+        return Err(Error::Config {
+            reason: String::from("The commands can't be recorded and replayed at the same time"),
+        });
+    }
+
+    // Defaults, then the file, then the command line
+    let mut cli_vehicle = VehicleSection::default();
+    cli_vehicle.interpolate_control = opts.interpolate_control.then_some(true);
+    cli_vehicle.pitch_compensation = opts.pitch_compensation;
+    cli_vehicle.steering_ratio = opts.steering_ratio;
+    cli_vehicle.steering_convention = opts.steering_convention;
+    cli_vehicle.control_path = opts.control_path;
+    cli_vehicle.jerk_limit = opts.jerk_limit;
+    cli_vehicle.sync_vehicle_lights = opts.sync_vehicle_lights;
+    cli_vehicle.publish_twist = opts.publish_twist.then_some(true);
+    cli_vehicle.battery_base_drain = opts.battery_base_drain;
+    cli_vehicle.battery_throttle_drain = opts.battery_throttle_drain;
+    let mut base_vehicle = VehicleConfig::default();
+    base_vehicle.record_commands = record_commands;
+    base_vehicle.replay_commands = replay_commands;
+    base_vehicle.signal_log = signal_log;
+    let file_vehicle = file.vehicle.apply(&base_vehicle)?;
+    let vehicles = file
+        .vehicles
+        .iter()
+        .map(|(name, section)| {
+            Ok((
+                name.clone(),
+                cli_vehicle.apply(&section.apply(&file_vehicle)?)?,
+            ))
+        })
+        .collect::<Result<HashMap<_, _>, Error>>()?;
+    if let Some(topic) = file
+        .topics
+        .query_latest
+        .iter()
+        .find(|topic| !topics::LATCHED_COMMAND_TOPICS.contains(&topic.as_str()))
+    {
+        return Err(Error::Config {
+            reason: format!(
+                "'{topic}' can't query its latest value, expect one of {:?}",
+                topics::LATCHED_COMMAND_TOPICS
+            ),
+        });
+    }
+    let redeclare_after = file.topics.redeclare_after.unwrap_or(2.0);
+    if !(redeclare_after.is_finite() && redeclare_after > 0.0) {
+        return Err(Error::Config {
+            reason: String::from("redeclare_after must be positive"),
+        });
+    }
+    // Without a configuration file, there's no directory to look into
+    let role_overrides = match &opts.config {
+        Some(path) => RoleOverrides::load(
+            &path
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(role_overrides::OVERRIDES_DIR),
+            cli_vehicle.clone(),
+            &file_vehicle,
+        )?,
+        None => RoleOverrides::default(),
+    };
+    let namespace_source = file.carla.namespace_source.unwrap_or_default();
+    let mut bridge_config = BridgeConfig::default();
+    bridge_config.vehicle = cli_vehicle.apply(&file_vehicle)?;
+    bridge_config.vehicles = vehicles;
+    bridge_config.role_overrides = role_overrides;
+    if let Some(role_name_prefix) = file.carla.role_name_prefix {
+        bridge_config.role_name_prefix = role_name_prefix;
+    }
+    bridge_config.cdr_endianness = cdr_endianness;
+    bridge_config.autoware_version = autoware_version;
+    bridge_config.topics =
+        TopicMap::new(file.topics.remap, file.topics.key_style.unwrap_or_default())?;
+    bridge_config.query_latest = file.topics.query_latest.into_iter().collect();
+    bridge_config.redeclare_after = Duration::from_secs_f64(redeclare_after);
+    bridge_config.noise_seed = file.sensors.noise_seed;
+    bridge_config.gnss = file.sensors.gnss;
+    bridge_config.imu = file.sensors.imu;
+    bridge_config.localization = file.localization;
+    if let Some(queue_depth) = file.sensors.queue_depth {
+        bridge_config.sensor_queue_depth = queue_depth;
+    }
+    if let Some(max_frame_lag) = file.sensors.max_frame_lag {
+        bridge_config.max_sensor_frame_lag = max_frame_lag;
+    }
+    bridge_config.lidar_compression = file.sensors.lidar_compression.unwrap_or_default();
+    bridge_config.lidar_compression_overrides = file
+        .sensors
+        .lidars
+        .iter()
+        .filter_map(|(name, lidar)| Some((name.clone(), lidar.compression?)))
+        .collect();
+    bridge_config.depth_format = file.sensors.depth_format.unwrap_or_default();
+    bridge_config.radar_format = file.sensors.radar_format.unwrap_or_default();
+    // dry_run stays off, the runtime sets it for --dry-run and --carla-replay
+    let mut runtime_config = RuntimeConfig::default();
+    runtime_config.bridge = bridge_config;
+    runtime_config.carla_address = carla_address;
+    runtime_config.carla_port = carla_port;
+    runtime_config.zenoh_listen = zenoh_listen;
+    runtime_config.zenoh_timestamping = file.zenoh.timestamping.unwrap_or(true);
+    runtime_config.backend = opts.backend.unwrap_or_default();
+    runtime_config.kinematic = file.kinematic;
+    runtime_config.namespace_source = namespace_source;
+    runtime_config.asynchronous = asynchronous;
+    runtime_config.fixed_delta_seconds = fixed_delta_seconds;
+    runtime_config.loop_rate = loop_rate;
+    runtime_config.status_rate = file.loop_timing.status_rate;
+    runtime_config.control_rate = file.loop_timing.control_rate;
+    runtime_config.min_real_time_factor = min_real_time_factor;
+    if let Some(max_frame_time) = file.loop_timing.max_frame_time {
+        runtime_config.max_frame_time = max_frame_time;
+    }
+    if let Some(stall_timeout) = file.loop_timing.stall_timeout {
+        runtime_config.watchdog.stall_timeout = stall_timeout;
+    }
+    if let Some(exit_on_stall) = file.loop_timing.exit_on_stall {
+        runtime_config.watchdog.exit_on_stall = exit_on_stall;
+    }
+    if let Some(disconnect_timeout) = file.zenoh.disconnect_timeout {
+        runtime_config.watchdog.disconnect_timeout = disconnect_timeout;
+    }
+    runtime_config.carla_lanes = file.debug.carla_lanes.unwrap_or(false);
+    runtime_config.lane_resolution = file.debug.lane_resolution.unwrap_or(2.0);
+    runtime_config.debug_draw = debug_draw_config;
+    runtime_config.debug_json = if opts.debug_json.is_empty() {
+        file.debug.json.clone().unwrap_or_default()
+    } else {
+        opts.debug_json.clone()
+    };
+    runtime_config.record = record.map(|path| {
+        let mut mcap = McapConfig::default();
+        mcap.path = path;
+        mcap.max_file_size = record_max_size.map(|size| size * 1024 * 1024);
+        mcap.record_received = record_received;
+        mcap
     });
+    runtime_config.carla_replay = opts.carla_replay.clone();
+    runtime_config.replay_speed = opts.replay_speed.unwrap_or(1.0);
+    if let Some(workers) = file.sensors.workers {
+        runtime_config.sensor_workers = workers;
+    }
+    runtime_config.topic_cache = file.topics.cache;
+    runtime_config.dry_run = opts.dry_run;
+    runtime_config.deterministic = opts.deterministic;
+    runtime_config.validate()?;
+    if opts.print_topics {
+        runtime_config
+            .bridge
+            .topics
+            .describe()
+            .iter()
+            .for_each(|topic| println!("{topic}"));
+        return Ok(());
+    }
+
+    if let Some(Command::Actors { type_prefix, json }) = &opts.command {
+        let client = runtime::connect_carla(&runtime_config.carla_address, carla_port);
+        return actor_query::print_actors(
+            &client.world(),
+            &runtime_config.bridge,
+            namespace_source,
+            type_prefix.as_deref(),
+            *json,
+        );
+    }
 
-    loop {
-        let mut run_step = true;
-        let elapsed_time = last_time.elapsed();
-        {
-            let mut actor_list: HashMap<ActorId, _> = world
-                .actors()
-                .iter()
-                .map(|actor| (actor.id(), actor))
-                .collect();
-            let prev_actor_ids: HashSet<u32> = bridge_list.keys().cloned().collect();
-            let cur_actor_ids: HashSet<u32> = actor_list.keys().cloned().collect();
-            let added_ids = &cur_actor_ids - &prev_actor_ids;
-            let deleted_ids = &prev_actor_ids - &cur_actor_ids;
-
-            for id in added_ids {
-                let actor = actor_list.remove(&id).unwrap();
-                let bridge = match bridge::actor_bridge::create_bridge(z_session.clone(), actor) {
-                    Ok(bridge) => bridge,
-                    Err(Error::OwnerlessSensor { sensor_id }) => {
-                        debug!(
-                            "Ignore the sensor with ID {sensor_id} is not attached to any vehicle."
-                        );
-                        continue;
-                    }
-                    Err(Error::Npc { npc_role_name }) => {
-                        debug!("Ignore NPC vehicle {npc_role_name}.");
-                        continue;
-                    }
-                    Err(err) => return Err(err),
-                };
-                bridge_list.insert(id, bridge);
-                info!("Actor {id} created");
-            }
-
-            for id in deleted_ids {
-                bridge_list.remove(&id).unwrap();
-                info!("Actor {id} deleted");
-                run_step = false; // If there is actors removed, reget all the actor's list
-            }
-        }
-
-        // We only run step while there is no actor removed. Avoid getting vehicles failed
-        if run_step {
-            let sec = world.snapshot().timestamp().elapsed_seconds;
-            bridge_list
-                .values_mut()
-                .try_for_each(|bridge| bridge.step(elapsed_time.as_secs_f64(), sec))?;
-            simulator_clock.publish_clock(Some(sec))?;
-        }
-
-        last_time = Instant::now();
-        world.wait_for_tick();
-
-        // Sleep here, since the elapsed_time should be larger than certain value or carla_ackermann will have wrong result.
-        thread::sleep(Duration::from_millis(50));
+    let bridge_runtime = BridgeRuntime::new(runtime_config)?;
+    if let Some(key) = &opts.inspect_compressed {
+        return compression::inspect(bridge_runtime.session().clone(), key);
     }
+    bridge_runtime.run()
 }
@@ -0,0 +1,255 @@
+use crate::{error::Result, ros_types, utils::LogThrottle};
+use log::{error, info, warn};
+use mcap::{records::MessageHeader, Channel, Schema, WriteOptions, Writer};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use zenoh::{prelude::sync::*, subscriber::Subscriber};
+
+/// How many messages may wait for the disk before new ones are dropped
+const QUEUE_DEPTH: usize = 256;
+/// How often the written data is flushed, so a crashed run keeps most of it
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct McapConfig {
+    pub path: PathBuf,
+    /// Start a new file once the current one exceeds this size (in bytes).
+    pub max_file_size: Option<u64>,
+    /// Also record the commands the bridge receives.
+    pub record_received: bool,
+}
+
+struct RecordedMessage {
+    key: String,
+    payload: Vec<u8>,
+    log_time: u64,
+}
+
+/// Record the bridge topics into MCAP files on a dedicated thread
+pub struct McapRecorder<'a> {
+    _subscriber: Subscriber<'a, ()>,
+    tx: Option<SyncSender<RecordedMessage>>,
+    worker: Option<JoinHandle<()>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl<'a> McapRecorder<'a> {
+    pub fn start(z_session: Arc<Session>, config: McapConfig) -> Result<McapRecorder<'a>> {
+        let (tx, rx) = mpsc::sync_channel(QUEUE_DEPTH);
+        let dropped = Arc::new(AtomicU64::new(0));
+
+        // Fail early if the file can't be created
+        let writer = McapFileWriter::create(config.path.clone(), config.max_file_size)?;
+        let worker = thread::spawn(move || writer.run(rx));
+
+        let cloned_tx = tx.clone();
+        let cloned_dropped = dropped.clone();
+        let record_received = config.record_received;
+        let mut drop_log = LogThrottle::new(Duration::from_secs(5));
+        // Local publications are delivered to the subscribers of the same session
+        let subscriber = z_session
+            .declare_subscriber("*/**")
+            .callback_mut(move |sample| {
+                let key = sample.key_expr.as_str();
+                if !record_received && key.contains("/control/command/") {
+                    return;
+                }
+                let message = RecordedMessage {
+                    key: key.to_string(),
+                    payload: sample.payload.contiguous().to_vec(),
+                    log_time: now_nanos(),
+                };
+                match cloned_tx.try_send(message) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(_)) => {
+                        let dropped = cloned_dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                        if drop_log.ready() {
+                            warn!("MCAP recorder can't keep up, {dropped} messages dropped so far");
+                        }
+                    }
+                    Err(TrySendError::Disconnected(_)) => {}
+                }
+            })
+            .res()?;
+
+        info!("Recording the bridge topics into {}", config.path.display());
+        Ok(McapRecorder {
+            _subscriber: subscriber,
+            tx: Some(tx),
+            worker: Some(worker),
+            dropped,
+        })
+    }
+}
+
+impl<'a> Drop for McapRecorder<'a> {
+    fn drop(&mut self) {
+        // Close the channel, so the worker finishes the file
+        self.tx.take();
+        if let Some(worker) = self.worker.take() {
+            if worker.join().is_err() {
+                error!("MCAP recorder thread panicked");
+            }
+        }
+        info!(
+            "MCAP recorder stopped, {} messages dropped",
+            self.dropped.load(Ordering::Relaxed)
+        );
+    }
+}
+
+struct McapFileWriter {
+    base_path: PathBuf,
+    max_file_size: Option<u64>,
+    file_index: usize,
+    writer: Writer<'static, BufWriter<File>>,
+    written: u64,
+    channels: HashMap<String, u16>,
+    sequences: HashMap<u16, u32>,
+}
+
+impl McapFileWriter {
+    fn create(base_path: PathBuf, max_file_size: Option<u64>) -> Result<McapFileWriter> {
+        let writer = open_writer(&base_path)?;
+        Ok(McapFileWriter {
+            base_path,
+            max_file_size,
+            file_index: 0,
+            writer,
+            written: 0,
+            channels: HashMap::new(),
+            sequences: HashMap::new(),
+        })
+    }
+
+    fn run(mut self, rx: Receiver<RecordedMessage>) {
+        let mut last_flush = Instant::now();
+        loop {
+            match rx.recv_timeout(FLUSH_INTERVAL) {
+                Ok(message) => {
+                    if let Err(e) = self.write(message) {
+                        error!("Failed to write the MCAP file: {e}");
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+            if last_flush.elapsed() >= FLUSH_INTERVAL {
+                if let Err(e) = self.writer.flush() {
+                    error!("Failed to flush the MCAP file: {e}");
+                }
+                last_flush = Instant::now();
+            }
+        }
+        if let Err(e) = self.writer.finish() {
+            error!("Failed to finish the MCAP file: {e}");
+        }
+    }
+
+    fn write(&mut self, message: RecordedMessage) -> Result<()> {
+        if matches!(self.max_file_size, Some(max) if self.written >= max) {
+            self.rotate()?;
+        }
+        let channel = match self.channels.get(&message.key) {
+            Some(channel) => *channel,
+            None => {
+                let channel = self.writer.add_channel(&create_channel(&message.key))?;
+                self.channels.insert(message.key.clone(), channel);
+                channel
+            }
+        };
+        let sequence = self.sequences.entry(channel).or_insert(0);
+        *sequence += 1;
+        self.writer.write_to_known_channel(
+            &MessageHeader {
+                channel,
+                sequence: *sequence,
+                log_time: message.log_time,
+                publish_time: message.log_time,
+            },
+            &message.payload,
+        )?;
+        self.written += message.payload.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> Result<()> {
+        self.file_index += 1;
+        let path = rotated_path(&self.base_path, self.file_index);
+        let writer = open_writer(&path)?;
+        std::mem::replace(&mut self.writer, writer).finish()?;
+        self.written = 0;
+        self.channels.clear();
+        self.sequences.clear();
+        info!("MCAP recorder continues in {}", path.display());
+        Ok(())
+    }
+}
+
+fn open_writer(path: &Path) -> Result<Writer<'static, BufWriter<File>>> {
+    let file = BufWriter::new(File::create(path)?);
+    Ok(WriteOptions::new().create(file)?)
+}
+
+/// `record.mcap` becomes `record_1.mcap`, `record_2.mcap`...
+fn rotated_path(base_path: &Path, index: usize) -> PathBuf {
+    let stem = base_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let file_name = match base_path.extension() {
+        Some(ext) => format!("{stem}_{index}.{}", ext.to_string_lossy()),
+        None => format!("{stem}_{index}"),
+    };
+    base_path.with_file_name(file_name)
+}
+
+fn create_channel(key: &str) -> Channel<'static> {
+    let type_name = ros_types::type_name_from_key(key);
+    // The bridge topics without a message type are JSON, the others are CDR
+    if type_name.is_none() && key.contains("/bridge/") {
+        return Channel {
+            topic: key.to_string(),
+            schema: None,
+            message_encoding: String::from("json"),
+            metadata: BTreeMap::new(),
+        };
+    }
+    let schema = type_name.map(|type_name| {
+        Arc::new(Schema {
+            name: type_name.to_string(),
+            encoding: String::from("ros2msg"),
+            data: Cow::Owned(
+                ros_types::definition(type_name)
+                    .unwrap_or_default()
+                    .into_bytes(),
+            ),
+        })
+    });
+    Channel {
+        topic: key.to_string(),
+        schema,
+        message_encoding: String::from("cdr"),
+        metadata: BTreeMap::new(),
+    }
+}
+
+fn now_nanos() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Unable to get current time")
+        .as_nanos() as u64
+}
@@ -0,0 +1,251 @@
+//! The zenoh namespace of each vehicle, derived from its role_name or its actor id
+use crate::config::NamespaceSource;
+use carla::rpc::ActorId;
+use log::warn;
+use serde_derive::Serialize;
+use std::collections::HashMap;
+
+/// The sanitized names are cut to this many characters
+const MAX_NAME_LEN: usize = 64;
+
+/// Make a role_name usable as a single key expression chunk.
+///
+/// Every run of characters but ASCII letters, digits and `_` (slashes, spaces, wildcards...)
+/// becomes a single `_`, a leading digit gets a `v` prefix, since ROS 2 namespaces can't
+/// start with one, and the result is cut to 64 characters. It may be empty, the callers
+/// fall back to a generated name then.
+pub fn sanitize(role_name: &str) -> String {
+    let mut sanitized = String::new();
+    let mut replacing = false;
+    for c in role_name.trim().chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            sanitized.push(c);
+            replacing = false;
+        } else if !replacing {
+            sanitized.push('_');
+            replacing = true;
+        }
+    }
+    if sanitized.starts_with(|c: char| c.is_ascii_digit()) {
+        sanitized.insert(0, 'v');
+    }
+    // Only ASCII is left, so any length is a char boundary
+    sanitized.truncate(MAX_NAME_LEN);
+    sanitized
+}
+
+/// Both identifiers of a bridged vehicle, so the tools can correlate them whatever the layout
+#[derive(Debug, Clone, Serialize)]
+pub struct VehicleIdentity {
+    pub actor_id: ActorId,
+    pub role_name: String,
+    pub namespace: String,
+}
+
+/// The namespaces in use, so two vehicles never share one.
+///
+/// The sensors look up the namespace of their parent vehicle here, and register their
+/// names so two sensors of a vehicle never share one either.
+#[derive(Default)]
+pub struct NamespaceRegistry {
+    source: NamespaceSource,
+    namespaces: HashMap<ActorId, String>,
+    /// The parent vehicle and the name of each sensor
+    sensor_names: HashMap<ActorId, (ActorId, String)>,
+}
+
+impl NamespaceRegistry {
+    pub fn new(source: NamespaceSource) -> NamespaceRegistry {
+        NamespaceRegistry {
+            source,
+            namespaces: HashMap::new(),
+            sensor_names: HashMap::new(),
+        }
+    }
+
+    /// The namespace of the vehicle, assigned on the first call.
+    ///
+    /// `name` is the role_name without the prefix. With the role_name source, an empty one
+    /// falls back to `vehicle_<actor_id>`, and a name taken by another vehicle gets the
+    /// actor id appended.
+    pub fn namespace(&mut self, actor_id: ActorId, name: &str) -> String {
+        if let Some(namespace) = self.namespaces.get(&actor_id) {
+            return namespace.clone();
+        }
+        let sanitized = sanitize(name);
+        let id_namespace = format!("id/{actor_id}");
+        let taken = |namespace: &str| self.namespaces.values().any(|used| used == namespace);
+        let mut namespace = match self.source {
+            NamespaceSource::ActorId => id_namespace,
+            NamespaceSource::RoleNameOrId if sanitized.is_empty() || taken(&sanitized) => {
+                id_namespace
+            }
+            NamespaceSource::RoleNameOrId => sanitized,
+            NamespaceSource::RoleName if sanitized.is_empty() => format!("vehicle_{actor_id}"),
+            NamespaceSource::RoleName => sanitized,
+        };
+        if self.namespaces.values().any(|used| *used == namespace) {
+            let unique = format!("{namespace}_{actor_id}");
+            warn!("The namespace {namespace} is already used, use {unique} for actor {actor_id}");
+            namespace = unique;
+        }
+        self.namespaces.insert(actor_id, namespace.clone());
+        namespace
+    }
+
+    pub fn release(&mut self, actor_id: ActorId) {
+        self.namespaces.remove(&actor_id);
+    }
+
+    /// The name of a sensor on the vehicle `parent_id`, assigned on the first call.
+    ///
+    /// `name` is already sanitized. A name taken by another sensor of the same vehicle,
+    /// e.g. two role_names which only differ by the replaced characters, gets the actor id
+    /// of the sensor appended.
+    pub fn sensor_name(&mut self, parent_id: ActorId, sensor_id: ActorId, name: &str) -> String {
+        if let Some((_, sensor_name)) = self.sensor_names.get(&sensor_id) {
+            return sensor_name.clone();
+        }
+        let taken = self
+            .sensor_names
+            .values()
+            .any(|(parent, used)| *parent == parent_id && used == name);
+        let sensor_name = if taken {
+            let unique = format!("{name}_{sensor_id}");
+            warn!("The sensor name {name} is already used, use {unique} for sensor {sensor_id}");
+            unique
+        } else {
+            name.to_string()
+        };
+        self.sensor_names
+            .insert(sensor_id, (parent_id, sensor_name.clone()));
+        sensor_name
+    }
+
+    pub fn release_sensor(&mut self, sensor_id: ActorId) {
+        self.sensor_names.remove(&sensor_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_a_role_name() {
+        assert_eq!(sanitize("ego"), "ego");
+        assert_eq!(sanitize(" ego_2 "), "ego_2");
+        assert_eq!(sanitize("my car/front"), "my_car_front");
+        assert_eq!(sanitize("1st"), "v1st");
+        assert_eq!(sanitize(""), "");
+        assert_eq!(sanitize(&"a".repeat(100)), "a".repeat(MAX_NAME_LEN));
+    }
+
+    #[test]
+    fn collapse_the_replaced_runs() {
+        assert_eq!(sanitize("my hero/1"), "my_hero_1");
+        assert_eq!(sanitize("a//b"), "a_b");
+        assert_eq!(sanitize("a / *b"), "a_b");
+        assert_eq!(sanitize("/ego/"), "_ego_");
+        assert_eq!(sanitize("**"), "_");
+        // The underscores of the name are kept, only the replacements collapse
+        assert_eq!(sanitize("a__b"), "a__b");
+        assert_eq!(sanitize("a_/b"), "a__b");
+        assert_eq!(sanitize("caf\u{e9} 2"), "caf_2");
+    }
+
+    #[test]
+    fn any_name_becomes_a_key_chunk() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+        use zenoh::prelude::KeyExpr;
+
+        const CHARS: &[char] = &[
+            'a',
+            'Z',
+            '0',
+            '9',
+            '_',
+            '-',
+            '/',
+            '*',
+            '$',
+            '#',
+            '?',
+            ' ',
+            '.',
+            '\u{e9}',
+            '\u{1f697}',
+        ];
+        let mut rng = StdRng::seed_from_u64(152);
+        for _ in 0..1000 {
+            let len = rng.gen_range(0..80);
+            let name: String = (0..len)
+                .map(|_| CHARS[rng.gen_range(0..CHARS.len())])
+                .collect();
+            let sanitized = sanitize(&name);
+            assert!(sanitized.len() <= MAX_NAME_LEN, "{name:?}");
+            assert!(
+                sanitized
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_'),
+                "{name:?}"
+            );
+            assert!(
+                !sanitized.starts_with(|c: char| c.is_ascii_digit()),
+                "{name:?}"
+            );
+            assert!(!sanitized.contains("__") || name.contains('_'), "{name:?}");
+            if !sanitized.is_empty() {
+                assert!(KeyExpr::try_from(format!("{sanitized}/status")).is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn a_role_name_source_falls_back_to_the_actor_id() {
+        let mut registry = NamespaceRegistry::new(NamespaceSource::RoleName);
+        assert_eq!(registry.namespace(7, ""), "vehicle_7");
+        assert_eq!(registry.namespace(8, "ego"), "ego");
+        // A vehicle keeps its namespace
+        assert_eq!(registry.namespace(8, "other"), "ego");
+    }
+
+    #[test]
+    fn two_vehicles_never_share_a_namespace() {
+        let mut registry = NamespaceRegistry::new(NamespaceSource::RoleName);
+        assert_eq!(registry.namespace(1, "ego"), "ego");
+        assert_eq!(registry.namespace(2, "ego"), "ego_2");
+        // Only the sanitized names collide
+        assert_eq!(registry.namespace(3, "my car"), "my_car");
+        assert_eq!(registry.namespace(4, "my/car"), "my_car_4");
+        // A released namespace is free again
+        registry.release(1);
+        assert_eq!(registry.namespace(5, "ego"), "ego");
+    }
+
+    #[test]
+    fn sensor_names_are_unique_per_vehicle() {
+        let mut registry = NamespaceRegistry::default();
+        assert_eq!(registry.sensor_name(1, 10, "front"), "front");
+        // A sensor keeps its name
+        assert_eq!(registry.sensor_name(1, 10, "other"), "front");
+        // Same parent, same name
+        assert_eq!(registry.sensor_name(1, 11, "front"), "front_11");
+        // Another vehicle has its own names
+        assert_eq!(registry.sensor_name(2, 12, "front"), "front");
+        // A released name is free again
+        registry.release_sensor(10);
+        assert_eq!(registry.sensor_name(1, 13, "front"), "front");
+    }
+
+    #[test]
+    fn the_actor_id_sources() {
+        let mut registry = NamespaceRegistry::new(NamespaceSource::ActorId);
+        assert_eq!(registry.namespace(5, "ego"), "id/5");
+
+        let mut registry = NamespaceRegistry::new(NamespaceSource::RoleNameOrId);
+        assert_eq!(registry.namespace(1, "ego"), "ego");
+        assert_eq!(registry.namespace(2, "ego"), "id/2");
+        assert_eq!(registry.namespace(3, ""), "id/3");
+    }
+}
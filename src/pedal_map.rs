@@ -0,0 +1,322 @@
+//! The accel and brake maps of raw_vehicle_cmd_converter, for the feedforward of `simple_pid`
+use crate::error::{Error, Result};
+use arc_swap::ArcSwap;
+use log::{info, warn};
+use serde_derive::Serialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// The acceleration of each pedal position at each speed.
+///
+/// The CSV layout of raw_vehicle_cmd_converter: the header row is a label followed by the
+/// speeds (m/s), and every other row is a pedal position (0 to 1) followed by the
+/// accelerations at those speeds (m/s^2). Both the speeds and the pedals are ascending.
+#[derive(Debug, Clone)]
+pub struct PedalMap {
+    speeds: Vec<f64>,
+    pedals: Vec<f64>,
+    /// By pedal, then by speed
+    accelerations: Vec<Vec<f64>>,
+}
+
+impl PedalMap {
+    pub fn load(path: &Path) -> Result<PedalMap> {
+        let text = fs::read_to_string(path)?;
+        PedalMap::parse(&text).map_err(|reason| Error::InvalidPedalMap {
+            path: path.to_path_buf(),
+            reason,
+        })
+    }
+
+    fn parse(text: &str) -> std::result::Result<PedalMap, String> {
+        let mut rows = text
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty());
+        let Some((header_line, header)) = rows.next() else {
+            return Err(String::from("the file is empty"));
+        };
+        // The first cell only labels the table
+        let speeds = parse_cells(header.split(',').skip(1), header_line)?;
+        let mut pedals = Vec::new();
+        let mut accelerations = Vec::new();
+        for (line, row) in rows {
+            let mut cells = parse_cells(row.split(','), line)?;
+            if cells.len() != speeds.len() + 1 {
+                return Err(format!(
+                    "line {line} has {} accelerations for {} speeds",
+                    cells.len().saturating_sub(1),
+                    speeds.len()
+                ));
+            }
+            pedals.push(cells.remove(0));
+            accelerations.push(cells);
+        }
+        if speeds.is_empty() || pedals.len() < 2 {
+            return Err(String::from(
+                "the map needs at least one speed and two pedal positions",
+            ));
+        }
+        if !is_ascending(&speeds) || !is_ascending(&pedals) {
+            return Err(String::from("the speeds and the pedals must be ascending"));
+        }
+        Ok(PedalMap {
+            speeds,
+            pedals,
+            accelerations,
+        })
+    }
+
+    /// The acceleration of every pedal position at the speed, interpolated between the
+    /// speed columns and clamped to the first and the last
+    fn accelerations_at(&self, speed: f64) -> Vec<f64> {
+        let (lower, upper, ratio) = bracket(&self.speeds, speed);
+        self.accelerations
+            .iter()
+            .map(|row| row[lower] + (row[upper] - row[lower]) * ratio)
+            .collect()
+    }
+
+    /// The pedal position giving the acceleration at the speed, clamped to the pedals of
+    /// the map.
+    ///
+    /// Along the speed and then along the acceleration, which is bilinear within a cell.
+    /// An acceleration which doesn't change monotonically with the pedal is searched in
+    /// order, so the first matching segment wins.
+    pub fn pedal(&self, speed: f64, acceleration: f64) -> f64 {
+        let column = self.accelerations_at(speed);
+        let increasing = column[column.len() - 1] >= column[0];
+        let (first, last) = (self.pedals[0], self.pedals[self.pedals.len() - 1]);
+        let below = |a: f64, b: f64| if increasing { a <= b } else { a >= b };
+        if below(acceleration, column[0]) {
+            return first;
+        }
+        for (pedals, accelerations) in self.pedals.windows(2).zip(column.windows(2)) {
+            if below(acceleration, accelerations[1]) {
+                let span = accelerations[1] - accelerations[0];
+                let ratio = if span == 0.0 {
+                    0.0
+                } else {
+                    (acceleration - accelerations[0]) / span
+                };
+                return pedals[0] + (pedals[1] - pedals[0]) * ratio.clamp(0.0, 1.0);
+            }
+        }
+        last
+    }
+
+    /// The acceleration with the pedal released at the speed
+    pub fn released_acceleration(&self, speed: f64) -> f64 {
+        self.accelerations_at(speed)[0]
+    }
+}
+
+/// The accel and brake maps of a vehicle
+#[derive(Debug, Clone)]
+pub struct PedalMaps {
+    pub accel: PedalMap,
+    pub brake: PedalMap,
+}
+
+impl PedalMaps {
+    pub fn load(accel_path: &Path, brake_path: &Path) -> Result<PedalMaps> {
+        Ok(PedalMaps {
+            accel: PedalMap::load(accel_path)?,
+            brake: PedalMap::load(brake_path)?,
+        })
+    }
+
+    /// The throttle and brake giving the acceleration at the speed (m/s, m/s^2).
+    ///
+    /// Like raw_vehicle_cmd_converter, an acceleration the released accelerator reaches
+    /// anyway, e.g. the engine brake, takes the throttle, anything lower the brake.
+    pub fn pedals(&self, speed: f64, acceleration: f64) -> (f64, f64) {
+        let speed = speed.abs();
+        if acceleration >= self.accel.released_acceleration(speed) {
+            (self.accel.pedal(speed, acceleration), 0.0)
+        } else {
+            (0.0, self.brake.pedal(speed, acceleration))
+        }
+    }
+}
+
+/// What `bridge/pedal_maps/reload` replies
+#[derive(Debug, Serialize)]
+pub struct ReloadReport {
+    pub accel_map: PathBuf,
+    pub brake_map: PathBuf,
+    /// Speeds by pedal positions of each map
+    pub accel_size: [usize; 2],
+    pub brake_size: [usize; 2],
+}
+
+/// Load the maps again, replacing the ones in use only if both are valid
+pub fn reload(
+    maps: &ArcSwap<PedalMaps>,
+    accel_path: &Path,
+    brake_path: &Path,
+) -> Result<ReloadReport> {
+    let loaded = PedalMaps::load(accel_path, brake_path).map_err(|e| {
+        warn!("Keep the previous pedal maps: {e}");
+        e
+    })?;
+    let report = ReloadReport {
+        accel_map: accel_path.to_path_buf(),
+        brake_map: brake_path.to_path_buf(),
+        accel_size: [loaded.accel.speeds.len(), loaded.accel.pedals.len()],
+        brake_size: [loaded.brake.speeds.len(), loaded.brake.pedals.len()],
+    };
+    maps.store(Arc::new(loaded));
+    info!(
+        "Reloaded the pedal maps {} and {}",
+        accel_path.display(),
+        brake_path.display()
+    );
+    Ok(report)
+}
+
+fn parse_cells<'a>(
+    cells: impl Iterator<Item = &'a str>,
+    line: usize,
+) -> std::result::Result<Vec<f64>, String> {
+    cells
+        .map(|cell| {
+            cell.trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|value| value.is_finite())
+                .ok_or_else(|| format!("line {line} has an invalid number '{}'", cell.trim()))
+        })
+        .collect()
+}
+
+fn is_ascending(values: &[f64]) -> bool {
+    values.windows(2).all(|pair| pair[0] < pair[1])
+}
+
+/// The indices around the value and its ratio between them, clamped to the ends
+fn bracket(values: &[f64], value: f64) -> (usize, usize, f64) {
+    let last = values.len() - 1;
+    if value <= values[0] {
+        return (0, 0, 0.0);
+    }
+    if value >= values[last] {
+        return (last, last, 0.0);
+    }
+    let upper = values.iter().position(|v| *v > value).unwrap_or(last);
+    let lower = upper - 1;
+    let ratio = (value - values[lower]) / (values[upper] - values[lower]);
+    (lower, upper, ratio)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ACCEL_MAP: &str = "default,0.0,10.0
+0.0,0.0,-0.5
+0.5,1.0,0.5
+1.0,2.0,1.5
+";
+    const BRAKE_MAP: &str = "default,0.0,10.0
+0.0,0.0,-0.5
+1.0,-8.0,-8.5
+";
+
+    fn maps() -> PedalMaps {
+        PedalMaps {
+            accel: PedalMap::parse(ACCEL_MAP).unwrap(),
+            brake: PedalMap::parse(BRAKE_MAP).unwrap(),
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn the_corners_of_the_map() {
+        let map = maps().accel;
+        assert_close(map.pedal(0.0, 0.0), 0.0);
+        assert_close(map.pedal(0.0, 2.0), 1.0);
+        assert_close(map.pedal(10.0, -0.5), 0.0);
+        assert_close(map.pedal(10.0, 1.5), 1.0);
+        assert_close(map.pedal(0.0, 1.0), 0.5);
+    }
+
+    #[test]
+    fn interpolate_within_a_cell() {
+        let map = maps().accel;
+        // Halfway between the speeds, the column is [-0.25, 0.75, 1.75]
+        assert_close(map.released_acceleration(5.0), -0.25);
+        assert_close(map.pedal(5.0, 0.25), 0.25);
+        assert_close(map.pedal(5.0, 1.25), 0.75);
+        assert_close(map.pedal(2.5, 0.875), 0.5);
+    }
+
+    #[test]
+    fn clamp_to_the_edges() {
+        let map = maps().accel;
+        assert_close(map.pedal(5.0, 10.0), 1.0);
+        assert_close(map.pedal(5.0, -10.0), 0.0);
+        // Beyond the speeds, the first or the last column
+        assert_close(map.pedal(-3.0, 1.0), 0.5);
+        assert_close(map.pedal(20.0, 0.5), 0.5);
+    }
+
+    #[test]
+    fn the_brake_or_the_throttle() {
+        let maps = maps();
+        let (throttle, brake) = maps.pedals(0.0, -4.0);
+        assert_close(throttle, 0.0);
+        assert_close(brake, 0.5);
+        // The released accelerator decelerates on its own at speed, so no brake
+        assert_eq!(maps.pedals(10.0, -0.5), (0.0, 0.0));
+        // Reversing looks the speed up by its magnitude
+        let (throttle, brake) = maps.pedals(-10.0, 0.5);
+        assert_close(throttle, 0.5);
+        assert_close(brake, 0.0);
+    }
+
+    #[test]
+    fn reject_an_invalid_map() {
+        for (text, reason) in [
+            ("", "empty"),
+            ("default,0.0,10.0\n0.0,0.0\n1.0,1.0,2.0\n", "accelerations"),
+            ("default,10.0,0.0\n0.0,0.0,0.0\n1.0,1.0,2.0\n", "ascending"),
+            ("default,0.0\n1.0,0.0\n0.0,1.0\n", "ascending"),
+            ("default,0.0\n0.0,0.0\n1.0,fast\n", "invalid number"),
+            ("default,0.0\n0.0,0.0\n1.0,inf\n", "invalid number"),
+            ("default,0.0\n0.0,0.0\n", "two pedal"),
+        ] {
+            let error = PedalMap::parse(text).unwrap_err();
+            assert!(error.contains(reason), "{text:?}: {error}");
+        }
+    }
+
+    #[test]
+    fn a_reload_keeps_the_maps_unless_both_are_valid() {
+        let dir = std::env::temp_dir().join(format!("pedal_map_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (accel_path, brake_path) = (dir.join("accel_map.csv"), dir.join("brake_map.csv"));
+        fs::write(&accel_path, ACCEL_MAP).unwrap();
+        fs::write(&brake_path, BRAKE_MAP).unwrap();
+        let maps = ArcSwap::from_pointee(PedalMaps::load(&accel_path, &brake_path).unwrap());
+
+        fs::write(&accel_path, ACCEL_MAP.replace("1.5", "3.5")).unwrap();
+        let report = reload(&maps, &accel_path, &brake_path).unwrap();
+        assert_eq!(report.accel_size, [2, 3]);
+        assert_eq!(report.brake_size, [2, 2]);
+        assert_close(maps.load().accel.pedal(10.0, 3.5), 1.0);
+
+        fs::write(&brake_path, "default,0.0\n").unwrap();
+        assert!(reload(&maps, &accel_path, &brake_path).is_err());
+        assert_close(maps.load().accel.pedal(10.0, 3.5), 1.0);
+        assert_close(maps.load().brake.pedal(0.0, -8.0), 1.0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,76 @@
+use carla::rpc::VehiclePhysicsControl;
+use nalgebra::Vector3;
+use serde_derive::{Deserialize, Serialize};
+
+/// The reply of `<vehicle>/bridge/physics/refresh`, what the bridge derives from the physics
+#[derive(Debug, Serialize)]
+pub struct PhysicsRefreshReply {
+    /// Counts the reads of the physics control, 1 at the creation of the bridge
+    pub generation: u64,
+    /// None if the physics of the vehicle can't be read
+    pub mass: Option<f32>,
+    pub wheels: usize,
+    pub two_wheeled: bool,
+    /// The tire angle at full steer (degrees)
+    pub max_steer_angle: f32,
+}
+
+/// A partial physics specification received as JSON on `<vehicle>/bridge/physics_update`.
+///
+/// The missing fields keep their current values.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PhysicsUpdate {
+    /// Mass of the vehicle (kg).
+    pub mass: Option<f32>,
+    /// Center of mass relative to the vehicle origin (m).
+    pub center_of_mass: Option<[f32; 3]>,
+    /// Tire friction applied to every wheel.
+    pub tire_friction: Option<f32>,
+    pub max_rpm: Option<f32>,
+    pub drag_coefficient: Option<f32>,
+}
+
+impl PhysicsUpdate {
+    /// Check the values, so CARLA never receives a broken physics control
+    pub fn validate(&self) -> Result<(), String> {
+        let positive = [
+            ("mass", self.mass),
+            ("tire_friction", self.tire_friction),
+            ("max_rpm", self.max_rpm),
+        ];
+        for (name, value) in positive {
+            if matches!(value, Some(value) if !(value.is_finite() && value > 0.0)) {
+                return Err(format!("{name} must be positive"));
+            }
+        }
+        if matches!(self.drag_coefficient, Some(drag) if !(drag.is_finite() && drag >= 0.0)) {
+            return Err(String::from("drag_coefficient must not be negative"));
+        }
+        if matches!(self.center_of_mass, Some(com) if com.iter().any(|v| !v.is_finite())) {
+            return Err(String::from("center_of_mass must be finite"));
+        }
+        Ok(())
+    }
+
+    /// Merge the given fields into the current physics control
+    pub fn merge_into(&self, physics: &mut VehiclePhysicsControl) {
+        if let Some(mass) = self.mass {
+            physics.mass = mass;
+        }
+        if let Some([x, y, z]) = self.center_of_mass {
+            physics.center_of_mass = Vector3::new(x, y, z);
+        }
+        if let Some(tire_friction) = self.tire_friction {
+            physics
+                .wheels
+                .iter_mut()
+                .for_each(|wheel| wheel.tire_friction = tire_friction);
+        }
+        if let Some(max_rpm) = self.max_rpm {
+            physics.max_rpm = max_rpm;
+        }
+        if let Some(drag_coefficient) = self.drag_coefficient {
+            physics.drag_coefficient = drag_coefficient;
+        }
+    }
+}
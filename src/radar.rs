@@ -0,0 +1,238 @@
+//! The radar detections, as a `radar_msgs/RadarScan` or a point cloud, `radar_format` in `[sensors]`
+use crate::{types::PointFieldType, utils};
+use carla::sensor::data::RadarDetection;
+use serde_derive::{Deserialize, Serialize};
+use std::str::FromStr;
+use zenoh_ros_type::{sensor_msgs, std_msgs::Header};
+
+/// The fields of the point cloud, all FLOAT32, in this order
+const POINT_FIELDS: [&str; 5] = ["x", "y", "z", "velocity", "intensity"];
+
+/// What a radar publishes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(try_from = "String")]
+#[non_exhaustive]
+pub enum RadarFormat {
+    /// `radar_msgs/RadarScan` on `scan`.
+    #[default]
+    RadarScan,
+    /// `sensor_msgs/PointCloud2` with a velocity field on `pointcloud`.
+    PointCloud,
+}
+
+impl FromStr for RadarFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.trim() {
+            "radar_scan" => Ok(RadarFormat::RadarScan),
+            "pointcloud" => Ok(RadarFormat::PointCloud),
+            format => Err(format!(
+                "Unknown radar format '{format}', expect radar_scan or pointcloud"
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for RadarFormat {
+    type Error = String;
+
+    fn try_from(format: String) -> Result<Self, Self::Error> {
+        format.parse()
+    }
+}
+
+/// A detection in the ROS frame of the sensor: x forward, y left, z up
+#[derive(Debug, Clone, Copy)]
+pub struct RadarPoint {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    /// (m)
+    pub range: f32,
+    /// Positive to the left (rad)
+    pub azimuth: f32,
+    /// Positive up (rad)
+    pub elevation: f32,
+    /// Towards the sensor is negative (m/s)
+    pub velocity: f32,
+}
+
+impl RadarPoint {
+    /// CARLA measures the azimuth to the right, since its y axis points right
+    pub fn from_detection(detection: &RadarDetection) -> RadarPoint {
+        let azimuth = -detection.azimuth;
+        let elevation = detection.altitude;
+        let range = detection.depth;
+        RadarPoint {
+            x: range * elevation.cos() * azimuth.cos(),
+            y: range * elevation.cos() * azimuth.sin(),
+            z: range * elevation.sin(),
+            range,
+            azimuth,
+            elevation,
+            velocity: detection.velocity,
+        }
+    }
+}
+
+/// radar_msgs/RadarReturn
+#[derive(Serialize, Deserialize)]
+struct RadarReturn {
+    range: f32,
+    azimuth: f32,
+    elevation: f32,
+    doppler_velocity: f32,
+    amplitude: f32,
+}
+
+/// radar_msgs/RadarScan
+#[derive(Serialize, Deserialize)]
+pub struct RadarScan {
+    header: Header,
+    returns: Vec<RadarReturn>,
+}
+
+/// The CARLA radar measures no amplitude, so it is 0 in both formats
+pub fn radar_scan(header: Header, points: &[RadarPoint]) -> RadarScan {
+    RadarScan {
+        header,
+        returns: points
+            .iter()
+            .map(|point| RadarReturn {
+                range: point.range,
+                azimuth: point.azimuth,
+                elevation: point.elevation,
+                doppler_velocity: point.velocity,
+                amplitude: 0.0,
+            })
+            .collect(),
+    }
+}
+
+pub fn point_cloud(header: Header, points: &[RadarPoint]) -> sensor_msgs::PointCloud2 {
+    let point_step = (POINT_FIELDS.len() * 4) as u32;
+    let fields = POINT_FIELDS
+        .iter()
+        .enumerate()
+        .map(|(index, name)| sensor_msgs::PointField {
+            name: name.to_string(),
+            offset: index as u32 * 4,
+            datatype: PointFieldType::FLOAT32 as u8,
+            count: 1,
+        })
+        .collect();
+    let data: Vec<u8> = points
+        .iter()
+        .flat_map(|point| [point.x, point.y, point.z, point.velocity, 0.0])
+        .flat_map(|value| value.to_ne_bytes())
+        .collect();
+    sensor_msgs::PointCloud2 {
+        header,
+        height: 1,
+        width: points.len() as u32,
+        fields,
+        is_bigendian: utils::is_bigendian(),
+        point_step,
+        row_step: data.len() as u32,
+        data,
+        is_dense: true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cdr_utils::{self, Endianness},
+        time::TimeStamp,
+    };
+
+    /// Two detections in a point cloud stamped 1.5 s in the `radar` frame, little-endian
+    const POINT_CLOUD: &[u8] =
+        include_bytes!("../tests/fixtures/ego_sensing_radar_front_pointcloud_000000.cdr");
+
+    fn point(x: f32, y: f32, z: f32, velocity: f32) -> RadarPoint {
+        RadarPoint {
+            x,
+            y,
+            z,
+            range: (x * x + y * y + z * z).sqrt(),
+            azimuth: y.atan2(x),
+            elevation: 0.0,
+            velocity,
+        }
+    }
+
+    #[test]
+    fn convert_a_detection_to_the_ros_frame() {
+        let detection = RadarDetection {
+            velocity: -3.0,
+            azimuth: 0.3,
+            altitude: 0.1,
+            depth: 20.0,
+        };
+        let point = RadarPoint::from_detection(&detection);
+        // CARLA's azimuth is to the right, so the point is on the right in ROS
+        assert!(point.y < 0.0 && point.z > 0.0);
+        assert_eq!(point.azimuth, -0.3);
+        let range = (point.x.powi(2) + point.y.powi(2) + point.z.powi(2)).sqrt();
+        assert!((range - 20.0).abs() < 1e-4);
+        assert!((point.x - 20.0 * 0.1f32.cos() * 0.3f32.cos()).abs() < 1e-4);
+        assert!((point.z - 20.0 * 0.1f32.sin()).abs() < 1e-4);
+        assert_eq!(point.velocity, -3.0);
+    }
+
+    #[test]
+    #[cfg(target_endian = "little")]
+    fn the_point_cloud_matches_the_fixture() {
+        let points = [point(10.0, -2.5, 0.5, -3.0), point(20.25, 4.0, -1.0, 1.5)];
+        let header = utils::ros_header(TimeStamp::from_secs_f64(1.5), "radar");
+        let cloud = point_cloud(header, &points);
+        let payload = cdr_utils::serialize(&cloud, Endianness::Little).unwrap();
+        assert_eq!(payload, POINT_CLOUD);
+    }
+
+    #[test]
+    fn the_fields_of_the_point_cloud() {
+        let cloud: sensor_msgs::PointCloud2 = cdr_utils::deserialize(POINT_CLOUD).unwrap();
+        let fields: Vec<_> = cloud
+            .fields
+            .iter()
+            .map(|field| {
+                (
+                    field.name.as_str(),
+                    field.offset,
+                    field.datatype,
+                    field.count,
+                )
+            })
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                ("x", 0, PointFieldType::FLOAT32 as u8, 1),
+                ("y", 4, PointFieldType::FLOAT32 as u8, 1),
+                ("z", 8, PointFieldType::FLOAT32 as u8, 1),
+                ("velocity", 12, PointFieldType::FLOAT32 as u8, 1),
+                ("intensity", 16, PointFieldType::FLOAT32 as u8, 1),
+            ]
+        );
+        assert_eq!((cloud.height, cloud.width), (1, 2));
+        assert_eq!(cloud.point_step, 20);
+        assert_eq!(cloud.row_step, 40);
+        assert!(cloud.is_dense);
+    }
+
+    #[test]
+    fn a_scan_keeps_the_spherical_coordinates() {
+        let points = [point(10.0, 0.0, 0.0, 2.0)];
+        let scan = radar_scan(utils::ros_header(TimeStamp::ZERO, "radar"), &points);
+        assert_eq!(scan.returns.len(), 1);
+        let radar_return = &scan.returns[0];
+        assert_eq!(radar_return.range, 10.0);
+        assert_eq!(radar_return.azimuth, 0.0);
+        assert_eq!(radar_return.doppler_velocity, 2.0);
+        assert_eq!(radar_return.amplitude, 0.0);
+    }
+}
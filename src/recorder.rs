@@ -0,0 +1,128 @@
+//! `*/bridge/recorder`: start and stop the recorder of the CARLA server
+use crate::{
+    error::Result,
+    topics::{self, TopicMap},
+};
+use carla::client::Client;
+use log::{info, warn};
+use serde_derive::Serialize;
+use std::sync::{Arc, Mutex};
+use zenoh::{prelude::sync::*, queryable::Query, queryable::Queryable};
+
+/// The reply of `*/bridge/recorder`
+#[derive(Debug, Default, Serialize)]
+pub struct RecorderReply {
+    pub recording: bool,
+    /// What the server reports about the file, including its path on the server
+    pub file: Option<String>,
+    /// The recording stopped by this request, also when `start` replaced it
+    pub stopped: Option<String>,
+}
+
+enum RecorderCommand {
+    Start(String),
+    Stop,
+}
+
+impl RecorderCommand {
+    fn parse(payload: &str) -> std::result::Result<RecorderCommand, String> {
+        let mut words = payload.split_whitespace();
+        match (words.next(), words.next(), words.next()) {
+            (Some("start"), Some(filename), None) => {
+                Ok(RecorderCommand::Start(filename.to_string()))
+            }
+            (Some("stop"), None, None) => Ok(RecorderCommand::Stop),
+            _ => Err(format!(
+                "Unknown recorder command '{payload}', expect 'start <filename>' or 'stop'"
+            )),
+        }
+    }
+}
+
+/// Keep the queries on `*/bridge/recorder` for the main loop, which owns the CARLA client.
+///
+/// The payload is `start <filename>` or `stop` as plain text. The server resolves a relative
+/// file name in its own `Saved` directory, so the reply carries what it reports.
+pub struct RecorderQueryable<'a> {
+    pending: Arc<Mutex<Vec<Query>>>,
+    /// The file being recorded, from the reply of the server
+    recording: Option<String>,
+    _queryable: Queryable<'a, ()>,
+}
+
+impl<'a> RecorderQueryable<'a> {
+    pub fn new(z_session: Arc<Session>, topic_map: &TopicMap) -> Result<RecorderQueryable<'a>> {
+        let pending = Arc::new(Mutex::new(Vec::new()));
+        let cloned_pending = pending.clone();
+        let queryable = z_session
+            .declare_queryable(topic_map.key("*", topics::RECORDER))
+            .callback(move |query| cloned_pending.lock().unwrap().push(query))
+            .res()?;
+        Ok(RecorderQueryable {
+            pending,
+            recording: None,
+            _queryable: queryable,
+        })
+    }
+
+    /// The file being recorded, none while the recorder is off
+    pub fn recording(&self) -> Option<&str> {
+        self.recording.as_deref()
+    }
+
+    /// Run the commands received since the last call, in order
+    pub fn handle(&mut self, client: &mut Client) {
+        let queries: Vec<_> = self.pending.lock().unwrap().drain(..).collect();
+        for query in queries {
+            let payload = query
+                .value()
+                .map(|value| String::from_utf8_lossy(&value.payload.contiguous()).into_owned())
+                .unwrap_or_default();
+            let reply = RecorderCommand::parse(payload.trim())
+                .map(|command| self.run(client, command))
+                .and_then(|reply| serde_json::to_vec(&reply).map_err(|e| e.to_string()));
+            let result = match reply {
+                Ok(payload) => Ok(Sample::new(query.key_expr().clone(), payload)),
+                Err(e) => {
+                    warn!("{e}");
+                    Err(e.into())
+                }
+            };
+            if let Err(e) = query.reply(result).res() {
+                warn!("Failed to reply to the recorder query: {e}");
+            }
+        }
+    }
+
+    fn run(&mut self, client: &mut Client, command: RecorderCommand) -> RecorderReply {
+        // CARLA records into a single file, so a start replaces the running recording
+        let stopped = self.recording.take();
+        if let Some(file) = &stopped {
+            client.stop_recorder();
+            info!("Stopped the CARLA recorder: {file}");
+        }
+        match command {
+            RecorderCommand::Start(filename) => {
+                let file = client.start_recorder(&filename, false);
+                info!("Started the CARLA recorder: {file}");
+                self.recording = Some(file.clone());
+                RecorderReply {
+                    recording: true,
+                    file: Some(file),
+                    stopped,
+                }
+            }
+            RecorderCommand::Stop => {
+                if stopped.is_none() {
+                    // Maybe another client started it, stopping an idle recorder is harmless
+                    client.stop_recorder();
+                }
+                RecorderReply {
+                    recording: false,
+                    file: None,
+                    stopped,
+                }
+            }
+        }
+    }
+}
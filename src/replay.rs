@@ -0,0 +1,75 @@
+//! `--carla-replay`: the CARLA server replays a recorder file and the bridge only observes it
+use carla::client::Client;
+use log::{info, warn};
+
+/// A replay started by the bridge, to tell when it reaches the end of the file.
+///
+/// The replayer advances with the simulation time scaled by the speed, so the end is the
+/// duration of the file after the first tick of the replayed world. Without a duration
+/// from the server, the end is the moment the last replayed vehicle is gone.
+pub struct Replay {
+    file: String,
+    speed: f64,
+    /// The length of the recording (s)
+    duration: Option<f64>,
+    /// The simulation time of the first tick in the current world
+    start: Option<f64>,
+    seen_vehicles: bool,
+    finished: bool,
+}
+
+impl Replay {
+    /// Replay the file from its start, the server resolves a relative name like the recorder
+    pub fn start(client: &mut Client, file: &str, speed: f64) -> Replay {
+        let duration = parse_duration(&client.show_recorder_file_info(file, false));
+        if duration.is_none() {
+            warn!("The server reports no duration of {file}, the end of the replay is when its vehicles are gone");
+        }
+        // The sensors too, so the replayed ones attached to a vehicle are bridged as well
+        let reply = client.replay_file(file, 0.0, 0.0, 0, true);
+        client.set_replayer_time_factor(speed);
+        info!("Replaying {file} at {speed}x: {}", reply.trim());
+        Replay {
+            file: file.to_string(),
+            speed,
+            duration,
+            start: None,
+            seen_vehicles: false,
+            finished: false,
+        }
+    }
+
+    /// A new world starts the simulation time over, e.g. the replay loading its map
+    pub fn restart_clock(&mut self) {
+        self.start = None;
+    }
+
+    /// Log the end of the replay once, at the simulation time of the tick
+    pub fn update(&mut self, sim_sec: f64, vehicle_count: usize) {
+        if self.finished {
+            return;
+        }
+        self.seen_vehicles |= vehicle_count > 0;
+        let start = *self.start.get_or_insert(sim_sec);
+        let ended = match self.duration {
+            Some(duration) => (sim_sec - start) * self.speed >= duration,
+            None => self.seen_vehicles && vehicle_count == 0,
+        };
+        if ended {
+            self.finished = true;
+            info!(
+                "The replay of {} ended, the bridge keeps publishing what is left in the world",
+                self.file
+            );
+        }
+    }
+}
+
+/// The `Duration: <s> seconds` line of the file info
+fn parse_duration(info: &str) -> Option<f64> {
+    info.lines()
+        .find_map(|line| line.trim().strip_prefix("Duration:"))
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(|secs| secs.parse::<f64>().ok())
+        .filter(|secs| secs.is_finite() && *secs > 0.0)
+}
@@ -0,0 +1,72 @@
+//! `*/bridge/reset_all`: reset the state the bridge owns, e.g. between the runs of a batch
+use crate::{
+    error::Result,
+    topics::{self, TopicMap},
+};
+use log::{info, warn};
+use serde_derive::Serialize;
+use std::sync::{Arc, Mutex};
+use zenoh::{prelude::sync::*, queryable::Query, queryable::Queryable};
+
+/// What a reset touched, the reply of `*/bridge/reset_all`
+#[derive(Debug, Default, Serialize)]
+pub struct ResetReport {
+    /// The namespaces of the vehicles moved back to their spawn pose
+    pub vehicles: Vec<String>,
+    /// The namespaces of the walkers moved back to their spawn pose
+    pub walkers: Vec<String>,
+    /// How many collision histories were cleared
+    pub collision_histories: usize,
+    /// The goals and trajectories are no longer drawn
+    pub debug_drawings: bool,
+}
+
+/// Keep the queries on `*/bridge/reset_all` for the main loop, which resets between two
+/// steps and then replies, so a reset never races with the stepping.
+pub struct ResetQueryable<'a> {
+    pending: Arc<Mutex<Vec<Query>>>,
+    _queryable: Queryable<'a, ()>,
+}
+
+impl<'a> ResetQueryable<'a> {
+    pub fn new(z_session: Arc<Session>, topic_map: &TopicMap) -> Result<ResetQueryable<'a>> {
+        let pending = Arc::new(Mutex::new(Vec::new()));
+        let cloned_pending = pending.clone();
+        let queryable = z_session
+            .declare_queryable(topic_map.key("*", topics::RESET_ALL))
+            .callback(move |query| cloned_pending.lock().unwrap().push(query))
+            .res()?;
+        Ok(ResetQueryable {
+            pending,
+            _queryable: queryable,
+        })
+    }
+
+    /// Run `reset` once if any query came since the last call, and reply to all of them
+    pub fn handle(&self, reset: impl FnOnce() -> ResetReport) {
+        let queries: Vec<_> = self.pending.lock().unwrap().drain(..).collect();
+        if queries.is_empty() {
+            return;
+        }
+        let report = reset();
+        info!(
+            "Reset {} vehicles, {} walkers and {} collision histories",
+            report.vehicles.len(),
+            report.walkers.len(),
+            report.collision_histories
+        );
+        let payload = match serde_json::to_vec(&report) {
+            Ok(payload) => payload,
+            Err(e) => {
+                warn!("Failed to encode the reset report: {e}");
+                return;
+            }
+        };
+        for query in queries {
+            let sample = Sample::new(query.key_expr().clone(), payload.clone());
+            if let Err(e) = query.reply(Ok(sample)).res() {
+                warn!("Failed to reply to the reset query: {e}");
+            }
+        }
+    }
+}
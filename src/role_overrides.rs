@@ -0,0 +1,389 @@
+//! The per-vehicle override files of `vehicles.d/`, next to the configuration file.
+//!
+//! Each `*.toml` file names a `role_name` pattern, where `*` matches any characters, and
+//! holds any subset of the `[vehicle]` settings at its top level, with `[topics]` for the
+//! topic groups. When a vehicle is discovered, the files matching its role_name are merged
+//! from the least to the most specific one: an exact name before the patterns, then more
+//! characters outside the `*`, then the file name. The tables merge key by key, e.g.
+//! `[topics]`, while every other value, the lists included, replaces the previous one.
+//!
+//! The result applies over `[vehicle]` and `[vehicles.<name>]`, and the command line flags
+//! still win after it.
+use crate::{
+    config::VehicleConfig,
+    config_file::VehicleSection,
+    error::{Error, Result},
+};
+use log::info;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use toml::{Table, Value};
+
+/// The directory of the override files, next to the configuration file
+pub const OVERRIDES_DIR: &str = "vehicles.d";
+
+/// One file of `vehicles.d/`
+#[derive(Debug, Clone)]
+pub struct RoleOverride {
+    pub file: PathBuf,
+    pub pattern: String,
+    /// The settings without `role_name`
+    table: Table,
+}
+
+/// Every file of `vehicles.d/`, and the command line flags to apply after them
+#[derive(Debug, Clone, Default)]
+pub struct RoleOverrides {
+    overrides: Vec<RoleOverride>,
+    cli: VehicleSection,
+}
+
+impl RoleOverrides {
+    /// Read the `*.toml` files of the directory, none if it doesn't exist. Each file is
+    /// checked on its own over `base`, so a typo fails at the start rather than at the spawn.
+    pub fn load(dir: &Path, cli: VehicleSection, base: &VehicleConfig) -> Result<RoleOverrides> {
+        let config_error = |file: &Path, reason: String| Error::Config {
+            reason: format!("{}: {reason}", file.display()),
+        };
+        let mut overrides = Vec::new();
+        if dir.is_dir() {
+            let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<std::io::Result<_>>()?;
+            files.retain(|file| file.extension().map_or(false, |ext| ext == "toml"));
+            files.sort();
+            for file in files {
+                let mut table: Table = toml::from_str(&fs::read_to_string(&file)?)
+                    .map_err(|e| config_error(&file, e.to_string()))?;
+                let pattern =
+                    match table.remove("role_name") {
+                        Some(Value::String(pattern)) if !pattern.is_empty() => pattern,
+                        _ => return Err(config_error(
+                            &file,
+                            String::from(
+                                "expect a role_name pattern, e.g. role_name = \"autoware_hero*\"",
+                            ),
+                        )),
+                    };
+                section(table.clone())
+                    .and_then(|section| section.apply(base))
+                    .map_err(|e| config_error(&file, e.to_string()))?;
+                info!(
+                    "Override the vehicles matching {pattern} with {}",
+                    file.display()
+                );
+                overrides.push(RoleOverride {
+                    file,
+                    pattern,
+                    table,
+                });
+            }
+        }
+        // Sorted once, the matching files then merge in this order
+        overrides.sort_by_key(|role_override| specificity(&role_override.pattern));
+        Ok(RoleOverrides { overrides, cli })
+    }
+
+    /// Every file, sorted from the least to the most specific pattern
+    pub fn files(&self) -> &[RoleOverride] {
+        &self.overrides
+    }
+
+    /// The files matching the role_name, from the least to the most specific one
+    pub fn matching<'a>(&'a self, role_name: &'a str) -> impl Iterator<Item = &'a RoleOverride> {
+        self.overrides
+            .iter()
+            .filter(move |role_override| matches(&role_override.pattern, role_name))
+    }
+
+    /// The settings of a vehicle with the merged files matching its role_name
+    pub fn apply(&self, role_name: &str, config: &VehicleConfig) -> Result<VehicleConfig> {
+        let mut merged = Table::new();
+        let mut files = Vec::new();
+        for role_override in self.matching(role_name) {
+            merge(&mut merged, &role_override.table);
+            files.push(role_override.file.display().to_string());
+        }
+        if files.is_empty() {
+            return Ok(config.clone());
+        }
+        let applied = section(merged)
+            .and_then(|section| section.apply(config))
+            .and_then(|config| self.cli.apply(&config))
+            .map_err(|e| Error::Config {
+                reason: format!("{role_name} with {}: {e}", files.join(", ")),
+            })?;
+        Ok(applied)
+    }
+}
+
+fn section(table: Table) -> Result<VehicleSection> {
+    Value::Table(table).try_into().map_err(|e| Error::Config {
+        reason: e.to_string(),
+    })
+}
+
+/// Merge the tables key by key, any other value of `over` replaces the one of `base`
+fn merge(base: &mut Table, over: &Table) {
+    for (key, value) in over {
+        match (base.get_mut(key), value) {
+            (Some(Value::Table(base_table)), Value::Table(over_table)) => {
+                merge(base_table, over_table)
+            }
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// Whether the role_name matches the pattern, where `*` matches any characters
+fn matches(pattern: &str, role_name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = role_name.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No `*` at all
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// Ascending with the specificity: the patterns before the exact names, then the more
+/// characters outside the `*`
+fn specificity(pattern: &str) -> (bool, usize) {
+    (!pattern.contains('*'), pattern.replace('*', "").len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh `vehicles.d/` of the test with the files, removed by `remove_dir_all`
+    fn overrides_dir(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("role_overrides_test_{test}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (name, text) in files {
+            fs::write(dir.join(name), text).unwrap();
+        }
+        dir
+    }
+
+    fn load(dir: &Path) -> Result<RoleOverrides> {
+        RoleOverrides::load(dir, VehicleSection::default(), &VehicleConfig::default())
+    }
+
+    #[test]
+    fn the_role_name_patterns() {
+        for (pattern, role_name, expected) in [
+            ("autoware_hero1", "autoware_hero1", true),
+            ("autoware_hero1", "autoware_hero10", false),
+            ("autoware_*", "autoware_hero1", true),
+            ("autoware_*", "autoware_", true),
+            ("autoware_*", "hero", false),
+            ("*_hero", "autoware_hero", true),
+            ("*_hero", "autoware_hero1", false),
+            ("autoware_*_*1", "autoware_bus_line1", true),
+            ("autoware_*_*1", "autoware_bus1", false),
+            ("*", "anything", true),
+            ("ab*b", "ab", false),
+        ] {
+            assert_eq!(
+                matches(pattern, role_name),
+                expected,
+                "{pattern} {role_name}"
+            );
+        }
+    }
+
+    #[test]
+    fn the_exact_names_are_more_specific_than_the_patterns() {
+        let mut patterns = ["autoware_hero1", "*", "autoware_hero*", "autoware_*"];
+        patterns.sort_by_key(|pattern| specificity(pattern));
+        assert_eq!(
+            patterns,
+            ["*", "autoware_*", "autoware_hero*", "autoware_hero1"]
+        );
+    }
+
+    #[test]
+    fn the_tables_merge_and_the_other_values_replace() {
+        let t = |text: &str| -> Table { toml::from_str(text).unwrap() };
+        let mut base = t(r#"
+            pid_kp = 1.0
+            gear_shift_speeds = [5.0, 10.0]
+            [topics]
+            gear = false
+            lights = false
+        "#);
+        merge(
+            &mut base,
+            &t(r#"
+                gear_shift_speeds = [6.0]
+                [topics]
+                gear = true
+            "#),
+        );
+        assert_eq!(
+            base,
+            t(r#"
+                pid_kp = 1.0
+                gear_shift_speeds = [6.0]
+                [topics]
+                gear = true
+                lights = false
+            "#)
+        );
+    }
+
+    #[test]
+    fn the_most_specific_file_wins() {
+        let dir = overrides_dir(
+            "specific",
+            &[
+                (
+                    "all.toml",
+                    r#"
+                        role_name = "autoware_*"
+                        pid_kp = 1.0
+                        gear_shift_speeds = [5.0, 10.0]
+                        [topics]
+                        gear = false
+                        lights = false
+                    "#,
+                ),
+                (
+                    "hero.toml",
+                    r#"
+                        role_name = "autoware_hero*"
+                        pid_kp = 2.0
+                        [topics]
+                        gear = true
+                    "#,
+                ),
+                (
+                    "hero1.toml",
+                    r#"
+                        role_name = "autoware_hero1"
+                        gear_shift_speeds = [6.0]
+                    "#,
+                ),
+                ("notes.txt", "not an override"),
+            ],
+        );
+        let overrides = load(&dir).unwrap();
+        let patterns: Vec<&str> = overrides
+            .files()
+            .iter()
+            .map(|role_override| role_override.pattern.as_str())
+            .collect();
+        assert_eq!(patterns, ["autoware_*", "autoware_hero*", "autoware_hero1"]);
+
+        let base = VehicleConfig::default();
+        let hero1 = overrides.apply("autoware_hero1", &base).unwrap();
+        assert_eq!(hero1.pid.kp, 2.0);
+        assert_eq!(hero1.gear_shift_speeds, [6.0]);
+        assert!(hero1.topic_groups.gear);
+        assert!(!hero1.topic_groups.lights);
+        assert!(hero1.topic_groups.velocity_report);
+
+        let hero2 = overrides.apply("autoware_hero2", &base).unwrap();
+        assert_eq!(hero2.pid.kp, 2.0);
+        assert_eq!(hero2.gear_shift_speeds, [5.0, 10.0]);
+
+        let ego = overrides.apply("autoware_ego", &base).unwrap();
+        assert_eq!(ego.pid.kp, 1.0);
+        assert!(!ego.topic_groups.gear);
+
+        let npc = overrides.apply("npc", &base).unwrap();
+        assert_eq!(npc.pid.kp, base.pid.kp);
+        assert_eq!(npc.gear_shift_speeds, base.gear_shift_speeds);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn the_file_name_breaks_the_ties() {
+        let dir = overrides_dir(
+            "ties",
+            &[
+                ("b.toml", "role_name = \"autoware_*\"\npid_kp = 2.0\n"),
+                ("a.toml", "role_name = \"autoware_*\"\npid_kp = 1.0\n"),
+            ],
+        );
+        let overrides = load(&dir).unwrap();
+        let config = overrides
+            .apply("autoware_ego", &VehicleConfig::default())
+            .unwrap();
+        assert_eq!(config.pid.kp, 2.0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn the_command_line_wins_over_the_files() {
+        let dir = overrides_dir(
+            "cli",
+            &[(
+                "all.toml",
+                "role_name = \"*\"\npid_kp = 2.0\npid_ki = 0.5\n",
+            )],
+        );
+        let cli = VehicleSection {
+            pid_kp: Some(3.0),
+            ..Default::default()
+        };
+        let overrides = RoleOverrides::load(&dir, cli, &VehicleConfig::default()).unwrap();
+        let config = overrides
+            .apply("autoware_ego", &VehicleConfig::default())
+            .unwrap();
+        assert_eq!(config.pid.kp, 3.0);
+        assert_eq!(config.pid.ki, 0.5);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn an_invalid_file_fails_at_the_start() {
+        for (test, text, reason) in [
+            ("no_role", "pid_kp = 2.0\n", "expect a role_name pattern"),
+            (
+                "empty_role",
+                "role_name = \"\"\n",
+                "expect a role_name pattern",
+            ),
+            (
+                "unknown",
+                "role_name = \"*\"\npid_kd_typo = 2.0\n",
+                "pid_kd_typo",
+            ),
+            (
+                "invalid",
+                "role_name = \"*\"\nsteering_ratio = -1.0\n",
+                "steering_ratio must be positive",
+            ),
+            ("syntax", "role_name = \n", "all.toml"),
+        ] {
+            let dir = overrides_dir(test, &[("all.toml", text)]);
+            let error = load(&dir).unwrap_err().to_string();
+            assert!(error.contains(reason), "{test}: {error}");
+            fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn no_directory_no_overrides() {
+        let dir = std::env::temp_dir().join("role_overrides_test_missing");
+        assert!(load(&dir).unwrap().files().is_empty());
+    }
+}
@@ -0,0 +1,365 @@
+//! The ROS 2 message types carried on the bridge topics
+
+/// Map a key expression to the ROS 2 type published on it, in any key style
+pub fn type_name_from_key(key: &str) -> Option<&'static str> {
+    let suffix_types = [
+        ("/clock", "rosgraph_msgs/msg/Clock"),
+        (
+            "/vehicle/status/velocity_status",
+            "autoware_auto_vehicle_msgs/msg/VelocityReport",
+        ),
+        (
+            "/vehicle/status/steering_status",
+            "autoware_auto_vehicle_msgs/msg/SteeringReport",
+        ),
+        (
+            "/vehicle/status/gear_status",
+            "autoware_auto_vehicle_msgs/msg/GearReport",
+        ),
+        (
+            "/vehicle/status/control_mode",
+            "autoware_auto_vehicle_msgs/msg/ControlModeReport",
+        ),
+        (
+            "/vehicle/status/turn_indicators_status",
+            "autoware_auto_vehicle_msgs/msg/TurnIndicatorsReport",
+        ),
+        (
+            "/vehicle/status/hazard_lights_status",
+            "autoware_auto_vehicle_msgs/msg/HazardLightsReport",
+        ),
+        (
+            "/vehicle/status/headlights_status",
+            "autoware_auto_vehicle_msgs/msg/HeadlightsReport",
+        ),
+        (
+            "/vehicle/status/battery_charge",
+            "tier4_vehicle_msgs/msg/BatteryStatus",
+        ),
+        ("/bridge/door_cmd", "zenoh_carla_bridge/msg/DoorCommand"),
+        ("/bridge/door_status", "zenoh_carla_bridge/msg/DoorStatus"),
+        ("/bridge/debug/slip", "zenoh_carla_bridge/msg/SlipEstimate"),
+        (
+            "/api/vehicle/doors/command",
+            "autoware_adapi_v1_msgs/srv/SetDoorCommand_Request",
+        ),
+        (
+            "/api/vehicle/doors/status",
+            "autoware_adapi_v1_msgs/msg/DoorStatusArray",
+        ),
+        ("/bridge/wheel_speeds", "zenoh_carla_bridge/msg/WheelSpeeds"),
+        (
+            "/control/command/control_cmd",
+            "autoware_auto_control_msgs/msg/AckermannControlCommand",
+        ),
+        (
+            "/control/command/actuation_cmd",
+            "tier4_vehicle_msgs/msg/ActuationCommandStamped",
+        ),
+        (
+            "/control/command/headlights_cmd",
+            "autoware_auto_vehicle_msgs/msg/HeadlightsCommand",
+        ),
+        (
+            "/control/command/gear_cmd",
+            "autoware_auto_vehicle_msgs/msg/GearCommand",
+        ),
+        (
+            "/control/current_gate_mode",
+            "tier4_control_msgs/msg/GateMode",
+        ),
+        (
+            "/planning/scenario_planning/trajectory",
+            "autoware_auto_planning_msgs/msg/Trajectory",
+        ),
+        ("/vehicle/footprint", "geometry_msgs/msg/PolygonStamped"),
+        ("/cmd_vel", "geometry_msgs/msg/Twist"),
+        ("/walker/status/pose", "geometry_msgs/msg/PoseStamped"),
+        ("/walker/status/velocity", "geometry_msgs/msg/TwistStamped"),
+        (
+            "/sensing/vehicle_velocity_converter/twist_with_covariance",
+            "geometry_msgs/msg/TwistWithCovarianceStamped",
+        ),
+        (
+            "/system/mrm/comfortable_stop/status",
+            "tier4_system_msgs/msg/MrmBehaviorStatus",
+        ),
+        (
+            "/system/mrm/emergency_stop/status",
+            "tier4_system_msgs/msg/MrmBehaviorStatus",
+        ),
+        (
+            "/bridge/debug/carla_lanes",
+            "visualization_msgs/msg/MarkerArray",
+        ),
+        ("/image_raw", "sensor_msgs/msg/Image"),
+        ("/compressedDepth", "sensor_msgs/msg/CompressedImage"),
+        ("/camera_info", "sensor_msgs/msg/CameraInfo"),
+        ("/carla_pointcloud", "sensor_msgs/msg/PointCloud2"),
+        ("/imu_raw", "sensor_msgs/msg/Imu"),
+        ("/nav_sat_fix", "sensor_msgs/msg/NavSatFix"),
+        (
+            "/pose_with_covariance",
+            "geometry_msgs/msg/PoseWithCovarianceStamped",
+        ),
+        ("/scan", "radar_msgs/msg/RadarScan"),
+        ("/pointcloud", "sensor_msgs/msg/PointCloud2"),
+    ];
+    suffix_types
+        .iter()
+        .find(|(suffix, _)| key.ends_with(suffix))
+        .map(|(_, type_name)| *type_name)
+}
+
+/// The message definition in the `ros2msg` format, including its dependencies
+pub fn definition(type_name: &str) -> Option<String> {
+    let (definition, dependencies): (&str, &[&str]) = match type_name {
+        "rosgraph_msgs/msg/Clock" => ("builtin_interfaces/Time clock", &["builtin_interfaces/Time"]),
+        "autoware_auto_vehicle_msgs/msg/VelocityReport" => (
+            "std_msgs/Header header\nfloat32 longitudinal_velocity\nfloat32 lateral_velocity\nfloat32 heading_rate",
+            &["std_msgs/Header", "builtin_interfaces/Time"],
+        ),
+        "autoware_auto_vehicle_msgs/msg/SteeringReport" => (
+            "builtin_interfaces/Time stamp\nfloat32 steering_tire_angle",
+            &["builtin_interfaces/Time"],
+        ),
+        "autoware_auto_vehicle_msgs/msg/GearReport"
+        | "autoware_auto_vehicle_msgs/msg/TurnIndicatorsReport"
+        | "autoware_auto_vehicle_msgs/msg/HazardLightsReport"
+        | "autoware_auto_vehicle_msgs/msg/HeadlightsReport" => (
+            "builtin_interfaces/Time stamp\nuint8 report",
+            &["builtin_interfaces/Time"],
+        ),
+        "tier4_system_msgs/msg/MrmBehaviorStatus" => (
+            "uint8 UNAVAILABLE = 0\nuint8 AVAILABLE = 1\nuint8 OPERATING = 2\n\nbuiltin_interfaces/Time stamp\nuint8 state",
+            &["builtin_interfaces/Time"],
+        ),
+        "autoware_auto_vehicle_msgs/msg/ControlModeReport" => (
+            "builtin_interfaces/Time stamp\nuint8 mode",
+            &["builtin_interfaces/Time"],
+        ),
+        "tier4_vehicle_msgs/msg/BatteryStatus" => (
+            "builtin_interfaces/Time stamp\nfloat32 energy_level",
+            &["builtin_interfaces/Time"],
+        ),
+        "zenoh_carla_bridge/msg/DoorCommand" => (
+            "builtin_interfaces/Time stamp\nuint8 position\nuint8 command",
+            &["builtin_interfaces/Time"],
+        ),
+        "zenoh_carla_bridge/msg/DoorStatus" => (
+            "builtin_interfaces/Time stamp\nuint8[4] doors",
+            &["builtin_interfaces/Time"],
+        ),
+        "autoware_adapi_v1_msgs/srv/SetDoorCommand_Request" => (
+            "builtin_interfaces/Time stamp\nautoware_adapi_v1_msgs/DoorCommand[] doors",
+            &["builtin_interfaces/Time", "autoware_adapi_v1_msgs/DoorCommand"],
+        ),
+        "autoware_adapi_v1_msgs/msg/DoorStatusArray" => (
+            "builtin_interfaces/Time stamp\nautoware_adapi_v1_msgs/DoorStatus[] doors",
+            &["builtin_interfaces/Time", "autoware_adapi_v1_msgs/DoorStatus"],
+        ),
+        "zenoh_carla_bridge/msg/SlipEstimate" => (
+            "builtin_interfaces/Time stamp\nAxleSlip front\nAxleSlip rear",
+            &["builtin_interfaces/Time", "zenoh_carla_bridge/AxleSlip"],
+        ),
+        "zenoh_carla_bridge/msg/WheelSpeeds" => (
+            "builtin_interfaces/Time stamp\nfloat32[4] wheel_speeds\nfloat32 vehicle_speed",
+            &["builtin_interfaces/Time"],
+        ),
+        "tier4_vehicle_msgs/msg/ActuationCommandStamped" => (
+            "std_msgs/Header header\nActuationCommand actuation",
+            &[
+                "std_msgs/Header",
+                "builtin_interfaces/Time",
+                "tier4_vehicle_msgs/ActuationCommand",
+            ],
+        ),
+        "autoware_auto_vehicle_msgs/msg/GearCommand"
+        | "autoware_auto_vehicle_msgs/msg/HeadlightsCommand" => (
+            "builtin_interfaces/Time stamp\nuint8 command",
+            &["builtin_interfaces/Time"],
+        ),
+        "autoware_auto_control_msgs/msg/AckermannControlCommand" => (
+            "builtin_interfaces/Time stamp\nAckermannLateralCommand lateral\nLongitudinalCommand longitudinal",
+            &[
+                "autoware_auto_control_msgs/AckermannLateralCommand",
+                "autoware_auto_control_msgs/LongitudinalCommand",
+                "builtin_interfaces/Time",
+            ],
+        ),
+        "geometry_msgs/msg/TwistWithCovarianceStamped" => (
+            "std_msgs/Header header\nTwistWithCovariance twist",
+            &[
+                "std_msgs/Header",
+                "builtin_interfaces/Time",
+                "geometry_msgs/TwistWithCovariance",
+                "geometry_msgs/Twist",
+                "geometry_msgs/Vector3",
+            ],
+        ),
+        "geometry_msgs/msg/Twist" => (
+            "Vector3 linear\nVector3 angular",
+            &["geometry_msgs/Vector3"],
+        ),
+        "geometry_msgs/msg/TwistStamped" => (
+            "std_msgs/Header header\nTwist twist",
+            &[
+                "std_msgs/Header",
+                "builtin_interfaces/Time",
+                "geometry_msgs/Twist",
+                "geometry_msgs/Vector3",
+            ],
+        ),
+        "geometry_msgs/msg/PoseStamped" => (
+            "std_msgs/Header header\nPose pose",
+            &[
+                "std_msgs/Header",
+                "builtin_interfaces/Time",
+                "geometry_msgs/Pose",
+                "geometry_msgs/Point",
+                "geometry_msgs/Quaternion",
+            ],
+        ),
+        "geometry_msgs/msg/PolygonStamped" => (
+            "std_msgs/Header header\nPolygon polygon",
+            &[
+                "std_msgs/Header",
+                "builtin_interfaces/Time",
+                "geometry_msgs/Polygon",
+                "geometry_msgs/Point32",
+            ],
+        ),
+        "geometry_msgs/msg/PoseWithCovarianceStamped" => (
+            "std_msgs/Header header\nPoseWithCovariance pose",
+            &[
+                "std_msgs/Header",
+                "builtin_interfaces/Time",
+                "geometry_msgs/PoseWithCovariance",
+                "geometry_msgs/Pose",
+                "geometry_msgs/Point",
+                "geometry_msgs/Quaternion",
+            ],
+        ),
+        "visualization_msgs/msg/MarkerArray" => (
+            "Marker[] markers",
+            &[
+                "visualization_msgs/Marker",
+                "std_msgs/Header",
+                "builtin_interfaces/Time",
+                "builtin_interfaces/Duration",
+                "geometry_msgs/Pose",
+                "geometry_msgs/Point",
+                "geometry_msgs/Quaternion",
+                "geometry_msgs/Vector3",
+                "std_msgs/ColorRGBA",
+                "sensor_msgs/CompressedImage",
+                "visualization_msgs/UVCoordinate",
+                "visualization_msgs/MeshFile",
+            ],
+        ),
+        "sensor_msgs/msg/Image" => (
+            "std_msgs/Header header\nuint32 height\nuint32 width\nstring encoding\nuint8 is_bigendian\nuint32 step\nuint8[] data",
+            &["std_msgs/Header", "builtin_interfaces/Time"],
+        ),
+        "sensor_msgs/msg/CompressedImage" => (
+            "std_msgs/Header header\nstring format\nuint8[] data",
+            &["std_msgs/Header", "builtin_interfaces/Time"],
+        ),
+        "radar_msgs/msg/RadarScan" => (
+            "std_msgs/Header header\nradar_msgs/RadarReturn[] returns",
+            &[
+                "std_msgs/Header",
+                "builtin_interfaces/Time",
+                "radar_msgs/RadarReturn",
+            ],
+        ),
+        "sensor_msgs/msg/CameraInfo" => (
+            "std_msgs/Header header\nuint32 height\nuint32 width\nstring distortion_model\nfloat64[] d\nfloat64[9] k\nfloat64[9] r\nfloat64[12] p\nuint32 binning_x\nuint32 binning_y\nRegionOfInterest roi",
+            &[
+                "std_msgs/Header",
+                "builtin_interfaces/Time",
+                "sensor_msgs/RegionOfInterest",
+            ],
+        ),
+        "sensor_msgs/msg/PointCloud2" => (
+            "std_msgs/Header header\nuint32 height\nuint32 width\nPointField[] fields\nbool is_bigendian\nuint32 point_step\nuint32 row_step\nuint8[] data\nbool is_dense",
+            &[
+                "std_msgs/Header",
+                "builtin_interfaces/Time",
+                "sensor_msgs/PointField",
+            ],
+        ),
+        "sensor_msgs/msg/Imu" => (
+            "std_msgs/Header header\ngeometry_msgs/Quaternion orientation\nfloat64[9] orientation_covariance\ngeometry_msgs/Vector3 angular_velocity\nfloat64[9] angular_velocity_covariance\ngeometry_msgs/Vector3 linear_acceleration\nfloat64[9] linear_acceleration_covariance",
+            &[
+                "std_msgs/Header",
+                "builtin_interfaces/Time",
+                "geometry_msgs/Quaternion",
+                "geometry_msgs/Vector3",
+            ],
+        ),
+        "sensor_msgs/msg/NavSatFix" => (
+            "std_msgs/Header header\nNavSatStatus status\nfloat64 latitude\nfloat64 longitude\nfloat64 altitude\nfloat64[9] position_covariance\nuint8 position_covariance_type",
+            &[
+                "std_msgs/Header",
+                "builtin_interfaces/Time",
+                "sensor_msgs/NavSatStatus",
+            ],
+        ),
+        _ => return None,
+    };
+
+    let mut full = definition.to_string();
+    for dependency in dependencies {
+        let dependency_definition = match *dependency {
+            "builtin_interfaces/Time" => "int32 sec\nuint32 nanosec",
+            "std_msgs/Header" => "builtin_interfaces/Time stamp\nstring frame_id",
+            "geometry_msgs/Quaternion" => "float64 x\nfloat64 y\nfloat64 z\nfloat64 w",
+            "geometry_msgs/Vector3" => "float64 x\nfloat64 y\nfloat64 z",
+            "geometry_msgs/Point" => "float64 x\nfloat64 y\nfloat64 z",
+            "geometry_msgs/Pose" => "Point position\nQuaternion orientation",
+            "geometry_msgs/Point32" => "float32 x\nfloat32 y\nfloat32 z",
+            "geometry_msgs/Polygon" => "Point32[] points",
+            "builtin_interfaces/Duration" => "int32 sec\nuint32 nanosec",
+            "std_msgs/ColorRGBA" => "float32 r\nfloat32 g\nfloat32 b\nfloat32 a",
+            "sensor_msgs/CompressedImage" => "std_msgs/Header header\nstring format\nuint8[] data",
+            "visualization_msgs/UVCoordinate" => "float32 u\nfloat32 v",
+            "visualization_msgs/MeshFile" => "string filename\nuint8[] data",
+            "visualization_msgs/Marker" => {
+                "std_msgs/Header header\nstring ns\nint32 id\nint32 type\nint32 action\ngeometry_msgs/Pose pose\ngeometry_msgs/Vector3 scale\nstd_msgs/ColorRGBA color\nbuiltin_interfaces/Duration lifetime\nbool frame_locked\ngeometry_msgs/Point[] points\nstd_msgs/ColorRGBA[] colors\nstring texture_resource\nsensor_msgs/CompressedImage texture\nUVCoordinate[] uv_coordinates\nstring text\nstring mesh_resource\nMeshFile mesh_file\nbool mesh_use_embedded_materials"
+            }
+            "geometry_msgs/Twist" => "Vector3 linear\nVector3 angular",
+            "geometry_msgs/TwistWithCovariance" => "Twist twist\nfloat64[36] covariance",
+            "geometry_msgs/PoseWithCovariance" => "Pose pose\nfloat64[36] covariance",
+            "sensor_msgs/RegionOfInterest" => {
+                "uint32 x_offset\nuint32 y_offset\nuint32 height\nuint32 width\nbool do_rectify"
+            }
+            "sensor_msgs/PointField" => "string name\nuint32 offset\nuint8 datatype\nuint32 count",
+            "sensor_msgs/NavSatStatus" => "int8 status\nuint16 service",
+            "zenoh_carla_bridge/AxleSlip" => "float32 longitudinal_slip\nfloat32 slip_angle",
+            "autoware_adapi_v1_msgs/DoorCommand" => {
+                "uint8 OPEN=1\nuint8 CLOSE=2\nuint32 index\nuint8 command"
+            }
+            "autoware_adapi_v1_msgs/DoorStatus" => {
+                "uint8 UNKNOWN=0\nuint8 NOT_AVAILABLE=1\nuint8 OPENED=2\nuint8 CLOSED=3\nuint8 OPENING=4\nuint8 CLOSING=5\nuint8 status"
+            }
+            "radar_msgs/RadarReturn" => {
+                "float32 range\nfloat32 azimuth\nfloat32 elevation\nfloat32 doppler_velocity\nfloat32 amplitude"
+            }
+            "autoware_auto_control_msgs/AckermannLateralCommand" => {
+                "builtin_interfaces/Time stamp\nfloat32 steering_tire_angle\nfloat32 steering_tire_rotation_rate"
+            }
+            "autoware_auto_control_msgs/LongitudinalCommand" => {
+                "builtin_interfaces/Time stamp\nfloat32 speed\nfloat32 acceleration\nfloat32 jerk"
+            }
+            "tier4_vehicle_msgs/ActuationCommand" => {
+                "float64 accel_cmd\nfloat64 brake_cmd\nfloat64 steer_cmd"
+            }
+            _ => unreachable!(),
+        };
+        full.push_str(&format!(
+            "\n================================================================================\nMSG: {dependency}\n{dependency_definition}"
+        ));
+    }
+    Some(full)
+}
@@ -0,0 +1,1008 @@
+//! The main loop of the bridge: follow the actors of the world and step a bridge per actor
+use crate::{
+    actor_query::ActorQueryable,
+    bridge::{
+        self,
+        actor_bridge::{self, ActorBridge, ActorMotion, BridgeKind, TickSnapshot},
+        kinematic_vehicle::{KinematicConfig, KinematicVehicle},
+        vehicle_bridge::VehicleBridge,
+    },
+    bridge_events::{BridgeEvents, EventReason},
+    bridge_info::{BridgeInfo, ConfigSummary, InfoQueryable},
+    bridge_query::BridgeListQueryable,
+    carla_stats::CarlaStats,
+    clock::{FrameReport, SimulatorClock},
+    config::{Backend, BridgeConfig, NamespaceSource},
+    debug_draw::{DebugDraw, DebugDrawConfig, HudVehicle},
+    debug_json,
+    error::{Error, Result},
+    heartbeat::{Heartbeat, WatchdogConfig},
+    lane_markers::LaneMarkers,
+    load_world::LoadWorldQueryable,
+    mcap_recorder::{McapConfig, McapRecorder},
+    namespace::NamespaceRegistry,
+    recorder::RecorderQueryable,
+    replay::Replay,
+    reset::{ResetQueryable, ResetReport},
+    scheduler::{FixedRate, TickSchedule},
+    sensor_pool::SensorPool,
+    sim_stats::SimStats,
+    spawn_points::SpawnPointQueryable,
+    time::{TickClock, TimeStamp},
+    topic_cache::TopicCaches,
+};
+use carla::{
+    client::{Client, World},
+    geom::Location,
+    prelude::*,
+    rpc::ActorId,
+};
+use log::{debug, info, warn};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{atomic::AtomicU64, Arc, Mutex},
+    time::{Duration, Instant},
+};
+use zenoh::prelude::sync::*;
+
+/// The zenoh configuration of the session opened by the runtime
+fn zenoh_config(config: &RuntimeConfig) -> Result<Config> {
+    let mut zenoh_config = Config::default();
+    for endpoint in &config.zenoh_listen {
+        zenoh_config
+            .listen
+            .endpoints
+            .push(endpoint.parse().map_err(|e| Error::Config {
+                reason: format!("Invalid zenoh endpoint '{endpoint}': {e}"),
+            })?);
+    }
+    // Only the routers stamp by default, the bridge runs as a peer
+    let timestamping = if config.zenoh_timestamping {
+        "true"
+    } else {
+        "false"
+    };
+    zenoh_config
+        .insert_json5("timestamping/enabled", timestamping)
+        .map_err(|e| Error::Config {
+            reason: format!("Failed to set the zenoh timestamping: {e}"),
+        })?;
+    Ok(zenoh_config)
+}
+
+/// The actor id of the kinematic vehicle, since there is no simulator to assign one
+const KINEMATIC_ACTOR_ID: u32 = 1;
+
+/// Everything the runtime needs, the defaults are the ones of the command line.
+///
+/// New fields may be added, so start from `RuntimeConfig::default()` and set the
+/// fields to change.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct RuntimeConfig {
+    /// The settings of the bridges.
+    pub bridge: BridgeConfig,
+    pub carla_address: String,
+    pub carla_port: u16,
+    /// The zenoh endpoints the bridge listens on.
+    pub zenoh_listen: Vec<String>,
+    /// Stamp every publication with the HLC time of the session, for the storages and the
+    /// replay alignment downstream.
+    pub zenoh_timestamping: bool,
+    pub backend: Backend,
+    /// The vehicle of the kinematic backend.
+    pub kinematic: KinematicConfig,
+    pub namespace_source: NamespaceSource,
+    /// Let CARLA run freely instead of ticking it, at `loop_rate`.
+    pub asynchronous: bool,
+    /// The simulation step of the synchronous mode (s).
+    pub fixed_delta_seconds: f64,
+    /// The rate of the loop in the asynchronous mode and of the kinematic backend (Hz).
+    pub loop_rate: f64,
+    /// Publish the vehicle status at this rate (Hz) instead of every tick.
+    pub status_rate: Option<f64>,
+    /// Apply the vehicle control at this rate (Hz) instead of every tick.
+    pub control_rate: Option<f64>,
+    /// Warn when the simulation runs slower than this factor of the real time.
+    pub min_real_time_factor: Option<f64>,
+    /// Above this average time between the frames of the server, the diagnostics warn
+    /// (wall-clock seconds).
+    pub max_frame_time: f64,
+    /// Tell a stalled simulation from a dead bridge.
+    pub watchdog: WatchdogConfig,
+    /// Publish the lane markers of the map.
+    pub carla_lanes: bool,
+    /// The distance between the points of the lane markers (m).
+    pub lane_resolution: f64,
+    pub debug_draw: DebugDrawConfig,
+    /// Mirror the outgoing messages of these key expressions as JSON, see `debug_json`.
+    pub debug_json: Vec<String>,
+    /// Record the bridge topics into MCAP files.
+    pub record: Option<McapConfig>,
+    /// Replay this file of the CARLA recorder and only observe it.
+    pub carla_replay: Option<String>,
+    /// Time factor of the replay.
+    pub replay_speed: f64,
+    /// Threads serializing the sensor data.
+    pub sensor_workers: usize,
+    /// Canonical topic name to the number of samples cached for late joiners.
+    pub topic_cache: BTreeMap<String, usize>,
+    /// Don't actuate the vehicles nor change the CARLA world, follow the ticks of the server.
+    pub dry_run: bool,
+    /// Refuse the settings which make a run irreproducible, and ignore the connectivity of
+    /// the zenoh session, which depends on the wall-clock time.
+    pub deterministic: bool,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        RuntimeConfig {
+            bridge: BridgeConfig::default(),
+            carla_address: String::from("127.0.0.1"),
+            carla_port: 2000,
+            zenoh_listen: vec![String::from("tcp/localhost:7447")],
+            zenoh_timestamping: true,
+            backend: Backend::default(),
+            kinematic: KinematicConfig::default(),
+            namespace_source: NamespaceSource::default(),
+            asynchronous: false,
+            fixed_delta_seconds: 0.05,
+            loop_rate: 20.0,
+            status_rate: None,
+            control_rate: None,
+            min_real_time_factor: None,
+            max_frame_time: 0.1,
+            watchdog: WatchdogConfig::default(),
+            carla_lanes: false,
+            lane_resolution: 2.0,
+            debug_draw: DebugDrawConfig::default(),
+            debug_json: Vec::new(),
+            record: None,
+            carla_replay: None,
+            replay_speed: 1.0,
+            sensor_workers: SensorPool::default_size(),
+            topic_cache: BTreeMap::new(),
+            dry_run: false,
+            deterministic: false,
+        }
+    }
+}
+
+impl RuntimeConfig {
+    /// Check the values a configuration file or the code can get wrong
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |reason: &str| {
+            Err(Error::Config {
+                reason: reason.to_string(),
+            })
+        };
+        if !(self.fixed_delta_seconds > 0.0 && self.loop_rate > 0.0) {
+            return invalid("fixed_delta_seconds and loop_rate must be positive");
+        }
+        if [self.status_rate, self.control_rate]
+            .iter()
+            .flatten()
+            .any(|rate| !(rate.is_finite() && *rate > 0.0))
+        {
+            return invalid("status_rate and control_rate must be positive");
+        }
+        if !(self.max_frame_time.is_finite() && self.max_frame_time > 0.0) {
+            return invalid("max_frame_time must be positive");
+        }
+        if !(self.watchdog.stall_timeout.is_finite() && self.watchdog.stall_timeout > 0.0) {
+            return invalid("stall_timeout must be positive");
+        }
+        if !(self.watchdog.disconnect_timeout.is_finite() && self.watchdog.disconnect_timeout > 0.0)
+        {
+            return invalid("disconnect_timeout must be positive");
+        }
+        if !(self.lane_resolution.is_finite() && self.lane_resolution > 0.0) {
+            return invalid("lane_resolution must be positive");
+        }
+        if !(self.debug_draw.trajectory_lifetime.is_finite()
+            && self.debug_draw.trajectory_lifetime > 0.0
+            && self.debug_draw.trajectory_max_points >= 2)
+        {
+            return invalid(
+                "trajectory_lifetime must be positive and trajectory_max_points at least 2",
+            );
+        }
+        if self.bridge.sensor_queue_depth == 0 || self.sensor_workers == 0 {
+            return invalid("queue_depth and workers of [sensors] must be at least 1");
+        }
+        if !(self.replay_speed.is_finite() && self.replay_speed > 0.0) {
+            return invalid("replay_speed must be positive");
+        }
+        if self.backend == Backend::Kinematic && self.carla_replay.is_some() {
+            return invalid("--carla-replay needs the carla backend");
+        }
+        if self.deterministic {
+            self.validate_deterministic()?;
+        }
+        self.bridge.gnss.validate()?;
+        self.bridge.imu.validate()?;
+        self.bridge.localization.validate()?;
+        self.kinematic.validate()?;
+        Ok(())
+    }
+
+    /// The same configuration with the same command log must give the same trajectory
+    fn validate_deterministic(&self) -> Result<()> {
+        let invalid = |reason: &str| {
+            Err(Error::Config {
+                reason: format!("--deterministic {reason}"),
+            })
+        };
+        if self.backend == Backend::Carla {
+            if self.asynchronous {
+                return invalid("needs the synchronous mode, where the bridge ticks CARLA");
+            }
+            if self.dry_run || self.carla_replay.is_some() {
+                return invalid("ticks the world itself, without --dry-run or --carla-replay");
+            }
+            if self.bridge.noise_seed.is_none() {
+                return invalid("needs noise_seed in [sensors]");
+            }
+        }
+        if std::iter::once(&self.bridge.vehicle)
+            .chain(self.bridge.vehicles.values())
+            .any(|vehicle| vehicle.replay_commands.is_none())
+        {
+            return invalid("drives the vehicles from a command log, set --replay-commands");
+        }
+        Ok(())
+    }
+}
+
+/// The bridge between a simulator and zenoh, until an error stops it.
+///
+/// ```no_run
+/// use zenoh_carla_bridge::{BridgeRuntime, RuntimeConfig};
+///
+/// let mut config = RuntimeConfig::default();
+/// config.carla_address = String::from("10.0.0.2");
+/// config.zenoh_listen = vec![String::from("tcp/0.0.0.0:7447")];
+/// config.bridge.role_name_prefix = String::from("ego_");
+/// config.bridge.vehicle.interpolate_control = true;
+/// BridgeRuntime::new(config)?.run()?;
+/// # Ok::<(), zenoh_carla_bridge::Error>(())
+/// ```
+pub struct BridgeRuntime {
+    config: RuntimeConfig,
+    z_session: Arc<Session>,
+}
+
+impl BridgeRuntime {
+    /// Validate the configuration and open a zenoh session listening on `zenoh_listen`
+    pub fn new(config: RuntimeConfig) -> Result<BridgeRuntime> {
+        config.validate()?;
+        info!("Running Carla Autoware Zenoh bridge...");
+        let z_session = Arc::new(zenoh::open(zenoh_config(&config)?).res()?);
+        Ok(BridgeRuntime { config, z_session })
+    }
+
+    /// Run on a session of the caller, e.g. one shared with a scenario runner.
+    /// `zenoh_listen` is then only reported in the bridge info, and the publications are
+    /// timestamped only if the session enables `timestamping`.
+    pub fn with_session(config: RuntimeConfig, z_session: Arc<Session>) -> Result<BridgeRuntime> {
+        config.validate()?;
+        Ok(BridgeRuntime { config, z_session })
+    }
+
+    pub fn session(&self) -> &Arc<Session> {
+        &self.z_session
+    }
+
+    /// Bridge until an error, the loop never ends otherwise
+    pub fn run(mut self) -> Result<()> {
+        let z_session = self.z_session.clone();
+        // The replay owns the replayed actors
+        self.config.bridge.dry_run |= self.config.dry_run || self.config.carla_replay.is_some();
+        if !self.config.debug_json.is_empty() {
+            debug_json::enable(z_session.clone(), &self.config.debug_json)?;
+        }
+        // Keep the recorder alive, it finishes the file when dropped
+        let _mcap_recorder = match self.config.record.clone() {
+            Some(record) => Some(McapRecorder::start(z_session.clone(), record)?),
+            None => None,
+        };
+        match self.config.backend {
+            Backend::Kinematic => run_kinematic(z_session, &self.config),
+            Backend::Carla => run_carla(z_session, &self.config),
+        }
+    }
+}
+
+/// The client of the bridge and of the subcommands
+pub fn connect_carla(carla_address: &str, carla_port: u16) -> Client {
+    info!("Connecting to CARLA at {carla_address}:{carla_port}");
+    Client::connect(carla_address, carla_port, None)
+}
+
+/// Switch CARLA to the synchronous mode, unless the bridge runs asynchronously
+fn apply_carla_settings(world: &mut World, asynchronous: bool, fixed_delta_seconds: f64) {
+    let mut carla_settings = world.settings();
+    carla_settings.synchronous_mode = !asynchronous;
+    carla_settings.fixed_delta_seconds = (!asynchronous).then_some(fixed_delta_seconds);
+    world.apply_settings(&carla_settings, Duration::from_millis(1000));
+}
+
+fn run_carla(z_session: Arc<Session>, config: &RuntimeConfig) -> Result<()> {
+    let bridge_config = &config.bridge;
+    let asynchronous = config.asynchronous;
+    let fixed_delta_seconds = config.fixed_delta_seconds;
+    let cdr_endianness = bridge_config.cdr_endianness;
+    let mut client = connect_carla(&config.carla_address, config.carla_port);
+    let mut world = client.world();
+    let mut map_name = world.map().name();
+    if config.dry_run {
+        warn!("Dry run: the vehicles are not actuated and the CARLA world is left as it is");
+    } else {
+        apply_carla_settings(&mut world, asynchronous, fixed_delta_seconds);
+    }
+    let mut replay = config
+        .carla_replay
+        .as_deref()
+        .map(|file| Replay::start(&mut client, file, config.replay_speed));
+    // In synchronous mode, every loop iteration ticks once, so the simulation runs in real time
+    let period = if asynchronous {
+        Duration::from_secs_f64(1.0 / config.loop_rate)
+    } else {
+        Duration::from_secs_f64(fixed_delta_seconds)
+    };
+    let mut scheduler = FixedRate::new(period);
+    let mut tick_schedule = TickSchedule::new(config.status_rate, config.control_rate);
+
+    // Declared before the bridges, so it drains their queues after they are dropped
+    let sensor_pool = SensorPool::new(config.sensor_workers);
+    // Create bridge list
+    let mut bridge_list: HashMap<ActorId, Box<dyn ActorBridge>> = HashMap::new();
+    let namespaces = Arc::new(Mutex::new(NamespaceRegistry::new(config.namespace_source)));
+
+    // The control integrates over the simulation time of the steps, never the wall-clock one
+    let mut last_step_sec = None;
+    // Create clock publisher
+    let mut tick_clock = TickClock::new();
+    let publish_failures = Arc::new(AtomicU64::new(0));
+    let simulator_clock =
+        SimulatorClock::new(z_session.clone(), bridge_config, publish_failures.clone())?;
+    let create_lane_markers = |world: &World| {
+        config
+            .carla_lanes
+            .then(|| {
+                LaneMarkers::new(
+                    z_session.clone(),
+                    &world.map(),
+                    config.lane_resolution,
+                    cdr_endianness,
+                    &bridge_config.topics,
+                )
+            })
+            .transpose()
+    };
+    let mut lane_markers = create_lane_markers(&world)?;
+    let mut debug_draw =
+        DebugDraw::new(z_session.clone(), config.debug_draw, &bridge_config.topics)?;
+    let _topic_caches = TopicCaches::new(
+        z_session.clone(),
+        &config.topic_cache,
+        &bridge_config.topics,
+    )?;
+    let heartbeat = Heartbeat::start(
+        z_session.clone(),
+        &bridge_config.topics,
+        config.watchdog,
+        cdr_endianness,
+    )?;
+    let mut vehicle_overrides: Vec<_> = bridge_config.vehicles.keys().cloned().collect();
+    vehicle_overrides.sort();
+    let mut role_overrides: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for role_override in bridge_config.role_overrides.files() {
+        role_overrides
+            .entry(role_override.pattern.clone())
+            .or_default()
+            .push(role_override.file.display().to_string());
+    }
+    let mut bridge_info = BridgeInfo::new(
+        client.server_version(),
+        ConfigSummary {
+            carla_address: config.carla_address.clone(),
+            carla_port: config.carla_port,
+            zenoh_listen: config
+                .zenoh_listen
+                .iter()
+                .map(|endpoint| ConfigSummary::strip_endpoint(endpoint))
+                .collect(),
+            asynchronous,
+            fixed_delta_seconds,
+            loop_rate: config.loop_rate,
+            role_name_prefix: bridge_config.role_name_prefix.clone(),
+            namespace_source: format!("{:?}", config.namespace_source),
+            autoware_version: bridge_config.autoware_version.to_string(),
+            cdr_endianness: format!("{cdr_endianness:?}"),
+            vehicle_overrides,
+            role_overrides,
+        },
+    );
+    let info_queryable =
+        InfoQueryable::new(z_session.clone(), &bridge_info, &bridge_config.topics)?;
+    let mut actor_queryable = ActorQueryable::new(z_session.clone(), &bridge_config.topics)?;
+    let spawn_point_queryable = SpawnPointQueryable::new(
+        z_session.clone(),
+        &bridge_config.topics,
+        &world.map(),
+        actor_queryable.snapshot(),
+    )?;
+    let reset_queryable = ResetQueryable::new(z_session.clone(), &bridge_config.topics)?;
+    let mut recorder_queryable = RecorderQueryable::new(z_session.clone(), &bridge_config.topics)?;
+    let mut load_world_queryable =
+        LoadWorldQueryable::new(z_session.clone(), &bridge_config.topics, config.dry_run)?;
+    let bridge_list_queryable = BridgeListQueryable::new(z_session.clone(), &bridge_config.topics)?;
+    let mut bridges_changed = true;
+    let mut bridge_events = BridgeEvents::new(z_session.clone(), &bridge_config.topics)?;
+    let mut sim_stats = SimStats::new(
+        z_session.clone(),
+        config.min_real_time_factor,
+        bridge_config,
+        publish_failures.clone(),
+    )?;
+    let mut carla_stats = CarlaStats::new(
+        z_session.clone(),
+        config.max_frame_time,
+        bridge_config,
+        publish_failures,
+    )?;
+    // The census of carla_stats reads the snapshot of the discovery, never the world
+    let actor_snapshot = actor_queryable.snapshot();
+
+    loop {
+        scheduler.wait();
+        let loaded_world = load_world_queryable.load(&mut client, &heartbeat);
+        // After a load_world(), here or from another client, every actor handle and the world
+        // are stale
+        let current_world = client.world();
+        if current_world.id() != world.id() {
+            world = current_world;
+            let new_map_name = world.map().name();
+            info!("The CARLA world was reloaded: {map_name} => {new_map_name}. Rebuild all the bridges");
+            map_name = new_map_name;
+            for id in bridge_list.keys() {
+                bridge_events.destroyed(*id, EventReason::Despawned, None);
+            }
+            bridge_list.clear();
+            bridges_changed = true;
+            if !config.dry_run {
+                apply_carla_settings(&mut world, asynchronous, fixed_delta_seconds);
+            }
+            lane_markers = create_lane_markers(&world)?;
+            spawn_point_queryable.set_map(&world.map());
+            debug_draw.reset();
+            sim_stats.restart();
+            carla_stats.restart();
+            // The time of the new episode starts over
+            tick_clock = TickClock::new();
+            last_step_sec = None;
+            if let Some(replay) = &mut replay {
+                replay.restart_clock();
+            }
+        }
+        if !(asynchronous || config.dry_run) {
+            let tick_start = Instant::now();
+            world.tick();
+            carla_stats.record_tick_wait(tick_start.elapsed());
+        }
+        let mut run_step = true;
+        {
+            let mut actor_list: HashMap<ActorId, _> = world
+                .actors()
+                .iter()
+                .map(|actor| (actor.id(), actor))
+                .collect();
+            actor_queryable.refresh(actor_list.values());
+            let prev_actor_ids: HashSet<u32> = bridge_list.keys().cloned().collect();
+            let cur_actor_ids: HashSet<u32> = actor_list.keys().cloned().collect();
+            let added_ids = &cur_actor_ids - &prev_actor_ids;
+            let deleted_ids = &prev_actor_ids - &cur_actor_ids;
+
+            for id in added_ids {
+                let actor = actor_list.remove(&id).unwrap();
+                let type_id = actor.type_id();
+                let bridge = match bridge::actor_bridge::create_bridge(
+                    z_session.clone(),
+                    actor,
+                    bridge_config,
+                    &namespaces,
+                    &sensor_pool,
+                ) {
+                    Ok(bridge) => bridge,
+                    Err(Error::OwnerlessSensor { sensor_id }) => {
+                        debug!(
+                            "Ignore the sensor with ID {sensor_id} is not attached to any vehicle."
+                        );
+                        continue;
+                    }
+                    Err(Error::Npc { npc_role_name }) => {
+                        debug!("Ignore NPC vehicle {npc_role_name}.");
+                        continue;
+                    }
+                    Err(err) => {
+                        bridge_events.creation_failed(id, type_id, err.to_string());
+                        return Err(err);
+                    }
+                };
+                bridge_events.created(bridge.as_ref());
+                bridge_list.insert(id, bridge);
+                bridges_changed = true;
+                info!("Actor {id} created");
+            }
+
+            for id in deleted_ids {
+                bridge_list.remove(&id).unwrap();
+                bridge_events.destroyed(id, EventReason::Despawned, None);
+                bridges_changed = true;
+                info!("Actor {id} deleted");
+                run_step = false; // If there is actors removed, reget all the actor's list
+            }
+        }
+
+        if bridges_changed {
+            bridge_list_queryable.update(bridge_list.values().map(|bridge| bridge.as_ref()));
+            bridge_info.set_vehicles(
+                bridge_list.values().map(|bridge| bridge.as_ref()),
+                bridge_config,
+            );
+            info_queryable.update(&bridge_info)?;
+            bridges_changed = false;
+        }
+
+        reset_queryable.handle(|| {
+            let mut report = ResetReport::default();
+            bridge_list
+                .values_mut()
+                .for_each(|bridge| bridge.reset(&mut report));
+            debug_draw.reset();
+            report.debug_drawings = true;
+            report
+        });
+        recorder_queryable.handle(&mut client);
+        heartbeat.set_recording(recorder_queryable.recording());
+        if loaded_world {
+            load_world_queryable.finish(&map_name);
+        }
+
+        // We only run step while there is no actor removed. Avoid getting vehicles failed
+        if run_step {
+            let world_snapshot = world.snapshot();
+            let timestamp = world_snapshot.timestamp();
+            let stamp = tick_clock.tick(timestamp.elapsed_seconds);
+            let sec = stamp.to_secs_f64();
+            let elapsed_sec =
+                last_step_sec.map_or(timestamp.delta_seconds, |last_sec: f64| sec - last_sec);
+            last_step_sec = Some(sec);
+            let tick = TickSnapshot {
+                stamp,
+                frame: timestamp.frame as u64,
+                actors: bridge_list
+                    .keys()
+                    .filter_map(|id| {
+                        let actor = world_snapshot.find(*id)?;
+                        let motion = ActorMotion {
+                            transform: actor.transform(),
+                            velocity: actor.velocity(),
+                            angular_velocity: actor.angular_velocity(),
+                        };
+                        Some((*id, motion))
+                    })
+                    .collect(),
+                zenoh_lost: !config.deterministic && heartbeat.zenoh_lost(),
+            };
+            let due = tick_schedule.tick(sec, elapsed_sec);
+            for (id, bridge) in &mut bridge_list {
+                if let Err(err) = actor_bridge::run_tick(bridge.as_mut(), elapsed_sec, &tick, due) {
+                    bridge_events.destroyed(*id, EventReason::Error, Some(err.to_string()));
+                    return Err(err);
+                }
+            }
+            simulator_clock.publish_clock(Some(stamp))?;
+            simulator_clock.publish_frame(&FrameReport {
+                frame: tick.frame,
+                episode_id: world_snapshot.id(),
+                stamp: sec,
+                elapsed_seconds: timestamp.elapsed_seconds,
+            })?;
+            sim_stats.update(
+                sec,
+                tick.frame,
+                scheduler.overruns(),
+                || {
+                    bridge_list
+                        .values()
+                        .filter_map(|bridge| bridge.dropped_frames())
+                        .collect()
+                },
+                || {
+                    bridge_list
+                        .values()
+                        .filter_map(|bridge| bridge.frame_lag())
+                        .collect()
+                },
+            )?;
+            carla_stats.update(
+                sec,
+                tick.frame,
+                timestamp.platform_timestamp,
+                &actor_snapshot.load(),
+                || {
+                    let settings = world.settings();
+                    (settings.synchronous_mode, settings.no_rendering_mode)
+                },
+            )?;
+            if let Some(lane_markers) = &mut lane_markers {
+                lane_markers.update()?;
+            }
+            debug_draw.update(&mut world, sec, || {
+                bridge_list
+                    .values()
+                    .filter(|bridge| bridge.kind() == BridgeKind::Vehicle)
+                    .filter_map(|bridge| {
+                        let description = bridge.describe()?;
+                        let motion = tick.actors.get(&description.actor_id)?;
+                        let translation = motion.transform.translation;
+                        Some(HudVehicle {
+                            namespace: description.namespace,
+                            location: Location::new(translation.x, translation.y, translation.z),
+                            speed: motion.velocity.norm(),
+                        })
+                    })
+                    .collect()
+            });
+            let count = |kind| {
+                bridge_list
+                    .values()
+                    .filter(|bridge| bridge.kind() == kind)
+                    .count()
+            };
+            heartbeat.update(
+                count(BridgeKind::Vehicle),
+                count(BridgeKind::Walker),
+                count(BridgeKind::Sensor),
+                tick.frame,
+            );
+            if let Some(replay) = &mut replay {
+                replay.update(sec, count(BridgeKind::Vehicle));
+            }
+        }
+    }
+}
+
+/// Drive a single synthetic vehicle with the kinematic model instead of CARLA.
+///
+/// The model is integrated at the loop rate and the clock starts from zero.
+fn run_kinematic(z_session: Arc<Session>, config: &RuntimeConfig) -> Result<()> {
+    let bridge_config = &config.bridge;
+    let role_name = format!(
+        "{}{}",
+        bridge_config.role_name_prefix, config.kinematic.vehicle_name
+    );
+    info!("Drive {role_name} with the kinematic model, without CARLA");
+    let vehicle = KinematicVehicle::new(KINEMATIC_ACTOR_ID, role_name, config.kinematic.clone());
+    let namespaces = Arc::new(Mutex::new(NamespaceRegistry::new(config.namespace_source)));
+    let mut bridge = VehicleBridge::new(
+        z_session.clone(),
+        Box::new(vehicle.clone()),
+        bridge_config,
+        namespaces,
+    )?;
+    let publish_failures = Arc::new(AtomicU64::new(0));
+    let simulator_clock =
+        SimulatorClock::new(z_session.clone(), bridge_config, publish_failures.clone())?;
+    let heartbeat = Heartbeat::start(
+        z_session.clone(),
+        &bridge_config.topics,
+        config.watchdog,
+        bridge_config.cdr_endianness,
+    )?;
+    let reset_queryable = ResetQueryable::new(z_session.clone(), &bridge_config.topics)?;
+    let bridge_list_queryable = BridgeListQueryable::new(z_session.clone(), &bridge_config.topics)?;
+    bridge_list_queryable.update([&bridge as &dyn ActorBridge].into_iter());
+    let mut bridge_events = BridgeEvents::new(z_session.clone(), &bridge_config.topics)?;
+    bridge_events.created(&bridge);
+    let mut sim_stats = SimStats::new(
+        z_session,
+        config.min_real_time_factor,
+        bridge_config,
+        publish_failures,
+    )?;
+    let mut tick_schedule = TickSchedule::new(config.status_rate, config.control_rate);
+    let period = Duration::from_secs_f64(1.0 / config.loop_rate);
+    let mut scheduler = FixedRate::new(period);
+    let mut frame: u64 = 0;
+    loop {
+        scheduler.wait();
+        frame += 1;
+        let stamp = TimeStamp::from_secs_f64(frame as f64 * period.as_secs_f64());
+        let sec = stamp.to_secs_f64();
+        reset_queryable.handle(|| {
+            let mut report = ResetReport::default();
+            bridge.reset(&mut report);
+            report
+        });
+        let tick = TickSnapshot {
+            zenoh_lost: !config.deterministic && heartbeat.zenoh_lost(),
+            ..TickSnapshot::without_world(stamp, frame)
+        };
+        let stepped = step_kinematic(
+            &vehicle,
+            &mut bridge,
+            &mut tick_schedule,
+            &tick,
+            period.as_secs_f64(),
+        );
+        if let Err(err) = stepped {
+            bridge_events.destroyed(
+                KINEMATIC_ACTOR_ID,
+                EventReason::Error,
+                Some(err.to_string()),
+            );
+            return Err(err);
+        }
+        simulator_clock.publish_clock(Some(stamp))?;
+        sim_stats.update(
+            sec,
+            frame,
+            scheduler.overruns(),
+            BTreeMap::new,
+            BTreeMap::new,
+        )?;
+        heartbeat.update(1, 0, 0, frame);
+    }
+}
+
+/// Integrate the kinematic model over the period, then bridge it like a tick of CARLA
+fn step_kinematic(
+    vehicle: &KinematicVehicle,
+    bridge: &mut VehicleBridge,
+    tick_schedule: &mut TickSchedule,
+    tick: &TickSnapshot,
+    period: f64,
+) -> Result<()> {
+    vehicle.advance(period);
+    let due = tick_schedule.tick(tick.stamp.to_secs_f64(), period);
+    actor_bridge::run_tick(bridge, period, tick, due)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bridge::mock_vehicle::MockVehicle,
+        cdr_utils::{self, Endianness},
+        command_log::{CommandKind, CommandRecorder},
+        topics,
+    };
+    use nalgebra::Vector3;
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use zenoh_ros_type::{
+        autoware_auto_control_msgs::{
+            AckermannControlCommand, AckermannLateralCommand, LongitudinalCommand,
+        },
+        autoware_auto_vehicle_msgs::VelocityReport,
+    };
+
+    /// The session of the runtime, without listening nor scouting
+    fn session(zenoh_timestamping: bool) -> Arc<Session> {
+        let config = RuntimeConfig {
+            zenoh_listen: Vec::new(),
+            zenoh_timestamping,
+            ..Default::default()
+        };
+        let mut zenoh_config = zenoh_config(&config).unwrap();
+        zenoh_config
+            .insert_json5("scouting/multicast/enabled", "false")
+            .unwrap();
+        Arc::new(zenoh::open(zenoh_config).res().unwrap())
+    }
+
+    /// The first velocity report of a mock vehicle, published by a bridge on `z_session`
+    fn velocity_sample(z_session: Arc<Session>) -> Sample {
+        let config = BridgeConfig::default();
+        let vehicle = MockVehicle::new(1, "autoware_ego");
+        vehicle.set_velocity(Vector3::new(1.0, 0.0, 0.0));
+        let namespaces = Arc::new(Mutex::new(NamespaceRegistry::default()));
+        let mut bridge =
+            VehicleBridge::new(z_session.clone(), Box::new(vehicle), &config, namespaces).unwrap();
+        let subscriber = z_session
+            .declare_subscriber(config.topics.key("ego", topics::VELOCITY_STATUS))
+            .res()
+            .unwrap();
+        let stamp = TimeStamp::from_secs_f64(0.05);
+        bridge
+            .step(0.05, &TickSnapshot::without_world(stamp, 1))
+            .unwrap();
+        bridge.publish_status(stamp).unwrap();
+        subscriber.recv_timeout(Duration::from_secs(2)).unwrap()
+    }
+
+    #[test]
+    fn the_publications_carry_the_hlc_time() {
+        let before = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let sample = velocity_sample(session(true));
+        let after = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let time = sample.timestamp.unwrap().get_time().to_duration();
+        // The HLC time follows the wall clock, to its NTP64 resolution
+        let resolution = Duration::from_millis(1);
+        assert!(time + resolution >= before && time <= after + resolution);
+    }
+
+    #[test]
+    fn the_peer_only_stamps_if_enabled() {
+        assert!(velocity_sample(session(false)).timestamp.is_none());
+    }
+
+    /// A command log of one drive of the kinematic vehicle: pull away, turn, then stop
+    fn command_log(name: &str) -> std::path::PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("runtime_test_{name}_{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut recorder = CommandRecorder::open(&path, "ego").unwrap();
+        for (timestamp, speed, acceleration, steering_tire_angle) in [
+            (10.0, 5.0, 3.0, 0.0),
+            (11.0, 8.0, 3.0, 0.1),
+            (12.0, 8.0, 1.0, -0.05),
+            (13.0, 0.0, -3.0, 0.0),
+        ] {
+            let stamp = TimeStamp::from_secs_f64(timestamp);
+            let cmd = AckermannControlCommand {
+                stamp: stamp.to_msg(),
+                lateral: AckermannLateralCommand {
+                    stamp: stamp.to_msg(),
+                    steering_tire_angle,
+                    steering_tire_rotation_rate: 0.0,
+                },
+                longitudinal: LongitudinalCommand {
+                    stamp: stamp.to_msg(),
+                    speed,
+                    acceleration,
+                    jerk: 0.0,
+                },
+            };
+            let payload = cdr_utils::serialize(&cmd, Endianness::Little).unwrap();
+            recorder
+                .record(CommandKind::Control, timestamp, &payload)
+                .unwrap();
+        }
+        path
+    }
+
+    fn deterministic_kinematic_config(replay_commands: &std::path::Path) -> RuntimeConfig {
+        let mut config = RuntimeConfig {
+            backend: Backend::Kinematic,
+            deterministic: true,
+            ..Default::default()
+        };
+        config.bridge.vehicle.replay_commands = Some(replay_commands.to_path_buf());
+        config
+    }
+
+    /// The velocity reports of a run of the kinematic backend, on a session of its own
+    fn kinematic_velocity_trace(config: &RuntimeConfig, frames: u64) -> Vec<Vec<u8>> {
+        let z_session = session(false);
+        let bridge_config = &config.bridge;
+        let role_name = format!(
+            "{}{}",
+            bridge_config.role_name_prefix, config.kinematic.vehicle_name
+        );
+        let vehicle =
+            KinematicVehicle::new(KINEMATIC_ACTOR_ID, role_name, config.kinematic.clone());
+        let namespaces = Arc::new(Mutex::new(NamespaceRegistry::new(config.namespace_source)));
+        let mut bridge = VehicleBridge::new(
+            z_session.clone(),
+            Box::new(vehicle.clone()),
+            bridge_config,
+            namespaces,
+        )
+        .unwrap();
+        let subscriber = z_session
+            .declare_subscriber(
+                bridge_config
+                    .topics
+                    .key(&config.kinematic.vehicle_name, topics::VELOCITY_STATUS),
+            )
+            .res()
+            .unwrap();
+        let mut tick_schedule = TickSchedule::new(config.status_rate, config.control_rate);
+        let period = Duration::from_secs_f64(1.0 / config.loop_rate).as_secs_f64();
+        (1..=frames)
+            .map(|frame| {
+                let stamp = TimeStamp::from_secs_f64(frame as f64 * period);
+                let tick = TickSnapshot::without_world(stamp, frame);
+                step_kinematic(&vehicle, &mut bridge, &mut tick_schedule, &tick, period).unwrap();
+                let sample = subscriber.recv_timeout(Duration::from_secs(2)).unwrap();
+                sample.payload.contiguous().to_vec()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn the_kinematic_backend_reproduces_the_velocity_trace() {
+        let path = command_log("trace");
+        let config = deterministic_kinematic_config(&path);
+        config.validate().unwrap();
+        // 5 s at 20 Hz, past the last command
+        let first = kinematic_velocity_trace(&config, 100);
+        let second = kinematic_velocity_trace(&config, 100);
+        assert_eq!(first.len(), 100);
+        assert!(
+            first == second,
+            "the two runs published different velocity reports"
+        );
+
+        // The commands drove the vehicle, the trace isn't just a vehicle at rest
+        let speeds: Vec<f32> = first
+            .iter()
+            .map(|payload| {
+                cdr_utils::deserialize::<VelocityReport>(payload)
+                    .unwrap()
+                    .longitudinal_velocity
+            })
+            .collect();
+        let top_speed = speeds.iter().copied().fold(0.0, f32::max);
+        assert!(top_speed > 4.0, "{top_speed}");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    fn validation_error(config: &RuntimeConfig) -> String {
+        config.validate().unwrap_err().to_string()
+    }
+
+    #[test]
+    fn the_deterministic_mode_refuses_the_irreproducible_settings() {
+        let path = std::path::PathBuf::from("commands.log");
+        let kinematic = deterministic_kinematic_config(&path);
+        kinematic.validate().unwrap();
+        let mut carla = RuntimeConfig {
+            backend: Backend::Carla,
+            ..kinematic.clone()
+        };
+        carla.bridge.noise_seed = Some(213);
+        carla.validate().unwrap();
+
+        let mut asynchronous = carla.clone();
+        asynchronous.asynchronous = true;
+        assert!(validation_error(&asynchronous).contains("needs the synchronous mode"));
+
+        let mut dry_run = carla.clone();
+        dry_run.dry_run = true;
+        assert!(validation_error(&dry_run).contains("without --dry-run or --carla-replay"));
+
+        let mut replay = carla.clone();
+        replay.carla_replay = Some(String::from("town.log"));
+        assert!(validation_error(&replay).contains("without --dry-run or --carla-replay"));
+
+        let mut unseeded = carla.clone();
+        unseeded.bridge.noise_seed = None;
+        assert!(validation_error(&unseeded).contains("needs noise_seed"));
+
+        for config in [&kinematic, &carla] {
+            let mut unlogged = config.clone();
+            unlogged.bridge.vehicle.replay_commands = None;
+            assert!(validation_error(&unlogged).contains("--replay-commands"));
+
+            // Every vehicle of `[vehicles.<name>]` as well
+            let mut unlogged_vehicle = config.clone();
+            let mut vehicle = config.bridge.vehicle.clone();
+            vehicle.replay_commands = None;
+            unlogged_vehicle
+                .bridge
+                .vehicles
+                .insert(String::from("hero"), vehicle);
+            assert!(validation_error(&unlogged_vehicle).contains("--replay-commands"));
+        }
+
+        // The same settings are fine without the deterministic mode
+        for mut config in [asynchronous, dry_run, unseeded] {
+            config.deterministic = false;
+            config.validate().unwrap();
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use crate::utils::LogThrottle;
+use log::warn;
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Run a loop at a fixed rate.
+///
+/// The deadlines are kept on an absolute schedule, so the sleep errors don't accumulate.
+pub struct FixedRate {
+    period: Duration,
+    next_deadline: Instant,
+    iteration_start: Instant,
+    overruns: u64,
+    log: LogThrottle,
+}
+
+impl FixedRate {
+    pub fn new(period: Duration) -> FixedRate {
+        let now = Instant::now();
+        FixedRate {
+            period,
+            next_deadline: now + period,
+            iteration_start: now,
+            overruns: 0,
+            log: LogThrottle::new(Duration::from_secs(5)),
+        }
+    }
+
+    /// The number of iterations which took longer than the period
+    pub fn overruns(&self) -> u64 {
+        self.overruns
+    }
+
+    /// Sleep until the next deadline and start the next iteration.
+    ///
+    /// An iteration which missed its deadline is counted as an overrun, and the schedule
+    /// restarts from now instead of running the missed iterations back to back.
+    pub fn wait(&mut self) {
+        let now = Instant::now();
+        let iteration = now - self.iteration_start;
+        if now > self.next_deadline {
+            self.overruns += 1;
+            if self.log.ready() {
+                warn!(
+                    "The loop iteration took {iteration:?}, longer than the period {:?} ({} overruns so far)",
+                    self.period, self.overruns
+                );
+            }
+            self.next_deadline = now + self.period;
+        } else {
+            thread::sleep(self.next_deadline - now);
+            self.next_deadline += self.period;
+        }
+        self.iteration_start = Instant::now();
+    }
+}
+
+/// Something run at most at a rate on the simulation time, within a faster loop
+struct Subsample {
+    /// None runs every tick
+    period: Option<f64>,
+    next: f64,
+}
+
+impl Subsample {
+    fn new(rate: Option<f64>) -> Subsample {
+        Subsample {
+            period: rate.map(|rate| 1.0 / rate),
+            next: f64::NEG_INFINITY,
+        }
+    }
+
+    fn due(&mut self, timestamp: f64) -> bool {
+        let Some(period) = self.period else {
+            return true;
+        };
+        // The time starts over with a new world
+        let restarted = timestamp + period < self.next;
+        // Tolerate the rounding of the tick times, e.g. 0.04 s ticks at 25 Hz
+        if !restarted && timestamp < self.next - 1e-6 {
+            return false;
+        }
+        // Stay on the schedule, unless a tick was missed by more than a period
+        self.next = if restarted || timestamp - self.next >= period {
+            timestamp + period
+        } else {
+            self.next + period
+        };
+        true
+    }
+}
+
+/// What the bridges do in a tick, besides the step
+#[derive(Debug, Clone, Copy)]
+pub struct TickDue {
+    pub status: bool,
+    /// The seconds since the last control, if it's due
+    pub control_elapsed_sec: Option<f64>,
+}
+
+/// Publish the status and apply the control of the bridges at their own rates, `status_rate`
+/// and `control_rate` in `[loop]`, both at every tick by default.
+///
+/// A rate above the tick rate runs at every tick.
+pub struct TickSchedule {
+    status: Subsample,
+    control: Subsample,
+    control_elapsed_sec: f64,
+}
+
+impl TickSchedule {
+    pub fn new(status_rate: Option<f64>, control_rate: Option<f64>) -> TickSchedule {
+        TickSchedule {
+            status: Subsample::new(status_rate),
+            control: Subsample::new(control_rate),
+            control_elapsed_sec: 0.0,
+        }
+    }
+
+    /// What is due at the simulation time, `elapsed_sec` after the previous tick
+    pub fn tick(&mut self, timestamp: f64, elapsed_sec: f64) -> TickDue {
+        self.control_elapsed_sec += elapsed_sec;
+        let control_elapsed_sec = self
+            .control
+            .due(timestamp)
+            .then(|| std::mem::take(&mut self.control_elapsed_sec));
+        TickDue {
+            status: self.status.due(timestamp),
+            control_elapsed_sec,
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! The CARLA frames of the events of a sensor, to tell when the sensor falls behind the world.
+//!
+//! The callback records the frame and the stamp of every event, the main loop compares the
+//! latest frame with the one of the tick. A sensor with a `sensor_tick` longer than the
+//! fixed delta fires every few frames, so its lag counts the frames past its own interval.
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+/// Events kept for the mapping from frame to stamp, and to find the interval
+const HISTORY_LEN: usize = 32;
+
+/// Shared by the callback of a sensor and its bridge
+#[derive(Clone, Default)]
+pub struct SensorFrames {
+    /// (frame, stamp) of the latest events, the oldest first
+    events: Arc<Mutex<VecDeque<(u64, f64)>>>,
+}
+
+impl SensorFrames {
+    pub fn record(&self, frame: u64, stamp: f64) {
+        let mut events = self.events.lock().unwrap();
+        if events.len() == HISTORY_LEN {
+            events.pop_front();
+        }
+        events.push_back((frame, stamp));
+    }
+
+    /// The stamp of the event of the frame, if it's among the latest ones
+    pub fn stamp(&self, frame: u64) -> Option<f64> {
+        let events = self.events.lock().unwrap();
+        events
+            .iter()
+            .rev()
+            .find(|(event_frame, _)| *event_frame == frame)
+            .map(|(_, stamp)| *stamp)
+    }
+
+    /// The frame and the stamp of the latest event, None before the first one
+    pub fn latest(&self) -> Option<(u64, f64)> {
+        self.events.lock().unwrap().back().copied()
+    }
+
+    /// The frames between two events, the smallest gap seen lately, 1 without two events
+    pub fn interval(&self) -> u64 {
+        let events = self.events.lock().unwrap();
+        events
+            .iter()
+            .zip(events.iter().skip(1))
+            .map(|((previous, _), (next, _))| next.saturating_sub(*previous))
+            .filter(|gap| *gap > 0)
+            .min()
+            .unwrap_or(1)
+    }
+
+    /// How many frames of the world the sensor is behind, beyond its own interval.
+    /// None before the first event, which may take a while after the spawn.
+    pub fn lag(&self, world_frame: u64) -> Option<u64> {
+        let (latest_frame, _) = self.latest()?;
+        Some(
+            world_frame
+                .saturating_sub(latest_frame)
+                .saturating_sub(self.interval()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The events of a sensor firing every `interval` frames of 0.05 s, from `first` to `last`
+    fn fire(frames: &SensorFrames, first: u64, last: u64, interval: u64) {
+        for frame in (first..=last).step_by(interval as usize) {
+            frames.record(frame, frame as f64 * 0.05);
+        }
+    }
+
+    #[test]
+    fn no_lag_before_the_first_event() {
+        let frames = SensorFrames::default();
+        assert_eq!(frames.latest(), None);
+        assert_eq!(frames.interval(), 1);
+        assert_eq!(frames.lag(100), None);
+    }
+
+    #[test]
+    fn the_stamps_of_the_recorded_frames() {
+        let frames = SensorFrames::default();
+        fire(&frames, 10, 12, 1);
+        assert_eq!(frames.stamp(11), Some(11.0 * 0.05));
+        assert_eq!(frames.stamp(13), None);
+        assert_eq!(frames.latest(), Some((12, 12.0 * 0.05)));
+        // Only the latest events are kept
+        fire(&frames, 13, 13 + HISTORY_LEN as u64, 1);
+        assert_eq!(frames.stamp(12), None);
+        assert_eq!(frames.stamp(14), Some(14.0 * 0.05));
+    }
+
+    #[test]
+    fn a_sensor_of_every_frame_lags_by_its_missed_events() {
+        let frames = SensorFrames::default();
+        fire(&frames, 1, 20, 1);
+        assert_eq!(frames.lag(20), Some(0));
+        assert_eq!(frames.lag(21), Some(0));
+        // The events of frames 21 to 27 were dropped
+        assert_eq!(frames.lag(28), Some(7));
+        frames.record(28, 28.0 * 0.05);
+        assert_eq!(frames.lag(28), Some(0));
+    }
+
+    #[test]
+    fn a_slower_sensor_lags_beyond_its_interval() {
+        let frames = SensorFrames::default();
+        fire(&frames, 4, 40, 4);
+        assert_eq!(frames.interval(), 4);
+        assert_eq!(frames.lag(43), Some(0));
+        assert_eq!(frames.lag(44), Some(0));
+        assert_eq!(frames.lag(50), Some(6));
+        // A dropped event widens one gap, the interval stays the smallest one
+        fire(&frames, 48, 52, 4);
+        assert_eq!(frames.interval(), 4);
+    }
+
+    #[test]
+    fn a_repeated_frame_keeps_the_interval() {
+        let frames = SensorFrames::default();
+        fire(&frames, 2, 10, 2);
+        frames.record(10, 0.5);
+        assert_eq!(frames.interval(), 2);
+    }
+}
@@ -0,0 +1,649 @@
+//! Noise added to the sensor measurements and to the ground-truth pose, since CARLA's are
+//! perfect by default
+use crate::error::{Error, Result};
+use nalgebra::UnitQuaternion;
+use rand::{rngs::StdRng, SeedableRng};
+use rand_distr::{Distribution, Normal};
+use serde_derive::Deserialize;
+
+/// Equatorial radius of WGS 84 (in meters)
+const EARTH_RADIUS: f64 = 6_378_137.0;
+
+/// The random source of a single sensor.
+///
+/// With a seed, each sensor derives its own stream from the seed and its key expression,
+/// so a run is reproducible regardless of the order in which the sensors are spawned.
+pub struct NoiseRng {
+    rng: StdRng,
+}
+
+impl NoiseRng {
+    pub fn new(seed: Option<u64>, stream: &str) -> NoiseRng {
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed ^ fnv1a(stream)),
+            None => StdRng::from_entropy(),
+        };
+        NoiseRng { rng }
+    }
+
+    /// A sample of a zero-mean normal distribution. A zero sigma returns 0.
+    pub fn gaussian(&mut self, sigma: f64) -> f64 {
+        match Normal::new(0.0, sigma) {
+            Ok(normal) if sigma > 0.0 => normal.sample(&mut self.rng),
+            _ => 0.0,
+        }
+    }
+}
+
+/// A stable hash, unlike the one of std which may change between releases
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// A bias drifting as a random walk
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomWalk {
+    /// Spread of the walk after one second.
+    sigma: f64,
+    value: f64,
+}
+
+impl RandomWalk {
+    pub fn new(sigma: f64) -> RandomWalk {
+        RandomWalk { sigma, value: 0.0 }
+    }
+
+    /// Move the bias by `elapsed_sec` and return it
+    pub fn step(&mut self, rng: &mut NoiseRng, elapsed_sec: f64) -> f64 {
+        if elapsed_sec > 0.0 {
+            self.value += rng.gaussian(self.sigma * elapsed_sec.sqrt());
+        }
+        self.value
+    }
+}
+
+/// Noise and covariance of the GNSS fixes, `[sensors.gnss]` in the configuration file
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct GnssConfig {
+    /// Standard deviation of the east and north position (m).
+    pub horizontal_noise: f64,
+    /// Standard deviation of the altitude (m).
+    pub vertical_noise: f64,
+    /// Spread of the bias random walk after one second (m), zero for no drift.
+    pub bias_drift: f64,
+    /// Variances of east, north and up published in the fix (m^2). Unknown if not set.
+    pub position_covariance: Option<[f64; 3]>,
+    /// Also publish the fixes as poses in the map frame, with the geo reference of the map.
+    pub publish_pose: bool,
+    /// Where the antenna is on the vehicle, x forward, y left and z up from base_link (m).
+    /// Removed from the published pose.
+    pub antenna_offset: [f64; 3],
+}
+
+impl GnssConfig {
+    pub fn validate(&self) -> Result<()> {
+        let sigmas = [
+            ("horizontal_noise", self.horizontal_noise),
+            ("vertical_noise", self.vertical_noise),
+            ("bias_drift", self.bias_drift),
+        ];
+        let variances = self.position_covariance.unwrap_or_default();
+        let variances = variances
+            .iter()
+            .map(|variance| ("position_covariance", *variance));
+        match sigmas
+            .into_iter()
+            .chain(variances)
+            .find(|(_, value)| !(value.is_finite() && *value >= 0.0))
+        {
+            Some((name, _)) => Err(Error::Config {
+                reason: format!("gnss {name} must not be negative"),
+            }),
+            None if self.antenna_offset.iter().all(|offset| offset.is_finite()) => Ok(()),
+            None => Err(Error::Config {
+                reason: String::from("gnss antenna_offset must be finite"),
+            }),
+        }
+    }
+}
+
+/// The noise state of a GNSS sensor
+pub struct GnssNoise {
+    config: GnssConfig,
+    rng: NoiseRng,
+    /// East, north and up
+    bias: [RandomWalk; 3],
+    last_timestamp: Option<f64>,
+}
+
+impl GnssNoise {
+    pub fn new(config: GnssConfig, rng: NoiseRng) -> GnssNoise {
+        GnssNoise {
+            config,
+            rng,
+            bias: [RandomWalk::new(config.bias_drift); 3],
+            last_timestamp: None,
+        }
+    }
+
+    /// Add the noise to a fix in degrees and meters, at the simulation time `timestamp`
+    pub fn apply(
+        &mut self,
+        timestamp: f64,
+        latitude: f64,
+        longitude: f64,
+        altitude: f64,
+    ) -> (f64, f64, f64) {
+        let elapsed_sec = self
+            .last_timestamp
+            .map_or(0.0, |last| (timestamp - last).max(0.0));
+        self.last_timestamp = Some(timestamp);
+        let mut bias = [0.0; 3];
+        for (walk, value) in self.bias.iter_mut().zip(&mut bias) {
+            *value = walk.step(&mut self.rng, elapsed_sec);
+        }
+        let [east_bias, north_bias, up_bias] = bias;
+        let east = east_bias + self.rng.gaussian(self.config.horizontal_noise);
+        let north = north_bias + self.rng.gaussian(self.config.horizontal_noise);
+        let up = up_bias + self.rng.gaussian(self.config.vertical_noise);
+        let latitude_radius = EARTH_RADIUS * latitude.to_radians().cos();
+        (
+            latitude + (north / EARTH_RADIUS).to_degrees(),
+            longitude + (east / latitude_radius).to_degrees(),
+            altitude + up,
+        )
+    }
+
+    /// The row-major position covariance of the fix, if configured
+    pub fn covariance(&self) -> Option<[f64; 9]> {
+        self.config
+            .position_covariance
+            .map(|[east, north, up]| [east, 0.0, 0.0, 0.0, north, 0.0, 0.0, 0.0, up])
+    }
+}
+
+/// Noise and covariance of the IMU, `[sensors.imu]` in the configuration file.
+///
+/// The noise is added on top of whatever the sensor blueprint is configured with.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct ImuConfig {
+    /// Row-major covariances published in the message. The EKF of Autoware rejects zeros.
+    pub orientation_covariance: [f64; 9],
+    pub angular_velocity_covariance: [f64; 9],
+    pub linear_acceleration_covariance: [f64; 9],
+    /// Standard deviation of the yaw (rad).
+    pub orientation_noise: f64,
+    /// Standard deviation of each axis of the angular velocity (rad/s).
+    pub angular_velocity_noise: f64,
+    /// Standard deviation of each axis of the linear acceleration (m/s^2).
+    pub linear_acceleration_noise: f64,
+    /// Spread of the gyroscope bias random walk after one second (rad/s).
+    pub angular_velocity_bias_drift: f64,
+    /// Spread of the accelerometer bias random walk after one second (m/s^2).
+    pub linear_acceleration_bias_drift: f64,
+}
+
+impl Default for ImuConfig {
+    fn default() -> Self {
+        ImuConfig {
+            orientation_covariance: diagonal(1e-4),
+            angular_velocity_covariance: diagonal(1e-4),
+            linear_acceleration_covariance: diagonal(1e-2),
+            orientation_noise: 0.0,
+            angular_velocity_noise: 0.0,
+            linear_acceleration_noise: 0.0,
+            angular_velocity_bias_drift: 0.0,
+            linear_acceleration_bias_drift: 0.0,
+        }
+    }
+}
+
+fn diagonal(variance: f64) -> [f64; 9] {
+    [variance, 0.0, 0.0, 0.0, variance, 0.0, 0.0, 0.0, variance]
+}
+
+impl ImuConfig {
+    pub fn validate(&self) -> Result<()> {
+        let sigmas = [
+            ("orientation_noise", self.orientation_noise),
+            ("angular_velocity_noise", self.angular_velocity_noise),
+            ("linear_acceleration_noise", self.linear_acceleration_noise),
+            (
+                "angular_velocity_bias_drift",
+                self.angular_velocity_bias_drift,
+            ),
+            (
+                "linear_acceleration_bias_drift",
+                self.linear_acceleration_bias_drift,
+            ),
+        ];
+        if let Some((name, _)) = sigmas
+            .iter()
+            .find(|(_, sigma)| !(sigma.is_finite() && *sigma >= 0.0))
+        {
+            return Err(Error::Config {
+                reason: format!("imu {name} must not be negative"),
+            });
+        }
+        let covariances = [
+            ("orientation_covariance", self.orientation_covariance),
+            (
+                "angular_velocity_covariance",
+                self.angular_velocity_covariance,
+            ),
+            (
+                "linear_acceleration_covariance",
+                self.linear_acceleration_covariance,
+            ),
+        ];
+        for (name, covariance) in covariances {
+            let diagonal = [covariance[0], covariance[4], covariance[8]];
+            if covariance.iter().any(|value| !value.is_finite())
+                || diagonal.iter().any(|variance| *variance < 0.0)
+            {
+                return Err(Error::Config {
+                    reason: format!("imu {name} must be finite with a non-negative diagonal"),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The noise state of an IMU
+pub struct ImuNoise {
+    config: ImuConfig,
+    rng: NoiseRng,
+    angular_velocity_bias: [RandomWalk; 3],
+    linear_acceleration_bias: [RandomWalk; 3],
+    last_timestamp: Option<f64>,
+}
+
+impl ImuNoise {
+    pub fn new(config: ImuConfig, rng: NoiseRng) -> ImuNoise {
+        ImuNoise {
+            config,
+            rng,
+            angular_velocity_bias: [RandomWalk::new(config.angular_velocity_bias_drift); 3],
+            linear_acceleration_bias: [RandomWalk::new(config.linear_acceleration_bias_drift); 3],
+            last_timestamp: None,
+        }
+    }
+
+    pub fn config(&self) -> &ImuConfig {
+        &self.config
+    }
+
+    /// An offset to add to the yaw (rad)
+    pub fn yaw(&mut self) -> f64 {
+        self.rng.gaussian(self.config.orientation_noise)
+    }
+
+    /// Add the noise to a measurement at the simulation time `timestamp`
+    pub fn apply(
+        &mut self,
+        timestamp: f64,
+        angular_velocity: [f64; 3],
+        linear_acceleration: [f64; 3],
+    ) -> ([f64; 3], [f64; 3]) {
+        let elapsed_sec = self
+            .last_timestamp
+            .map_or(0.0, |last| (timestamp - last).max(0.0));
+        self.last_timestamp = Some(timestamp);
+        let mut angular_velocity = angular_velocity;
+        for (value, bias) in angular_velocity
+            .iter_mut()
+            .zip(&mut self.angular_velocity_bias)
+        {
+            *value += bias.step(&mut self.rng, elapsed_sec)
+                + self.rng.gaussian(self.config.angular_velocity_noise);
+        }
+        let mut linear_acceleration = linear_acceleration;
+        for (value, bias) in linear_acceleration
+            .iter_mut()
+            .zip(&mut self.linear_acceleration_bias)
+        {
+            *value += bias.step(&mut self.rng, elapsed_sec)
+                + self.rng.gaussian(self.config.linear_acceleration_noise);
+        }
+        (angular_velocity, linear_acceleration)
+    }
+}
+
+/// The variance published for an exact axis of the pose, since the EKF of Autoware rejects zeros
+pub const MIN_POSE_VARIANCE: f64 = 1e-6;
+
+/// The ground-truth pose of the vehicles and its noise, `[localization]` in the configuration
+/// file
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct LocalizationConfig {
+    /// Publish the pose of the vehicle in the map frame, in place of a pose estimator.
+    pub publish_pose: bool,
+    /// Standard deviation of x and y (m).
+    pub horizontal_noise: f64,
+    /// Standard deviation of z (m).
+    pub vertical_noise: f64,
+    /// Standard deviation of the yaw (rad).
+    pub yaw_noise: f64,
+    /// Spread of the position bias random walk after one second (m), zero for no drift.
+    pub bias_drift: f64,
+    /// Spread of the yaw bias random walk after one second (rad), zero for no drift.
+    pub yaw_bias_drift: f64,
+    /// The pose is published this late behind the simulation (ms).
+    pub latency_ms: u64,
+    /// Variances of x, y, z, roll, pitch and yaw published in the pose. If not set, the
+    /// squares of the noise, at least `MIN_POSE_VARIANCE`.
+    pub covariance: Option<[f64; 6]>,
+}
+
+impl LocalizationConfig {
+    pub fn validate(&self) -> Result<()> {
+        let sigmas = [
+            ("horizontal_noise", self.horizontal_noise),
+            ("vertical_noise", self.vertical_noise),
+            ("yaw_noise", self.yaw_noise),
+            ("bias_drift", self.bias_drift),
+            ("yaw_bias_drift", self.yaw_bias_drift),
+        ];
+        let variances = self.covariance.unwrap_or_default();
+        let variances = variances.iter().map(|variance| ("covariance", *variance));
+        match sigmas
+            .into_iter()
+            .chain(variances)
+            .find(|(_, value)| !(value.is_finite() && *value >= 0.0))
+        {
+            Some((name, _)) => Err(Error::Config {
+                reason: format!("localization {name} must not be negative"),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// The delay of the publication (s)
+    pub fn latency(&self) -> f64 {
+        self.latency_ms as f64 / 1000.0
+    }
+
+    fn is_exact(&self) -> bool {
+        [
+            self.horizontal_noise,
+            self.vertical_noise,
+            self.yaw_noise,
+            self.bias_drift,
+            self.yaw_bias_drift,
+        ]
+        .iter()
+        .all(|sigma| *sigma == 0.0)
+    }
+}
+
+/// The noise state of the ground-truth pose of a vehicle
+pub struct PoseNoise {
+    config: LocalizationConfig,
+    rng: NoiseRng,
+    /// x, y and z in the map frame
+    bias: [RandomWalk; 3],
+    yaw_bias: RandomWalk,
+    last_timestamp: Option<f64>,
+}
+
+impl PoseNoise {
+    pub fn new(config: LocalizationConfig, rng: NoiseRng) -> PoseNoise {
+        PoseNoise {
+            config,
+            rng,
+            bias: [RandomWalk::new(config.bias_drift); 3],
+            yaw_bias: RandomWalk::new(config.yaw_bias_drift),
+            last_timestamp: None,
+        }
+    }
+
+    pub fn config(&self) -> &LocalizationConfig {
+        &self.config
+    }
+
+    /// Add the noise to a position (m) and an orientation in the map frame, at the simulation
+    /// time `timestamp`. Without noise, the pose is returned as it is.
+    pub fn apply(
+        &mut self,
+        timestamp: f64,
+        position: [f64; 3],
+        orientation: UnitQuaternion<f64>,
+    ) -> ([f64; 3], UnitQuaternion<f64>) {
+        if self.config.is_exact() {
+            return (position, orientation);
+        }
+        let elapsed_sec = self
+            .last_timestamp
+            .map_or(0.0, |last| (timestamp - last).max(0.0));
+        self.last_timestamp = Some(timestamp);
+        let sigmas = [
+            self.config.horizontal_noise,
+            self.config.horizontal_noise,
+            self.config.vertical_noise,
+        ];
+        let mut position = position;
+        for ((value, walk), sigma) in position.iter_mut().zip(&mut self.bias).zip(sigmas) {
+            *value += walk.step(&mut self.rng, elapsed_sec) + self.rng.gaussian(sigma);
+        }
+        let yaw = self.yaw_bias.step(&mut self.rng, elapsed_sec)
+            + self.rng.gaussian(self.config.yaw_noise);
+        // About the z axis of the map, which leaves the roll and the pitch
+        let orientation = UnitQuaternion::from_euler_angles(0.0, 0.0, yaw) * orientation;
+        (position, orientation)
+    }
+
+    /// The covariance of the published pose, in rows
+    pub fn covariance(&self) -> [[f64; 6]; 6] {
+        let config = &self.config;
+        let variances = config.covariance.unwrap_or_else(|| {
+            [
+                config.horizontal_noise,
+                config.horizontal_noise,
+                config.vertical_noise,
+                0.0,
+                0.0,
+                config.yaw_noise,
+            ]
+            .map(|sigma| (sigma * sigma).max(MIN_POSE_VARIANCE))
+        });
+        let mut covariance = [[0.0; 6]; 6];
+        for (axis, variance) in variances.into_iter().enumerate() {
+            covariance[axis][axis] = variance;
+        }
+        covariance
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn without_noise_the_measurement_passes_through() {
+        let mut noise = ImuNoise::new(ImuConfig::default(), NoiseRng::new(Some(1), "imu"));
+        let measurement = ([0.1, -0.2, 0.3], [1.0, 2.0, 9.81]);
+        assert_eq!(noise.apply(0.0, measurement.0, measurement.1), measurement);
+        assert_eq!(noise.apply(0.1, measurement.0, measurement.1), measurement);
+        assert_eq!(noise.yaw(), 0.0);
+    }
+
+    #[test]
+    fn a_seed_reproduces_the_noise_of_a_stream() {
+        let config = ImuConfig {
+            angular_velocity_noise: 0.01,
+            linear_acceleration_noise: 0.1,
+            angular_velocity_bias_drift: 0.001,
+            ..ImuConfig::default()
+        };
+        let run = |seed, stream| {
+            let mut noise = ImuNoise::new(config, NoiseRng::new(Some(seed), stream));
+            (0..10)
+                .map(|step| noise.apply(step as f64 * 0.1, [0.0; 3], [0.0; 3]))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(run(7, "ego/imu"), run(7, "ego/imu"));
+        assert_ne!(run(7, "ego/imu"), run(8, "ego/imu"));
+        // Every sensor has its own stream
+        assert_ne!(run(7, "ego/imu"), run(7, "other/imu"));
+    }
+
+    #[test]
+    fn the_noise_has_the_configured_spread() {
+        let mut rng = NoiseRng::new(Some(3), "spread");
+        let samples: Vec<_> = (0..10_000).map(|_| rng.gaussian(0.5)).collect();
+        let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+        let variance =
+            samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / samples.len() as f64;
+        assert!(mean.abs() < 0.02, "mean {mean}");
+        assert!(
+            (variance.sqrt() - 0.5).abs() < 0.02,
+            "sigma {}",
+            variance.sqrt()
+        );
+        assert_eq!(rng.gaussian(0.0), 0.0);
+    }
+
+    #[test]
+    fn the_bias_walks_only_with_time() {
+        let mut rng = NoiseRng::new(Some(1), "walk");
+        let mut walk = RandomWalk::new(1.0);
+        assert_eq!(walk.step(&mut rng, 0.0), 0.0);
+        let moved = walk.step(&mut rng, 1.0);
+        assert_ne!(moved, 0.0);
+        assert_eq!(walk.step(&mut rng, 0.0), moved);
+    }
+
+    #[test]
+    fn validate_the_imu_config() {
+        assert!(ImuConfig::default().validate().is_ok());
+        let negative = ImuConfig {
+            angular_velocity_noise: -0.1,
+            ..ImuConfig::default()
+        };
+        assert!(negative.validate().is_err());
+        let mut covariance = ImuConfig::default();
+        covariance.orientation_covariance[4] = -1.0;
+        assert!(covariance.validate().is_err());
+        let mut covariance = ImuConfig::default();
+        covariance.linear_acceleration_covariance[1] = f64::NAN;
+        assert!(covariance.validate().is_err());
+        // A negative correlation is a valid covariance
+        let mut covariance = ImuConfig::default();
+        covariance.angular_velocity_covariance[1] = -1e-5;
+        assert!(covariance.validate().is_ok());
+    }
+
+    #[test]
+    fn without_noise_the_pose_is_the_ground_truth() {
+        let config = LocalizationConfig {
+            publish_pose: true,
+            ..LocalizationConfig::default()
+        };
+        let mut noise = PoseNoise::new(config, NoiseRng::new(Some(1), "pose"));
+        let orientation = UnitQuaternion::from_euler_angles(0.01, -0.02, 2.5);
+        for (step, position) in [[12.5, -0.0, 0.3], [-7.25, 1e-9, 0.0]].iter().enumerate() {
+            let (noisy_position, noisy_orientation) =
+                noise.apply(step as f64 * 0.1, *position, orientation);
+            let bits = |values: &[f64]| values.iter().map(|v| v.to_bits()).collect::<Vec<_>>();
+            assert_eq!(bits(&noisy_position[..]), bits(&position[..]));
+            assert_eq!(
+                bits(noisy_orientation.coords.as_slice()),
+                bits(orientation.coords.as_slice())
+            );
+        }
+        // The EKF still gets an invertible covariance
+        let covariance = noise.covariance();
+        for (axis, row) in covariance.iter().enumerate() {
+            assert_eq!(row[axis], MIN_POSE_VARIANCE);
+        }
+    }
+
+    #[test]
+    fn the_pose_noise_has_the_configured_spread() {
+        let config = LocalizationConfig {
+            publish_pose: true,
+            horizontal_noise: 0.3,
+            vertical_noise: 0.1,
+            yaw_noise: 0.02,
+            ..LocalizationConfig::default()
+        };
+        let mut noise = PoseNoise::new(config, NoiseRng::new(Some(5), "ego/pose"));
+        let orientation = UnitQuaternion::from_euler_angles(0.0, 0.0, 1.0);
+        let samples: Vec<_> = (0..10_000)
+            .map(|step| {
+                let (position, noisy) =
+                    noise.apply(step as f64 * 0.05, [10.0, 20.0, 1.0], orientation);
+                let (roll, pitch, yaw) = noisy.euler_angles();
+                assert!(roll.abs() < 1e-9 && pitch.abs() < 1e-9);
+                [
+                    position[0] - 10.0,
+                    position[1] - 20.0,
+                    position[2] - 1.0,
+                    yaw - 1.0,
+                ]
+            })
+            .collect();
+        for (axis, sigma) in [0.3, 0.3, 0.1, 0.02].into_iter().enumerate() {
+            let values: Vec<_> = samples.iter().map(|sample| sample[axis]).collect();
+            let mean = values.iter().sum::<f64>() / values.len() as f64;
+            let variance =
+                values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / values.len() as f64;
+            assert!(mean.abs() < 0.05 * sigma, "axis {axis} mean {mean}");
+            assert!(
+                (variance.sqrt() - sigma).abs() < 0.03 * sigma,
+                "axis {axis} sigma {}",
+                variance.sqrt()
+            );
+        }
+        // The published covariance reflects the noise
+        let covariance = noise.covariance();
+        assert_eq!(covariance[0][0], 0.3 * 0.3);
+        assert_eq!(covariance[1][1], 0.3 * 0.3);
+        assert_eq!(covariance[2][2], 0.1 * 0.1);
+        assert_eq!(covariance[3][3], MIN_POSE_VARIANCE);
+        assert_eq!(covariance[5][5], 0.02 * 0.02);
+        assert_eq!(covariance[0][1], 0.0);
+        // A seed reproduces the noise
+        let mut again = PoseNoise::new(config, NoiseRng::new(Some(5), "ego/pose"));
+        let (position, _) = again.apply(0.0, [10.0, 20.0, 1.0], orientation);
+        assert_eq!(position[0] - 10.0, samples[0][0]);
+    }
+
+    #[test]
+    fn the_configured_pose_covariance_wins() {
+        let config = LocalizationConfig {
+            horizontal_noise: 0.3,
+            covariance: Some([1.0, 2.0, 3.0, 4.0, 5.0, 6.0]),
+            ..LocalizationConfig::default()
+        };
+        let noise = PoseNoise::new(config, NoiseRng::new(Some(1), "pose"));
+        let covariance = noise.covariance();
+        for (axis, row) in covariance.iter().enumerate() {
+            assert_eq!(row[axis], axis as f64 + 1.0);
+        }
+    }
+
+    #[test]
+    fn validate_the_localization_config() {
+        assert!(LocalizationConfig::default().validate().is_ok());
+        let negative = LocalizationConfig {
+            yaw_noise: -0.1,
+            ..LocalizationConfig::default()
+        };
+        assert!(negative.validate().is_err());
+        let covariance = LocalizationConfig {
+            covariance: Some([1.0, 1.0, f64::NAN, 1.0, 1.0, 1.0]),
+            ..LocalizationConfig::default()
+        };
+        assert!(covariance.validate().is_err());
+    }
+}
@@ -0,0 +1,167 @@
+//! The worker threads converting and serializing the sensor frames, `workers` in `[sensors]`
+use crate::frame_queue::{self, FrameSender, Recv, Wake};
+use log::{info, warn};
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// A worker with nothing to do checks for new sensors and the shutdown this often anyway
+const IDLE_WAIT: Duration = Duration::from_millis(100);
+/// At the shutdown, the frames still queued after this are abandoned
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Takes one frame of its sensor, if any
+type Job = Box<dyn FnMut() -> Poll + Send>;
+
+enum Poll {
+    Busy,
+    Idle,
+    Finished,
+}
+
+struct Worker {
+    wake: Arc<Wake>,
+    /// The sensors added since the worker last looked
+    incoming: Mutex<Vec<Job>>,
+    sensors: AtomicUsize,
+}
+
+/// A fixed number of threads sharing the sensors, so a multi-MB point cloud or image is
+/// converted away from both the CARLA callbacks and the main loop.
+///
+/// Each sensor stays with one worker, which takes a frame of each of its sensors in turn,
+/// so the frames of a sensor are published in order.
+pub struct SensorPool {
+    workers: Vec<Arc<Worker>>,
+    handles: Vec<JoinHandle<()>>,
+    /// Finish the queued frames and stop
+    draining: Arc<AtomicBool>,
+    /// Stop after the frame at hand
+    abandon: Arc<AtomicBool>,
+}
+
+impl SensorPool {
+    /// The physical cores minus 2, left to the main loop and zenoh
+    pub fn default_size() -> usize {
+        num_cpus::get_physical().saturating_sub(2).max(1)
+    }
+
+    pub fn new(size: usize) -> SensorPool {
+        let draining = Arc::new(AtomicBool::new(false));
+        let abandon = Arc::new(AtomicBool::new(false));
+        let workers: Vec<_> = (0..size.max(1))
+            .map(|_| {
+                Arc::new(Worker {
+                    wake: Arc::new(Wake::default()),
+                    incoming: Mutex::new(Vec::new()),
+                    sensors: AtomicUsize::new(0),
+                })
+            })
+            .collect();
+        let handles = workers
+            .iter()
+            .enumerate()
+            .map(|(index, worker)| {
+                let worker = worker.clone();
+                let draining = draining.clone();
+                let abandon = abandon.clone();
+                thread::Builder::new()
+                    .name(format!("sensor-worker-{index}"))
+                    .spawn(move || run_worker(&worker, &draining, &abandon))
+                    .expect("Failed to spawn a sensor worker")
+            })
+            .collect();
+        info!(
+            "Converting the sensor frames on {} worker threads",
+            workers.len()
+        );
+        SensorPool {
+            workers,
+            handles,
+            draining,
+            abandon,
+        }
+    }
+
+    /// Queue the frames of a sensor for the least loaded worker, which calls `process` on
+    /// them in order, until the returned sender is closed.
+    pub fn add<T: Send + 'static>(
+        &self,
+        name: String,
+        depth: usize,
+        mut process: impl FnMut(T) + Send + 'static,
+    ) -> FrameSender<T> {
+        let worker = self
+            .workers
+            .iter()
+            .min_by_key(|worker| worker.sensors.load(Ordering::Relaxed))
+            .unwrap();
+        let (tx, rx) = frame_queue::frame_queue(depth, worker.wake.clone());
+        worker.sensors.fetch_add(1, Ordering::Relaxed);
+        let job = move || match rx.try_recv() {
+            Recv::Frame(frame) => {
+                process(frame);
+                Poll::Busy
+            }
+            Recv::Empty => Poll::Idle,
+            Recv::Closed => {
+                info!("Sensor worker for {name} stop.");
+                Poll::Finished
+            }
+        };
+        worker.incoming.lock().unwrap().push(Box::new(job));
+        worker.wake.notify();
+        tx
+    }
+}
+
+fn run_worker(worker: &Worker, draining: &AtomicBool, abandon: &AtomicBool) {
+    let mut jobs: Vec<Job> = Vec::new();
+    loop {
+        jobs.append(&mut worker.incoming.lock().unwrap());
+        let mut busy = false;
+        jobs.retain_mut(|job| match job() {
+            Poll::Busy => {
+                busy = true;
+                true
+            }
+            Poll::Idle => true,
+            Poll::Finished => {
+                worker.sensors.fetch_sub(1, Ordering::Relaxed);
+                false
+            }
+        });
+        if abandon.load(Ordering::Relaxed) || (!busy && draining.load(Ordering::Relaxed)) {
+            break;
+        }
+        if !busy {
+            worker.wake.wait(IDLE_WAIT);
+        }
+    }
+}
+
+impl Drop for SensorPool {
+    fn drop(&mut self) {
+        self.draining.store(true, Ordering::Relaxed);
+        self.workers.iter().for_each(|worker| worker.wake.notify());
+        let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+        while Instant::now() < deadline && !self.handles.iter().all(|handle| handle.is_finished()) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let busy = self
+            .handles
+            .iter()
+            .filter(|handle| !handle.is_finished())
+            .count();
+        if busy > 0 {
+            // The threads finish the frame at hand on their own, nobody waits for them
+            warn!("Abandoned the queued sensor frames of {busy} workers at the shutdown");
+            self.abandon.store(true, Ordering::Relaxed);
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//! Services over zenoh queryables, for the requests which act on the actors.
+//!
+//! A service is a key, the types of its request and of its response, and a handler. The
+//! queryable decodes the requests in the zenoh thread and rejects the invalid ones at once.
+//! The owner of the registry then runs the handlers on its own thread with `dispatch`, e.g.
+//! a vehicle bridge in its step, so a handler may drive the actor. The reply carries the
+//! encoded response, or the reason of the failure as a reply error.
+//!
+//! The ROS 2 services follow the layout of zenoh-plugin-ros2dds, a queryable on the name of
+//! the service whose payloads are the CDR of the request and of the response. Their
+//! canonical names start with `rs/`, which `key_style` in `[topics]` handles like the `rt/`
+//! of the topics, so `plain` gives the keys of the plugin.
+use crate::{
+    cdr_utils::{self, Endianness},
+    error::Result,
+};
+use log::warn;
+use serde::{de::DeserializeOwned, Serialize};
+use std::sync::{
+    mpsc::{self, TrySendError},
+    Arc,
+};
+use zenoh::{
+    prelude::sync::*,
+    queryable::{Query, Queryable},
+};
+
+/// Requests of a service waiting for the dispatch, the next ones are rejected
+const QUEUE_DEPTH: usize = 16;
+
+/// How the requests and the responses of a service are encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// A ROS 2 service
+    Cdr(Endianness),
+    /// A service of the bridge, where an empty request stands for `null`
+    Json,
+}
+
+impl Encoding {
+    fn decode<T: DeserializeOwned>(self, payload: &[u8]) -> ServiceResult<T> {
+        match self {
+            Encoding::Cdr(_) => cdr_utils::deserialize(payload).map_err(|e| e.to_string()),
+            Encoding::Json if payload.is_empty() => {
+                serde_json::from_slice(b"null").map_err(|e| e.to_string())
+            }
+            Encoding::Json => serde_json::from_slice(payload).map_err(|e| e.to_string()),
+        }
+    }
+
+    fn encode<T: Serialize>(self, value: &T) -> ServiceResult<Vec<u8>> {
+        match self {
+            Encoding::Cdr(endianness) => {
+                cdr_utils::serialize(value, endianness).map_err(|e| e.to_string())
+            }
+            Encoding::Json => serde_json::to_vec(value).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// What a handler returns, the error is the reason replied to the client
+pub type ServiceResult<T> = std::result::Result<T, String>;
+
+/// The services of an owner, whose handlers take it as `&mut S`
+pub struct ServiceRegistry<'a, S> {
+    z_session: Arc<Session>,
+    services: Vec<Service<'a, S>>,
+}
+
+struct Service<'a, S> {
+    /// Run the handler on the requests received since the last dispatch
+    dispatch: Box<dyn FnMut(&mut S) + 'a>,
+    _queryable: Queryable<'a, ()>,
+}
+
+impl<'a, S> ServiceRegistry<'a, S> {
+    pub fn new(z_session: Arc<Session>) -> ServiceRegistry<'a, S> {
+        ServiceRegistry {
+            z_session,
+            services: Vec::new(),
+        }
+    }
+
+    /// Serve the key with the handler, run by the next `dispatch` after each request
+    pub fn register<Req, Resp, F>(
+        &mut self,
+        key: String,
+        encoding: Encoding,
+        mut handler: F,
+    ) -> Result<()>
+    where
+        Req: DeserializeOwned + Send + 'static,
+        Resp: Serialize,
+        F: FnMut(&mut S, Req) -> ServiceResult<Resp> + 'a,
+    {
+        let (tx, rx) = mpsc::sync_channel::<(Query, Req)>(QUEUE_DEPTH);
+        let cloned_key = key.clone();
+        let queryable = self
+            .z_session
+            .declare_queryable(&key)
+            .callback_mut(move |query| {
+                let payload = query
+                    .value()
+                    .map(|value| value.payload.contiguous().to_vec())
+                    .unwrap_or_default();
+                let request = match encoding.decode::<Req>(&payload) {
+                    Ok(request) => request,
+                    Err(e) => {
+                        warn!("Rejected an invalid request of {cloned_key}: {e}");
+                        reply(&cloned_key, query, Err(format!("Invalid request: {e}")));
+                        return;
+                    }
+                };
+                match tx.try_send((query, request)) {
+                    Ok(()) | Err(TrySendError::Disconnected(_)) => {}
+                    Err(TrySendError::Full((query, _))) => reply(
+                        &cloned_key,
+                        query,
+                        Err(String::from("Too many pending requests")),
+                    ),
+                }
+            })
+            .res()?;
+        let dispatch = move |state: &mut S| {
+            for (query, request) in rx.try_iter() {
+                let response =
+                    handler(state, request).and_then(|response| encoding.encode(&response));
+                reply(&key, query, response);
+            }
+        };
+        self.services.push(Service {
+            dispatch: Box::new(dispatch),
+            _queryable: queryable,
+        });
+        Ok(())
+    }
+
+    /// Run the handlers on the requests received since the last call
+    pub fn dispatch(&mut self, state: &mut S) {
+        for service in &mut self.services {
+            (service.dispatch)(state);
+        }
+    }
+}
+
+fn reply(key: &str, query: Query, payload: ServiceResult<Vec<u8>>) {
+    let result = payload
+        .map(|payload| Sample::new(query.key_expr().clone(), payload))
+        .map_err(Into::into);
+    if let Err(e) = query.reply(result).res() {
+        warn!("Failed to reply to {key}: {e}");
+    }
+}
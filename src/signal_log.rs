@@ -0,0 +1,178 @@
+//! `--signal-log`: a CSV row per control step of a vehicle, to tune the controllers offline.
+//!
+//! The columns, in this order:
+//!
+//! | column                  | unit  | meaning                                                  |
+//! |-------------------------|-------|----------------------------------------------------------|
+//! | sim_time                | s     | simulation time of the step                              |
+//! | source                  |       | ackermann, raw, fallback, comfortable_stop, emergency_stop or frozen |
+//! | cmd_speed               | m/s   | speed of the latest control_cmd                          |
+//! | cmd_acceleration        | m/s^2 | acceleration of the latest control_cmd                   |
+//! | cmd_steering_tire_angle | rad   | tire angle of the latest control_cmd                     |
+//! | speed                   | m/s   | measured longitudinal speed, negative in reverse         |
+//! | throttle                | 0-1   | applied throttle                                         |
+//! | brake                   | 0-1   | applied brake                                            |
+//! | steer                   | -1-1  | applied CARLA steer, positive to the right               |
+//! | pitch                   | rad   | measured pitch, before the pitch compensation            |
+//! | gear                    |       | gear of the vehicle                                      |
+use crate::{error::Result, utils::LogThrottle};
+use log::{error, info, warn};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// The first row of every file, the order of the fields of `SignalRow`
+pub const HEADER: &str = "sim_time,source,cmd_speed,cmd_acceleration,cmd_steering_tire_angle,speed,throttle,brake,steer,pitch,gear";
+/// How many rows may wait for the disk before new ones are dropped
+const QUEUE_DEPTH: usize = 1024;
+/// How often the written rows are flushed, so a crashed run keeps most of them
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct SignalRow {
+    pub sim_time: f64,
+    pub source: &'static str,
+    pub cmd_speed: f32,
+    pub cmd_acceleration: f32,
+    pub cmd_steering_tire_angle: f32,
+    pub speed: f32,
+    pub throttle: f32,
+    pub brake: f32,
+    pub steer: f32,
+    pub pitch: f32,
+    pub gear: i32,
+}
+
+impl SignalRow {
+    fn write_to(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        writeln!(
+            writer,
+            "{:.6},{},{},{},{},{},{},{},{},{},{}",
+            self.sim_time,
+            self.source,
+            self.cmd_speed,
+            self.cmd_acceleration,
+            self.cmd_steering_tire_angle,
+            self.speed,
+            self.throttle,
+            self.brake,
+            self.steer,
+            self.pitch,
+            self.gear
+        )
+    }
+}
+
+/// The signal log of a vehicle, written on a dedicated thread
+pub struct SignalLog {
+    tx: Option<SyncSender<SignalRow>>,
+    worker: Option<JoinHandle<()>>,
+    dropped: Arc<AtomicU64>,
+    drop_log: LogThrottle,
+    path: PathBuf,
+}
+
+impl SignalLog {
+    /// Create the file of the vehicle next to `base_path`, see `vehicle_path`
+    pub fn create(base_path: &Path, vehicle_name: &str) -> Result<SignalLog> {
+        let path = vehicle_path(base_path, vehicle_name);
+        let mut writer = BufWriter::new(File::create(&path)?);
+        writeln!(writer, "{HEADER}")?;
+        let (tx, rx) = mpsc::sync_channel(QUEUE_DEPTH);
+        let cloned_path = path.clone();
+        let worker = thread::spawn(move || write_rows(writer, rx, &cloned_path));
+        info!(
+            "Logging the control signals of {vehicle_name} into {}",
+            path.display()
+        );
+        Ok(SignalLog {
+            tx: Some(tx),
+            worker: Some(worker),
+            dropped: Arc::new(AtomicU64::new(0)),
+            drop_log: LogThrottle::new(Duration::from_secs(5)),
+            path,
+        })
+    }
+
+    /// Queue the row, never blocks the control loop
+    pub fn push(&mut self, row: SignalRow) {
+        let Some(tx) = &self.tx else {
+            return;
+        };
+        match tx.try_send(row) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                if self.drop_log.ready() {
+                    warn!(
+                        "Signal log {} can't keep up, {dropped} rows dropped so far",
+                        self.path.display()
+                    );
+                }
+            }
+            Err(TrySendError::Disconnected(_)) => {}
+        }
+    }
+}
+
+impl Drop for SignalLog {
+    fn drop(&mut self) {
+        // Close the channel, so the worker flushes the file
+        self.tx.take();
+        if let Some(worker) = self.worker.take() {
+            if worker.join().is_err() {
+                error!("Signal log thread of {} panicked", self.path.display());
+            }
+        }
+        let dropped = self.dropped.load(Ordering::Relaxed);
+        if dropped > 0 {
+            warn!("Signal log {}: {dropped} rows dropped", self.path.display());
+        }
+    }
+}
+
+fn write_rows(mut writer: BufWriter<File>, rx: Receiver<SignalRow>, path: &Path) {
+    let mut last_flush = Instant::now();
+    loop {
+        match rx.recv_timeout(FLUSH_INTERVAL) {
+            Ok(row) => {
+                if let Err(e) = row.write_to(&mut writer) {
+                    error!("Failed to write the signal log {}: {e}", path.display());
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        if last_flush.elapsed() >= FLUSH_INTERVAL {
+            if let Err(e) = writer.flush() {
+                error!("Failed to flush the signal log {}: {e}", path.display());
+            }
+            last_flush = Instant::now();
+        }
+    }
+    if let Err(e) = writer.flush() {
+        error!("Failed to flush the signal log {}: {e}", path.display());
+    }
+}
+
+/// `signals.csv` becomes `signals_ego.csv` for the vehicle `ego`, a `/` of a namespace becomes `_`
+pub fn vehicle_path(base_path: &Path, vehicle_name: &str) -> PathBuf {
+    let stem = base_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let vehicle_name = vehicle_name.replace('/', "_");
+    let file_name = match base_path.extension() {
+        Some(ext) => format!("{stem}_{vehicle_name}.{}", ext.to_string_lossy()),
+        None => format!("{stem}_{vehicle_name}"),
+    };
+    base_path.with_file_name(file_name)
+}
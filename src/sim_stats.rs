@@ -0,0 +1,108 @@
+use crate::{config::BridgeConfig, error::Result, status_publisher::StatusPublisher, topics};
+use log::warn;
+use serde_derive::Serialize;
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+use zenoh::prelude::sync::*;
+
+/// How often the simulation stats are published (in wall-clock seconds)
+const SIM_STATS_INTERVAL_SEC: f64 = 1.0;
+
+/// The stats message published as JSON on `*/bridge/stats/sim`
+#[derive(Serialize)]
+pub struct SimStatsReport {
+    pub stamp: f64,
+    pub frame: u64,
+    /// Simulated seconds per wall-clock second over the last interval
+    pub real_time_factor: f64,
+    /// Ticks processed since the start
+    pub ticks: u64,
+    /// Loop iterations which took longer than the period
+    pub overruns: u64,
+    /// Frames dropped by the full queue of each sensor since the start, by `<vehicle>/<sensor>`
+    pub dropped_sensor_frames: BTreeMap<String, u64>,
+    /// Frames of the world each sensor is behind at this tick, beyond its own interval
+    pub sensor_frame_lag: BTreeMap<String, u64>,
+    /// Failed puts of the clock, the frames and these stats since the start
+    pub publish_failures: u64,
+}
+
+/// Compare the simulation time with the wall-clock time, to tell if the simulation keeps up
+pub struct SimStats {
+    publisher: StatusPublisher,
+    publish_failures: Arc<AtomicU64>,
+    min_real_time_factor: Option<f64>,
+    window_start: Option<(Instant, f64)>,
+    ticks: u64,
+}
+
+impl SimStats {
+    /// `publish_failures` is shared with the `SimulatorClock`
+    pub fn new(
+        z_session: Arc<Session>,
+        min_real_time_factor: Option<f64>,
+        bridge_config: &BridgeConfig,
+        publish_failures: Arc<AtomicU64>,
+    ) -> Result<SimStats> {
+        let publisher = StatusPublisher::declare(
+            z_session,
+            bridge_config.topics.key("*", topics::SIM_STATS),
+            bridge_config.redeclare_after,
+            publish_failures.clone(),
+        )?;
+        Ok(SimStats {
+            publisher,
+            publish_failures,
+            min_real_time_factor,
+            window_start: None,
+            ticks: 0,
+        })
+    }
+
+    /// Start a new window, e.g. when the simulation time starts over with a new world
+    pub fn restart(&mut self) {
+        self.window_start = None;
+    }
+
+    /// Count a processed tick, and publish the stats once per interval
+    pub fn update(
+        &mut self,
+        timestamp: f64,
+        frame: u64,
+        overruns: u64,
+        dropped_sensor_frames: impl FnOnce() -> BTreeMap<String, u64>,
+        sensor_frame_lag: impl FnOnce() -> BTreeMap<String, u64>,
+    ) -> Result<()> {
+        self.ticks += 1;
+        let now = Instant::now();
+        let (start_instant, start_timestamp) = *self.window_start.get_or_insert((now, timestamp));
+        let wall_sec = (now - start_instant).as_secs_f64();
+        if wall_sec < SIM_STATS_INTERVAL_SEC {
+            return Ok(());
+        }
+        self.window_start = Some((now, timestamp));
+
+        let real_time_factor = (timestamp - start_timestamp) / wall_sec;
+        if matches!(self.min_real_time_factor, Some(min) if real_time_factor < min) {
+            warn!("The simulation runs at {real_time_factor:.2}x real time");
+        }
+        let report = SimStatsReport {
+            stamp: timestamp,
+            frame,
+            real_time_factor,
+            ticks: self.ticks,
+            overruns,
+            dropped_sensor_frames: dropped_sensor_frames(),
+            sensor_frame_lag: sensor_frame_lag(),
+            publish_failures: self.publish_failures.load(Ordering::Relaxed),
+        };
+        self.publisher.put(serde_json::to_vec(&report)?);
+        Ok(())
+    }
+}
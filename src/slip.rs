@@ -0,0 +1,91 @@
+//! The tire slip estimates of `<vehicle>/bridge/debug/slip`, for vehicle dynamics work.
+//!
+//! The longitudinal slip of a wheel is `(ω r - v) / v`, its rotational speed against the
+//! ground speed, and the slip angle of an axle is the angle from the heading of its wheels
+//! to the velocity of its center, positive to the left. Both are estimates from the motion
+//! of the body, which ignore the suspension and the lateral offset of each wheel.
+use crate::autoware_type::{AxleSlip, SlipEstimate};
+use carla::rpc::VehiclePhysicsControl;
+use nalgebra::{Isometry3, Point3};
+use zenoh_ros_type::builtin_interfaces::Time;
+
+/// Below this ground speed, the slips divide by it instead, so they stay small at a standstill.
+/// Backwards, the slips are taken against the ground speed forward (m/s).
+const MIN_SPEED: f32 = 0.5;
+
+/// Where the axles are, read once from the physics control of the vehicle
+#[derive(Debug, Clone, Copy)]
+pub struct SlipGeometry {
+    /// Forward from the origin of the vehicle (m)
+    front_axle: f32,
+    rear_axle: f32,
+    /// FL, FR, RL and RR (m)
+    wheel_radii: [f32; 4],
+    two_wheeled: bool,
+}
+
+impl SlipGeometry {
+    /// CARLA reports the wheel positions in the world (cm), so they're moved into the vehicle
+    /// with its transform at the same time. None without the wheels.
+    pub fn from_physics(
+        physics: &VehiclePhysicsControl,
+        transform: &Isometry3<f32>,
+    ) -> Option<SlipGeometry> {
+        let forward = |index: usize| {
+            let position = physics.wheels.get(index)?.position / 100.0;
+            Some(transform.inverse_transform_point(&Point3::from(position)).x)
+        };
+        let radius = |index: usize| physics.wheels.get(index).map(|wheel| wheel.radius / 100.0);
+        let geometry = match physics.wheels.len() {
+            2 => SlipGeometry {
+                front_axle: forward(0)?,
+                rear_axle: forward(1)?,
+                wheel_radii: [radius(0)?, f32::NAN, radius(1)?, f32::NAN],
+                two_wheeled: true,
+            },
+            4 => SlipGeometry {
+                front_axle: (forward(0)? + forward(1)?) / 2.0,
+                rear_axle: (forward(2)? + forward(3)?) / 2.0,
+                wheel_radii: [radius(0)?, radius(1)?, radius(2)?, radius(3)?],
+                two_wheeled: false,
+            },
+            _ => return None,
+        };
+        Some(geometry)
+    }
+
+    /// The slips of both axles for the velocity in base_link (m/s), the yaw rate (rad/s), the
+    /// tire angle of the front axle, positive to the left (rad), and the wheel speeds (rad/s).
+    ///
+    /// Without the wheel speeds, and for the two-wheelers, the longitudinal slips are NaN.
+    pub fn estimate(
+        &self,
+        stamp: Time,
+        (longitudinal, lateral, yaw_rate): (f32, f32, f32),
+        tire_angle: f32,
+        wheel_speeds: Option<[f32; 4]>,
+    ) -> SlipEstimate {
+        let ground_speed = longitudinal.abs().max(MIN_SPEED);
+        let slip_angle = |axle: f32| (lateral + axle * yaw_rate).atan2(ground_speed);
+        let longitudinal_slip = |wheels: [usize; 2]| match wheel_speeds {
+            Some(speeds) if !self.two_wheeled => {
+                let slip = |index: usize| {
+                    (speeds[index] * self.wheel_radii[index] - longitudinal) / ground_speed
+                };
+                (slip(wheels[0]) + slip(wheels[1])) / 2.0
+            }
+            _ => f32::NAN,
+        };
+        SlipEstimate {
+            stamp,
+            front: AxleSlip {
+                longitudinal_slip: longitudinal_slip([0, 1]),
+                slip_angle: slip_angle(self.front_axle) - tire_angle,
+            },
+            rear: AxleSlip {
+                longitudinal_slip: longitudinal_slip([2, 3]),
+                slip_angle: slip_angle(self.rear_axle),
+            },
+        }
+    }
+}
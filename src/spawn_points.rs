@@ -0,0 +1,138 @@
+//! `*/bridge/map/spawn_points`: the recommended spawn points of the current map
+use crate::{
+    actor_query::ActorInfo,
+    error::Result,
+    topics::{self, TopicMap},
+};
+use arc_swap::ArcSwap;
+use carla::client::Map;
+use log::warn;
+use serde_derive::Serialize;
+use std::sync::Arc;
+use zenoh::{prelude::sync::*, queryable::Queryable};
+
+/// A spawn point is occupied by a vehicle closer than this, unless the query sets `radius` (m)
+const DEFAULT_RADIUS: f32 = 5.0;
+
+/// A spawn point in the reply of `*/bridge/map/spawn_points`
+#[derive(Debug, Clone, Serialize)]
+pub struct SpawnPoint {
+    /// In the list of the map, which doesn't change with the filter
+    pub index: usize,
+    /// In the CARLA coordinates (m)
+    pub location: [f32; 3],
+    /// Roll, pitch and yaw in the CARLA coordinates (degrees)
+    pub rotation: [f32; 3],
+}
+
+/// Answer the queries on `*/bridge/map/spawn_points` with a JSON array of the spawn points.
+///
+/// The list is read once per world, by the main loop. With the selector parameter
+/// `unoccupied`, e.g. `*/bridge/map/spawn_points?unoccupied&radius=8`, only the spawn
+/// points without a vehicle within `radius` are returned, checked against the snapshot of
+/// `*/bridge/actors`, so a vehicle spawned in the last second may be missed.
+pub struct SpawnPointQueryable<'a> {
+    spawn_points: Arc<ArcSwap<Vec<SpawnPoint>>>,
+    _queryable: Queryable<'a, ()>,
+}
+
+impl<'a> SpawnPointQueryable<'a> {
+    pub fn new(
+        z_session: Arc<Session>,
+        topic_map: &TopicMap,
+        map: &Map,
+        actors: Arc<ArcSwap<Vec<ActorInfo>>>,
+    ) -> Result<SpawnPointQueryable<'a>> {
+        let spawn_points = Arc::new(ArcSwap::from_pointee(read_spawn_points(map)));
+        let cloned_spawn_points = spawn_points.clone();
+        let queryable = z_session
+            .declare_queryable(topic_map.key("*", topics::SPAWN_POINTS))
+            .callback(move |query| {
+                let spawn_points = cloned_spawn_points.load();
+                let reply = match parse_radius(query.parameters()) {
+                    Ok(None) => serde_json::to_vec(&**spawn_points).map_err(|e| e.to_string()),
+                    Ok(Some(radius)) => {
+                        let actors = actors.load();
+                        let vehicles: Vec<_> = actors
+                            .iter()
+                            .filter(|actor| actor.type_id.starts_with("vehicle."))
+                            .map(|actor| actor.location)
+                            .collect();
+                        let unoccupied: Vec<_> = spawn_points
+                            .iter()
+                            .filter(|spawn_point| {
+                                vehicles.iter().all(|vehicle| {
+                                    distance(vehicle, &spawn_point.location) > radius
+                                })
+                            })
+                            .collect();
+                        serde_json::to_vec(&unoccupied).map_err(|e| e.to_string())
+                    }
+                    Err(e) => Err(e),
+                };
+                let result = reply
+                    .map(|payload| Sample::new(query.key_expr().clone(), payload))
+                    .map_err(Into::into);
+                if let Err(e) = query.reply(result).res() {
+                    warn!("Failed to reply to the spawn point query: {e}");
+                }
+            })
+            .res()?;
+        Ok(SpawnPointQueryable {
+            spawn_points,
+            _queryable: queryable,
+        })
+    }
+
+    /// Read the spawn points of a new world
+    pub fn set_map(&self, map: &Map) {
+        self.spawn_points.store(Arc::new(read_spawn_points(map)));
+    }
+}
+
+fn read_spawn_points(map: &Map) -> Vec<SpawnPoint> {
+    map.recommended_spawn_points()
+        .iter()
+        .enumerate()
+        .map(|(index, transform)| {
+            let (roll, pitch, yaw) = transform.rotation.euler_angles();
+            SpawnPoint {
+                index,
+                location: transform.translation.vector.into(),
+                rotation: [roll.to_degrees(), pitch.to_degrees(), yaw.to_degrees()],
+            }
+        })
+        .collect()
+}
+
+/// The radius of `unoccupied&radius=...`, None without `unoccupied`
+fn parse_radius(parameters: &str) -> std::result::Result<Option<f32>, String> {
+    let mut unoccupied = false;
+    let mut radius = DEFAULT_RADIUS;
+    for parameter in parameters
+        .split('&')
+        .filter(|parameter| !parameter.is_empty())
+    {
+        let (name, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+        match name {
+            "unoccupied" => unoccupied = value != "false",
+            "radius" => {
+                radius = value
+                    .parse()
+                    .ok()
+                    .filter(|radius: &f32| radius.is_finite() && *radius > 0.0)
+                    .ok_or_else(|| format!("The radius must be positive, not '{value}'"))?
+            }
+            _ => {}
+        }
+    }
+    Ok(unoccupied.then_some(radius))
+}
+
+fn distance(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| (a - b).powi(2))
+        .sum::<f32>()
+        .sqrt()
+}
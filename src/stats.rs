@@ -0,0 +1,204 @@
+use crate::{config::ControlPath, namespace::VehicleIdentity};
+use atomic_float::AtomicF64;
+use serde_derive::Serialize;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// How often the stats are published (in simulation seconds)
+pub const STATS_INTERVAL_SEC: f64 = 1.0;
+/// How many arrival times of a command topic are kept, enough for the window at 60 Hz
+const ARRIVAL_CAPACITY: usize = 128;
+/// The command rate covers the arrivals of this last wall-clock time (s)
+const RATE_WINDOW_SEC: f64 = 2.0;
+
+/// The wall-clock arrival times of a command topic.
+///
+/// The callback only pushes a timestamp, the rate is computed on the stats timer.
+#[derive(Default)]
+pub struct ArrivalTimes {
+    times: Mutex<VecDeque<Instant>>,
+}
+
+/// The arrival rate of a command topic over the window
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct CommandRate {
+    pub rate_hz: f64,
+    /// Standard deviation of the intervals between the arrivals (ms)
+    pub jitter_ms: f64,
+}
+
+impl ArrivalTimes {
+    pub fn record(&self) {
+        let mut times = self.times.lock().unwrap();
+        if times.len() == ARRIVAL_CAPACITY {
+            times.pop_front();
+        }
+        times.push_back(Instant::now());
+    }
+
+    pub fn rate(&self, now: Instant) -> CommandRate {
+        let window = Duration::from_secs_f64(RATE_WINDOW_SEC);
+        let times = self.times.lock().unwrap();
+        let recent: Vec<_> = times
+            .iter()
+            .filter(|time| now.duration_since(**time) <= window)
+            .collect();
+        // Counting in the window lets the rate fall to zero when the commands stop
+        let rate_hz = recent.len() as f64 / RATE_WINDOW_SEC;
+        let intervals: Vec<_> = recent
+            .windows(2)
+            .map(|pair| pair[1].duration_since(*pair[0]).as_secs_f64())
+            .collect();
+        let jitter_ms = if intervals.len() < 2 {
+            0.0
+        } else {
+            let mean = intervals.iter().sum::<f64>() / intervals.len() as f64;
+            let variance = intervals
+                .iter()
+                .map(|interval| (interval - mean).powi(2))
+                .sum::<f64>()
+                / intervals.len() as f64;
+            variance.sqrt() * 1000.0
+        };
+        CommandRate { rate_hz, jitter_ms }
+    }
+}
+
+/// Counters of a vehicle bridge, shared with its subscriber callbacks
+#[derive(Default)]
+pub struct VehicleStats {
+    /// Samples dropped because their CDR payload is invalid
+    pub rejected_samples: AtomicU64,
+    /// Commands dropped because of NaN, infinite or out of range values
+    pub rejected_commands: AtomicU64,
+    /// The hand brake state read back from CARLA
+    pub hand_brake: AtomicBool,
+    /// The bits of the light state read back from CARLA
+    pub light_state: AtomicU32,
+    /// Time the speed ceiling changed the control (s)
+    pub speed_limited_sec: AtomicF64,
+    /// The fallback policy drives the vehicle, since no valid command does
+    pub in_fallback: AtomicBool,
+    /// How many times the fallback took over
+    pub fallback_entries: AtomicU64,
+    /// False while the operator froze the vehicle
+    pub control_enabled: AtomicBool,
+    /// Control updates skipped, since the loop stepped twice in a frame
+    pub skipped_control_updates: AtomicU64,
+    /// Control updates after a pause of the loop, which reset the controller
+    pub control_gaps: AtomicU64,
+    /// apply_control RPCs skipped, since the control repeated the last applied one
+    pub skipped_control_applies: AtomicU64,
+    /// Every received control_cmd, valid or not
+    pub control_cmd_arrivals: ArrivalTimes,
+    /// The same, per subscribed topic
+    failures_by_topic: Mutex<BTreeMap<String, Arc<AtomicU64>>>,
+    /// Failed puts, per published topic
+    publish_failures_by_topic: Mutex<BTreeMap<String, Arc<AtomicU64>>>,
+}
+
+/// The stats message published as JSON on `<vehicle>/bridge/stats`
+#[derive(Serialize)]
+pub struct VehicleStatsReport {
+    pub stamp: f64,
+    #[serde(flatten)]
+    pub identity: VehicleIdentity,
+    pub rejected_samples: u64,
+    pub rejected_commands: u64,
+    pub hand_brake: bool,
+    pub light_state: u32,
+    pub speed_limited_sec: f64,
+    pub in_fallback: bool,
+    pub fallback_entries: u64,
+    pub control_enabled: bool,
+    pub skipped_control_updates: u64,
+    pub control_gaps: u64,
+    pub skipped_control_applies: u64,
+    pub control_path: ControlPath,
+    pub control_cmd_rate: CommandRate,
+    pub deserialize_failures: BTreeMap<String, u64>,
+    pub publish_failures: BTreeMap<String, u64>,
+}
+
+impl VehicleStats {
+    /// The failure counter of a topic, created on first use
+    pub fn topic_failures(&self, topic: &str) -> Arc<AtomicU64> {
+        self.failures_by_topic
+            .lock()
+            .unwrap()
+            .entry(topic.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// The failed put counter of a published topic, created on first use
+    pub fn publish_failures(&self, topic: &str) -> Arc<AtomicU64> {
+        self.publish_failures_by_topic
+            .lock()
+            .unwrap()
+            .entry(topic.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Zero the counters, keeping the hand brake state
+    pub fn reset(&self) {
+        self.rejected_samples.store(0, Ordering::Relaxed);
+        self.rejected_commands.store(0, Ordering::Relaxed);
+        self.speed_limited_sec.store(0.0, Ordering::Relaxed);
+        self.fallback_entries.store(0, Ordering::Relaxed);
+        self.skipped_control_updates.store(0, Ordering::Relaxed);
+        self.control_gaps.store(0, Ordering::Relaxed);
+        self.skipped_control_applies.store(0, Ordering::Relaxed);
+        // The callbacks hold the counters, so they are zeroed in place
+        for count in self.failures_by_topic.lock().unwrap().values() {
+            count.store(0, Ordering::Relaxed);
+        }
+        for count in self.publish_failures_by_topic.lock().unwrap().values() {
+            count.store(0, Ordering::Relaxed);
+        }
+    }
+
+    pub fn report(
+        &self,
+        timestamp: f64,
+        identity: &VehicleIdentity,
+        control_path: ControlPath,
+        control_cmd_rate: CommandRate,
+    ) -> VehicleStatsReport {
+        VehicleStatsReport {
+            stamp: timestamp,
+            identity: identity.clone(),
+            rejected_samples: self.rejected_samples.load(Ordering::Relaxed),
+            rejected_commands: self.rejected_commands.load(Ordering::Relaxed),
+            hand_brake: self.hand_brake.load(Ordering::Relaxed),
+            light_state: self.light_state.load(Ordering::Relaxed),
+            speed_limited_sec: self.speed_limited_sec.load(Ordering::Relaxed),
+            in_fallback: self.in_fallback.load(Ordering::Relaxed),
+            fallback_entries: self.fallback_entries.load(Ordering::Relaxed),
+            control_enabled: self.control_enabled.load(Ordering::Relaxed),
+            skipped_control_updates: self.skipped_control_updates.load(Ordering::Relaxed),
+            control_gaps: self.control_gaps.load(Ordering::Relaxed),
+            skipped_control_applies: self.skipped_control_applies.load(Ordering::Relaxed),
+            control_path,
+            control_cmd_rate,
+            deserialize_failures: counts(&self.failures_by_topic),
+            publish_failures: counts(&self.publish_failures_by_topic),
+        }
+    }
+}
+
+fn counts(by_topic: &Mutex<BTreeMap<String, Arc<AtomicU64>>>) -> BTreeMap<String, u64> {
+    by_topic
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(topic, count)| (topic.clone(), count.load(Ordering::Relaxed)))
+        .collect()
+}
@@ -0,0 +1,292 @@
+//! Publishers of the status topics, which keep the bridge running through zenoh errors.
+//!
+//! A failed put is counted and logged at most once per few seconds. When a publisher keeps
+//! failing for `redeclare_after` (`[topics] redeclare_after`), it's declared again, with a
+//! doubling backoff between the attempts. Only the first declaration returns an error, so a
+//! status topic never stops the control of a vehicle.
+//!
+//! With `status_delay`, the payloads wait in the publisher until `flush` finds their delay
+//! elapsed on the simulation clock.
+use crate::{error::Result, latency::DelayQueue, topics, utils::LogThrottle};
+use atomic_float::AtomicF64;
+use log::{info, warn};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use zenoh::{prelude::sync::*, publication::Publisher};
+
+/// The first wait between two declarations of a failing publisher
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+/// The backoff doubles up to this
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+pub struct StatusPublisher {
+    z_session: Arc<Session>,
+    key: String,
+    redeclare_after: Duration,
+    failures: Arc<AtomicU64>,
+    /// The simulation time of the delayed payloads
+    clock: Option<Arc<AtomicF64>>,
+    state: Mutex<PublisherState>,
+}
+
+struct PublisherState {
+    /// None after a failed declaration, until the next attempt
+    publisher: Option<Publisher<'static>>,
+    failing_since: Option<Instant>,
+    next_declaration: Option<Instant>,
+    backoff: Duration,
+    log: LogThrottle,
+    delayed: Option<DelayQueue<Vec<u8>>>,
+}
+
+impl StatusPublisher {
+    /// `failures` counts the failed puts, e.g. a counter of the stats
+    pub fn declare(
+        z_session: Arc<Session>,
+        key: String,
+        redeclare_after: Duration,
+        failures: Arc<AtomicU64>,
+    ) -> Result<StatusPublisher> {
+        let publisher = topics::declare_publisher(&z_session, &key)?;
+        Ok(StatusPublisher {
+            z_session,
+            key,
+            redeclare_after,
+            failures,
+            clock: None,
+            state: Mutex::new(PublisherState {
+                publisher: Some(publisher),
+                failing_since: None,
+                next_declaration: None,
+                backoff: INITIAL_BACKOFF,
+                log: LogThrottle::new(Duration::from_secs(5)),
+                delayed: None,
+            }),
+        })
+    }
+
+    /// Hold every payload for `delay` simulation seconds of the clock, see `flush`
+    pub fn with_delay(mut self, delay: f64, clock: Arc<AtomicF64>) -> StatusPublisher {
+        self.state.get_mut().unwrap().delayed = Some(DelayQueue::new(delay));
+        self.clock = Some(clock);
+        self
+    }
+
+    pub fn key_expr(&self) -> &str {
+        &self.key
+    }
+
+    /// Put the payload, or count and log the failure. With a delay, queue it instead.
+    pub fn put(&self, payload: Vec<u8>) {
+        let mut state = self.state.lock().unwrap();
+        if let (Some(delayed), Some(clock)) = (&mut state.delayed, &self.clock) {
+            delayed.push(clock.load(Ordering::Relaxed), payload);
+            return;
+        }
+        self.put_now(&mut state, payload);
+    }
+
+    /// Put the delayed payloads whose delay elapsed, nothing without a delay
+    pub fn flush(&self) {
+        let mut state = self.state.lock().unwrap();
+        let (Some(delayed), Some(clock)) = (&mut state.delayed, &self.clock) else {
+            return;
+        };
+        for payload in delayed.pop_due(clock.load(Ordering::Relaxed)) {
+            self.put_now(&mut state, payload);
+        }
+    }
+
+    /// Drop the delayed payloads, e.g. on a reset
+    pub fn clear_delayed(&self) {
+        if let Some(delayed) = &mut self.state.lock().unwrap().delayed {
+            delayed.clear();
+        }
+    }
+
+    fn put_now(&self, state: &mut PublisherState, payload: Vec<u8>) {
+        let now = Instant::now();
+        if matches!(state.failing_since, Some(since) if now - since >= self.redeclare_after)
+            && state.next_declaration.map_or(true, |next| now >= next)
+        {
+            self.redeclare(state, now);
+        }
+        let error = match &state.publisher {
+            Some(publisher) => publisher.put(payload).res().err().map(|e| e.to_string()),
+            None => Some(String::from("the publisher is not declared")),
+        };
+        match error {
+            None => {
+                if let Some(since) = state.failing_since.take() {
+                    info!(
+                        "Publishing on {} again after {:.1} s of failures",
+                        self.key,
+                        (now - since).as_secs_f64()
+                    );
+                }
+                state.next_declaration = None;
+                state.backoff = INITIAL_BACKOFF;
+            }
+            Some(e) => {
+                let failures = self.failures.fetch_add(1, Ordering::Relaxed) + 1;
+                state.failing_since.get_or_insert(now);
+                if state.log.ready() {
+                    warn!(
+                        "Failed to publish on {} ({failures} failures so far): {e}",
+                        self.key
+                    );
+                }
+            }
+        }
+    }
+
+    fn redeclare(&self, state: &mut PublisherState, now: Instant) {
+        // Undeclare the failing one first
+        state.publisher = None;
+        match topics::declare_publisher(&self.z_session, &self.key) {
+            Ok(publisher) => {
+                info!("Declared the publisher of {} again", self.key);
+                state.publisher = Some(publisher);
+            }
+            Err(e) => warn!(
+                "Failed to declare the publisher of {} again, next attempt in {:.1} s: {e}",
+                self.key,
+                state.backoff.as_secs_f64()
+            ),
+        }
+        state.next_declaration = Some(now + state.backoff);
+        state.backoff = (state.backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WAIT: Duration = Duration::from_secs(5);
+
+    fn session() -> Arc<Session> {
+        let mut config = Config::default();
+        config
+            .insert_json5("scouting/multicast/enabled", "false")
+            .unwrap();
+        Arc::new(zenoh::open(config).res().unwrap())
+    }
+
+    fn publisher(
+        session: &Arc<Session>,
+        key: &str,
+        redeclare_after: Duration,
+    ) -> (StatusPublisher, Arc<AtomicU64>) {
+        let failures = Arc::new(AtomicU64::new(0));
+        let publisher = StatusPublisher::declare(
+            session.clone(),
+            key.to_string(),
+            redeclare_after,
+            failures.clone(),
+        )
+        .unwrap();
+        (publisher, failures)
+    }
+
+    /// Drop the zenoh publisher like a failed declaration, failing for `since`
+    fn break_publisher(publisher: &StatusPublisher, since: Duration) {
+        let mut state = publisher.state.lock().unwrap();
+        state.publisher = None;
+        state.failing_since = Some(Instant::now() - since);
+    }
+
+    #[test]
+    fn put_the_payloads() {
+        let session = session();
+        let subscriber = session.declare_subscriber("test/put").res().unwrap();
+        let (publisher, failures) = publisher(&session, "test/put", Duration::from_secs(2));
+        publisher.put(vec![1, 2, 3]);
+        let sample = subscriber.recv_timeout(WAIT).unwrap();
+        assert_eq!(sample.payload.contiguous().to_vec(), vec![1, 2, 3]);
+        assert_eq!(failures.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn a_failing_publisher_is_counted_then_declared_again() {
+        let session = session();
+        let subscriber = session.declare_subscriber("test/redeclare").res().unwrap();
+        let redeclare_after = Duration::from_millis(100);
+        let (publisher, failures) = publisher(&session, "test/redeclare", redeclare_after);
+
+        // Too early to declare it again
+        break_publisher(&publisher, Duration::ZERO);
+        publisher.put(vec![1]);
+        assert_eq!(failures.load(Ordering::Relaxed), 1);
+        assert!(publisher.state.lock().unwrap().publisher.is_none());
+
+        break_publisher(&publisher, Duration::from_secs(1));
+        publisher.put(vec![2]);
+        let sample = subscriber.recv_timeout(WAIT).unwrap();
+        assert_eq!(sample.payload.contiguous().to_vec(), vec![2]);
+        assert_eq!(failures.load(Ordering::Relaxed), 1);
+        let state = publisher.state.lock().unwrap();
+        assert!(state.failing_since.is_none());
+        assert!(state.next_declaration.is_none());
+        assert_eq!(state.backoff, INITIAL_BACKOFF);
+    }
+
+    #[test]
+    fn the_declarations_back_off() {
+        let session = session();
+        let (publisher, _) = publisher(&session, "test/backoff", Duration::ZERO);
+        let mut state = publisher.state.lock().unwrap();
+        let now = Instant::now();
+        for wait in [0.5, 1.0, 2.0, 4.0, 8.0, 16.0, 30.0, 30.0] {
+            publisher.redeclare(&mut state, now);
+            assert_eq!(
+                state.next_declaration,
+                Some(now + Duration::from_secs_f64(wait))
+            );
+        }
+        assert_eq!(state.backoff, MAX_BACKOFF);
+    }
+
+    #[test]
+    fn wait_for_the_backoff_before_declaring_again() {
+        let session = session();
+        let (publisher, failures) = publisher(&session, "test/wait", Duration::ZERO);
+        break_publisher(&publisher, Duration::from_secs(1));
+        publisher.state.lock().unwrap().next_declaration =
+            Some(Instant::now() + Duration::from_secs(60));
+        publisher.put(vec![1]);
+        publisher.put(vec![2]);
+        assert_eq!(failures.load(Ordering::Relaxed), 2);
+        assert!(publisher.state.lock().unwrap().publisher.is_none());
+    }
+
+    #[test]
+    fn the_delayed_payloads_wait_for_the_clock() {
+        let session = session();
+        let subscriber = session.declare_subscriber("test/delay").res().unwrap();
+        let clock = Arc::new(AtomicF64::new(1.0));
+        let (publisher, _) = publisher(&session, "test/delay", Duration::from_secs(2));
+        let publisher = publisher.with_delay(0.5, clock.clone());
+        publisher.put(vec![1]);
+        publisher.flush();
+        assert!(subscriber.recv_timeout(Duration::from_millis(100)).is_err());
+
+        clock.store(1.5, Ordering::Relaxed);
+        publisher.put(vec![2]);
+        publisher.flush();
+        let sample = subscriber.recv_timeout(WAIT).unwrap();
+        assert_eq!(sample.payload.contiguous().to_vec(), vec![1]);
+        assert!(subscriber.recv_timeout(Duration::from_millis(100)).is_err());
+
+        // A reset drops what is still waiting
+        publisher.clear_delayed();
+        clock.store(3.0, Ordering::Relaxed);
+        publisher.flush();
+        assert!(subscriber.recv_timeout(Duration::from_millis(100)).is_err());
+    }
+}
@@ -0,0 +1,270 @@
+//! The stamps of the ROS messages, built from the simulation or the wall-clock time
+use crate::utils::LogThrottle;
+use log::warn;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use zenoh_ros_type::builtin_interfaces::Time;
+
+const NANOS_PER_SEC: u32 = 1_000_000_000;
+
+/// A point in time as `builtin_interfaces/Time` carries it.
+///
+/// Both components are never negative and the nanoseconds stay below a second, the
+/// conversions round to the nearest nanosecond and saturate at zero and at the largest
+/// stamp instead of wrapping. The stamps compare in time order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct TimeStamp {
+    sec: i32,
+    nanosec: u32,
+}
+
+impl TimeStamp {
+    pub const ZERO: TimeStamp = TimeStamp { sec: 0, nanosec: 0 };
+    pub const MAX: TimeStamp = TimeStamp {
+        sec: i32::MAX,
+        nanosec: NANOS_PER_SEC - 1,
+    };
+
+    /// Seconds to a stamp, negative and NaN become zero
+    pub fn from_secs_f64(secs: f64) -> TimeStamp {
+        if secs.is_nan() || secs <= 0.0 {
+            return TimeStamp::ZERO;
+        }
+        let whole = secs.floor();
+        if whole > i32::MAX as f64 {
+            return TimeStamp::MAX;
+        }
+        // Rounding the fraction may reach the next second, e.g. 0.9999999996
+        let nanosec = ((secs - whole) * NANOS_PER_SEC as f64).round() as u32;
+        let stamp = TimeStamp {
+            sec: whole as i32,
+            nanosec: 0,
+        };
+        stamp.saturating_add(Duration::from_nanos(nanosec as u64))
+    }
+
+    pub fn from_duration(duration: Duration) -> TimeStamp {
+        TimeStamp::ZERO.saturating_add(duration)
+    }
+
+    /// The wall-clock time, for the messages without a simulation time
+    pub fn now() -> TimeStamp {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(TimeStamp::ZERO, TimeStamp::from_duration)
+    }
+
+    pub fn from_msg(time: &Time) -> TimeStamp {
+        // The messages of other nodes may break the invariants, so go through the duration
+        let secs = Duration::from_secs(time.sec.max(0) as u64);
+        TimeStamp::from_duration(secs + Duration::from_nanos(time.nanosec as u64))
+    }
+
+    pub fn to_msg(self) -> Time {
+        Time {
+            sec: self.sec,
+            nanosec: self.nanosec,
+        }
+    }
+
+    pub fn to_secs_f64(self) -> f64 {
+        self.sec as f64 + self.nanosec as f64 / NANOS_PER_SEC as f64
+    }
+
+    pub fn to_duration(self) -> Duration {
+        Duration::new(self.sec as u64, self.nanosec)
+    }
+
+    pub fn saturating_add(self, duration: Duration) -> TimeStamp {
+        let total = self.to_duration().saturating_add(duration);
+        if total.as_secs() > i32::MAX as u64 {
+            return TimeStamp::MAX;
+        }
+        TimeStamp {
+            sec: total.as_secs() as i32,
+            nanosec: total.subsec_nanos(),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn saturating_sub(self, duration: Duration) -> TimeStamp {
+        let total = self.to_duration().saturating_sub(duration);
+        TimeStamp {
+            sec: total.as_secs() as i32,
+            nanosec: total.subsec_nanos(),
+        }
+    }
+
+    /// How long after `earlier` this is, zero if it isn't later
+    pub fn elapsed_since(self, earlier: TimeStamp) -> Duration {
+        self.to_duration().saturating_sub(earlier.to_duration())
+    }
+}
+
+/// The stamp of each tick of the main loop, which never goes backwards.
+///
+/// Every message of a tick carries the same stamp, so the consumers can sync the
+/// topics on it. The snapshot time of CARLA can briefly jump back, e.g. right after a
+/// world reload, then the previous stamp is kept until the simulation catches up. The
+/// reload itself starts a new clock, since every bridge is rebuilt.
+pub struct TickClock {
+    last: Option<TimeStamp>,
+    log: LogThrottle,
+}
+
+impl Default for TickClock {
+    fn default() -> Self {
+        TickClock::new()
+    }
+}
+
+impl TickClock {
+    pub fn new() -> TickClock {
+        TickClock {
+            last: None,
+            log: LogThrottle::new(Duration::from_secs(5)),
+        }
+    }
+
+    /// The stamp of the tick at the simulation time `secs`
+    pub fn tick(&mut self, secs: f64) -> TimeStamp {
+        let stamp = TimeStamp::from_secs_f64(secs);
+        match self.last {
+            Some(last) if stamp < last => {
+                if self.log.ready() {
+                    warn!(
+                        "The simulation time went back by {:?}, keep the stamp {:.3}",
+                        last.elapsed_since(stamp),
+                        last.to_secs_f64()
+                    );
+                }
+                last
+            }
+            _ => {
+                self.last = Some(stamp);
+                stamp
+            }
+        }
+    }
+}
+
+impl From<TimeStamp> for Time {
+    fn from(stamp: TimeStamp) -> Time {
+        stamp.to_msg()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stamp(sec: i32, nanosec: u32) -> TimeStamp {
+        TimeStamp { sec, nanosec }
+    }
+
+    #[test]
+    fn convert_seconds() {
+        assert_eq!(TimeStamp::from_secs_f64(1.5), stamp(1, 500_000_000));
+        assert_eq!(TimeStamp::from_secs_f64(0.05), stamp(0, 50_000_000));
+        // Rounding to the nearest nanosecond, not down
+        assert_eq!(TimeStamp::from_secs_f64(2.0000000006), stamp(2, 1));
+        assert_eq!(stamp(3, 250_000_000).to_secs_f64(), 3.25);
+        assert_eq!(TimeStamp::from_duration(Duration::new(4, 7)), stamp(4, 7));
+        assert_eq!(stamp(4, 7).to_duration(), Duration::new(4, 7));
+    }
+
+    #[test]
+    fn round_over_to_the_next_second() {
+        assert_eq!(TimeStamp::from_secs_f64(0.9999999996), stamp(1, 0));
+        assert_eq!(TimeStamp::from_secs_f64(41.9999999999), stamp(42, 0));
+        assert_eq!(TimeStamp::from_secs_f64(0.999999999), stamp(0, 999_999_999));
+        assert_eq!(
+            stamp(1, 999_999_999).saturating_add(Duration::from_nanos(1)),
+            stamp(2, 0)
+        );
+    }
+
+    #[test]
+    fn negative_and_invalid_inputs_are_zero() {
+        assert_eq!(TimeStamp::from_secs_f64(0.0), TimeStamp::ZERO);
+        assert_eq!(TimeStamp::from_secs_f64(-1.5), TimeStamp::ZERO);
+        assert_eq!(TimeStamp::from_secs_f64(f64::NAN), TimeStamp::ZERO);
+        assert_eq!(TimeStamp::from_secs_f64(f64::NEG_INFINITY), TimeStamp::ZERO);
+        // Another node may send a negative or denormalized stamp
+        let time = Time {
+            sec: -3,
+            nanosec: 5,
+        };
+        assert_eq!(TimeStamp::from_msg(&time), stamp(0, 5));
+        let time = Time {
+            sec: 1,
+            nanosec: 2_500_000_000,
+        };
+        assert_eq!(TimeStamp::from_msg(&time), stamp(3, 500_000_000));
+    }
+
+    #[test]
+    fn saturate_at_the_bounds() {
+        assert_eq!(TimeStamp::from_secs_f64(1e12), TimeStamp::MAX);
+        assert_eq!(TimeStamp::from_secs_f64(f64::INFINITY), TimeStamp::MAX);
+        assert_eq!(
+            TimeStamp::MAX.saturating_add(Duration::from_secs(1)),
+            TimeStamp::MAX
+        );
+        assert_eq!(
+            stamp(1, 0).saturating_sub(Duration::from_secs(2)),
+            TimeStamp::ZERO
+        );
+        assert_eq!(
+            stamp(2, 100).saturating_sub(Duration::from_nanos(200)),
+            stamp(1, 999_999_900)
+        );
+        // A long simulation keeps the nanoseconds
+        let long = TimeStamp::from_secs_f64(86_400.0 * 365.0 + 0.25);
+        assert_eq!(long, stamp(31_536_000, 250_000_000));
+    }
+
+    #[test]
+    fn compare_in_time_order() {
+        assert!(stamp(1, 999_999_999) < stamp(2, 0));
+        assert!(stamp(2, 1) > stamp(2, 0));
+        assert_eq!(
+            stamp(2, 0).elapsed_since(stamp(1, 500_000_000)),
+            Duration::from_millis(500)
+        );
+        assert_eq!(stamp(1, 0).elapsed_since(stamp(2, 0)), Duration::ZERO);
+        let time: Time = stamp(5, 6).into();
+        assert_eq!(TimeStamp::from_msg(&time), stamp(5, 6));
+    }
+
+    #[test]
+    fn the_tick_stamps_never_go_back() {
+        let mut clock = TickClock::default();
+        assert_eq!(clock.tick(1.0), stamp(1, 0));
+        assert_eq!(clock.tick(1.05), stamp(1, 50_000_000));
+        // A jump back keeps the previous stamp until the simulation catches up
+        assert_eq!(clock.tick(0.2), stamp(1, 50_000_000));
+        assert_eq!(clock.tick(1.0), stamp(1, 50_000_000));
+        assert_eq!(clock.tick(1.05), stamp(1, 50_000_000));
+        assert_eq!(clock.tick(1.1), stamp(1, 100_000_000));
+    }
+
+    #[test]
+    fn a_new_episode_starts_a_new_clock() {
+        let mut clock = TickClock::new();
+        let stamps: Vec<_> = [0.5, 0.55, 0.6]
+            .iter()
+            .map(|secs| clock.tick(*secs))
+            .collect();
+        assert!(stamps.windows(2).all(|pair| pair[0] < pair[1]));
+        // After a reload, the bridges are rebuilt with a fresh clock from the new time
+        let mut clock = TickClock::new();
+        assert_eq!(clock.tick(0.05), stamp(0, 50_000_000));
+        let mut previous = TimeStamp::ZERO;
+        for secs in [0.1, 0.15, 0.12, 0.2, 0.0, 0.25] {
+            let tick_stamp = clock.tick(secs);
+            assert!(tick_stamp >= previous, "{secs}");
+            previous = tick_stamp;
+        }
+        assert_eq!(previous, stamp(0, 250_000_000));
+    }
+}
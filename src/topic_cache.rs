@@ -0,0 +1,141 @@
+//! Publication caches, so a late querying subscriber gets the latest sample of a low-rate topic
+use crate::{
+    error::{Error, Result},
+    topics::{self, TopicMap},
+};
+use log::info;
+use std::{collections::BTreeMap, sync::Arc};
+use zenoh::prelude::sync::*;
+use zenoh_ext::{PublicationCache, SessionExt};
+
+/// The topics cached unless the configuration says otherwise. The high-rate ones aren't,
+/// their next sample is never far.
+const DEFAULT_DEPTHS: &[(&str, usize)] =
+    &[(topics::CARLA_LANES, 1), (topics::COLLISION_HISTORY, 1)];
+
+/// The caches of every vehicle and global topic with a history depth
+pub struct TopicCaches<'a> {
+    _caches: Vec<PublicationCache<'a>>,
+}
+
+impl<'a> TopicCaches<'a> {
+    /// `depths` maps the canonical topic names to their history depth, over the defaults
+    pub fn new(
+        z_session: Arc<Session>,
+        depths: &BTreeMap<String, usize>,
+        topic_map: &TopicMap,
+    ) -> Result<TopicCaches<'a>> {
+        if let Some(topic) = depths.keys().find(|topic| !topics::is_known(topic)) {
+            return Err(Error::Config {
+                reason: format!("Unknown topic '{topic}' in the cache table"),
+            });
+        }
+        let mut merged: BTreeMap<&str, usize> = DEFAULT_DEPTHS.iter().copied().collect();
+        merged.extend(depths.iter().map(|(topic, depth)| (topic.as_str(), *depth)));
+
+        let mut caches = Vec::new();
+        for (topic, depth) in merged.into_iter().filter(|(_, depth)| *depth > 0) {
+            // The global topics are published on `*/...`, the vehicle ones on one or two chunks
+            let namespace = if topics::GLOBAL_TOPICS.contains(&topic) {
+                "*"
+            } else {
+                "**"
+            };
+            let key = topic_map.key(namespace, topic).replace("{sensor}", "*");
+            info!("Cache the latest {depth} samples of {key}");
+            caches.push(
+                z_session
+                    .declare_publication_cache(key)
+                    .history(depth)
+                    .res()?,
+            );
+        }
+        Ok(TopicCaches { _caches: caches })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_subscriber::CommandSubscriber;
+    use std::{sync::Mutex, time::Duration};
+
+    /// A pair of peers linked over a loopback port, without scouting
+    fn sessions(port: u16) -> (Arc<Session>, Arc<Session>) {
+        let endpoint = format!(r#"["tcp/127.0.0.1:{port}"]"#);
+        let mut publisher_config = Config::default();
+        publisher_config
+            .insert_json5("scouting/multicast/enabled", "false")
+            .unwrap();
+        publisher_config
+            .insert_json5("listen/endpoints", &endpoint)
+            .unwrap();
+        let publisher = zenoh::open(publisher_config).res().unwrap();
+        let mut subscriber_config = Config::default();
+        subscriber_config
+            .insert_json5("scouting/multicast/enabled", "false")
+            .unwrap();
+        subscriber_config
+            .insert_json5("connect/endpoints", &endpoint)
+            .unwrap();
+        let subscriber = zenoh::open(subscriber_config).res().unwrap();
+        // Let the peers exchange their routes
+        std::thread::sleep(Duration::from_millis(200));
+        (Arc::new(publisher), Arc::new(subscriber))
+    }
+
+    /// Subscribe late with a query of the caches, and collect the payloads for a while
+    fn late_subscriber(session: &Arc<Session>, key: &str, wait: Duration) -> Vec<Vec<u8>> {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        let _subscriber =
+            CommandSubscriber::declare(session, key.to_string(), true, move |sample| {
+                sink.lock()
+                    .unwrap()
+                    .push(sample.payload.contiguous().to_vec())
+            })
+            .unwrap();
+        std::thread::sleep(wait);
+        let received = received.lock().unwrap().clone();
+        received
+    }
+
+    #[test]
+    fn a_late_subscriber_gets_the_latest_samples() {
+        let (publisher, subscriber) = sessions(17_481);
+        let topic_map = TopicMap::default();
+        let depths = BTreeMap::from([(topics::CARLA_LANES.to_string(), 2)]);
+        let _caches = TopicCaches::new(publisher.clone(), &depths, &topic_map).unwrap();
+        let key = topic_map.key("ego", topics::CARLA_LANES);
+        for payload in [[1u8], [2], [3]] {
+            publisher.put(&key, payload.to_vec()).res().unwrap();
+        }
+        // Only the history depth is kept
+        let received = late_subscriber(&subscriber, &key, Duration::from_secs(1));
+        assert_eq!(received, vec![vec![2], vec![3]]);
+    }
+
+    #[test]
+    fn the_high_rate_topics_are_not_cached() {
+        let (publisher, subscriber) = sessions(17_482);
+        let topic_map = TopicMap::default();
+        let _caches = TopicCaches::new(publisher.clone(), &BTreeMap::new(), &topic_map).unwrap();
+        let key = topic_map.key("ego", topics::VELOCITY_STATUS);
+        publisher.put(&key, vec![1u8]).res().unwrap();
+        assert!(late_subscriber(&subscriber, &key, Duration::from_millis(500)).is_empty());
+    }
+
+    #[test]
+    fn reject_an_unknown_topic() {
+        let mut config = Config::default();
+        config
+            .insert_json5("scouting/multicast/enabled", "false")
+            .unwrap();
+        let session = Arc::new(zenoh::open(config).res().unwrap());
+        let depths = BTreeMap::from([(String::from("rt/no/such/topic"), 1)]);
+        assert!(matches!(
+            TopicCaches::new(session, &depths, &TopicMap::default()),
+            Err(Error::Config { .. })
+        ));
+    }
+}
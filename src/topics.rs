@@ -0,0 +1,332 @@
+//! The canonical names of the bridge topics, and their remapping.
+//!
+//! A canonical name is relative to the vehicle namespace. `{sensor}` stands for the sensor name.
+//! The ROS topics start with `rt/`, and the ROS services with `rs/`.
+use crate::error::{Error, Result};
+use serde_derive::Deserialize;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    str::FromStr,
+    sync::Arc,
+};
+use zenoh::{prelude::sync::*, publication::Publisher};
+
+pub const CLOCK: &str = "rt/clock";
+pub const SIM_STATS: &str = "bridge/stats/sim";
+pub const CARLA_STATS: &str = "bridge/stats/carla";
+pub const ACTORS: &str = "bridge/actors";
+pub const HEARTBEAT: &str = "bridge/heartbeat";
+pub const INFO: &str = "bridge/info";
+pub const RESET_ALL: &str = "bridge/reset_all";
+pub const RECORDER: &str = "bridge/recorder";
+pub const FRAME: &str = "bridge/frame";
+pub const LOAD_WORLD: &str = "bridge/load_world";
+pub const SPAWN_POINTS: &str = "bridge/map/spawn_points";
+pub const BRIDGE_LIST: &str = "bridge/list";
+pub const BRIDGE_EVENTS: &str = "bridge/events";
+/// A ROS topic, so RViz can show it
+pub const CARLA_LANES: &str = "rt/bridge/debug/carla_lanes";
+
+pub const VELOCITY_STATUS: &str = "rt/vehicle/status/velocity_status";
+pub const STEERING_STATUS: &str = "rt/vehicle/status/steering_status";
+pub const GEAR_STATUS: &str = "rt/vehicle/status/gear_status";
+pub const CONTROL_MODE: &str = "rt/vehicle/status/control_mode";
+pub const TURN_INDICATORS_STATUS: &str = "rt/vehicle/status/turn_indicators_status";
+pub const HAZARD_LIGHTS_STATUS: &str = "rt/vehicle/status/hazard_lights_status";
+pub const HEADLIGHTS_STATUS: &str = "rt/vehicle/status/headlights_status";
+pub const BATTERY_CHARGE: &str = "rt/vehicle/status/battery_charge";
+pub const FOOTPRINT: &str = "rt/vehicle/footprint";
+pub const TWIST_WITH_COVARIANCE: &str =
+    "rt/sensing/vehicle_velocity_converter/twist_with_covariance";
+pub const POSE_WITH_COVARIANCE: &str = "rt/localization/pose_estimator/pose_with_covariance";
+pub const CONTROL_CMD: &str = "rt/control/command/control_cmd";
+pub const GEAR_CMD: &str = "rt/control/command/gear_cmd";
+pub const ACTUATION_CMD: &str = "rt/control/command/actuation_cmd";
+pub const TURN_INDICATORS_CMD: &str = "rt/control/command/turn_indicators_cmd";
+pub const HAZARD_LIGHTS_CMD: &str = "rt/control/command/hazard_lights_cmd";
+pub const HEADLIGHTS_CMD: &str = "rt/control/command/headlights_cmd";
+pub const STATS: &str = "bridge/stats";
+pub const DOOR_CMD: &str = "bridge/door_cmd";
+pub const DOOR_STATUS: &str = "bridge/door_status";
+pub const SLIP: &str = "bridge/debug/slip";
+/// The request of the ADAPI door service, received as a topic
+pub const ADAPI_DOOR_CMD: &str = "rt/api/vehicle/doors/command";
+pub const ADAPI_DOOR_STATUS: &str = "rt/api/vehicle/doors/status";
+/// The ADAPI door service itself, see `service`
+pub const ADAPI_DOOR_SERVICE: &str = "rs/api/vehicle/doors/command";
+pub const WHEEL_SPEEDS: &str = "bridge/wheel_speeds";
+pub const PHYSICS_UPDATE: &str = "bridge/physics_update";
+pub const PHYSICS_REFRESH: &str = "bridge/physics/refresh";
+pub const CONTROL_MODE_SELECT: &str = "bridge/control_mode_select";
+pub const CONTROL_ENABLE: &str = "bridge/control_enable";
+pub const STEERING_OFFSET: &str = "bridge/steering_offset";
+pub const STEERING_OFFSET_CALIBRATE: &str = "bridge/steering_offset/auto_calibrate";
+pub const PEDAL_MAPS_RELOAD: &str = "bridge/pedal_maps/reload";
+pub const COLLISION_HISTORY: &str = "bridge/collision_history";
+pub const COLLISION_HISTORY_RESET: &str = "bridge/collision_history/reset";
+pub const TRAJECTORY: &str = "rt/planning/scenario_planning/trajectory";
+pub const GOAL: &str = "rt/planning/mission_planning/goal";
+pub const AUTOWARE_STATE: &str = "rt/autoware/state";
+pub const ENGAGE: &str = "rt/autoware/engage";
+pub const MRM_STATE: &str = "rt/system/fail_safe/mrm_state";
+pub const MRM_COMFORTABLE_STOP_STATUS: &str = "rt/system/mrm/comfortable_stop/status";
+pub const MRM_EMERGENCY_STOP_STATUS: &str = "rt/system/mrm/emergency_stop/status";
+pub const DIAGNOSTICS: &str = "rt/diagnostics";
+
+pub const CAMERA_IMAGE: &str = "rt/sensing/camera/{sensor}/image_raw";
+pub const CAMERA_INFO: &str = "rt/sensing/camera/{sensor}/camera_info";
+pub const DEPTH_IMAGE: &str = "rt/sensing/camera/{sensor}/depth/image_raw";
+/// The image_transport convention, a subtopic of the raw image
+pub const DEPTH_COMPRESSED: &str = "rt/sensing/camera/{sensor}/depth/image_raw/compressedDepth";
+pub const DEPTH_INFO: &str = "rt/sensing/camera/{sensor}/depth/camera_info";
+pub const LIDAR_POINTCLOUD: &str = "rt/carla_pointcloud";
+pub const IMU: &str = "rt/sensing/imu/{sensor}/imu_raw";
+pub const GNSS: &str = "rt/sensing/gnss/{sensor}/nav_sat_fix";
+/// The fixes of every GNSS in the map frame, like the output of gnss_poser
+pub const GNSS_POSE: &str = "rt/sensing/gnss/pose_with_covariance";
+pub const RADAR_SCAN: &str = "rt/sensing/radar/{sensor}/scan";
+pub const RADAR_POINTCLOUD: &str = "rt/sensing/radar/{sensor}/pointcloud";
+
+/// Shared by every namespace at once
+pub const GLOBAL_TOPICS: &[&str] = &[
+    CLOCK,
+    SIM_STATS,
+    CARLA_STATS,
+    ACTORS,
+    HEARTBEAT,
+    INFO,
+    RESET_ALL,
+    RECORDER,
+    FRAME,
+    LOAD_WORLD,
+    SPAWN_POINTS,
+    BRIDGE_LIST,
+    BRIDGE_EVENTS,
+    CARLA_LANES,
+];
+
+pub const VEHICLE_TOPICS: &[&str] = &[
+    VELOCITY_STATUS,
+    STEERING_STATUS,
+    GEAR_STATUS,
+    CONTROL_MODE,
+    TURN_INDICATORS_STATUS,
+    HAZARD_LIGHTS_STATUS,
+    HEADLIGHTS_STATUS,
+    BATTERY_CHARGE,
+    FOOTPRINT,
+    TWIST_WITH_COVARIANCE,
+    POSE_WITH_COVARIANCE,
+    CONTROL_CMD,
+    GEAR_CMD,
+    ACTUATION_CMD,
+    TURN_INDICATORS_CMD,
+    HAZARD_LIGHTS_CMD,
+    HEADLIGHTS_CMD,
+    STATS,
+    DOOR_CMD,
+    DOOR_STATUS,
+    SLIP,
+    ADAPI_DOOR_CMD,
+    ADAPI_DOOR_STATUS,
+    ADAPI_DOOR_SERVICE,
+    WHEEL_SPEEDS,
+    PHYSICS_UPDATE,
+    PHYSICS_REFRESH,
+    CONTROL_MODE_SELECT,
+    CONTROL_ENABLE,
+    STEERING_OFFSET,
+    STEERING_OFFSET_CALIBRATE,
+    PEDAL_MAPS_RELOAD,
+    COLLISION_HISTORY,
+    COLLISION_HISTORY_RESET,
+    TRAJECTORY,
+    GOAL,
+    AUTOWARE_STATE,
+    ENGAGE,
+    MRM_STATE,
+    MRM_COMFORTABLE_STOP_STATUS,
+    MRM_EMERGENCY_STOP_STATUS,
+    DIAGNOSTICS,
+];
+
+/// The command topics holding a state rather than a stream, which may query the latest value
+pub const LATCHED_COMMAND_TOPICS: &[&str] = &[
+    GEAR_CMD,
+    HEADLIGHTS_CMD,
+    CONTROL_MODE_SELECT,
+    CONTROL_ENABLE,
+    STEERING_OFFSET,
+    ENGAGE,
+];
+
+pub const WALKER_CMD_VEL: &str = "rt/cmd_vel";
+pub const WALKER_POSE: &str = "rt/walker/status/pose";
+pub const WALKER_VELOCITY: &str = "rt/walker/status/velocity";
+
+pub const WALKER_TOPICS: &[&str] = &[WALKER_CMD_VEL, WALKER_POSE, WALKER_VELOCITY];
+
+pub const SENSOR_TOPICS: &[&str] = &[
+    CAMERA_IMAGE,
+    CAMERA_INFO,
+    DEPTH_IMAGE,
+    DEPTH_COMPRESSED,
+    DEPTH_INFO,
+    LIDAR_POINTCLOUD,
+    IMU,
+    GNSS,
+    GNSS_POSE,
+    RADAR_SCAN,
+    RADAR_POINTCLOUD,
+];
+
+/// Whether the canonical topic is published or subscribed by the bridge
+pub fn is_known(topic: &str) -> bool {
+    GLOBAL_TOPICS
+        .iter()
+        .chain(VEHICLE_TOPICS)
+        .chain(WALKER_TOPICS)
+        .chain(SENSOR_TOPICS)
+        .any(|known| *known == topic)
+}
+
+/// How the ROS topics and services, the names starting with `rt/` and `rs/` after the remap,
+/// become keys
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(try_from = "String")]
+#[non_exhaustive]
+pub enum KeyStyle {
+    /// `<namespace>/rt/vehicle/status/velocity_status`, the convention of zenoh-bridge-dds
+    #[default]
+    DdsRt,
+    /// `<namespace>/vehicle/status/velocity_status`, e.g. for zenoh-plugin-ros2dds
+    Plain,
+    /// `{namespace}` and `{topic}`, the name without `rt/`, replaced in the template
+    Template(String),
+}
+
+impl FromStr for KeyStyle {
+    type Err = String;
+
+    fn from_str(style: &str) -> Result<Self, Self::Err> {
+        match style {
+            "dds_rt" => Ok(KeyStyle::DdsRt),
+            "plain" => Ok(KeyStyle::Plain),
+            _ if style.contains("{topic}") => Ok(KeyStyle::Template(style.to_string())),
+            _ => Err(format!(
+                "Unknown key style '{style}', expect dds_rt, plain or a template with {{topic}}"
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for KeyStyle {
+    type Error = String;
+
+    fn try_from(style: String) -> Result<Self, Self::Error> {
+        style.parse()
+    }
+}
+
+/// Map the canonical topic names to the configured keys
+#[derive(Debug, Clone, Default)]
+pub struct TopicMap {
+    remap: HashMap<String, String>,
+    key_style: KeyStyle,
+}
+
+impl TopicMap {
+    /// Validate the remap table: only known topics, valid key expressions and no duplicate keys
+    pub fn new(remap: BTreeMap<String, String>, key_style: KeyStyle) -> Result<TopicMap> {
+        let config_error = |reason: String| Error::Config { reason };
+        let all_topics = || {
+            GLOBAL_TOPICS
+                .iter()
+                .chain(VEHICLE_TOPICS)
+                .chain(WALKER_TOPICS)
+                .chain(SENSOR_TOPICS)
+        };
+        for (topic, target) in &remap {
+            if !is_known(topic) {
+                return Err(config_error(format!(
+                    "Unknown topic '{topic}' in the remap table"
+                )));
+            }
+            let example = format!("vehicle/{}", target.replace("{sensor}", "sensor"));
+            if target.starts_with('/') || KeyExpr::try_from(example).is_err() {
+                return Err(config_error(format!(
+                    "'{target}' (remapped from '{topic}') is not a valid key expression"
+                )));
+            }
+        }
+        let map = TopicMap {
+            remap: remap.into_iter().collect(),
+            key_style,
+        };
+        let mut keys = HashSet::new();
+        for topic in all_topics() {
+            let key = map.sensor_key("vehicle", topic, "sensor");
+            // A query never reaches a subscriber, so a service may share the key of a topic,
+            // e.g. with the plain style
+            let is_service = map.resolve(topic).starts_with("rs/");
+            if KeyExpr::try_from(key.clone()).is_err() {
+                return Err(config_error(format!(
+                    "The key style makes '{key}' of '{topic}', which is not a valid key expression"
+                )));
+            }
+            if !keys.insert((is_service, key.clone())) {
+                return Err(config_error(format!(
+                    "'{key}' is used by more than one topic"
+                )));
+            }
+        }
+        Ok(map)
+    }
+
+    /// The configured name of a canonical topic, still relative to the namespace
+    pub fn resolve<'a>(&'a self, topic: &'a str) -> &'a str {
+        self.remap
+            .get(topic)
+            .map_or(topic, |target| target.as_str())
+    }
+
+    /// The key expression of a topic in the namespace, in the key style
+    pub fn key(&self, namespace: &str, topic: &str) -> String {
+        let name = self.resolve(topic);
+        let ros_name = name
+            .strip_prefix("rt/")
+            .or_else(|| name.strip_prefix("rs/"));
+        match (ros_name, &self.key_style) {
+            (Some(ros_name), KeyStyle::Plain) => format!("{namespace}/{ros_name}"),
+            (Some(ros_name), KeyStyle::Template(template)) => template
+                .replace("{namespace}", namespace)
+                .replace("{topic}", ros_name),
+            _ => format!("{namespace}/{name}"),
+        }
+    }
+
+    /// The key expression of a sensor topic in the namespace
+    pub fn sensor_key(&self, namespace: &str, topic: &str, sensor_name: &str) -> String {
+        self.key(namespace, topic).replace("{sensor}", sensor_name)
+    }
+
+    /// Every resolved topic, for `--print-topics`
+    pub fn describe(&self) -> Vec<String> {
+        let global = GLOBAL_TOPICS.iter().map(|topic| self.key("*", topic));
+        let vehicle = VEHICLE_TOPICS
+            .iter()
+            .chain(SENSOR_TOPICS)
+            .map(|topic| self.key("<vehicle>", topic).replace("{sensor}", "<sensor>"));
+        let walker = WALKER_TOPICS
+            .iter()
+            .map(|topic| self.key("<walker>", topic));
+        global.chain(vehicle).chain(walker).collect()
+    }
+}
+
+/// Declare the key expression on the session, then a publisher on it, so the samples carry
+/// a numeric id instead of the whole key. Every publisher of the bridge goes through here.
+pub fn declare_publisher(z_session: &Arc<Session>, key: &str) -> Result<Publisher<'static>> {
+    let key_expr = z_session.declare_keyexpr(key.to_string()).res()?;
+    Ok(z_session.declare_publisher(key_expr).res()?)
+}
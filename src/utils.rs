@@ -1,29 +1,62 @@
-use crate::error::Result;
-use std::time::{SystemTime, UNIX_EPOCH};
-use zenoh_ros_type::{builtin_interfaces, std_msgs};
+use crate::{error::Result, time::TimeStamp};
+use nalgebra::UnitQuaternion;
+use std::time::{Duration, Instant};
+use zenoh_ros_type::std_msgs;
 
 pub fn is_bigendian() -> bool {
     cfg!(target_endian = "big")
 }
 
 pub fn create_ros_header(timestamp: Option<f64>) -> Result<std_msgs::Header> {
-    let time = if let Some(sec) = timestamp {
-        builtin_interfaces::Time {
-            sec: sec.floor() as i32,
-            nanosec: (sec.fract() * 1000_000_000_f64) as u32,
-        }
-    } else {
-        // If there is no timestamp, use system time
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("Unable to get current time");
-        builtin_interfaces::Time {
-            sec: now.as_secs() as i32,
-            nanosec: now.subsec_nanos(),
+    // If there is no timestamp, use system time
+    let time = timestamp.map_or_else(TimeStamp::now, TimeStamp::from_secs_f64);
+    Ok(ros_header(time, ""))
+}
+
+/// A header at the stamp, e.g. the one of the tick
+pub fn ros_header(stamp: TimeStamp, frame_id: &str) -> std_msgs::Header {
+    std_msgs::Header {
+        stamp: stamp.to_msg(),
+        frame_id: frame_id.to_string(),
+    }
+}
+
+/// Convert a CARLA position to ROS. CARLA is left-handed with y to the right, ROS has y to the left.
+pub fn carla_to_ros_position(x: f32, y: f32, z: f32) -> [f64; 3] {
+    [x as f64, -y as f64, z as f64]
+}
+
+/// Convert a CARLA rotation to a ROS quaternion as x, y, z, w, mirrored like the position
+pub fn carla_to_ros_orientation(rotation: &UnitQuaternion<f32>) -> [f64; 4] {
+    let (roll, pitch, yaw) = rotation.euler_angles();
+    let rotation = UnitQuaternion::from_euler_angles(roll as f64, -pitch as f64, -yaw as f64);
+    [rotation.i, rotation.j, rotation.k, rotation.w]
+}
+
+/// The inverse of `carla_to_ros_position`
+pub fn ros_to_carla_position(x: f64, y: f64, z: f64) -> [f32; 3] {
+    [x as f32, -y as f32, z as f32]
+}
+
+/// Allow an action (usually logging) at most once per period
+pub struct LogThrottle {
+    period: Duration,
+    last: Option<Instant>,
+}
+
+impl LogThrottle {
+    pub fn new(period: Duration) -> LogThrottle {
+        LogThrottle { period, last: None }
+    }
+
+    pub fn ready(&mut self) -> bool {
+        let now = Instant::now();
+        match self.last {
+            Some(last) if now.duration_since(last) < self.period => false,
+            _ => {
+                self.last = Some(now);
+                true
+            }
         }
-    };
-    Ok(std_msgs::Header {
-        stamp: time,
-        frame_id: "".to_string(),
-    })
+    }
 }
@@ -0,0 +1,239 @@
+//! A vehicle bridge over the mock vehicle, commanded and observed from a second zenoh peer
+//! the way Autoware would
+use nalgebra::Vector3;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use zenoh::prelude::sync::*;
+use zenoh_carla_bridge::{
+    testing::{
+        cdr_utils::{self, Endianness},
+        topics::{self, KeyStyle, TopicMap},
+        ActorBridge, MockVehicle, NamespaceRegistry, TickSnapshot, TimeStamp, VehicleBridge,
+    },
+    BridgeConfig,
+};
+use zenoh_ros_type::{
+    autoware_auto_control_msgs::{
+        AckermannControlCommand, AckermannLateralCommand, LongitudinalCommand,
+    },
+    autoware_auto_vehicle_msgs::VelocityReport,
+};
+
+const DT: f64 = 0.05;
+const WAIT: Duration = Duration::from_secs(5);
+
+/// The bridge listens on the loopback and the Autoware side connects, without scouting
+fn peers(port: u16) -> (Arc<Session>, Arc<Session>) {
+    let endpoint = format!(r#"["tcp/127.0.0.1:{port}"]"#);
+    let mut bridge_config = Config::default();
+    bridge_config
+        .insert_json5("scouting/multicast/enabled", "false")
+        .unwrap();
+    bridge_config
+        .insert_json5("listen/endpoints", &endpoint)
+        .unwrap();
+    let bridge = zenoh::open(bridge_config).res().unwrap();
+    let mut autoware_config = Config::default();
+    autoware_config
+        .insert_json5("scouting/multicast/enabled", "false")
+        .unwrap();
+    autoware_config
+        .insert_json5("connect/endpoints", &endpoint)
+        .unwrap();
+    let autoware = zenoh::open(autoware_config).res().unwrap();
+    // Let the peers exchange their routes
+    thread::sleep(Duration::from_millis(200));
+    (Arc::new(bridge), Arc::new(autoware))
+}
+
+/// A bridge of the mock vehicle on its own peer, stepped like the main loop
+struct Harness {
+    autoware: Arc<Session>,
+    vehicle: MockVehicle,
+    bridge: VehicleBridge<'static>,
+    frame: u64,
+}
+
+impl Harness {
+    fn new(port: u16, role_name: &str, config: &BridgeConfig) -> Harness {
+        let (session, autoware) = peers(port);
+        let vehicle = MockVehicle::new(1, role_name);
+        let namespaces = Arc::new(Mutex::new(NamespaceRegistry::default()));
+        let bridge =
+            VehicleBridge::new(session, Box::new(vehicle.clone()), config, namespaces).unwrap();
+        Harness {
+            autoware,
+            vehicle,
+            bridge,
+            frame: 0,
+        }
+    }
+
+    fn stamp(&self) -> TimeStamp {
+        TimeStamp::from_secs_f64(self.frame as f64 * DT)
+    }
+
+    fn tick(&mut self) {
+        self.frame += 1;
+        let stamp = self.stamp();
+        let tick = TickSnapshot::without_world(stamp, self.frame);
+        self.bridge.step(DT, &tick).unwrap();
+        self.bridge.publish_status(stamp).unwrap();
+        self.bridge.apply_control(stamp, DT);
+    }
+
+    /// Put a control command on `key` and tick until the vehicle follows one, since the
+    /// subscriber of the bridge may not be known to the Autoware peer yet
+    fn command(&mut self, key: &str, speed: f32, steering_tire_angle: f32) {
+        let start = Instant::now();
+        while self.vehicle.applied_ackermann_controls().is_empty() {
+            assert!(start.elapsed() < WAIT, "no control_cmd on {key}");
+            let cmd = control_command(self.stamp(), speed, steering_tire_angle);
+            let payload = cdr_utils::serialize(&cmd, Endianness::Little).unwrap();
+            self.autoware.put(key, payload).res().unwrap();
+            thread::sleep(Duration::from_millis(10));
+            self.tick();
+        }
+    }
+
+    /// Subscribe to `key` on the Autoware peer and tick until a sample arrives
+    fn payload(&mut self, key: &str) -> Vec<u8> {
+        let autoware = self.autoware.clone();
+        let subscriber = autoware.declare_subscriber(key).res().unwrap();
+        let start = Instant::now();
+        loop {
+            assert!(start.elapsed() < WAIT, "no sample on {key}");
+            self.tick();
+            if let Ok(sample) = subscriber.recv_timeout(Duration::from_millis(50)) {
+                return sample.payload.contiguous().to_vec();
+            }
+        }
+    }
+
+    fn velocity_report(&mut self, key: &str) -> VelocityReport {
+        cdr_utils::deserialize(&self.payload(key)).unwrap()
+    }
+}
+
+fn control_command(
+    stamp: TimeStamp,
+    speed: f32,
+    steering_tire_angle: f32,
+) -> AckermannControlCommand {
+    AckermannControlCommand {
+        stamp: stamp.to_msg(),
+        lateral: AckermannLateralCommand {
+            stamp: stamp.to_msg(),
+            steering_tire_angle,
+            steering_tire_rotation_rate: 0.0,
+        },
+        longitudinal: LongitudinalCommand {
+            stamp: stamp.to_msg(),
+            speed,
+            acceleration: 1.0,
+            jerk: 0.0,
+        },
+    }
+}
+
+#[test]
+fn a_command_of_the_other_peer_actuates_the_vehicle() {
+    let config = BridgeConfig::default();
+    let mut harness = Harness::new(17491, "autoware_ego", &config);
+    harness.command(&config.topics.key("ego", topics::CONTROL_CMD), 5.0, 0.1);
+    // The ticks until the command arrived held the brake
+    harness.vehicle.clear_applied();
+    harness.tick();
+    let applied = harness.vehicle.applied_ackermann_controls();
+    let control = applied.last().unwrap();
+    assert_eq!(control.speed, 5.0);
+    assert_eq!(control.acceleration, 1.0);
+    // Autoware turns left with a positive angle, CARLA with a negative steer
+    assert!(control.steer < 0.0);
+    assert!(harness.vehicle.applied_controls().is_empty());
+}
+
+#[test]
+fn the_other_peer_decodes_the_velocity_report() {
+    let config = BridgeConfig::default();
+    let mut harness = Harness::new(17492, "autoware_ego", &config);
+    harness.vehicle.set_velocity(Vector3::new(3.0, 0.0, 0.0));
+    let report = harness.velocity_report(&config.topics.key("ego", topics::VELOCITY_STATUS));
+    assert_eq!(report.header.frame_id, "base_link");
+    assert_eq!(report.longitudinal_velocity, 3.0);
+}
+
+#[test]
+fn the_published_samples_have_the_configured_endianness() {
+    for (port, endianness, header) in [
+        (17495, Endianness::Little, [0x00, 0x01]),
+        (17496, Endianness::Big, [0x00, 0x00]),
+    ] {
+        let mut config = BridgeConfig::default();
+        config.cdr_endianness = endianness;
+        let mut harness = Harness::new(port, "autoware_ego", &config);
+        harness.vehicle.set_velocity(Vector3::new(3.0, 0.0, 0.0));
+        let key = config.topics.key("ego", topics::VELOCITY_STATUS);
+        let payload = harness.payload(&key);
+        assert_eq!(payload[0..2], header, "{endianness:?}");
+        let report: VelocityReport = cdr_utils::deserialize(&payload).unwrap();
+        assert_eq!(report.longitudinal_velocity, 3.0);
+    }
+}
+
+#[test]
+fn the_remapped_topics_use_their_keys() {
+    let remap = BTreeMap::from([
+        (
+            topics::CONTROL_CMD.to_string(),
+            String::from("rt/autoware/control_cmd"),
+        ),
+        (
+            topics::VELOCITY_STATUS.to_string(),
+            String::from("rt/autoware/velocity"),
+        ),
+    ]);
+    let mut config = BridgeConfig::default();
+    config.topics = TopicMap::new(remap, KeyStyle::Plain).unwrap();
+    let mut harness = Harness::new(17493, "autoware_ego", &config);
+    harness.vehicle.set_velocity(Vector3::new(2.0, 0.0, 0.0));
+    let report = harness.velocity_report("ego/autoware/velocity");
+    assert_eq!(report.longitudinal_velocity, 2.0);
+    harness.command("ego/autoware/control_cmd", 4.0, 0.0);
+    assert_eq!(
+        harness
+            .vehicle
+            .applied_ackermann_controls()
+            .last()
+            .unwrap()
+            .speed,
+        4.0
+    );
+}
+
+#[test]
+fn the_role_name_is_sanitized_into_the_namespace() {
+    let config = BridgeConfig::default();
+    let mut harness = Harness::new(17494, "autoware_my car/1", &config);
+    harness.vehicle.set_velocity(Vector3::new(1.0, 0.0, 0.0));
+    let report = harness.velocity_report(&config.topics.key("my_car_1", topics::VELOCITY_STATUS));
+    assert_eq!(report.longitudinal_velocity, 1.0);
+    harness.command(
+        &config.topics.key("my_car_1", topics::CONTROL_CMD),
+        3.0,
+        0.0,
+    );
+    assert_eq!(
+        harness
+            .vehicle
+            .applied_ackermann_controls()
+            .last()
+            .unwrap()
+            .speed,
+        3.0
+    );
+}
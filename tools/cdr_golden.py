@@ -0,0 +1,298 @@
+#!/usr/bin/env python3
+"""The golden bytes of the message tests in src/autoware_type/tests.rs.
+
+An encoder of plain CDR which shares nothing with the Rust cdr crate: the layouts below are
+written after the interfaces in msg/, and the ones of zenoh-ros-type after their ROS
+definitions, field by field. The samples are those of the `Golden` impls. Print the hex of
+the named samples, or of all:
+
+    python3 tools/cdr_golden.py Lateral Trajectory
+
+Or write the synthetic payloads of tests/fixtures, named like `echo --save-raw` does:
+
+    python3 tools/cdr_golden.py --fixtures tests/fixtures
+"""
+import os
+import struct
+import sys
+
+PRIM = {
+    'bool': ('?', 1), 'u8': ('B', 1), 'i8': ('b', 1), 'u16': ('H', 2), 'i16': ('h', 2),
+    'u32': ('I', 4), 'i32': ('i', 4), 'u64': ('Q', 8), 'i64': ('q', 8),
+    'f32': ('f', 4), 'f64': ('d', 8),
+}
+
+# Struct layouts: name -> list of (field, type). Types: prim, 'string', ('array', t, n),
+# ('seq', t), or another struct name.
+LAYOUTS = {
+    'Time': [('sec', 'i32'), ('nanosec', 'u32')],
+    'Header': [('stamp', 'Time'), ('frame_id', 'string')],
+    'Lateral': [('stamp', 'Time'), ('control_time', 'Time'), ('steering_tire_angle', 'f32'),
+                ('steering_tire_rotation_rate', 'f32'),
+                ('is_defined_steering_tire_rotation_rate', 'bool')],
+    'Longitudinal': [('stamp', 'Time'), ('control_time', 'Time'), ('velocity', 'f32'),
+                     ('acceleration', 'f32'), ('jerk', 'f32'),
+                     ('is_defined_acceleration', 'bool'), ('is_defined_jerk', 'bool')],
+    'Control': [('stamp', 'Time'), ('control_time', 'Time'), ('lateral', 'Lateral'),
+                ('longitudinal', 'Longitudinal')],
+    'BatteryStatus': [('stamp', 'Time'), ('energy_level', 'f32')],
+    'DoorCommand': [('stamp', 'Time'), ('position', 'u8'), ('command', 'u8')],
+    'DoorStatus': [('stamp', 'Time'), ('doors', ('array', 'u8', 4))],
+    'AdapiDoorCommand': [('index', 'u32'), ('command', 'u8')],
+    'SetDoorCommand': [('stamp', 'Time'), ('doors', ('seq', 'AdapiDoorCommand'))],
+    'ResponseStatus': [('success', 'bool'), ('code', 'u16'), ('message', 'string')],
+    'SetDoorCommandResponse': [('status', 'ResponseStatus')],
+    'AdapiDoorStatus': [('status', 'u8')],
+    'DoorStatusArray': [('stamp', 'Time'), ('doors', ('seq', 'AdapiDoorStatus'))],
+    'AxleSlip': [('longitudinal_slip', 'f32'), ('slip_angle', 'f32')],
+    'SlipEstimate': [('stamp', 'Time'), ('front', 'AxleSlip'), ('rear', 'AxleSlip')],
+    'WheelSpeeds': [('stamp', 'Time'), ('wheel_speeds', ('array', 'f32', 4)),
+                    ('vehicle_speed', 'f32')],
+    'ActuationCommand': [('accel_cmd', 'f64'), ('brake_cmd', 'f64'), ('steer_cmd', 'f64')],
+    'ActuationCommandStamped': [('header', 'Header'), ('actuation', 'ActuationCommand')],
+    'HeadlightsCommand': [('stamp', 'Time'), ('command', 'u8')],
+    'HeadlightsReport': [('stamp', 'Time'), ('report', 'u8')],
+    'MrmState': [('stamp', 'Time'), ('state', 'u16'), ('behavior', 'u16')],
+    'MrmBehaviorStatus': [('stamp', 'Time'), ('state', 'u8')],
+    'KeyValue': [('key', 'string'), ('value', 'string')],
+    'DiagnosticStatus': [('level', 'u8'), ('name', 'string'), ('message', 'string'),
+                         ('hardware_id', 'string'), ('values', ('seq', 'KeyValue'))],
+    'DiagnosticArray': [('header', 'Header'), ('status', ('seq', 'DiagnosticStatus'))],
+    'Twist': [('linear', ('array', 'f64', 3)), ('angular', ('array', 'f64', 3))],
+    'TwistStamped': [('header', 'Header'), ('twist', 'Twist')],
+    'TwistWithCovariance': [('twist', 'Twist'), ('covariance', ('array', 'f64', 36))],
+    'TwistWithCovarianceStamped': [('header', 'Header'), ('twist', 'TwistWithCovariance')],
+    'Point32': [('x', 'f32'), ('y', 'f32'), ('z', 'f32')],
+    'Polygon': [('points', ('seq', 'Point32'))],
+    'PolygonStamped': [('header', 'Header'), ('polygon', 'Polygon')],
+    'AutowareState': [('stamp', 'Time'), ('state', 'u8')],
+    'Engage': [('stamp', 'Time'), ('engage', 'bool')],
+    'Pose': [('position', ('array', 'f64', 3)), ('orientation', ('array', 'f64', 4))],
+    'PoseWithCovariance': [('pose', 'Pose'), ('covariance', ('array', 'f64', 36))],
+    'PoseWithCovarianceStamped': [('header', 'Header'), ('pose', 'PoseWithCovariance')],
+    'PoseStamped': [('header', 'Header'), ('pose', 'Pose')],
+    'TrajectoryPoint': [('time_from_start', 'Time'), ('pose', 'Pose'),
+                        ('longitudinal_velocity_mps', 'f32'), ('lateral_velocity_mps', 'f32'),
+                        ('acceleration_mps2', 'f32'), ('heading_rate_rps', 'f32'),
+                        ('front_wheel_angle_rad', 'f32'), ('rear_wheel_angle_rad', 'f32')],
+    'Trajectory': [('header', 'Header'), ('points', ('seq', 'TrajectoryPoint'))],
+    'GateMode': [('data', 'u8')],
+    # Of zenoh-ros-type
+    'AckermannLateralCommand': [('stamp', 'Time'), ('steering_tire_angle', 'f32'),
+                                ('steering_tire_rotation_rate', 'f32')],
+    'LongitudinalCommand': [('stamp', 'Time'), ('speed', 'f32'), ('acceleration', 'f32'),
+                            ('jerk', 'f32')],
+    'AckermannControlCommand': [('stamp', 'Time'), ('lateral', 'AckermannLateralCommand'),
+                                ('longitudinal', 'LongitudinalCommand')],
+    'GearCommand': [('stamp', 'Time'), ('command', 'u8')],
+    'Clock': [('clock', 'Time')],
+    'VelocityReport': [('header', 'Header'), ('longitudinal_velocity', 'f32'),
+                       ('lateral_velocity', 'f32'), ('heading_rate', 'f32')],
+    'SteeringReport': [('stamp', 'Time'), ('steering_tire_angle', 'f32')],
+    'GearReport': [('stamp', 'Time'), ('report', 'u8')],
+}
+
+
+class Writer:
+    def __init__(self, big):
+        self.order = '>' if big else '<'
+        self.data = bytearray()
+
+    def align(self, n):
+        while len(self.data) % n:
+            self.data.append(0)
+
+    def prim(self, t, v):
+        fmt, size = PRIM[t]
+        self.align(size)
+        self.data += struct.pack(self.order + fmt, v)
+
+    def value(self, t, v):
+        if isinstance(t, tuple):
+            if t[0] == 'array':
+                assert len(v) == t[2], (t, v)
+                for item in v:
+                    self.value(t[1], item)
+            else:
+                self.prim('u32', len(v))
+                for item in v:
+                    self.value(t[1], item)
+        elif t == 'string':
+            raw = v.encode() + b'\0'
+            self.prim('u32', len(raw))
+            self.data += raw
+        elif t in PRIM:
+            self.prim(t, v)
+        else:
+            fields = LAYOUTS[t]
+            assert set(v) == {f for f, _ in fields}, (t, set(v) ^ {f for f, _ in fields})
+            for field, ft in fields:
+                self.value(ft, v[field])
+
+
+def encode(t, v, big=False):
+    w = Writer(big)
+    w.value(t, v)
+    return bytes([0, 0 if big else 1, 0, 0]) + bytes(w.data)
+
+
+def time(sec, nanosec=0):
+    return {'sec': sec, 'nanosec': nanosec}
+
+
+STAMP = time(1, 500_000_000)
+HEADER = {'stamp': STAMP, 'frame_id': 'base_link'}
+# A diagonal covariance of 0.01 to 0.06, row-major
+COVARIANCE = [(i // 6 + 1) * 0.01 if i // 6 == i % 6 else 0.0 for i in range(36)]
+TWIST = {'linear': [1.5, -0.25, 0.0], 'angular': [0.0, 0.0, 0.125]}
+POSE = {'position': [10.0, -2.5, 0.5], 'orientation': [0.0, 0.0, 0.6, 0.8]}
+LATERAL = {'stamp': STAMP, 'control_time': time(2), 'steering_tire_angle': 0.25,
+           'steering_tire_rotation_rate': -0.5, 'is_defined_steering_tire_rotation_rate': True}
+LONGITUDINAL = {'stamp': STAMP, 'control_time': time(2), 'velocity': 2.0, 'acceleration': 1.0,
+                'jerk': -1.0, 'is_defined_acceleration': True, 'is_defined_jerk': False}
+RESPONSE_STATUS = {'success': False, 'code': 50001, 'message': 'no doors'}
+AXLE_SLIP = {'longitudinal_slip': 0.125, 'slip_angle': -0.0625}
+KEY_VALUE = {'key': 'rate_hz', 'value': '30'}
+DIAGNOSTIC_STATUS = {'level': 1, 'name': 'control_cmd', 'message': 'below 10 Hz',
+                     'hardware_id': 'ego', 'values': [KEY_VALUE]}
+TRAJECTORY_POINT = {'time_from_start': time(0, 100_000_000), 'pose': POSE,
+                    'longitudinal_velocity_mps': 3.0, 'lateral_velocity_mps': 0.0,
+                    'acceleration_mps2': 0.5, 'heading_rate_rps': -0.125,
+                    'front_wheel_angle_rad': 0.0625, 'rear_wheel_angle_rad': 0.0}
+
+SAMPLES = {
+    'Lateral': LATERAL,
+    'Longitudinal': LONGITUDINAL,
+    'Control': {'stamp': STAMP, 'control_time': time(2), 'lateral': LATERAL,
+                'longitudinal': LONGITUDINAL},
+    'BatteryStatus': {'stamp': STAMP, 'energy_level': 87.5},
+    'DoorCommand': {'stamp': STAMP, 'position': 4, 'command': 1},
+    'DoorStatus': {'stamp': STAMP, 'doors': [2, 1, 1, 0]},
+    'AdapiDoorCommand': {'index': 3, 'command': 2},
+    'SetDoorCommand': {'stamp': STAMP, 'doors': [{'index': 0, 'command': 1},
+                                                 {'index': 3, 'command': 2}]},
+    'SetDoorCommandResponse': {'status': RESPONSE_STATUS},
+    'ResponseStatus': RESPONSE_STATUS,
+    'AdapiDoorStatus': {'status': 4},
+    'DoorStatusArray': {'stamp': STAMP, 'doors': [{'status': 2}, {'status': 3}]},
+    'AxleSlip': AXLE_SLIP,
+    'SlipEstimate': {'stamp': STAMP, 'front': AXLE_SLIP,
+                     'rear': {'longitudinal_slip': -0.25, 'slip_angle': 0.0}},
+    'WheelSpeeds': {'stamp': STAMP, 'wheel_speeds': [10.0, 10.5, 9.75, 10.25],
+                    'vehicle_speed': 3.5},
+    'ActuationCommand': {'accel_cmd': 0.3, 'brake_cmd': 0.0, 'steer_cmd': -0.1},
+    'ActuationCommandStamped': {'header': HEADER, 'actuation': {'accel_cmd': 0.3,
+                                                                'brake_cmd': 0.0,
+                                                                'steer_cmd': -0.1}},
+    'HeadlightsCommand': {'stamp': STAMP, 'command': 3},
+    'HeadlightsReport': {'stamp': STAMP, 'report': 2},
+    'MrmState': {'stamp': STAMP, 'state': 2, 'behavior': 3},
+    'MrmBehaviorStatus': {'stamp': STAMP, 'state': 2},
+    'KeyValue': KEY_VALUE,
+    'DiagnosticStatus': DIAGNOSTIC_STATUS,
+    'DiagnosticArray': {'header': HEADER, 'status': [DIAGNOSTIC_STATUS]},
+    'Twist': TWIST,
+    'TwistStamped': {'header': HEADER, 'twist': TWIST},
+    'TwistWithCovariance': {'twist': TWIST, 'covariance': COVARIANCE},
+    'TwistWithCovarianceStamped': {'header': HEADER,
+                                   'twist': {'twist': TWIST, 'covariance': COVARIANCE}},
+    'Point32': {'x': 2.5, 'y': -1.0, 'z': 0.0},
+    'Polygon': {'points': [{'x': 2.5, 'y': -1.0, 'z': 0.0}, {'x': 2.5, 'y': 1.0, 'z': 0.0}]},
+    'PolygonStamped': {'header': HEADER, 'polygon': {
+        'points': [{'x': 2.5, 'y': -1.0, 'z': 0.0}, {'x': 2.5, 'y': 1.0, 'z': 0.0}]}},
+    'AutowareState': {'stamp': STAMP, 'state': 5},
+    'Engage': {'stamp': STAMP, 'engage': True},
+    'Pose': POSE,
+    'PoseWithCovariance': {'pose': POSE, 'covariance': COVARIANCE},
+    'PoseWithCovarianceStamped': {'header': HEADER,
+                                  'pose': {'pose': POSE, 'covariance': COVARIANCE}},
+    'PoseStamped': {'header': HEADER, 'pose': POSE},
+    'TrajectoryPoint': TRAJECTORY_POINT,
+    'Trajectory': {'header': HEADER, 'points': [TRAJECTORY_POINT]},
+    'GateMode': {'data': 1},
+}
+
+
+# The payloads of tests/fixtures decoded by the echo tests, by key expression. They are
+# synthetic, none of them is a capture of Autoware.
+FIXTURE_STAMP = time(42, 100_000_000)
+FIXTURES = {
+    'carla/clock': ('Clock', {'clock': STAMP}),
+    'ego/vehicle/status/velocity_status': ('VelocityReport', {
+        'header': HEADER, 'longitudinal_velocity': 3.0, 'lateral_velocity': -0.5,
+        'heading_rate': 0.25}),
+    'ego/vehicle/status/steering_status': ('SteeringReport', {
+        'stamp': STAMP, 'steering_tire_angle': 0.125}),
+    'ego/vehicle/status/gear_status': ('GearReport', {'stamp': STAMP, 'report': 2}),
+    'ego/control/command/control_cmd': ('AckermannControlCommand', {
+        'stamp': FIXTURE_STAMP,
+        'lateral': {'stamp': time(42, 95_000_000), 'steering_tire_angle': -0.0390625,
+                    'steering_tire_rotation_rate': 0.015625},
+        'longitudinal': {'stamp': time(42, 97_500_000), 'speed': 4.1875,
+                         'acceleration': -0.625, 'jerk': 0.3125},
+    }),
+    'ego/control/command/gear_cmd': ('GearCommand', {'stamp': FIXTURE_STAMP, 'command': 20}),
+    'ego/control/current_gate_mode': ('GateMode', {'data': 0}),
+    'ego/planning/scenario_planning/trajectory': ('Trajectory', {
+        'header': {'stamp': time(42, 50_000_000), 'frame_id': 'map'},
+        'points': [
+            {'time_from_start': time(0, 100_000_000 * i),
+             'pose': {'position': [120.5 + 0.5 * i, -34.25, 0.0],
+                      'orientation': [0.0, 0.0, 0.0, 1.0]},
+             'longitudinal_velocity_mps': 4.0 + 0.25 * i, 'lateral_velocity_mps': 0.0,
+             'acceleration_mps2': 0.5, 'heading_rate_rps': 0.0,
+             'front_wheel_angle_rad': 0.0, 'rear_wheel_angle_rad': 0.0}
+            for i in range(3)
+        ],
+    }),
+}
+
+# The control command of each Autoware release, in the layout of the release, by file name
+VERSION_LATERAL = {'stamp': STAMP, 'steering_tire_angle': 0.25,
+                   'steering_tire_rotation_rate': 0.5}
+VERSION_LONGITUDINAL = {'speed': 2.0, 'acceleration': 1.0, 'jerk': -1.0}
+VERSION_FIXTURES = {
+    'control_cmd_universe_2023.cdr': ('AckermannControlCommand', {
+        'stamp': STAMP, 'lateral': VERSION_LATERAL,
+        'longitudinal': {'stamp': STAMP, **VERSION_LONGITUDINAL}}),
+    'control_cmd_universe_2024.cdr': ('Control', {
+        'stamp': STAMP, 'control_time': STAMP,
+        'lateral': {'control_time': STAMP, 'is_defined_steering_tire_rotation_rate': True,
+                    **VERSION_LATERAL},
+        'longitudinal': {'stamp': STAMP, 'control_time': STAMP, 'velocity': 2.0,
+                         'acceleration': 1.0, 'jerk': -1.0, 'is_defined_acceleration': True,
+                         'is_defined_jerk': False}}),
+}
+
+
+def write_fixtures(directory):
+    files = [(key.replace('/', '_') + '_000000.cdr', fixture)
+             for key, fixture in FIXTURES.items()]
+    for file_name, (name, sample) in files + list(VERSION_FIXTURES.items()):
+        path = os.path.join(directory, file_name)
+        with open(path, 'wb') as file:
+            file.write(encode(name, sample))
+        print(path)
+
+
+def rust_hex(name, data):
+    """A Rust string of the hex, in words of 4 bytes and 8 words per line"""
+    words = [data[i:i + 4].hex() for i in range(0, len(data), 4)]
+    lines = [' '.join(words[i:i + 8]) for i in range(0, len(words), 8)]
+    body = '\n'.join(f'        {line}' for line in lines)
+    return f'    const {name}: &\'static str = "\\\n{body}";'
+
+
+def main(names):
+    for name in names or SAMPLES:
+        print(name)
+        for big, const in [(False, 'LE'), (True, 'BE')]:
+            # Without the encapsulation header, which the tests prepend
+            print(rust_hex(const, encode(name, SAMPLES[name], big)[4:]))
+
+
+if __name__ == '__main__':
+    if sys.argv[1:2] == ['--fixtures']:
+        write_fixtures(sys.argv[2])
+    else:
+        main(sys.argv[1:])
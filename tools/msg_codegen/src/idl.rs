@@ -0,0 +1,437 @@
+//! The subset of OMG IDL which rosidl generates and reads
+//!
+//! Modules, structs, the constants of a struct in its `<Name>_Constants` module, typedefs,
+//! arrays, sequences, and the `@default` and `@verbatim (language="comment")` annotations.
+use crate::{Constant, Field, Message, Primitive, Result, Type};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Number(String),
+    Text(String),
+    /// `::`
+    Scope,
+    Punct(char),
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut at = 0;
+    while at < chars.len() {
+        let c = chars[at];
+        let next = chars.get(at + 1).copied();
+        if c.is_whitespace() {
+            at += 1;
+        } else if c == '#' || (c == '/' && next == Some('/')) {
+            // Preprocessor lines, the includes only name what the scoped names already do
+            while at < chars.len() && chars[at] != '\n' {
+                at += 1;
+            }
+        } else if c == '/' && next == Some('*') {
+            at += 2;
+            while at < chars.len() && !(chars[at] == '*' && chars.get(at + 1) == Some(&'/')) {
+                at += 1;
+            }
+            at += 2;
+        } else if c == '"' {
+            let mut text = String::new();
+            at += 1;
+            loop {
+                match chars.get(at) {
+                    None => return Err("unterminated string".to_string()),
+                    Some('"') => break,
+                    Some('\\') => {
+                        at += 1;
+                        text.push(match chars.get(at) {
+                            Some('n') => '\n',
+                            Some('t') => '\t',
+                            Some(&c) => c,
+                            None => return Err("unterminated string".to_string()),
+                        });
+                    }
+                    Some(&c) => text.push(c),
+                }
+                at += 1;
+            }
+            at += 1;
+            tokens.push(Token::Text(text));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = at;
+            while at < chars.len() && (chars[at].is_ascii_alphanumeric() || chars[at] == '_') {
+                at += 1;
+            }
+            tokens.push(Token::Word(chars[start..at].iter().collect()));
+        } else if c.is_ascii_digit() || (c == '-' && next.is_some_and(|n| n.is_ascii_digit())) {
+            let start = at;
+            at += 1;
+            while at < chars.len()
+                && (chars[at].is_ascii_alphanumeric()
+                    || chars[at] == '.'
+                    || (matches!(chars[at], '+' | '-') && matches!(chars[at - 1], 'e' | 'E')))
+            {
+                at += 1;
+            }
+            tokens.push(Token::Number(chars[start..at].iter().collect()));
+        } else if c == ':' && next == Some(':') {
+            at += 2;
+            tokens.push(Token::Scope);
+        } else {
+            at += 1;
+            tokens.push(Token::Punct(c));
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Default)]
+struct Annotations {
+    default: Option<String>,
+    doc: Vec<String>,
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    at: usize,
+    /// The enclosing modules
+    modules: Vec<String>,
+    typedefs: BTreeMap<String, Type>,
+    structs: BTreeMap<String, Message>,
+    constants: BTreeMap<String, Vec<Constant>>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.at)
+    }
+
+    fn next(&mut self) -> Result<Token> {
+        let token = self.peek().cloned().ok_or("unexpected end")?;
+        self.at += 1;
+        Ok(token)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(&Token::Punct(c)) {
+            self.at += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        match self.next()? {
+            Token::Punct(found) if found == c => Ok(()),
+            found => Err(format!("expect {c:?}, found {found:?}")),
+        }
+    }
+
+    fn word(&mut self) -> Result<String> {
+        match self.next()? {
+            Token::Word(word) => Ok(word),
+            found => Err(format!("expect a name, found {found:?}")),
+        }
+    }
+
+    fn is_word(&self, word: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(found)) if found == word)
+    }
+
+    fn value(&mut self) -> Result<String> {
+        self.literal().map(as_value)
+    }
+
+    fn literal(&mut self) -> Result<Token> {
+        match self.next()? {
+            Token::Text(mut text) => {
+                // Adjacent texts are one
+                while let Some(Token::Text(more)) = self.peek() {
+                    text.push_str(more);
+                    self.at += 1;
+                }
+                Ok(Token::Text(text))
+            }
+            token @ (Token::Number(_) | Token::Word(_)) => Ok(token),
+            found => Err(format!("expect a value, found {found:?}")),
+        }
+    }
+
+    fn number(&mut self) -> Result<usize> {
+        match self.next()? {
+            Token::Number(number) => number.parse().map_err(|_| format!("bad size {number}")),
+            found => Err(format!("expect a size, found {found:?}")),
+        }
+    }
+
+    fn annotations(&mut self) -> Result<Annotations> {
+        let mut annotations = Annotations::default();
+        while self.eat('@') {
+            let name = self.word()?;
+            let mut arguments = BTreeMap::new();
+            if self.eat('(') {
+                while !self.eat(')') {
+                    let key = self.word()?;
+                    self.expect('=')?;
+                    arguments.insert(key, self.literal()?);
+                    self.eat(',');
+                }
+            }
+            match name.as_str() {
+                "default" => annotations.default = arguments.remove("value").map(as_value),
+                "verbatim" if arguments.get("language") == Some(&Token::Text("comment".into())) => {
+                    if let Some(Token::Text(text)) = arguments.remove("text") {
+                        annotations
+                            .doc
+                            .extend(text.lines().map(|line| line.trim().to_string()));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(annotations)
+    }
+
+    fn definitions(&mut self) -> Result<()> {
+        while self.peek().is_some() && self.peek() != Some(&Token::Punct('}')) {
+            let annotations = self.annotations()?;
+            match self.word()?.as_str() {
+                "module" => {
+                    let name = self.word()?;
+                    self.expect('{')?;
+                    self.modules.push(name);
+                    self.definitions()?;
+                    self.modules.pop();
+                    self.expect('}')?;
+                }
+                "struct" => {
+                    let name = self.word()?;
+                    self.expect('{')?;
+                    let mut fields = Vec::new();
+                    while !self.eat('}') {
+                        fields.push(self.member()?);
+                    }
+                    self.structs.insert(
+                        name,
+                        Message {
+                            doc: annotations.doc,
+                            constants: Vec::new(),
+                            fields,
+                        },
+                    );
+                }
+                "const" => {
+                    let ty = self.parse_type()?;
+                    let name = self.word()?;
+                    self.expect('=')?;
+                    let value = self.value()?;
+                    let owner = self
+                        .modules
+                        .last()
+                        .and_then(|module| module.strip_suffix("_Constants"))
+                        .ok_or_else(|| format!("the constant {name} isn't in a _Constants module"))?
+                        .to_string();
+                    self.constants.entry(owner).or_default().push(Constant {
+                        name,
+                        ty,
+                        value,
+                        doc: annotations.doc,
+                    });
+                }
+                "typedef" => {
+                    let ty = self.parse_type()?;
+                    let name = self.word()?;
+                    let ty = self.array(ty)?;
+                    self.typedefs.insert(name, ty);
+                }
+                word => return Err(format!("unexpected {word}")),
+            }
+            self.expect(';')?;
+        }
+        Ok(())
+    }
+
+    fn member(&mut self) -> Result<Field> {
+        let annotations = self.annotations()?;
+        let ty = self.parse_type()?;
+        let name = self.word()?;
+        let ty = self.array(ty)?;
+        self.expect(';')?;
+        Ok(Field {
+            name,
+            ty,
+            default: annotations.default,
+            doc: annotations.doc,
+        })
+    }
+
+    /// The `[N]` after a name
+    fn array(&mut self, ty: Type) -> Result<Type> {
+        if self.eat('[') {
+            let size = self.number()?;
+            self.expect(']')?;
+            Ok(Type::Array(Box::new(ty), size))
+        } else {
+            Ok(ty)
+        }
+    }
+
+    fn parse_type(&mut self) -> Result<Type> {
+        let word = self.word()?;
+        let primitive = |p| Ok(Type::Primitive(p));
+        match word.as_str() {
+            "sequence" => {
+                self.expect('<')?;
+                let element = Box::new(self.parse_type()?);
+                let bound = if self.eat(',') {
+                    Some(self.number()?)
+                } else {
+                    None
+                };
+                self.expect('>')?;
+                Ok(Type::Sequence(element, bound))
+            }
+            "string" => {
+                if self.eat('<') {
+                    self.number()?;
+                    self.expect('>')?;
+                }
+                Ok(Type::String)
+            }
+            "boolean" => primitive(Primitive::Bool),
+            "octet" => primitive(Primitive::Byte),
+            "char" => primitive(Primitive::Char),
+            "float" => primitive(Primitive::Float32),
+            "double" => primitive(Primitive::Float64),
+            "short" => primitive(Primitive::Int16),
+            "long" if self.is_word("double") => Err("long double isn't supported".to_string()),
+            "long" if self.is_word("long") => {
+                self.at += 1;
+                primitive(Primitive::Int64)
+            }
+            "long" => primitive(Primitive::Int32),
+            "unsigned" => match self.word()?.as_str() {
+                "short" => primitive(Primitive::UInt16),
+                "long" if self.is_word("long") => {
+                    self.at += 1;
+                    primitive(Primitive::UInt64)
+                }
+                "long" => primitive(Primitive::UInt32),
+                other => Err(format!("unexpected unsigned {other}")),
+            },
+            word => {
+                if let Some(primitive) = Primitive::from_msg(word).filter(|_| word != "byte") {
+                    return Ok(Type::Primitive(primitive));
+                }
+                let mut path = vec![word.to_string()];
+                while self.peek() == Some(&Token::Scope) {
+                    self.at += 1;
+                    path.push(self.word()?);
+                }
+                match path.as_slice() {
+                    [name] => match self.typedefs.get(name) {
+                        Some(ty) => Ok(ty.clone()),
+                        None => Ok(Type::Named(format!("{}/msg/{name}", self.modules[0]))),
+                    },
+                    [package, kind, name] => Ok(Type::Named(format!("{package}/{kind}/{name}"))),
+                    _ => Err(format!("bad type {}", path.join("::"))),
+                }
+            }
+        }
+    }
+}
+
+/// A literal as it's written in a .msg file: a number, a name like TRUE, or a text in quotes
+fn as_value(literal: Token) -> String {
+    match literal {
+        Token::Text(text) => format!("{text:?}"),
+        Token::Number(value) | Token::Word(value) => value,
+        _ => unreachable!("not a literal"),
+    }
+}
+
+/// The structs by name, with their constants
+pub fn parse(source: &str) -> Result<BTreeMap<String, Message>> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        at: 0,
+        modules: Vec::new(),
+        typedefs: BTreeMap::new(),
+        structs: BTreeMap::new(),
+        constants: BTreeMap::new(),
+    };
+    parser.definitions()?;
+    if let Some(token) = parser.peek() {
+        return Err(format!("unexpected {token:?}"));
+    }
+    for (owner, constants) in parser.constants {
+        parser
+            .structs
+            .get_mut(&owner)
+            .ok_or_else(|| format!("no struct {owner} for its constants"))?
+            .constants = constants;
+    }
+    Ok(parser.structs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg;
+
+    const IDL: &str = r#"
+#include "builtin_interfaces/msg/Time.idl"
+
+module pkg {
+  module msg {
+    typedef double double__4[4];
+    module Sample_Constants {
+      @verbatim (language="comment", text=
+        " The modes")
+      const uint8 AUTO = 0;
+      const int16 LOW = -2;
+    };
+    @verbatim (language="comment", text=
+      " A sample" "\n"
+      "" "\n"
+      " of every type")
+    struct Sample {
+      builtin_interfaces::msg::Time stamp;
+      @verbatim (language="comment", text=
+        " In m")
+      @default (value=0.5)
+      float x;
+      double__4 values;
+      sequence<pkg::msg::Other, 3> bounded;
+      sequence<double> unbounded;
+      unsigned long long big;
+      long small;
+      boolean flag;
+      octet raw;
+      string name;
+      Other other;
+    };
+  };
+};
+"#;
+
+    const MSG: &str = "# A sample\n#\n# of every type\n\n\
+        # The modes\nuint8 AUTO = 0\nint16 LOW = -2\n\n\
+        builtin_interfaces/Time stamp\n# In m\nfloat32 x 0.5\nfloat64[4] values\n\
+        Other[<=3] bounded\nfloat64[] unbounded\nuint64 big\nint32 small\nbool flag\n\
+        byte raw\nstring name\nOther other\n";
+
+    #[test]
+    fn an_idl_is_the_same_as_its_msg() {
+        let structs = parse(IDL).unwrap();
+        assert_eq!(structs["Sample"], msg::parse(MSG, "pkg").unwrap());
+    }
+
+    #[test]
+    fn reject_what_the_bridge_cannot_generate() {
+        assert!(parse("module pkg { module msg { struct A { long double x; }; }; };").is_err());
+        assert!(parse("module pkg { const uint8 A = 1; };").is_err());
+        assert!(parse("module pkg { module msg { struct A { uint8 x; } }; };").is_err());
+    }
+}
@@ -0,0 +1,258 @@
+//! Generate the serde structs of `src/autoware_type.rs` from the ROS 2 interfaces in `msg/`.
+//!
+//! `msg/messages.txt` lists the messages to generate, and the types taken from elsewhere.
+//! The interfaces are read from `msg/<package>/msg/<Name>.msg` or `.idl`, and the services
+//! from `msg/<package>/srv/<Name>.srv` or `.idl`. Each message becomes a struct whose fields
+//! follow the interface in order, which is all the CDR layout depends on, and its constants
+//! a module named after it.
+mod idl;
+mod msg;
+mod rust;
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+/// Where the generated code goes, relative to the bridge
+pub const OUTPUT: &str = "src/autoware_type.rs";
+/// The list of messages, relative to the interfaces directory
+pub const MANIFEST: &str = "messages.txt";
+
+pub type Result<T> = std::result::Result<T, String>;
+
+/// A primitive type of the interfaces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Primitive {
+    Bool,
+    Byte,
+    Char,
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Int64,
+    UInt64,
+    Float32,
+    Float64,
+}
+
+impl Primitive {
+    /// The name in a .msg file
+    fn from_msg(name: &str) -> Option<Primitive> {
+        Some(match name {
+            "bool" => Primitive::Bool,
+            "byte" => Primitive::Byte,
+            "char" => Primitive::Char,
+            "int8" => Primitive::Int8,
+            "uint8" => Primitive::UInt8,
+            "int16" => Primitive::Int16,
+            "uint16" => Primitive::UInt16,
+            "int32" => Primitive::Int32,
+            "uint32" => Primitive::UInt32,
+            "int64" => Primitive::Int64,
+            "uint64" => Primitive::UInt64,
+            "float32" => Primitive::Float32,
+            "float64" => Primitive::Float64,
+            _ => return None,
+        })
+    }
+
+    fn rust(self) -> &'static str {
+        match self {
+            Primitive::Bool => "bool",
+            Primitive::Byte | Primitive::Char | Primitive::UInt8 => "u8",
+            Primitive::Int8 => "i8",
+            Primitive::Int16 => "i16",
+            Primitive::UInt16 => "u16",
+            Primitive::Int32 => "i32",
+            Primitive::UInt32 => "u32",
+            Primitive::Int64 => "i64",
+            Primitive::UInt64 => "u64",
+            Primitive::Float32 => "f32",
+            Primitive::Float64 => "f64",
+        }
+    }
+}
+
+/// The type of a field or a constant
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    Primitive(Primitive),
+    String,
+    /// Another message, as `<package>/msg/<Name>`
+    Named(String),
+    Array(Box<Type>, usize),
+    /// With its upper bound, if any
+    Sequence(Box<Type>, Option<usize>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub name: String,
+    pub ty: Type,
+    /// The default value as written in a .msg file
+    pub default: Option<String>,
+    pub doc: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Constant {
+    pub name: String,
+    pub ty: Type,
+    pub value: String,
+    pub doc: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Message {
+    pub doc: Vec<String>,
+    pub constants: Vec<Constant>,
+    pub fields: Vec<Field>,
+}
+
+/// An interface to generate, by its ROS name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Interface {
+    /// `<package>/msg/<Name>` or `<package>/srv/<Name>`
+    pub ros_name: String,
+    pub rust_name: String,
+}
+
+/// What `msg/messages.txt` lists
+#[derive(Debug, Default)]
+pub(crate) struct Manifest {
+    pub interfaces: Vec<Interface>,
+    /// The types of other crates, by ROS name, as paths
+    pub externs: BTreeMap<String, String>,
+    /// The types replaced with a Rust type of the same layout, by ROS name
+    pub maps: BTreeMap<String, String>,
+}
+
+impl Manifest {
+    fn parse(source: &str) -> Result<Manifest> {
+        let mut manifest = Manifest::default();
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: &str| format!("{MANIFEST}:{}: {message}", index + 1);
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                ["extern", ros_name, "=", path] => {
+                    manifest
+                        .externs
+                        .insert(full_name(ros_name)?, path.to_string());
+                }
+                ["map", ros_name, "=", rust @ ..] if !rust.is_empty() => {
+                    manifest.maps.insert(full_name(ros_name)?, rust.join(" "));
+                }
+                [ros_name] | [ros_name, "as", _] => {
+                    let ros_name = full_name(ros_name).map_err(|e| error(&e))?;
+                    let rust_name = match words.as_slice() {
+                        [_, "as", rust_name] => rust_name.to_string(),
+                        _ => ros_name.rsplit('/').next().unwrap().to_string(),
+                    };
+                    manifest.interfaces.push(Interface {
+                        ros_name,
+                        rust_name,
+                    });
+                }
+                _ => return Err(error("expect a message, `extern` or `map`")),
+            }
+        }
+        Ok(manifest)
+    }
+}
+
+/// Check a `<package>/msg/<Name>` or `<package>/srv/<Name>`
+fn full_name(name: &str) -> Result<String> {
+    match name.split('/').collect::<Vec<_>>().as_slice() {
+        [_, "msg" | "srv", _] => Ok(name.to_string()),
+        _ => Err(format!(
+            "expect <package>/msg/<Name> or <package>/srv/<Name>, not {name}"
+        )),
+    }
+}
+
+/// The message, or the request and response of a service, of an interface
+fn load(dir: &Path, ros_name: &str) -> Result<Vec<Message>> {
+    let (package, kind, name) = match ros_name.split('/').collect::<Vec<_>>().as_slice() {
+        [package, kind, name] => (package.to_string(), kind.to_string(), name.to_string()),
+        _ => unreachable!("checked by full_name"),
+    };
+    let base = dir.join(&package).join(&kind).join(&name);
+    let text = |extension: &str| {
+        let path = base.with_extension(extension);
+        fs::read_to_string(&path)
+            .ok()
+            .map(|source| (path.display().to_string(), source))
+    };
+    let located = |path: &str, e: String| format!("{path}: {e}");
+    if let Some((path, source)) = text(&kind) {
+        return if kind == "srv" {
+            msg::parse_service(&source, &package)
+                .map(|(request, response)| vec![request, response])
+                .map_err(|e| located(&path, e))
+        } else {
+            msg::parse(&source, &package)
+                .map(|message| vec![message])
+                .map_err(|e| located(&path, e))
+        };
+    }
+    if let Some((path, source)) = text("idl") {
+        let structs = idl::parse(&source).map_err(|e| located(&path, e))?;
+        let find = |struct_name: String| {
+            structs
+                .get(&struct_name)
+                .cloned()
+                .ok_or_else(|| located(&path, format!("no struct {struct_name}")))
+        };
+        return if kind == "srv" {
+            Ok(vec![
+                find(format!("{name}_Request"))?,
+                find(format!("{name}_Response"))?,
+            ])
+        } else {
+            Ok(vec![find(name)?])
+        };
+    }
+    Err(format!(
+        "no {}.{kind} or .idl for {ros_name}",
+        base.display()
+    ))
+}
+
+/// The code of `src/autoware_type.rs` for the interfaces directory
+pub fn generate(dir: &Path) -> Result<String> {
+    let manifest_path = dir.join(MANIFEST);
+    let source = fs::read_to_string(&manifest_path)
+        .map_err(|e| format!("{}: {e}", manifest_path.display()))?;
+    let manifest = Manifest::parse(&source)?;
+    let mut messages = Vec::new();
+    for interface in &manifest.interfaces {
+        let loaded = load(dir, &interface.ros_name)?;
+        if let [request, response] = loaded.as_slice() {
+            messages.push(rust::Item {
+                ros_name: interface.ros_name.clone(),
+                rust_name: interface.rust_name.clone(),
+                role: rust::Role::Request,
+                message: request.clone(),
+            });
+            messages.push(rust::Item {
+                ros_name: interface.ros_name.clone(),
+                rust_name: format!("{}Response", interface.rust_name),
+                role: rust::Role::Response,
+                message: response.clone(),
+            });
+        } else {
+            messages.push(rust::Item {
+                ros_name: interface.ros_name.clone(),
+                rust_name: interface.rust_name.clone(),
+                role: rust::Role::Message,
+                message: loaded.into_iter().next().unwrap(),
+            });
+        }
+    }
+    rust::generate(&manifest, &messages)
+}
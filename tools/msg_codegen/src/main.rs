@@ -0,0 +1,28 @@
+//! Regenerate `src/autoware_type.rs` from `msg/`, or with `--check` fail if it's stale
+use std::{fs, path::Path, process::ExitCode};
+
+fn main() -> ExitCode {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../..");
+    let check = std::env::args().any(|arg| arg == "--check");
+    let code = match msg_codegen::generate(&root.join("msg")) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let output = root.join(msg_codegen::OUTPUT);
+    if check {
+        if fs::read_to_string(&output).ok().as_deref() != Some(code.as_str()) {
+            eprintln!(
+                "{} is stale, run `cargo run -p msg_codegen`",
+                msg_codegen::OUTPUT
+            );
+            return ExitCode::FAILURE;
+        }
+    } else if let Err(e) = fs::write(&output, code) {
+        eprintln!("{}: {e}", output.display());
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,179 @@
+//! The .msg and .srv formats
+//!
+//! A comment block followed by an empty line, before any field or constant, describes the
+//! message. Any other comment describes the field or constant right after it.
+use crate::{Constant, Field, Message, Primitive, Result, Type};
+use std::mem;
+
+pub fn parse(source: &str, package: &str) -> Result<Message> {
+    let mut message = Message::default();
+    let mut comment: Vec<String> = Vec::new();
+    let mut started = false;
+    for (index, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            if !started && message.doc.is_empty() {
+                message.doc = mem::take(&mut comment);
+            } else {
+                comment.clear();
+            }
+            continue;
+        }
+        if let Some(text) = line.strip_prefix('#') {
+            comment.push(text.trim().to_string());
+            continue;
+        }
+        let mut doc = mem::take(&mut comment);
+        let (item, trailing) = split_comment(line);
+        doc.extend(trailing);
+        parse_item(&mut message, item, package, doc)
+            .map_err(|e| format!("line {}: {e}", index + 1))?;
+        started = true;
+    }
+    Ok(message)
+}
+
+/// The request and the response
+pub fn parse_service(source: &str, package: &str) -> Result<(Message, Message)> {
+    let mut parts = source.splitn(2, "\n---");
+    let request = parts.next().unwrap();
+    let response = parts
+        .next()
+        .ok_or("expect a --- between the request and the response")?;
+    // The rest of the separator line
+    let response = response.split_once('\n').map_or("", |(_, rest)| rest);
+    Ok((parse(request, package)?, parse(response, package)?))
+}
+
+/// The item and its trailing comment, a string constant keeps its `#`
+fn split_comment(line: &str) -> (&str, Option<String>) {
+    let is_string = line.starts_with("string");
+    match line.find('#') {
+        Some(at) if !(is_string && line[..at].contains('=')) => {
+            (line[..at].trim(), Some(line[at + 1..].trim().to_string()))
+        }
+        _ => (line, None),
+    }
+}
+
+fn parse_item(message: &mut Message, item: &str, package: &str, doc: Vec<String>) -> Result<()> {
+    let (ty, rest) = item
+        .split_once(char::is_whitespace)
+        .ok_or_else(|| format!("expect a type and a name in {item:?}"))?;
+    let ty = parse_type(ty, package)?;
+    let rest = rest.trim();
+    if let Some((name, value)) = rest.split_once('=') {
+        if !matches!(ty, Type::Primitive(_) | Type::String) {
+            return Err(format!("a constant of a {ty:?}"));
+        }
+        message.constants.push(Constant {
+            name: name.trim().to_string(),
+            ty,
+            value: value.trim().to_string(),
+            doc,
+        });
+        return Ok(());
+    }
+    let (name, default) = match rest.split_once(char::is_whitespace) {
+        Some((name, default)) => (name, Some(default.trim().to_string())),
+        None => (rest, None),
+    };
+    message.fields.push(Field {
+        name: name.to_string(),
+        ty,
+        default,
+        doc,
+    });
+    Ok(())
+}
+
+pub fn parse_type(text: &str, package: &str) -> Result<Type> {
+    if let Some(base) = text.strip_suffix(']') {
+        let (base, bound) = base
+            .rsplit_once('[')
+            .ok_or_else(|| format!("unbalanced brackets in {text}"))?;
+        let element = Box::new(parse_type(base, package)?);
+        let number = |n: &str| {
+            n.parse::<usize>()
+                .map_err(|_| format!("bad array size in {text}"))
+        };
+        return Ok(match bound.strip_prefix("<=") {
+            _ if bound.is_empty() => Type::Sequence(element, None),
+            Some(bound) => Type::Sequence(element, Some(number(bound)?)),
+            None => Type::Array(element, number(bound)?),
+        });
+    }
+    if text == "string" || text.starts_with("string<=") {
+        return Ok(Type::String);
+    }
+    if let Some(primitive) = Primitive::from_msg(text) {
+        return Ok(Type::Primitive(primitive));
+    }
+    match text.split('/').collect::<Vec<_>>().as_slice() {
+        ["wstring"] => Err("wstring isn't supported".to_string()),
+        [name] => Ok(Type::Named(format!("{package}/msg/{name}"))),
+        [package, name] | [package, "msg", name] => {
+            Ok(Type::Named(format!("{package}/msg/{name}")))
+        }
+        _ => Err(format!("bad type {text}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_the_docs() {
+        let message = parse(
+            "# A message\n#\n# Second paragraph\n\n# The values\nuint8 A = 1\n\n\
+             # The field\nfloat32 x # in m\n",
+            "pkg",
+        )
+        .unwrap();
+        assert_eq!(message.doc, ["A message", "", "Second paragraph"]);
+        assert_eq!(message.constants[0].doc, ["The values"]);
+        assert_eq!(message.fields[0].doc, ["The field", "in m"]);
+    }
+
+    #[test]
+    fn a_leading_comment_of_a_field_is_not_the_message_doc() {
+        let message = parse("# The key\nstring key\n", "pkg").unwrap();
+        assert!(message.doc.is_empty());
+        assert_eq!(message.fields[0].doc, ["The key"]);
+    }
+
+    #[test]
+    fn parse_the_types() {
+        let message = parse(
+            "uint8[4] a\nfloat64[] b\nPoint[<=3] c\nstring<=8 d\nstd_msgs/Header e\n\
+             float32 f 0.5\nstring G = \"a # b\"\nbyte H=0\n",
+            "pkg",
+        )
+        .unwrap();
+        let types: Vec<_> = message.fields.iter().map(|field| &field.ty).collect();
+        assert_eq!(
+            types,
+            [
+                &Type::Array(Box::new(Type::Primitive(Primitive::UInt8)), 4),
+                &Type::Sequence(Box::new(Type::Primitive(Primitive::Float64)), None),
+                &Type::Sequence(Box::new(Type::Named("pkg/msg/Point".into())), Some(3)),
+                &Type::String,
+                &Type::Named("std_msgs/msg/Header".into()),
+                &Type::Primitive(Primitive::Float32),
+            ]
+        );
+        assert_eq!(message.fields[5].default.as_deref(), Some("0.5"));
+        assert_eq!(message.constants[0].value, "\"a # b\"");
+        assert_eq!(message.constants[1].name, "H");
+        assert_eq!(message.constants[1].value, "0");
+    }
+
+    #[test]
+    fn parse_a_service() {
+        let (request, response) = parse_service("int32 a\n---\nbool ok\n", "pkg").unwrap();
+        assert_eq!(request.fields[0].name, "a");
+        assert_eq!(response.fields[0].name, "ok");
+        assert!(parse_service("int32 a\n", "pkg").is_err());
+    }
+}
@@ -0,0 +1,508 @@
+//! The Rust code of the messages
+use crate::{Constant, Field, Manifest, Message, Primitive, Result, Type};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+};
+
+/// The widest line of the doc comments
+const WIDTH: usize = 100;
+/// serde implements its traits for the arrays up to this size
+const MAX_ARRAY: usize = 32;
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use",
+    "where", "while",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Message,
+    Request,
+    Response,
+}
+
+/// A struct to generate
+#[derive(Debug, Clone)]
+pub struct Item {
+    pub ros_name: String,
+    pub rust_name: String,
+    pub role: Role,
+    pub message: Message,
+}
+
+/// What the fields know of the types they refer to
+struct Types<'a> {
+    manifest: &'a Manifest,
+    /// The generated messages by ROS name, the services have no message of their own
+    messages: BTreeMap<&'a str, &'a Item>,
+    /// The extern paths in use
+    used: BTreeSet<String>,
+}
+
+impl Types<'_> {
+    fn rust(&mut self, ty: &Type) -> Result<String> {
+        Ok(match ty {
+            Type::Primitive(primitive) => primitive.rust().to_string(),
+            Type::String => "String".to_string(),
+            Type::Named(name) => {
+                if let Some(item) = self.messages.get(name.as_str()) {
+                    item.rust_name.clone()
+                } else if let Some(rust) = self.manifest.maps.get(name) {
+                    rust.clone()
+                } else if let Some(path) = self.manifest.externs.get(name) {
+                    self.used.insert(path.clone());
+                    path.rsplit("::").next().unwrap().to_string()
+                } else {
+                    return Err(format!("{name} is neither listed nor extern nor mapped"));
+                }
+            }
+            Type::Array(element, size) if *size <= MAX_ARRAY => {
+                format!("[{}; {size}]", self.rust(element)?)
+            }
+            Type::Array(element, size) => {
+                let rows = square_root(*size)
+                    .ok_or_else(|| format!("an array of {size}, larger than {MAX_ARRAY}"))?;
+                format!("[[{}; {rows}]; {rows}]", self.rust(element)?)
+            }
+            Type::Sequence(element, _) => format!("Vec<{}>", self.rust(element)?),
+        })
+    }
+
+    /// If a value of this type is plain data, which is Copy and zero by default
+    fn is_plain(&self, ty: &Type) -> bool {
+        match ty {
+            Type::Primitive(_) => true,
+            Type::Array(element, size) => *size <= MAX_ARRAY && self.is_plain(element),
+            Type::Named(name) => match self.messages.get(name.as_str()) {
+                Some(item) => self.is_plain_message(&item.message),
+                None => self.manifest.maps.contains_key(name),
+            },
+            Type::String | Type::Sequence(..) => false,
+        }
+    }
+
+    fn is_plain_message(&self, message: &Message) -> bool {
+        message.fields.iter().all(|field| self.is_plain(&field.ty))
+    }
+
+    /// If the type implements Default
+    fn has_default(&self, ty: &Type) -> bool {
+        match ty {
+            Type::Primitive(_) | Type::String | Type::Sequence(..) => true,
+            Type::Array(element, size) => *size <= MAX_ARRAY && self.has_default(element),
+            Type::Named(name) => match self.messages.get(name.as_str()) {
+                Some(item) => self.has_default_message(&item.message),
+                None => self.manifest.maps.contains_key(name),
+            },
+        }
+    }
+
+    fn has_default_message(&self, message: &Message) -> bool {
+        message
+            .fields
+            .iter()
+            .all(|field| self.has_default(&field.ty))
+    }
+
+    /// A note on what the Rust type hides of the ROS one
+    fn note(&self, ty: &Type) -> Option<String> {
+        match ty {
+            Type::Named(name) => {
+                let path = self.manifest.externs.get(name)?;
+                let rust = path.rsplit("::").next().unwrap();
+                (!name.ends_with(&format!("/{rust}")))
+                    .then(|| format!("A {name}, which has the layout of `{rust}`"))
+            }
+            Type::Array(element, size) if *size > MAX_ARRAY => Some(format!(
+                "In rows, serde has no arrays of {size} {}",
+                element_name(element)
+            )),
+            Type::Sequence(_, Some(bound)) => Some(format!("At most {bound}")),
+            _ => None,
+        }
+    }
+}
+
+fn element_name(ty: &Type) -> String {
+    match ty {
+        Type::Primitive(primitive) => primitive.rust().to_string(),
+        _ => "elements".to_string(),
+    }
+}
+
+fn square_root(size: usize) -> Option<usize> {
+    (1..=MAX_ARRAY).find(|rows| rows * rows == size)
+}
+
+/// `DoorStatusArray` to `door_status_array`
+pub fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut snake = String::new();
+    for (index, c) in chars.iter().enumerate() {
+        if c.is_uppercase() && index > 0 {
+            let previous = chars[index - 1];
+            let next_is_lower = chars.get(index + 1).is_some_and(|c| c.is_lowercase());
+            if previous.is_lowercase()
+                || previous.is_ascii_digit()
+                || (previous.is_uppercase() && next_is_lower)
+            {
+                snake.push('_');
+            }
+        }
+        snake.extend(c.to_lowercase());
+    }
+    snake
+}
+
+fn field_name(name: &str) -> String {
+    if KEYWORDS.contains(&name) {
+        format!("r#{name}")
+    } else {
+        name.to_string()
+    }
+}
+
+/// Write the lines as a doc comment, wrapped to the width
+fn doc(out: &mut String, indent: &str, lines: &[String]) {
+    let width = WIDTH - indent.len() - "/// ".len();
+    for line in lines {
+        if line.is_empty() {
+            let _ = writeln!(out, "{indent}///");
+            continue;
+        }
+        let mut current = String::new();
+        for word in line.split_whitespace() {
+            if !current.is_empty() && current.len() + 1 + word.len() > width {
+                let _ = writeln!(out, "{indent}/// {current}");
+                current.clear();
+            }
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(word);
+        }
+        let _ = writeln!(out, "{indent}/// {current}");
+    }
+}
+
+/// A value of a primitive or a string as a Rust literal
+fn literal(ty: &Type, value: &str) -> Result<String> {
+    let value = value.trim();
+    let bad = || format!("bad value {value} for {ty:?}");
+    Ok(match ty {
+        Type::Primitive(Primitive::Bool) => match value.to_lowercase().as_str() {
+            "true" | "1" => "true".to_string(),
+            "false" | "0" => "false".to_string(),
+            _ => return Err(bad()),
+        },
+        Type::Primitive(Primitive::Float32 | Primitive::Float64) => {
+            value.parse::<f64>().map_err(|_| bad())?;
+            if value.contains(['.', 'e', 'E']) {
+                value.to_string()
+            } else {
+                format!("{value}.0")
+            }
+        }
+        Type::Primitive(_) => {
+            value.parse::<i128>().map_err(|_| bad())?;
+            value.to_string()
+        }
+        Type::String => {
+            let text = ['"', '\'']
+                .iter()
+                .find_map(|quote| value.strip_prefix(*quote)?.strip_suffix(*quote))
+                .unwrap_or(value);
+            format!("{text:?}")
+        }
+        _ => return Err(bad()),
+    })
+}
+
+/// The default of a field as a Rust expression
+fn default_value(ty: &Type, value: &str) -> Result<String> {
+    let items = |value: &str| -> Result<Vec<String>> {
+        let inner = value
+            .trim()
+            .strip_prefix('[')
+            .and_then(|value| value.strip_suffix(']'))
+            .ok_or_else(|| format!("expect [...] as the default of a {ty:?}"))?;
+        Ok(inner
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect())
+    };
+    Ok(match ty {
+        Type::String => format!("String::from({})", literal(ty, value)?),
+        Type::Array(element, size) => {
+            let items = items(value)?;
+            if items.len() != *size {
+                return Err(format!("{} defaults for an array of {size}", items.len()));
+            }
+            let items: Result<Vec<_>> = items.iter().map(|item| literal(element, item)).collect();
+            format!("[{}]", items?.join(", "))
+        }
+        Type::Sequence(element, _) => {
+            let items: Result<Vec<_>> = items(value)?
+                .iter()
+                .map(|item| literal(element, item))
+                .collect();
+            format!("vec![{}]", items?.join(", "))
+        }
+        _ => literal(ty, value)?,
+    })
+}
+
+fn constants(out: &mut String, item: &Item) -> Result<()> {
+    let _ = writeln!(out, "/// The constants of `{}`", item.rust_name);
+    let _ = writeln!(out, "pub mod {} {{", snake_case(&item.rust_name));
+    for Constant {
+        name,
+        ty,
+        value,
+        doc: lines,
+    } in &item.message.constants
+    {
+        let rust = match ty {
+            Type::Primitive(primitive) => primitive.rust(),
+            _ => "&str",
+        };
+        doc(out, "    ", lines);
+        let _ = writeln!(
+            out,
+            "    pub const {name}: {rust} = {};",
+            literal(ty, value)?
+        );
+    }
+    out.push_str("}\n\n");
+    Ok(())
+}
+
+fn message(out: &mut String, types: &mut Types, item: &Item) -> Result<()> {
+    let Item {
+        ros_name,
+        rust_name,
+        role,
+        message,
+    } = item;
+    if !message.constants.is_empty() {
+        constants(out, item)?;
+    }
+    let mut lines = message.doc.clone();
+    let title = match role {
+        Role::Message => ros_name.clone(),
+        Role::Request => format!("The request of {ros_name}"),
+        Role::Response => format!("The response of {ros_name}"),
+    };
+    match lines.first_mut() {
+        Some(first) => *first = format!("{title}: {first}"),
+        None => lines.push(title),
+    }
+    doc(out, "", &lines);
+    let defaults = message.fields.iter().any(|field| field.default.is_some());
+    let mut derives = vec!["Serialize", "Deserialize", "PartialEq", "Debug"];
+    if types.is_plain_message(message) {
+        derives.extend(["Clone", "Copy"]);
+        if !defaults {
+            derives.push("Default");
+        }
+    }
+    let _ = writeln!(out, "#[derive({})]", derives.join(", "));
+    let _ = writeln!(out, "pub struct {rust_name} {{");
+    for Field {
+        name,
+        ty,
+        default,
+        doc: field_doc,
+    } in &message.fields
+    {
+        let mut lines = field_doc.clone();
+        lines.extend(types.note(ty));
+        if let Some(default) = default {
+            lines.push(format!("Defaults to {}", default_value(ty, default)?));
+        }
+        doc(out, "    ", &lines);
+        let _ = writeln!(out, "    pub {}: {},", field_name(name), types.rust(ty)?);
+    }
+    out.push_str("}\n\n");
+    if defaults && types.has_default_message(message) {
+        let _ = writeln!(out, "impl Default for {rust_name} {{");
+        out.push_str("    fn default() -> Self {\n");
+        let _ = writeln!(out, "        {rust_name} {{");
+        for Field {
+            name, ty, default, ..
+        } in &message.fields
+        {
+            let value = match default {
+                Some(default) => default_value(ty, default)?,
+                None => "Default::default()".to_string(),
+            };
+            let _ = writeln!(out, "            {}: {value},", field_name(name));
+        }
+        out.push_str("        }\n    }\n}\n\n");
+    }
+    Ok(())
+}
+
+/// `use` the extern types, by crate
+fn uses(used: &BTreeSet<String>) -> String {
+    let mut by_crate: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for path in used {
+        let (krate, rest) = path.split_once("::").unwrap_or((path, ""));
+        by_crate.entry(krate).or_default().push(rest);
+    }
+    let mut out = String::new();
+    for (krate, items) in by_crate {
+        match items.as_slice() {
+            [item] => {
+                let _ = writeln!(out, "use {krate}::{item};");
+            }
+            items => {
+                let _ = writeln!(out, "use {krate}::{{{}}};", items.join(", "));
+            }
+        }
+    }
+    out
+}
+
+pub fn generate(manifest: &Manifest, items: &[Item]) -> Result<String> {
+    let mut types = Types {
+        manifest,
+        messages: items
+            .iter()
+            .filter(|item| item.role == Role::Message)
+            .map(|item| (item.ros_name.as_str(), item))
+            .collect(),
+        used: BTreeSet::new(),
+    };
+    let mut body = String::new();
+    for item in items {
+        message(&mut body, &mut types, item)
+            .map_err(|e| format!("{} ({}): {e}", item.ros_name, item.rust_name))?;
+    }
+    let mut out = String::new();
+    out.push_str(
+        "//! Autoware messages which are not provided by zenoh-ros-type\n\
+         //!\n\
+         //! Generated by `tools/msg_codegen` from the interfaces in `msg/`, don't edit it but\n\
+         //! regenerate it with `cargo run -p msg_codegen`.\n\
+         #![allow(dead_code)]\n",
+    );
+    out.push_str("use serde_derive::{Deserialize, Serialize};\n");
+    out.push_str(&uses(&types.used));
+    out.push('\n');
+    out.push_str(&body);
+    out.push_str(
+        "/// Call the macro with the name of every struct, to test each of them\n\
+         #[cfg(test)]\n\
+         macro_rules! for_each_message {\n    ($macro:ident) => {\n        $macro!(\n",
+    );
+    for item in items {
+        let _ = writeln!(out, "            {},", item.rust_name);
+    }
+    out.push_str(
+        "        );\n    };\n}\n\n\
+         #[cfg(test)]\n\
+         mod tests;\n",
+    );
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg;
+
+    fn item(ros_name: &str, source: &str) -> Item {
+        Item {
+            ros_name: ros_name.to_string(),
+            rust_name: ros_name.rsplit('/').next().unwrap().to_string(),
+            role: Role::Message,
+            message: msg::parse(source, ros_name.split('/').next().unwrap()).unwrap(),
+        }
+    }
+
+    fn manifest() -> Manifest {
+        let mut manifest = Manifest::default();
+        manifest.externs.insert(
+            "builtin_interfaces/msg/Duration".into(),
+            "zenoh_ros_type::builtin_interfaces::Time".into(),
+        );
+        manifest
+            .maps
+            .insert("geometry_msgs/msg/Point".into(), "[f64; 3]".into());
+        manifest
+    }
+
+    #[test]
+    fn name_the_modules() {
+        assert_eq!(snake_case("DoorStatusArray"), "door_status_array");
+        assert_eq!(snake_case("AdapiDoorCommand"), "adapi_door_command");
+        assert_eq!(snake_case("GNSSFix"), "gnss_fix");
+        assert_eq!(snake_case("Point32"), "point32");
+    }
+
+    #[test]
+    fn generate_the_defaults() {
+        let items = [item(
+            "pkg/msg/Sample",
+            "float32 x 1\nbool flag True\nstring name 'a'\nint8[2] pair [1, -2]\n\
+             float64[] values [0.5]\npkg/Other other\n",
+        )];
+        let mut items = items.to_vec();
+        items.push(item("pkg/msg/Other", "geometry_msgs/Point point\n"));
+        let code = generate(&manifest(), &items).unwrap();
+        assert!(code.contains(
+            "impl Default for Sample {\n    fn default() -> Self {\n        Sample {\n            \
+             x: 1.0,\n            flag: true,\n            name: String::from(\"a\"),\n            \
+             pair: [1, -2],\n            values: vec![0.5],\n            \
+             other: Default::default(),\n"
+        ));
+        assert!(code.contains(
+            "#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Default)]\n\
+             pub struct Other {\n    pub point: [f64; 3],\n}"
+        ));
+    }
+
+    #[test]
+    fn no_default_without_one_for_every_field() {
+        let items = [item(
+            "pkg/msg/Sample",
+            "builtin_interfaces/Duration time\nfloat32 x 0.0\nuint8 type\n",
+        )];
+        let code = generate(&manifest(), &items).unwrap();
+        assert!(!code.contains("impl Default"));
+        assert!(code.contains("use zenoh_ros_type::builtin_interfaces::Time;"));
+        assert!(code.contains(
+            "    /// A builtin_interfaces/msg/Duration, which has the layout of `Time`\n    \
+             pub time: Time,\n    /// Defaults to 0.0\n    pub x: f32,\n    pub r#type: u8,"
+        ));
+    }
+
+    #[test]
+    fn generate_the_constants() {
+        let items = [item(
+            "pkg/msg/Sample",
+            "# The modes\nuint8 AUTO = 0\nfloat32 LIMIT = 2\nstring NAME = \"a\"\nuint8 mode\n",
+        )];
+        let code = generate(&manifest(), &items).unwrap();
+        assert!(code.contains(
+            "/// The constants of `Sample`\npub mod sample {\n    /// The modes\n    \
+             pub const AUTO: u8 = 0;\n    pub const LIMIT: f32 = 2.0;\n    \
+             pub const NAME: &str = \"a\";\n}\n"
+        ));
+    }
+
+    #[test]
+    fn reject_the_types_it_cannot_generate() {
+        let unknown = [item("pkg/msg/Sample", "pkg/Unknown x\n")];
+        assert!(generate(&manifest(), &unknown).is_err());
+        let large = [item("pkg/msg/Sample", "float64[40] x\n")];
+        assert!(generate(&manifest(), &large).is_err());
+        let bad_constant = [item("pkg/msg/Sample", "uint8 A = x\n")];
+        assert!(generate(&manifest(), &bad_constant).is_err());
+    }
+}
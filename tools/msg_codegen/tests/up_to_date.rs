@@ -0,0 +1,14 @@
+use std::{fs, path::Path};
+
+/// The checked-in code is what the interfaces generate, so the bridge builds without them
+#[test]
+fn autoware_type_is_up_to_date() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../..");
+    let generated = msg_codegen::generate(&root.join("msg")).unwrap();
+    let checked_in = fs::read_to_string(root.join(msg_codegen::OUTPUT)).unwrap();
+    assert!(
+        generated == checked_in,
+        "{} is stale, run `cargo run -p msg_codegen`",
+        msg_codegen::OUTPUT
+    );
+}